@@ -1,13 +1,40 @@
-
 fn main() {
     // Generate Build Timestamp
     println!("cargo:rustc-env=BUILD_TIMESTAMP={}", chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"));
+    println!("cargo:rustc-env=GIT_COMMIT={}", git_commit());
 
-    // App Icon Generation
+    // App Icon Generation - only embed icon on Windows
     #[cfg(windows)]
+    embed_windows_icon();
+}
+
+// Short hash of the checked-out commit, "-dirty" with uncommitted changes; GIT_COMMIT in the
+// environment wins, for builds from a source archive
+fn git_commit() -> String {
+    if let Ok(commit) = std::env::var("GIT_COMMIT") {
+        return commit;
+    }
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    match git(&["rev-parse", "--short=12", "HEAD"]) {
+        Some(commit) if git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|changes| !changes.is_empty()) => {
+            format!("{}-dirty", commit)
+        }
+        Some(commit) => commit,
+        None => "unknown".to_string(),
+    }
+}
+
+#[cfg(windows)]
+fn embed_windows_icon() {
     use std::path::Path;
-    
-    // Only embed icon on Windows
+
     if Path::new("assets/icon.ico").exists() {
         let mut res = winres::WindowsResource::new();
         res.set_icon("assets/icon.ico");
@@ -18,9 +45,3 @@ fn main() {
         }
     }
 }
-
-#[cfg(not(windows))]
-fn main() {
-    // Generate Build Timestamp
-    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"));
-}
\ No newline at end of file
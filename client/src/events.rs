@@ -0,0 +1,46 @@
+// client/src/events.rs
+// Follows the bridge's event feed by polling /api/events with the last seen id as cursor
+
+use crate::{BridgeClient, Event, PageQuery, Result};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub struct EventSubscription {
+    client: BridgeClient,
+    last_id: u64,
+    interval: Duration,
+    pending: VecDeque<Event>,
+}
+
+impl EventSubscription {
+    pub(crate) fn new(client: BridgeClient, last_id: u64, interval: Duration) -> Self {
+        Self {
+            client,
+            last_id,
+            interval,
+            pending: VecDeque::new(),
+        }
+    }
+
+    // Next event, oldest first, waiting for one to be published. The bridge buffers the last
+    // 200 events, so a subscriber that stops calling this for long can miss some.
+    pub async fn next(&mut self) -> Result<Event> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Ok(event);
+            }
+            let query = PageQuery {
+                since: Some(self.last_id),
+                ..PageQuery::default()
+            };
+            let events = self.client.events(&query).await?.items;
+            if let Some(last) = events.last() {
+                self.last_id = last.id;
+            }
+            self.pending.extend(events);
+            if self.pending.is_empty() {
+                tokio::time::sleep(self.interval).await;
+            }
+        }
+    }
+}
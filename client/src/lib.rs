@@ -0,0 +1,372 @@
+// client/src/lib.rs
+// Async client for the Telescope Park Bridge web API: typed calls for status, raw commands, jobs,
+// forced IsSafe and the event feed, for observatory automation written in Rust
+
+mod events;
+mod models;
+#[cfg(feature = "python")]
+mod python;
+
+pub use events::EventSubscription;
+pub use models::*;
+
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Requests taking longer than this fail with Error::Http
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How often wait_for_job polls
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid bridge URL: {0}")]
+    Url(String),
+
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    // Error status, with the bridge's message
+    #[error("Bridge answered {status}: {message}")]
+    Api { status: StatusCode, message: String },
+
+    // The bridge handled the request but reported failure, e.g. a command the firmware rejected
+    #[error("{0}")]
+    Failed(String),
+
+    #[error("Alpaca error {number}: {message}")]
+    Alpaca { number: i64, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Serialize)]
+struct ConnectRequest<'a> {
+    port: &'a str,
+    baud_rate: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct CommandRequest<'a> {
+    command: &'a str,
+}
+
+#[derive(Serialize)]
+struct JobRequest {
+    operation: JobOperation,
+    device_number: Option<u32>,
+}
+
+// {"success", "message"} answers, also the body of API errors
+#[derive(Deserialize)]
+struct Outcome {
+    #[serde(default)]
+    success: bool,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct CommandResponse {
+    success: bool,
+    response: Option<String>,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct AlpacaValue<T> {
+    #[serde(rename = "Value")]
+    value: T,
+    #[serde(rename = "ErrorNumber", default)]
+    error_number: i64,
+    #[serde(rename = "ErrorMessage", default)]
+    error_message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct BridgeClient {
+    base: Url,
+    http: reqwest::Client,
+    // Bearer token for /api/safety/force and an [events]-protected event feed
+    token: Option<String>,
+}
+
+impl BridgeClient {
+    // `url` is the bridge's web address, e.g. "http://pier-north.local:11111"
+    pub fn new(url: &str) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("park_bridge_client/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            base: parse_base(url)?,
+            http,
+            token: None,
+        })
+    }
+
+    // The bridge's [safety_force] token, needed to force IsSafe, which is also sent for the event
+    // feed when the bridge protects it with an [events] token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    // Custom timeouts, proxies and the like
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.base
+    }
+
+    pub async fn status(&self) -> Result<Status> {
+        self.get("/api/status", &[]).await
+    }
+
+    // Fields changed since `since`, the sequence of an earlier delta; None gets the full status
+    pub async fn status_delta(&self, since: Option<u64>) -> Result<StatusDelta> {
+        let query: Vec<_> = since.map(|since| ("since", since.to_string())).into_iter().collect();
+        self.get("/api/status/delta", &query).await
+    }
+
+    pub async fn about(&self) -> Result<About> {
+        self.get("/api/about", &[]).await
+    }
+
+    // Why the bridge is in safe mode; a 404 Api error when it started normally
+    pub async fn safe_mode(&self) -> Result<SafeModeStatus> {
+        self.get("/api/safe_mode", &[]).await
+    }
+
+    pub async fn devices(&self) -> Result<Vec<DeviceSummary>> {
+        self.get("/api/devices", &[]).await
+    }
+
+    pub async fn health(&self) -> Result<HealthStatus> {
+        self.get("/api/health", &[]).await
+    }
+
+    // Replies of the device's firmware that broke their schema (strict_schema on the bridge)
+    pub async fn firmware_schema(&self, device_number: u32) -> Result<SchemaReport> {
+        self.get("/api/firmware/schema", &[("device_number", device_number.to_string())]).await
+    }
+
+    // IsSafe as ASCOM clients see it, through the Alpaca endpoint
+    pub async fn is_safe(&self, device_number: u32) -> Result<bool> {
+        let path = format!("/api/v1/safetymonitor/{}/issafe", device_number);
+        let answer: AlpacaValue<bool> = self.get(&path, &[]).await?;
+        if answer.error_number != 0 {
+            return Err(Error::Alpaca {
+                number: answer.error_number,
+                message: answer.error_message,
+            });
+        }
+        Ok(answer.value)
+    }
+
+    // Open the primary device's serial port; the baud rate defaults to the bridge's
+    pub async fn connect(&self, port: &str, baud_rate: Option<u32>) -> Result<()> {
+        let outcome: Outcome = self
+            .send(Method::POST, "/api/connect", &[], Some(&ConnectRequest { port, baud_rate }))
+            .await?;
+        outcome.into_result()
+    }
+
+    pub async fn disconnect(&self) -> Result<()> {
+        let outcome: Outcome = self.send(Method::POST, "/api/disconnect", &[], None::<&()>).await?;
+        outcome.into_result()
+    }
+
+    // Send a raw firmware command code such as "01"; returns the data response
+    pub async fn send_command(&self, command: &str) -> Result<String> {
+        let answer: CommandResponse = self
+            .send(Method::POST, "/api/command", &[], Some(&CommandRequest { command }))
+            .await?;
+        match (answer.success, answer.response) {
+            (true, Some(response)) => Ok(response),
+            (true, None) => Ok(String::new()),
+            (false, _) => Err(Error::Failed(answer.message)),
+        }
+    }
+
+    pub async fn command_history(&self, query: &PageQuery) -> Result<Page<CommandRecord>> {
+        self.get_page("/api/command/history", query).await
+    }
+
+    // Start a long-running operation; None runs it on the primary device
+    pub async fn submit_job(&self, operation: JobOperation, device_number: Option<u32>) -> Result<Job> {
+        let request = JobRequest {
+            operation,
+            device_number,
+        };
+        self.send(Method::POST, "/api/jobs", &[], Some(&request)).await
+    }
+
+    pub async fn jobs(&self) -> Result<Vec<Job>> {
+        self.get("/api/jobs", &[]).await
+    }
+
+    pub async fn job(&self, id: u64) -> Result<Job> {
+        self.get(&format!("/api/jobs/{}", id), &[]).await
+    }
+
+    pub async fn cancel_job(&self, id: u64) -> Result<Job> {
+        self.send(Method::DELETE, &format!("/api/jobs/{}", id), &[], None::<&()>).await
+    }
+
+    // Poll until the job has succeeded, failed or been cancelled
+    pub async fn wait_for_job(&self, id: u64) -> Result<Job> {
+        loop {
+            let job = self.job(id).await?;
+            if job.status.is_finished() {
+                return Ok(job);
+            }
+            tokio::time::sleep(JOB_POLL_INTERVAL).await;
+        }
+    }
+
+    // Needs with_token(); the bridge answers 403 when forcing is disabled
+    pub async fn force_safety(&self, request: &ForceSafety) -> Result<SafetyOverride> {
+        self.send(Method::POST, "/api/safety/force", &[], Some(request)).await
+    }
+
+    pub async fn clear_forced_safety(&self, device_number: u32) -> Result<()> {
+        let query = [("device_number", device_number.to_string())];
+        let outcome: Outcome = self
+            .send(Method::DELETE, "/api/safety/force", &query, None::<&()>)
+            .await?;
+        outcome.into_result()
+    }
+
+    // Holds back the bridge's chat alerts and relay switching until it expires or is ended
+    pub async fn start_maintenance(&self, request: &StartMaintenance) -> Result<Maintenance> {
+        self.send(Method::POST, "/api/maintenance", &[], Some(request)).await
+    }
+
+    pub async fn end_maintenance(&self, device_number: u32) -> Result<()> {
+        let query = [("device_number", device_number.to_string())];
+        let outcome: Outcome = self
+            .send(Method::DELETE, "/api/maintenance", &query, None::<&()>)
+            .await?;
+        outcome.into_result()
+    }
+
+    // The bridge's notification channels, e.g. "chat_bot", with their snoozes
+    pub async fn notification_channels(&self) -> Result<Vec<NotificationChannel>> {
+        self.send(Method::GET, "/api/notifications", &[], None::<&()>).await
+    }
+
+    // Holds back everything the channel would send for `minutes`
+    pub async fn snooze_notifications(&self, channel: &str, minutes: f64) -> Result<NotificationChannel> {
+        let path = format!("/api/notifications/{}/snooze", channel);
+        self.send(Method::POST, &path, &[], Some(&serde_json::json!({ "minutes": minutes }))).await
+    }
+
+    pub async fn unsnooze_notifications(&self, channel: &str) -> Result<()> {
+        let path = format!("/api/notifications/{}/snooze", channel);
+        let outcome: Outcome = self.send(Method::DELETE, &path, &[], None::<&()>).await?;
+        outcome.into_result()
+    }
+
+    // Buffered events; see subscribe_events() to follow new ones
+    pub async fn events(&self, query: &PageQuery) -> Result<Page<Event>> {
+        self.get_page("/api/events", query).await
+    }
+
+    // Events published from now on, fetched by polling every `interval`
+    pub async fn subscribe_events(&self, interval: Duration) -> Result<EventSubscription> {
+        let newest = PageQuery {
+            order: SortOrder::Desc,
+            limit: Some(1),
+            ..PageQuery::default()
+        };
+        let last_id = self.events(&newest).await?.items.first().map_or(0, |event| event.id);
+        Ok(EventSubscription::new(self.clone(), last_id, interval))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T> {
+        self.send(Method::GET, path, query, None::<&()>).await
+    }
+
+    async fn get_page<T: DeserializeOwned>(&self, path: &str, query: &PageQuery) -> Result<Page<T>> {
+        let response = self.request(Method::GET, path, &query.pairs()).send().await?;
+        let response = check_status(response).await?;
+        let total = response
+            .headers()
+            .get("x-total-count")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let items: Vec<T> = response.json().await?;
+        Ok(Page {
+            total: total.unwrap_or(items.len()),
+            items,
+        })
+    }
+
+    async fn send<T: DeserializeOwned, B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<&B>,
+    ) -> Result<T> {
+        let mut request = self.request(method, path, query);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = check_status(request.send().await?).await?;
+        Ok(response.json().await?)
+    }
+
+    fn request(&self, method: Method, path: &str, query: &[(&str, String)]) -> RequestBuilder {
+        // Keep a path prefix of the base URL, for bridges behind a reverse proxy
+        let mut url = self.base.clone();
+        let full_path = format!("{}{}", url.path().trim_end_matches('/'), path);
+        url.set_path(&full_path);
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        let request = self.http.request(method, url);
+        match &self.token {
+            Some(token) => request.header(AUTHORIZATION, format!("Bearer {}", token)),
+            None => request,
+        }
+    }
+}
+
+impl Outcome {
+    fn into_result(self) -> Result<()> {
+        if self.success {
+            Ok(())
+        } else {
+            Err(Error::Failed(self.message))
+        }
+    }
+}
+
+fn parse_base(url: &str) -> Result<Url> {
+    let base = Url::parse(url).map_err(|e| Error::Url(format!("{}: {}", url, e)))?;
+    if !matches!(base.scheme(), "http" | "https") || base.cannot_be_a_base() {
+        return Err(Error::Url(format!("{}: expected an http:// or https:// address", url)));
+    }
+    Ok(base)
+}
+
+// Error statuses become Error::Api, with the message from the bridge's JSON body when it has one
+async fn check_status(response: Response) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<Outcome>(&text)
+        .map(|outcome| outcome.message)
+        .unwrap_or(text);
+    Err(Error::Api { status, message })
+}
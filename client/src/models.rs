@@ -0,0 +1,480 @@
+// client/src/models.rs
+// Request and response types of the bridge web API; unknown fields are ignored so newer bridges keep
+// working, and every type serializes back to the same JSON (the Python bindings hand it out as dicts)
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub type Timestamp = DateTime<FixedOffset>;
+
+// GET /api/status: the primary device's state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Status {
+    pub connected: bool,
+    pub serial_port: Option<String>,
+    pub error_message: Option<String>,
+    // None before the first firmware update
+    pub last_update: Option<Timestamp>,
+    #[serde(default)]
+    pub stale: bool,
+    // Passed the startup self-check; bridges without one report nothing and count as operational
+    #[serde(default = "operational_by_default")]
+    pub operational: bool,
+
+    pub device_name: String,
+    pub device_version: String,
+    pub manufacturer: String,
+    pub platform: String,
+    pub imu: String,
+
+    pub current_pitch: f32,
+    pub current_roll: f32,
+    pub park_pitch: f32,
+    pub park_roll: f32,
+    pub position_tolerance: f32,
+
+    pub is_parked: bool,
+    // What ASCOM clients see, including a forced value
+    pub is_safe: bool,
+    pub is_calibrated: bool,
+    pub has_builtin_imu: bool,
+    pub storage_available: bool,
+    // None from bridges that do not discover firmware capabilities
+    #[serde(default)]
+    pub capabilities: Option<FirmwareCapabilities>,
+    // "v1" or "v2"; None from bridges that predate version negotiation
+    #[serde(default)]
+    pub protocol_version: Option<String>,
+
+    pub uptime: u64,
+    pub free_heap: u64,
+    // IMU temperature in °C; None when the firmware reports none
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    pub ascom_connected: bool,
+    pub unique_id: String,
+    #[serde(default)]
+    pub safety_override: Option<SafetyOverride>,
+    // The Sun is above the bridge's [sun_safety] limit; false from bridges without it
+    #[serde(default)]
+    pub sun_unsafe: bool,
+    // Safety schedule window holding IsSafe false; None from bridges without schedules
+    #[serde(default)]
+    pub safety_schedule: Option<String>,
+    // Alerts and relay switching held back; None outside maintenance mode
+    #[serde(default)]
+    pub maintenance: Option<Maintenance>,
+    // The running build; None from bridges that do not report it
+    #[serde(default)]
+    pub server: Option<ServerInfo>,
+}
+
+// Which bridge build is running and since when
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerInfo {
+    pub version: String,
+    // Short commit hash, "-dirty" with uncommitted changes, or "unknown"
+    pub git_commit: String,
+    pub build_timestamp: String,
+    pub started_at: Timestamp,
+}
+
+// GET /api/status/delta: the Status fields changed since an earlier sequence, as raw JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusDelta {
+    pub sequence: u64,
+    // True when `changed` holds every field
+    pub full: bool,
+    pub changed: serde_json::Map<String, serde_json::Value>,
+    pub removed: Vec<String>,
+}
+
+// GET /api/about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct About {
+    pub name: String,
+    #[serde(flatten)]
+    pub server: ServerInfo,
+    pub uptime_secs: u64,
+    // Cargo features the bridge was built with
+    pub features: Vec<String>,
+}
+
+// GET /api/safe_mode, served only while the bridge's configuration does not load
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafeModeStatus {
+    pub safe_mode: bool,
+    pub error: String,
+    pub config_file: Option<String>,
+    pub restore_points: Vec<RestorePoint>,
+}
+
+// An earlier copy of the config file safe mode can restore
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorePoint {
+    pub name: String,
+    pub saved_at: Option<String>,
+    // Why the copy would not load either
+    pub problem: Option<String>,
+}
+
+// Commands and extras the firmware listed when the bridge connected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareCapabilities {
+    // False when the firmware gave no usable list and every command is assumed
+    pub discovered: bool,
+    pub commands: Vec<String>,
+    pub set_tolerance: bool,
+    pub identify: bool,
+    pub battery: bool,
+    pub temperature: bool,
+}
+
+fn operational_by_default() -> bool {
+    true
+}
+
+// Entry of GET /api/devices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceSummary {
+    pub device_number: u32,
+    pub name: String,
+    pub unique_id: String,
+    pub serial_port: Option<String>,
+    pub connected: bool,
+    pub is_safe: bool,
+    pub stale: bool,
+    #[serde(default)]
+    pub forced: bool,
+    #[serde(default)]
+    pub maintenance: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Maintenance {
+    pub started_at: Timestamp,
+    pub expires_at: Timestamp,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyOverride {
+    pub is_safe: bool,
+    pub expires_at: Timestamp,
+    pub reason: Option<String>,
+}
+
+// Body of POST /api/safety/force
+#[derive(Debug, Clone, Serialize)]
+pub struct ForceSafety {
+    pub device_number: u32,
+    #[serde(rename = "state", serialize_with = "serialize_forced_state")]
+    pub is_safe: bool,
+    // Capped by the bridge's [safety_force] max_minutes
+    pub minutes: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// Body of POST /api/maintenance
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StartMaintenance {
+    pub device_number: u32,
+    // The bridge's [maintenance] default_minutes when None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// One channel of GET /api/notifications
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationChannel {
+    pub channel: String,
+    // None when not snoozed
+    pub snoozed_until: Option<Timestamp>,
+    pub snoozed_count: u64,
+    pub duplicate_count: u64,
+}
+
+fn serialize_forced_state<S: serde::Serializer>(is_safe: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(if *is_safe { "safe" } else { "unsafe" })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobOperation {
+    Calibrate,
+    SetPark,
+    FactoryReset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        self != JobStatus::Running
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub operation: JobOperation,
+    pub device_number: u32,
+    pub status: JobStatus,
+    pub submitted_at: Timestamp,
+    pub elapsed_ms: u64,
+    pub timeout_ms: u64,
+    pub phase: Option<String>,
+    pub percent: Option<u8>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandOutcome {
+    Completed,
+    TimedOut,
+    Cancelled,
+    Failed,
+}
+
+// Entry of GET /api/command/history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandRecord {
+    pub id: u64,
+    pub command: String,
+    pub sent_at: Timestamp,
+    pub ack: Option<String>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: f64,
+    pub outcome: CommandOutcome,
+}
+
+// GET /api/health: serial link health of the primary device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthStatus {
+    pub uptime: Option<u64>,
+    pub free_heap: Option<u64>,
+    pub heap_baseline: Option<u64>,
+    pub min_free_heap: Option<u64>,
+    pub reboots: u32,
+    pub reboots_in_window: usize,
+    pub latency_avg_ms: Option<f64>,
+    pub latency_baseline_ms: Option<f64>,
+    pub latency_samples: u64,
+    // Absent from bridges without park drift detection
+    #[serde(default)]
+    pub park_drift: Option<ParkDrift>,
+    pub warnings: Vec<HealthWarning>,
+}
+
+// Average parked offset from the park definition over the drift window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParkDrift {
+    pub pitch_offset: f64,
+    pub roll_offset: f64,
+    pub percent_of_tolerance: f64,
+    pub days: usize,
+    pub samples: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthWarning {
+    // "heap_shrinking", "frequent_reboots", "slow_responses" or "park_drift"
+    pub issue: String,
+    pub message: String,
+    pub since: Timestamp,
+}
+
+// GET /api/firmware/schema: firmware replies that did not match their schema under strict_schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaReport {
+    pub strict: bool,
+    pub checked: u64,
+    pub violations: u64,
+    // Keyed by schema, e.g. "envelope", "status" or "position_v2"
+    pub schemas: BTreeMap<String, SchemaViolations>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaViolations {
+    pub count: u64,
+    pub last_problem: String,
+    pub last_response: String,
+    pub last_seen: Timestamp,
+}
+
+// Entry of GET /api/events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub id: u64,
+    pub timestamp: Timestamp,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    FirmwareEvent {
+        event: String,
+        data: Option<serde_json::Value>,
+    },
+    UnsolicitedResponse {
+        response: String,
+    },
+    ParkStateChanged {
+        parked: bool,
+        pitch: f32,
+        roll: f32,
+    },
+    SensorDisagreement {
+        device_number: u32,
+        is_safe: bool,
+        votes: Vec<SensorVote>,
+    },
+    FirmwareRebooted {
+        previous_uptime: u64,
+        uptime: u64,
+    },
+    HealthWarning {
+        issue: String,
+        message: String,
+    },
+    CalibrationProgress {
+        phase: String,
+        percent: Option<u8>,
+    },
+    DeviceReset {
+        port: String,
+        method: String,
+    },
+    SafetyForced {
+        device_number: u32,
+        is_safe: bool,
+        expires_at: Timestamp,
+        reason: Option<String>,
+    },
+    SafetyForceCleared {
+        device_number: u32,
+        expired: bool,
+    },
+    SunAltitudeCrossed {
+        above: bool,
+        altitude: f64,
+        max_altitude: f64,
+        forces_unsafe: bool,
+    },
+    MaintenanceStarted {
+        device_number: u32,
+        expires_at: Timestamp,
+        reason: Option<String>,
+    },
+    MaintenanceEnded {
+        device_number: u32,
+        expired: bool,
+    },
+    SafetyScheduleChanged {
+        device_number: u32,
+        schedule: String,
+        active: bool,
+    },
+    RelaySwitched {
+        relay: String,
+        device_number: u32,
+        on: bool,
+        error: Option<String>,
+    },
+    TelescopeLinkChanged {
+        telescope: String,
+        state: String,
+        error: Option<String>,
+    },
+    // change is slew_started, slew_finished, parked, unparked, tracking_started, tracking_stopped
+    // or pier_flipped
+    TelescopeChanged {
+        telescope: String,
+        change: String,
+        ra: f64,
+        dec: f64,
+        pier_side: String,
+    },
+    NightlyReport {
+        night: String,
+        summary: String,
+    },
+    // Event types added by a newer bridge
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SensorVote {
+    pub device_number: u32,
+    pub available: bool,
+    pub is_safe: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+// Filters of the paged endpoints; the default returns every buffered entry, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct PageQuery {
+    // Only entries with a larger id than this
+    pub since: Option<u64>,
+    // Inclusive time range
+    pub from: Option<Timestamp>,
+    pub to: Option<Timestamp>,
+    pub order: SortOrder,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl PageQuery {
+    pub(crate) fn pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(since) = self.since {
+            pairs.push(("since", since.to_string()));
+        }
+        if let Some(from) = self.from {
+            pairs.push(("from", from.to_rfc3339()));
+        }
+        if let Some(to) = self.to {
+            pairs.push(("to", to.to_rfc3339()));
+        }
+        if self.order == SortOrder::Desc {
+            pairs.push(("order", "desc".to_string()));
+        }
+        if self.offset > 0 {
+            pairs.push(("offset", self.offset.to_string()));
+        }
+        if let Some(limit) = self.limit {
+            pairs.push(("limit", limit.to_string()));
+        }
+        pairs
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // Matching entries before offset and limit, from X-Total-Count
+    pub total: usize,
+}
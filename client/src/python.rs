@@ -0,0 +1,218 @@
+// client/src/python.rs
+// Python bindings (feature "python"): a blocking Bridge class over BridgeClient, returning the
+// API's JSON as dicts and lists, and an iterator over new events
+
+use crate::{BridgeClient, Error, EventSubscription, ForceSafety, JobOperation, PageQuery, SortOrder};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+// Longest a blocking call waits before checking for Ctrl+C
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+create_exception!(park_bridge_client, BridgeError, PyException);
+
+fn to_py_err(e: Error) -> PyErr {
+    BridgeError::new_err(e.to_string())
+}
+
+// Through JSON, so Python sees the bridge's own field names and RFC 3339 timestamps
+fn to_py<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let text = serde_json::to_string(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    Ok(py.import("json")?.call_method1("loads", (text,))?.unbind())
+}
+
+fn page_query(since: Option<u64>, limit: Option<usize>, newest_first: bool) -> PageQuery {
+    PageQuery {
+        since,
+        limit,
+        order: if newest_first { SortOrder::Desc } else { SortOrder::Asc },
+        ..PageQuery::default()
+    }
+}
+
+#[pyclass(name = "Bridge", module = "park_bridge_client")]
+struct PyBridge {
+    client: BridgeClient,
+    runtime: Arc<Runtime>,
+}
+
+impl PyBridge {
+    // Run a client call with the GIL released, so other Python threads keep going meanwhile
+    fn block_on<T: Send>(&self, py: Python<'_>, call: impl Future<Output = crate::Result<T>> + Send) -> PyResult<T> {
+        py.allow_threads(|| self.runtime.block_on(call)).map_err(to_py_err)
+    }
+}
+
+#[pymethods]
+impl PyBridge {
+    #[new]
+    #[pyo3(signature = (url, token=None))]
+    fn new(url: &str, token: Option<String>) -> PyResult<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let mut client = BridgeClient::new(url).map_err(to_py_err)?;
+        if let Some(token) = token {
+            client = client.with_token(token);
+        }
+        Ok(Self {
+            client,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    fn status(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.status())?)
+    }
+
+    fn about(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.about())?)
+    }
+
+    fn devices(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.devices())?)
+    }
+
+    fn health(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.health())?)
+    }
+
+    #[pyo3(signature = (device_number=0))]
+    fn firmware_schema(&self, py: Python<'_>, device_number: u32) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.firmware_schema(device_number))?)
+    }
+
+    #[pyo3(signature = (device_number=0))]
+    fn is_safe(&self, py: Python<'_>, device_number: u32) -> PyResult<bool> {
+        self.block_on(py, self.client.is_safe(device_number))
+    }
+
+    #[pyo3(signature = (port, baud_rate=None))]
+    fn connect(&self, py: Python<'_>, port: &str, baud_rate: Option<u32>) -> PyResult<()> {
+        self.block_on(py, self.client.connect(port, baud_rate))
+    }
+
+    fn disconnect(&self, py: Python<'_>) -> PyResult<()> {
+        self.block_on(py, self.client.disconnect())
+    }
+
+    fn send_command(&self, py: Python<'_>, command: &str) -> PyResult<String> {
+        self.block_on(py, self.client.send_command(command))
+    }
+
+    #[pyo3(signature = (since=None, limit=None, newest_first=false))]
+    fn command_history(&self, py: Python<'_>, since: Option<u64>, limit: Option<usize>, newest_first: bool) -> PyResult<PyObject> {
+        let query = page_query(since, limit, newest_first);
+        to_py(py, &self.block_on(py, self.client.command_history(&query))?.items)
+    }
+
+    // operation: "calibrate", "set_park" or "factory_reset"
+    #[pyo3(signature = (operation, device_number=None))]
+    fn submit_job(&self, py: Python<'_>, operation: &str, device_number: Option<u32>) -> PyResult<PyObject> {
+        let operation: JobOperation = serde_json::from_value(serde_json::Value::from(operation))
+            .map_err(|_| PyValueError::new_err(format!("unknown operation '{}'", operation)))?;
+        to_py(py, &self.block_on(py, self.client.submit_job(operation, device_number))?)
+    }
+
+    fn jobs(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.jobs())?)
+    }
+
+    fn job(&self, py: Python<'_>, id: u64) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.job(id))?)
+    }
+
+    fn cancel_job(&self, py: Python<'_>, id: u64) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.cancel_job(id))?)
+    }
+
+    fn wait_for_job(&self, py: Python<'_>, id: u64) -> PyResult<PyObject> {
+        to_py(py, &self.block_on(py, self.client.wait_for_job(id))?)
+    }
+
+    #[pyo3(signature = (is_safe, minutes, device_number=0, reason=None))]
+    fn force_safety(
+        &self,
+        py: Python<'_>,
+        is_safe: bool,
+        minutes: f64,
+        device_number: u32,
+        reason: Option<String>,
+    ) -> PyResult<PyObject> {
+        let request = ForceSafety {
+            device_number,
+            is_safe,
+            minutes,
+            reason,
+        };
+        to_py(py, &self.block_on(py, self.client.force_safety(&request))?)
+    }
+
+    #[pyo3(signature = (device_number=0))]
+    fn clear_forced_safety(&self, py: Python<'_>, device_number: u32) -> PyResult<()> {
+        self.block_on(py, self.client.clear_forced_safety(device_number))
+    }
+
+    #[pyo3(signature = (since=None, limit=None, newest_first=false))]
+    fn events(&self, py: Python<'_>, since: Option<u64>, limit: Option<usize>, newest_first: bool) -> PyResult<PyObject> {
+        let query = page_query(since, limit, newest_first);
+        to_py(py, &self.block_on(py, self.client.events(&query))?.items)
+    }
+
+    // Iterator over events published from now on: `for event in bridge.subscribe_events(): ...`
+    #[pyo3(signature = (interval=1.0))]
+    fn subscribe_events(&self, py: Python<'_>, interval: f64) -> PyResult<PyEventSubscription> {
+        if !interval.is_finite() || interval <= 0.0 {
+            return Err(PyValueError::new_err("interval must be a positive number of seconds"));
+        }
+        let subscription = self.block_on(py, self.client.subscribe_events(Duration::from_secs_f64(interval)))?;
+        Ok(PyEventSubscription {
+            subscription,
+            runtime: self.runtime.clone(),
+        })
+    }
+}
+
+#[pyclass(name = "EventSubscription", module = "park_bridge_client")]
+struct PyEventSubscription {
+    subscription: EventSubscription,
+    runtime: Arc<Runtime>,
+}
+
+#[pymethods]
+impl PyEventSubscription {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    // Blocks until the next event; Ctrl+C raises KeyboardInterrupt as usual
+    fn __next__(mut slf: PyRefMut<'_, Self>, py: Python<'_>) -> PyResult<PyObject> {
+        let this = &mut *slf;
+        loop {
+            let runtime = &this.runtime;
+            let subscription = &mut this.subscription;
+            let next = py.allow_threads(|| {
+                runtime.block_on(async { tokio::time::timeout(SIGNAL_CHECK_INTERVAL, subscription.next()).await })
+            });
+            match next {
+                Ok(event) => return to_py(py, &event.map_err(to_py_err)?),
+                Err(_) => py.check_signals()?,
+            }
+        }
+    }
+}
+
+#[pymodule]
+fn park_bridge_client(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyBridge>()?;
+    m.add_class::<PyEventSubscription>()?;
+    m.add("BridgeError", m.py().get_type::<BridgeError>())?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+// src/alpaca_params.rs
+// Alpaca parameter names are case insensitive: rewrite them to their canonical spelling in query
+// strings and form bodies before any handler or extractor sees them
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+// Canonical spellings of every Alpaca parameter the bridge reads, for GET queries and PUT forms
+pub const PARAMETER_NAMES: &[&str] = &[
+    "ClientID",
+    "ClientTransactionID",
+    "Connected",
+    "Action",
+    "Parameters",
+    "Command",
+    "Raw",
+];
+
+// Alpaca PUT forms are a handful of short parameters; larger bodies are rejected
+const MAX_FORM_BODY: usize = 64 * 1024;
+
+pub fn canonical_name(name: &str) -> Option<&'static str> {
+    PARAMETER_NAMES.iter().copied().find(|canonical| canonical.eq_ignore_ascii_case(name))
+}
+
+// Rewrite the parameter names of a urlencoded query string or form body; values are kept as sent
+pub fn normalize(encoded: &str) -> String {
+    encoded
+        .split('&')
+        .map(|pair| {
+            let (key, rest) = match pair.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (pair, None),
+            };
+            let decoded = urlencoding::decode(key).map(|key| key.into_owned()).unwrap_or_else(|_| key.to_string());
+            let key = canonical_name(&decoded).unwrap_or(key);
+            match rest {
+                Some(value) => format!("{}={}", key, value),
+                None => key.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// Decoded value of a parameter, matching its name in any casing
+pub fn find(encoded: &str, name: &str) -> Option<String> {
+    encoded
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| urlencoding::decode(key).is_ok_and(|key| key.eq_ignore_ascii_case(name)))
+        .and_then(|(_, value)| {
+            let value = value.replace('+', " ");
+            urlencoding::decode(&value).ok().map(|value| value.trim().to_string())
+        })
+}
+
+// Middleware for the Alpaca device and management APIs
+pub async fn normalize_alpaca_params(request: Request<Body>, next: Next) -> Response {
+    let path = request.uri().path();
+    if !path.starts_with("/api/v1/") && !path.starts_with("/management/") {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    if let Some(query) = parts.uri.query() {
+        let path_and_query = format!("{}?{}", parts.uri.path(), normalize(query));
+        let mut uri_parts = parts.uri.clone().into_parts();
+        uri_parts.path_and_query = path_and_query.parse().ok();
+        if let Ok(uri) = Uri::from_parts(uri_parts) {
+            parts.uri = uri;
+        }
+    }
+
+    let is_form = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return next.run(Request::from_parts(parts, body)).await;
+    }
+
+    let body = match axum::body::to_bytes(body, MAX_FORM_BODY).await {
+        Ok(bytes) => match std::str::from_utf8(&bytes) {
+            Ok(form) => Body::from(normalize(form)),
+            Err(_) => Body::from(bytes),
+        },
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+    // The rewritten body may differ in length when a name was percent-encoded
+    parts.headers.remove(header::CONTENT_LENGTH);
+    next.run(Request::from_parts(parts, body)).await
+}
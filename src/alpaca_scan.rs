@@ -0,0 +1,204 @@
+// src/alpaca_scan.rs
+// Alpaca discovery scanner (discover subcommand): broadcasts the discovery message, then asks
+// every server that answers for its description and configured devices
+
+use crate::discovery_server::{DISCOVERY_MESSAGE, DISCOVERY_PORT};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::Instant;
+use tracing::debug;
+
+// Management replies are a few hundred bytes; anything far larger is not an Alpaca server
+const MAX_RESPONSE_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    // Where the discovery message is sent: the broadcast address, a subnet broadcast or single hosts
+    pub targets: Vec<IpAddr>,
+    pub discovery_port: u16,
+    // How long to collect discovery replies, and the limit for each management request
+    pub timeout: Duration,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            targets: vec![IpAddr::V4(Ipv4Addr::BROADCAST)],
+            discovery_port: DISCOVERY_PORT,
+            timeout: Duration::from_secs(2),
+        }
+    }
+}
+
+// Entry of a server's /management/v1/configureddevices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct DiscoveredDevice {
+    pub device_name: String,
+    pub device_type: String,
+    pub device_number: u32,
+    #[serde(rename = "UniqueID", default)]
+    pub unique_id: String,
+}
+
+// One server that answered the discovery broadcast
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveredServer {
+    pub address: IpAddr,
+    pub alpaca_port: u16,
+    pub server_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub manufacturer_version: Option<String>,
+    pub location: Option<String>,
+    pub devices: Vec<DiscoveredDevice>,
+    // Set when the server answered discovery but its management API did not
+    pub error: Option<String>,
+}
+
+// Broadcast, collect the replies until the timeout, then query each server; sorted by address
+pub async fn scan(options: &ScanOptions) -> std::io::Result<Vec<DiscoveredServer>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    socket.set_broadcast(true)?;
+    for target in &options.targets {
+        let destination = SocketAddr::new(*target, options.discovery_port);
+        debug!("Sending Alpaca discovery to {}", destination);
+        socket.send_to(DISCOVERY_MESSAGE.as_bytes(), destination).await?;
+    }
+
+    let mut responders: Vec<(IpAddr, u16)> = Vec::new();
+    let deadline = Instant::now() + options.timeout;
+    let mut buf = [0; 1024];
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, from) = received?;
+        match alpaca_port(&buf[..len]) {
+            Some(port) if !responders.contains(&(from.ip(), port)) => {
+                debug!("Alpaca server at {}:{}", from.ip(), port);
+                responders.push((from.ip(), port));
+            }
+            Some(_) => {}
+            None => debug!("Ignoring discovery reply from {}: {}", from, String::from_utf8_lossy(&buf[..len])),
+        }
+    }
+
+    let mut servers = Vec::new();
+    for (address, port) in responders {
+        servers.push(query_server(address, port, options.timeout).await);
+    }
+    servers.sort_by_key(|server| (server.address, server.alpaca_port));
+    Ok(servers)
+}
+
+// Description and configured devices of one server; a failure is reported on the entry
+pub async fn query_server(address: IpAddr, alpaca_port: u16, timeout: Duration) -> DiscoveredServer {
+    let mut server = DiscoveredServer {
+        address,
+        alpaca_port,
+        server_name: None,
+        manufacturer: None,
+        manufacturer_version: None,
+        location: None,
+        devices: Vec::new(),
+        error: None,
+    };
+    let target = SocketAddr::new(address, alpaca_port);
+
+    match get_value(target, "/management/v1/description", timeout).await {
+        Ok(description) => {
+            let field = |name: &str| description.get(name).and_then(Value::as_str).map(str::to_string);
+            server.server_name = field("ServerName");
+            server.manufacturer = field("Manufacturer");
+            server.manufacturer_version = field("ManufacturerVersion");
+            server.location = field("Location");
+        }
+        Err(e) => server.error = Some(e),
+    }
+    match get_value(target, "/management/v1/configureddevices", timeout).await {
+        Ok(devices) => match serde_json::from_value(devices) {
+            Ok(devices) => server.devices = devices,
+            Err(e) => server.error = Some(format!("unexpected configureddevices reply: {}", e)),
+        },
+        Err(e) => {
+            server.error.get_or_insert(e);
+        }
+    }
+    server
+}
+
+// Fixed-width listing: one line per server, its devices indented below it
+pub fn format_table(servers: &[DiscoveredServer]) -> String {
+    if servers.is_empty() {
+        return "No Alpaca servers answered the discovery broadcast".to_string();
+    }
+
+    let mut table = format!("{:<22} {:<30} {}\n", "address", "server", "manufacturer");
+    for server in servers {
+        let version = server.manufacturer_version.as_deref().map(|v| format!(" {}", v)).unwrap_or_default();
+        table.push_str(&format!(
+            "{:<22} {:<30} {}{}\n",
+            SocketAddr::new(server.address, server.alpaca_port).to_string(),
+            server.server_name.as_deref().unwrap_or("-"),
+            server.manufacturer.as_deref().unwrap_or("-"),
+            version
+        ));
+        if let Some(error) = &server.error {
+            table.push_str(&format!("    error: {}\n", error));
+        }
+        for device in &server.devices {
+            table.push_str(&format!(
+                "    {:<16} #{:<3} {:<30} {}\n",
+                device.device_type, device.device_number, device.device_name, device.unique_id
+            ));
+        }
+    }
+    table.push_str(&format!("\n{} server(s) found\n", servers.len()));
+    table
+}
+
+fn alpaca_port(reply: &[u8]) -> Option<u16> {
+    let reply: Value = serde_json::from_slice(reply).ok()?;
+    reply.get("AlpacaPort")?.as_u64()?.try_into().ok()
+}
+
+// Value of an Alpaca management response, over a plain HTTP/1.0 request so the scanner needs no
+// HTTP client dependency
+async fn get_value(target: SocketAddr, path: &str, timeout: Duration) -> Result<Value, String> {
+    let body = tokio::time::timeout(timeout, http_get(target, path))
+        .await
+        .map_err(|_| format!("{} timed out", path))?
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let mut response: Value = serde_json::from_slice(&body).map_err(|e| format!("{}: invalid JSON: {}", path, e))?;
+    match response.get("ErrorNumber").and_then(Value::as_i64) {
+        Some(number) if number != 0 => {
+            let message = response.get("ErrorMessage").and_then(Value::as_str).unwrap_or_default();
+            Err(format!("{}: Alpaca error {}: {}", path, number, message))
+        }
+        _ => Ok(response.get_mut("Value").map(Value::take).unwrap_or(Value::Null)),
+    }
+}
+
+async fn http_get(target: SocketAddr, path: &str) -> std::io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(target).await?;
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+        path, target
+    );
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = Vec::new();
+    stream.take(MAX_RESPONSE_BYTES).read_to_end(&mut response).await?;
+
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| invalid("incomplete HTTP response".to_string()))?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let status_line = head.lines().next().unwrap_or_default();
+    if status_line.split_whitespace().nth(1) != Some("200") {
+        return Err(invalid(format!("HTTP {}", status_line)));
+    }
+    Ok(response[split + 4..].to_vec())
+}
@@ -1,30 +1,79 @@
 // src/alpaca_server.rs
 // Fixed version with proper ClientTransactionID handling and PUT endpoints
 
-use crate::device_state::DeviceState;
+use crate::alpaca_params::{self, normalize_alpaca_params};
+use crate::ascom_clients::{AscomClient, AscomClientRegistry};
+use crate::device_registry::{AlpacaDeviceType, AlpacaDevices, ConfiguredDevice, DeviceHandle, DeviceRegistry, DeviceSummary};
+use crate::device_state::{DeviceState, Maintenance, SafetyOverride, StatusSummary};
 use crate::connection_manager::ConnectionManager;
+use crate::discovery_server::{DiscoveryClient, DiscoveryTracker};
+use crate::config::{EventsConfig, StaleReply};
+use crate::events::{BridgeEvent, EventCategory, EventFilter};
+use crate::session_log::SessionEntry;
+use crate::nightly_report::NightlyReport;
+use crate::notifications::ChannelStatus;
+use crate::fault_injection::{FaultPlan, FaultStatus};
+use crate::command_history::CommandRecord;
+use crate::firmware_schema::SchemaReport;
+use crate::health::HealthStatus;
+use crate::noise::NoiseStats;
+use crate::temperature_compensation::CompensationStatus;
+#[cfg(feature = "telescope-client")]
+use crate::telescope_manager::{TelescopeManager, TelescopeSnapshot};
+#[cfg(feature = "telescope-client")]
+use crate::telescope_client::{AxisRates, SlewDirection, TelescopeAxis, TelescopeClient};
+#[cfg(feature = "telescope-client")]
+use crate::errors::TelescopeError;
+use crate::errors::BridgeError;
+use crate::self_test::SelfTestReport;
+use crate::server_info::{self, About, ServerInfo};
+use crate::http_metrics::{track_http_metrics, HttpMetrics, RouteMetrics};
+use crate::pagination::{Page, PageQuery, TOTAL_COUNT_HEADER};
+use crate::jobs::{Job, JobManager, JobOperation};
+use crate::transaction_log::{log_ascom_transactions, TransactionLog};
+use crate::http_cache::{self, etag_response};
+use crate::status_delta::StatusHistory;
+use crate::web_users::{self, WebUsers};
+#[cfg(feature = "web-ui")]
+use crate::web_assets::{WebAssets, ICON_PNG};
 use axum::{
-    extract::{Path, Query, State, Extension},
-    response::{Html, Json, Response},  // Add Response
-    routing::{get, put},
+    extract::{ConnectInfo, Path, Query, State, Extension},
+    response::{sse::{Event as SseEvent, KeepAlive, Sse}, IntoResponse, Json, Response},
+    routing::get,
     middleware,
     Router,
-    http::{StatusCode, HeaderMap, HeaderValue, header},
+    http::{StatusCode, HeaderMap, header},
     body::Body,
 };
+use futures_util::{Stream, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, RwLock};
+use tokio_util::sync::CancellationToken;
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
-use tracing::info;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tower_http::timeout::TimeoutLayer;
+use tower_http::trace::{DefaultOnResponse, TraceLayer};
+use tower_http::LatencyUnit;
+use tracing::{debug, info, warn, Level};
+#[cfg(feature = "web-ui")]
+use {
+    crate::config::{self, ConfigIssues, SafetyConfig},
+    crate::config_store::DeviceSettings,
+    crate::protocol,
+    crate::web_assets::{DeviceSetup, SetupPage},
+    axum::Form,
+    tracing::error,
+};
 use std::sync::atomic::{AtomicU32, Ordering};
 
 
 // External template files
-const INDEX_HTML: &str = include_str!("../templates/index.html");
-const STYLE_CSS: &str = include_str!("../templates/style.css");
-const SCRIPT_JS: &str = include_str!("../templates/script.js");
-const ICON_PNG: &[u8] = include_bytes!("../assets/telescope-icon.png");
 
 // Global server transaction ID counter
 static SERVER_TRANSACTION_ID: AtomicU32 = AtomicU32::new(0);
@@ -37,6 +86,7 @@ fn next_server_transaction_id() -> u32 {
 #[derive(Clone, Debug)]
 struct ConnectedFormData {
     client_transaction_id: u32,
+    client_id: u32,
     connected: String,
 }
 
@@ -50,7 +100,7 @@ struct AlpacaResponse<T> {
     #[serde(rename = "ServerTransactionID")]
     server_transaction_id: u32,
     #[serde(rename = "ErrorNumber")]
-    error_number: u32,
+    error_number: i32,
     #[serde(rename = "ErrorMessage")]
     error_message: String,
 }
@@ -66,7 +116,7 @@ impl<T> AlpacaResponse<T> {
         }
     }
     
-    fn error(value: T, client_transaction_id: u32, error_number: u32, error_message: String) -> Self {
+    fn error(value: T, client_transaction_id: u32, error_number: i32, error_message: String) -> Self {
         Self {
             value,
             client_transaction_id,
@@ -77,19 +127,13 @@ impl<T> AlpacaResponse<T> {
     }
 }
 
-// Query parameters for GET requests (case insensitive)
+// Query parameters for GET requests; names arrive canonically cased from normalize_alpaca_params
 #[derive(Deserialize)]
 struct AlpacaQuery {
     #[serde(rename = "ClientTransactionID")]
-    #[serde(alias = "clienttransactionid")]
-    #[serde(alias = "ClientTransactionId")]
-    #[serde(alias = "clientTransactionID")]
     client_transaction_id: Option<u32>,
     
     #[serde(rename = "ClientID")]
-    #[serde(alias = "clientid")]
-    #[serde(alias = "ClientId")]
-    #[serde(alias = "clientID")]
     client_id: Option<u32>,
 }
 
@@ -105,6 +149,13 @@ struct CommandRequest {
     command: String,
 }
 
+#[derive(Deserialize)]
+struct JobRequest {
+    operation: JobOperation,
+    // Defaults to the primary device
+    device_number: Option<u32>,
+}
+
 #[derive(Serialize)]
 struct PortListResponse {
     ports: Vec<crate::port_discovery::PortInfo>,
@@ -124,11 +175,173 @@ struct CommandResponse {
     message: String,
 }
 
+#[derive(Serialize)]
+struct MetricsResponse {
+    http: Vec<RouteMetrics>,
+    devices: Vec<DeviceMetrics>,
+}
+
+#[derive(Serialize)]
+struct DeviceMetrics {
+    device_number: u32,
+    health: HealthStatus,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ForcedSafety {
+    Safe,
+    Unsafe,
+}
+
+#[derive(Deserialize)]
+struct SafetyForceRequest {
+    #[serde(default)]
+    device_number: u32,
+    state: ForcedSafety,
+    // Fractions allowed; capped by [safety_force] max_minutes
+    minutes: f64,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SafetyForceQuery {
+    #[serde(default)]
+    device_number: u32,
+}
+
+#[derive(Deserialize)]
+struct MaintenanceRequest {
+    #[serde(default)]
+    device_number: u32,
+    // [maintenance] default_minutes when left out; capped by max_minutes
+    minutes: Option<f64>,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MaintenanceQuery {
+    #[serde(default)]
+    device_number: u32,
+}
+
+#[derive(Deserialize)]
+struct SnoozeRequest {
+    // Capped by [notifications] max_snooze_minutes
+    minutes: f64,
+}
+
+#[derive(Deserialize)]
+struct StatusLineQuery {
+    #[serde(default)]
+    device_number: u32,
+}
+
+#[derive(Deserialize)]
+struct NoiseQuery {
+    #[serde(default)]
+    device_number: u32,
+}
+
+#[derive(Deserialize)]
+struct SchemaQuery {
+    #[serde(default)]
+    device_number: u32,
+}
+
+#[derive(Deserialize)]
+struct CompensationQuery {
+    #[serde(default)]
+    device_number: u32,
+}
+
+#[derive(Deserialize)]
+struct SelfTestQuery {
+    #[serde(default)]
+    device_number: u32,
+}
+
+// One Name/Value entry of the Alpaca DeviceState list
+#[derive(Serialize)]
+struct StateValue {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Value")]
+    value: serde_json::Value,
+}
+
+impl StateValue {
+    fn new(name: &str, value: impl Into<serde_json::Value>) -> Self {
+        Self {
+            name: name.to_string(),
+            value: value.into(),
+        }
+    }
+}
+
 // Updated SharedState to include ConnectionManager
 #[derive(Clone)]
 struct AppState {
-    device_state: Arc<RwLock<DeviceState>>,
-    connection_manager: Arc<ConnectionManager>,
+    devices: DeviceRegistry,
+    discovery: DiscoveryTracker,
+    ascom_clients: AscomClientRegistry,
+    jobs: JobManager,
+    http_metrics: HttpMetrics,
+    status_history: StatusHistory,
+    #[cfg(feature = "web-ui")]
+    assets: WebAssets,
+    // Ends open event streams, which would otherwise hold up the graceful shutdown
+    shutdown: CancellationToken,
+}
+
+impl AppState {
+    fn new(devices: DeviceRegistry, discovery: DiscoveryTracker) -> Self {
+        Self {
+            devices,
+            discovery,
+            ascom_clients: AscomClientRegistry::new(),
+            jobs: JobManager::new(),
+            http_metrics: HttpMetrics::new(),
+            status_history: StatusHistory::default(),
+            #[cfg(feature = "web-ui")]
+            assets: WebAssets::embedded(),
+            shutdown: CancellationToken::new(),
+        }
+    }
+
+    // The web interface and its API act on the primary device
+    fn connection_manager(&self) -> &Arc<ConnectionManager> {
+        &self.devices.primary().connection_manager
+    }
+
+    fn device_state(&self) -> &Arc<RwLock<DeviceState>> {
+        &self.devices.primary().device_state
+    }
+}
+
+// Middleware to refresh the last-transaction time of connected ASCOM clients
+async fn track_ascom_transactions(
+    State(state): State<AppState>,
+    request: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let path = request.uri().path().to_string();
+    if request.method() != axum::http::Method::GET || !path.starts_with("/api/v1/") {
+        return next.run(request).await;
+    }
+
+    let client_id = alpaca_params::find(request.uri().query().unwrap_or(""), "ClientID")
+        .and_then(|value| value.parse::<u32>().ok())
+        .unwrap_or(0);
+    let address = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip());
+
+    let response = next.run(request).await;
+    let endpoint = path.rsplit('/').next().unwrap_or("");
+    state.ascom_clients.record_transaction(client_id, address, endpoint);
+    response
 }
 
 // Middleware to parse form data for PUT Connected requests
@@ -147,18 +360,24 @@ async fn parse_connected_form(
             let body_str = String::from_utf8_lossy(&body_bytes);
             
             let mut client_transaction_id = 0u32;
+            let mut client_id = 0u32;
             let mut connected = String::new();
             
             // Parse form data manually since axum::extract::Form doesn't work in middleware
             for pair in body_str.split('&') {
                 if let Some((key, value)) = pair.split_once('=') {
                     match key {
-                        "ClientTransactionID" | "clienttransactionid" | "ClientTransactionId" | "clientTransactionID" => {
+                        "ClientTransactionID" => {
                             if let Ok(decoded) = urlencoding::decode(value) {
                                 client_transaction_id = decoded.parse().unwrap_or(0);
                             }
                         }
-                        "Connected" | "connected" => {
+                        "ClientID" => {
+                            if let Ok(decoded) = urlencoding::decode(value) {
+                                client_id = decoded.parse().unwrap_or(0);
+                            }
+                        }
+                        "Connected" => {
                             if let Ok(decoded) = urlencoding::decode(value) {
                                 connected = decoded.into_owned();
                             }
@@ -171,6 +390,7 @@ async fn parse_connected_form(
             // Insert parsed form data into request extensions
             parts.extensions.insert(Some(ConnectedFormData {
                 client_transaction_id,
+                client_id,
                 connected,
             }));
             
@@ -187,28 +407,119 @@ async fn parse_connected_form(
     }
 }
 
+// Listener and logging settings for the HTTP server
+pub struct ServerOptions {
+    pub bind_address: String,
+    pub port: u16,
+    // Log every request at INFO instead of DEBUG
+    pub access_log: bool,
+    pub transaction_log: Option<TransactionLog>,
+    // Requests still unanswered after this get 408 Request Timeout
+    pub request_timeout: Option<Duration>,
+    // Cancelled on shutdown: stop accepting connections and let in-flight requests finish
+    pub shutdown: CancellationToken,
+    // Embedded web UI, or the on-disk copy with --dev-assets
+    #[cfg(feature = "web-ui")]
+    pub assets: WebAssets,
+    // Serve only the JSON and Alpaca APIs, without the web UI routes
+    pub headless: bool,
+    // Sign-in for the web interface and JSON API; everything stays open without configured users
+    pub web_users: WebUsers,
+    // Bearer token of the event feed
+    pub events: EventsConfig,
+}
+
 pub async fn create_alpaca_server(
-    bind_address: String,
-    port: u16,
-    device_state: Arc<RwLock<DeviceState>>,
-    connection_manager: Arc<ConnectionManager>,
+    options: ServerOptions,
+    devices: DeviceRegistry,
+    discovery: DiscoveryTracker,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let app_state = AppState {
-        device_state,
-        connection_manager,
+    let routes = api_routes();
+    #[cfg(feature = "web-ui")]
+    let routes = match options.headless {
+        true => routes,
+        false => routes.merge(web_ui_routes()),
     };
+    let mut app_state = AppState::new(devices, discovery);
+    app_state.shutdown = options.shutdown.clone();
+    #[cfg(feature = "web-ui")]
+    {
+        app_state.assets = options.assets;
+    }
+    let mut app = protect_event_feed(build_router(app_state, routes), options.events, options.web_users.clone());
+    if options.web_users.is_enabled() {
+        app = web_users::protect(app, options.web_users);
+    }
+    if let Some(transaction_log) = options.transaction_log {
+        app = app.layer(middleware::from_fn_with_state(transaction_log, log_ascom_transactions));
+    }
+    if let Some(request_timeout) = options.request_timeout {
+        app = app.layer(TimeoutLayer::new(request_timeout));
+    }
     
-    let app = create_router(app_state);
+    // Every request gets an x-request-id (kept if the client sent one). The id is recorded on the
+    // request span, which the serial client re-enters for the firmware exchange the request causes
+    let access_level = if options.access_log { Level::INFO } else { Level::DEBUG };
+    let app = app
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|request: &axum::http::Request<Body>| {
+                    let request_id = request
+                        .headers()
+                        .get("x-request-id")
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or("-");
+                    tracing::info_span!(
+                        "request",
+                        id = %request_id,
+                        method = %request.method(),
+                        uri = %request.uri(),
+                    )
+                })
+                .on_response(DefaultOnResponse::new().level(access_level).latency_unit(LatencyUnit::Millis)),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
     
+    let (bind_address, port) = (options.bind_address, options.port);
     let listener = tokio::net::TcpListener::bind(format!("{}:{}", bind_address, port)).await?;
     
     info!("ASCOM Alpaca server listening on {}:{}", bind_address, port);
     
-    axum::serve(listener, app).await?;
+    // Peer addresses are needed to tell ASCOM clients apart
+    let shutdown = options.shutdown;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move {
+            shutdown.cancelled().await;
+            info!("ASCOM Alpaca server draining in-flight requests");
+        })
+        .await?;
     Ok(())
 }
 
-fn create_router(app_state: AppState) -> Router {
+// Build the full HTTP router (web UI, web API and ASCOM Alpaca endpoints)
+pub fn create_router(devices: DeviceRegistry, discovery: DiscoveryTracker) -> Router {
+    let routes = api_routes();
+    #[cfg(feature = "web-ui")]
+    let routes = routes.merge(web_ui_routes());
+    build_router(AppState::new(devices, discovery), routes)
+}
+
+// Web API and ASCOM Alpaca endpoints only (--headless), for deployments with their own dashboard
+pub fn create_api_router(devices: DeviceRegistry, discovery: DiscoveryTracker) -> Router {
+    build_router(AppState::new(devices, discovery), api_routes())
+}
+
+#[cfg(feature = "web-ui")]
+pub fn create_router_with_assets(devices: DeviceRegistry, discovery: DiscoveryTracker, assets: WebAssets) -> Router {
+    let mut app_state = AppState::new(devices, discovery);
+    app_state.assets = assets;
+    build_router(app_state, api_routes().merge(web_ui_routes()))
+}
+
+// The browser dashboard and the Alpaca setup pages
+#[cfg(feature = "web-ui")]
+fn web_ui_routes() -> Router<AppState> {
     Router::new()
         // Web interface
         .route("/", get(web_interface))
@@ -217,20 +528,55 @@ fn create_router(app_state: AppState) -> Router {
         .route("/favicon.ico", get(serve_favicon))
         .route("/icon-192.png", get(serve_icon_192))
         .route("/icon-512.png", get(serve_icon_512))
+        .route("/assets/:name", get(serve_asset))
+        .route("/dev/reload", get(dev_reload))
         
-        // Device setup endpoints
-        .route("/setup", get(web_interface))
-        .route("/setup/v1/safetymonitor/:device_number/setup", get(web_interface_device_control))
+        // Sign-in form for [[web_auth.users]]
+        .route("/login", get(web_login))
         
+        // Device setup endpoints
+        .route("/setup", get(web_setup))
+        .route("/setup/v1/safetymonitor/:device_number/setup", get(web_device_setup).post(save_device_setup))
+}
+
+fn api_routes() -> Router<AppState> {
+    Router::new()
         // Web API endpoints
         .route("/api/status", get(api_status))
+        .route("/api/status/delta", get(api_status_delta))
+        .route("/api/about", get(api_about))
+        .route("/status.txt", get(status_text))
+        .route("/status.json", get(status_json))
         .route("/api/ports", get(api_ports))
         .route("/api/connect", axum::routing::post(api_connect))
         .route("/api/disconnect", axum::routing::post(api_disconnect))
         .route("/api/command", axum::routing::post(api_send_command))
+        .route("/api/command/history", get(api_command_history))
+        .route("/api/jobs", get(api_list_jobs).post(api_submit_job))
+        .route("/api/jobs/:id", get(api_get_job).delete(api_cancel_job))
         .route("/api/device/calibrate", axum::routing::post(api_calibrate))
         .route("/api/device/set_park", axum::routing::post(api_set_park))
         .route("/api/device/factory_reset", axum::routing::post(api_factory_reset))
+        .route("/api/events", get(api_events))
+        .route("/api/events/stream", get(api_event_stream))
+        .route("/api/session_log", get(api_session_log))
+        .route("/api/reports/nightly", get(api_nightly_report))
+        .route("/api/discovery/clients", get(api_discovery_clients))
+        .route("/api/clients", get(api_ascom_clients))
+        .route("/api/devices", get(api_devices))
+        .route("/api/health", get(api_health))
+        .route("/api/noise", get(api_noise))
+        .route("/api/firmware/schema", get(api_firmware_schema))
+        .route("/api/compensation", get(api_compensation))
+        .route("/api/selftest", get(api_self_test))
+        .route("/api/metrics", get(api_metrics))
+        .route("/api/safety/force", axum::routing::post(api_force_safety).delete(api_clear_forced_safety))
+        .route("/api/maintenance", axum::routing::post(api_start_maintenance).delete(api_end_maintenance))
+        .route("/api/notifications", get(api_notifications))
+        .route("/api/notifications/:channel/snooze", axum::routing::post(api_snooze_notifications).delete(api_unsnooze_notifications))
+        
+        // Debug-only fault injection (requires --fault-injection)
+        .route("/api/debug/faults", get(api_get_faults).post(api_inject_faults).delete(api_clear_faults))
         
         // ASCOM Management API
         .route("/management/apiversions", get(get_management_api_versions))
@@ -238,19 +584,54 @@ fn create_router(app_state: AppState) -> Router {
         .route("/management/v1/configureddevices", get(get_configured_devices))
         
         // ASCOM Device API - Common endpoints
-        .route("/api/v1/safetymonitor/:device_number/connected", get(get_connected))
-        .route("/api/v1/safetymonitor/:device_number/connected", put(put_connected))
-        .route("/api/v1/safetymonitor/:device_number/description", get(get_description))
-        .route("/api/v1/safetymonitor/:device_number/driverinfo", get(get_driver_info))
-        .route("/api/v1/safetymonitor/:device_number/driverversion", get(get_driver_version))
-        .route("/api/v1/safetymonitor/:device_number/interfaceversion", get(get_interface_version))
-        .route("/api/v1/safetymonitor/:device_number/name", get(get_name))
-        .route("/api/v1/safetymonitor/:device_number/supportedactions", get(get_supported_actions))
+        .merge(common_device_routes(AlpacaDeviceType::SafetyMonitor))
         
         // ASCOM Device API - SafetyMonitor specific
         .route("/api/v1/safetymonitor/:device_number/issafe", get(get_is_safe))
-        
+        .route("/api/v1/safetymonitor/:device_number/devicestate", get(get_device_state))
+
+        // The mounts' Alpaca servers ([[telescopes]])
+        .merge(telescope_routes())
+}
+
+// Endpoints every Alpaca device type has, under /api/v1/{type}/:device_number/
+fn common_device_routes(device_type: AlpacaDeviceType) -> Router<AppState> {
+    let base = format!("/api/v1/{}/:device_number", device_type.name().to_ascii_lowercase());
+    Router::new()
+        .route(&format!("{}/connected", base), get(get_connected).put(put_connected))
+        .route(&format!("{}/description", base), get(get_description))
+        .route(&format!("{}/driverinfo", base), get(get_driver_info))
+        .route(&format!("{}/driverversion", base), get(get_driver_version))
+        .route(&format!("{}/interfaceversion", base), get(get_interface_version))
+        .route(&format!("{}/name", base), get(get_name))
+        .route(&format!("{}/supportedactions", base), get(get_supported_actions))
+}
+
+#[cfg(feature = "telescope-client")]
+fn telescope_routes() -> Router<AppState> {
+    Router::new()
+        .route("/api/telescopes", get(api_telescopes))
+        .route("/api/telescopes/:id", get(api_telescope))
+        .route("/api/telescopes/:id/move", axum::routing::post(api_telescope_move))
+        .route("/api/telescopes/:id/axis_rates", get(api_telescope_axis_rates))
+        .route("/api/telescopes/:id/actions", get(api_telescope_supported_actions))
+        .route("/api/telescopes/:id/action", axum::routing::post(api_telescope_run_action))
+        .route("/api/telescopes/:id/:action", axum::routing::post(api_telescope_action))
+}
+
+#[cfg(not(feature = "telescope-client"))]
+fn telescope_routes() -> Router<AppState> {
+    Router::new()
+}
+
+fn build_router(app_state: AppState, routes: Router<AppState>) -> Router {
+    routes
+        .route_layer(middleware::from_fn_with_state(app_state.http_metrics.clone(), track_http_metrics))
+        .layer(middleware::from_fn_with_state(app_state.clone(), track_ascom_transactions))
         .layer(middleware::from_fn(parse_connected_form))
+        .layer(middleware::from_fn(normalize_alpaca_params))
+        // gzip/br per Accept-Encoding; images and the SSE stream are left uncompressed
+        .layer(CompressionLayer::new())
         .layer(CorsLayer::permissive())
         .with_state(app_state)
 }
@@ -260,35 +641,429 @@ fn get_client_transaction_id(query_id: Option<u32>) -> u32 {
     query_id.unwrap_or(0)
 }
 
+// Device named by an Alpaca device API path, /api/v1/{device_type}/{device_number}/...; the
+// routes spell out the type so their templates (and /api/metrics labels) stay literal
+struct DevicePath {
+    device_type: String,
+    device_number: u32,
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync> axum::extract::FromRequestParts<S> for DevicePath {
+    type Rejection = axum::extract::rejection::PathRejection;
+
+    async fn from_request_parts(parts: &mut axum::http::request::Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(device_number) = Path::<u32>::from_request_parts(parts, state).await?;
+        let device_type = parts.uri.path().split('/').nth(3).unwrap_or_default().to_string();
+        Ok(Self {
+            device_type,
+            device_number,
+        })
+    }
+}
+
+// Answer of an Alpaca device API handler: the value, or the error in the envelope of the value's type
+type AlpacaResult<T> = Result<Json<AlpacaResponse<T>>, (StatusCode, Json<AlpacaResponse<T>>)>;
+
+// A BridgeError on its way to an Alpaca client. `?` turns it into the handler's envelope with
+// the error's ASCOM number and HTTP status, so handlers never assemble error responses themselves.
+struct AlpacaError {
+    error: BridgeError,
+    client_transaction_id: u32,
+}
+
+impl<T: Default> From<AlpacaError> for (StatusCode, Json<AlpacaResponse<T>>) {
+    fn from(failed: AlpacaError) -> Self {
+        let status = if failed.error.is_bad_request() { StatusCode::BAD_REQUEST } else { StatusCode::OK };
+        let response = AlpacaResponse::error(
+            T::default(),
+            failed.client_transaction_id,
+            failed.error.alpaca_error_number(),
+            failed.error.to_string(),
+        );
+        (status, Json(response))
+    }
+}
+
+// Ties a failure to the request's ClientTransactionID, e.g. `manager.thing().in_transaction(id)?`
+trait InTransaction<V> {
+    fn in_transaction(self, client_transaction_id: u32) -> Result<V, AlpacaError>;
+}
+
+impl<V> InTransaction<V> for crate::errors::Result<V> {
+    fn in_transaction(self, client_transaction_id: u32) -> Result<V, AlpacaError> {
+        self.map_err(|error| AlpacaError {
+            error,
+            client_transaction_id,
+        })
+    }
+}
+
+// The device a device API path names, resolved through the registry by type and number
+fn alpaca_device<'a>(state: &'a AppState, path: &DevicePath) -> crate::errors::Result<&'a DeviceHandle> {
+    let device_type = AlpacaDeviceType::parse(&path.device_type)
+        .ok_or_else(|| BridgeError::UnsupportedDeviceType(path.device_type.clone()))?;
+    state
+        .devices
+        .alpaca_device(device_type, path.device_number)
+        .ok_or(BridgeError::UnknownDevice(path.device_number))
+}
+
 // Web interface handlers
-async fn web_interface() -> Html<String> {
-    let html = INDEX_HTML
-        .replace("{{STYLE_CSS}}", STYLE_CSS)
-        .replace("{{SCRIPT_JS}}", SCRIPT_JS)
-        .replace("{{VERSION}}", env!("CARGO_PKG_VERSION"))
-        .replace("{{BUILD}}", env!("BUILD_TIMESTAMP"));
-    
-    Html(html)
+#[cfg(feature = "web-ui")]
+async fn render_index(state: &AppState, headers: &HeaderMap) -> Response<Body> {
+    match state.assets.render_index(state.devices.summaries().await).await {
+        Ok(html) => etag_response(headers, "text/html; charset=utf-8", html.into_bytes()),
+        Err(e) => {
+            error!("Cannot render the web interface: {:#}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Template error: {:#}", e)).into_response()
+        }
+    }
+}
+
+#[cfg(feature = "web-ui")]
+async fn web_interface(State(state): State<AppState>, headers: HeaderMap) -> Response<Body> {
+    render_index(&state, &headers).await
 }
 
-async fn web_interface_device_control(Path(device_number): Path<u32>) -> Html<String> {
-    if device_number != 0 {
-        return Html("<h1>Error: Invalid device number. Only device 0 is supported.</h1>".to_string());
+#[cfg(feature = "web-ui")]
+async fn web_login(State(state): State<AppState>) -> Response<Body> {
+    match state.assets.render_login().await {
+        Ok(html) => ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response(),
+        Err(e) => {
+            error!("Cannot render the sign-in page: {:#}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Template error: {:#}", e)).into_response()
+        }
     }
-    
-    let html = INDEX_HTML
-        .replace("{{STYLE_CSS}}", STYLE_CSS)
-        .replace("{{SCRIPT_JS}}", SCRIPT_JS)
-        .replace("{{VERSION}}", env!("CARGO_PKG_VERSION"))
-        .replace("{{BUILD}}", env!("BUILD_TIMESTAMP"));
-    
-    Html(html)
+}
+
+// Alpaca server setup page: the served devices, each linking to its own setup page
+#[cfg(feature = "web-ui")]
+async fn web_setup(State(state): State<AppState>) -> Response<Body> {
+    render_setup(&state, SetupPage::default(), StatusCode::OK).await
+}
+
+#[cfg(feature = "web-ui")]
+async fn render_setup(state: &AppState, mut page: SetupPage, status: StatusCode) -> Response<Body> {
+    page.version = env!("CARGO_PKG_VERSION");
+    page.devices = state.devices.summaries().await;
+    page.config_path = state.devices.config_store().path().map(|path| path.display().to_string());
+    match state.assets.render_setup(page).await {
+        Ok(html) => (status, [(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response(),
+        Err(e) => {
+            error!("Cannot render the setup page: {:#}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Template error: {:#}", e)).into_response()
+        }
+    }
+}
+
+#[cfg(feature = "web-ui")]
+async fn setup_not_found(state: &AppState, device_number: u32) -> Response<Body> {
+    let page = SetupPage {
+        errors: vec![format!("There is no SafetyMonitor #{}", device_number)],
+        ..SetupPage::default()
+    };
+    render_setup(state, page, StatusCode::NOT_FOUND).await
+}
+
+// The form's values as the device runs now; port and baud come from the config file while disconnected
+#[cfg(feature = "web-ui")]
+async fn device_setup(state: &AppState, device: &DeviceHandle) -> DeviceSetup {
+    let manager = &device.connection_manager;
+    let configured = state.devices.config_store().load().await;
+    let configured = configured
+        .as_ref()
+        .and_then(|config| config.devices.iter().find(|configured| configured.device_number == device.device_number));
+    let (port, baud) = match manager.get_current_connection().await {
+        Some(connection) => (Some(connection.port), connection.baud_rate),
+        None => configured.map_or((None, 115200), |configured| (configured.port.clone(), configured.baud)),
+    };
+    let safety = manager.safety();
+    let state = device.device_state.read().await;
+    // Remote and voting devices are connected with a baud rate of 0
+    let serial = baud > 0 && configured.is_none_or(|configured| configured.remote.is_none() && configured.voting.is_none());
+    DeviceSetup {
+        device_number: device.device_number,
+        name: manager.identity().device_name(&state.device_name),
+        serial,
+        ports: crate::port_discovery::discover_ports()
+            .map(|ports| ports.into_iter().map(|port| port.name).collect())
+            .unwrap_or_default(),
+        port: port.unwrap_or_default(),
+        baud: baud.to_string(),
+        tolerance: if state.connected { format!("{:.2}", state.position_tolerance) } else { String::new() },
+        tolerance_settable: serial && state.connected && state.capabilities.set_tolerance,
+        max_data_age_secs: safety.max_data_age_secs.to_string(),
+        refresh_after_secs: safety.refresh_after_secs.to_string(),
+        refresh_wait_ms: safety.refresh_wait_ms.to_string(),
+        on_stale: safety.on_stale.as_str().to_string(),
+    }
+}
+
+#[cfg(feature = "web-ui")]
+async fn web_device_setup(Path(device_number): Path<u32>, State(state): State<AppState>) -> Response<Body> {
+    let Some(device) = state.devices.get(device_number) else {
+        return setup_not_found(&state, device_number).await;
+    };
+    let page = SetupPage {
+        device: Some(device_setup(&state, device).await),
+        ..SetupPage::default()
+    };
+    render_setup(&state, page, StatusCode::OK).await
+}
+
+// Fields of the device setup form; numbers arrive as typed so they can be reported back
+#[cfg(feature = "web-ui")]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct SetupForm {
+    port: String,
+    baud: String,
+    tolerance: String,
+    max_data_age_secs: String,
+    refresh_after_secs: String,
+    refresh_wait_ms: String,
+    on_stale: String,
+}
+
+#[cfg(feature = "web-ui")]
+impl SetupForm {
+    // The settings and the tolerance to send to the firmware, or every problem with the form
+    fn parse(&self, serial: bool) -> std::result::Result<(DeviceSettings, Option<f32>), Vec<String>> {
+        let mut errors = Vec::new();
+        let on_stale = match self.on_stale.as_str() {
+            "error" => StaleReply::Error,
+            "false" | "" => StaleReply::False,
+            other => {
+                errors.push(format!("on_stale: '{}' is not false or error", other));
+                StaleReply::False
+            }
+        };
+        let mut number = |name: &str, text: &str| -> u64 {
+            text.trim().parse().unwrap_or_else(|_| {
+                errors.push(format!("{}: '{}' is not a whole number", name, text));
+                0
+            })
+        };
+        let safety = SafetyConfig {
+            max_data_age_secs: number("max_data_age_secs", &self.max_data_age_secs),
+            refresh_after_secs: number("refresh_after_secs", &self.refresh_after_secs),
+            refresh_wait_ms: number("refresh_wait_ms", &self.refresh_wait_ms),
+            on_stale,
+        };
+        let baud = if serial { number("baud", &self.baud) as u32 } else { 115200 };
+        if errors.is_empty() {
+            let mut issues = ConfigIssues::default();
+            safety.check("safety", &mut issues);
+            errors.extend(issues.iter().map(|issue| format!("{}: {}", issue.field, issue.message)));
+            if serial {
+                if let Err(message) = config::check_baud(baud) {
+                    errors.push(format!("baud: {}", message));
+                }
+            }
+        }
+        let port = Some(self.port.trim()).filter(|port| serial && !port.is_empty());
+        if let Some(Err(message)) = port.map(config::check_port_syntax) {
+            errors.push(format!("port: {}", message));
+        }
+        let tolerance = match self.tolerance.trim() {
+            "" => None,
+            text => {
+                let degrees = text.parse::<f32>().map_err(|_| format!("tolerance: '{}' is not a number", text));
+                match degrees.and_then(|degrees| protocol::set_tolerance_command(degrees).map(|_| degrees)) {
+                    Ok(degrees) => Some(degrees),
+                    Err(message) => {
+                        errors.push(message);
+                        None
+                    }
+                }
+            }
+        };
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok((DeviceSettings { port: port.map(str::to_string), baud, safety }, tolerance))
+    }
+}
+
+// Save the device setup form to the config file, then apply it to the running device
+#[cfg(feature = "web-ui")]
+async fn save_device_setup(
+    Path(device_number): Path<u32>,
+    State(state): State<AppState>,
+    Form(form): Form<SetupForm>,
+) -> Response<Body> {
+    let Some(device) = state.devices.get(device_number) else {
+        return setup_not_found(&state, device_number).await;
+    };
+    let current = device_setup(&state, device).await;
+    let (settings, tolerance) = match form.parse(current.serial) {
+        Ok(parsed) => parsed,
+        Err(errors) => return reject_setup(&state, current, form, errors).await,
+    };
+    let store = state.devices.config_store();
+    let mut saved = Vec::new();
+    if let Some(path) = store.path() {
+        if let Err(e) = store.save_device_settings(device_number, &settings).await {
+            return reject_setup(&state, current, form, vec![format!("Not saved: {}", e)]).await;
+        }
+        saved.push(format!("Saved to {}", path.display()));
+    }
+
+    let manager = &device.connection_manager;
+    let mut errors = Vec::new();
+    manager.set_safety(settings.safety);
+    let connection = manager.get_current_connection().await;
+    let reconnect = connection.is_none_or(|connection| Some(&connection.port) != settings.port.as_ref() || connection.baud_rate != settings.baud);
+    if let (Some(port), true) = (&settings.port, current.serial && reconnect) {
+        match manager.connect(port.clone(), settings.baud).await {
+            Ok(message) => saved.push(message),
+            Err(e) => errors.push(format!("Failed to connect: {}", e)),
+        }
+    }
+    let position_tolerance = device.device_state.read().await.position_tolerance;
+    if let Some(degrees) = tolerance.filter(|degrees| (degrees - position_tolerance).abs() >= 0.005) {
+        match manager.set_tolerance(degrees).await {
+            Ok(_) => saved.push(format!("Tolerance set to {:.2}°", degrees)),
+            Err(e) => errors.push(format!("Tolerance not set: {}", e)),
+        }
+    }
+
+    let page = SetupPage {
+        device: Some(device_setup(&state, device).await),
+        saved,
+        errors,
+        ..SetupPage::default()
+    };
+    render_setup(&state, page, StatusCode::OK).await
+}
+
+// The form again with the user's input and what was wrong with it
+#[cfg(feature = "web-ui")]
+async fn reject_setup(state: &AppState, current: DeviceSetup, form: SetupForm, errors: Vec<String>) -> Response<Body> {
+    let device = DeviceSetup {
+        port: form.port,
+        baud: if current.serial { form.baud } else { current.baud },
+        tolerance: form.tolerance,
+        max_data_age_secs: form.max_data_age_secs,
+        refresh_after_secs: form.refresh_after_secs,
+        refresh_wait_ms: form.refresh_wait_ms,
+        on_stale: form.on_stale,
+        ..current
+    };
+    let page = SetupPage {
+        device: Some(device),
+        errors,
+        ..SetupPage::default()
+    };
+    render_setup(state, page, StatusCode::BAD_REQUEST).await
+}
+
+// CSS/JS/icons under content-hashed names, cached by browsers until the next build changes them
+#[cfg(feature = "web-ui")]
+async fn serve_asset(Path(name): Path<String>, State(state): State<AppState>) -> Response<Body> {
+    match state.assets.serve(&name).await {
+        Some(response) => response,
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// Revision of the on-disk web UI, polled by the live-reload script; only with --dev-assets
+#[cfg(feature = "web-ui")]
+async fn dev_reload(State(state): State<AppState>) -> Response<Body> {
+    if !state.assets.is_dev() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    Json(serde_json::json!({ "revision": state.assets.revision().await })).into_response()
 }
 
 // API handlers for web interface - UNSTUBBED to use ConnectionManager
-async fn api_status(State(state): State<AppState>) -> Json<DeviceState> {
-    let device_state = state.device_state.read().await;
-    Json(device_state.clone())
+// ETag over the serialized state, so dashboards polling an unchanged sensor get 304s. The tag
+// covers every field, last_update and the firmware uptime included, so it changes with each poll
+// that reads the sensor and holds while polls pause (disconnected, or between polls)
+async fn api_status(State(state): State<AppState>, headers: HeaderMap) -> Response<Body> {
+    let status = primary_status(&state).await;
+    let body = serde_json::to_vec(&StatusResponse { status, server: server_info::server_info() }).unwrap_or_default();
+    etag_response(&headers, "application/json", body)
+}
+
+async fn primary_status(state: &AppState) -> DeviceState {
+    let device_state = state.device_state().read().await;
+    let mut status = device_state.snapshot(state.connection_manager().max_data_age_secs());
+    status.device_name = state.connection_manager().identity().device_name(&status.device_name);
+    status
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusDeltaQuery {
+    since: Option<u64>,
+}
+
+// The fields of /api/status (without the server block) changed since ?since=, or since the
+// sequence sent back as If-None-Match, which gets 304 while nothing changed
+async fn api_status_delta(
+    State(state): State<AppState>,
+    Query(query): Query<StatusDeltaQuery>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    let status = match serde_json::to_value(primary_status(&state).await) {
+        Ok(serde_json::Value::Object(fields)) => fields,
+        _ => serde_json::Map::new(),
+    };
+    let since = query.since.or_else(|| {
+        http_cache::request_etags(&headers).find_map(|tag| tag.trim_matches('"').parse().ok())
+    });
+    let delta = state.status_history.delta(since, status);
+    let etag = format!("\"{}\"", delta.sequence);
+    let builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, http_cache::REVALIDATE_CACHE_CONTROL);
+    if http_cache::if_none_match(&headers, &etag) {
+        return builder.status(StatusCode::NOT_MODIFIED).body(Body::empty()).unwrap();
+    }
+    builder
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(serde_json::to_vec(&delta).unwrap_or_default()))
+        .unwrap()
+}
+
+// The primary device's state, with the running build and its uptime alongside
+#[derive(Serialize)]
+struct StatusResponse {
+    #[serde(flatten)]
+    status: DeviceState,
+    server: ServerInfo,
+}
+
+// Version, commit, build time, uptime and compiled-in features of the running bridge
+async fn api_about() -> Json<About> {
+    Json(server_info::about())
+}
+
+// One-line status for OBS overlays, MagicMirror modules and LCD tickers
+async fn status_text(
+    State(state): State<AppState>,
+    Query(query): Query<StatusLineQuery>,
+) -> Result<Response<Body>, (StatusCode, Json<ConnectResponse>)> {
+    let summary = status_summary(&state, query.device_number).await?;
+    let headers = [(header::CONTENT_TYPE, "text/plain; charset=utf-8"), (header::CACHE_CONTROL, "no-cache")];
+    Ok((headers, format!("{}\n", summary.text)).into_response())
+}
+
+async fn status_json(
+    State(state): State<AppState>,
+    Query(query): Query<StatusLineQuery>,
+) -> Result<Json<StatusSummary>, (StatusCode, Json<ConnectResponse>)> {
+    Ok(Json(status_summary(&state, query.device_number).await?))
+}
+
+async fn status_summary(state: &AppState, device_number: u32) -> Result<StatusSummary, (StatusCode, Json<ConnectResponse>)> {
+    let device = state
+        .devices
+        .get(device_number)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No device {}", device_number)))?;
+    let device_state = device.device_state.read().await;
+    Ok(device_state.status_summary(device.connection_manager.max_data_age_secs()))
 }
 
 async fn api_ports() -> Json<PortListResponse> {
@@ -304,7 +1079,7 @@ async fn api_connect(
 ) -> Json<ConnectResponse> {
     let baud_rate = request.baud_rate.unwrap_or(115200);
     
-    match state.connection_manager.connect(request.port.clone(), baud_rate).await {
+    match state.connection_manager().connect(request.port.clone(), baud_rate).await {
         Ok(message) => {
             info!("Connection successful: {}", message);
             Json(ConnectResponse {
@@ -324,7 +1099,7 @@ async fn api_connect(
 }
 
 async fn api_disconnect(State(state): State<AppState>) -> Json<ConnectResponse> {
-    match state.connection_manager.disconnect().await {
+    match state.connection_manager().disconnect().await {
         Ok(message) => {
             info!("Disconnection successful: {}", message);
             Json(ConnectResponse {
@@ -347,7 +1122,17 @@ async fn api_send_command(
     State(state): State<AppState>,
     Json(request): Json<CommandRequest>,
 ) -> Json<CommandResponse> {
-    match state.connection_manager.send_command(&request.command).await {
+    let connection_manager = state.connection_manager();
+    if let Err(e) = connection_manager.check_api_command(&request.command) {
+        warn!("Rejected command '{}': {}", request.command, e);
+        return Json(CommandResponse {
+            success: false,
+            command: request.command,
+            response: None,
+            message: e.to_string(),
+        });
+    }
+    match connection_manager.send_command(&request.command).await {
         Ok(response) => {
             info!("Command '{}' executed successfully", request.command);
             Json(CommandResponse {
@@ -371,7 +1156,7 @@ async fn api_send_command(
 }
 
 async fn api_calibrate(State(state): State<AppState>) -> Json<CommandResponse> {
-    match state.connection_manager.calibrate_sensor().await {
+    match state.connection_manager().calibrate_sensor().await {
         Ok(response) => {
             info!("Sensor calibration completed successfully");
             Json(CommandResponse {
@@ -395,7 +1180,7 @@ async fn api_calibrate(State(state): State<AppState>) -> Json<CommandResponse> {
 }
 
 async fn api_set_park(State(state): State<AppState>) -> Json<CommandResponse> {
-    match state.connection_manager.set_park_position().await {
+    match state.connection_manager().set_park_position().await {
         Ok(response) => {
             info!("Park position set successfully");
             Json(CommandResponse {
@@ -419,7 +1204,7 @@ async fn api_set_park(State(state): State<AppState>) -> Json<CommandResponse> {
 }
 
 async fn api_factory_reset(State(state): State<AppState>) -> Json<CommandResponse> {
-    match state.connection_manager.factory_reset().await {
+    match state.connection_manager().factory_reset().await {
         Ok(response) => {
             info!("Factory reset completed successfully");
             Json(CommandResponse {
@@ -442,6 +1227,765 @@ async fn api_factory_reset(State(state): State<AppState>) -> Json<CommandRespons
     }
 }
 
+// Fault injection handlers - refuse to act unless enabled at startup
+fn fault_injection_disabled() -> (StatusCode, Json<ConnectResponse>) {
+    (
+        StatusCode::FORBIDDEN,
+        Json(ConnectResponse {
+            success: false,
+            message: "Fault injection is disabled - start the bridge with --fault-injection".to_string(),
+        }),
+    )
+}
+
+fn safety_force_error(status: StatusCode, message: impl Into<String>) -> (StatusCode, Json<ConnectResponse>) {
+    (
+        status,
+        Json(ConnectResponse {
+            success: false,
+            message: message.into(),
+        }),
+    )
+}
+
+// The device's manager, once the request carries the configured bearer token
+fn authorize_safety_force<'a>(
+    state: &'a AppState,
+    headers: &HeaderMap,
+    device_number: u32,
+) -> Result<&'a Arc<ConnectionManager>, (StatusCode, Json<ConnectResponse>)> {
+    let Some(device) = state.devices.get(device_number) else {
+        return Err(safety_force_error(
+            StatusCode::NOT_FOUND,
+            format!("No device {}", device_number),
+        ));
+    };
+    let config = device.connection_manager.safety_force();
+    if !config.is_enabled() {
+        return Err(safety_force_error(
+            StatusCode::FORBIDDEN,
+            "Forcing IsSafe is disabled - set a token in the [safety_force] configuration section",
+        ));
+    }
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !config.authorizes(presented.trim()) {
+        warn!("Rejected /api/safety/force request without a valid token");
+        return Err(safety_force_error(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token"));
+    }
+    Ok(&device.connection_manager)
+}
+
+async fn api_force_safety(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<SafetyForceRequest>,
+) -> Result<Json<SafetyOverride>, (StatusCode, Json<ConnectResponse>)> {
+    let manager = authorize_safety_force(&state, &headers, request.device_number)?;
+    let max_minutes = manager.safety_force().max_minutes;
+    if !request.minutes.is_finite() || request.minutes <= 0.0 || request.minutes > max_minutes as f64 {
+        return Err(safety_force_error(
+            StatusCode::BAD_REQUEST,
+            format!("minutes must be above 0 and at most {}", max_minutes),
+        ));
+    }
+    let is_safe = matches!(request.state, ForcedSafety::Safe);
+    let duration = Duration::from_secs_f64(request.minutes * 60.0);
+    let reason = request.reason.filter(|reason| !reason.trim().is_empty());
+    Ok(Json(manager.force_safety(request.device_number, is_safe, duration, reason).await))
+}
+
+async fn api_clear_forced_safety(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SafetyForceQuery>,
+) -> Result<Json<ConnectResponse>, (StatusCode, Json<ConnectResponse>)> {
+    let manager = authorize_safety_force(&state, &headers, query.device_number)?;
+    let cleared = manager.clear_forced_safety(query.device_number).await;
+    Ok(Json(ConnectResponse {
+        success: true,
+        message: if cleared {
+            "Forced IsSafe cleared".to_string()
+        } else {
+            "IsSafe was not forced".to_string()
+        },
+    }))
+}
+
+async fn api_start_maintenance(
+    State(state): State<AppState>,
+    Json(request): Json<MaintenanceRequest>,
+) -> Result<Json<Maintenance>, (StatusCode, Json<ConnectResponse>)> {
+    let Some(device) = state.devices.get(request.device_number) else {
+        return Err(job_error(StatusCode::NOT_FOUND, format!("No device {}", request.device_number)));
+    };
+    let manager = &device.connection_manager;
+    let config = manager.maintenance();
+    let minutes = request.minutes.unwrap_or(config.default_minutes as f64);
+    if !minutes.is_finite() || minutes <= 0.0 || minutes > config.max_minutes as f64 {
+        return Err(job_error(
+            StatusCode::BAD_REQUEST,
+            format!("minutes must be above 0 and at most {}", config.max_minutes),
+        ));
+    }
+    let duration = Duration::from_secs_f64(minutes * 60.0);
+    let reason = request.reason.filter(|reason| !reason.trim().is_empty());
+    Ok(Json(manager.start_maintenance(request.device_number, duration, reason).await))
+}
+
+async fn api_end_maintenance(
+    State(state): State<AppState>,
+    Query(query): Query<MaintenanceQuery>,
+) -> Result<Json<ConnectResponse>, (StatusCode, Json<ConnectResponse>)> {
+    let Some(device) = state.devices.get(query.device_number) else {
+        return Err(job_error(StatusCode::NOT_FOUND, format!("No device {}", query.device_number)));
+    };
+    let ended = device.connection_manager.end_maintenance(query.device_number).await;
+    Ok(Json(ConnectResponse {
+        success: true,
+        message: if ended {
+            "Maintenance mode ended".to_string()
+        } else {
+            "Maintenance mode was not on".to_string()
+        },
+    }))
+}
+
+async fn api_notifications(State(state): State<AppState>) -> Json<Vec<ChannelStatus>> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    Json(state.devices.notifications().status(now))
+}
+
+async fn api_snooze_notifications(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+    Json(request): Json<SnoozeRequest>,
+) -> Result<Json<ChannelStatus>, (StatusCode, Json<ConnectResponse>)> {
+    let notifications = state.devices.notifications();
+    let max_minutes = notifications.config().max_snooze_minutes;
+    if !request.minutes.is_finite() || request.minutes <= 0.0 || request.minutes > max_minutes as f64 {
+        return Err(job_error(
+            StatusCode::BAD_REQUEST,
+            format!("minutes must be above 0 and at most {}", max_minutes),
+        ));
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let until = now + (request.minutes * 60.0).round() as u64;
+    if !notifications.snooze(&channel, until) {
+        return Err(unknown_channel(&state, &channel, now));
+    }
+    info!("Notifications on {} snoozed until {}", channel, crate::timestamps::format_secs(until));
+    let status = notifications.status(now).into_iter().find(|status| status.channel == channel);
+    status.map(Json).ok_or_else(|| unknown_channel(&state, &channel, now))
+}
+
+async fn api_unsnooze_notifications(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+) -> Result<Json<ConnectResponse>, (StatusCode, Json<ConnectResponse>)> {
+    if !state.devices.notifications().unsnooze(&channel) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        return Err(unknown_channel(&state, &channel, now));
+    }
+    Ok(Json(ConnectResponse {
+        success: true,
+        message: format!("Notifications on {} resumed", channel),
+    }))
+}
+
+fn unknown_channel(state: &AppState, channel: &str, now: u64) -> (StatusCode, Json<ConnectResponse>) {
+    let channels: Vec<String> = state.devices.notifications().status(now).into_iter().map(|status| status.channel).collect();
+    let known = if channels.is_empty() { "none configured".to_string() } else { channels.join(", ") };
+    job_error(StatusCode::NOT_FOUND, format!("No notification channel {} (channels: {})", channel, known))
+}
+
+async fn api_events(
+    State(state): State<AppState>,
+    Query(query): Query<PageQuery>,
+    Query(filter): Query<EventFilter>,
+) -> Result<(HeaderMap, Json<Vec<BridgeEvent>>), (StatusCode, Json<ConnectResponse>)> {
+    let categories = filter.categories().map_err(|message| job_error(StatusCode::BAD_REQUEST, message))?;
+    let mut events = state.connection_manager().event_bus().recent(None);
+    events.retain(|event| event.in_categories(&categories));
+    Ok(paged(query.apply(events)))
+}
+
+// Sensor and mount events of every device in one timeline; same filters as /api/events
+async fn api_session_log(
+    State(state): State<AppState>,
+    Query(query): Query<PageQuery>,
+    Query(filter): Query<EventFilter>,
+) -> Result<(HeaderMap, Json<Vec<SessionEntry>>), (StatusCode, Json<ConnectResponse>)> {
+    let categories = filter.categories().map_err(|message| job_error(StatusCode::BAD_REQUEST, message))?;
+    let mut entries = state.devices.session_log().entries();
+    entries.retain(|entry| entry.in_categories(&categories));
+    Ok(paged(query.apply(entries)))
+}
+
+#[derive(Debug, Deserialize)]
+struct NightQuery {
+    // Date the night began, or "current" for the night still running; the newest finished night
+    // when absent
+    night: Option<String>,
+}
+
+async fn api_nightly_report(
+    State(state): State<AppState>,
+    Query(query): Query<NightQuery>,
+) -> Result<Json<NightlyReport>, (StatusCode, Json<ConnectResponse>)> {
+    let reports = state.devices.nightly_reports();
+    let report = match query.night.as_deref() {
+        Some("current") => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            Some(reports.current(&state.devices, now))
+        }
+        Some(night) => reports.night(night),
+        None => reports.latest(),
+    };
+    report.map(Json).ok_or_else(|| {
+        let kept = reports.nights();
+        let message = if kept.is_empty() {
+            "No night has finished yet".to_string()
+        } else {
+            format!("No report for the night of {} (kept: {})", query.night.unwrap_or_default(), kept.join(", "))
+        };
+        job_error(StatusCode::NOT_FOUND, message)
+    })
+}
+
+// GET /api/events/stream: events as server-sent events while they are published. Buffered events
+// newer than Last-Event-ID (sent by a reconnecting EventSource) or ?since= are replayed first.
+async fn api_event_stream(
+    State(state): State<AppState>,
+    Query(query): Query<PageQuery>,
+    Query(filter): Query<EventFilter>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, (StatusCode, Json<ConnectResponse>)> {
+    let categories = filter.categories().map_err(|message| job_error(StatusCode::BAD_REQUEST, message))?;
+    let event_bus = state.connection_manager().event_bus();
+    // Subscribed before reading the buffer, so nothing published in between is lost
+    let receiver = event_bus.subscribe();
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .or(query.since);
+    let backlog: VecDeque<BridgeEvent> = match last_event_id {
+        Some(since) => event_bus.recent(Some(since)).into(),
+        None => VecDeque::new(),
+    };
+    let feed = EventFeed {
+        backlog,
+        receiver,
+        last_id: last_event_id.unwrap_or(0),
+        categories,
+    };
+    let stream = futures_util::stream::unfold(feed, |mut feed| async move {
+        let event = feed.next().await?;
+        let data = serde_json::to_string(&event).unwrap_or_default();
+        Some((Ok(SseEvent::default().id(event.id.to_string()).data(data)), feed))
+    });
+    Ok(Sse::new(stream.take_until(state.shutdown.cancelled_owned())).keep_alive(KeepAlive::default()))
+}
+
+// One subscriber's position in the event stream
+struct EventFeed {
+    backlog: VecDeque<BridgeEvent>,
+    receiver: broadcast::Receiver<BridgeEvent>,
+    last_id: u64,
+    categories: Vec<EventCategory>,
+}
+
+impl EventFeed {
+    async fn next(&mut self) -> Option<BridgeEvent> {
+        loop {
+            let event = match self.backlog.pop_front() {
+                Some(event) => event,
+                None => match self.receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Event stream subscriber fell behind, {} events skipped", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                },
+            };
+            // Events replayed from the buffer come through the receiver again
+            if event.id <= self.last_id {
+                continue;
+            }
+            self.last_id = event.id;
+            if event.in_categories(&self.categories) {
+                return Some(event);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct EventFeedAccess {
+    config: EventsConfig,
+    users: WebUsers,
+}
+
+// The event feed needs the [events] bearer token or a signed-in web user once either is
+// configured. Web users are checked here rather than by web_users::protect so that API clients
+// holding only the token get through.
+pub fn protect_event_feed(router: Router, config: EventsConfig, users: WebUsers) -> Router {
+    router.layer(middleware::from_fn_with_state(
+        EventFeedAccess { config, users },
+        require_event_feed_access,
+    ))
+}
+
+async fn require_event_feed_access(
+    State(access): State<EventFeedAccess>,
+    request: axum::http::Request<Body>,
+    next: middleware::Next,
+) -> Response<Body> {
+    let protected = access.config.token.is_some() || access.users.is_enabled();
+    if !protected || !web_users::is_event_feed(request.uri().path()) {
+        return next.run(request).await;
+    }
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if bearer.is_some_and(|token| access.config.authorizes(token.trim()))
+        || access.users.authenticate(request.headers()).await.is_some()
+    {
+        return next.run(request).await;
+    }
+    job_error(
+        StatusCode::UNAUTHORIZED,
+        "The event feed needs the [events] bearer token or a signed-in web user".to_string(),
+    )
+    .into_response()
+}
+
+async fn api_discovery_clients(State(state): State<AppState>) -> Json<Vec<DiscoveryClient>> {
+    Json(state.discovery.clients())
+}
+
+async fn api_ascom_clients(State(state): State<AppState>) -> Json<Vec<AscomClient>> {
+    Json(state.ascom_clients.clients())
+}
+
+async fn api_health(State(state): State<AppState>) -> Json<HealthStatus> {
+    Json(state.connection_manager().health_monitor().status())
+}
+
+// Firmware replies that broke their schema while strict_schema is on
+async fn api_firmware_schema(
+    State(state): State<AppState>,
+    Query(query): Query<SchemaQuery>,
+) -> Result<Json<SchemaReport>, (StatusCode, Json<ConnectResponse>)> {
+    let device = state
+        .devices
+        .get(query.device_number)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No device {}", query.device_number)))?;
+    Ok(Json(device.connection_manager.schema_checker().report()))
+}
+
+// Pitch/roll noise while parked, against the device's position tolerance
+async fn api_noise(
+    State(state): State<AppState>,
+    Query(query): Query<NoiseQuery>,
+) -> Result<Json<NoiseStats>, (StatusCode, Json<ConnectResponse>)> {
+    let device = state
+        .devices
+        .get(query.device_number)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No device {}", query.device_number)))?;
+    let tolerance = device.device_state.read().await.position_tolerance;
+    Ok(Json(device.connection_manager.health_monitor().noise(tolerance)))
+}
+
+async fn api_compensation(
+    State(state): State<AppState>,
+    Query(query): Query<CompensationQuery>,
+) -> Result<Json<CompensationStatus>, (StatusCode, Json<ConnectResponse>)> {
+    let device = state
+        .devices
+        .get(query.device_number)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No device {}", query.device_number)))?;
+    Ok(Json(device.connection_manager.temperature_compensation().status()))
+}
+
+// Entry of /api/telescopes: link state and latest status of a mount
+#[cfg(feature = "telescope-client")]
+#[derive(Serialize)]
+struct TelescopeView {
+    #[serde(flatten)]
+    snapshot: TelescopeSnapshot,
+    interlock: ParkInterlock,
+}
+
+// Whether the mount's park sensor agrees with the mount's own AtPark; null where either is unknown
+#[cfg(feature = "telescope-client")]
+#[derive(Serialize)]
+struct ParkInterlock {
+    sensor_device: u32,
+    sensor_parked: Option<bool>,
+    mount_at_park: Option<bool>,
+    agree: Option<bool>,
+}
+
+#[cfg(feature = "telescope-client")]
+async fn telescope_view(state: &AppState, telescope: &TelescopeManager) -> TelescopeView {
+    let snapshot = telescope.snapshot().await;
+    let sensor_parked = match state.devices.get(telescope.sensor_device()) {
+        Some(device) => {
+            let max_data_age = device.connection_manager.max_data_age_secs();
+            let device_state = device.device_state.read().await;
+            (device_state.connected && !device_state.is_stale(max_data_age)).then_some(device_state.is_parked)
+        }
+        None => None,
+    };
+    let mount_at_park = snapshot.status.as_ref().map(|status| status.at_park);
+    TelescopeView {
+        interlock: ParkInterlock {
+            sensor_device: telescope.sensor_device(),
+            sensor_parked,
+            mount_at_park,
+            agree: sensor_parked.zip(mount_at_park).map(|(sensor, mount)| sensor == mount),
+        },
+        snapshot,
+    }
+}
+
+#[cfg(feature = "telescope-client")]
+async fn api_telescopes(State(state): State<AppState>) -> Json<Vec<TelescopeView>> {
+    let mut views = Vec::new();
+    for telescope in state.devices.telescopes().telescopes() {
+        views.push(telescope_view(&state, telescope).await);
+    }
+    Json(views)
+}
+
+#[cfg(feature = "telescope-client")]
+async fn api_telescope(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TelescopeView>, (StatusCode, Json<ConnectResponse>)> {
+    let telescope = state
+        .devices
+        .telescopes()
+        .get(&id)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No telescope {}", id)))?;
+    Ok(Json(telescope_view(&state, telescope).await))
+}
+
+// POST /api/telescopes/{id}/park, unpark, setpark, findhome or abort
+#[cfg(feature = "telescope-client")]
+async fn api_telescope_action(
+    State(state): State<AppState>,
+    Path((id, action)): Path<(String, String)>,
+) -> Result<Json<ConnectResponse>, (StatusCode, Json<ConnectResponse>)> {
+    let telescope = state
+        .devices
+        .telescopes()
+        .get(&id)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No telescope {}", id)))?;
+    let client = telescope
+        .client()
+        .await
+        .ok_or_else(|| job_error(StatusCode::SERVICE_UNAVAILABLE, format!("Telescope {} is not connected", id)))?;
+    let result = match action.as_str() {
+        "park" => client.park().await,
+        "unpark" => client.unpark().await,
+        "setpark" => client.set_park().await,
+        "findhome" => client.find_home().await,
+        "abort" => client.stop_all_movement().await,
+        _ => return Err(job_error(StatusCode::NOT_FOUND, format!("Unknown telescope action '{}'", action))),
+    };
+    match result {
+        Ok(()) => Ok(Json(ConnectResponse {
+            success: true,
+            message: format!("Telescope {}: {} sent", id, action),
+        })),
+        Err(e) => Err(telescope_error(&id, &action, e)),
+    }
+}
+
+#[cfg(feature = "telescope-client")]
+fn telescope_error(id: &str, action: &str, error: TelescopeError) -> (StatusCode, Json<ConnectResponse>) {
+    match error {
+        TelescopeError::NotConnected => {
+            job_error(StatusCode::SERVICE_UNAVAILABLE, format!("Telescope {} is not connected", id))
+        }
+        TelescopeError::Unsupported(what) => {
+            job_error(StatusCode::CONFLICT, format!("Telescope {} does not support {}", id, what))
+        }
+        e => job_error(StatusCode::BAD_GATEWAY, format!("Telescope {}: {} failed: {}", id, action, e)),
+    }
+}
+
+// MoveAxis rates of a mount: primary is RA/azimuth (east/west), secondary Dec/altitude
+#[cfg(feature = "telescope-client")]
+#[derive(Debug, Serialize)]
+struct TelescopeAxisRates {
+    primary: AxisRates,
+    secondary: AxisRates,
+}
+
+#[cfg(feature = "telescope-client")]
+async fn api_telescope_axis_rates(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TelescopeAxisRates>, (StatusCode, Json<ConnectResponse>)> {
+    let client = connected_telescope(&state, &id).await?;
+    let (primary, secondary) = tokio::try_join!(
+        client.get_axis_rates(TelescopeAxis::Primary),
+        client.get_axis_rates(TelescopeAxis::Secondary),
+    )
+    .map_err(|e| telescope_error(&id, "axis_rates", e))?;
+    Ok(Json(TelescopeAxisRates { primary, secondary }))
+}
+
+// The connected client of a mount, or the 404/503 to answer with
+#[cfg(feature = "telescope-client")]
+async fn connected_telescope(
+    state: &AppState,
+    id: &str,
+) -> Result<TelescopeClient, (StatusCode, Json<ConnectResponse>)> {
+    let telescope = state
+        .devices
+        .telescopes()
+        .get(id)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No telescope {}", id)))?;
+    telescope
+        .client()
+        .await
+        .ok_or_else(|| job_error(StatusCode::SERVICE_UNAVAILABLE, format!("Telescope {} is not connected", id)))
+}
+
+// SupportedActions of a mount, for POST /api/telescopes/{id}/action
+#[cfg(feature = "telescope-client")]
+async fn api_telescope_supported_actions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<String>>, (StatusCode, Json<ConnectResponse>)> {
+    let client = connected_telescope(&state, &id).await?;
+    let actions = client
+        .supported_actions()
+        .await
+        .map_err(|e| telescope_error(&id, "supportedactions", e))?;
+    Ok(Json(actions))
+}
+
+#[cfg(feature = "telescope-client")]
+#[derive(Debug, Deserialize)]
+struct TelescopeActionRequest {
+    action: String,
+    // Passed to the driver as is; the format is up to the driver
+    #[serde(default)]
+    parameters: String,
+}
+
+#[cfg(feature = "telescope-client")]
+#[derive(Debug, Serialize)]
+struct TelescopeActionResponse {
+    action: String,
+    // The driver's reply
+    value: String,
+}
+
+// Forward a driver-specific action (Alpaca Action), e.g. PEC control; only SupportedActions are sent
+#[cfg(feature = "telescope-client")]
+async fn api_telescope_run_action(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<TelescopeActionRequest>,
+) -> Result<Json<TelescopeActionResponse>, (StatusCode, Json<ConnectResponse>)> {
+    let client = connected_telescope(&state, &id).await?;
+    let value = client
+        .action(&request.action, &request.parameters)
+        .await
+        .map_err(|e| telescope_error(&id, &request.action, e))?;
+    Ok(Json(TelescopeActionResponse {
+        action: request.action,
+        value,
+    }))
+}
+
+#[cfg(feature = "telescope-client")]
+#[derive(Debug, Deserialize)]
+struct TelescopeMoveRequest {
+    direction: SlewDirection,
+    // Degrees per second; 0 stops the axis
+    rate: f64,
+}
+
+#[cfg(feature = "telescope-client")]
+#[derive(Debug, Serialize)]
+struct TelescopeMoveResponse {
+    direction: SlewDirection,
+    // Rate sent to the mount, after clamping into its AxisRates
+    rate: f64,
+    // The axis stops after this long unless the move is sent again
+    timeout_secs: u64,
+}
+
+// POST /api/telescopes/{id}/move: MoveAxis with a timeout, for jogging from a hand pad
+#[cfg(feature = "telescope-client")]
+async fn api_telescope_move(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(request): Json<TelescopeMoveRequest>,
+) -> Result<Json<TelescopeMoveResponse>, (StatusCode, Json<ConnectResponse>)> {
+    let telescope = state
+        .devices
+        .telescopes()
+        .get(&id)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No telescope {}", id)))?;
+    if !request.rate.is_finite() || request.rate < 0.0 {
+        return Err(job_error(
+            StatusCode::BAD_REQUEST,
+            format!("rate must be a non-negative number of degrees per second, not {}", request.rate),
+        ));
+    }
+    let rate = telescope
+        .move_axis(request.direction, request.rate)
+        .await
+        .map_err(|e| telescope_error(&id, "move", e))?;
+    Ok(Json(TelescopeMoveResponse {
+        direction: request.direction,
+        rate,
+        timeout_secs: telescope.move_timeout().as_secs(),
+    }))
+}
+
+// Per-step results of the device's startup self-check
+async fn api_self_test(
+    State(state): State<AppState>,
+    Query(query): Query<SelfTestQuery>,
+) -> Result<Json<SelfTestReport>, (StatusCode, Json<ConnectResponse>)> {
+    let device = state
+        .devices
+        .get(query.device_number)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No device {}", query.device_number)))?;
+    Ok(Json(device.connection_manager.self_test().report()))
+}
+
+// HTTP endpoint metrics next to each device's serial health, to tell slow handlers from a slow backend
+async fn api_metrics(State(state): State<AppState>) -> Json<MetricsResponse> {
+    let devices = state
+        .devices
+        .devices()
+        .map(|device| DeviceMetrics {
+            device_number: device.device_number,
+            health: device.connection_manager.health_monitor().status(),
+        })
+        .collect();
+    Json(MetricsResponse {
+        http: state.http_metrics.snapshot(),
+        devices,
+    })
+}
+
+async fn api_command_history(
+    State(state): State<AppState>,
+    Query(query): Query<PageQuery>,
+) -> (HeaderMap, Json<Vec<CommandRecord>>) {
+    paged(query.apply(state.connection_manager().command_history().entries()))
+}
+
+// Page items as a plain JSON array, with the unpaged match count in X-Total-Count
+fn paged<T>(page: Page<T>) -> (HeaderMap, Json<Vec<T>>) {
+    let mut headers = HeaderMap::new();
+    headers.insert(TOTAL_COUNT_HEADER, page.total.into());
+    (headers, Json(page.items))
+}
+
+async fn api_submit_job(
+    State(state): State<AppState>,
+    Json(request): Json<JobRequest>,
+) -> Result<(StatusCode, Json<Job>), (StatusCode, Json<ConnectResponse>)> {
+    let device = match request.device_number {
+        Some(device_number) => state.devices.get(device_number).ok_or_else(|| {
+            job_error(StatusCode::NOT_FOUND, format!("No device {}", device_number))
+        })?,
+        None => state.devices.primary(),
+    };
+    let job = state
+        .jobs
+        .submit(request.operation, device.device_number, device.connection_manager.clone());
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+async fn api_list_jobs(State(state): State<AppState>) -> Json<Vec<Job>> {
+    Json(state.jobs.list())
+}
+
+async fn api_get_job(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<Job>, (StatusCode, Json<ConnectResponse>)> {
+    state
+        .jobs
+        .get(id)
+        .map(Json)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No job {}", id)))
+}
+
+async fn api_cancel_job(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<Job>, (StatusCode, Json<ConnectResponse>)> {
+    state
+        .jobs
+        .cancel(id)
+        .map(Json)
+        .ok_or_else(|| job_error(StatusCode::NOT_FOUND, format!("No job {}", id)))
+}
+
+fn job_error(status: StatusCode, message: String) -> (StatusCode, Json<ConnectResponse>) {
+    (status, Json(ConnectResponse { success: false, message }))
+}
+
+async fn api_devices(State(state): State<AppState>) -> Json<Vec<DeviceSummary>> {
+    Json(state.devices.summaries().await)
+}
+
+async fn api_get_faults(State(state): State<AppState>) -> Json<FaultStatus> {
+    let injector = state.connection_manager().fault_injector();
+    let status = injector.lock().unwrap().status();
+    Json(status)
+}
+
+async fn api_inject_faults(
+    State(state): State<AppState>,
+    Json(plan): Json<FaultPlan>,
+) -> Result<Json<FaultStatus>, (StatusCode, Json<ConnectResponse>)> {
+    let injector = state.connection_manager().fault_injector();
+    let mut injector = injector.lock().unwrap();
+    if !injector.is_enabled() {
+        return Err(fault_injection_disabled());
+    }
+    if let Some(problem) = plan.problem() {
+        return Err((StatusCode::BAD_REQUEST, Json(ConnectResponse { success: false, message: problem })));
+    }
+    injector.inject(plan);
+    Ok(Json(injector.status()))
+}
+
+async fn api_clear_faults(
+    State(state): State<AppState>,
+) -> Result<Json<FaultStatus>, (StatusCode, Json<ConnectResponse>)> {
+    let injector = state.connection_manager().fault_injector();
+    let mut injector = injector.lock().unwrap();
+    if !injector.is_enabled() {
+        return Err(fault_injection_disabled());
+    }
+    injector.clear();
+    info!("Fault injection cleared");
+    Ok(Json(injector.status()))
+}
+
 // ASCOM Management API handlers
 async fn get_management_api_versions(Query(query): Query<AlpacaQuery>) -> Json<AlpacaResponse<Vec<u32>>> {
     Json(AlpacaResponse::success(
@@ -450,12 +1994,16 @@ async fn get_management_api_versions(Query(query): Query<AlpacaQuery>) -> Json<A
     ))
 }
 
-async fn get_management_description(Query(query): Query<AlpacaQuery>) -> Json<AlpacaResponse<serde_json::Value>> {
+async fn get_management_description(
+    Query(query): Query<AlpacaQuery>,
+    State(state): State<AppState>,
+) -> Json<AlpacaResponse<serde_json::Value>> {
+    let identity = state.connection_manager().identity();
     let description = serde_json::json!({
-        "ServerName": "nRF52840 Telescope Park Bridge",
-        "Manufacturer": "Corey Smart",
+        "ServerName": identity.server_name(),
+        "Manufacturer": identity.manufacturer(),
         "ManufacturerVersion": env!("CARGO_PKG_VERSION"),
-        "Location": "Local"
+        "Location": identity.location()
     });
     
     Json(AlpacaResponse::success(
@@ -467,14 +2015,8 @@ async fn get_management_description(Query(query): Query<AlpacaQuery>) -> Json<Al
 async fn get_configured_devices(
     Query(query): Query<AlpacaQuery>, 
     State(state): State<AppState>
-) -> Json<AlpacaResponse<Vec<serde_json::Value>>> {
-    let device_state = state.device_state.read().await;
-    let devices = vec![serde_json::json!({
-        "DeviceName": device_state.device_name,
-        "DeviceType": "SafetyMonitor", 
-        "DeviceNumber": 0,
-        "UniqueID": device_state.unique_id
-    })];
+) -> Json<AlpacaResponse<Vec<ConfiguredDevice>>> {
+    let devices = state.devices.configured_devices().await;
     
     Json(AlpacaResponse::success(
         devices,
@@ -484,150 +2026,94 @@ async fn get_configured_devices(
 
 // ASCOM Device API handlers
 async fn get_connected(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Query(query): Query<AlpacaQuery>,
     State(state): State<AppState>,
-) -> Result<Json<AlpacaResponse<bool>>, (StatusCode, Json<AlpacaResponse<bool>>)> {
+) -> AlpacaResult<bool> {
     let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
     
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                false,
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    let device = alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
-    let device_state = state.device_state.read().await;
+    let device_state = device.device_state.read().await;
     Ok(Json(AlpacaResponse::success(device_state.ascom_connected, client_transaction_id)))
 }
 
 // PUT Connected handler with proper parameter validation
 async fn put_connected(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Extension(form_data): Extension<Option<ConnectedFormData>>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     State(state): State<AppState>,
-) -> Result<Json<AlpacaResponse<()>>, (StatusCode, Json<AlpacaResponse<()>>)> {
+) -> AlpacaResult<()> {
     let client_transaction_id = form_data.as_ref().map(|d| d.client_transaction_id).unwrap_or(0);
     
-    // Validate device number
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                (),
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    let device = alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
     // Validate form data exists
-    let form_data = match form_data {
-        Some(data) => data,
-        None => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(AlpacaResponse::error(
-                    (),
-                    client_transaction_id,
-                    1025,
-                    "Missing form data".to_string(),
-                ))
-            ));
-        }
-    };
+    let form_data = form_data
+        .ok_or_else(|| BridgeError::InvalidValue("Missing form data".to_string()))
+        .in_transaction(client_transaction_id)?;
     
     // Validate Connected parameter
     let connected_value = match form_data.connected.to_lowercase().as_str() {
-        "true" => true,
-        "false" => false,
-        "" => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(AlpacaResponse::error(
-                    (),
-                    client_transaction_id,
-                    1026,
-                    "Empty Connected parameter".to_string(),
-                ))
-            ));
-        }
-        _ => {
-            return Err((
-                StatusCode::BAD_REQUEST,
-                Json(AlpacaResponse::error(
-                    (),
-                    client_transaction_id,
-                    1027,
-                    "Invalid Connected parameter - must be 'true' or 'false'".to_string(),
-                ))
-            ));
-        }
-    };
+        "true" => Ok(true),
+        "false" => Ok(false),
+        "" => Err(BridgeError::ValueNotSet("Empty Connected parameter".to_string())),
+        _ => Err(BridgeError::InvalidValue(
+            "Invalid Connected parameter - must be 'true' or 'false'".to_string(),
+        )),
+    }
+    .in_transaction(client_transaction_id)?;
     
     // Update device state
     {
-        let mut device_state = state.device_state.write().await;
+        let mut device_state = device.device_state.write().await;
         device_state.ascom_connected = connected_value;
         info!("ASCOM Connected set to: {}", connected_value);
     }
     
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    state.ascom_clients.set_connected(
+        form_data.client_id,
+        connect_info.map(|info| info.0.ip()),
+        user_agent,
+        connected_value,
+    );
+    
     Ok(Json(AlpacaResponse::success((), client_transaction_id)))
 }
 
 async fn get_description(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Query(query): Query<AlpacaQuery>,
-    State(_state): State<AppState>,
-) -> Result<Json<AlpacaResponse<String>>, (StatusCode, Json<AlpacaResponse<String>>)> {
+    State(state): State<AppState>,
+) -> AlpacaResult<String> {
     let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
     
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                String::new(),
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    let device = alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
     Ok(Json(AlpacaResponse::success(
-        "nRF52840 based telescope park position sensor for ASCOM safety monitoring".to_string(),
+        device.connection_manager.identity().description(),
         client_transaction_id,
     )))
 }
 
 async fn get_driver_info(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Query(query): Query<AlpacaQuery>,
     State(state): State<AppState>,
-) -> Result<Json<AlpacaResponse<String>>, (StatusCode, Json<AlpacaResponse<String>>)> {
+) -> AlpacaResult<String> {
     let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
     
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                String::new(),
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    let device = alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
-    let device_state = state.device_state.read().await;
+    let device_state = device.device_state.read().await;
     let driver_info = format!("nRF52840 Telescope Park Bridge v{} for {}", 
-        env!("CARGO_PKG_VERSION"), device_state.device_name);
+        env!("CARGO_PKG_VERSION"), device.connection_manager.identity().device_name(&device_state.device_name));
     
     Ok(Json(AlpacaResponse::success(
         driver_info,
@@ -636,23 +2122,13 @@ async fn get_driver_info(
 }
 
 async fn get_driver_version(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Query(query): Query<AlpacaQuery>,
-    State(_state): State<AppState>,
-) -> Result<Json<AlpacaResponse<String>>, (StatusCode, Json<AlpacaResponse<String>>)> {
+    State(state): State<AppState>,
+) -> AlpacaResult<String> {
     let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
     
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                String::new(),
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
     Ok(Json(AlpacaResponse::success(
         env!("CARGO_PKG_VERSION").to_string(),
@@ -661,102 +2137,71 @@ async fn get_driver_version(
 }
 
 async fn get_interface_version(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Query(query): Query<AlpacaQuery>,
-    State(_state): State<AppState>,
-) -> Result<Json<AlpacaResponse<u32>>, (StatusCode, Json<AlpacaResponse<u32>>)> {
+    State(state): State<AppState>,
+) -> AlpacaResult<u32> {
     let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
     
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                0,
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
     Ok(Json(AlpacaResponse::success(1, client_transaction_id)))
 }
 
 async fn get_name(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Query(query): Query<AlpacaQuery>,
     State(state): State<AppState>,
-) -> Result<Json<AlpacaResponse<String>>, (StatusCode, Json<AlpacaResponse<String>>)> {
+) -> AlpacaResult<String> {
     let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
     
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                String::new(),
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    let device = alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
-    let device_state = state.device_state.read().await;
+    let device_state = device.device_state.read().await;
     Ok(Json(AlpacaResponse::success(
-        device_state.device_name.clone(),
+        device.connection_manager.identity().device_name(&device_state.device_name),
         client_transaction_id,
     )))
 }
 
 async fn get_supported_actions(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Query(query): Query<AlpacaQuery>,
-    State(_state): State<AppState>,
-) -> Result<Json<AlpacaResponse<Vec<String>>>, (StatusCode, Json<AlpacaResponse<Vec<String>>>)> {
+    State(state): State<AppState>,
+) -> AlpacaResult<Vec<String>> {
     let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
     
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                vec![],
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
     Ok(Json(AlpacaResponse::success(vec![], client_transaction_id)))
 }
 
 async fn get_is_safe(
-    Path(device_number): Path<u32>,
+    path: DevicePath,
     Query(query): Query<AlpacaQuery>,
     State(state): State<AppState>,
-) -> Result<Json<AlpacaResponse<bool>>, (StatusCode, Json<AlpacaResponse<bool>>)> {
+) -> AlpacaResult<bool> {
     let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
     
-    if device_number != 0 {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            Json(AlpacaResponse::error(
-                false,
-                client_transaction_id,
-                1024,
-                format!("Invalid device number: {}", device_number),
-            ))
-        ));
-    }
+    let device = alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
     
-    let device_state = state.device_state.read().await;
+    device.connection_manager.refresh_stale_state().await;
+    let device_state = device.device_state.read().await;
     
-    // ASCOM compliance: IsSafe should return false if not connected
-    let is_safe = if device_state.connected {
-        device_state.is_safe
-    } else {
-        false
-    };
+    // ASCOM compliance: IsSafe is false (not an error) when disconnected or the data is stale,
+    // unless [safety] on_stale asks for an error; a forced value still wins
+    let safety = device.connection_manager.safety();
+    if safety.on_stale == StaleReply::Error
+        && device_state.active_override().is_none()
+        && device_state.is_stale(safety.max_data_age_secs)
+    {
+        return Err(AlpacaError {
+            error: BridgeError::StaleData(device_state.data_age_secs().unwrap_or_default()),
+            client_transaction_id,
+        }
+        .into());
+    }
+    let is_safe = device_state.is_safe_now(safety.max_data_age_secs);
     
     Ok(Json(AlpacaResponse::success(
         is_safe,
@@ -764,29 +2209,61 @@ async fn get_is_safe(
     )))
 }
 
+async fn get_device_state(
+    path: DevicePath,
+    Query(query): Query<AlpacaQuery>,
+    State(state): State<AppState>,
+) -> AlpacaResult<Vec<StateValue>> {
+    let client_transaction_id = get_client_transaction_id(query.client_transaction_id);
+    
+    let device = alpaca_device(&state, &path).in_transaction(client_transaction_id)?;
+    
+    let max_data_age = device.connection_manager.max_data_age_secs();
+    device.connection_manager.refresh_stale_state().await;
+    let device_state = device.device_state.read().await;
+    let timestamp = chrono::DateTime::from_timestamp(device_state.last_update as i64, 0)
+        .unwrap_or_default()
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string();
+    
+    let values = vec![
+        StateValue::new("IsSafe", device_state.is_safe_now(max_data_age)),
+        StateValue::new("Stale", device_state.is_stale(max_data_age)),
+        // Name of the safety schedule window holding IsSafe false, empty outside one
+        StateValue::new("SafetySchedule", device_state.safety_schedule.clone().unwrap_or_default()),
+        StateValue::new("Maintenance", device_state.active_maintenance().is_some()),
+        StateValue::new("TimeStamp", timestamp),
+    ];
+    
+    Ok(Json(AlpacaResponse::success(values, client_transaction_id)))
+}
+
+#[cfg(feature = "web-ui")]
 async fn serve_favicon() -> Response<Body> {
     Response::builder()
         .status(200)
         .header(header::CONTENT_TYPE, "image/png")
         .header(header::CACHE_CONTROL, "public, max-age=86400")
-        .body(Body::from(ICON_PNG))
+        .body(Body::from(ICON_PNG.body))
         .unwrap()
 }
 
+#[cfg(feature = "web-ui")]
 async fn serve_icon_192() -> Response<Body> {
     Response::builder()
         .status(200)
         .header(header::CONTENT_TYPE, "image/png")
         .header(header::CACHE_CONTROL, "public, max-age=86400")
-        .body(Body::from(ICON_PNG))
+        .body(Body::from(ICON_PNG.body))
         .unwrap()
 }
 
+#[cfg(feature = "web-ui")]
 async fn serve_icon_512() -> Response<Body> {
     Response::builder()
         .status(200)
         .header(header::CONTENT_TYPE, "image/png")
         .header(header::CACHE_CONTROL, "public, max-age=86400")
-        .body(Body::from(ICON_PNG))
+        .body(Body::from(ICON_PNG.body))
         .unwrap()
 }
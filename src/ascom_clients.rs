@@ -0,0 +1,106 @@
+// src/ascom_clients.rs
+// Registry of ASCOM clients that have set Connected=true, exposed at /api/clients
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AscomClient {
+    pub client_id: u32,
+    pub address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    // Seconds since the Unix epoch, serialized as RFC 3339
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub connected_at: u64,
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub last_transaction: u64,
+    pub last_endpoint: String,
+    pub transactions: u64,
+}
+
+// Clients are keyed by ClientID and source address, since ClientID alone is only unique per host
+type ClientKey = (u32, Option<IpAddr>);
+
+#[derive(Clone, Default)]
+pub struct AscomClientRegistry {
+    clients: Arc<Mutex<HashMap<ClientKey, AscomClient>>>,
+}
+
+impl AscomClientRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_connected(
+        &self,
+        client_id: u32,
+        address: Option<IpAddr>,
+        user_agent: Option<String>,
+        connected: bool,
+    ) {
+        let mut clients = self.clients.lock().unwrap();
+        let key = (client_id, address);
+
+        if !connected {
+            if clients.remove(&key).is_some() {
+                info!("ASCOM client {} ({}) disconnected", client_id, display_address(address));
+            }
+            return;
+        }
+
+        let now = unix_now();
+        let client = clients.entry(key).or_insert_with(|| {
+            info!(
+                "ASCOM client {} ({}, {}) connected",
+                client_id,
+                display_address(address),
+                user_agent.as_deref().unwrap_or("unknown agent")
+            );
+            AscomClient {
+                client_id,
+                address,
+                user_agent: user_agent.clone(),
+                connected_at: now,
+                last_transaction: now,
+                last_endpoint: "connected".to_string(),
+                transactions: 0,
+            }
+        });
+        client.user_agent = user_agent.or(client.user_agent.take());
+        client.last_transaction = now;
+        client.last_endpoint = "connected".to_string();
+        client.transactions += 1;
+    }
+
+    // Note a device API transaction; ignored for clients that never connected
+    pub fn record_transaction(&self, client_id: u32, address: Option<IpAddr>, endpoint: &str) {
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get_mut(&(client_id, address)) {
+            client.last_transaction = unix_now();
+            client.last_endpoint = endpoint.to_string();
+            client.transactions += 1;
+        }
+    }
+
+    // Connected clients, most recently active first
+    pub fn clients(&self) -> Vec<AscomClient> {
+        let mut clients: Vec<AscomClient> = self.clients.lock().unwrap().values().cloned().collect();
+        clients.sort_by_key(|client| std::cmp::Reverse(client.last_transaction));
+        clients
+    }
+}
+
+fn display_address(address: Option<IpAddr>) -> String {
+    address.map(|a| a.to_string()).unwrap_or_else(|| "unknown address".to_string())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
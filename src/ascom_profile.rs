@@ -0,0 +1,144 @@
+// src/ascom_profile.rs
+// ASCOM Profile entries of an Alpaca Dynamic Client driver pointing at a bridge device, so
+// COM-only Windows applications can use the sensor through the ASCOM Platform
+
+use std::fmt::Write;
+
+// Profile key of SafetyMonitor drivers, in the 32-bit registry view the ASCOM Platform uses
+pub const DRIVERS_KEY: &str = r"SOFTWARE\ASCOM\SafetyMonitor Drivers";
+const DRIVERS_KEY_64BIT_HOST: &str = r"HKEY_LOCAL_MACHINE\SOFTWARE\WOW6432Node\ASCOM\SafetyMonitor Drivers";
+
+// The Platform serves dynamic drivers under ProgIDs of this form, numbered from 1
+const PROG_ID_PREFIX: &str = "ASCOM.AlpacaDynamic";
+const PROG_ID_SUFFIX: &str = ".SafetyMonitor";
+
+// Alpaca discovery port the dynamic client rediscovers a moved device on
+const DISCOVERY_PORT: u16 = 32227;
+
+#[derive(Debug, Clone)]
+pub struct DynamicDriver {
+    pub driver_number: u32,
+    // Name shown in the Chooser
+    pub description: String,
+    pub host: String,
+    pub port: u16,
+    pub device_number: u32,
+    // Lets the client find the device again after an address change; only stable when set in
+    // the config file, since the bridge otherwise picks a new UniqueID on every start
+    pub unique_id: Option<String>,
+}
+
+impl DynamicDriver {
+    pub fn prog_id(&self) -> String {
+        prog_id(self.driver_number)
+    }
+
+    // Values as the Chooser's "Create Alpaca Driver" writes them; the Profile stores strings only
+    pub fn profile_values(&self) -> Vec<(&'static str, String)> {
+        let flag = |on: bool| if on { "True" } else { "False" }.to_string();
+        vec![
+            ("IP Address", self.host.clone()),
+            ("Port Number", self.port.to_string()),
+            ("Remote Device Number", self.device_number.to_string()),
+            ("Service Type", "http".to_string()),
+            ("Establish Connection Timeout", "5".to_string()),
+            ("Standard Server Response Timeout", "10".to_string()),
+            ("Long Server Response Timeout", "120".to_string()),
+            ("User Name", String::new()),
+            ("Password", String::new()),
+            ("Manage Connect Locally", flag(false)),
+            ("Trace Level", flag(false)),
+            ("Include Debug Trace", flag(false)),
+            ("UniqueID", self.unique_id.clone().unwrap_or_default()),
+            ("Enable Rediscovery", flag(self.unique_id.is_some())),
+            ("IP V4 Enabled", flag(true)),
+            ("IP V6 Enabled", flag(false)),
+            ("Discovery Port", DISCOVERY_PORT.to_string()),
+        ]
+    }
+
+    // Registry file for regedit on 64-bit Windows, for machines the bridge does not run on
+    pub fn to_reg_file(&self) -> String {
+        let mut reg = String::from("Windows Registry Editor Version 5.00\r\n\r\n");
+        let _ = write!(reg, "[{}\\{}]\r\n", DRIVERS_KEY_64BIT_HOST, self.prog_id());
+        let _ = write!(reg, "@=\"{}\"\r\n", reg_escape(&self.description));
+        for (name, value) in self.profile_values() {
+            let _ = write!(reg, "\"{}\"=\"{}\"\r\n", name, reg_escape(&value));
+        }
+        reg
+    }
+}
+
+pub fn prog_id(driver_number: u32) -> String {
+    format!("{}{}{}", PROG_ID_PREFIX, driver_number, PROG_ID_SUFFIX)
+}
+
+// Driver number of an Alpaca dynamic SafetyMonitor ProgID
+pub fn driver_number(prog_id: &str) -> Option<u32> {
+    prog_id.strip_prefix(PROG_ID_PREFIX)?.strip_suffix(PROG_ID_SUFFIX)?.parse().ok()
+}
+
+// regedit reads .reg files as UTF-16 with a byte order mark
+pub fn encode_reg_file(reg: &str) -> Vec<u8> {
+    let mut bytes = vec![0xff, 0xfe];
+    bytes.extend(reg.encode_utf16().flat_map(u16::to_le_bytes));
+    bytes
+}
+
+fn reg_escape(value: &str) -> String {
+    value.replace('\\', r"\\").replace('"', "\\\"")
+}
+
+// Writing the Profile directly (needs an elevated prompt for HKEY_LOCAL_MACHINE)
+#[cfg(all(windows, feature = "desktop"))]
+mod registry {
+    use super::{driver_number, DynamicDriver, DRIVERS_KEY};
+    use std::io;
+    use winreg::enums::{HKEY_LOCAL_MACHINE, KEY_READ, KEY_WOW64_32KEY, KEY_WRITE};
+    use winreg::RegKey;
+
+    fn drivers_key(access: u32) -> io::Result<RegKey> {
+        RegKey::predef(HKEY_LOCAL_MACHINE)
+            .create_subkey_with_flags(DRIVERS_KEY, access | KEY_WOW64_32KEY)
+            .map(|(key, _)| key)
+    }
+
+    // The dynamic driver already pointing at this host, port and device, or the lowest free number
+    pub fn free_driver_number(host: &str, port: u16, device_number: u32) -> io::Result<u32> {
+        let drivers = drivers_key(KEY_READ)?;
+        let mut used = Vec::new();
+        for name in drivers.enum_keys() {
+            let name = name?;
+            let Some(number) = driver_number(&name) else {
+                continue;
+            };
+            let key = drivers.open_subkey_with_flags(&name, KEY_READ | KEY_WOW64_32KEY)?;
+            let value = |name: &str| key.get_value::<String, _>(name).unwrap_or_default();
+            if value("IP Address") == host
+                && value("Port Number") == port.to_string()
+                && value("Remote Device Number") == device_number.to_string()
+            {
+                return Ok(number);
+            }
+            used.push(number);
+        }
+        Ok((1..).find(|number| !used.contains(number)).unwrap_or(1))
+    }
+
+    pub fn install(driver: &DynamicDriver) -> io::Result<()> {
+        let (key, _) = drivers_key(KEY_READ | KEY_WRITE)?
+            .create_subkey_with_flags(driver.prog_id(), KEY_READ | KEY_WRITE | KEY_WOW64_32KEY)?;
+        key.set_value("", &driver.description)?;
+        for (name, value) in driver.profile_values() {
+            key.set_value(name, &value)?;
+        }
+        Ok(())
+    }
+
+    pub fn uninstall(prog_id: &str) -> io::Result<()> {
+        drivers_key(KEY_READ | KEY_WRITE)?.delete_subkey_all(prog_id)
+    }
+}
+
+#[cfg(all(windows, feature = "desktop"))]
+pub use registry::{free_driver_number, install, uninstall};
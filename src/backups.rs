@@ -0,0 +1,214 @@
+// src/backups.rs
+// Scheduled backups to a directory off the SD card: the config file, each device's park and
+// calibration settings as last read from the firmware, and the compacted event and command
+// history, so a dead card loses neither. Only the newest `keep` backups are kept.
+
+use crate::command_history::{CommandOutcome, CommandRecord};
+use crate::config::BackupConfig;
+use crate::device_registry::DeviceRegistry;
+use crate::errors::Result;
+use crate::events::{BridgeEvent, EventKind};
+use crate::protocol;
+use crate::session_log::SessionEntry;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+// Backups are named park-bridge-backup-<UTC time>.json, so they sort oldest first by name
+const FILE_PREFIX: &str = "park-bridge-backup-";
+const FILE_SUFFIX: &str = ".json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Backup {
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub created_at: u64,
+    pub bridge_version: &'static str,
+    // The --config file as it was on disk; null without one
+    pub config_file: Option<String>,
+    pub config: Option<String>,
+    pub devices: Vec<DeviceBackup>,
+    // Every device's events in one timeline, for looking back at the last nights
+    pub session_log: Vec<SessionEntry>,
+}
+
+// The part of a backup file that safe mode restores from
+#[derive(Debug, Clone, Deserialize)]
+pub struct StoredBackup {
+    #[serde(deserialize_with = "crate::timestamps::rfc3339_secs::deserialize")]
+    pub created_at: u64,
+    pub config: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceBackup {
+    pub device_number: u32,
+    pub name: String,
+    pub unique_id: String,
+    pub serial_port: Option<String>,
+    pub settings: DeviceSettings,
+    pub events: Vec<BridgeEvent>,
+    pub commands: Vec<CommandRecord>,
+}
+
+// What the firmware keeps in flash, as the bridge last read it
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceSettings {
+    // null when the firmware has not reported since the bridge started
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub read_at: u64,
+    pub firmware_version: String,
+    pub park_pitch: f32,
+    pub park_roll: f32,
+    pub position_tolerance: f32,
+    pub is_calibrated: bool,
+}
+
+pub struct BackupScheduler {
+    config: BackupConfig,
+    devices: DeviceRegistry,
+    config_path: Option<PathBuf>,
+}
+
+impl BackupScheduler {
+    pub fn new(config: BackupConfig, devices: DeviceRegistry, config_path: Option<PathBuf>) -> Self {
+        Self {
+            config,
+            devices,
+            config_path,
+        }
+    }
+
+    // Back up every interval_hours until cancelled, and once more on the way out so a restart
+    // keeps the history gathered since the last backup
+    pub async fn run(self, cancel_token: CancellationToken) {
+        let Some(directory) = self.config.directory.clone() else {
+            return;
+        };
+        info!(
+            "Backing up settings and history to {} every {}h, keeping {}",
+            directory, self.config.interval_hours, self.config.keep
+        );
+        let period = Duration::from_secs(self.config.interval_hours * 3600);
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            let stopping = tokio::select! {
+                _ = cancel_token.cancelled() => true,
+                _ = interval.tick() => false,
+            };
+            if let Err(e) = self.write_backup(Path::new(&directory)).await {
+                warn!("Backup to {} failed: {}", directory, e);
+            }
+            if stopping {
+                return;
+            }
+        }
+    }
+
+    pub async fn collect(&self) -> Backup {
+        let config = match &self.config_path {
+            Some(path) => match tokio::fs::read_to_string(path).await {
+                Ok(text) => Some(text),
+                Err(e) => {
+                    warn!("Backup without the config file {}: {}", path.display(), e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let mut devices = Vec::with_capacity(self.devices.len());
+        for device in self.devices.devices() {
+            let manager = &device.connection_manager;
+            let state = device.device_state.read().await;
+            let mut events = manager.event_bus().recent(None);
+            events.retain(|event| !matches!(event.kind, EventKind::CalibrationProgress { .. }));
+            let mut commands = manager.command_history().entries();
+            commands.retain(|record| !is_routine_query(record));
+            devices.push(DeviceBackup {
+                device_number: device.device_number,
+                name: manager.identity().device_name(&state.device_name),
+                unique_id: state.unique_id.clone(),
+                serial_port: state.serial_port.clone(),
+                settings: DeviceSettings {
+                    read_at: state.last_update,
+                    firmware_version: state.device_version.clone(),
+                    park_pitch: state.park_pitch,
+                    park_roll: state.park_roll,
+                    position_tolerance: state.position_tolerance,
+                    is_calibrated: state.is_calibrated,
+                },
+                events,
+                commands,
+            });
+        }
+        Backup {
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            bridge_version: env!("CARGO_PKG_VERSION"),
+            config_file: self.config_path.as_ref().map(|path| path.display().to_string()),
+            config,
+            devices,
+            session_log: self.devices.session_log().entries(),
+        }
+    }
+
+    // Write a backup into `directory`, creating it if needed, then drop the oldest beyond `keep`
+    pub async fn write_backup(&self, directory: &Path) -> Result<PathBuf> {
+        let backup = self.collect().await;
+        let json = serde_json::to_vec_pretty(&backup)?;
+        tokio::fs::create_dir_all(directory).await?;
+        let name = format!("{}{}{}", FILE_PREFIX, chrono::Utc::now().format("%Y%m%dT%H%M%SZ"), FILE_SUFFIX);
+        let path = directory.join(&name);
+        // Written aside and renamed, so a power cut never leaves a half-written newest backup
+        let partial = directory.join(format!("{}.partial", name));
+        tokio::fs::write(&partial, json).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        info!("Backup written to {}", path.display());
+        self.remove_old_backups(directory).await?;
+        Ok(path)
+    }
+
+    async fn remove_old_backups(&self, directory: &Path) -> Result<()> {
+        let backups = list_backups(directory).await?;
+        let excess = backups.len().saturating_sub(self.config.keep);
+        for name in &backups[..excess] {
+            debug!("Removing old backup {}", name);
+            tokio::fs::remove_file(directory.join(name)).await?;
+        }
+        Ok(())
+    }
+}
+
+// File names of the backups in `directory`, oldest first
+pub async fn list_backups(directory: &Path) -> Result<Vec<String>> {
+    let mut backups = Vec::new();
+    let mut entries = tokio::fs::read_dir(directory).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX) {
+            backups.push(name);
+        }
+    }
+    backups.sort();
+    Ok(backups)
+}
+
+pub async fn read_backup(path: &Path) -> Result<StoredBackup> {
+    let bytes = tokio::fs::read(path).await?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+// Polls and queries answered from the firmware's RAM; only failed ones are worth keeping
+fn is_routine_query(record: &CommandRecord) -> bool {
+    record.outcome == CommandOutcome::Completed
+        && matches!(
+            protocol::command_code(&record.command),
+            protocol::GET_STATUS
+                | protocol::GET_POSITION
+                | protocol::IS_PARKED
+                | protocol::GET_PARK
+                | protocol::GET_VERSION
+                | protocol::GET_TOLERANCE
+                | protocol::SYSTEM_INFO
+        )
+}
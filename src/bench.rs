@@ -0,0 +1,204 @@
+// src/bench.rs
+// Serial latency benchmark: bursts of status commands at one or more baud rates
+
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio_serial::SerialPortBuilderExt;
+use tracing::{debug, info};
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub port: String,
+    pub baud_rates: Vec<u32>,
+    pub count: u32,
+    pub command: String,
+    pub timeout: Duration,
+}
+
+// Latency distribution for one baud rate, all values in milliseconds
+#[derive(Debug, Serialize)]
+pub struct BenchResult {
+    pub baud_rate: u32,
+    pub sent: u32,
+    pub completed: u32,
+    pub failed: u32,
+    pub ack_mean_ms: Option<f64>,
+    pub min_ms: Option<f64>,
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub throughput_per_sec: f64,
+    pub error: Option<String>,
+}
+
+pub async fn run_bench(options: &BenchOptions) -> Vec<BenchResult> {
+    let mut results = Vec::new();
+    for &baud_rate in &options.baud_rates {
+        info!("Benchmarking {} at {} baud ({} x <{}>)", options.port, baud_rate, options.count, options.command);
+        results.push(bench_baud_rate(options, baud_rate).await);
+        // Give the port a moment to be released before reopening at the next rate
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    results
+}
+
+async fn bench_baud_rate(options: &BenchOptions, baud_rate: u32) -> BenchResult {
+    let mut result = BenchResult {
+        baud_rate,
+        sent: 0,
+        completed: 0,
+        failed: 0,
+        ack_mean_ms: None,
+        min_ms: None,
+        mean_ms: None,
+        p50_ms: None,
+        p90_ms: None,
+        p99_ms: None,
+        max_ms: None,
+        throughput_per_sec: 0.0,
+        error: None,
+    };
+
+    let port = match tokio_serial::new(&options.port, baud_rate)
+        .timeout(Duration::from_millis(1000))
+        .open_native_async()
+    {
+        Ok(port) => port,
+        Err(e) => {
+            result.error = Some(format!("Failed to open port: {}", e));
+            return result;
+        }
+    };
+
+    let (reader, mut writer) = tokio::io::split(port);
+    let mut reader = BufReader::new(reader);
+
+    // Let the device settle and discard startup chatter
+    let settle_until = Instant::now() + Duration::from_millis(1500);
+    while Instant::now() < settle_until {
+        let mut line = String::new();
+        let _ = tokio::time::timeout(Duration::from_millis(100), reader.read_line(&mut line)).await;
+    }
+
+    let mut round_trips = Vec::new();
+    let mut ack_times = Vec::new();
+    let burst_start = Instant::now();
+    let frame = format!("<{}>\n", options.command);
+
+    for _ in 0..options.count {
+        result.sent += 1;
+        let start = Instant::now();
+        if let Err(e) = async {
+            writer.write_all(frame.as_bytes()).await?;
+            writer.flush().await
+        }
+        .await
+        {
+            result.error = Some(format!("Write failed: {}", e));
+            break;
+        }
+
+        let mut completed = false;
+        while start.elapsed() < options.timeout {
+            let mut line = String::new();
+            let remaining = options.timeout.saturating_sub(start.elapsed());
+            match tokio::time::timeout(remaining, reader.read_line(&mut line)).await {
+                Ok(Ok(0)) | Ok(Err(_)) | Err(_) => break,
+                Ok(Ok(_)) => {
+                    let Ok(value) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+                        continue;
+                    };
+                    match value.get("status").and_then(|s| s.as_str()) {
+                        Some("ack") => ack_times.push(start.elapsed().as_secs_f64() * 1000.0),
+                        Some("ok") => {
+                            completed = true;
+                            break;
+                        }
+                        Some("error") => break,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if completed {
+            result.completed += 1;
+            round_trips.push(start.elapsed().as_secs_f64() * 1000.0);
+        } else {
+            result.failed += 1;
+            debug!("Round trip {} failed", result.sent);
+        }
+    }
+
+    let elapsed = burst_start.elapsed().as_secs_f64();
+    if elapsed > 0.0 {
+        result.throughput_per_sec = result.completed as f64 / elapsed;
+    }
+
+    if !ack_times.is_empty() {
+        result.ack_mean_ms = Some(ack_times.iter().sum::<f64>() / ack_times.len() as f64);
+    }
+
+    if !round_trips.is_empty() {
+        round_trips.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        result.min_ms = round_trips.first().copied();
+        result.max_ms = round_trips.last().copied();
+        result.mean_ms = Some(round_trips.iter().sum::<f64>() / round_trips.len() as f64);
+        result.p50_ms = Some(percentile(&round_trips, 50.0));
+        result.p90_ms = Some(percentile(&round_trips, 90.0));
+        result.p99_ms = Some(percentile(&round_trips, 99.0));
+    }
+
+    result
+}
+
+// Nearest-rank percentile of an already sorted slice
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// Human-readable report with a polling-interval suggestion
+pub fn format_report(options: &BenchOptions, results: &[BenchResult]) -> String {
+    let ms = |value: Option<f64>| value.map(|v| format!("{:.1}", v)).unwrap_or_else(|| "-".to_string());
+
+    let mut report = format!(
+        "Serial latency benchmark: {} x <{}> on {}\n\n{:>8} {:>6} {:>6} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>9}\n",
+        options.count, options.command, options.port,
+        "baud", "ok", "fail", "ack", "min", "p50", "p90", "p99", "max", "cmds/s"
+    );
+
+    for result in results {
+        if let Some(error) = &result.error {
+            report.push_str(&format!("{:>8} error: {}\n", result.baud_rate, error));
+            continue;
+        }
+        report.push_str(&format!(
+            "{:>8} {:>6} {:>6} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>9.1}\n",
+            result.baud_rate,
+            result.completed,
+            result.failed,
+            ms(result.ack_mean_ms),
+            ms(result.min_ms),
+            ms(result.p50_ms),
+            ms(result.p90_ms),
+            ms(result.p99_ms),
+            ms(result.max_ms),
+            result.throughput_per_sec
+        ));
+    }
+
+    if let Some(worst_p99) = results.iter().filter_map(|r| r.p99_ms).reduce(f64::max) {
+        // Two status-type polls per cycle must fit comfortably inside the interval
+        let suggested_ms = (worst_p99 * 4.0).max(250.0);
+        report.push_str(&format!(
+            "\nAll latencies in ms. Suggested minimum polling interval: {:.0} ms\n",
+            suggested_ms
+        ));
+    }
+
+    report
+}
@@ -0,0 +1,168 @@
+// src/bin/park_sensor_sim.rs
+// Standalone firmware simulator for testing the bridge (and other ASCOM tooling) without hardware
+
+use clap::Parser;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use telescope_park_bridge::session_recording::{load_recording, replay_recording, TrafficLine, REPLAY_SPEEDS};
+use telescope_park_bridge::simulator::{parse_scenario, run_scenario, run_simulator, SimulatorState};
+use tokio::sync::mpsc;
+use tokio_serial::SerialPortBuilderExt;
+use tracing::{error, info};
+
+#[derive(Parser)]
+#[command(name = "park-sensor-sim", version, about = "nRF52840 Telescope Park Sensor firmware simulator")]
+struct Args {
+    #[arg(short, long, help = "Serial port to serve the simulated firmware on (e.g., COM10 of a com0com pair)")]
+    port: Option<String>,
+
+    #[arg(long = "virtual", help = "Create a virtual serial port (pty) and print its path (Unix only)")]
+    virtual_port: bool,
+
+    #[arg(short, long, default_value = "115200", help = "Baud rate when using a real serial port")]
+    baud: u32,
+
+    #[arg(short, long, help = "Scenario script to run (see simulator::parse_scenario for the syntax)")]
+    scenario: Option<String>,
+
+    #[arg(long, default_value = "0.0", help = "Initial jitter amplitude in degrees")]
+    noise: f32,
+
+    #[arg(long, default_value = "0.0", help = "Initial pitch in degrees")]
+    pitch: f32,
+
+    #[arg(long, default_value = "0.0", help = "Initial roll in degrees")]
+    roll: f32,
+
+    #[arg(long, help = "Replay the device side of a recorded session (bridge --record) instead of simulating")]
+    replay: Option<String>,
+
+    #[arg(long, default_value = "1.0", value_parser = parse_replay_speed, help = "Replay speed multiplier (0.01 to 1000)")]
+    replay_speed: f64,
+
+    #[arg(long, help = "Restart the replay from the beginning when it ends")]
+    replay_loop: bool,
+
+    #[arg(short, long, help = "Enable debug logging")]
+    debug: bool,
+}
+
+fn parse_replay_speed(text: &str) -> Result<f64, String> {
+    let speed: f64 = text.parse().map_err(|e| format!("{}", e))?;
+    if !REPLAY_SPEEDS.contains(&speed) {
+        return Err(format!("must be from {} to {}", REPLAY_SPEEDS.start(), REPLAY_SPEEDS.end()));
+    }
+    Ok(speed)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    tracing_subscriber::fmt()
+        .with_max_level(if args.debug { tracing::Level::DEBUG } else { tracing::Level::INFO })
+        .with_writer(std::io::stderr)
+        .init();
+
+    if let Some(path) = &args.replay {
+        let recording = load_recording(std::path::Path::new(path))?;
+        info!("Loaded {} recorded lines from {}", recording.len(), path);
+        if let Err(e) = replay(&args, &recording).await {
+            error!("Replay stopped: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let steps = match &args.scenario {
+        Some(path) => parse_scenario(&std::fs::read_to_string(path)?)?,
+        None => Vec::new(),
+    };
+
+    let mut initial_state = SimulatorState::default();
+    initial_state.pitch = args.pitch;
+    initial_state.roll = args.roll;
+    initial_state.noise_amplitude = args.noise;
+    let state = Arc::new(Mutex::new(initial_state));
+
+    let (output_sender, output_receiver) = mpsc::unbounded_channel();
+    let scenario_state = state.clone();
+    tokio::spawn(async move {
+        run_scenario(steps, scenario_state, output_sender).await;
+        info!("Scenario finished - simulator keeps serving the final state");
+    });
+
+    let result = if let Some(port_name) = &args.port {
+        info!("Serving simulated firmware on {} at {} baud", port_name, args.baud);
+        let port = tokio_serial::new(port_name, args.baud)
+            .timeout(Duration::from_millis(1000))
+            .open_native_async()?;
+        run_simulator(port, state, output_receiver).await
+    } else if args.virtual_port {
+        serve_virtual_port(state, output_receiver).await
+    } else {
+        error!("Specify --port <PORT> or --virtual");
+        std::process::exit(2);
+    };
+
+    if let Err(e) = result {
+        error!("Simulator stopped: {}", e);
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn replay(args: &Args, recording: &[TrafficLine]) -> std::io::Result<()> {
+    if let Some(port_name) = &args.port {
+        info!("Replaying session on {} at {} baud", port_name, args.baud);
+        let port = tokio_serial::new(port_name, args.baud)
+            .timeout(Duration::from_millis(1000))
+            .open_native_async()?;
+        return replay_recording(port, recording, args.replay_speed, args.replay_loop).await;
+    }
+
+    #[cfg(unix)]
+    if args.virtual_port {
+        use tokio_serial::SerialPort;
+
+        let (master, slave) = tokio_serial::SerialStream::pair()?;
+        let slave_name = slave.name().unwrap_or_default();
+        info!("Virtual serial port ready for replay: {}", slave_name);
+        println!("{}", slave_name);
+        let _slave = slave;
+        return replay_recording(master, recording, args.replay_speed, args.replay_loop).await;
+    }
+
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "specify --port <PORT> or --virtual (Unix) for replay",
+    ))
+}
+
+#[cfg(unix)]
+async fn serve_virtual_port(
+    state: Arc<Mutex<SimulatorState>>,
+    output_receiver: mpsc::UnboundedReceiver<String>,
+) -> std::io::Result<()> {
+    use tokio_serial::SerialPort;
+
+    let (master, slave) = tokio_serial::SerialStream::pair()?;
+    let slave_name = slave.name().unwrap_or_default();
+    info!("Virtual serial port ready: {}", slave_name);
+    println!("{}", slave_name);
+
+    // Keep the slave open so the pty survives bridge reconnects
+    let _slave = slave;
+    run_simulator(master, state, output_receiver).await
+}
+
+#[cfg(not(unix))]
+async fn serve_virtual_port(
+    _state: Arc<Mutex<SimulatorState>>,
+    _output_receiver: mpsc::UnboundedReceiver<String>,
+) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "virtual ports are only supported on Unix; use a com0com pair with --port instead",
+    ))
+}
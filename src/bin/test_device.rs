@@ -1,35 +1,325 @@
+use clap::Parser;
+use serde::Serialize;
 use std::io::{self, Write};
-use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio_serial::SerialPortBuilderExt;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+
+#[derive(Parser)]
+#[command(name = "test_device", version, about = "nRF52840 device communication test")]
+struct Args {
+    #[arg(short, long, help = "Serial port (prompted for when omitted in interactive mode)")]
+    port: Option<String>,
+
+    #[arg(short, long, default_value = "115200", help = "Baud rate for serial communication")]
+    baud: u32,
+
+    #[arg(short, long, help = "Run the command sequence from this file unattended instead of prompting")]
+    script: Option<String>,
+
+    #[arg(long, help = "Print machine-readable JSON results (scripted mode)")]
+    json: bool,
+
+    #[arg(long, default_value = "5", help = "Default seconds to wait for each command's data response")]
+    timeout: u64,
+}
+
+// One command of a smoke-test script:
+//   <command> [timeout=<secs>] [expect <field>=<value> ...]
+// e.g. "01 expect calibrated=true" or "06 timeout=30"
+#[derive(Debug)]
+struct ScriptStep {
+    line: usize,
+    command: String,
+    timeout: Duration,
+    expectations: Vec<(String, String)>,
+}
+
+#[derive(Serialize)]
+struct StepResult {
+    line: usize,
+    command: String,
+    passed: bool,
+    duration_ms: u64,
+    acknowledged: bool,
+    response: Option<serde_json::Value>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ScriptReport {
+    port: String,
+    baud: u32,
+    passed: usize,
+    failed: usize,
+    startup_lines: usize,
+    steps: Vec<StepResult>,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    if let Some(script) = &args.script {
+        let Some(port_name) = args.port.clone() else {
+            eprintln!("--port is required with --script");
+            std::process::exit(2);
+        };
+        let exit_code = run_script(&port_name, args.baud, script, args.json, Duration::from_secs(args.timeout)).await?;
+        std::process::exit(exit_code);
+    }
+
+    run_interactive(args.port, args.baud).await
+}
+
+fn open_port(port_name: &str, baud: u32) -> tokio_serial::Result<SerialStream> {
+    tokio_serial::new(port_name, baud)
+        .timeout(Duration::from_millis(1000))
+        .data_bits(tokio_serial::DataBits::Eight)
+        .flow_control(tokio_serial::FlowControl::None)
+        .parity(tokio_serial::Parity::None)
+        .stop_bits(tokio_serial::StopBits::One)
+        .open_native_async()
+}
+
+fn parse_script(text: &str, default_timeout: Duration) -> Result<Vec<ScriptStep>, String> {
+    let mut steps = Vec::new();
+
+    for (index, raw_line) in text.lines().enumerate() {
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        let command = tokens.next().unwrap_or("").trim_start_matches('<').trim_end_matches('>').to_string();
+        let mut step = ScriptStep {
+            line: index + 1,
+            command,
+            timeout: default_timeout,
+            expectations: Vec::new(),
+        };
+
+        let mut in_expect = false;
+        for token in tokens {
+            if token == "expect" {
+                in_expect = true;
+                continue;
+            }
+            let Some((key, value)) = token.split_once('=') else {
+                return Err(format!("line {}: expected key=value, got '{}'", index + 1, token));
+            };
+            if in_expect {
+                step.expectations.push((key.to_string(), value.to_string()));
+            } else if key == "timeout" {
+                // Negative, NaN and overflowing values are refused rather than panicking
+                step.timeout = value
+                    .parse()
+                    .ok()
+                    .and_then(|secs: f64| Duration::try_from_secs_f64(secs).ok())
+                    .ok_or_else(|| format!("line {}: invalid timeout '{}'", index + 1, value))?;
+            } else {
+                return Err(format!("line {}: unknown option '{}'", index + 1, key));
+            }
+        }
+        steps.push(step);
+    }
+
+    Ok(steps)
+}
+
+async fn run_script(
+    port_name: &str,
+    baud: u32,
+    script_path: &str,
+    json: bool,
+    default_timeout: Duration,
+) -> Result<i32, Box<dyn std::error::Error>> {
+    let steps = match parse_script(&std::fs::read_to_string(script_path)?, default_timeout) {
+        Ok(steps) => steps,
+        Err(e) => {
+            eprintln!("Invalid script {}: {}", script_path, e);
+            return Ok(2);
+        }
+    };
+
+    let port = match open_port(port_name, baud) {
+        Ok(port) => port,
+        Err(e) => {
+            eprintln!("Failed to open {}: {}", port_name, e);
+            return Ok(3);
+        }
+    };
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+
+    let (reader, mut writer) = tokio::io::split(port);
+    let mut reader = BufReader::new(reader);
+
+    // Drain startup messages before starting the sequence
+    let mut startup_lines = 0;
+    let start_time = Instant::now();
+    while start_time.elapsed() < Duration::from_secs(3) {
+        let mut line = String::new();
+        match tokio::time::timeout(Duration::from_millis(200), reader.read_line(&mut line)).await {
+            Ok(Ok(bytes_read)) if bytes_read > 0 => startup_lines += 1,
+            Ok(Err(_)) => break,
+            _ => continue,
+        }
+    }
+
+    let mut results = Vec::new();
+    for step in &steps {
+        let result = run_step(step, &mut reader, &mut writer).await;
+        if !json {
+            println!(
+                "[{}] line {:>3}: <{}> ({} ms){}",
+                if result.passed { "PASS" } else { "FAIL" },
+                result.line,
+                result.command,
+                result.duration_ms,
+                result.error.as_ref().map(|e| format!(" - {}", e)).unwrap_or_default()
+            );
+        }
+        results.push(result);
+    }
+
+    let failed = results.iter().filter(|r| !r.passed).count();
+    let report = ScriptReport {
+        port: port_name.to_string(),
+        baud,
+        passed: results.len() - failed,
+        failed,
+        startup_lines,
+        steps: results,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{} passed, {} failed", report.passed, report.failed);
+    }
+
+    Ok(if failed == 0 { 0 } else { 1 })
+}
+
+async fn run_step(
+    step: &ScriptStep,
+    reader: &mut BufReader<ReadHalf<SerialStream>>,
+    writer: &mut WriteHalf<SerialStream>,
+) -> StepResult {
+    let start = Instant::now();
+    let mut result = StepResult {
+        line: step.line,
+        command: step.command.clone(),
+        passed: false,
+        duration_ms: 0,
+        acknowledged: false,
+        response: None,
+        error: None,
+    };
+
+    let command_str = format!("<{}>\n", step.command);
+    if let Err(e) = async {
+        writer.write_all(command_str.as_bytes()).await?;
+        writer.flush().await
+    }
+    .await
+    {
+        result.error = Some(format!("write failed: {}", e));
+        return result;
+    }
+
+    // Wait for the data response; ACKs and non-JSON chatter are skipped
+    while start.elapsed() < step.timeout {
+        let mut line = String::new();
+        let remaining = step.timeout.saturating_sub(start.elapsed());
+        match tokio::time::timeout(remaining, reader.read_line(&mut line)).await {
+            Ok(Ok(0)) => {
+                result.error = Some("device disconnected".to_string());
+                break;
+            }
+            Ok(Ok(_)) => {
+                let Ok(value) = serde_json::from_str::<serde_json::Value>(line.trim()) else {
+                    continue;
+                };
+                match value.get("status").and_then(|s| s.as_str()) {
+                    Some("ack") => result.acknowledged = true,
+                    Some("ok") => {
+                        result.error = check_expectations(&value, &step.expectations);
+                        result.passed = result.error.is_none();
+                        result.response = Some(value);
+                        break;
+                    }
+                    Some("error") => {
+                        result.error = Some(
+                            value.get("message").and_then(|m| m.as_str()).unwrap_or("device error").to_string(),
+                        );
+                        result.response = Some(value);
+                        break;
+                    }
+                    _ => continue,
+                }
+            }
+            Ok(Err(e)) => {
+                result.error = Some(format!("read failed: {}", e));
+                break;
+            }
+            Err(_) => break,
+        }
+    }
+
+    if !result.passed && result.error.is_none() {
+        result.error = Some(format!("no data response within {:.1}s", step.timeout.as_secs_f64()));
+    }
+    result.duration_ms = start.elapsed().as_millis() as u64;
+    result
+}
+
+// Compare expected data fields against the response ("data.parked" and "parked" are equivalent)
+fn check_expectations(response: &serde_json::Value, expectations: &[(String, String)]) -> Option<String> {
+    let data = response.get("data").unwrap_or(response);
+    for (key, expected) in expectations {
+        let field = key.strip_prefix("data.").unwrap_or(key);
+        let actual = match data.get(field) {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => return Some(format!("missing field '{}'", field)),
+        };
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Some(format!("expected {}={}, got {}", field, expected, actual));
+        }
+    }
+    None
+}
+
+async fn run_interactive(port_arg: Option<String>, baud: u32) -> Result<(), Box<dyn std::error::Error>> {
     println!("nRF52840 Device Communication Test - With DTR/RTS Control");
     println!("=========================================================");
     
     // Get port from user
-    print!("Enter COM port (e.g. COM26): ");
-    io::stdout().flush()?;
-    let mut port_input = String::new();
-    io::stdin().read_line(&mut port_input)?;
-    let port_name = port_input.trim();
+    let port_name = match port_arg {
+        Some(port) => port,
+        None => {
+            print!("Enter COM port (e.g. COM26): ");
+            io::stdout().flush()?;
+            let mut port_input = String::new();
+            io::stdin().read_line(&mut port_input)?;
+            port_input.trim().to_string()
+        }
+    };
+    let port_name = port_name.as_str();
     
-    println!("Connecting to {} at 115200 baud...", port_name);
+    println!("Connecting to {} at {} baud...", port_name, baud);
     
     // Open serial port
-    let mut port = tokio_serial::new(port_name, 115200)
-        .timeout(Duration::from_millis(1000))
-        .data_bits(tokio_serial::DataBits::Eight)
-        .flow_control(tokio_serial::FlowControl::None)
-        .parity(tokio_serial::Parity::None)
-        .stop_bits(tokio_serial::StopBits::One)
-        .open_native_async()?;
+    let port = open_port(port_name, baud)?;
     
     println!("Port opened, setting DTR/RTS control signals...");
     
     // Try setting DTR and RTS like Arduino might
     #[cfg(windows)]
+    let mut port = port;
+    #[cfg(windows)]
     {
         use tokio_serial::SerialPort;
         match port.write_data_terminal_ready(true) {
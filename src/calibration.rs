@@ -0,0 +1,61 @@
+// src/calibration.rs
+// Calibration progress reported by the firmware while the calibrate command (06) runs
+
+use crate::device_state::FirmwareResponse;
+use crate::protocol;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CalibrationProgress {
+    // snake_case phase name, e.g. "collecting_samples"
+    pub phase: String,
+    pub percent: Option<u8>,
+}
+
+// {"status":"progress","command":"06","data":{"phase":"collecting_samples","percent":40}}
+pub fn from_response(response: &FirmwareResponse) -> Option<CalibrationProgress> {
+    if response.command.as_deref().is_some_and(|command| protocol::command_code(command) != protocol::CALIBRATE) {
+        return None;
+    }
+    let data = response.data.as_ref()?;
+    let phase = data.get("phase").and_then(|phase| phase.as_str()).unwrap_or("calibrating");
+    let percent = data.get("percent").and_then(|percent| percent.as_u64()).map(|percent| percent.min(100) as u8);
+    Some(CalibrationProgress {
+        phase: phase_name(phase),
+        percent,
+    })
+}
+
+// Plain-text progress lines, e.g. "Calibrating: collecting samples 40/100" or "Calibration 40%"
+pub fn from_text(line: &str) -> Option<CalibrationProgress> {
+    let lower = line.to_lowercase();
+    let rest = ["calibrating", "calibration"]
+        .iter()
+        .find_map(|prefix| lower.strip_prefix(prefix))?;
+
+    let mut percent = None;
+    let mut words = Vec::new();
+    for word in rest.split(|c: char| c.is_whitespace() || c == ':' || c == '.' || c == ',') {
+        if let Some(value) = word.strip_suffix('%').and_then(|value| value.parse::<u64>().ok()) {
+            percent = Some(value.min(100) as u8);
+        } else if let Some((done, total)) = word.split_once('/') {
+            if let (Ok(done), Ok(total)) = (done.parse::<u64>(), total.parse::<u64>()) {
+                percent = (done.min(total) * 100).checked_div(total).map(|value| value as u8).or(percent);
+            }
+        } else if !word.is_empty() && !word.chars().all(|c| c.is_ascii_digit()) {
+            words.push(word);
+        }
+    }
+
+    let phase = if words.is_empty() { "calibrating".to_string() } else { words.join("_") };
+    Some(CalibrationProgress { phase, percent })
+}
+
+fn phase_name(phase: &str) -> String {
+    phase
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
@@ -0,0 +1,510 @@
+// src/chat_bot.rs
+// Chat-ops bot (feature "chat-bot"): posts a device's park and safety events to a Discord channel
+// or Matrix room and answers !status, !park and !calibrate there, for club observatories whose
+// members coordinate in chat. Both platforms are polled over HTTPS; no gateway connection is kept.
+
+use crate::config::{ChatBotConfig, ChatPlatform};
+use crate::device_registry::DeviceHandle;
+use crate::errors::{BridgeError, Result};
+use crate::events::{EventKind, TelescopeLink};
+use crate::jobs::JobOperation;
+use crate::notifications::{self, Delivery, Notifications};
+use crate::timestamps;
+use reqwest::{RequestBuilder, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+const DISCORD_API: &str = "https://discord.com/api/v10";
+
+// Requests other than the Matrix long poll that take longer than this count as failed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+// The homeserver holds /sync open this long while the room is quiet
+const MATRIX_SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Pause after a failed poll; only the first failure in a row is logged as a warning
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+const COMMAND_PREFIX: char = '!';
+
+const HELP: &str = "!status - connection, park state and IsSafe\n\
+    !park - record the current position as the park position\n\
+    !calibrate - calibrate the sensor (keep the telescope still)";
+
+fn chat_error(e: impl std::fmt::Display) -> BridgeError {
+    BridgeError::ChatBot(e.to_string())
+}
+
+// Device operations run from the chat, restricted to the authorized users
+struct Operation {
+    operation: JobOperation,
+    started: &'static str,
+    finished: &'static str,
+}
+
+fn operation(name: &str) -> Option<Operation> {
+    match name {
+        "park" => Some(Operation {
+            operation: JobOperation::SetPark,
+            started: "Recording the current position as the park position...",
+            finished: "Park position set",
+        }),
+        "calibrate" => Some(Operation {
+            operation: JobOperation::Calibrate,
+            started: "Calibrating, keep the telescope still...",
+            finished: "Calibration finished",
+        }),
+        _ => None,
+    }
+}
+
+struct ChatMessage {
+    // Discord user id or Matrix user id, matched against authorized_users
+    sender: String,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct DiscordMessage {
+    id: String,
+    content: String,
+    author: DiscordUser,
+}
+
+#[derive(Deserialize)]
+struct DiscordUser {
+    id: String,
+    // Set for bots, including this one
+    #[serde(default)]
+    bot: bool,
+}
+
+#[derive(Deserialize)]
+struct MatrixSync {
+    next_batch: String,
+    #[serde(default)]
+    rooms: MatrixRooms,
+}
+
+#[derive(Default, Deserialize)]
+struct MatrixRooms {
+    #[serde(default)]
+    join: HashMap<String, MatrixRoom>,
+}
+
+#[derive(Deserialize)]
+struct MatrixRoom {
+    #[serde(default)]
+    timeline: MatrixTimeline,
+}
+
+#[derive(Default, Deserialize)]
+struct MatrixTimeline {
+    #[serde(default)]
+    events: Vec<MatrixEvent>,
+}
+
+#[derive(Deserialize)]
+struct MatrixEvent {
+    sender: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    content: serde_json::Value,
+}
+
+#[derive(Clone)]
+pub struct ChatBot {
+    config: ChatBotConfig,
+    platform: ChatPlatform,
+    base: Url,
+    token: String,
+    client: reqwest::Client,
+    notifications: Notifications,
+}
+
+// Name of the channel in /api/notifications
+pub const NOTIFICATION_CHANNEL: &str = "chat_bot";
+
+impl ChatBot {
+    pub fn new(config: ChatBotConfig) -> Result<Self> {
+        let platform = config
+            .platform
+            .ok_or_else(|| BridgeError::Config("chat_bot.platform is not set".to_string()))?;
+        let token = config.token.clone().ok_or_else(|| {
+            BridgeError::Config("chat_bot.token is not set; store it with `secrets set chat_bot.token`".to_string())
+        })?;
+        let base = config.url.as_deref().unwrap_or(DISCORD_API);
+        let base = Url::parse(base).map_err(|e| BridgeError::Config(format!("chat_bot.url {}: {}", base, e)))?;
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("telescope_park_bridge/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(chat_error)?;
+        Ok(Self {
+            config,
+            platform,
+            base,
+            token,
+            client,
+            notifications: Notifications::default(),
+        })
+    }
+
+    // Snoozes and duplicate suppression shared with /api/notifications
+    pub fn with_notifications(mut self, notifications: Notifications) -> Self {
+        notifications.register(NOTIFICATION_CHANNEL);
+        self.notifications = notifications;
+        self
+    }
+
+    // Post events until cancelled while answering commands from the channel
+    pub async fn run(self, device: DeviceHandle, cancel_token: CancellationToken) {
+        info!(
+            "{} chat bot posting device {} events to {}",
+            self.platform, device.device_number, self.config.channel
+        );
+        if self.config.authorized_users.is_empty() {
+            info!("No chat_bot.authorized_users; !park and !calibrate are refused");
+        }
+        tokio::join!(self.announce(&device, &cancel_token), self.listen(&device, &cancel_token));
+        debug!("Chat bot stopped");
+    }
+
+    pub async fn post(&self, text: &str) -> Result<()> {
+        let channel = self.config.channel.as_str();
+        let request = match self.platform {
+            ChatPlatform::Discord => self
+                .client
+                .post(self.endpoint(&["channels", channel, "messages"]))
+                .json(&json!({ "content": text })),
+            ChatPlatform::Matrix => {
+                // The transaction id lets the homeserver drop a resent duplicate
+                let transaction = uuid::Uuid::new_v4().simple().to_string();
+                let path = ["_matrix", "client", "v3", "rooms", channel, "send", "m.room.message", &transaction];
+                // Notices are the message type meant for bots; other bots do not answer them
+                self.client
+                    .put(self.endpoint(&path))
+                    .json(&json!({ "msgtype": "m.notice", "body": text }))
+            }
+        };
+        self.request::<serde_json::Value>(request).await.map(|_| ())
+    }
+
+    async fn announce(&self, device: &DeviceHandle, cancel_token: &CancellationToken) {
+        let mut events = device.connection_manager.event_bus().subscribe();
+        loop {
+            let event = tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                received = events.recv() => match received {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Chat bot fell behind, {} events not posted", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+            };
+            // Alerts are held back while someone works on the mount; its start and end still go out
+            let maintenance_change = matches!(event.kind, EventKind::MaintenanceStarted { .. } | EventKind::MaintenanceEnded { .. });
+            if !maintenance_change && device.device_state.read().await.active_maintenance().is_some() {
+                debug!("Not posting event {} during maintenance", event.id);
+                continue;
+            }
+            let message = announcement(&event.kind);
+            let text = match self.notifications.template(&self.config.templates, event.kind.type_name(), message.is_some()) {
+                Some(template) => {
+                    let variables = notifications::event_variables(&event, device, message.as_deref().unwrap_or_default()).await;
+                    Some(notifications::render_template(template, &variables))
+                }
+                None => message,
+            };
+            if let Some(mut text) = text {
+                match self.notifications.deliver(NOTIFICATION_CHANNEL, &text, unix_now()) {
+                    Delivery::Send { held_back: 0 } => {}
+                    Delivery::Send { held_back } => {
+                        text.push_str(&format!(" (repeated {} more time(s) since last posted)", held_back));
+                    }
+                    Delivery::Snoozed | Delivery::Duplicate => {
+                        debug!("Not posting event {}: {:?}", event.id, text);
+                        continue;
+                    }
+                }
+                if let Err(e) = self.post(&text).await {
+                    warn!("Cannot post event {} to {}: {}", event.id, self.platform, e);
+                }
+            }
+        }
+    }
+
+    async fn listen(&self, device: &DeviceHandle, cancel_token: &CancellationToken) {
+        // Where the last poll left off; the first poll only skips the channel's history
+        let mut cursor = None;
+        let mut failing = false;
+        loop {
+            let polled = tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                polled = self.poll(&mut cursor) => polled,
+            };
+            match polled {
+                Ok(messages) => {
+                    if failing {
+                        info!("{} reachable again", self.platform);
+                        failing = false;
+                    }
+                    for message in messages {
+                        self.handle(message, device, cancel_token).await;
+                    }
+                }
+                Err(e) if !failing => {
+                    warn!("Cannot read {} messages: {}; retrying every {}s", self.platform, e, RETRY_INTERVAL.as_secs());
+                    failing = true;
+                }
+                Err(e) => debug!("{} poll failed again: {}", self.platform, e),
+            }
+            let pause = match (failing, self.platform) {
+                (true, _) => RETRY_INTERVAL,
+                (false, ChatPlatform::Discord) => Duration::from_secs(self.config.poll_interval_secs),
+                // /sync already waited on the server
+                (false, ChatPlatform::Matrix) => Duration::ZERO,
+            };
+            tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                _ = tokio::time::sleep(pause) => {}
+            }
+        }
+    }
+
+    async fn handle(&self, message: ChatMessage, device: &DeviceHandle, cancel_token: &CancellationToken) {
+        let Some(command) = message.text.trim().strip_prefix(COMMAND_PREFIX) else {
+            return;
+        };
+        // Unknown commands are left alone, they may be meant for another bot in the channel
+        let command = command.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+        let reply = match (command.as_str(), operation(&command)) {
+            ("status", _) => status_text(device).await,
+            ("help", _) => HELP.to_string(),
+            (_, Some(_)) if !self.config.authorized_users.contains(&message.sender) => {
+                info!("Chat command !{} from {} refused: not in chat_bot.authorized_users", command, message.sender);
+                format!("{} may not run !{}", message.sender, command)
+            }
+            (_, Some(operation)) => {
+                info!("Chat command !{} from {}", command, message.sender);
+                let started = operation.started.to_string();
+                self.start(operation, device, cancel_token.clone());
+                started
+            }
+            _ => return,
+        };
+        if let Err(e) = self.post(&reply).await {
+            warn!("Cannot answer !{} on {}: {}", command, self.platform, e);
+        }
+    }
+
+    // Run the operation in the background, so calibration does not hold up other commands; on
+    // shutdown its command is abandoned and nothing is posted
+    fn start(&self, operation: Operation, device: &DeviceHandle, cancel_token: CancellationToken) {
+        let bot = self.clone();
+        let manager = device.connection_manager.clone();
+        tokio::spawn(async move {
+            let reply = match operation.operation.run(&manager, &cancel_token).await {
+                Ok(_) => operation.finished.to_string(),
+                Err(_) if cancel_token.is_cancelled() => return,
+                Err(e) => format!("Failed: {}", e),
+            };
+            if let Err(e) = bot.post(&reply).await {
+                warn!("Cannot post the result of {:?} to {}: {}", operation.operation, bot.platform, e);
+            }
+        });
+    }
+
+    // New messages since the cursor, oldest first
+    async fn poll(&self, cursor: &mut Option<String>) -> Result<Vec<ChatMessage>> {
+        match self.platform {
+            ChatPlatform::Discord => self.poll_discord(cursor).await,
+            ChatPlatform::Matrix => self.poll_matrix(cursor).await,
+        }
+    }
+
+    async fn poll_discord(&self, cursor: &mut Option<String>) -> Result<Vec<ChatMessage>> {
+        let query = match cursor.as_deref() {
+            Some(after) => vec![("after", after.to_string()), ("limit", "50".to_string())],
+            None => vec![("limit", "1".to_string())],
+        };
+        let url = self.endpoint(&["channels", &self.config.channel, "messages"]);
+        let mut messages: Vec<DiscordMessage> = self.request(self.client.get(url).query(&query)).await?;
+        // Snowflake ids grow with time; compare them as numbers
+        messages.sort_by_key(|message| message.id.parse::<u64>().unwrap_or_default());
+        let newest = messages.last().map(|message| message.id.clone());
+        if cursor.is_none() {
+            *cursor = Some(newest.unwrap_or_else(|| "0".to_string()));
+            return Ok(Vec::new());
+        }
+        if newest.is_some() {
+            *cursor = newest;
+        }
+        Ok(messages
+            .into_iter()
+            .filter(|message| !message.author.bot)
+            .map(|message| ChatMessage {
+                sender: message.author.id,
+                text: message.content,
+            })
+            .collect())
+    }
+
+    async fn poll_matrix(&self, cursor: &mut Option<String>) -> Result<Vec<ChatMessage>> {
+        let filter = json!({
+            "room": {
+                "rooms": [self.config.channel],
+                "timeline": { "types": ["m.room.message"] },
+            },
+            "presence": { "types": [] },
+            "account_data": { "types": [] },
+        });
+        let wait = if cursor.is_some() { MATRIX_SYNC_TIMEOUT } else { Duration::ZERO };
+        let mut query = vec![("filter", filter.to_string()), ("timeout", wait.as_millis().to_string())];
+        if let Some(since) = cursor.as_deref() {
+            query.push(("since", since.to_string()));
+        }
+        let url = self.endpoint(&["_matrix", "client", "v3", "sync"]);
+        let request = self.client.get(url).query(&query).timeout(wait + REQUEST_TIMEOUT);
+        let mut sync: MatrixSync = self.request(request).await?;
+        if cursor.replace(sync.next_batch).is_none() {
+            return Ok(Vec::new());
+        }
+        let Some(room) = sync.rooms.join.remove(&self.config.channel) else {
+            return Ok(Vec::new());
+        };
+        // The bot's own posts are notices, so only text messages can be commands
+        Ok(room
+            .timeline
+            .events
+            .into_iter()
+            .filter(|event| event.kind == "m.room.message" && event.content["msgtype"] == "m.text")
+            .filter_map(|event| {
+                Some(ChatMessage {
+                    text: event.content["body"].as_str()?.to_string(),
+                    sender: event.sender,
+                })
+            })
+            .collect())
+    }
+
+    // The base URL with path segments appended; room ids are passed through as path segments
+    fn endpoint(&self, segments: &[&str]) -> Url {
+        let mut url = self.base.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        url
+    }
+
+    async fn request<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        let request = match self.platform {
+            ChatPlatform::Discord => request.header(reqwest::header::AUTHORIZATION, format!("Bot {}", self.token)),
+            ChatPlatform::Matrix => request.bearer_auth(&self.token),
+        };
+        let response = request.send().await.map_err(chat_error)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(BridgeError::ChatBot(format!("{} answered {}", self.platform, status)));
+        }
+        response.json().await.map_err(chat_error)
+    }
+}
+
+async fn status_text(device: &DeviceHandle) -> String {
+    let max_data_age = device.connection_manager.max_data_age_secs();
+    let state = device.device_state.read().await;
+    let is_safe = if state.is_safe_now(max_data_age) { "safe" } else { "unsafe" };
+    let mut text = format!(
+        "Device {}: {} | {} | IsSafe: {}",
+        device.device_number,
+        state.connection_summary(max_data_age),
+        state.park_status_summary(),
+        is_safe
+    );
+    if let Some(safety_override) = state.active_override() {
+        text += &format!(" (forced until {}", timestamps::format_secs(safety_override.expires_at));
+        if let Some(reason) = &safety_override.reason {
+            text += &format!(": {}", reason);
+        }
+        text.push(')');
+    }
+    if let Some(maintenance) = state.active_maintenance() {
+        text += &format!(" | Maintenance until {}", timestamps::format_secs(maintenance.expires_at));
+    }
+    text
+}
+
+// Chat text for the events worth telling the observers about
+fn announcement(kind: &EventKind) -> Option<String> {
+    let switched = |on: bool| if on { "on" } else { "off" };
+    let safe = |is_safe: bool| if is_safe { "safe" } else { "unsafe" };
+    Some(match kind {
+        // The event's pitch and roll can lag behind the park flag by a position poll, so they are left out
+        EventKind::ParkStateChanged { parked: true, .. } => "Telescope parked".to_string(),
+        EventKind::ParkStateChanged { parked: false, .. } => "Telescope left the park position".to_string(),
+        EventKind::SafetyForced { is_safe, expires_at, reason, .. } => {
+            let reason = reason.as_deref().map(|reason| format!(": {}", reason)).unwrap_or_default();
+            format!("IsSafe forced {} until {}{}", safe(*is_safe), timestamps::format_secs(*expires_at), reason)
+        }
+        EventKind::SafetyForceCleared { expired, .. } => {
+            let ended = if *expired { "expired" } else { "cleared" };
+            format!("Forced IsSafe {}; the sensor decides again", ended)
+        }
+        EventKind::SunAltitudeCrossed { above: true, altitude, forces_unsafe, .. } => {
+            let effect = if *forces_unsafe { ", IsSafe is false until it sets" } else { "" };
+            format!("The Sun is up ({:.1}°){}", altitude, effect)
+        }
+        EventKind::SunAltitudeCrossed { above: false, altitude, .. } => format!("The Sun has set ({:.1}°)", altitude),
+        EventKind::MaintenanceStarted { expires_at, reason, .. } => {
+            let reason = reason.as_deref().map(|reason| format!(": {}", reason)).unwrap_or_default();
+            format!("Maintenance mode until {}{}; alerts are held back", timestamps::format_secs(*expires_at), reason)
+        }
+        EventKind::MaintenanceEnded { expired, .. } => {
+            let ended = if *expired { "expired" } else { "ended" };
+            format!("Maintenance mode {}; alerts resume", ended)
+        }
+        EventKind::SafetyScheduleChanged { schedule, active: true, .. } => {
+            format!("Safety schedule {} began; IsSafe is false until it ends", schedule)
+        }
+        EventKind::SafetyScheduleChanged { schedule, active: false, .. } => format!("Safety schedule {} ended", schedule),
+        EventKind::SensorDisagreement { is_safe, votes, .. } => {
+            let safe_votes = votes.iter().filter(|vote| vote.is_safe).count();
+            format!("Sensors disagree: {} of {} report safe, IsSafe is {}", safe_votes, votes.len(), safe(*is_safe))
+        }
+        EventKind::FirmwareRebooted { .. } => "Sensor firmware rebooted".to_string(),
+        EventKind::HealthWarning { message, .. } => format!("Sensor health warning: {}", message),
+        EventKind::DeviceReset { port, .. } => format!("Sensor on {} reset after repeated connection failures", port),
+        EventKind::RelaySwitched { relay, on, error: None, .. } => format!("Relay {} switched {}", relay, switched(*on)),
+        EventKind::RelaySwitched { relay, on, error: Some(error), .. } => {
+            format!("Relay {} could not be switched {}: {}", relay, switched(*on), error)
+        }
+        EventKind::TelescopeLinkChanged { telescope, state: TelescopeLink::Connected, .. } => {
+            format!("Mount {} connected", telescope)
+        }
+        EventKind::TelescopeLinkChanged { telescope, state: TelescopeLink::Lost, error, .. } => {
+            format!("Lost the connection to mount {}: {}", telescope, error.as_deref().unwrap_or("unknown error"))
+        }
+        EventKind::NightlyReport { summary, .. } => summary.clone(),
+        EventKind::FirmwareEvent { .. }
+        | EventKind::UnsolicitedResponse { .. }
+        | EventKind::CalibrationProgress { .. }
+        | EventKind::TelescopeLinkChanged { .. }
+        | EventKind::TelescopeChanged { .. } => {
+            return None
+        }
+    })
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
@@ -0,0 +1,98 @@
+// src/command_history.rs
+// Transcript of recent user and ASCOM commands with their firmware replies, served at /api/command/history
+
+use crate::errors::{BridgeError, Result};
+use crate::pagination::Paginated;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandOutcome {
+    Completed,
+    TimedOut,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandRecord {
+    pub id: u64,
+    pub command: String,
+    // Milliseconds since the Unix epoch at which the command was written; RFC 3339 as "sent_at"
+    #[serde(rename = "sent_at", serialize_with = "crate::timestamps::rfc3339_millis::serialize")]
+    pub sent_at_ms: u64,
+    pub ack: Option<String>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub duration_ms: f64,
+    pub outcome: CommandOutcome,
+}
+
+impl Paginated for CommandRecord {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn timestamp_secs(&self) -> u64 {
+        self.sent_at_ms / 1000
+    }
+}
+
+#[derive(Clone)]
+pub struct CommandHistory {
+    capacity: usize,
+    entries: Arc<Mutex<VecDeque<CommandRecord>>>,
+}
+
+impl CommandHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    // Log a finished command; `duration` runs from the write to the final reply or failure
+    pub fn record(&self, command: &str, duration: Duration, ack: Option<String>, result: &Result<String>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let (response, error, outcome) = match result {
+            Ok(response) => (Some(response.clone()), None, CommandOutcome::Completed),
+            Err(BridgeError::Timeout) => (None, Some(BridgeError::Timeout.to_string()), CommandOutcome::TimedOut),
+            Err(BridgeError::Cancelled) => (None, Some(BridgeError::Cancelled.to_string()), CommandOutcome::Cancelled),
+            Err(e) => (None, Some(e.to_string()), CommandOutcome::Failed),
+        };
+        let sent_at = SystemTime::now().checked_sub(duration).unwrap_or_else(SystemTime::now);
+
+        let mut entries = self.entries.lock().unwrap();
+        let id = entries.back().map_or(1, |last| last.id + 1);
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(CommandRecord {
+            id,
+            command: command.to_string(),
+            sent_at_ms: sent_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64,
+            ack,
+            response,
+            error,
+            duration_ms: duration.as_secs_f64() * 1000.0,
+            outcome,
+        });
+    }
+
+    // Oldest first
+    pub fn entries(&self) -> Vec<CommandRecord> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+impl Default for CommandHistory {
+    fn default() -> Self {
+        Self::new(crate::config::CommandHistoryConfig::default().size)
+    }
+}
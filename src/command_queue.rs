@@ -0,0 +1,337 @@
+// src/command_queue.rs
+// Prioritized serial command queue: user/ASCOM commands preempt periodic polls,
+// identical queued polls are coalesced and writes are spaced by a minimum gap
+
+use crate::errors::{BridgeError, Result};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, Notify};
+use tracing::debug;
+
+// Slow firmware builds drop commands that arrive back-to-back
+pub const DEFAULT_MIN_COMMAND_GAP: Duration = Duration::from_millis(50);
+
+// Higher priorities are always sent first; FIFO within a priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CommandPriority {
+    Poll,
+    User,
+}
+
+#[derive(Debug)]
+pub struct QueuedCommand {
+    // Unique within the queue; 0 for polls, which are never cancelled
+    pub id: u64,
+    pub command: String,
+    pub priority: CommandPriority,
+    // How long the data response may take once the command has been written
+    pub timeout: Duration,
+    // None for fire-and-forget polls whose responses only update the device state
+    pub response_sender: Option<oneshot::Sender<Result<String>>>,
+    // False for the bridge's own checks, which report elsewhere and stay out of the command history
+    pub recorded: bool,
+    // Span of the request that queued the command, re-entered for the serial exchange
+    pub span: tracing::Span,
+}
+
+// Request to abandon a command the serial task has already written
+#[derive(Debug, Clone)]
+pub struct Cancellation {
+    pub command: String,
+    // The exact command to abandon; None for the oldest one with this code
+    pub id: Option<u64>,
+    // Firmware abort code written after the command is abandoned
+    pub abort_command: Option<String>,
+}
+
+impl Cancellation {
+    pub fn matches(&self, id: u64, command: &str) -> bool {
+        match self.id {
+            Some(cancelled) => cancelled == id,
+            None => self.command == command,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CommandQueue {
+    entries: Arc<Mutex<VecDeque<QueuedCommand>>>,
+    notify: Arc<Notify>,
+    cancellations: Arc<Mutex<VecDeque<Cancellation>>>,
+    cancel_notify: Arc<Notify>,
+    next_id: Arc<AtomicU64>,
+    min_gap: Duration,
+}
+
+impl Default for CommandQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandQueue {
+    pub fn new() -> Self {
+        Self::with_min_gap(DEFAULT_MIN_COMMAND_GAP)
+    }
+
+    pub fn with_min_gap(min_gap: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::new())),
+            notify: Arc::new(Notify::new()),
+            cancellations: Arc::new(Mutex::new(VecDeque::new())),
+            cancel_notify: Arc::new(Notify::new()),
+            next_id: Arc::new(AtomicU64::new(0)),
+            min_gap,
+        }
+    }
+
+    pub fn min_gap(&self) -> Duration {
+        self.min_gap
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Queue a user/ASCOM command and return its id, for cancel_id(), and the receiver for its
+    // data response
+    pub fn push_user(&self, command: &str, timeout: Duration) -> (u64, oneshot::Receiver<Result<String>>) {
+        self.push_awaited(command, timeout, true)
+    }
+
+    // Same as push_user() for the bridge's own queries, left out of the command history
+    pub fn push_internal(&self, command: &str, timeout: Duration) -> oneshot::Receiver<Result<String>> {
+        self.push_awaited(command, timeout, false).1
+    }
+
+    fn push_awaited(&self, command: &str, timeout: Duration, recorded: bool) -> (u64, oneshot::Receiver<Result<String>>) {
+        let (response_sender, response_receiver) = oneshot::channel();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.push(QueuedCommand {
+            id,
+            command: command.to_string(),
+            priority: CommandPriority::User,
+            timeout,
+            response_sender: Some(response_sender),
+            recorded,
+            span: tracing::Span::current(),
+        });
+        (id, response_receiver)
+    }
+
+    // Queue a periodic poll unless the same poll is still waiting to be sent
+    pub fn push_poll(&self, command: &str) -> bool {
+        {
+            let entries = self.entries.lock().unwrap();
+            let already_queued = entries
+                .iter()
+                .any(|entry| entry.priority == CommandPriority::Poll && entry.command == command);
+            if already_queued {
+                debug!("Coalescing poll {} with one already queued", command);
+                return false;
+            }
+        }
+        self.push(QueuedCommand {
+            id: 0,
+            command: command.to_string(),
+            priority: CommandPriority::Poll,
+            timeout: crate::protocol::default_timeout(command),
+            response_sender: None,
+            recorded: false,
+            span: tracing::Span::none(),
+        });
+        true
+    }
+
+    pub fn push(&self, queued: QueuedCommand) {
+        {
+            let mut entries = self.entries.lock().unwrap();
+            // Insert behind the last entry of equal or higher priority
+            let position = entries
+                .iter()
+                .rposition(|entry| entry.priority >= queued.priority)
+                .map(|index| index + 1)
+                .unwrap_or(0);
+            entries.insert(position, queued);
+        }
+        self.notify.notify_one();
+    }
+
+    pub fn pop(&self) -> Option<QueuedCommand> {
+        self.entries.lock().unwrap().pop_front()
+    }
+
+    // Wait for the next command; cancel-safe, nothing is removed until it is returned
+    pub async fn next(&self) -> QueuedCommand {
+        loop {
+            let notified = self.notify.notified();
+            if let Some(queued) = self.pop() {
+                return queued;
+            }
+            notified.await;
+        }
+    }
+
+    // Cancel the oldest user command with this code: dropped if still queued, otherwise handed
+    // to the serial task to abandon (writing the abort code, if any)
+    pub fn cancel(&self, command: &str, abort_command: Option<String>) {
+        self.cancel_entry(Cancellation {
+            command: command.to_string(),
+            id: None,
+            abort_command,
+        });
+    }
+
+    // Cancel the command push_user() returned this id for, the same way. Nothing happens once
+    // its response has been handed over, so a later command with the same code is never hit
+    pub fn cancel_id(&self, id: u64, command: &str, abort_command: Option<String>) {
+        self.cancel_entry(Cancellation {
+            command: command.to_string(),
+            id: Some(id),
+            abort_command,
+        });
+    }
+
+    // Guard that calls cancel_id() unless disarmed, for callers whose wait may be dropped halfway
+    pub fn cancel_on_drop(&self, id: u64, command: &str, abort_command: Option<String>) -> CancelOnDrop {
+        CancelOnDrop {
+            queue: self.clone(),
+            id,
+            command: command.to_string(),
+            abort_command,
+            armed: true,
+        }
+    }
+
+    fn cancel_entry(&self, cancellation: Cancellation) {
+        let queued = {
+            let mut entries = self.entries.lock().unwrap();
+            entries
+                .iter()
+                .position(|entry| entry.priority == CommandPriority::User && cancellation.matches(entry.id, &entry.command))
+                .and_then(|index| entries.remove(index))
+        };
+        if let Some(queued) = queued {
+            debug!("Cancelled queued command {}", queued.command);
+            if let Some(sender) = queued.response_sender {
+                let _ = sender.send(Err(BridgeError::Cancelled));
+            }
+            return;
+        }
+        self.cancellations.lock().unwrap().push_back(cancellation);
+        self.cancel_notify.notify_one();
+    }
+
+    // Wait for the next cancellation of an in-flight command; cancel-safe like next()
+    pub async fn next_cancellation(&self) -> Cancellation {
+        loop {
+            let notified = self.cancel_notify.notified();
+            if let Some(cancellation) = self.cancellations.lock().unwrap().pop_front() {
+                return cancellation;
+            }
+            notified.await;
+        }
+    }
+
+    // Fail everything still queued, e.g. when the serial connection goes away
+    pub fn fail_all(&self, reason: &str) {
+        self.cancellations.lock().unwrap().clear();
+        let drained: Vec<QueuedCommand> = self.entries.lock().unwrap().drain(..).collect();
+        for queued in drained {
+            if let Some(sender) = queued.response_sender {
+                debug!("Failing queued command {}: {}", queued.command, reason);
+                let _ = sender.send(Err(BridgeError::Device(reason.to_string())));
+            }
+        }
+    }
+}
+
+// Cancels an awaited command when dropped, e.g. with the handler future of an HTTP request whose
+// client went away, so the command stops holding back the queue until its timeout
+pub struct CancelOnDrop {
+    queue: CommandQueue,
+    id: u64,
+    command: String,
+    abort_command: Option<String>,
+    armed: bool,
+}
+
+impl CancelOnDrop {
+    // The response arrived; nothing is left to cancel
+    pub fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if self.armed {
+            debug!("Command {} abandoned by its caller", self.command);
+            self.queue.cancel_id(self.id, &self.command, self.abort_command.take());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(1);
+
+    fn commands(queue: &CommandQueue) -> Vec<String> {
+        std::iter::from_fn(|| queue.pop()).map(|queued| queued.command).collect()
+    }
+
+    #[test]
+    fn user_commands_jump_ahead_of_queued_polls() {
+        let queue = CommandQueue::new();
+        queue.push_poll("01");
+        queue.push_poll("03");
+        queue.push_user("0A", TIMEOUT);
+        assert_eq!(commands(&queue), ["0A", "01", "03"]);
+    }
+
+    #[test]
+    fn commands_of_one_priority_keep_their_order() {
+        let queue = CommandQueue::new();
+        queue.push_poll("01");
+        queue.push_user("04", TIMEOUT);
+        queue.push_poll("03");
+        queue.push_user("0A", TIMEOUT);
+        queue.push_internal("08", TIMEOUT);
+        assert_eq!(commands(&queue), ["04", "0A", "08", "01", "03"]);
+    }
+
+    #[test]
+    fn identical_queued_polls_are_coalesced() {
+        let queue = CommandQueue::new();
+        assert!(queue.push_poll("01"));
+        assert!(!queue.push_poll("01"));
+        assert!(queue.push_poll("03"));
+        assert_eq!(queue.len(), 2);
+        // Once sent, the same poll can be queued again
+        assert_eq!(queue.pop().unwrap().command, "01");
+        assert!(queue.push_poll("01"));
+    }
+
+    #[tokio::test]
+    async fn cancel_id_removes_a_queued_command() {
+        let queue = CommandQueue::new();
+        let (first, first_response) = queue.push_user("0A", TIMEOUT);
+        let (_, mut second_response) = queue.push_user("0A", TIMEOUT);
+        queue.cancel_id(first, "0A", None);
+        assert!(matches!(first_response.await, Ok(Err(BridgeError::Cancelled))));
+        // Still queued, so there is nothing for the serial task to abandon
+        assert!(queue.cancellations.lock().unwrap().is_empty());
+        // Only that command: the other one with the same code stays queued and unanswered
+        assert_eq!(queue.len(), 1);
+        assert!(second_response.try_recv().is_err());
+        assert_ne!(queue.pop().unwrap().id, first);
+    }
+}
@@ -0,0 +1,2150 @@
+// src/config.rs
+// Optional TOML configuration file loaded with --config
+
+use crate::config_migrations::{self, CURRENT_SCHEMA_VERSION};
+use crate::device_state::DEFAULT_MAX_DATA_AGE_SECS;
+use crate::device_transport::TCP_PREFIX;
+use crate::errors::{BridgeError, Result};
+use crate::events::EVENT_TYPES;
+use crate::notifications;
+use crate::protocol::{self, CommandTimeouts};
+use crate::secrets::{SecretStore, SECRET_NAMES};
+use crate::timestamps::DisplayTimezone;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BridgeConfig {
+    // Layout version of the file; older files are upgraded on load (see config_migrations)
+    #[serde(default = "current_schema_version")]
+    pub schema_version: u32,
+    // Seconds to wait for a command's data response, keyed by command code, e.g. "06" = 45
+    pub command_timeouts: HashMap<String, f64>,
+    pub heartbeat: HeartbeatConfig,
+    pub serial: SerialConfig,
+    pub framing: FramingConfig,
+    pub reconnect: ReconnectConfig,
+    pub auto_reset: AutoResetConfig,
+    pub discovery: DiscoveryConfig,
+    pub safety: SafetyConfig,
+    pub safety_force: SafetyForceConfig,
+    pub maintenance: MaintenanceConfig,
+    pub sun_safety: SunSafetyConfig,
+    // Recurring windows in which IsSafe is false whatever the sensor says
+    pub safety_schedules: Vec<SafetyScheduleConfig>,
+    pub health: HealthConfig,
+    pub set_park: SetParkConfig,
+    pub temperature_compensation: TemperatureCompensationConfig,
+    pub identity: IdentityConfig,
+    pub command_api: CommandApiConfig,
+    pub command_history: CommandHistoryConfig,
+    // Several sensors served by one bridge; empty means a single device 0 set up from the command line
+    pub devices: Vec<DeviceConfig>,
+    // USB device the command-line device 0 attaches to wherever it appears; [devices.usb] with [[devices]]
+    pub usb: Option<UsbMatch>,
+    // JSON-lines file receiving every Alpaca device API transaction (--transaction-log)
+    pub transaction_log: Option<String>,
+    // Log every HTTP request with its request id, status and latency at INFO level (--access-log)
+    pub access_log: bool,
+    // Check every firmware reply against the fields and types of its protocol version and count
+    // the mismatches at /api/firmware/schema (--strict-schema), for firmware development
+    pub strict_schema: bool,
+    // Zone of the RFC 3339 timestamps in API responses and events: "utc", "local" or an IANA name
+    pub display_timezone: DisplayTimezone,
+    pub http: HttpConfig,
+    pub gpio: GpioConfig,
+    // Relays switched when a device's IsSafe changes (cargo feature relays)
+    pub relays: Vec<RelayConfig>,
+    pub serial_tee: SerialTeeConfig,
+    pub indi: IndiConfig,
+    pub chat_bot: ChatBotConfig,
+    pub notifications: NotificationsConfig,
+    // Alpaca Telescopes the sensors sit on, keyed by id (cargo feature telescope-client)
+    pub telescopes: Vec<TelescopeConfig>,
+    pub web_auth: WebAuthConfig,
+    pub events: EventsConfig,
+    pub backup: BackupConfig,
+    pub nightly_report: NightlyReportConfig,
+    pub mdns: MdnsConfig,
+    pub logging: LoggingConfig,
+}
+
+fn current_schema_version() -> u32 {
+    CURRENT_SCHEMA_VERSION
+}
+
+// Upper bounds that catch unit mix-ups (milliseconds typed into a seconds field and the like)
+const MAX_INTERVAL_SECS: u64 = 3600;
+const MAX_COMMAND_TIMEOUT_SECS: f64 = 600.0;
+const MAX_DATA_AGE_LIMIT_SECS: u64 = 86_400;
+const MIN_BAUD: u32 = 300;
+const MAX_BAUD: u32 = 4_000_000;
+
+// One problem with a setting, named by its path in the file, e.g. "devices[1].port"
+#[derive(Debug, Clone)]
+pub struct ConfigIssue {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConfigIssues(Vec<ConfigIssue>);
+
+impl ConfigIssues {
+    pub fn push(&mut self, field: &str, message: impl Into<String>) {
+        self.0.push(ConfigIssue {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ConfigIssue> {
+        self.0.iter()
+    }
+}
+
+// "2 problems:" followed by one "  - field: message" line per issue
+impl std::fmt::Display for ConfigIssues {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let count = self.0.len();
+        write!(f, "{} problem{}:", count, if count == 1 { "" } else { "s" })?;
+        for issue in &self.0 {
+            write!(f, "\n  - {}: {}", issue.field, issue.message)?;
+        }
+        Ok(())
+    }
+}
+
+fn is_command_code(command: &str) -> bool {
+    command.len() == 2 && command.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub fn check_baud(baud: u32) -> std::result::Result<(), String> {
+    if (MIN_BAUD..=MAX_BAUD).contains(&baud) {
+        Ok(())
+    } else {
+        Err(format!("{} is not a usable baud rate; must be between {} and {}", baud, MIN_BAUD, MAX_BAUD))
+    }
+}
+
+// Serial port names as the OS spells them: COM3 (or \\.\COM12) on Windows, a device path elsewhere;
+// or tcp://host:port for a serial server
+pub fn check_port_syntax(port: &str) -> std::result::Result<(), String> {
+    if let Some(address) = port.strip_prefix(TCP_PREFIX) {
+        return match address.rsplit_once(':') {
+            Some((host, tcp_port)) if !host.is_empty() && tcp_port.parse::<u16>().is_ok_and(|number| number > 0) => Ok(()),
+            _ => Err(format!("'{}' is not a serial server address; use tcp://host:port", port)),
+        };
+    }
+    let windows_name = port.strip_prefix(r"\\.\").unwrap_or(port);
+    let is_com_port = match (windows_name.get(..3), windows_name.get(3..)) {
+        (Some(prefix), Some(number)) => {
+            prefix.eq_ignore_ascii_case("com") && !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        }
+        _ => false,
+    };
+    if is_com_port || (port.starts_with('/') && port.len() > 1 && !port.contains(char::is_whitespace)) {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' is not a serial port name; use a device path like /dev/ttyACM0, a COM port like COM3 or tcp://host:port",
+            port
+        ))
+    }
+}
+
+// HTTP server limits; both keep hung serial or telescope operations from piling up sockets
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    // Requests without a response by then get 408 Request Timeout; 0 disables the limit
+    pub request_timeout_secs: u64,
+    // On shutdown, how long in-flight requests may finish before remaining connections are dropped
+    pub shutdown_grace_secs: u64,
+    // Serve only the JSON and Alpaca APIs, without the web UI (same as --headless)
+    pub headless: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout_secs: 60,
+            shutdown_grace_secs: 10,
+            headless: false,
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn request_timeout(&self) -> Option<Duration> {
+        (self.request_timeout_secs > 0).then(|| Duration::from_secs(self.request_timeout_secs))
+    }
+}
+
+// Read-only TCP stream of one device's serial traffic for diagnostic tools; the bridge keeps
+// the port, and anything clients send is discarded
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SerialTeeConfig {
+    // Address to listen on, e.g. "127.0.0.1:11112"; unset disables the stream (same as --serial-tee)
+    pub bind: Option<String>,
+    pub device_number: u32,
+    pub format: TeeFormat,
+    // Further connections are refused
+    pub max_clients: usize,
+}
+
+impl Default for SerialTeeConfig {
+    fn default() -> Self {
+        Self {
+            bind: None,
+            device_number: 0,
+            format: TeeFormat::Text,
+            max_clients: 4,
+        }
+    }
+}
+
+// INDI server publishing every device as a weather device for KStars/Ekos
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IndiConfig {
+    // Address to listen on, e.g. "0.0.0.0:7624" (the INDI port); unset disables the server
+    pub bind: Option<String>,
+    // Further connections are refused
+    pub max_clients: usize,
+}
+
+impl Default for IndiConfig {
+    fn default() -> Self {
+        Self {
+            bind: None,
+            max_clients: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TeeFormat {
+    // "<time> TX <line>" and "<time> RX <line>", for a terminal or nc
+    Text,
+    // JSON lines as written by --record, so a captured stream can be replayed by park-sensor-sim
+    Json,
+}
+
+pub fn check_socket_addr(addr: &str) -> std::result::Result<(), String> {
+    addr.parse::<std::net::SocketAddr>()
+        .map(|_| ())
+        .map_err(|_| format!("'{}' is not an address and port like 127.0.0.1:11112", addr))
+}
+
+// Detects half-open links where the port stays open but the device has stopped answering
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeartbeatConfig {
+    // Seconds without any received line before a ping is sent; 0 disables the heartbeat
+    pub interval_secs: u64,
+    // Consecutive unanswered pings before the link is treated as dead
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 5,
+            max_missed: 3,
+        }
+    }
+}
+
+// Control lines and flow control applied when the serial port is opened
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SerialConfig {
+    // Initial DTR and RTS levels; some USB-serial adapters hold the MCU in reset unless these are changed
+    pub dtr: bool,
+    // Ignored with hardware flow control, where the driver drives RTS
+    pub rts: bool,
+    pub flow_control: FlowControlMode,
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self {
+            dtr: true,
+            rts: false,
+            flow_control: FlowControlMode::None,
+        }
+    }
+}
+
+// How commands are wrapped on the wire and how response lines end, for firmware variants
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FramingConfig {
+    pub command_prefix: String,
+    pub command_suffix: String,
+    // Written after the suffix, e.g. "\r\n" for firmware expecting CRLF
+    pub command_terminator: String,
+    // Responses are split on this; surrounding whitespace is trimmed from each line
+    pub response_terminator: String,
+}
+
+impl Default for FramingConfig {
+    fn default() -> Self {
+        Self {
+            command_prefix: "<".to_string(),
+            command_suffix: ">".to_string(),
+            command_terminator: "\n".to_string(),
+            response_terminator: "\n".to_string(),
+        }
+    }
+}
+
+impl FramingConfig {
+    // Bytes written for a command, e.g. "<01>\n"
+    pub fn encode(&self, command: &str) -> String {
+        format!("{}{}{}{}", self.command_prefix, command, self.command_suffix, self.command_terminator)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowControlMode {
+    #[default]
+    None,
+    // XON/XOFF
+    Software,
+    // RTS/CTS
+    Hardware,
+}
+
+impl From<FlowControlMode> for tokio_serial::FlowControl {
+    fn from(mode: FlowControlMode) -> Self {
+        match mode {
+            FlowControlMode::None => tokio_serial::FlowControl::None,
+            FlowControlMode::Software => tokio_serial::FlowControl::Software,
+            FlowControlMode::Hardware => tokio_serial::FlowControl::Hardware,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectConfig {
+    // Reopen the port automatically after a serial error or heartbeat failure
+    pub enabled: bool,
+    // First retry delay in seconds, doubled after every failed attempt
+    pub initial_delay_secs: u64,
+    pub max_delay_secs: u64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            initial_delay_secs: 2,
+            max_delay_secs: 30,
+        }
+    }
+}
+
+// Serial control-line trick used to reset the sensor board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetMethod {
+    // Pulse DTR low, the usual auto-reset wiring of USB-serial boards
+    Dtr,
+    Rts,
+    // Open and close the port at 1200 baud, which restarts nRF52840 and Arduino bootloaders
+    #[serde(rename = "touch_1200")]
+    Touch1200,
+}
+
+// Resets a sensor whose firmware keeps failing to respond even after the port is reopened
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AutoResetConfig {
+    // Unset disables automatic resets
+    pub method: Option<ResetMethod>,
+    // Consecutive failed reconnects before the board is reset
+    pub after_failures: u32,
+    // Seconds to let the board boot before the port is reopened
+    pub boot_wait_secs: u64,
+}
+
+impl Default for AutoResetConfig {
+    fn default() -> Self {
+        Self {
+            method: None,
+            after_failures: 3,
+            boot_wait_secs: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    // Minimum time between two discovery replies to the same source address
+    pub min_response_interval_ms: u64,
+    // Source addresses remembered for /api/discovery/clients
+    pub max_tracked_clients: usize,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            min_response_interval_ms: 1000,
+            max_tracked_clients: 256,
+        }
+    }
+}
+
+// ASCOM clients give up on a request after a few seconds
+const MAX_REFRESH_WAIT_MS: u64 = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyConfig {
+    // Seconds without firmware data after which IsSafe reports false and the state is flagged stale
+    pub max_data_age_secs: u64,
+    // IsSafe/DeviceState reads finding data older than this trigger an immediate status poll; 0 disables it
+    pub refresh_after_secs: u64,
+    // How long such a read waits for the poll's reply before answering from the cache
+    pub refresh_wait_ms: u64,
+    // What IsSafe answers on stale data
+    pub on_stale: StaleReply,
+}
+
+// IsSafe on stale data: a plain false, or an Alpaca error for clients that want to tell a silent
+// sensor from an unsafe one. Many clients treat any Alpaca error as fatal, hence the default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaleReply {
+    #[default]
+    False,
+    Error,
+}
+
+impl StaleReply {
+    // As written in the config file
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StaleReply::False => "false",
+            StaleReply::Error => "error",
+        }
+    }
+}
+
+impl SafetyConfig {
+    pub(crate) fn check(&self, prefix: &str, issues: &mut ConfigIssues) {
+        if self.max_data_age_secs == 0 || self.max_data_age_secs > MAX_DATA_AGE_LIMIT_SECS {
+            issues.push(
+                &format!("{}.max_data_age_secs", prefix),
+                format!("must be between 1 and {}", MAX_DATA_AGE_LIMIT_SECS),
+            );
+        }
+        if self.refresh_after_secs >= self.max_data_age_secs && self.refresh_after_secs > 0 {
+            issues.push(
+                &format!("{}.refresh_after_secs", prefix),
+                format!("must be below max_data_age_secs ({}) to refresh before data goes stale, or 0 to disable it", self.max_data_age_secs),
+            );
+        }
+        if self.refresh_wait_ms > MAX_REFRESH_WAIT_MS {
+            issues.push(&format!("{}.refresh_wait_ms", prefix), format!("must be at most {}", MAX_REFRESH_WAIT_MS));
+        }
+    }
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            max_data_age_secs: DEFAULT_MAX_DATA_AGE_SECS,
+            refresh_after_secs: 5,
+            refresh_wait_ms: 1000,
+            on_stale: StaleReply::False,
+        }
+    }
+}
+
+// POST /api/safety/force, for testing client shutdown sequences; disabled until a token is set
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyForceConfig {
+    // Required as "Authorization: Bearer <token>"; better kept in the keyring (`secrets set safety_force.token`)
+    pub token: Option<String>,
+    // Longest override a single request may set
+    pub max_minutes: u64,
+}
+
+impl Default for SafetyForceConfig {
+    fn default() -> Self {
+        Self {
+            token: None,
+            max_minutes: 120,
+        }
+    }
+}
+
+impl SafetyForceConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some()
+    }
+
+    pub fn authorizes(&self, presented: &str) -> bool {
+        self.token.as_deref().is_some_and(|token| tokens_match(token, presented))
+    }
+}
+
+// Maintenance mode (/api/maintenance): chat alerts and relay switching are held back while
+// someone works on the mount, until it is ended or expires
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MaintenanceConfig {
+    // Used when a request names no duration
+    pub default_minutes: u64,
+    // Longest a single request may set
+    pub max_minutes: u64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            default_minutes: 60,
+            max_minutes: 480,
+        }
+    }
+}
+
+impl MaintenanceConfig {
+    fn check(&self, issues: &mut ConfigIssues) {
+        if self.max_minutes == 0 {
+            issues.push("maintenance.max_minutes", "must be at least 1");
+        }
+        if self.default_minutes == 0 || self.default_minutes > self.max_minutes {
+            issues.push(
+                "maintenance.default_minutes",
+                format!("must be between 1 and max_minutes ({})", self.max_minutes),
+            );
+        }
+    }
+}
+
+// Duplicate suppression, snoozes and message templates of the notification channels (/api/notifications)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    // A text already sent on a channel within this many seconds is held back; 0 sends every one
+    pub dedup_secs: u64,
+    // Longest a single snooze may last
+    pub max_snooze_minutes: u64,
+    // Message per event type (or "default"), e.g. park_state_changed = "{location}: {park_state}";
+    // a channel's own templates, such as [chat_bot.templates], take precedence
+    pub templates: HashMap<String, String>,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            dedup_secs: 600,
+            max_snooze_minutes: 720,
+            templates: HashMap::new(),
+        }
+    }
+}
+
+impl NotificationsConfig {
+    fn check(&self, issues: &mut ConfigIssues) {
+        if self.dedup_secs > 86_400 {
+            issues.push("notifications.dedup_secs", "must be at most 86400 (one day)");
+        }
+        if self.max_snooze_minutes == 0 {
+            issues.push("notifications.max_snooze_minutes", "must be at least 1");
+        }
+        check_templates("notifications.templates", &self.templates, issues);
+    }
+}
+
+// Templates keyed by event type as on /api/events, or "default"
+fn check_templates(field: &str, templates: &HashMap<String, String>, issues: &mut ConfigIssues) {
+    for (key, template) in templates {
+        let field = format!("{}.{}", field, key);
+        if key != notifications::DEFAULT_TEMPLATE && !EVENT_TYPES.contains(&key.as_str()) {
+            issues.push(&field, "is not an event type (as on /api/events) or \"default\"");
+        }
+        if let Err(message) = notifications::check_template(template) {
+            issues.push(&field, message);
+        }
+    }
+}
+
+// IsSafe false (or only a warning) while the Sun is above max_altitude at the site; off until
+// latitude and longitude are set
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SunSafetyConfig {
+    // Degrees, north and east positive
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    // Degrees above the horizon; -6 is the end of civil twilight
+    pub max_altitude: f64,
+    pub action: SunAction,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SunAction {
+    // Every device reports IsSafe false while the Sun is up
+    #[default]
+    Unsafe,
+    // Only a sun_altitude_crossed event (and chat post); IsSafe follows the sensor
+    Warn,
+}
+
+impl Default for SunSafetyConfig {
+    fn default() -> Self {
+        Self {
+            latitude: None,
+            longitude: None,
+            max_altitude: -6.0,
+            action: SunAction::Unsafe,
+        }
+    }
+}
+
+impl SunSafetyConfig {
+    // Site as (latitude, longitude), when both are set
+    pub fn site(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.site().is_some()
+    }
+
+    fn check(&self, issues: &mut ConfigIssues) {
+        if self.latitude.is_some() != self.longitude.is_some() {
+            issues.push("sun_safety", "needs both latitude and longitude");
+        }
+        if self.latitude.is_some_and(|latitude| !(-90.0..=90.0).contains(&latitude)) {
+            issues.push("sun_safety.latitude", "must be between -90 and 90");
+        }
+        if self.longitude.is_some_and(|longitude| !(-180.0..=180.0).contains(&longitude)) {
+            issues.push("sun_safety.longitude", "must be between -180 and 180");
+        }
+        if !(-18.0..=90.0).contains(&self.max_altitude) {
+            issues.push("sun_safety.max_altitude", "must be between -18 (astronomical twilight) and 90");
+        }
+    }
+}
+
+// "Never safe" window, e.g. 10:00-16:00 on weekdays; times are in the display timezone and a
+// window ending before it starts runs past midnight
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SafetyScheduleConfig {
+    // Shown in devicestate, /api/status and safety_schedule_changed events
+    pub name: String,
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+    // Days the window starts on; every day when empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    // Devices it applies to; every device when empty
+    #[serde(default)]
+    pub devices: Vec<u32>,
+}
+
+impl SafetyScheduleConfig {
+    fn check(&self, field: &str, device_numbers: &[u32], issues: &mut ConfigIssues) {
+        if self.name.trim().is_empty() {
+            issues.push(&format!("{}.name", field), "must not be empty");
+        }
+        if self.start == self.end {
+            issues.push(&format!("{}.end", field), "must differ from start");
+        }
+        for device_number in &self.devices {
+            if !device_numbers.contains(device_number) {
+                issues.push(&format!("{}.devices", field), format!("device {} is not configured", device_number));
+            }
+        }
+    }
+}
+
+// "HH:MM", stored as minutes after midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TimeOfDay(pub u32);
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(text: String) -> std::result::Result<Self, Self::Error> {
+        // Two digits after the colon, so "16:3" is not read as 16:03
+        let parsed = text.split_once(':').filter(|(_, minutes)| minutes.len() == 2).and_then(|(hours, minutes)| {
+            let (hours, minutes) = (hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?);
+            (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+        });
+        parsed.map(Self).ok_or_else(|| format!("'{}' is not a time of day like \"16:30\"", text))
+    }
+}
+
+impl std::fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Weekday {
+    // Days since Monday, as chrono's num_days_from_monday()
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+}
+
+// Compares the whole token regardless of where it differs, so timing reveals nothing
+pub fn tokens_match(expected: &str, presented: &str) -> bool {
+    expected.len() == presented.len()
+        && expected.bytes().zip(presented.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// Thresholds for sensor health warnings (events on /api/events, details at /api/health)
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthConfig {
+    pub enabled: bool,
+    // Warn when free heap falls this far below its value right after boot
+    pub heap_drop_percent: f64,
+    // Warn after this many firmware reboots within the window
+    pub reboot_warning_count: u32,
+    pub reboot_window_secs: u64,
+    // Warn when the average response latency exceeds its baseline by this factor...
+    pub latency_warning_factor: f64,
+    // ...and this absolute floor, so fast links do not warn over a few milliseconds
+    pub latency_floor_ms: f64,
+    // Warn when the average parked reading sits this share of the position tolerance away from
+    // the park definition, before false unparks start (0 disables drift detection)...
+    pub park_drift_percent: f64,
+    // ...averaged over the parked readings of the last park_drift_window_days days, once
+    // park_drift_min_days of them have readings
+    pub park_drift_window_days: u32,
+    pub park_drift_min_days: u32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            heap_drop_percent: 20.0,
+            reboot_warning_count: 3,
+            reboot_window_secs: 3600,
+            latency_warning_factor: 3.0,
+            latency_floor_ms: 250.0,
+            park_drift_percent: 50.0,
+            park_drift_window_days: 7,
+            park_drift_min_days: 3,
+        }
+    }
+}
+
+// Averaged set-park: the firmware stores whatever single reading it takes when told to set park,
+// so the bridge first samples the position, drops outliers and waits for a reading at the average
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SetParkConfig {
+    // Position readings averaged; 1 sets park from the firmware's instantaneous reading as before
+    pub samples: u32,
+    pub interval_ms: u64,
+    // Readings further than this many median absolute deviations from the median are dropped
+    pub outlier_mads: f64,
+    // Refuse to set park when the kept readings scatter more than this (degrees), e.g. while slewing
+    pub max_std_dev: f64,
+    // Set-park commands tried until the stored position lands close enough to the average
+    pub attempts: u32,
+}
+
+impl Default for SetParkConfig {
+    fn default() -> Self {
+        Self {
+            samples: 15,
+            interval_ms: 200,
+            outlier_mads: 3.0,
+            max_std_dev: 0.5,
+            attempts: 3,
+        }
+    }
+}
+
+impl SetParkConfig {
+    fn check(&self, issues: &mut ConfigIssues) {
+        if self.samples == 0 || self.samples > 200 {
+            issues.push("set_park.samples", "must be between 1 and 200");
+        }
+        if self.interval_ms < 10 || self.interval_ms > 5000 {
+            issues.push("set_park.interval_ms", "must be between 10 and 5000");
+        }
+        if !self.outlier_mads.is_finite() || self.outlier_mads <= 0.0 {
+            issues.push("set_park.outlier_mads", "must be greater than 0");
+        }
+        if !self.max_std_dev.is_finite() || self.max_std_dev <= 0.0 {
+            issues.push("set_park.max_std_dev", "must be greater than 0 degrees");
+        }
+        if self.attempts == 0 || self.attempts > 10 {
+            issues.push("set_park.attempts", "must be between 1 and 10");
+        }
+    }
+}
+
+// Correction of pitch/roll for IMU temperature, for outdoor mounts whose readings wander with the
+// seasons; needs firmware that reports "temperature" with its readings
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TemperatureCompensationConfig {
+    pub mode: CompensationMode,
+    // Coefficients mode: degrees of tilt per °C, relative to the temperature the park position
+    // was set at
+    pub pitch_per_c: f64,
+    pub roll_per_c: f64,
+    pub reference_c: f64,
+    // Learned mode: the fit is used once the parked readings cover this temperature span...
+    pub learn_min_span_c: f64,
+    // ...with at least this many readings
+    pub learn_min_samples: u64,
+}
+
+impl Default for TemperatureCompensationConfig {
+    fn default() -> Self {
+        Self {
+            mode: CompensationMode::Off,
+            pitch_per_c: 0.0,
+            roll_per_c: 0.0,
+            reference_c: 20.0,
+            learn_min_span_c: 5.0,
+            learn_min_samples: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompensationMode {
+    Off,
+    Coefficients,
+    // Fitted from parked readings against temperature since the park position was last set
+    Learned,
+}
+
+// Tilt change per °C beyond which a coefficient is surely a unit mix-up
+const MAX_TILT_PER_C: f64 = 1.0;
+
+impl TemperatureCompensationConfig {
+    fn check(&self, issues: &mut ConfigIssues) {
+        for (field, coefficient) in [("pitch_per_c", self.pitch_per_c), ("roll_per_c", self.roll_per_c)] {
+            if !coefficient.is_finite() || coefficient.abs() > MAX_TILT_PER_C {
+                issues.push(
+                    &format!("temperature_compensation.{}", field),
+                    format!("must be between -{0} and {0} degrees per °C", MAX_TILT_PER_C),
+                );
+            }
+        }
+        if self.mode == CompensationMode::Coefficients && self.pitch_per_c == 0.0 && self.roll_per_c == 0.0 {
+            issues.push("temperature_compensation.mode", "\"coefficients\" needs pitch_per_c or roll_per_c");
+        }
+        if !(-50.0..=85.0).contains(&self.reference_c) {
+            issues.push("temperature_compensation.reference_c", "must be between -50 and 85 °C");
+        }
+        if !(self.learn_min_span_c > 0.0 && self.learn_min_span_c <= 50.0) {
+            issues.push("temperature_compensation.learn_min_span_c", "must be between 0 and 50 °C");
+        }
+        if self.learn_min_samples < 10 {
+            issues.push("temperature_compensation.learn_min_samples", "must be at least 10");
+        }
+    }
+}
+
+// Which raw firmware commands POST /api/command may send
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandApiConfig {
+    // Command codes accepted; parameter digits after the code (e.g. "0A150") are allowed
+    pub allowed_commands: Vec<String>,
+    // Accept any well-formed command, factory reset included (also --expert-mode)
+    pub expert_mode: bool,
+    // Firmware code that aborts a running operation, written when a job is cancelled; unset
+    // means cancelling only stops the bridge waiting for the reply
+    pub abort_command: Option<String>,
+}
+
+impl Default for CommandApiConfig {
+    fn default() -> Self {
+        Self {
+            allowed_commands: protocol::DEFAULT_ALLOWED_COMMANDS.iter().map(|code| code.to_string()).collect(),
+            expert_mode: false,
+            abort_command: None,
+        }
+    }
+}
+
+impl CommandApiConfig {
+    pub fn allows(&self, command: &str) -> bool {
+        let code = protocol::command_code(command);
+        self.expert_mode || self.allowed_commands.iter().any(|allowed| allowed.eq_ignore_ascii_case(code))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandHistoryConfig {
+    // Commands kept for /api/command/history; 0 disables the history
+    pub size: usize,
+}
+
+impl Default for CommandHistoryConfig {
+    fn default() -> Self {
+        Self { size: 50 }
+    }
+}
+
+// Names advertised to ASCOM clients, to tell several bridges on one network apart
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdentityConfig {
+    // SafetyMonitor Name and configured-device DeviceName; the firmware's name when unset
+    pub device_name: Option<String>,
+    pub description: Option<String>,
+    // Management API description
+    pub server_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub location: Option<String>,
+}
+
+impl IdentityConfig {
+    pub fn device_name(&self, firmware_name: &str) -> String {
+        self.device_name.clone().unwrap_or_else(|| firmware_name.to_string())
+    }
+
+    pub fn description(&self) -> String {
+        self.description.clone().unwrap_or_else(|| {
+            "nRF52840 based telescope park position sensor for ASCOM safety monitoring".to_string()
+        })
+    }
+
+    pub fn server_name(&self) -> String {
+        self.server_name.clone().unwrap_or_else(|| "nRF52840 Telescope Park Bridge".to_string())
+    }
+
+    pub fn manufacturer(&self) -> String {
+        self.manufacturer.clone().unwrap_or_else(|| "Corey Smart".to_string())
+    }
+
+    pub fn location(&self) -> String {
+        self.location.clone().unwrap_or_else(|| "Local".to_string())
+    }
+}
+
+// One [[devices]] entry
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    // Alpaca device numbers must run 0, 1, 2, ... without gaps
+    pub device_number: u32,
+    // Serial port opened at startup; only the primary device may omit it and connect from the web interface
+    pub port: Option<String>,
+    // Mirror a sensor served by another bridge instead of opening a serial port
+    pub remote: Option<RemoteConfig>,
+    // Export a vote of other devices (redundant sensors on one mount) instead of a sensor
+    pub voting: Option<VotingConfig>,
+    #[serde(default = "default_baud")]
+    pub baud: u32,
+    pub device_name: Option<String>,
+    pub description: Option<String>,
+    // Fixed Alpaca UniqueID so clients keep recognising the device across restarts
+    pub unique_id: Option<String>,
+    // Replaces the top-level [safety] table for this device
+    pub safety: Option<SafetyConfig>,
+    // Replaces the top-level [serial] table, for sensors behind a different USB-serial adapter
+    pub serial: Option<SerialConfig>,
+    // Attach to whichever port this USB device shows up on, e.g. after a USB reset renames it
+    pub usb: Option<UsbMatch>,
+}
+
+fn default_baud() -> u32 {
+    115200
+}
+
+// Identifies a sensor's USB-serial port by what the device reports rather than by its name;
+// every field set must match
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UsbMatch {
+    // e.g. vid = 0x2886 (Seeed Studio)
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    // USB serial number string, compared without regard to case
+    pub serial_number: Option<String>,
+}
+
+impl UsbMatch {
+    fn check(&self, prefix: &str, issues: &mut ConfigIssues) {
+        if self.vid.is_none() && self.pid.is_none() && self.serial_number.is_none() {
+            issues.push(prefix, "needs vid, pid or serial_number");
+        }
+        if self.serial_number.as_deref().is_some_and(|serial| serial.trim().is_empty()) {
+            issues.push(&format!("{}.serial_number", prefix), "must not be empty");
+        }
+    }
+}
+
+// "VID:2886 PID:0045 serial 8A3F", the fields that are set
+impl std::fmt::Display for UsbMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(vid) = self.vid {
+            parts.push(format!("VID:{:04X}", vid));
+        }
+        if let Some(pid) = self.pid {
+            parts.push(format!("PID:{:04X}", pid));
+        }
+        if let Some(serial) = &self.serial_number {
+            parts.push(format!("serial {}", serial));
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    // Base URL of the remote bridge, e.g. "http://pier-north.local:11111"
+    pub url: String,
+    // Alpaca device number on the remote bridge
+    #[serde(default)]
+    pub device_number: u32,
+    #[serde(default = "default_remote_poll_interval")]
+    pub poll_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VotingConfig {
+    // Device numbers of the sensors attached to the same mount
+    pub members: Vec<u32>,
+    #[serde(default)]
+    pub policy: VotingPolicy,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingPolicy {
+    // Safe only when every sensor reports safe
+    #[default]
+    All,
+    // Safe when more than half of the sensors report safe
+    Majority,
+}
+
+// Panel lamps mirroring one device (cargo feature gpio, Linux only); nothing is driven unless
+// safe or connected is set
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpioConfig {
+    // GPIO character device the pin numbers refer to
+    pub chip: String,
+    // Device whose state the lamps show
+    pub device_number: u32,
+    // Lit while IsSafe is true, e.g. safe = { pin = 17 }
+    pub safe: Option<StatusOutput>,
+    // Lit while the sensor is connected, e.g. connected = { led = "ACT" }
+    pub connected: Option<StatusOutput>,
+    // Pins go low when lit, for lamps wired between 3.3 V and the pin
+    pub active_low: bool,
+}
+
+impl Default for GpioConfig {
+    fn default() -> Self {
+        Self {
+            chip: "/dev/gpiochip0".to_string(),
+            device_number: 0,
+            safe: None,
+            connected: None,
+            active_low: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusOutput {
+    // Line offset on the chip; the BCM GPIO number on a Raspberry Pi
+    Pin(u32),
+    // Kernel LED under /sys/class/leds, e.g. "ACT"
+    Led(String),
+}
+
+impl std::fmt::Display for StatusOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pin(pin) => write!(f, "GPIO pin {}", pin),
+            Self::Led(led) => write!(f, "LED {}", led),
+        }
+    }
+}
+
+impl GpioConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.safe.is_some() || self.connected.is_some()
+    }
+
+    fn check(&self, device_numbers: &[u32], issues: &mut ConfigIssues) {
+        if !self.is_enabled() {
+            return;
+        }
+        if cfg!(not(all(feature = "gpio", target_os = "linux"))) {
+            issues.push("gpio", "this build has no GPIO support (cargo feature gpio, Linux only)");
+        }
+        if !self.chip.starts_with('/') {
+            issues.push("gpio.chip", format!("'{}' is not a device path like /dev/gpiochip0", self.chip));
+        }
+        if !device_numbers.contains(&self.device_number) {
+            issues.push("gpio.device_number", format!("device {} is not configured", self.device_number));
+        }
+        for (field, output) in [("gpio.safe", &self.safe), ("gpio.connected", &self.connected)] {
+            if let Some(StatusOutput::Led(led)) = output {
+                if led.trim().is_empty() || led.contains('/') || led.starts_with('.') {
+                    issues.push(field, format!("'{}' is not an LED name from /sys/class/leds", led));
+                }
+            }
+        }
+        if self.safe.is_some() && self.safe == self.connected {
+            issues.push("gpio.connected", format!("{} is already the safe lamp", self.connected.as_ref().unwrap()));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RelayConfig {
+    // Shown in logs and relay_switched events
+    pub name: String,
+    // Device whose IsSafe drives the relay
+    #[serde(default)]
+    pub device_number: u32,
+    // What to do when IsSafe turns false, and when it turns true again
+    #[serde(default = "default_on_unsafe")]
+    pub on_unsafe: RelayAction,
+    #[serde(default = "default_on_safe")]
+    pub on_safe: RelayAction,
+    pub driver: RelayDriver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayAction {
+    On,
+    Off,
+    // Leave the relay as it is
+    None,
+}
+
+impl RelayAction {
+    // The relay state to switch to, if any
+    pub fn target(self) -> Option<bool> {
+        match self {
+            Self::On => Some(true),
+            Self::Off => Some(false),
+            Self::None => None,
+        }
+    }
+}
+
+fn default_on_unsafe() -> RelayAction {
+    RelayAction::Off
+}
+
+fn default_on_safe() -> RelayAction {
+    RelayAction::On
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RelayDriver {
+    // USB HID relay board (16c0:05df, "USBRelay2" and similar); the first board found unless
+    // `board` names one by its five-character id
+    UsbHid {
+        board: Option<String>,
+        // Relay number on the board, from 1
+        channel: u8,
+    },
+    // Shelly relay on the local network; generation 1 devices use /relay/N, Plus and Pro
+    // devices (generation 2 and later) the RPC API
+    Shelly {
+        url: String,
+        #[serde(default)]
+        channel: u32,
+        #[serde(default = "default_shelly_generation")]
+        generation: u8,
+    },
+    // Tasmota device; channel 1 is POWER1
+    Tasmota {
+        url: String,
+        #[serde(default = "default_tasmota_channel")]
+        channel: u32,
+    },
+}
+
+fn default_shelly_generation() -> u8 {
+    2
+}
+
+fn default_tasmota_channel() -> u32 {
+    1
+}
+
+// Board ids of USB HID relays are at most this long
+const MAX_RELAY_BOARD_ID_LEN: usize = 5;
+
+impl RelayConfig {
+    fn check(&self, field: &str, device_numbers: &[u32], issues: &mut ConfigIssues) {
+        if cfg!(not(feature = "relays")) {
+            issues.push(field, "this build has no relay support (cargo feature relays)");
+        }
+        if self.name.trim().is_empty() {
+            issues.push(&format!("{}.name", field), "must not be empty");
+        }
+        if !device_numbers.contains(&self.device_number) {
+            issues.push(&format!("{}.device_number", field), format!("device {} is not configured", self.device_number));
+        }
+        if self.on_unsafe == RelayAction::None && self.on_safe == RelayAction::None {
+            issues.push(&format!("{}.on_unsafe", field), "on_unsafe and on_safe are both \"none\"; the relay would never switch");
+        }
+        let driver = format!("{}.driver", field);
+        match &self.driver {
+            RelayDriver::UsbHid { board, channel } => {
+                if let Some(board) = board {
+                    if board.is_empty() || board.len() > MAX_RELAY_BOARD_ID_LEN || !board.is_ascii() {
+                        issues.push(&format!("{}.board", driver), format!("'{}' is not a board id (up to {} characters)", board, MAX_RELAY_BOARD_ID_LEN));
+                    }
+                }
+                if !(1..=8).contains(channel) {
+                    issues.push(&format!("{}.channel", driver), "must be between 1 and 8");
+                }
+            }
+            RelayDriver::Shelly { url, generation, .. } => {
+                check_relay_url(url, &driver, issues);
+                if *generation == 0 {
+                    issues.push(&format!("{}.generation", driver), "must be 1 for Gen1 devices or 2 and up for Plus/Pro devices");
+                }
+            }
+            RelayDriver::Tasmota { url, channel } => {
+                check_relay_url(url, &driver, issues);
+                if *channel == 0 {
+                    issues.push(&format!("{}.channel", driver), "must be at least 1 (POWER1)");
+                }
+            }
+        }
+    }
+}
+
+// Only plain HTTP is built in, as for remote sensors
+fn check_relay_url(url: &str, field: &str, issues: &mut ConfigIssues) {
+    if !url.starts_with("http://") || url.len() <= "http://".len() {
+        issues.push(&format!("{}.url", field), format!("'{}' must be an http:// address", url));
+    }
+}
+
+// Web interface accounts; without any the web interface and JSON API stay open to everyone.
+// Admins may change things, viewers only watch status and history.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebAuthConfig {
+    pub users: Vec<WebUserConfig>,
+    // Sign-ins expire after this many hours
+    pub session_hours: u64,
+}
+
+impl Default for WebAuthConfig {
+    fn default() -> Self {
+        Self {
+            users: Vec::new(),
+            session_hours: 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebUserConfig {
+    pub name: String,
+    pub role: WebRole,
+    // Argon2 hash printed by the hash-password subcommand; the password itself is never stored
+    pub password_hash: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebRole {
+    Viewer,
+    Admin,
+}
+
+// Longest sign-in, a month
+const MAX_SESSION_HOURS: u64 = 24 * 30;
+
+impl WebAuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.users.is_empty()
+    }
+
+    fn check(&self, issues: &mut ConfigIssues) {
+        let mut names = std::collections::HashSet::new();
+        for (index, user) in self.users.iter().enumerate() {
+            let field = |name: &str| format!("web_auth.users[{}].{}", index, name);
+            // The colon separates name and password in Basic credentials
+            if user.name.trim().is_empty() || user.name.contains(':') {
+                issues.push(&field("name"), "must not be empty or contain ':'");
+            } else if !names.insert(user.name.as_str()) {
+                issues.push(&field("name"), format!("'{}' is already used by another user", user.name));
+            }
+            if argon2::PasswordHash::new(&user.password_hash).is_err() {
+                issues.push(&field("password_hash"), "is not a password hash; create one with the hash-password subcommand");
+            }
+        }
+        if self.session_hours == 0 || self.session_hours > MAX_SESSION_HOURS {
+            issues.push("web_auth.session_hours", format!("must be between 1 and {}", MAX_SESSION_HOURS));
+        }
+    }
+}
+
+// The event feed (/api/events and its /api/events/stream subscription) carries all of the
+// sensor's telemetry; with a token, or with web users, it needs one of them
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventsConfig {
+    // Required as "Authorization: Bearer <token>"; better kept in the keyring (`secrets set events.token`)
+    pub token: Option<String>,
+}
+
+impl EventsConfig {
+    pub fn authorizes(&self, presented: &str) -> bool {
+        self.token.as_deref().is_some_and(|token| tokens_match(token, presented))
+    }
+}
+
+// Scheduled backups of the config file, each device's settings and the compacted history;
+// disabled until a directory is set
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackupConfig {
+    // Best on a USB stick or network share rather than the SD card the bridge runs from
+    pub directory: Option<String>,
+    pub interval_hours: u64,
+    // Newest backups kept; older ones are deleted
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            interval_hours: 24,
+            keep: 14,
+        }
+    }
+}
+
+// A month between backups at most
+const MAX_BACKUP_INTERVAL_HOURS: u64 = 24 * 31;
+
+impl BackupConfig {
+    fn check(&self, issues: &mut ConfigIssues) {
+        if self.directory.as_deref().is_some_and(|directory| directory.trim().is_empty()) {
+            issues.push("backup.directory", "must not be empty; leave it out to disable backups");
+        }
+        if self.interval_hours == 0 || self.interval_hours > MAX_BACKUP_INTERVAL_HOURS {
+            issues.push("backup.interval_hours", format!("must be between 1 and {}", MAX_BACKUP_INTERVAL_HOURS));
+        }
+        if self.keep == 0 {
+            issues.push("backup.keep", "must be at least 1");
+        }
+    }
+}
+
+// End-of-night summaries at /api/reports/nightly; a night runs from end_hour to end_hour the
+// next day, in the display timezone
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NightlyReportConfig {
+    // Hour of the morning the night's report is made, 0-23
+    pub end_hour: u32,
+    // Finished nights kept
+    pub keep: usize,
+}
+
+impl Default for NightlyReportConfig {
+    fn default() -> Self {
+        Self { end_hour: 9, keep: 14 }
+    }
+}
+
+impl NightlyReportConfig {
+    fn check(&self, issues: &mut ConfigIssues) {
+        if self.end_hour > 23 {
+            issues.push("nightly_report.end_hour", "must be an hour from 0 to 23");
+        }
+        if self.keep == 0 {
+            issues.push("nightly_report.keep", "must be at least 1");
+        }
+    }
+}
+
+// mDNS/Bonjour advertisement of the HTTP server (cargo feature mdns), so the bridge can be found
+// by browsing on networks that block the Alpaca discovery broadcast on UDP 32227
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MdnsConfig {
+    pub enabled: bool,
+    // Name shown when browsing; identity.server_name when unset
+    pub instance_name: Option<String>,
+    // Answered as <host_name>.local; made from the instance name when unset
+    pub host_name: Option<String>,
+}
+
+impl Default for MdnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            instance_name: None,
+            host_name: None,
+        }
+    }
+}
+
+// Longest DNS label
+const MAX_DNS_LABEL_LEN: usize = 63;
+
+impl MdnsConfig {
+    fn check(&self, issues: &mut ConfigIssues) {
+        if let Some(name) = &self.instance_name {
+            if name.trim().is_empty() || name.len() > MAX_DNS_LABEL_LEN {
+                issues.push("mdns.instance_name", format!("must be 1 to {} bytes long", MAX_DNS_LABEL_LEN));
+            }
+        }
+        if let Some(host) = &self.host_name {
+            let valid = !host.is_empty()
+                && host.len() <= MAX_DNS_LABEL_LEN
+                && host.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                && !host.starts_with('-')
+                && !host.ends_with('-');
+            if !valid {
+                issues.push(
+                    "mdns.host_name",
+                    format!("'{}' must be letters, digits and inner hyphens, without .local", host),
+                );
+            }
+        }
+    }
+}
+
+// Where log messages go besides the console, so errors show up where operators already look
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub target: LogTarget,
+    // Least severe level sent to syslog or the Event Log
+    pub level: LogLevel,
+    // Keep printing to the console as well
+    pub console: bool,
+    // syslog tag and Event Log source name
+    pub name: String,
+    pub syslog_facility: SyslogFacility,
+    // Datagram socket of the local syslog daemon; /dev/log (/var/run/syslog on macOS) when unset
+    pub syslog_socket: Option<String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            target: LogTarget::Console,
+            level: LogLevel::Info,
+            console: true,
+            name: "telescope_park_bridge".to_string(),
+            syslog_facility: SyslogFacility::Daemon,
+            syslog_socket: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogTarget {
+    Console,
+    // Unix only
+    Syslog,
+    // Windows only
+    Eventlog,
+}
+
+impl std::fmt::Display for LogTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Console => f.write_str("the console"),
+            Self::Syslog => f.write_str("syslog"),
+            Self::Eventlog => f.write_str("the Windows Event Log"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogFacility {
+    User,
+    Daemon,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl SyslogFacility {
+    // Facility number of RFC 5424
+    pub fn code(self) -> u8 {
+        match self {
+            Self::User => 1,
+            Self::Daemon => 3,
+            Self::Local0 => 16,
+            Self::Local1 => 17,
+            Self::Local2 => 18,
+            Self::Local3 => 19,
+            Self::Local4 => 20,
+            Self::Local5 => 21,
+            Self::Local6 => 22,
+            Self::Local7 => 23,
+        }
+    }
+}
+
+impl LoggingConfig {
+    fn check(&self, issues: &mut ConfigIssues) {
+        match self.target {
+            LogTarget::Syslog if !cfg!(unix) => issues.push("logging.target", "syslog is only available on Unix"),
+            LogTarget::Eventlog if !cfg!(windows) => {
+                issues.push("logging.target", "eventlog is only available on Windows")
+            }
+            LogTarget::Eventlog if !cfg!(feature = "event-log") => {
+                issues.push("logging.target", "eventlog needs a build with the event-log feature")
+            }
+            _ => {}
+        }
+        if self.name.trim().is_empty() || self.name.len() > 255 {
+            issues.push("logging.name", "must be 1 to 255 bytes long");
+        }
+        if self.target == LogTarget::Console && !self.console {
+            issues.push("logging.console", "cannot be false while target is console; nothing would be logged");
+        }
+    }
+}
+
+// Chat-ops bot (cargo feature chat-bot) in a Discord channel or Matrix room; disabled unless
+// platform is set
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChatBotConfig {
+    pub platform: Option<ChatPlatform>,
+    // Matrix homeserver, e.g. "https://matrix.example.org"; Discord uses its public API unless set
+    pub url: Option<String>,
+    // Discord channel id, or Matrix room id like "!AbCdEf:example.org"
+    pub channel: String,
+    // Discord bot token or Matrix access token; better kept in the keyring (`secrets set chat_bot.token`)
+    pub token: Option<String>,
+    // Device whose events are posted and which the commands act on
+    pub device_number: u32,
+    // May run !park and !calibrate: Discord user ids, or Matrix ids like "@alice:example.org".
+    // !status answers everyone in the channel.
+    pub authorized_users: Vec<String>,
+    // Seconds between checks for new Discord messages; Matrix waits on the server instead
+    pub poll_interval_secs: u64,
+    // Messages for this channel, by event type; others come from [notifications.templates]
+    pub templates: HashMap<String, String>,
+}
+
+impl Default for ChatBotConfig {
+    fn default() -> Self {
+        Self {
+            platform: None,
+            url: None,
+            channel: String::new(),
+            token: None,
+            device_number: 0,
+            authorized_users: Vec::new(),
+            poll_interval_secs: 3,
+            templates: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatPlatform {
+    Discord,
+    Matrix,
+}
+
+impl std::fmt::Display for ChatPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Discord => f.write_str("Discord"),
+            Self::Matrix => f.write_str("Matrix"),
+        }
+    }
+}
+
+impl ChatBotConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.platform.is_some()
+    }
+
+    // The token may still come from the secret store, so only its presence at startup is checked
+    fn check(&self, device_numbers: &[u32], issues: &mut ConfigIssues) {
+        let Some(platform) = self.platform else {
+            return;
+        };
+        if cfg!(not(feature = "chat-bot")) {
+            issues.push("chat_bot", "this build has no chat bot (cargo feature chat-bot)");
+        }
+        match &self.url {
+            Some(url) if !(url.starts_with("https://") || url.starts_with("http://")) => {
+                issues.push("chat_bot.url", format!("'{}' must be an https:// address", url));
+            }
+            None if platform == ChatPlatform::Matrix => {
+                issues.push("chat_bot.url", "must name the Matrix homeserver, e.g. \"https://matrix.example.org\"");
+            }
+            _ => {}
+        }
+        let channel_ok = match platform {
+            ChatPlatform::Discord => !self.channel.is_empty() && self.channel.bytes().all(|b| b.is_ascii_digit()),
+            ChatPlatform::Matrix => self.channel.starts_with('!') && self.channel.contains(':'),
+        };
+        if !channel_ok {
+            let expected = match platform {
+                ChatPlatform::Discord => "a Discord channel id (copy it with developer mode on)",
+                ChatPlatform::Matrix => "a Matrix room id like \"!AbCdEf:example.org\"",
+            };
+            issues.push("chat_bot.channel", format!("'{}' is not {}", self.channel, expected));
+        }
+        if self.token.as_deref().is_some_and(|token| token.trim().is_empty()) {
+            issues.push("chat_bot.token", "must not be empty");
+        }
+        if !device_numbers.contains(&self.device_number) {
+            issues.push("chat_bot.device_number", format!("device {} is not configured", self.device_number));
+        }
+        if self.authorized_users.iter().any(|user| user.trim().is_empty()) {
+            issues.push("chat_bot.authorized_users", "must not contain empty entries");
+        }
+        if self.poll_interval_secs == 0 || self.poll_interval_secs > MAX_INTERVAL_SECS {
+            issues.push("chat_bot.poll_interval_secs", format!("must be between 1 and {}", MAX_INTERVAL_SECS));
+        }
+        check_templates("chat_bot.templates", &self.templates, issues);
+    }
+}
+
+// A mount's Alpaca server, watched by a telescope manager; reconnects follow [reconnect]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelescopeConfig {
+    // Names the mount in /api/telescopes/{id}, e.g. "main" or "piggyback"
+    pub id: String,
+    // e.g. "http://192.168.1.20:11111"
+    pub url: String,
+    // Alpaca device number of the Telescope on that server
+    pub device_number: u32,
+    // Park sensor on this mount, checked against the mount's AtPark
+    pub sensor_device: u32,
+    pub poll_interval_secs: u64,
+    pub connect_timeout_ms: u64,
+    pub request_timeout_ms: u64,
+    // Extra attempts for status reads; commands are never repeated
+    pub get_retries: u32,
+    // Delay before the first retry, doubled for each further one
+    pub retry_delay_ms: u64,
+    // An axis moved through /api/telescopes/{id}/move stops after this long unless the move is
+    // sent again, so a client that goes away cannot leave the mount running
+    pub move_timeout_secs: u64,
+}
+
+impl Default for TelescopeConfig {
+    fn default() -> Self {
+        Self {
+            id: "main".to_string(),
+            url: String::new(),
+            device_number: 0,
+            sensor_device: 0,
+            poll_interval_secs: 2,
+            connect_timeout_ms: 3000,
+            request_timeout_ms: 10_000,
+            get_retries: 2,
+            retry_delay_ms: 250,
+            move_timeout_secs: 5,
+        }
+    }
+}
+
+impl TelescopeConfig {
+    fn check(&self, field: &str, device_numbers: &[u32], issues: &mut ConfigIssues) {
+        let valid_id = !self.id.is_empty() && self.id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !valid_id {
+            issues.push(&format!("{}.id", field), format!("'{}' must be letters, digits, '-' and '_'", self.id));
+        }
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            issues.push(&format!("{}.url", field), format!("'{}' must be an http:// address", self.url));
+        }
+        if !device_numbers.contains(&self.sensor_device) {
+            issues.push(&format!("{}.sensor_device", field), format!("device {} is not configured", self.sensor_device));
+        }
+        if self.poll_interval_secs == 0 || self.poll_interval_secs > MAX_INTERVAL_SECS {
+            issues.push(&format!("{}.poll_interval_secs", field), format!("must be between 1 and {}", MAX_INTERVAL_SECS));
+        }
+        if self.connect_timeout_ms == 0 {
+            issues.push(&format!("{}.connect_timeout_ms", field), "must be at least 1");
+        }
+        if self.request_timeout_ms < self.connect_timeout_ms {
+            issues.push(
+                &format!("{}.request_timeout_ms", field),
+                format!("must be at least connect_timeout_ms ({})", self.connect_timeout_ms),
+            );
+        }
+        if self.retry_delay_ms > MAX_INTERVAL_SECS * 1000 {
+            issues.push(&format!("{}.retry_delay_ms", field), format!("must be at most {}", MAX_INTERVAL_SECS * 1000));
+        }
+        if self.move_timeout_secs == 0 || self.move_timeout_secs > 60 {
+            issues.push(&format!("{}.move_timeout_secs", field), "must be between 1 and 60");
+        }
+    }
+}
+
+fn default_remote_poll_interval() -> u64 {
+    2
+}
+
+impl RemoteConfig {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            device_number: 0,
+            poll_interval_secs: default_remote_poll_interval(),
+        }
+    }
+
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if cfg!(not(feature = "remote-sensors")) {
+            return Err("this build has no remote sensor support (cargo feature remote-sensors)".to_string());
+        }
+        // Bridges only serve plain HTTP, so no TLS support is built in
+        if !self.url.starts_with("http://") {
+            return Err(format!("remote url '{}' must start with http://", self.url));
+        }
+        if self.poll_interval_secs == 0 {
+            return Err("remote poll_interval_secs must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl DeviceConfig {
+    // Shared identity with this device's name and description applied
+    pub fn identity(&self, shared: &IdentityConfig) -> IdentityConfig {
+        IdentityConfig {
+            device_name: self.device_name.clone().or_else(|| shared.device_name.clone()),
+            description: self.description.clone().or_else(|| shared.description.clone()),
+            ..shared.clone()
+        }
+    }
+
+    pub fn safety(&self, shared: SafetyConfig) -> SafetyConfig {
+        self.safety.unwrap_or(shared)
+    }
+
+    pub fn serial(&self, shared: SerialConfig) -> SerialConfig {
+        self.serial.unwrap_or(shared)
+    }
+}
+
+impl BridgeConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| BridgeError::Config(format!("{}: {}", path.display(), e)))?;
+        let config = Self::parse(&text).map_err(|e| BridgeError::Config(format!("{}: {}", path.display(), e)))?;
+        let issues = config.issues();
+        if !issues.is_empty() {
+            return Err(BridgeError::Config(format!("{} has {}", path.display(), issues)));
+        }
+        Ok(config)
+    }
+
+    // Parse a configuration file's text, upgrading older schema versions first (not validated)
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let table: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+        let migrated = config_migrations::migrate(table)?;
+        if migrated.from_version == CURRENT_SCHEMA_VERSION {
+            // Deserialize the text itself so errors keep their line and column
+            return toml::from_str(text).map_err(|e| e.to_string());
+        }
+
+        if migrated.applied.is_empty() {
+            warn!(
+                "Configuration file is schema_version {}; add schema_version = {} (no other changes needed)",
+                migrated.from_version, CURRENT_SCHEMA_VERSION
+            );
+        } else {
+            warn!(
+                "Configuration file is schema_version {}, upgraded to {} on load",
+                migrated.from_version, CURRENT_SCHEMA_VERSION
+            );
+            for step in &migrated.applied {
+                warn!("  - {}", step);
+            }
+            warn!("Apply these changes and set schema_version = {} to silence this warning", CURRENT_SCHEMA_VERSION);
+        }
+        migrated.table.try_into().map_err(|e: toml::de::Error| e.to_string())
+    }
+
+    // Every problem in the file, so a broken config is fixed in one pass instead of one error per start
+    pub fn issues(&self) -> ConfigIssues {
+        let mut issues = ConfigIssues::default();
+        for (command, seconds) in &self.command_timeouts {
+            let field = format!("command_timeouts.\"{}\"", command);
+            if !is_command_code(command) {
+                issues.push(&field, "is not a two-digit hex command code, e.g. \"06\"");
+            }
+            if !seconds.is_finite() || *seconds <= 0.0 || *seconds > MAX_COMMAND_TIMEOUT_SECS {
+                issues.push(&field, format!("must be between 0 and {} seconds", MAX_COMMAND_TIMEOUT_SECS));
+            }
+        }
+        for command in &self.command_api.allowed_commands {
+            if !is_command_code(command) {
+                issues.push("command_api.allowed_commands", format!("'{}' is not a two-digit hex command code", command));
+            }
+        }
+        if let Some(abort) = &self.command_api.abort_command {
+            if let Err(message) = protocol::validate_command(abort) {
+                issues.push("command_api.abort_command", message);
+            }
+        }
+        if self.heartbeat.interval_secs > MAX_INTERVAL_SECS {
+            issues.push("heartbeat.interval_secs", format!("must be at most {} (0 disables the heartbeat)", MAX_INTERVAL_SECS));
+        }
+        if self.heartbeat.interval_secs > 0 && self.heartbeat.max_missed == 0 {
+            issues.push("heartbeat.max_missed", "must be at least 1 while the heartbeat is enabled");
+        }
+        if self.reconnect.initial_delay_secs == 0 {
+            issues.push("reconnect.initial_delay_secs", "must be at least 1");
+        }
+        if self.reconnect.max_delay_secs < self.reconnect.initial_delay_secs || self.reconnect.max_delay_secs > MAX_INTERVAL_SECS {
+            issues.push(
+                "reconnect.max_delay_secs",
+                format!("must be between initial_delay_secs ({}) and {}", self.reconnect.initial_delay_secs, MAX_INTERVAL_SECS),
+            );
+        }
+        if self.framing.command_terminator.is_empty() {
+            issues.push("framing.command_terminator", "must not be empty");
+        }
+        if self.framing.response_terminator.is_empty() {
+            issues.push("framing.response_terminator", "must not be empty");
+        }
+        if self.auto_reset.after_failures == 0 {
+            issues.push("auto_reset.after_failures", "must be at least 1");
+        }
+        if self.auto_reset.boot_wait_secs > MAX_INTERVAL_SECS {
+            issues.push("auto_reset.boot_wait_secs", format!("must be at most {}", MAX_INTERVAL_SECS));
+        }
+        if self.discovery.max_tracked_clients == 0 {
+            issues.push("discovery.max_tracked_clients", "must be at least 1");
+        }
+        if self.discovery.min_response_interval_ms > MAX_INTERVAL_SECS * 1000 {
+            issues.push("discovery.min_response_interval_ms", format!("must be at most {}", MAX_INTERVAL_SECS * 1000));
+        }
+        let identity = [
+            ("identity.device_name", &self.identity.device_name),
+            ("identity.description", &self.identity.description),
+            ("identity.server_name", &self.identity.server_name),
+            ("identity.manufacturer", &self.identity.manufacturer),
+            ("identity.location", &self.identity.location),
+        ];
+        for (field, value) in identity {
+            if value.as_deref().is_some_and(|v| v.trim().is_empty()) {
+                issues.push(field, "must not be empty; leave it out to keep the default");
+            }
+        }
+        let health = &self.health;
+        if health.heap_drop_percent.is_nan() || health.heap_drop_percent <= 0.0 || health.heap_drop_percent >= 100.0 {
+            issues.push("health.heap_drop_percent", "must be between 0 and 100");
+        }
+        if health.reboot_warning_count == 0 {
+            issues.push("health.reboot_warning_count", "must be at least 1");
+        }
+        if health.reboot_window_secs == 0 {
+            issues.push("health.reboot_window_secs", "must be at least 1");
+        }
+        if health.latency_warning_factor.is_nan() || health.latency_warning_factor <= 1.0 {
+            issues.push("health.latency_warning_factor", "must be above 1");
+        }
+        if health.latency_floor_ms.is_nan() || health.latency_floor_ms < 0.0 {
+            issues.push("health.latency_floor_ms", "must not be negative");
+        }
+        if health.park_drift_percent.is_nan() || health.park_drift_percent < 0.0 || health.park_drift_percent >= 100.0 {
+            issues.push("health.park_drift_percent", "must be between 0 (off) and 100");
+        }
+        if health.park_drift_window_days == 0 || health.park_drift_window_days > 365 {
+            issues.push("health.park_drift_window_days", "must be between 1 and 365");
+        }
+        if health.park_drift_min_days == 0 || health.park_drift_min_days > health.park_drift_window_days {
+            issues.push(
+                "health.park_drift_min_days",
+                format!("must be between 1 and park_drift_window_days ({})", health.park_drift_window_days),
+            );
+        }
+        if let Some(request_timeout) = self.http.request_timeout() {
+            let slowest_command = self.command_timeouts().longest() + protocol::QUEUE_GRACE;
+            if request_timeout <= slowest_command {
+                issues.push(
+                    "http.request_timeout_secs",
+                    format!(
+                        "must be longer than the slowest command ({} s including queueing), or 0 to disable it",
+                        slowest_command.as_secs_f64()
+                    ),
+                );
+            }
+        }
+        if self.safety_force.token.as_deref().is_some_and(|token| token.trim().is_empty()) {
+            issues.push("safety_force.token", "must not be empty; leave it out to disable the endpoint");
+        }
+        if self.safety_force.max_minutes == 0 {
+            issues.push("safety_force.max_minutes", "must be at least 1");
+        }
+        self.set_park.check(&mut issues);
+        self.temperature_compensation.check(&mut issues);
+        self.safety.check("safety", &mut issues);
+        self.check_devices(&mut issues);
+        if let Some(usb) = &self.usb {
+            usb.check("usb", &mut issues);
+            if !self.devices.is_empty() {
+                issues.push("usb", "applies only without [[devices]]; use [devices.usb] instead");
+            }
+        }
+        let device_numbers: Vec<u32> = match self.devices.is_empty() {
+            true => vec![0],
+            false => self.devices.iter().map(|device| device.device_number).collect(),
+        };
+        self.gpio.check(&device_numbers, &mut issues);
+        if let Some(bind) = &self.serial_tee.bind {
+            if let Err(message) = check_socket_addr(bind) {
+                issues.push("serial_tee.bind", message);
+            }
+            if !device_numbers.contains(&self.serial_tee.device_number) {
+                issues.push("serial_tee.device_number", format!("device {} is not configured", self.serial_tee.device_number));
+            }
+            if self.serial_tee.max_clients == 0 {
+                issues.push("serial_tee.max_clients", "must be at least 1");
+            }
+        }
+        if let Some(bind) = &self.indi.bind {
+            if let Err(message) = check_socket_addr(bind) {
+                issues.push("indi.bind", message);
+            }
+            if self.indi.max_clients == 0 {
+                issues.push("indi.max_clients", "must be at least 1");
+            }
+        }
+        let mut relay_names = std::collections::HashSet::new();
+        for (index, relay) in self.relays.iter().enumerate() {
+            let field = format!("relays[{}]", index);
+            relay.check(&field, &device_numbers, &mut issues);
+            if !relay_names.insert(relay.name.as_str()) {
+                issues.push(&format!("{}.name", field), format!("'{}' is already used by another relay", relay.name));
+            }
+        }
+        self.chat_bot.check(&device_numbers, &mut issues);
+        if !self.telescopes.is_empty() && cfg!(not(feature = "telescope-client")) {
+            issues.push("telescopes", "this build has no telescope client (cargo feature telescope-client)");
+        }
+        let mut telescope_ids = std::collections::HashSet::new();
+        for (index, telescope) in self.telescopes.iter().enumerate() {
+            let field = format!("telescopes[{}]", index);
+            telescope.check(&field, &device_numbers, &mut issues);
+            if !telescope_ids.insert(telescope.id.as_str()) {
+                issues.push(&format!("{}.id", field), format!("'{}' is already used by another telescope", telescope.id));
+            }
+        }
+        self.web_auth.check(&mut issues);
+        if self.events.token.as_deref().is_some_and(|token| token.trim().is_empty()) {
+            issues.push("events.token", "must not be empty; leave it out to leave the event feed open");
+        }
+        self.maintenance.check(&mut issues);
+        self.notifications.check(&mut issues);
+        self.sun_safety.check(&mut issues);
+        let mut schedule_names = std::collections::HashSet::new();
+        for (index, schedule) in self.safety_schedules.iter().enumerate() {
+            let field = format!("safety_schedules[{}]", index);
+            schedule.check(&field, &device_numbers, &mut issues);
+            if !schedule_names.insert(schedule.name.as_str()) {
+                issues.push(&format!("{}.name", field), format!("'{}' is already used by another schedule", schedule.name));
+            }
+        }
+        self.backup.check(&mut issues);
+        self.nightly_report.check(&mut issues);
+        self.mdns.check(&mut issues);
+        self.logging.check(&mut issues);
+        issues
+    }
+
+    fn check_devices(&self, issues: &mut ConfigIssues) {
+        let mut numbers: Vec<u32> = self.devices.iter().map(|device| device.device_number).collect();
+        numbers.sort_unstable();
+        if numbers.iter().enumerate().any(|(index, number)| *number != index as u32) {
+            issues.push(
+                "devices",
+                format!(
+                    "device numbers must be unique and run 0..{} without gaps, got {:?}",
+                    self.devices.len(),
+                    numbers
+                ),
+            );
+        }
+
+        let mut ports = std::collections::HashSet::new();
+        for (index, device) in self.devices.iter().enumerate() {
+            let field = |name: &str| format!("devices[{}].{}", index, name);
+            let sources = [device.port.is_some() || device.usb.is_some(), device.remote.is_some(), device.voting.is_some()];
+            if sources.iter().filter(|set| **set).count() > 1 {
+                issues.push(&field("port"), "port, remote and voting are mutually exclusive; keep one");
+            }
+            if let Some(usb) = &device.usb {
+                usb.check(&field("usb"), issues);
+            }
+            match &device.port {
+                Some(port) => {
+                    if let Err(message) = check_port_syntax(port) {
+                        issues.push(&field("port"), message);
+                    }
+                    if !ports.insert(port.as_str()) {
+                        issues.push(&field("port"), format!("{} is already used by another device", port));
+                    }
+                }
+                None if device.device_number != 0 && !sources.contains(&true) => {
+                    issues.push(&field("port"), "needs a port, usb or remote; only device 0 can be connected from the web interface");
+                }
+                None => {}
+            }
+            if let Some(voting) = &device.voting {
+                self.check_voting(device.device_number, voting, &field("voting.members"), issues);
+            }
+            if let Some(remote) = &device.remote {
+                if let Err(message) = remote.validate() {
+                    issues.push(&field("remote"), message);
+                }
+            }
+            if let Err(message) = check_baud(device.baud) {
+                issues.push(&field("baud"), message);
+            }
+            let names = [("device_name", &device.device_name), ("description", &device.description), ("unique_id", &device.unique_id)];
+            for (name, value) in names {
+                if value.as_deref().is_some_and(|v| v.trim().is_empty()) {
+                    issues.push(&field(name), "must not be empty");
+                }
+            }
+            if let Some(safety) = &device.safety {
+                safety.check(&field("safety"), issues);
+            }
+        }
+    }
+
+    fn check_voting(&self, device_number: u32, voting: &VotingConfig, field: &str, issues: &mut ConfigIssues) {
+        if voting.members.len() < 2 {
+            issues.push(field, "voting needs at least two member devices");
+        }
+        let mut seen = std::collections::HashSet::new();
+        for member in &voting.members {
+            let Some(member_device) = self.devices.iter().find(|d| d.device_number == *member) else {
+                issues.push(field, format!("member {} is not a configured device", member));
+                continue;
+            };
+            if *member == device_number || member_device.voting.is_some() {
+                issues.push(field, format!("member {} must be a sensor, not a voting device", member));
+            }
+            if !seen.insert(*member) {
+                issues.push(field, format!("member {} is listed twice", member));
+            }
+        }
+    }
+
+    // Fill secret fields left out of the file from the keyring or secrets file; values written
+    // into the file still work, with a warning
+    pub fn resolve_secrets(&mut self, store: &SecretStore) -> Result<()> {
+        for name in SECRET_NAMES {
+            let Some(field) = self.secret_field(name) else {
+                continue;
+            };
+            if field.is_some() {
+                warn!("{} is stored in plaintext in the config file; move it with `secrets set {}`", name, name);
+                continue;
+            }
+            if let Some((value, location)) = store.get(name)? {
+                debug!("{} read from the {}", name, location);
+                *field = Some(value);
+            }
+        }
+        Ok(())
+    }
+
+    fn secret_field(&mut self, name: &str) -> Option<&mut Option<String>> {
+        match name {
+            "safety_force.token" => Some(&mut self.safety_force.token),
+            "chat_bot.token" => Some(&mut self.chat_bot.token),
+            "events.token" => Some(&mut self.events.token),
+            _ => None,
+        }
+    }
+
+    pub fn command_timeouts(&self) -> CommandTimeouts {
+        CommandTimeouts::new(
+            self.command_timeouts
+                .iter()
+                .map(|(command, seconds)| (command.clone(), Duration::from_secs_f64(*seconds)))
+                .collect(),
+        )
+    }
+}
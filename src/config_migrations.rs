@@ -0,0 +1,109 @@
+// src/config_migrations.rs
+// Schema version of the configuration file, and the steps upgrading older layouts when loaded
+
+use toml::{Table, Value};
+
+// Layout written by this version of the bridge, stamped as `schema_version = 3`
+pub const CURRENT_SCHEMA_VERSION: u32 = 3;
+
+// Files without schema_version predate versioning
+const UNVERSIONED: u32 = 1;
+
+struct Migration {
+    // Upgrades files of this version to the next one
+    from: u32,
+    description: &'static str,
+    // True when the file used the old layout and was changed
+    apply: fn(&mut Table) -> Result<bool, String>,
+}
+
+// One step per version bump, in order; add a step whenever an option is renamed or moved
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 1,
+        description: "[[devices]] name is now device_name, as in [identity]",
+        apply: rename_device_name,
+    },
+    Migration {
+        from: 2,
+        description: "[telescope] is now a [[telescopes]] entry with id \"main\"",
+        apply: move_telescope_to_list,
+    },
+];
+
+pub struct Migrated {
+    pub table: Table,
+    pub from_version: u32,
+    // Descriptions of the steps that changed the file, oldest first
+    pub applied: Vec<&'static str>,
+}
+
+pub fn schema_version(table: &Table) -> Result<u32, String> {
+    match table.get("schema_version") {
+        None => Ok(UNVERSIONED),
+        Some(Value::Integer(version)) if *version >= 1 => {
+            u32::try_from(*version).map_err(|_| format!("schema_version {} is out of range", version))
+        }
+        Some(value) => Err(format!("schema_version must be a positive integer, not {}", value)),
+    }
+}
+
+// Upgrade a parsed file to CURRENT_SCHEMA_VERSION; files from a newer bridge are refused
+// rather than guessed at
+pub fn migrate(mut table: Table) -> Result<Migrated, String> {
+    let from_version = schema_version(&table)?;
+    if from_version > CURRENT_SCHEMA_VERSION {
+        return Err(format!(
+            "schema_version {} is newer than this bridge understands ({}); upgrade the bridge",
+            from_version, CURRENT_SCHEMA_VERSION
+        ));
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|migration| migration.from >= from_version) {
+        let changed = (migration.apply)(&mut table)
+            .map_err(|e| format!("upgrading from schema_version {}: {}", migration.from, e))?;
+        if changed {
+            applied.push(migration.description);
+        }
+    }
+    table.insert("schema_version".to_string(), Value::Integer(i64::from(CURRENT_SCHEMA_VERSION)));
+    Ok(Migrated {
+        table,
+        from_version,
+        applied,
+    })
+}
+
+fn rename_device_name(table: &mut Table) -> Result<bool, String> {
+    let Some(Value::Array(devices)) = table.get_mut("devices") else {
+        return Ok(false);
+    };
+    let mut changed = false;
+    for device in devices.iter_mut().filter_map(Value::as_table_mut) {
+        let Some(name) = device.remove("name") else {
+            continue;
+        };
+        if device.contains_key("device_name") {
+            return Err("a [[devices]] entry sets both name and device_name".to_string());
+        }
+        device.insert("device_name".to_string(), name);
+        changed = true;
+    }
+    Ok(changed)
+}
+
+fn move_telescope_to_list(table: &mut Table) -> Result<bool, String> {
+    let Some(telescope) = table.remove("telescope") else {
+        return Ok(false);
+    };
+    let Value::Table(mut telescope) = telescope else {
+        return Err("telescope must be a table".to_string());
+    };
+    if table.contains_key("telescopes") {
+        return Err("the file has both [telescope] and [[telescopes]]".to_string());
+    }
+    telescope.entry("id").or_insert_with(|| Value::String("main".to_string()));
+    table.insert("telescopes".to_string(), Value::Array(vec![Value::Table(telescope)]));
+    Ok(true)
+}
@@ -0,0 +1,146 @@
+// src/config_store.rs
+// The --config file as edited from the Alpaca setup pages: changes are made in place with toml_edit,
+// so the file's comments and layout survive, and the result must load like the file would at
+// startup before it replaces the old one
+
+use crate::config::{BridgeConfig, SafetyConfig};
+use crate::errors::{BridgeError, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use toml_edit::{value, Document, Item, Table};
+use tracing::info;
+
+// What the device setup page edits
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSettings {
+    // None: connected from the web interface
+    pub port: Option<String>,
+    pub baud: u32,
+    pub safety: SafetyConfig,
+}
+
+// Shared by the registry's clones
+#[derive(Clone, Default)]
+pub struct ConfigStore {
+    // None when the bridge was started without --config; nothing can be saved then
+    path: Option<PathBuf>,
+    // One save at a time, so two browser tabs cannot interleave their read-modify-write
+    lock: Arc<Mutex<()>>,
+}
+
+impl ConfigStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            lock: Arc::default(),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    // The file as it is now; None without --config or when it cannot be read
+    pub async fn load(&self) -> Option<BridgeConfig> {
+        let text = tokio::fs::read_to_string(self.path.as_ref()?).await.ok()?;
+        BridgeConfig::parse(&text).ok()
+    }
+
+    // Write a device's settings to the file; an invalid result leaves the file untouched
+    pub async fn save_device_settings(&self, device_number: u32, settings: &DeviceSettings) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Err(BridgeError::Config("the bridge was started without --config".to_string()));
+        };
+        let _guard = self.lock.lock().await;
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| BridgeError::Config(format!("{}: {}", path.display(), e)))?;
+        let updated = apply_device_settings(&text, device_number, settings).map_err(BridgeError::Config)?;
+        if updated == text {
+            return Ok(());
+        }
+        // Written next to the file and renamed over it, so a crash cannot leave half a config
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        tokio::fs::write(&temporary, &updated)
+            .await
+            .map_err(|e| BridgeError::Config(format!("{}: {}", temporary.display(), e)))?;
+        tokio::fs::rename(&temporary, path)
+            .await
+            .map_err(|e| BridgeError::Config(format!("{}: {}", path.display(), e)))?;
+        info!("Saved the settings of device {} to {}", device_number, path.display());
+        Ok(())
+    }
+}
+
+// The file's text with the device's settings changed; only settings that differ are written.
+// A port on a file without [[devices]] adds a device 0 entry, which then replaces --port. Safety
+// settings go to the table the device reads them from, except that a device sharing the top-level
+// [safety] with other devices gets its own [devices.safety]
+pub fn apply_device_settings(text: &str, device_number: u32, settings: &DeviceSettings) -> std::result::Result<String, String> {
+    let current = BridgeConfig::parse(text)?;
+    let mut document: Document = text.parse().map_err(|e: toml_edit::TomlError| e.to_string())?;
+    match current.devices.iter().position(|device| device.device_number == device_number) {
+        Some(index) => {
+            let configured = &current.devices[index];
+            let devices = document
+                .get_mut("devices")
+                .and_then(Item::as_array_of_tables_mut)
+                .ok_or("devices must be written as [[devices]] tables to be edited")?;
+            let device = devices.get_mut(index).ok_or("devices changed while being edited")?;
+            let has_serial = configured.remote.is_none() && configured.voting.is_none();
+            if has_serial && configured.port != settings.port {
+                match &settings.port {
+                    Some(port) => device["port"] = value(port.as_str()),
+                    None => {
+                        device.remove("port");
+                    }
+                }
+            }
+            if has_serial && configured.baud != settings.baud {
+                device["baud"] = value(i64::from(settings.baud));
+            }
+            if configured.safety(current.safety) != settings.safety {
+                if configured.safety.is_some() || current.devices.len() > 1 {
+                    write_safety(device, &settings.safety);
+                } else {
+                    write_safety(document.as_table_mut(), &settings.safety);
+                }
+            }
+        }
+        None if device_number == 0 && current.devices.is_empty() => {
+            if let Some(port) = &settings.port {
+                let mut device = Table::new();
+                device["device_number"] = value(0);
+                device["port"] = value(port.as_str());
+                device["baud"] = value(i64::from(settings.baud));
+                let mut devices = toml_edit::ArrayOfTables::new();
+                devices.push(device);
+                document["devices"] = Item::ArrayOfTables(devices);
+            }
+            if current.safety != settings.safety {
+                write_safety(document.as_table_mut(), &settings.safety);
+            }
+        }
+        None => return Err(format!("device {} is not in the configuration file", device_number)),
+    }
+
+    let updated = document.to_string();
+    let config = BridgeConfig::parse(&updated)?;
+    let issues = config.issues();
+    if !issues.is_empty() {
+        return Err(issues.to_string());
+    }
+    Ok(updated)
+}
+
+// Set the [safety] keys of `parent`, creating the table if needed
+fn write_safety(parent: &mut Table, safety: &SafetyConfig) {
+    let table = parent.entry("safety").or_insert(toml_edit::table());
+    table["max_data_age_secs"] = value(safety.max_data_age_secs as i64);
+    table["refresh_after_secs"] = value(safety.refresh_after_secs as i64);
+    table["refresh_wait_ms"] = value(safety.refresh_wait_ms as i64);
+    table["on_stale"] = value(safety.on_stale.as_str());
+}
@@ -1,9 +1,26 @@
 // src/connection_manager.rs
-use crate::device_state::DeviceState;
+use crate::device_state::{DeviceState, Maintenance, SafetyOverride};
+use crate::command_history::CommandHistory;
+use crate::command_queue::CommandQueue;
+use crate::config::{AutoResetConfig, CommandApiConfig, FramingConfig, HealthConfig, HeartbeatConfig, IdentityConfig, MaintenanceConfig, ReconnectConfig, RemoteConfig, SafetyConfig, SafetyForceConfig, SerialConfig, SetParkConfig, TemperatureCompensationConfig, VotingPolicy};
 use crate::errors::{Result, BridgeError};
+use crate::device_reset;
+use crate::events::{EventBus, EventKind};
+use crate::fault_injection::{FaultInjector, SharedFaultInjector};
+use crate::firmware_schema::SchemaChecker;
+use crate::health::HealthMonitor;
+use crate::park_capture;
+use crate::temperature_compensation::TemperatureCompensation;
+use crate::self_test::SelfTest;
+use crate::protocol::{self, CommandTimeouts};
+#[cfg(feature = "remote-sensors")]
+use crate::remote_sensor::{self, RemoteSensor};
+use crate::sensor_voting::{self, VotingMember};
+use crate::serial_client::SerialClientContext;
+use crate::session_recording::TrafficTap;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{RwLock, mpsc, oneshot};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn, debug, error};
@@ -14,31 +31,414 @@ pub struct ConnectionInfo {
     pub baud_rate: u32,
 }
 
-#[derive(Debug)]
-pub struct CommandRequest {
-    pub command: String,
-    pub response_sender: oneshot::Sender<Result<String>>,
-}
-
 pub struct ConnectionManager {
     device_state: Arc<RwLock<DeviceState>>,
     current_task: Arc<RwLock<Option<JoinHandle<()>>>>,
     current_cancellation: Arc<RwLock<Option<CancellationToken>>>,
     current_connection: Arc<RwLock<Option<ConnectionInfo>>>,
-    command_sender: Arc<RwLock<Option<mpsc::UnboundedSender<CommandRequest>>>>,
+    command_queue: Arc<RwLock<Option<CommandQueue>>>,
+    // Set while mirroring another bridge instead of driving a serial port
+    #[cfg(feature = "remote-sensors")]
+    remote: Arc<RwLock<Option<RemoteSensor>>>,
+    fault_injector: SharedFaultInjector,
+    traffic: TrafficTap,
+    events: EventBus,
+    health: HealthMonitor,
+    compensation: TemperatureCompensation,
+    history: CommandHistory,
+    self_test: SelfTest,
+    schema: SchemaChecker,
+    command_timeouts: CommandTimeouts,
+    heartbeat: HeartbeatConfig,
+    serial: SerialConfig,
+    framing: FramingConfig,
+    reconnect: ReconnectConfig,
+    auto_reset: AutoResetConfig,
+    // Changed at runtime from the device setup page
+    safety: std::sync::RwLock<SafetyConfig>,
+    identity: IdentityConfig,
+    command_api: CommandApiConfig,
+    safety_force: SafetyForceConfig,
+    maintenance: MaintenanceConfig,
+    set_park: SetParkConfig,
 }
 
 impl ConnectionManager {
     pub fn new(device_state: Arc<RwLock<DeviceState>>) -> Self {
+        let events = EventBus::new();
         Self {
             device_state,
             current_task: Arc::new(RwLock::new(None)),
             current_cancellation: Arc::new(RwLock::new(None)),
             current_connection: Arc::new(RwLock::new(None)),
-            command_sender: Arc::new(RwLock::new(None)),
+            command_queue: Arc::new(RwLock::new(None)),
+            #[cfg(feature = "remote-sensors")]
+            remote: Arc::new(RwLock::new(None)),
+            fault_injector: FaultInjector::shared(false),
+            traffic: TrafficTap::new(),
+            health: HealthMonitor::new(HealthConfig::default(), events.clone()),
+            events,
+            compensation: TemperatureCompensation::default(),
+            history: CommandHistory::default(),
+            self_test: SelfTest::default(),
+            schema: SchemaChecker::default(),
+            command_timeouts: CommandTimeouts::default(),
+            heartbeat: HeartbeatConfig::default(),
+            serial: SerialConfig::default(),
+            framing: FramingConfig::default(),
+            reconnect: ReconnectConfig::default(),
+            auto_reset: AutoResetConfig::default(),
+            safety: std::sync::RwLock::default(),
+            identity: IdentityConfig::default(),
+            command_api: CommandApiConfig::default(),
+            safety_force: SafetyForceConfig::default(),
+            maintenance: MaintenanceConfig::default(),
+            set_park: SetParkConfig::default(),
+        }
+    }
+
+    // Replace the protocol's default per-command timeouts (config overrides)
+    pub fn with_command_timeouts(mut self, command_timeouts: CommandTimeouts) -> Self {
+        self.self_test = SelfTest::new(command_timeouts.clone());
+        self.command_timeouts = command_timeouts;
+        self
+    }
+
+    // How long the command may take to answer, after config overrides
+    pub fn command_timeout(&self, command: &str) -> Duration {
+        self.command_timeouts.timeout_for(command)
+    }
+
+    pub fn with_heartbeat(mut self, heartbeat: HeartbeatConfig) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    // DTR/RTS levels and flow control used whenever the port is opened
+    pub fn with_serial(mut self, serial: SerialConfig) -> Self {
+        self.serial = serial;
+        self
+    }
+
+    // Command wrapper and line terminators for firmware variants
+    pub fn with_framing(mut self, framing: FramingConfig) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    pub fn with_reconnect(mut self, reconnect: ReconnectConfig) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    // Reset the board through DTR/RTS when reconnecting alone does not bring it back
+    pub fn with_auto_reset(mut self, auto_reset: AutoResetConfig) -> Self {
+        self.auto_reset = auto_reset;
+        self
+    }
+
+    pub fn with_safety(mut self, safety: SafetyConfig) -> Self {
+        self.safety = std::sync::RwLock::new(safety);
+        self
+    }
+
+    pub fn safety(&self) -> SafetyConfig {
+        *self.safety.read().unwrap()
+    }
+
+    // Takes effect from the next IsSafe read
+    pub fn set_safety(&self, safety: SafetyConfig) {
+        *self.safety.write().unwrap() = safety;
+    }
+
+    pub fn with_health(mut self, health: HealthConfig) -> Self {
+        self.health = HealthMonitor::new(health, self.events.clone());
+        self
+    }
+
+    pub fn with_temperature_compensation(mut self, config: TemperatureCompensationConfig) -> Self {
+        self.compensation = TemperatureCompensation::new(config);
+        self
+    }
+
+    // Correction of the readings for IMU temperature (/api/compensation)
+    pub fn temperature_compensation(&self) -> TemperatureCompensation {
+        self.compensation.clone()
+    }
+
+    // Heap, reboot and latency trends of every connection made by this manager
+    pub fn health_monitor(&self) -> HealthMonitor {
+        self.health.clone()
+    }
+
+    // Check every firmware reply against the schema of its protocol version (strict_schema)
+    pub fn with_strict_schema(mut self, strict: bool) -> Self {
+        self.schema = SchemaChecker::new(strict);
+        self
+    }
+
+    // Replies that broke their schema, across connections (/api/firmware/schema)
+    pub fn schema_checker(&self) -> SchemaChecker {
+        self.schema.clone()
+    }
+
+    pub fn with_command_history(mut self, size: usize) -> Self {
+        self.history = CommandHistory::new(size);
+        self
+    }
+
+    // Recent commands of every connection made by this manager, with their replies
+    pub fn command_history(&self) -> CommandHistory {
+        self.history.clone()
+    }
+
+    // Startup self-check of the latest serial connection
+    pub fn self_test(&self) -> SelfTest {
+        self.self_test.clone()
+    }
+
+    pub fn with_identity(mut self, identity: IdentityConfig) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    // Names advertised to ASCOM clients in place of the built-in defaults
+    pub fn identity(&self) -> &IdentityConfig {
+        &self.identity
+    }
+
+    pub fn with_command_api(mut self, command_api: CommandApiConfig) -> Self {
+        self.command_api = command_api;
+        self
+    }
+
+    // Gate for raw commands from /api/command: well-formed and on the allowlist unless in expert mode
+    pub fn check_api_command(&self, command: &str) -> Result<()> {
+        protocol::validate_command(command).map_err(BridgeError::InvalidCommand)?;
+        if !self.command_api.allows(command) {
+            return Err(BridgeError::InvalidCommand(format!(
+                "command {} is not allowed on /api/command without expert mode",
+                protocol::command_code(command)
+            )));
+        }
+        Ok(())
+    }
+
+    // Firmware data older than this makes IsSafe false and the state stale
+    pub fn max_data_age_secs(&self) -> u64 {
+        self.safety().max_data_age_secs
+    }
+
+    pub fn with_safety_force(mut self, safety_force: SafetyForceConfig) -> Self {
+        self.safety_force = safety_force;
+        self
+    }
+
+    pub fn with_maintenance(mut self, maintenance: MaintenanceConfig) -> Self {
+        self.maintenance = maintenance;
+        self
+    }
+
+    pub fn with_set_park(mut self, set_park: SetParkConfig) -> Self {
+        self.set_park = set_park;
+        self
+    }
+
+    // Sampling plus the set-park command, for job progress estimates
+    pub fn set_park_timeout(&self) -> Duration {
+        let sampling = Duration::from_millis(self.set_park.interval_ms) * self.set_park.samples.saturating_sub(1) * 2;
+        sampling + self.command_timeout(protocol::SOFTWARE_SET_PARK)
+    }
+
+    // Token and duration limit of /api/safety/force
+    pub fn safety_force(&self) -> &SafetyForceConfig {
+        &self.safety_force
+    }
+
+    // Report `is_safe` from IsSafe and DeviceState until the duration (rounded up to whole seconds)
+    // has passed; a timer then clears the override and publishes safety_force_cleared
+    pub async fn force_safety(
+        &self,
+        device_number: u32,
+        is_safe: bool,
+        duration: Duration,
+        reason: Option<String>,
+    ) -> SafetyOverride {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let expires_at = now.as_secs() + duration.as_secs_f64().ceil().max(1.0) as u64;
+        let safety_override = SafetyOverride {
+            is_safe,
+            expires_at,
+            reason,
+        };
+        self.device_state.write().await.safety_override = Some(safety_override.clone());
+        warn!(
+            "Device {}: IsSafe forced {} for {:.0} s{}",
+            device_number,
+            if is_safe { "safe" } else { "unsafe" },
+            duration.as_secs_f64().ceil(),
+            safety_override.reason.as_deref().map(|reason| format!(" ({})", reason)).unwrap_or_default()
+        );
+        self.events.publish(EventKind::SafetyForced {
+            device_number,
+            is_safe,
+            expires_at,
+            reason: safety_override.reason.clone(),
+        });
+
+        let device_state = self.device_state.clone();
+        let events = self.events.clone();
+        let remaining = Duration::from_secs(expires_at).saturating_sub(now);
+        tokio::spawn(async move {
+            tokio::time::sleep(remaining).await;
+            let mut state = device_state.write().await;
+            // A newer override or an explicit clear replaced this one in the meantime
+            if state.safety_override.as_ref().is_none_or(|current| current.expires_at != expires_at) {
+                return;
+            }
+            state.safety_override = None;
+            drop(state);
+            info!("Device {}: Forced IsSafe expired", device_number);
+            events.publish(EventKind::SafetyForceCleared {
+                device_number,
+                expired: true,
+            });
+        });
+        safety_override
+    }
+
+    // Default and longest duration of /api/maintenance
+    pub fn maintenance(&self) -> &MaintenanceConfig {
+        &self.maintenance
+    }
+
+    // Hold back chat alerts and relay switching for the duration (rounded up to whole seconds);
+    // a timer then ends it and publishes maintenance_ended
+    pub async fn start_maintenance(&self, device_number: u32, duration: Duration, reason: Option<String>) -> Maintenance {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let expires_at = now.as_secs() + duration.as_secs_f64().ceil().max(1.0) as u64;
+        let maintenance = Maintenance {
+            started_at: now.as_secs(),
+            expires_at,
+            reason,
+        };
+        self.device_state.write().await.maintenance = Some(maintenance.clone());
+        warn!(
+            "Device {}: Maintenance mode for {:.0} s{}",
+            device_number,
+            duration.as_secs_f64().ceil(),
+            maintenance.reason.as_deref().map(|reason| format!(" ({})", reason)).unwrap_or_default()
+        );
+        self.events.publish(EventKind::MaintenanceStarted {
+            device_number,
+            expires_at,
+            reason: maintenance.reason.clone(),
+        });
+
+        let device_state = self.device_state.clone();
+        let events = self.events.clone();
+        let remaining = Duration::from_secs(expires_at).saturating_sub(now);
+        tokio::spawn(async move {
+            tokio::time::sleep(remaining).await;
+            let mut state = device_state.write().await;
+            // Extended or ended in the meantime
+            if state.maintenance.as_ref().is_none_or(|current| current.expires_at != expires_at) {
+                return;
+            }
+            state.maintenance = None;
+            drop(state);
+            info!("Device {}: Maintenance mode expired", device_number);
+            events.publish(EventKind::MaintenanceEnded {
+                device_number,
+                expired: true,
+            });
+        });
+        maintenance
+    }
+
+    // End maintenance mode early; false when it was not on
+    pub async fn end_maintenance(&self, device_number: u32) -> bool {
+        let ended = self.device_state.write().await.maintenance.take();
+        if !ended.is_some_and(|maintenance| maintenance.is_active()) {
+            return false;
+        }
+        info!("Device {}: Maintenance mode ended", device_number);
+        self.events.publish(EventKind::MaintenanceEnded {
+            device_number,
+            expired: false,
+        });
+        true
+    }
+
+    // End an override early; false when none was active
+    pub async fn clear_forced_safety(&self, device_number: u32) -> bool {
+        let cleared = self.device_state.write().await.safety_override.take();
+        if !cleared.is_some_and(|safety_override| safety_override.is_active()) {
+            return false;
+        }
+        info!("Device {}: Forced IsSafe cleared", device_number);
+        self.events.publish(EventKind::SafetyForceCleared {
+            device_number,
+            expired: false,
+        });
+        true
+    }
+
+    // Before an IsSafe/DeviceState read: when the cached data is older than refresh_after_secs, queue
+    // an out-of-band status poll and wait up to refresh_wait_ms for its reply
+    pub async fn refresh_stale_state(&self) {
+        let safety = self.safety();
+        if safety.refresh_after_secs == 0 {
+            return;
+        }
+        let last_update = {
+            let state = self.device_state.read().await;
+            if !state.connected || state.is_recent(safety.refresh_after_secs) {
+                return;
+            }
+            state.last_update
+        };
+        // Remote devices are refreshed by their own poll loop
+        #[cfg(feature = "remote-sensors")]
+        if self.remote.read().await.is_some() {
+            return;
+        }
+        let Some(queue) = self.command_queue.read().await.clone() else {
+            return;
+        };
+
+        debug!("ConnectionManager: Cached data is stale, polling status before answering");
+        queue.push_poll(protocol::GET_STATUS);
+        let refreshed = tokio::time::timeout(Duration::from_millis(safety.refresh_wait_ms), async {
+            while self.device_state.read().await.last_update == last_update {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        if refreshed.is_err() {
+            debug!("ConnectionManager: No status reply within {} ms, answering from cache", safety.refresh_wait_ms);
         }
     }
 
+    // Allow the debug fault-injection API to act on this connection's serial pipeline
+    pub fn enable_fault_injection(&self) {
+        *self.fault_injector.lock().unwrap() = FaultInjector::new(true);
+    }
+
+    pub fn fault_injector(&self) -> SharedFaultInjector {
+        self.fault_injector.clone()
+    }
+
+    // Raw serial traffic of every connection made by this manager (recording, diagnostics)
+    pub fn traffic_tap(&self) -> TrafficTap {
+        self.traffic.clone()
+    }
+
+    // Firmware notifications and state changes from every connection made by this manager
+    pub fn event_bus(&self) -> EventBus {
+        self.events.clone()
+    }
+
     pub async fn connect(&self, port: String, baud_rate: u32) -> Result<String> {
         info!("ConnectionManager: Connecting to {} at {} baud", port, baud_rate);
 
@@ -52,26 +452,101 @@ impl ConnectionManager {
             *current_cancel = Some(cancel_token.clone());
         }
 
-        // Create command channel
-        let (cmd_sender, cmd_receiver) = mpsc::unbounded_channel::<CommandRequest>();
+        // Create the prioritized command queue shared with the serial task
+        let command_queue = CommandQueue::new();
         {
-            let mut current_cmd_sender = self.command_sender.write().await;
-            *current_cmd_sender = Some(cmd_sender);
+            let mut current_queue = self.command_queue.write().await;
+            *current_queue = Some(command_queue.clone());
         }
 
         // Start new serial connection task with command support
         let device_state_clone = self.device_state.clone();
         let port_clone = port.clone();
+        let context = SerialClientContext {
+            command_queue,
+            fault_injector: self.fault_injector.clone(),
+            traffic: self.traffic.clone(),
+            heartbeat: self.heartbeat,
+            serial: self.serial,
+            framing: self.framing.clone(),
+            events: self.events.clone(),
+            health: self.health.clone(),
+            compensation: self.compensation.clone(),
+            history: self.history.clone(),
+            self_test: self.self_test.clone(),
+            schema: self.schema.clone(),
+        };
+        let reconnect = self.reconnect;
+        let auto_reset = self.auto_reset;
+        let events = self.events.clone();
         
         let new_task = tokio::spawn(async move {
-            if let Err(e) = crate::serial_client::run_serial_client_with_commands(
-                port_clone,
-                baud_rate,
-                device_state_clone,
-                cancel_token,
-                cmd_receiver,
-            ).await {
-                error!("Serial client error: {}", e);
+            let initial_delay = Duration::from_secs(reconnect.initial_delay_secs);
+            let max_delay = Duration::from_secs(reconnect.max_delay_secs);
+            let mut delay = initial_delay;
+            // Sessions in a row that ended before the link proved itself stable
+            let mut failures = 0u32;
+            
+            loop {
+                let started = std::time::Instant::now();
+                if let Err(e) = crate::serial_client::run_serial_client_with_commands(
+                    port_clone.clone(),
+                    baud_rate,
+                    device_state_clone.clone(),
+                    cancel_token.clone(),
+                    context.clone(),
+                ).await {
+                    error!("Serial client error: {}", e);
+                }
+                
+                if cancel_token.is_cancelled() || !reconnect.enabled {
+                    break;
+                }
+                
+                // A link that stayed up for a while starts the backoff over
+                if started.elapsed() > max_delay {
+                    delay = initial_delay;
+                    failures = 0;
+                }
+                failures += 1;
+                
+                if let Some(method) = auto_reset.method.filter(|_| failures >= auto_reset.after_failures) {
+                    warn!("{} failed {} times in a row - resetting the board via {:?}", port_clone, failures, method);
+                    device_state_clone.write().await.set_error("Device not responding - resetting board");
+                    match device_reset::reset_board(&port_clone, baud_rate, method).await {
+                        Ok(()) => {
+                            events.publish(EventKind::DeviceReset {
+                                port: port_clone.clone(),
+                                method,
+                            });
+                        }
+                        Err(e) => warn!("Board reset failed: {}", e),
+                    }
+                    failures = 0;
+                    delay = initial_delay;
+                    
+                    // Reconnect as soon as the board has booted, re-running the startup handshake
+                    tokio::select! {
+                        _ = cancel_token.cancelled() => break,
+                        _ = tokio::time::sleep(Duration::from_secs(auto_reset.boot_wait_secs)) => {}
+                    }
+                    info!("Reconnecting to {} after reset...", port_clone);
+                    continue;
+                }
+                
+                warn!("Serial link to {} lost - reconnecting in {} seconds", port_clone, delay.as_secs());
+                {
+                    let mut state = device_state_clone.write().await;
+                    state.serial_port = Some(port_clone.clone());
+                    state.set_error(&format!("Serial link lost - reconnecting in {} seconds", delay.as_secs()));
+                }
+                
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = tokio::time::sleep(delay) => {}
+                }
+                delay = (delay * 2).min(max_delay);
+                info!("Reconnecting to {}...", port_clone);
             }
         });
 
@@ -99,6 +574,101 @@ impl ConnectionManager {
         Ok(format!("Connecting to nRF52840 device on {} at {} baud", port, baud_rate))
     }
 
+    // Mirror a sensor served by another bridge; commands are forwarded to its /api/command
+    #[cfg(feature = "remote-sensors")]
+    pub async fn connect_remote(&self, config: RemoteConfig) -> Result<String> {
+        info!("ConnectionManager: Connecting to remote sensor {}", config.url);
+        config.validate().map_err(BridgeError::Config)?;
+        let remote = RemoteSensor::new(config)?;
+        let url = remote.url().to_string();
+
+        self.disconnect_internal().await;
+
+        let cancel_token = CancellationToken::new();
+        {
+            let mut current_cancel = self.current_cancellation.write().await;
+            *current_cancel = Some(cancel_token.clone());
+        }
+        {
+            let mut current_remote = self.remote.write().await;
+            *current_remote = Some(remote.clone());
+        }
+
+        let new_task = tokio::spawn(remote_sensor::run_remote_sensor(
+            remote,
+            self.device_state.clone(),
+            cancel_token,
+        ));
+        {
+            let mut current_task = self.current_task.write().await;
+            *current_task = Some(new_task);
+        }
+        {
+            let mut current_conn = self.current_connection.write().await;
+            *current_conn = Some(ConnectionInfo {
+                port: url.clone(),
+                baud_rate: 0,
+            });
+        }
+        {
+            let mut device_state = self.device_state.write().await;
+            device_state.serial_port = Some(url.clone());
+            device_state.clear_error();
+        }
+
+        Ok(format!("Mirroring remote sensor {}", url))
+    }
+
+    #[cfg(not(feature = "remote-sensors"))]
+    pub async fn connect_remote(&self, config: RemoteConfig) -> Result<String> {
+        Err(BridgeError::Remote(format!(
+            "cannot mirror {}: built without the remote-sensors feature",
+            config.url
+        )))
+    }
+
+    // Turn this device into a vote over redundant sensors managed elsewhere
+    pub async fn connect_voting(&self, device_number: u32, members: Vec<VotingMember>, policy: VotingPolicy) -> Result<String> {
+        self.disconnect_internal().await;
+
+        let cancel_token = CancellationToken::new();
+        {
+            let mut current_cancel = self.current_cancellation.write().await;
+            *current_cancel = Some(cancel_token.clone());
+        }
+
+        let description = format!(
+            "vote of devices {}",
+            members.iter().map(|member| member.device_number.to_string()).collect::<Vec<_>>().join(", ")
+        );
+        let new_task = tokio::spawn(sensor_voting::run_voting(
+            device_number,
+            members,
+            policy,
+            self.device_state.clone(),
+            self.events.clone(),
+            cancel_token,
+        ));
+        {
+            let mut current_task = self.current_task.write().await;
+            *current_task = Some(new_task);
+        }
+        {
+            let mut current_conn = self.current_connection.write().await;
+            *current_conn = Some(ConnectionInfo {
+                port: description.clone(),
+                baud_rate: 0,
+            });
+        }
+        {
+            let mut device_state = self.device_state.write().await;
+            device_state.serial_port = Some(description.clone());
+            device_state.clear_error();
+        }
+
+        Ok(format!("Voting device over {}", description))
+    }
+
     pub async fn disconnect(&self) -> Result<String> {
         info!("ConnectionManager: Disconnecting from device");
         self.disconnect_internal().await;
@@ -113,11 +683,15 @@ impl ConnectionManager {
     }
 
     async fn disconnect_internal(&self) {
-        // Clear command sender first
+        // Clear the command queue first, failing anything still waiting to be sent
         {
-            let mut cmd_sender = self.command_sender.write().await;
-            *cmd_sender = None;
+            let mut command_queue = self.command_queue.write().await;
+            if let Some(queue) = command_queue.take() {
+                queue.fail_all("Disconnected");
+            }
         }
+        #[cfg(feature = "remote-sensors")]
+        self.remote.write().await.take();
 
         // Cancel the current operation
         let cancel_token = {
@@ -156,34 +730,67 @@ impl ConnectionManager {
     }
 
     pub async fn send_command(&self, command: &str) -> Result<String> {
-        let cmd_sender = {
-            let cmd_sender_guard = self.command_sender.read().await;
-            cmd_sender_guard.clone()
+        self.send_command_with_cancel(command, &CancellationToken::new()).await
+    }
+
+    // Same as send_command(), failing with BridgeError::Cancelled as soon as `cancel` fires. A
+    // command given up on, by the token or by dropping this future (an HTTP client that
+    // disconnected), leaves the queue or is abandoned by the serial task like cancel_command()
+    // does, instead of holding back the commands behind it until its timeout
+    pub async fn send_command_with_cancel(&self, command: &str, cancel: &CancellationToken) -> Result<String> {
+        #[cfg(feature = "remote-sensors")]
+        {
+            let remote = self.remote.read().await.clone();
+            if let Some(remote) = remote {
+                debug!("ConnectionManager: Forwarding command {} to {}", command, remote.url());
+                return tokio::select! {
+                    _ = cancel.cancelled() => Err(BridgeError::Cancelled),
+                    result = remote.send_command(command) => result,
+                };
+            }
+        }
+
+        let command_queue = {
+            let queue_guard = self.command_queue.read().await;
+            queue_guard.clone()
         };
 
-        let sender = cmd_sender.ok_or_else(|| {
+        let queue = command_queue.ok_or_else(|| {
             BridgeError::NotConnected
         })?;
 
-        debug!("ConnectionManager: Sending command: {}", command);
+        if !self.device_state.read().await.capabilities.supports(command) {
+            return Err(BridgeError::InvalidCommand(format!(
+                "the connected firmware does not support command {}",
+                protocol::command_code(command)
+            )));
+        }
 
-        let (response_sender, response_receiver) = oneshot::channel();
-        let cmd_request = CommandRequest {
-            command: command.to_string(),
-            response_sender,
-        };
+        debug!("ConnectionManager: Queueing command: {}", command);
 
-        sender.send(cmd_request).map_err(|_| {
-            BridgeError::Device("Command channel closed".to_string())
-        })?;
+        // User/ASCOM commands jump ahead of any queued periodic polls
+        let command_timeout = self.command_timeouts.timeout_for(command);
+        let (id, response_receiver) = queue.push_user(command, command_timeout);
+        let mut abandon = queue.cancel_on_drop(id, command, self.command_api.abort_command.clone());
 
-        // Wait for response with timeout - now waits for actual data response, not just ACK
-        match tokio::time::timeout(Duration::from_secs(15), response_receiver).await {
+        // The serial task enforces the command timeout once the command is written; this outer
+        // limit also covers time spent in the queue and the task's timeout-check granularity
+        let overall_timeout = command_timeout + protocol::QUEUE_GRACE;
+        let outcome = tokio::select! {
+            _ = cancel.cancelled() => {
+                info!("ConnectionManager: Command {} cancelled by its caller", command);
+                return Err(BridgeError::Cancelled);
+            }
+            outcome = tokio::time::timeout(overall_timeout, response_receiver) => outcome,
+        };
+        match outcome {
             Ok(Ok(result)) => {
+                abandon.disarm();
                 debug!("ConnectionManager: Command response received");
                 result
             }
             Ok(Err(_)) => {
+                abandon.disarm();
                 error!("ConnectionManager: Command response channel closed");
                 Err(BridgeError::Device("Command response channel closed".to_string()))
             }
@@ -194,19 +801,47 @@ impl ConnectionManager {
         }
     }
 
+    // Abandon the oldest pending command with this code so it stops holding back other traffic
+    pub async fn cancel_command(&self, command: &str) -> Result<()> {
+        #[cfg(feature = "remote-sensors")]
+        if self.remote.read().await.is_some() {
+            return Err(BridgeError::Remote("commands forwarded to a remote bridge cannot be cancelled".to_string()));
+        }
+        let queue = self.command_queue.read().await.clone().ok_or(BridgeError::NotConnected)?;
+        info!("ConnectionManager: Cancelling command {}", command);
+        queue.cancel(command, self.command_api.abort_command.clone());
+        Ok(())
+    }
+
     pub async fn calibrate_sensor(&self) -> Result<String> {
         info!("ConnectionManager: Starting sensor calibration");
-        self.send_command("06").await
+        self.send_command(protocol::CALIBRATE).await
     }
 
+    // Averaged over [set_park] samples; a remote bridge gets the plain set-park command
     pub async fn set_park_position(&self) -> Result<String> {
         info!("ConnectionManager: Setting park position");
-        self.send_command("0D").await // Use software set park command
+        #[cfg(feature = "remote-sensors")]
+        let remote = self.remote.read().await.is_some();
+        #[cfg(not(feature = "remote-sensors"))]
+        let remote = false;
+        if self.set_park.samples <= 1 || remote {
+            return self.send_command(protocol::SOFTWARE_SET_PARK).await; // Use software set park command
+        }
+        let capture = park_capture::capture(self, &self.set_park).await?;
+        Ok(serde_json::to_string(&capture)?)
+    }
+
+    // Position tolerance in degrees, stored by the firmware in flash
+    pub async fn set_tolerance(&self, degrees: f32) -> Result<String> {
+        let command = protocol::set_tolerance_command(degrees).map_err(BridgeError::InvalidValue)?;
+        info!("ConnectionManager: Setting position tolerance to {:.2}°", degrees);
+        self.send_command(&command).await
     }
 
     pub async fn factory_reset(&self) -> Result<String> {
         info!("ConnectionManager: Performing factory reset");
-        self.send_command("0E").await
+        self.send_command(protocol::FACTORY_RESET).await
     }
 
     pub async fn is_connected(&self) -> bool {
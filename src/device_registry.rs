@@ -0,0 +1,231 @@
+// src/device_registry.rs
+// SafetyMonitor devices served by this bridge, keyed by Alpaca device number
+
+use crate::config_store::ConfigStore;
+use crate::connection_manager::ConnectionManager;
+use crate::device_state::DeviceState;
+use crate::nightly_report::NightlyReports;
+use crate::notifications::Notifications;
+use crate::session_log::SessionLog;
+#[cfg(feature = "telescope-client")]
+use crate::telescope_manager::TelescopeRegistry;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+#[derive(Clone)]
+pub struct DeviceHandle {
+    pub device_number: u32,
+    pub device_state: Arc<RwLock<DeviceState>>,
+    pub connection_manager: Arc<ConnectionManager>,
+}
+
+impl DeviceHandle {
+    // Alpaca device type, as used in the /api/v1/{type}/{number}/ paths (case aside)
+    pub fn device_type(&self) -> &'static str {
+        AlpacaDeviceType::SafetyMonitor.name()
+    }
+}
+
+// Device types of the Alpaca device API. The bridge serves SafetyMonitors; ObservingConditions
+// and Switch paths are recognised but have no instances yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlpacaDeviceType {
+    SafetyMonitor,
+    ObservingConditions,
+    Switch,
+}
+
+impl AlpacaDeviceType {
+    // Path segment such as "safetymonitor"; clients differ in case
+    pub fn parse(segment: &str) -> Option<Self> {
+        [Self::SafetyMonitor, Self::ObservingConditions, Self::Switch]
+            .into_iter()
+            .find(|device_type| device_type.name().eq_ignore_ascii_case(segment))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SafetyMonitor => "SafetyMonitor",
+            Self::ObservingConditions => "ObservingConditions",
+            Self::Switch => "Switch",
+        }
+    }
+}
+
+// Resolves the {type}/{number} of an Alpaca device API path, so handlers need not know how the
+// devices of each type are kept
+pub trait AlpacaDevices {
+    fn alpaca_device(&self, device_type: AlpacaDeviceType, device_number: u32) -> Option<&DeviceHandle>;
+}
+
+// Summary row for /api/devices
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceSummary {
+    pub device_number: u32,
+    pub name: String,
+    pub unique_id: String,
+    pub serial_port: Option<String>,
+    pub connected: bool,
+    pub is_safe: bool,
+    pub stale: bool,
+    // IsSafe is currently forced through /api/safety/force
+    pub forced: bool,
+    // Maintenance mode is on (/api/maintenance)
+    pub maintenance: bool,
+}
+
+// Entry of /management/v1/configureddevices
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ConfiguredDevice {
+    pub device_name: String,
+    pub device_type: &'static str,
+    pub device_number: u32,
+    #[serde(rename = "UniqueID")]
+    pub unique_id: String,
+}
+
+// Built once at startup and shared read-only by the HTTP handlers
+#[derive(Clone, Default)]
+pub struct DeviceRegistry {
+    devices: Arc<BTreeMap<u32, DeviceHandle>>,
+    // Every device's events merged into one timeline, see session_log::run_session_log()
+    session_log: SessionLog,
+    // Running and finished nights, see nightly_report::run_nightly_reports()
+    nightly_reports: NightlyReports,
+    // Snoozes and duplicate suppression of the chat bot and other channels
+    notifications: Notifications,
+    // The --config file, edited from the setup pages
+    config_store: ConfigStore,
+    // The mounts the sensors sit on ([[telescopes]])
+    #[cfg(feature = "telescope-client")]
+    telescopes: TelescopeRegistry,
+}
+
+impl DeviceRegistry {
+    pub fn new(devices: Vec<DeviceHandle>) -> Self {
+        Self {
+            devices: Arc::new(devices.into_iter().map(|device| (device.device_number, device)).collect()),
+            session_log: SessionLog::default(),
+            nightly_reports: NightlyReports::default(),
+            notifications: Notifications::default(),
+            config_store: ConfigStore::default(),
+            #[cfg(feature = "telescope-client")]
+            telescopes: TelescopeRegistry::default(),
+        }
+    }
+
+    pub fn with_notifications(mut self, notifications: Notifications) -> Self {
+        self.notifications = notifications;
+        self
+    }
+
+    pub fn with_config_store(mut self, config_store: ConfigStore) -> Self {
+        self.config_store = config_store;
+        self
+    }
+
+    #[cfg(feature = "telescope-client")]
+    pub fn with_telescopes(mut self, telescopes: TelescopeRegistry) -> Self {
+        self.telescopes = telescopes;
+        self
+    }
+
+    #[cfg(feature = "telescope-client")]
+    pub fn telescopes(&self) -> &TelescopeRegistry {
+        &self.telescopes
+    }
+
+    // The classic single-sensor setup: one device, number 0
+    pub fn single(device_state: Arc<RwLock<DeviceState>>, connection_manager: Arc<ConnectionManager>) -> Self {
+        Self::new(vec![DeviceHandle {
+            device_number: 0,
+            device_state,
+            connection_manager,
+        }])
+    }
+
+    pub fn get(&self, device_number: u32) -> Option<&DeviceHandle> {
+        self.devices.get(&device_number)
+    }
+
+    // Device used by the web interface and its API (the lowest device number)
+    pub fn primary(&self) -> &DeviceHandle {
+        self.devices.values().next().expect("device registry is empty")
+    }
+
+    pub fn devices(&self) -> impl Iterator<Item = &DeviceHandle> {
+        self.devices.values()
+    }
+
+    pub fn session_log(&self) -> &SessionLog {
+        &self.session_log
+    }
+
+    pub fn nightly_reports(&self) -> &NightlyReports {
+        &self.nightly_reports
+    }
+
+    pub fn notifications(&self) -> &Notifications {
+        &self.notifications
+    }
+
+    pub fn config_store(&self) -> &ConfigStore {
+        &self.config_store
+    }
+
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    pub async fn summaries(&self) -> Vec<DeviceSummary> {
+        let mut summaries = Vec::with_capacity(self.devices.len());
+        for device in self.devices.values() {
+            let manager = &device.connection_manager;
+            let max_data_age = manager.max_data_age_secs();
+            let state = device.device_state.read().await;
+            summaries.push(DeviceSummary {
+                device_number: device.device_number,
+                name: manager.identity().device_name(&state.device_name),
+                unique_id: state.unique_id.clone(),
+                serial_port: state.serial_port.clone(),
+                connected: state.connected,
+                is_safe: state.is_safe_now(max_data_age),
+                stale: state.is_stale(max_data_age),
+                forced: state.active_override().is_some(),
+                maintenance: state.active_maintenance().is_some(),
+            });
+        }
+        summaries
+    }
+
+    // Every registered device with the name and UniqueID it currently advertises, by device number
+    pub async fn configured_devices(&self) -> Vec<ConfiguredDevice> {
+        let mut configured = Vec::with_capacity(self.devices.len());
+        for device in self.devices.values() {
+            let state = device.device_state.read().await;
+            configured.push(ConfiguredDevice {
+                device_name: device.connection_manager.identity().device_name(&state.device_name),
+                device_type: device.device_type(),
+                device_number: device.device_number,
+                unique_id: state.unique_id.clone(),
+            });
+        }
+        configured
+    }
+}
+
+impl AlpacaDevices for DeviceRegistry {
+    fn alpaca_device(&self, device_type: AlpacaDeviceType, device_number: u32) -> Option<&DeviceHandle> {
+        match device_type {
+            AlpacaDeviceType::SafetyMonitor => self.get(device_number),
+            AlpacaDeviceType::ObservingConditions | AlpacaDeviceType::Switch => None,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+// src/device_reset.rs
+// Hardware reset of a hung sensor board through the serial control lines
+
+use crate::config::ResetMethod;
+use crate::device_transport::TCP_PREFIX;
+use crate::errors::{BridgeError, Result};
+use std::time::Duration;
+use tracing::{info, warn};
+
+// How long a control line is held asserted before being released
+const PULSE_LENGTH: Duration = Duration::from_millis(100);
+
+// Reset the board behind `port_name`; the port must not be held open by the serial client
+pub async fn reset_board(port_name: &str, baud_rate: u32, method: ResetMethod) -> Result<()> {
+    if port_name.starts_with(TCP_PREFIX) {
+        return Err(BridgeError::Device(format!("{} is a serial server; its control lines are out of reach", port_name)));
+    }
+    info!("Resetting device on {} via {:?}", port_name, method);
+    let port_name = port_name.to_string();
+
+    tokio::task::spawn_blocking(move || pulse(&port_name, baud_rate, method))
+        .await
+        .map_err(|e| BridgeError::Device(format!("Reset task failed: {}", e)))?
+}
+
+fn pulse(port_name: &str, baud_rate: u32, method: ResetMethod) -> Result<()> {
+    let baud_rate = match method {
+        // Opening and closing the port at 1200 baud is the Arduino-style "touch" reset
+        ResetMethod::Touch1200 => 1200,
+        ResetMethod::Dtr | ResetMethod::Rts => baud_rate,
+    };
+    let mut port = serialport::new(port_name, baud_rate)
+        .timeout(Duration::from_millis(500))
+        .open()
+        .map_err(|e| BridgeError::Device(format!("Cannot open {} for reset: {}", port_name, e)))?;
+
+    let result = match method {
+        ResetMethod::Dtr => port
+            .write_data_terminal_ready(false)
+            .and_then(|_| {
+                std::thread::sleep(PULSE_LENGTH);
+                port.write_data_terminal_ready(true)
+            }),
+        ResetMethod::Rts => port
+            .write_request_to_send(true)
+            .and_then(|_| {
+                std::thread::sleep(PULSE_LENGTH);
+                port.write_request_to_send(false)
+            }),
+        ResetMethod::Touch1200 => port.write_data_terminal_ready(false),
+    };
+    drop(port);
+
+    result.map_err(|e| {
+        warn!("Reset pulse on {} failed: {}", port_name, e);
+        BridgeError::Device(format!("Reset pulse on {} failed: {}", port_name, e))
+    })
+}
@@ -1,16 +1,29 @@
 // src/device_state.rs
 // Fixed version with backward compatible nRF52840 response parsing
 
+use crate::protocol::{FirmwareCapabilities, ProtocolVersion};
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+// Firmware data older than this is stale unless the config says otherwise
+pub const DEFAULT_MAX_DATA_AGE_SECS: u64 = 30;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceState {
     // Connection status
     pub connected: bool,
     pub serial_port: Option<String>,
     pub error_message: Option<String>,
+    // Seconds since the Unix epoch; RFC 3339 (null before the first update) in JSON, and
+    // bare numbers from older bridges are still accepted
+    #[serde(with = "crate::timestamps::rfc3339_secs")]
     pub last_update: u64,
+    // Connected, but no firmware data within the max data age (evaluated per request)
+    #[serde(default)]
+    pub stale: bool,
+    // Passed the startup self-check (see /api/selftest); bridges predating it did not report it
+    #[serde(default = "operational_by_default")]
+    pub operational: bool,
     
     // Device information (from firmware)
     pub device_name: String,
@@ -36,23 +49,89 @@ pub struct DeviceState {
     // Device capabilities
     pub has_builtin_imu: bool,
     pub storage_available: bool,
+    // Commands and extras the firmware listed at connect time
+    #[serde(default)]
+    pub capabilities: FirmwareCapabilities,
+    // Negotiated from the firmware's <08> reply; decides how replies are parsed
+    #[serde(default)]
+    pub protocol_version: ProtocolVersion,
     
     // System info
     pub uptime: u64,
     pub free_heap: u64,
+    // IMU temperature in °C, from firmware that reports one with its readings
+    #[serde(default)]
+    pub temperature: Option<f32>,
     
     // ASCOM client connection state (separate from hardware)
     pub ascom_connected: bool,
     
     // Unique device identifier
     pub unique_id: String,
+
+    // IsSafe forced through /api/safety/force for testing client shutdown sequences
+    #[serde(default)]
+    pub safety_override: Option<SafetyOverride>,
+    // The Sun is above [sun_safety] max_altitude, which makes IsSafe false
+    #[serde(default)]
+    pub sun_unsafe: bool,
+    // Name of the [[safety_schedules]] window in force, which makes IsSafe false
+    #[serde(default)]
+    pub safety_schedule: Option<String>,
+    // Maintenance mode set through /api/maintenance; IsSafe is unaffected, alerts and relay
+    // switching are held back
+    #[serde(default)]
+    pub maintenance: Option<Maintenance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyOverride {
+    pub is_safe: bool,
+    // Seconds since the Unix epoch; the sensor's own IsSafe applies again afterwards
+    #[serde(with = "crate::timestamps::rfc3339_secs")]
+    pub expires_at: u64,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Maintenance {
+    #[serde(with = "crate::timestamps::rfc3339_secs")]
+    pub started_at: u64,
+    // Seconds since the Unix epoch; alerts and relays resume afterwards
+    #[serde(with = "crate::timestamps::rfc3339_secs")]
+    pub expires_at: u64,
+    pub reason: Option<String>,
+}
+
+impl Maintenance {
+    pub fn is_active(&self) -> bool {
+        unix_now() < self.expires_at
+    }
+}
+
+fn operational_by_default() -> bool {
+    true
+}
+
+impl SafetyOverride {
+    pub fn is_active(&self) -> bool {
+        unix_now() < self.expires_at
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
 }
 
 // Firmware response structures to match nRF52840 JSON output
 #[derive(Debug, Deserialize)]
 pub struct FirmwareResponse {
-    pub status: String,  // "ack", "ok", "error"
+    pub status: String,  // "ack", "ok", "error", "event", "progress"
     pub command: Option<String>,
+    pub event: Option<String>,  // name of an unsolicited "event" message
     pub data: Option<serde_json::Value>,
     pub message: Option<String>,
 }
@@ -138,6 +217,8 @@ impl DeviceState {
             serial_port: None,
             error_message: None,
             last_update: 0,
+            stale: false,
+            operational: false,
             
             // Device defaults
             device_name: "Telescope Park Sensor".to_string(),
@@ -161,16 +242,24 @@ impl DeviceState {
             // Capabilities
             has_builtin_imu: true,
             storage_available: true,
+            capabilities: FirmwareCapabilities::default(),
+            protocol_version: ProtocolVersion::default(),
             
             // System defaults
             uptime: 0,
             free_heap: 0,
+            temperature: None,
             
             // ASCOM defaults
             ascom_connected: false,
             
             // Generate unique ID using UUID
             unique_id: uuid::Uuid::new_v4().to_string(),
+
+            safety_override: None,
+            sun_unsafe: false,
+            safety_schedule: None,
+            maintenance: None,
         }
     }
     
@@ -193,6 +282,9 @@ impl DeviceState {
     
     pub fn reset_to_disconnected(&mut self) {
         self.connected = false;
+        self.operational = false;
+        self.capabilities = FirmwareCapabilities::default();
+        self.protocol_version = ProtocolVersion::default();
         self.serial_port = None;
         self.error_message = None;
         self.current_pitch = 0.0;
@@ -209,6 +301,115 @@ impl DeviceState {
             .as_secs();
         now.saturating_sub(self.last_update) <= max_age_seconds
     }
+
+    pub fn is_stale(&self, max_age_seconds: u64) -> bool {
+        self.connected && !self.is_recent(max_age_seconds)
+    }
+
+    // Unexpired override set through /api/safety/force
+    pub fn active_override(&self) -> Option<&SafetyOverride> {
+        self.safety_override.as_ref().filter(|safety_override| safety_override.is_active())
+    }
+
+    // Unexpired maintenance mode set through /api/maintenance
+    pub fn active_maintenance(&self) -> Option<&Maintenance> {
+        self.maintenance.as_ref().filter(|maintenance| maintenance.is_active())
+    }
+
+    // ASCOM IsSafe: a forced value while an override is active; otherwise never safe while
+    // disconnected, before the startup self-check passed, when the data is stale, while the Sun
+    // is up or during a scheduled unsafe window
+    pub fn is_safe_now(&self, max_age_seconds: u64) -> bool {
+        if let Some(safety_override) = self.active_override() {
+            return safety_override.is_safe;
+        }
+        self.connected && self.operational && self.is_safe && !self.is_stale(max_age_seconds) && !self.held_unsafe()
+    }
+
+    // The Sun or a safety schedule rules IsSafe out, whatever the sensor reports
+    pub fn held_unsafe(&self) -> bool {
+        self.sun_unsafe || self.safety_schedule.is_some()
+    }
+
+    // Copy for API responses with the stale flag evaluated now; an active override replaces
+    // is_safe, and the Sun being up or a safety schedule clears it, so dashboards and chained
+    // bridges see the IsSafe clients get
+    pub fn snapshot(&self, max_age_seconds: u64) -> DeviceState {
+        let mut snapshot = self.clone();
+        snapshot.stale = self.is_stale(max_age_seconds);
+        snapshot.safety_override = self.active_override().cloned();
+        snapshot.maintenance = self.active_maintenance().cloned();
+        if let Some(safety_override) = &snapshot.safety_override {
+            snapshot.is_safe = safety_override.is_safe;
+        } else if self.held_unsafe() {
+            snapshot.is_safe = false;
+        }
+        snapshot
+    }
+    
+    // Mirror the state reported by a remote bridge, keeping this bridge's identity and ASCOM state
+    pub fn update_from_remote(&mut self, remote: &DeviceState) {
+        self.device_name = remote.device_name.clone();
+        self.device_version = remote.device_version.clone();
+        self.manufacturer = remote.manufacturer.clone();
+        self.platform = remote.platform.clone();
+        self.imu = remote.imu.clone();
+        self.current_pitch = remote.current_pitch;
+        self.current_roll = remote.current_roll;
+        self.park_pitch = remote.park_pitch;
+        self.park_roll = remote.park_roll;
+        self.position_tolerance = remote.position_tolerance;
+        self.is_parked = remote.is_parked;
+        // Stale data upstream is unsafe here too
+        self.is_safe = remote.connected && remote.is_safe && !remote.stale;
+        self.operational = remote.operational;
+        self.is_calibrated = remote.is_calibrated;
+        self.has_builtin_imu = remote.has_builtin_imu;
+        self.storage_available = remote.storage_available;
+        self.capabilities = remote.capabilities.clone();
+        self.protocol_version = remote.protocol_version;
+        self.uptime = remote.uptime;
+        self.free_heap = remote.free_heap;
+        self.temperature = remote.temperature;
+
+        self.connected = remote.connected;
+        self.error_message = match (&remote.error_message, remote.connected) {
+            (Some(error), _) => Some(format!("Remote: {}", error)),
+            (None, false) => Some("Remote bridge has no device connected".to_string()),
+            (None, true) => None,
+        };
+        self.update_timestamp();
+    }
+
+    // Voting device: position data from one available sensor, IsSafe from the vote
+    pub fn update_from_vote(&mut self, reference: &DeviceState, is_safe: bool) {
+        self.current_pitch = reference.current_pitch;
+        self.current_roll = reference.current_roll;
+        self.park_pitch = reference.park_pitch;
+        self.park_roll = reference.park_roll;
+        self.position_tolerance = reference.position_tolerance;
+        self.temperature = reference.temperature;
+        self.is_calibrated = reference.is_calibrated;
+        self.is_parked = is_safe;
+        self.is_safe = is_safe;
+        self.connected = true;
+        self.operational = true;
+        self.clear_error();
+        self.update_timestamp();
+    }
+
+    // Mirror a plain Alpaca SafetyMonitor, which only reports IsSafe (and its name)
+    pub fn update_from_remote_is_safe(&mut self, is_safe: bool, name: Option<String>) {
+        if let Some(name) = name {
+            self.device_name = name;
+        }
+        self.is_parked = is_safe;
+        self.is_safe = is_safe;
+        self.connected = true;
+        self.operational = true;
+        self.clear_error();
+        self.update_timestamp();
+    }
     
     // Backward compatible update method - handles both old and new firmware formats
     pub fn update_from_status(&mut self, status: &StatusResponse) {
@@ -304,14 +505,14 @@ impl DeviceState {
     }
     
     // Get connection status summary for web interface
-    pub fn connection_summary(&self) -> String {
+    pub fn connection_summary(&self, max_age_seconds: u64) -> String {
         if !self.connected {
             if let Some(ref error) = self.error_message {
                 format!("Disconnected: {}", error)
             } else {
                 "Disconnected".to_string()
             }
-        } else if self.is_recent(30) {
+        } else if self.is_recent(max_age_seconds) {
             "Connected".to_string()
         } else {
             "Connected (stale data)".to_string()
@@ -329,4 +530,98 @@ impl DeviceState {
             format!("Not Parked (P:{:.1}°, R:{:.1}°)", pitch_diff, roll_diff)
         }
     }
+
+    // Seconds since the last firmware data, None before the first update
+    pub fn data_age_secs(&self) -> Option<u64> {
+        if self.last_update == 0 {
+            return None;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Some(now.saturating_sub(self.last_update))
+    }
+
+    // One-line status for overlays and tickers (/status.txt, /status.json), e.g.
+    // "PARKED | pitch -0.2 roll 0.1 | data 2s old"
+    pub fn status_summary(&self, max_age_seconds: u64) -> StatusSummary {
+        let data_age_secs = self.data_age_secs();
+        let stale = self.is_stale(max_age_seconds);
+        let state = if !self.connected {
+            "disconnected"
+        } else if !self.operational {
+            "not_ready"
+        } else if self.is_parked {
+            "parked"
+        } else {
+            "not_parked"
+        };
+
+        let mut text = if self.connected {
+            let mut text = format!(
+                "{} | pitch {:.1} roll {:.1} | {}",
+                state.replace('_', " ").to_uppercase(),
+                self.current_pitch,
+                self.current_roll,
+                data_age_secs.map_or_else(|| "no data".to_string(), |age| format!("data {} old", format_age(age)))
+            );
+            if stale {
+                text.push_str(" (stale)");
+            }
+            if let Some(safety_override) = self.active_override() {
+                text.push_str(if safety_override.is_safe { " | forced safe" } else { " | forced unsafe" });
+            } else if let Some(schedule) = &self.safety_schedule {
+                text.push_str(&format!(" | schedule {}", schedule));
+            } else if self.sun_unsafe {
+                text.push_str(" | sun up");
+            }
+            text
+        } else {
+            match &self.error_message {
+                Some(error) => format!("DISCONNECTED | {}", error),
+                None => "DISCONNECTED".to_string(),
+            }
+        };
+
+        let maintenance = self.active_maintenance().is_some();
+        if maintenance {
+            text.push_str(" | MAINTENANCE");
+        }
+
+        StatusSummary {
+            state,
+            is_safe: self.is_safe_now(max_age_seconds),
+            maintenance,
+            pitch: self.current_pitch,
+            roll: self.current_roll,
+            data_age_secs,
+            stale,
+            text,
+        }
+    }
+}
+
+// Minimal status for displays that cannot parse the full DeviceState
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSummary {
+    // "parked", "not_parked", "not_ready" (self-check not passed) or "disconnected"
+    pub state: &'static str,
+    pub is_safe: bool,
+    pub pitch: f32,
+    pub roll: f32,
+    pub data_age_secs: Option<u64>,
+    pub stale: bool,
+    // Maintenance mode is on (/api/maintenance)
+    pub maintenance: bool,
+    pub text: String,
+}
+
+// "45s", "12m" or "3h"
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..=119 => format!("{}s", secs),
+        120..=7199 => format!("{}m", secs / 60),
+        _ => format!("{}h", secs / 3600),
+    }
 }
\ No newline at end of file
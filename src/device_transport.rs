@@ -0,0 +1,190 @@
+// src/device_transport.rs
+// Links to the sensor firmware. serial_client runs its polling and command matching over any
+// DeviceTransport: the USB serial port, a TCP socket to a serial server such as ser2net or
+// ESP-Link (a port named "tcp://host:port"), or an in-memory mock for tests
+
+use crate::config::{FlowControlMode, FramingConfig, SerialConfig};
+use crate::errors::{BridgeError, Result};
+use std::future::Future;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_serial::{SerialPortBuilderExt, SerialStream};
+use tracing::{debug, error, warn};
+
+// Port names starting with this are TCP addresses, e.g. "tcp://pier-north.local:4000"
+pub const TCP_PREFIX: &str = "tcp://";
+
+// A serial server that is down or unreachable should fail like a missing port, not hang
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub trait DeviceTransport: Send {
+    // The next response frame without its terminator; None once the device closed the link.
+    // Cancel-safe: a frame cut short in a select! is completed by the next call
+    fn read_line(&mut self) -> impl Future<Output = io::Result<Option<String>>> + Send;
+
+    // Send one command, e.g. "01", framed as the link requires
+    fn write_line(&mut self, command: &str) -> impl Future<Output = io::Result<()>> + Send;
+
+    fn close(&mut self) -> impl Future<Output = io::Result<()>> + Send;
+}
+
+// Commands and responses framed per [framing] over a byte stream
+pub struct StreamTransport<S> {
+    reader: BufReader<ReadHalf<S>>,
+    writer: WriteHalf<S>,
+    framing: FramingConfig,
+    // Bytes of the frame being read, kept when a read is cancelled
+    partial: Vec<u8>,
+}
+
+impl<S: AsyncRead + AsyncWrite> StreamTransport<S> {
+    pub fn new(stream: S, framing: FramingConfig) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self {
+            reader: BufReader::new(reader),
+            writer,
+            framing,
+            partial: Vec::new(),
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Send> DeviceTransport for StreamTransport<S> {
+    // Reads up to the response terminator, which may span several bytes (e.g. CRLF)
+    async fn read_line(&mut self) -> io::Result<Option<String>> {
+        let terminator = self.framing.response_terminator.as_bytes();
+        let last = terminator[terminator.len() - 1];
+        loop {
+            if self.reader.read_until(last, &mut self.partial).await? == 0 {
+                if self.partial.is_empty() {
+                    return Ok(None);
+                }
+                let frame = std::mem::take(&mut self.partial);
+                return Ok(Some(String::from_utf8_lossy(&frame).into_owned()));
+            }
+            if self.partial.ends_with(terminator) {
+                let frame = std::mem::take(&mut self.partial);
+                return Ok(Some(String::from_utf8_lossy(&frame[..frame.len() - terminator.len()]).into_owned()));
+            }
+        }
+    }
+
+    async fn write_line(&mut self, command: &str) -> io::Result<()> {
+        self.writer.write_all(self.framing.encode(command).as_bytes()).await?;
+        self.writer.flush().await
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        self.writer.shutdown().await
+    }
+}
+
+// Open a serial port with the [serial] line settings
+pub fn open_serial(port_name: &str, baud_rate: u32, serial: &SerialConfig, framing: &FramingConfig) -> Result<StreamTransport<SerialStream>> {
+    let mut port = tokio_serial::new(port_name, baud_rate)
+        .timeout(Duration::from_millis(1000))
+        .data_bits(tokio_serial::DataBits::Eight)
+        .flow_control(serial.flow_control.into())
+        .parity(tokio_serial::Parity::None)
+        .stop_bits(tokio_serial::StopBits::One)
+        .open_native_async()
+        .map_err(|e| {
+            error!("Failed to open serial port {}: {}", port_name, e);
+            BridgeError::Serial(e)
+        })?;
+
+    {
+        use tokio_serial::SerialPort;
+        if let Err(e) = port.write_data_terminal_ready(serial.dtr) {
+            warn!("Failed to set DTR: {}", e);
+        } else {
+            debug!("DTR set to {}", serial.dtr);
+        }
+        if serial.flow_control != FlowControlMode::Hardware {
+            if let Err(e) = port.write_request_to_send(serial.rts) {
+                warn!("Failed to set RTS: {}", e);
+            } else {
+                debug!("RTS set to {}", serial.rts);
+            }
+        }
+    }
+
+    Ok(StreamTransport::new(port, framing.clone()))
+}
+
+// Connect to a serial server; `address` is the part after tcp://
+pub async fn connect_tcp(address: &str, framing: &FramingConfig) -> Result<StreamTransport<TcpStream>> {
+    let stream = match tokio::time::timeout(TCP_CONNECT_TIMEOUT, TcpStream::connect(address)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => {
+            error!("Failed to connect to {}{}: {}", TCP_PREFIX, address, e);
+            return Err(BridgeError::Io(e));
+        }
+        Err(_) => {
+            error!("Timed out connecting to {}{}", TCP_PREFIX, address);
+            return Err(BridgeError::Timeout);
+        }
+    };
+    // Commands are single short lines; do not hold them back waiting for more
+    if let Err(e) = stream.set_nodelay(true) {
+        debug!("Cannot disable Nagle's algorithm: {}", e);
+    }
+    Ok(StreamTransport::new(stream, framing.clone()))
+}
+
+// In-memory link, for driving the serial client without a port
+pub struct MockTransport {
+    lines: mpsc::UnboundedReceiver<String>,
+    commands: mpsc::UnboundedSender<String>,
+}
+
+// The firmware side of a MockTransport
+pub struct MockDevice {
+    lines: mpsc::UnboundedSender<String>,
+    commands: mpsc::UnboundedReceiver<String>,
+}
+
+impl MockTransport {
+    pub fn pair() -> (MockTransport, MockDevice) {
+        let (line_sender, lines) = mpsc::unbounded_channel();
+        let (commands, command_receiver) = mpsc::unbounded_channel();
+        let transport = MockTransport { lines, commands };
+        let device = MockDevice {
+            lines: line_sender,
+            commands: command_receiver,
+        };
+        (transport, device)
+    }
+}
+
+impl DeviceTransport for MockTransport {
+    async fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(self.lines.recv().await)
+    }
+
+    async fn write_line(&mut self, command: &str) -> io::Result<()> {
+        self.commands
+            .send(command.to_string())
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "mock device dropped"))
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        self.lines.close();
+        Ok(())
+    }
+}
+
+impl MockDevice {
+    // Send a line as the firmware would; false once the client closed the link
+    pub fn send_line(&self, line: impl Into<String>) -> bool {
+        self.lines.send(line.into()).is_ok()
+    }
+
+    // The next command the client wrote, unframed; None once the client is gone
+    pub async fn next_command(&mut self) -> Option<String> {
+        self.commands.recv().await
+    }
+}
@@ -1,12 +1,111 @@
-use std::net::SocketAddr;
+use crate::config::DiscoveryConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::net::UdpSocket;
-use tracing::{info, error, debug};
+use tracing::{info, error, debug, warn};
 use serde_json::json;
 
-const DISCOVERY_PORT: u16 = 32227;
-const DISCOVERY_MESSAGE: &str = "alpacadiscovery1";
+pub const DISCOVERY_PORT: u16 = 32227;
+pub const DISCOVERY_MESSAGE: &str = "alpacadiscovery1";
 
-pub async fn start_discovery_server(alpaca_port: u16) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+// Per-source discovery statistics, exposed at /api/discovery/clients
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscoveryClient {
+    pub address: IpAddr,
+    pub requests: u64,
+    pub responses: u64,
+    pub throttled: u64,
+    // Seconds since the Unix epoch, serialized as RFC 3339
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub first_seen: u64,
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub last_seen: u64,
+    #[serde(skip)]
+    last_response: Option<Instant>,
+}
+
+// Records who is scanning for Alpaca devices and rate-limits replies per source address
+#[derive(Clone)]
+pub struct DiscoveryTracker {
+    clients: Arc<Mutex<HashMap<IpAddr, DiscoveryClient>>>,
+    config: DiscoveryConfig,
+}
+
+impl Default for DiscoveryTracker {
+    fn default() -> Self {
+        Self::new(DiscoveryConfig::default())
+    }
+}
+
+impl DiscoveryTracker {
+    pub fn new(config: DiscoveryConfig) -> Self {
+        Self {
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            config,
+        }
+    }
+
+    // Record a request and decide whether this source may get a reply now
+    pub fn should_respond(&self, address: IpAddr) -> bool {
+        let now = Instant::now();
+        let min_interval = Duration::from_millis(self.config.min_response_interval_ms);
+        let mut clients = self.clients.lock().unwrap();
+
+        if !clients.contains_key(&address) && clients.len() >= self.config.max_tracked_clients {
+            // Forget the quietest source so a network-wide scan cannot grow the table forever
+            if let Some(oldest) = clients.values().min_by_key(|c| c.last_seen).map(|c| c.address) {
+                clients.remove(&oldest);
+            }
+        }
+
+        let timestamp = unix_now();
+        let client = clients.entry(address).or_insert_with(|| DiscoveryClient {
+            address,
+            requests: 0,
+            responses: 0,
+            throttled: 0,
+            first_seen: timestamp,
+            last_seen: timestamp,
+            last_response: None,
+        });
+        client.requests += 1;
+        client.last_seen = timestamp;
+
+        let throttled = client
+            .last_response
+            .is_some_and(|last| now.duration_since(last) < min_interval);
+        if throttled {
+            client.throttled += 1;
+            if client.throttled == 1 || client.throttled.is_multiple_of(100) {
+                warn!("Throttling discovery requests from {} ({} suppressed so far)", address, client.throttled);
+            }
+            return false;
+        }
+
+        client.responses += 1;
+        client.last_response = Some(now);
+        true
+    }
+
+    // Known sources, most recently seen first
+    pub fn clients(&self) -> Vec<DiscoveryClient> {
+        let mut clients: Vec<DiscoveryClient> = self.clients.lock().unwrap().values().cloned().collect();
+        clients.sort_by_key(|c| std::cmp::Reverse(c.last_seen));
+        clients
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+pub async fn start_discovery_server(alpaca_port: u16, tracker: DiscoveryTracker) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let bind_addr = format!("0.0.0.0:{}", DISCOVERY_PORT);
     let socket = UdpSocket::bind(&bind_addr).await?;
     
@@ -22,7 +121,9 @@ pub async fn start_discovery_server(alpaca_port: u16) -> Result<(), Box<dyn std:
                 debug!("Received discovery message from {}: '{}'", addr, message.trim());
                 
                 if message.trim() == DISCOVERY_MESSAGE {
-                    handle_discovery_request(&socket, addr, alpaca_port).await;
+                    if tracker.should_respond(addr.ip()) {
+                        handle_discovery_request(&socket, addr, alpaca_port).await;
+                    }
                 } else {
                     debug!("Ignoring non-discovery message: '{}'", message.trim());
                 }
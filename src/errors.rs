@@ -28,6 +28,115 @@ pub enum BridgeError {
     
     #[error("Invalid command format: {0}")]
     InvalidCommand(String),
+    
+    #[error("Configuration error: {0}")]
+    Config(String),
+    
+    #[error("Command cancelled")]
+    Cancelled,
+    
+    #[error("Remote sensor error: {0}")]
+    Remote(String),
+    
+    #[error("Relay error: {0}")]
+    Relay(String),
+    
+    #[error("Chat bot error: {0}")]
+    ChatBot(String),
+    
+    #[error("mDNS error: {0}")]
+    Mdns(String),
+    
+    #[error("Telescope error: {0}")]
+    Telescope(#[from] TelescopeError),
+    
+    // Alpaca device API requests naming something the bridge does not serve
+    #[error("Invalid device number: {0}")]
+    UnknownDevice(u32),
+    
+    #[error("Unsupported device type: {0}")]
+    UnsupportedDeviceType(String),
+    
+    // Alpaca request parameters
+    #[error("{0}")]
+    InvalidValue(String),
+    
+    #[error("{0}")]
+    ValueNotSet(String),
+    
+    // IsSafe with [safety] on_stale = "error"
+    #[error("Sensor data is stale: no firmware data for {0} s")]
+    StaleData(u64),
+}
+
+// Failures talking to the mount's Alpaca server (telescope_client). Only network failures are
+// worth retrying; the others are the mount's answer and will not change on their own
+#[derive(Error, Debug)]
+pub enum TelescopeError {
+    #[error("HTTP error: {0}")]
+    Http(String),
+    
+    #[error("Alpaca error {code:#x}: {message}")]
+    Alpaca { code: i32, message: String },
+    
+    #[error("Telescope not connected")]
+    NotConnected,
+    
+    #[error("Not supported by the telescope: {0}")]
+    Unsupported(String),
+}
+
+// ASCOM error numbers with a variant of their own
+const ALPACA_NOT_IMPLEMENTED: i32 = 0x400;
+const ALPACA_INVALID_VALUE: i32 = 0x401;
+const ALPACA_VALUE_NOT_SET: i32 = 0x402;
+const ALPACA_NOT_CONNECTED: i32 = 0x407;
+const ALPACA_STALE_DATA: i32 = 0x408;
+// Start of the range left to drivers for their own errors
+const ALPACA_DRIVER_ERROR: i32 = 0x500;
+
+impl BridgeError {
+    // ErrorNumber of the Alpaca envelope reporting this error. Unknown devices keep 0x400, which
+    // the bridge has always answered them with.
+    pub fn alpaca_error_number(&self) -> i32 {
+        match self {
+            BridgeError::UnknownDevice(_) | BridgeError::UnsupportedDeviceType(_) => ALPACA_NOT_IMPLEMENTED,
+            BridgeError::InvalidValue(_) | BridgeError::InvalidCommand(_) => ALPACA_INVALID_VALUE,
+            BridgeError::ValueNotSet(_) => ALPACA_VALUE_NOT_SET,
+            BridgeError::NotConnected | BridgeError::Telescope(TelescopeError::NotConnected) => ALPACA_NOT_CONNECTED,
+            BridgeError::StaleData(_) => ALPACA_STALE_DATA,
+            BridgeError::Telescope(TelescopeError::Unsupported(_)) => ALPACA_NOT_IMPLEMENTED,
+            BridgeError::Telescope(TelescopeError::Alpaca { code, .. }) => *code,
+            _ => ALPACA_DRIVER_ERROR,
+        }
+    }
+    
+    // Whether the request itself was wrong, which Alpaca answers with HTTP 400; the device's own
+    // failures travel in the envelope of an HTTP 200 response
+    pub fn is_bad_request(&self) -> bool {
+        matches!(
+            self,
+            BridgeError::UnknownDevice(_)
+                | BridgeError::UnsupportedDeviceType(_)
+                | BridgeError::InvalidValue(_)
+                | BridgeError::ValueNotSet(_)
+        )
+    }
+}
+
+impl TelescopeError {
+    // From the ErrorNumber/ErrorMessage of an Alpaca response
+    pub fn from_alpaca(code: i32, message: impl Into<String>) -> Self {
+        match code {
+            ALPACA_NOT_IMPLEMENTED => TelescopeError::Unsupported(message.into()),
+            ALPACA_NOT_CONNECTED => TelescopeError::NotConnected,
+            _ => TelescopeError::Alpaca { code, message: message.into() },
+        }
+    }
+    
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, TelescopeError::Http(_))
+    }
 }
 
 pub type Result<T> = std::result::Result<T, BridgeError>;
\ No newline at end of file
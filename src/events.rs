@@ -0,0 +1,336 @@
+// src/events.rs
+// Bridge event bus: firmware notifications and notable state changes for API clients
+
+use crate::config::ResetMethod;
+use crate::health::HealthIssue;
+use crate::pagination::Paginated;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::debug;
+
+// Events kept for clients that poll /api/events instead of subscribing
+const RECENT_EVENT_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BridgeEvent {
+    pub id: u64,
+    // Seconds since the Unix epoch, serialized as RFC 3339
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Paginated for BridgeEvent {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn timestamp_secs(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    // Spontaneous {"status":"event"} message from the firmware
+    FirmwareEvent {
+        event: String,
+        data: Option<serde_json::Value>,
+    },
+    // Data response that no pending command or poll was waiting for
+    UnsolicitedResponse { response: String },
+    ParkStateChanged { parked: bool, pitch: f32, roll: f32 },
+    // Redundant sensors of a voting device stopped agreeing on IsSafe
+    SensorDisagreement {
+        device_number: u32,
+        is_safe: bool,
+        votes: Vec<SensorVote>,
+    },
+    // Firmware uptime went backwards
+    FirmwareRebooted { previous_uptime: u64, uptime: u64 },
+    HealthWarning { issue: HealthIssue, message: String },
+    // Intermediate calibration report from the firmware while command 06 runs
+    CalibrationProgress { phase: String, percent: Option<u8> },
+    // Board reset through the serial control lines after repeated reconnect failures
+    DeviceReset { port: String, method: ResetMethod },
+    // IsSafe forced through /api/safety/force, until expires_at
+    SafetyForced {
+        device_number: u32,
+        is_safe: bool,
+        #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+        expires_at: u64,
+        reason: Option<String>,
+    },
+    // The override ended, cleared by request or on expiry; the sensor's IsSafe applies again
+    SafetyForceCleared { device_number: u32, expired: bool },
+    // The Sun rose above or set below [sun_safety] max_altitude; forces_unsafe when that makes
+    // IsSafe false rather than only warning
+    SunAltitudeCrossed {
+        above: bool,
+        altitude: f64,
+        max_altitude: f64,
+        forces_unsafe: bool,
+    },
+    // Maintenance mode began through /api/maintenance, until expires_at; alerts and relay
+    // switching are held back meanwhile
+    MaintenanceStarted {
+        device_number: u32,
+        #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+        expires_at: u64,
+        reason: Option<String>,
+    },
+    // Maintenance mode ended, by request or on expiry
+    MaintenanceEnded { device_number: u32, expired: bool },
+    // A [[safety_schedules]] window began (IsSafe false until it ends) or ended
+    SafetyScheduleChanged {
+        device_number: u32,
+        schedule: String,
+        active: bool,
+    },
+    // A [[relays]] output followed the device's IsSafe; error is set when the switch failed
+    RelaySwitched {
+        relay: String,
+        device_number: u32,
+        on: bool,
+        error: Option<String>,
+    },
+    // The connection to a mount's Alpaca server changed; error says why it was lost
+    TelescopeLinkChanged {
+        // [[telescopes]] id
+        telescope: String,
+        state: TelescopeLink,
+        error: Option<String>,
+    },
+    // A mount started or finished a slew, parked or unparked, switched tracking or flipped to the
+    // other side of the pier, as seen by comparing consecutive status polls; ra/dec and pier_side
+    // ("East", "West" or "Unknown") are from the poll that saw it
+    TelescopeChanged {
+        telescope: String,
+        change: TelescopeChange,
+        ra: f64,
+        dec: f64,
+        pier_side: String,
+    },
+    // A night ended; the full report is at /api/reports/nightly?night=<night>
+    NightlyReport {
+        // Date the night began, in the display timezone
+        night: String,
+        summary: String,
+    },
+}
+
+// Broad kinds of events, for subscribers that only want some of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventCategory {
+    // IsSafe overrides, maintenance mode, disagreeing sensors, the Sun rising or setting, safety
+    // schedules, the relays following IsSafe and the nightly reports
+    Safety,
+    // The mount parking and unparking, and the link to its Alpaca server
+    Telescope,
+    // Firmware messages, reboots, health warnings, calibration and board resets
+    Sensor,
+}
+
+impl std::str::FromStr for EventCategory {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.trim() {
+            "safety" => Ok(Self::Safety),
+            "telescope" => Ok(Self::Telescope),
+            "sensor" => Ok(Self::Sensor),
+            other => Err(format!("Unknown event category '{}': use safety, telescope or sensor", other)),
+        }
+    }
+}
+
+// The "type" of every event kind, as sent on /api/events
+pub const EVENT_TYPES: &[&str] = &[
+    "firmware_event",
+    "unsolicited_response",
+    "park_state_changed",
+    "sensor_disagreement",
+    "firmware_rebooted",
+    "health_warning",
+    "calibration_progress",
+    "device_reset",
+    "safety_forced",
+    "safety_force_cleared",
+    "sun_altitude_crossed",
+    "maintenance_started",
+    "maintenance_ended",
+    "safety_schedule_changed",
+    "relay_switched",
+    "telescope_link_changed",
+    "telescope_changed",
+    "nightly_report",
+];
+
+impl EventKind {
+    // The "type" field, one of EVENT_TYPES
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::FirmwareEvent { .. } => "firmware_event",
+            Self::UnsolicitedResponse { .. } => "unsolicited_response",
+            Self::ParkStateChanged { .. } => "park_state_changed",
+            Self::SensorDisagreement { .. } => "sensor_disagreement",
+            Self::FirmwareRebooted { .. } => "firmware_rebooted",
+            Self::HealthWarning { .. } => "health_warning",
+            Self::CalibrationProgress { .. } => "calibration_progress",
+            Self::DeviceReset { .. } => "device_reset",
+            Self::SafetyForced { .. } => "safety_forced",
+            Self::SafetyForceCleared { .. } => "safety_force_cleared",
+            Self::SunAltitudeCrossed { .. } => "sun_altitude_crossed",
+            Self::MaintenanceStarted { .. } => "maintenance_started",
+            Self::MaintenanceEnded { .. } => "maintenance_ended",
+            Self::SafetyScheduleChanged { .. } => "safety_schedule_changed",
+            Self::RelaySwitched { .. } => "relay_switched",
+            Self::TelescopeLinkChanged { .. } => "telescope_link_changed",
+            Self::TelescopeChanged { .. } => "telescope_changed",
+            Self::NightlyReport { .. } => "nightly_report",
+        }
+    }
+
+    pub fn category(&self) -> EventCategory {
+        match self {
+            Self::SensorDisagreement { .. }
+            | Self::SafetyForced { .. }
+            | Self::SafetyForceCleared { .. }
+            | Self::SunAltitudeCrossed { .. }
+            | Self::SafetyScheduleChanged { .. }
+            | Self::MaintenanceStarted { .. }
+            | Self::MaintenanceEnded { .. }
+            | Self::RelaySwitched { .. }
+            | Self::NightlyReport { .. } => EventCategory::Safety,
+            Self::ParkStateChanged { .. } | Self::TelescopeLinkChanged { .. } | Self::TelescopeChanged { .. } => {
+                EventCategory::Telescope
+            }
+            Self::FirmwareEvent { .. }
+            | Self::UnsolicitedResponse { .. }
+            | Self::FirmwareRebooted { .. }
+            | Self::HealthWarning { .. }
+            | Self::CalibrationProgress { .. }
+            | Self::DeviceReset { .. } => EventCategory::Sensor,
+        }
+    }
+}
+
+// ?category=safety,telescope on the event feed; every event without it
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EventFilter {
+    pub category: Option<String>,
+}
+
+impl EventFilter {
+    // The categories asked for, empty for all
+    pub fn categories(&self) -> Result<Vec<EventCategory>, String> {
+        let Some(list) = &self.category else {
+            return Ok(Vec::new());
+        };
+        list.split(',').filter(|name| !name.trim().is_empty()).map(str::parse).collect()
+    }
+}
+
+impl BridgeEvent {
+    pub fn in_categories(&self, categories: &[EventCategory]) -> bool {
+        categories.is_empty() || categories.contains(&self.kind.category())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SensorVote {
+    pub device_number: u32,
+    // Disconnected or stale sensors vote unsafe
+    pub available: bool,
+    pub is_safe: bool,
+}
+
+// Connection state of the telescope manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TelescopeLink {
+    Connecting,
+    Connected,
+    // Dropped or never reached; a reconnect is scheduled
+    Lost,
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TelescopeChange {
+    SlewStarted,
+    SlewFinished,
+    Parked,
+    Unparked,
+    TrackingStarted,
+    TrackingStopped,
+    // SideOfPier changed between East and West, i.e. a meridian flip
+    PierFlipped,
+}
+
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<BridgeEvent>,
+    recent: Arc<Mutex<VecDeque<BridgeEvent>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(256);
+        Self {
+            sender,
+            recent: Arc::new(Mutex::new(VecDeque::with_capacity(RECENT_EVENT_LIMIT))),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    pub fn publish(&self, kind: EventKind) -> BridgeEvent {
+        let event = BridgeEvent {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            kind,
+        };
+        debug!("Bridge event {}: {:?}", event.id, event.kind);
+
+        {
+            let mut recent = self.recent.lock().unwrap();
+            if recent.len() == RECENT_EVENT_LIMIT {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        let _ = self.sender.send(event.clone());
+        event
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<BridgeEvent> {
+        self.sender.subscribe()
+    }
+
+    // Buffered events with an id greater than `since` (all buffered events when None)
+    pub fn recent(&self, since: Option<u64>) -> Vec<BridgeEvent> {
+        let recent = self.recent.lock().unwrap();
+        recent
+            .iter()
+            .filter(|event| since.is_none_or(|since| event.id > since))
+            .cloned()
+            .collect()
+    }
+}
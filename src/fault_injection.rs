@@ -0,0 +1,155 @@
+// src/fault_injection.rs
+// Debug-only fault injection for the serial pipeline and the firmware simulator
+// Lets ASCOM client and automation behavior be verified under deliberate failures
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+pub type SharedFaultInjector = Arc<Mutex<FaultInjector>>;
+
+// Longest stale period a plan may ask for, one day
+pub const MAX_STALE_SECS: u64 = 86_400;
+
+// Requested faults; counters are consumed as responses pass through
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FaultPlan {
+    // Silently drop the next N responses
+    #[serde(default)]
+    pub drop_responses: u32,
+    // Corrupt the JSON of the next N responses
+    #[serde(default)]
+    pub corrupt_responses: u32,
+    // Delay every ACK by this many milliseconds
+    #[serde(default)]
+    pub ack_delay_ms: u64,
+    // Withhold all data responses for this many seconds so cached device data goes stale
+    #[serde(default)]
+    pub stale_secs: u64,
+}
+
+impl FaultPlan {
+    // Why the plan cannot be armed, if it asks for more than the injector allows
+    pub fn problem(&self) -> Option<String> {
+        (self.stale_secs > MAX_STALE_SECS)
+            .then(|| format!("stale_secs must be at most {} (one day), not {}", MAX_STALE_SECS, self.stale_secs))
+    }
+}
+
+// What the pipeline should do with a single response line
+#[derive(Debug, PartialEq)]
+pub enum FaultAction {
+    Pass(String),
+    Drop,
+    Delay(Duration, String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FaultInjector {
+    enabled: bool,
+    plan: FaultPlan,
+    stale_until: Option<Instant>,
+    injected_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FaultStatus {
+    pub enabled: bool,
+    pub drop_responses: u32,
+    pub corrupt_responses: u32,
+    pub ack_delay_ms: u64,
+    pub stale_remaining_secs: u64,
+    pub injected_count: u64,
+}
+
+impl FaultInjector {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            ..Self::default()
+        }
+    }
+
+    pub fn shared(enabled: bool) -> SharedFaultInjector {
+        Arc::new(Mutex::new(Self::new(enabled)))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    // Merge a new plan into the active one (counters add up, delays replace); stale periods
+    // are capped at MAX_STALE_SECS
+    pub fn inject(&mut self, plan: FaultPlan) {
+        warn!("Fault injection armed: {:?}", plan);
+        self.plan.drop_responses = self.plan.drop_responses.saturating_add(plan.drop_responses);
+        self.plan.corrupt_responses = self.plan.corrupt_responses.saturating_add(plan.corrupt_responses);
+        self.plan.ack_delay_ms = plan.ack_delay_ms;
+        if plan.stale_secs > 0 {
+            self.stale_until = Instant::now().checked_add(Duration::from_secs(plan.stale_secs.min(MAX_STALE_SECS)));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.plan = FaultPlan::default();
+        self.stale_until = None;
+    }
+
+    pub fn status(&self) -> FaultStatus {
+        FaultStatus {
+            enabled: self.enabled,
+            drop_responses: self.plan.drop_responses,
+            corrupt_responses: self.plan.corrupt_responses,
+            ack_delay_ms: self.plan.ack_delay_ms,
+            stale_remaining_secs: self
+                .stale_until
+                .map(|until| until.saturating_duration_since(Instant::now()).as_secs())
+                .unwrap_or(0),
+            injected_count: self.injected_count,
+        }
+    }
+
+    // Apply any armed faults to one response line
+    pub fn apply(&mut self, line: &str) -> FaultAction {
+        if !self.enabled || line.is_empty() {
+            return FaultAction::Pass(line.to_string());
+        }
+
+        if self.plan.drop_responses > 0 {
+            self.plan.drop_responses -= 1;
+            self.injected_count += 1;
+            warn!("Fault injection: dropping response {}", line);
+            return FaultAction::Drop;
+        }
+
+        let is_json = line.starts_with('{');
+
+        if let Some(until) = self.stale_until {
+            if Instant::now() < until {
+                if is_json && line.contains("\"ok\"") {
+                    self.injected_count += 1;
+                    return FaultAction::Drop;
+                }
+            } else {
+                self.stale_until = None;
+            }
+        }
+
+        if is_json && self.plan.corrupt_responses > 0 {
+            self.plan.corrupt_responses -= 1;
+            self.injected_count += 1;
+            let half: String = line.chars().take(line.chars().count() / 2).collect();
+            let corrupted = format!("{}#~corrupt", half);
+            warn!("Fault injection: corrupting response to {}", corrupted);
+            return FaultAction::Pass(corrupted);
+        }
+
+        if is_json && self.plan.ack_delay_ms > 0 && line.contains("\"ack\"") {
+            self.injected_count += 1;
+            return FaultAction::Delay(Duration::from_millis(self.plan.ack_delay_ms), line.to_string());
+        }
+
+        FaultAction::Pass(line.to_string())
+    }
+}
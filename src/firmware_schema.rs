@@ -0,0 +1,301 @@
+// src/firmware_schema.rs
+// Strict checking of firmware replies (strict_schema, --strict-schema) for firmware developers:
+// every JSON line is held against the fields and types the bridge expects for the negotiated
+// protocol version, and an unknown field, a missing one or a value of the wrong type is logged
+// and counted at /api/firmware/schema. The bridge itself stays lenient either way
+
+use crate::protocol::{self, ProtocolVersion, ReplyKind};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+// The schemas below are only deserialized, to check the fields and their types
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Envelope {
+    status: Status,
+    command: Option<String>,
+    event: Option<String>,
+    data: Option<Value>,
+    message: Option<String>,
+}
+
+#[derive(Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ack,
+    Ok,
+    Error,
+    Event,
+    Progress,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct StatusReply {
+    device_name: Option<String>,
+    version: Option<String>,
+    manufacturer: Option<String>,
+    platform: Option<String>,
+    imu: Option<String>,
+    led_status: Option<bool>,
+    parked: bool,
+    calibrated: bool,
+    uptime: Option<u64>,
+    park_pitch: Option<f32>,
+    park_roll: Option<f32>,
+    tolerance: Option<f32>,
+    free_heap: Option<u64>,
+    temperature: Option<f32>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PositionReplyV1 {
+    pitch: f32,
+    roll: f32,
+    timestamp: Option<u64>,
+    temperature: Option<f32>,
+}
+
+// V2 readings may carry the park flags, since the reply names its command
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PositionReplyV2 {
+    pitch: f32,
+    roll: f32,
+    timestamp: Option<u64>,
+    temperature: Option<f32>,
+    parked: Option<bool>,
+    calibrated: Option<bool>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct ParkStatusReply {
+    parked: bool,
+    current_pitch: f32,
+    current_roll: f32,
+    park_pitch: f32,
+    park_roll: f32,
+    tolerance: f32,
+    pitch_diff: Option<f32>,
+    roll_diff: Option<f32>,
+    temperature: Option<f32>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct ParkDefinitionReply {
+    park_pitch: f32,
+    park_roll: f32,
+}
+
+// Firmware speaking v1 has no protocolVersion; the reply that carries it is what negotiates v2
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct VersionReplyV1 {
+    firmware_version: String,
+    device_name: String,
+    manufacturer: String,
+    platform: String,
+    imu: String,
+    bluetooth_ready: Option<bool>,
+    protocol_version: Option<u32>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct VersionReplyV2 {
+    firmware_version: String,
+    device_name: String,
+    manufacturer: String,
+    platform: String,
+    imu: String,
+    bluetooth_ready: Option<bool>,
+    protocol_version: u32,
+}
+
+// Also the help text of <00> and the replies of the tolerance and system info commands
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct MessageReply {
+    message: String,
+    tolerance: Option<f32>,
+    uptime: Option<u64>,
+    free_heap: Option<u64>,
+}
+
+// The structured form of <00>
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HelpReply {
+    commands: Vec<String>,
+    features: Option<Vec<String>>,
+    capabilities: Option<Vec<String>>,
+}
+
+#[allow(dead_code)]
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgressReport {
+    phase: String,
+    percent: Option<u8>,
+}
+
+// GET /api/firmware/schema
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaReport {
+    pub strict: bool,
+    // JSON lines checked since the bridge started
+    pub checked: u64,
+    pub violations: u64,
+    // Keyed by schema, e.g. "envelope", "status" or "position_v2"
+    pub schemas: BTreeMap<String, SchemaViolations>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaViolations {
+    pub count: u64,
+    pub last_problem: String,
+    // The offending line as the firmware sent it
+    pub last_response: String,
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub last_seen: u64,
+}
+
+#[derive(Default)]
+struct Counts {
+    checked: u64,
+    schemas: BTreeMap<String, SchemaViolations>,
+}
+
+// Shared by the connection manager and its serial tasks
+#[derive(Clone, Default)]
+pub struct SchemaChecker {
+    strict: bool,
+    counts: Arc<Mutex<Counts>>,
+}
+
+impl SchemaChecker {
+    pub fn new(strict: bool) -> Self {
+        Self {
+            strict,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    // Check a JSON line from the firmware, spoken to in `version`; does nothing unless strict
+    pub fn check(&self, version: ProtocolVersion, response: &str) {
+        if !self.strict {
+            return;
+        }
+        let problem = match serde_json::from_str::<Value>(response) {
+            Ok(line) => check_line(version, &line).err(),
+            Err(e) => Some(("envelope", e.to_string())),
+        };
+        let mut counts = self.counts.lock().unwrap();
+        counts.checked += 1;
+        let Some((schema, problem)) = problem else {
+            return;
+        };
+        // Repeats of the same problem, say on every poll, are only logged at debug level
+        let repeated = counts.schemas.get(schema).is_some_and(|seen| seen.last_problem == problem);
+        if repeated {
+            debug!("Firmware reply does not match the {} schema: {} in {}", schema, problem, response);
+        } else {
+            warn!("Firmware reply does not match the {} schema: {} in {}", schema, problem, response);
+        }
+        let seen = counts.schemas.entry(schema.to_string()).or_insert_with(|| SchemaViolations {
+            count: 0,
+            last_problem: String::new(),
+            last_response: String::new(),
+            last_seen: 0,
+        });
+        seen.count += 1;
+        seen.last_problem = problem;
+        seen.last_response = response.to_string();
+        seen.last_seen = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    }
+
+    pub fn report(&self) -> SchemaReport {
+        let counts = self.counts.lock().unwrap();
+        SchemaReport {
+            strict: self.strict,
+            checked: counts.checked,
+            violations: counts.schemas.values().map(|seen| seen.count).sum(),
+            schemas: counts.schemas.clone(),
+        }
+    }
+}
+
+// The schema a line breaks and how, if it breaks one
+fn check_line(version: ProtocolVersion, line: &Value) -> Result<(), (&'static str, String)> {
+    let envelope: Envelope = parse(line).map_err(|problem| ("envelope", problem))?;
+    // A v2 reply names its command even before the <08> reply negotiated v2
+    let version = match &envelope.command {
+        Some(_) if envelope.status == Status::Ok => version.max(ProtocolVersion::V2),
+        _ => version,
+    };
+    if version == ProtocolVersion::V2 && envelope.status == Status::Ok && envelope.command.is_none() {
+        return Err(("envelope", "protocol v2 data reply without its command".to_string()));
+    }
+    let Some(data) = &envelope.data else {
+        return Ok(());
+    };
+    match envelope.status {
+        Status::Progress => parse::<ProgressReport>(data).map(drop).map_err(|problem| ("progress", problem)),
+        Status::Ok | Status::Event => check_payload(version, envelope.command.as_deref(), data),
+        Status::Ack | Status::Error => Ok(()),
+    }
+}
+
+fn check_payload(version: ProtocolVersion, command: Option<&str>, data: &Value) -> Result<(), (&'static str, String)> {
+    let code = command.map(protocol::command_code);
+    let has = |field: &str| data.get(field).is_some();
+    let (schema, checked) = match version.classify(command, data) {
+        _ if code == Some(protocol::HELP) || has("commands") => match has("commands") {
+            true => ("help", parse::<HelpReply>(data).map(drop)),
+            false => ("message", parse::<MessageReply>(data).map(drop)),
+        },
+        ReplyKind::Status => ("status", parse::<StatusReply>(data).map(drop)),
+        ReplyKind::Position => match version {
+            ProtocolVersion::V1 => ("position_v1", parse::<PositionReplyV1>(data).map(drop)),
+            ProtocolVersion::V2 => ("position_v2", parse::<PositionReplyV2>(data).map(drop)),
+        },
+        ReplyKind::ParkStatus => ("park_status", parse::<ParkStatusReply>(data).map(drop)),
+        ReplyKind::Version => match version {
+            ProtocolVersion::V1 => ("version_v1", parse::<VersionReplyV1>(data).map(drop)),
+            ProtocolVersion::V2 => ("version_v2", parse::<VersionReplyV2>(data).map(drop)),
+        },
+        ReplyKind::Message => ("message", parse::<MessageReply>(data).map(drop)),
+        _ if code == Some(protocol::GET_PARK) || (code.is_none() && has("parkPitch") && has("parkRoll")) => {
+            ("park_definition", parse::<ParkDefinitionReply>(data).map(drop))
+        }
+        ReplyKind::Unknown => ("unknown", Err(format!("no schema for {}", data))),
+    };
+    checked.map_err(|problem| (schema, problem))
+}
+
+fn parse<T: DeserializeOwned>(value: &Value) -> Result<T, String> {
+    T::deserialize(value).map_err(|e| e.to_string())
+}
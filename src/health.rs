@@ -0,0 +1,303 @@
+// src/health.rs
+// Sensor health monitoring: free heap, firmware reboots, response latency trends and drift of
+// the parked position, raising warnings before a degrading sensor dies mid-session
+
+use crate::config::HealthConfig;
+use crate::events::{EventBus, EventKind};
+use crate::noise::{NoiseStats, NoiseWindow};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+// Round trips averaged into the latency baseline before trend warnings are possible
+const LATENCY_BASELINE_SAMPLES: u32 = 20;
+// Weight of the newest sample in the moving latency average
+const LATENCY_SMOOTHING: f64 = 0.1;
+// Parked readings needed in the drift window before its average means anything
+const PARK_DRIFT_MIN_SAMPLES: u64 = 100;
+const SECS_PER_DAY: u64 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthIssue {
+    HeapShrinking,
+    FrequentReboots,
+    SlowResponses,
+    ParkDrift,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthWarning {
+    pub issue: HealthIssue,
+    pub message: String,
+    // Seconds since the Unix epoch, serialized as RFC 3339
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub since: u64,
+}
+
+// Snapshot served at /api/health
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HealthStatus {
+    pub uptime: Option<u64>,
+    pub free_heap: Option<u64>,
+    // Free heap at the first status after the last (re)boot
+    pub heap_baseline: Option<u64>,
+    pub min_free_heap: Option<u64>,
+    pub reboots: u32,
+    pub reboots_in_window: usize,
+    pub latency_avg_ms: Option<f64>,
+    pub latency_baseline_ms: Option<f64>,
+    pub latency_samples: u64,
+    // Average parked offset from the park definition over the drift window
+    pub park_drift: Option<ParkDrift>,
+    pub warnings: Vec<HealthWarning>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ParkDrift {
+    // Average parked reading minus the park definition, in degrees
+    pub pitch_offset: f64,
+    pub roll_offset: f64,
+    // Larger of the two offsets as a share of the position tolerance
+    pub percent_of_tolerance: f64,
+    pub days: usize,
+    pub samples: u64,
+}
+
+// Parked readings of one UTC day, as offsets from the park definition
+struct ParkDay {
+    day: u64,
+    samples: u64,
+    pitch_sum: f64,
+    roll_sum: f64,
+}
+
+#[derive(Default)]
+struct HealthTracker {
+    status: HealthStatus,
+    reboot_times: VecDeque<u64>,
+    baseline_sum_ms: f64,
+    park_days: VecDeque<ParkDay>,
+    // Park pitch and roll the offsets are measured from
+    park_definition: Option<(f32, f32)>,
+    noise: NoiseWindow,
+}
+
+#[derive(Clone)]
+pub struct HealthMonitor {
+    config: HealthConfig,
+    tracker: Arc<Mutex<HealthTracker>>,
+    events: EventBus,
+}
+
+impl HealthMonitor {
+    pub fn new(config: HealthConfig, events: EventBus) -> Self {
+        Self {
+            config,
+            tracker: Arc::new(Mutex::new(HealthTracker::default())),
+            events,
+        }
+    }
+
+    pub fn status(&self) -> HealthStatus {
+        let mut tracker = self.tracker.lock().unwrap();
+        let now = unix_now();
+        let window = self.config.reboot_window_secs;
+        tracker.reboot_times.retain(|time| now.saturating_sub(*time) <= window);
+        let mut status = tracker.status.clone();
+        status.reboots_in_window = tracker.reboot_times.len();
+        status
+    }
+
+    // Firmware-reported uptime and free heap from a status response
+    pub fn record_status(&self, uptime: Option<u64>, free_heap: Option<u64>) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut tracker = self.tracker.lock().unwrap();
+        let now = unix_now();
+
+        if let Some(uptime) = uptime {
+            if let Some(previous) = tracker.status.uptime.filter(|previous| uptime < *previous) {
+                tracker.status.reboots += 1;
+                tracker.reboot_times.push_back(now);
+                // A fresh boot starts a fresh heap baseline
+                tracker.status.heap_baseline = None;
+                tracker.status.min_free_heap = None;
+                self.clear(&mut tracker, HealthIssue::HeapShrinking);
+                warn!("Firmware rebooted (uptime {} -> {})", previous, uptime);
+                self.events.publish(EventKind::FirmwareRebooted {
+                    previous_uptime: previous,
+                    uptime,
+                });
+
+                let window = self.config.reboot_window_secs;
+                tracker.reboot_times.retain(|time| now.saturating_sub(*time) <= window);
+                let recent = tracker.reboot_times.len();
+                if recent >= self.config.reboot_warning_count as usize {
+                    let message = format!("{} firmware reboots in the last {} seconds", recent, window);
+                    self.raise(&mut tracker, HealthIssue::FrequentReboots, message);
+                }
+            }
+            tracker.status.uptime = Some(uptime);
+        }
+
+        if let Some(free_heap) = free_heap {
+            let baseline = *tracker.status.heap_baseline.get_or_insert(free_heap);
+            tracker.status.free_heap = Some(free_heap);
+            tracker.status.min_free_heap = Some(tracker.status.min_free_heap.map_or(free_heap, |min| min.min(free_heap)));
+
+            let limit = baseline as f64 * (1.0 - self.config.heap_drop_percent / 100.0);
+            if (free_heap as f64) < limit {
+                let message = format!("Free heap dropped from {} to {} bytes since boot", baseline, free_heap);
+                self.raise(&mut tracker, HealthIssue::HeapShrinking, message);
+            } else {
+                self.clear(&mut tracker, HealthIssue::HeapShrinking);
+            }
+        }
+    }
+
+    // Time from writing a command or poll to its data response
+    pub fn record_latency(&self, latency: Duration) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut tracker = self.tracker.lock().unwrap();
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        tracker.status.latency_samples += 1;
+
+        let average = match tracker.status.latency_avg_ms {
+            Some(average) => average + LATENCY_SMOOTHING * (latency_ms - average),
+            None => latency_ms,
+        };
+        tracker.status.latency_avg_ms = Some(average);
+
+        if tracker.status.latency_samples <= LATENCY_BASELINE_SAMPLES as u64 {
+            tracker.baseline_sum_ms += latency_ms;
+            if tracker.status.latency_samples == LATENCY_BASELINE_SAMPLES as u64 {
+                let baseline = tracker.baseline_sum_ms / LATENCY_BASELINE_SAMPLES as f64;
+                tracker.status.latency_baseline_ms = Some(baseline);
+                info!("Sensor response latency baseline: {:.1} ms", baseline);
+            }
+            return;
+        }
+
+        let Some(baseline) = tracker.status.latency_baseline_ms else {
+            return;
+        };
+        if average > baseline * self.config.latency_warning_factor && average > self.config.latency_floor_ms {
+            let message = format!("Average response latency {:.0} ms (baseline {:.0} ms)", average, baseline);
+            self.raise(&mut tracker, HealthIssue::SlowResponses, message);
+        } else {
+            self.clear(&mut tracker, HealthIssue::SlowResponses);
+        }
+    }
+
+    // Pitch/roll noise of the current park session
+    pub fn noise(&self, position_tolerance: f32) -> NoiseStats {
+        self.tracker.lock().unwrap().noise.stats(position_tolerance)
+    }
+
+    // The telescope just parked; noise is measured per park session
+    pub fn park_session_started(&self) {
+        self.tracker.lock().unwrap().noise.clear();
+    }
+
+    // A reading taken while parked: part of the noise window, and compared with the park
+    // definition in day-by-day averages
+    pub fn record_parked_position(&self, pitch: f32, roll: f32, park_pitch: f32, park_roll: f32, tolerance: f32) {
+        let mut tracker = self.tracker.lock().unwrap();
+        tracker.noise.push(pitch, roll);
+        if !self.config.enabled || self.config.park_drift_percent <= 0.0 || tolerance <= 0.0 {
+            return;
+        }
+
+        // Setting the park position or recalibrating starts a new baseline
+        if tracker.park_definition != Some((park_pitch, park_roll)) {
+            if tracker.park_definition.is_some() {
+                info!("Park definition changed, restarting park drift tracking");
+            }
+            tracker.park_definition = Some((park_pitch, park_roll));
+            tracker.park_days.clear();
+            tracker.status.park_drift = None;
+            self.clear(&mut tracker, HealthIssue::ParkDrift);
+        }
+
+        let today = unix_now() / SECS_PER_DAY;
+        let (pitch_offset, roll_offset) = ((pitch - park_pitch) as f64, (roll - park_roll) as f64);
+        match tracker.park_days.back_mut().filter(|day| day.day == today) {
+            Some(day) => {
+                day.samples += 1;
+                day.pitch_sum += pitch_offset;
+                day.roll_sum += roll_offset;
+            }
+            None => tracker.park_days.push_back(ParkDay {
+                day: today,
+                samples: 1,
+                pitch_sum: pitch_offset,
+                roll_sum: roll_offset,
+            }),
+        }
+        let window = self.config.park_drift_window_days as u64;
+        tracker.park_days.retain(|day| today - day.day < window);
+
+        let samples: u64 = tracker.park_days.iter().map(|day| day.samples).sum();
+        let pitch_offset = tracker.park_days.iter().map(|day| day.pitch_sum).sum::<f64>() / samples as f64;
+        let roll_offset = tracker.park_days.iter().map(|day| day.roll_sum).sum::<f64>() / samples as f64;
+        let drift = ParkDrift {
+            pitch_offset,
+            roll_offset,
+            percent_of_tolerance: pitch_offset.abs().max(roll_offset.abs()) / tolerance as f64 * 100.0,
+            days: tracker.park_days.len(),
+            samples,
+        };
+
+        if drift.days >= self.config.park_drift_min_days as usize && samples >= PARK_DRIFT_MIN_SAMPLES {
+            if drift.percent_of_tolerance >= self.config.park_drift_percent {
+                let message = format!(
+                    "Parked position has drifted {:+.2}° pitch, {:+.2}° roll from the park definition over {} days ({:.0}% of the {:.1}° tolerance); recalibrate or set the park position again",
+                    drift.pitch_offset, drift.roll_offset, drift.days, drift.percent_of_tolerance, tolerance
+                );
+                self.raise(&mut tracker, HealthIssue::ParkDrift, message);
+            } else {
+                self.clear(&mut tracker, HealthIssue::ParkDrift);
+            }
+        }
+        tracker.status.park_drift = Some(drift);
+    }
+
+    // Publish a warning the first time an issue appears; later updates only refresh the message
+    fn raise(&self, tracker: &mut HealthTracker, issue: HealthIssue, message: String) {
+        if let Some(existing) = tracker.status.warnings.iter_mut().find(|w| w.issue == issue) {
+            existing.message = message;
+            return;
+        }
+        warn!("Sensor health warning: {}", message);
+        self.events.publish(EventKind::HealthWarning {
+            issue,
+            message: message.clone(),
+        });
+        tracker.status.warnings.push(HealthWarning {
+            issue,
+            message,
+            since: unix_now(),
+        });
+    }
+
+    fn clear(&self, tracker: &mut HealthTracker, issue: HealthIssue) {
+        let before = tracker.status.warnings.len();
+        tracker.status.warnings.retain(|warning| warning.issue != issue);
+        if tracker.status.warnings.len() != before {
+            info!("Sensor health recovered: {:?}", issue);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
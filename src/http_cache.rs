@@ -0,0 +1,47 @@
+// src/http_cache.rs
+// ETag and Cache-Control helpers for polled API responses and the web UI page
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, HeaderValue, Response, StatusCode};
+
+// Pages and polled JSON are always revalidated, answered with 304 while the ETag still matches
+pub const REVALIDATE_CACHE_CONTROL: &str = "no-cache";
+
+// FNV-1a, stable across builds and platforms unlike std's DefaultHasher
+pub fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+// Answer with the body, or 304 Not Modified when the client already holds this version
+pub fn etag_response(request_headers: &HeaderMap, content_type: &str, body: Vec<u8>) -> Response<Body> {
+    let etag = format!("\"{}\"", content_hash(&body));
+    let builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, REVALIDATE_CACHE_CONTROL);
+
+    if if_none_match(request_headers, &etag) {
+        return builder.status(StatusCode::NOT_MODIFIED).body(Body::empty()).unwrap();
+    }
+    builder
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+pub fn if_none_match(request_headers: &HeaderMap, etag: &str) -> bool {
+    request_etags(request_headers).any(|tag| tag == "*" || tag == etag)
+}
+
+// The entity tags of If-None-Match, quoted, without the weak prefix
+pub fn request_etags(request_headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    request_headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value: &HeaderValue| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+}
@@ -0,0 +1,118 @@
+// src/http_metrics.rs
+// Per-route HTTP request counts, error counts and latency histograms, served at /api/metrics
+
+use axum::body::Body;
+use axum::extract::{MatchedPath, State};
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Upper bounds of the latency buckets; slower requests land in a final unbounded bucket
+const BUCKET_BOUNDS_MS: [f64; 12] = [1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyBucket {
+    // Inclusive upper bound; None for the overflow bucket
+    pub le_ms: Option<f64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteMetrics {
+    pub method: String,
+    // Route template, e.g. /api/v1/safetymonitor/:device_number/issafe
+    pub route: String,
+    pub requests: u64,
+    // Responses with a 4xx or 5xx status
+    pub errors: u64,
+    pub latency_avg_ms: f64,
+    pub latency_max_ms: f64,
+    pub latency_buckets: Vec<LatencyBucket>,
+}
+
+#[derive(Default)]
+struct RouteStats {
+    requests: u64,
+    errors: u64,
+    total_ms: f64,
+    max_ms: f64,
+    bucket_counts: [u64; BUCKET_BOUNDS_MS.len() + 1],
+}
+
+#[derive(Clone, Default)]
+pub struct HttpMetrics {
+    routes: Arc<Mutex<HashMap<(String, String), RouteStats>>>,
+}
+
+impl HttpMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, method: &str, route: &str, error: bool, latency: Duration) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| latency_ms <= *bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+
+        let mut routes = self.routes.lock().unwrap();
+        let stats = routes.entry((route.to_string(), method.to_string())).or_default();
+        stats.requests += 1;
+        stats.errors += u64::from(error);
+        stats.total_ms += latency_ms;
+        stats.max_ms = stats.max_ms.max(latency_ms);
+        stats.bucket_counts[bucket] += 1;
+    }
+
+    // Sorted by route, then method
+    pub fn snapshot(&self) -> Vec<RouteMetrics> {
+        let routes = self.routes.lock().unwrap();
+        let mut metrics: Vec<RouteMetrics> = routes
+            .iter()
+            .map(|((route, method), stats)| RouteMetrics {
+                method: method.clone(),
+                route: route.clone(),
+                requests: stats.requests,
+                errors: stats.errors,
+                latency_avg_ms: stats.total_ms / stats.requests.max(1) as f64,
+                latency_max_ms: stats.max_ms,
+                latency_buckets: stats
+                    .bucket_counts
+                    .iter()
+                    .enumerate()
+                    .map(|(index, count)| LatencyBucket {
+                        le_ms: BUCKET_BOUNDS_MS.get(index).copied(),
+                        count: *count,
+                    })
+                    .collect(),
+            })
+            .collect();
+        metrics.sort_by(|a, b| (&a.route, &a.method).cmp(&(&b.route, &b.method)));
+        metrics
+    }
+}
+
+// Route middleware timing each request against its matched route template
+pub async fn track_http_metrics(
+    State(metrics): State<HttpMetrics>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let status = response.status();
+    metrics.record(&method, &route, status.is_client_error() || status.is_server_error(), started.elapsed());
+    response
+}
@@ -0,0 +1,406 @@
+// src/indi_server.rs
+// Minimal INDI server ([indi]): each device appears as an INDI weather device whose
+// WEATHER_STATUS follows IsSafe, so KStars/Ekos observatories on Linux can use the sensor
+// without an Alpaca bridge. The properties are read-only; CONNECTION only reports the serial link.
+
+use crate::device_registry::{DeviceHandle, DeviceRegistry};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+// How often the devices are checked for changes to send to the clients
+const UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+
+// DRIVER_INTERFACE bit of an INDI weather device
+const WEATHER_INTERFACE: u32 = 128;
+
+// A client sending more than this without completing an element is dropped
+const MAX_PENDING_BYTES: usize = 64 * 1024;
+
+// What the INDI properties show of a device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndiReading {
+    pub connected: bool,
+    pub is_safe: bool,
+    pub is_parked: bool,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+impl IndiReading {
+    pub async fn of(device: &DeviceHandle) -> Self {
+        let state = device.device_state.read().await;
+        Self {
+            connected: state.connected,
+            is_safe: state.is_safe_now(device.connection_manager.max_data_age_secs()),
+            is_parked: state.is_parked,
+            pitch: state.current_pitch,
+            roll: state.current_roll,
+        }
+    }
+
+    // State of WEATHER_STATUS: Ekos treats Ok as safe and Alert as unsafe
+    fn status(&self) -> &'static str {
+        match (self.connected, self.is_safe) {
+            (_, true) => "Ok",
+            (true, false) => "Alert",
+            (false, false) => "Idle",
+        }
+    }
+
+    fn parked_light(&self) -> &'static str {
+        match (self.connected, self.is_parked) {
+            (false, _) => "Idle",
+            (true, true) => "Ok",
+            (true, false) => "Alert",
+        }
+    }
+}
+
+// The definitions of one device's properties, or only of `property` when given
+pub fn define_properties(device: &str, property: Option<&str>, reading: &IndiReading) -> String {
+    let timestamp = indi_timestamp();
+    let mut xml = String::new();
+    let wanted = |name: &str| property.is_none_or(|property| property == name);
+    if wanted("CONNECTION") {
+        let _ = write!(
+            xml,
+            "<defSwitchVector device=\"{}\" name=\"CONNECTION\" label=\"Connection\" group=\"Main Control\" state=\"{}\" perm=\"rw\" rule=\"OneOfMany\" timeout=\"60\" timestamp=\"{}\">\n{}</defSwitchVector>\n",
+            escape(device),
+            connection_state(reading),
+            timestamp,
+            connection_switches("defSwitch", reading)
+        );
+    }
+    if wanted("DRIVER_INFO") {
+        let _ = write!(
+            xml,
+            "<defTextVector device=\"{}\" name=\"DRIVER_INFO\" label=\"Driver Info\" group=\"General Info\" state=\"Idle\" perm=\"ro\" timeout=\"60\" timestamp=\"{}\">\n\
+             <defText name=\"DRIVER_NAME\" label=\"Name\">nRF52840 Telescope Park Bridge</defText>\n\
+             <defText name=\"DRIVER_EXEC\" label=\"Exec\">telescope_park_bridge</defText>\n\
+             <defText name=\"DRIVER_VERSION\" label=\"Version\">{}</defText>\n\
+             <defText name=\"DRIVER_INTERFACE\" label=\"Interface\">{}</defText>\n\
+             </defTextVector>\n",
+            escape(device),
+            timestamp,
+            env!("CARGO_PKG_VERSION"),
+            WEATHER_INTERFACE
+        );
+    }
+    if wanted("WEATHER_STATUS") {
+        let _ = write!(
+            xml,
+            "<defLightVector device=\"{}\" name=\"WEATHER_STATUS\" label=\"Status\" group=\"Main Control\" state=\"{}\" timestamp=\"{}\">\n\
+             <defLight name=\"WEATHER_PARKED\" label=\"Telescope parked\">{}</defLight>\n\
+             </defLightVector>\n",
+            escape(device),
+            reading.status(),
+            timestamp,
+            reading.parked_light()
+        );
+    }
+    if wanted("WEATHER_PARAMETERS") {
+        let _ = write!(
+            xml,
+            "<defNumberVector device=\"{}\" name=\"WEATHER_PARAMETERS\" label=\"Parameters\" group=\"Parameters\" state=\"{}\" perm=\"ro\" timeout=\"60\" timestamp=\"{}\">\n\
+             <defNumber name=\"WEATHER_PITCH\" label=\"Pitch (°)\" format=\"%.2f\" min=\"-180\" max=\"180\" step=\"0\">{:.2}</defNumber>\n\
+             <defNumber name=\"WEATHER_ROLL\" label=\"Roll (°)\" format=\"%.2f\" min=\"-180\" max=\"180\" step=\"0\">{:.2}</defNumber>\n\
+             </defNumberVector>\n",
+            escape(device),
+            connection_state(reading),
+            timestamp,
+            reading.pitch,
+            reading.roll
+        );
+    }
+    xml
+}
+
+// Updates of the properties that changed between `previous` and `reading`
+pub fn update_properties(device: &str, previous: &IndiReading, reading: &IndiReading) -> String {
+    let timestamp = indi_timestamp();
+    let device = escape(device);
+    let mut xml = String::new();
+    if previous.connected != reading.connected {
+        xml.push_str(&update_connection(&device, reading));
+    }
+    if previous.status() != reading.status() || previous.parked_light() != reading.parked_light() {
+        let _ = write!(
+            xml,
+            "<setLightVector device=\"{}\" name=\"WEATHER_STATUS\" state=\"{}\" timestamp=\"{}\">\n\
+             <oneLight name=\"WEATHER_PARKED\">{}</oneLight>\n\
+             </setLightVector>\n",
+            device,
+            reading.status(),
+            timestamp,
+            reading.parked_light()
+        );
+    }
+    // Compared as sent, so jitter below the displayed precision is not streamed
+    let shown = |reading: &IndiReading| (format!("{:.2}", reading.pitch), format!("{:.2}", reading.roll), reading.connected);
+    if shown(previous) != shown(reading) {
+        let _ = write!(
+            xml,
+            "<setNumberVector device=\"{}\" name=\"WEATHER_PARAMETERS\" state=\"{}\" timestamp=\"{}\">\n\
+             <oneNumber name=\"WEATHER_PITCH\">{:.2}</oneNumber>\n\
+             <oneNumber name=\"WEATHER_ROLL\">{:.2}</oneNumber>\n\
+             </setNumberVector>\n",
+            device,
+            connection_state(reading),
+            timestamp,
+            reading.pitch,
+            reading.roll
+        );
+    }
+    xml
+}
+
+// CONNECTION as it is; `device` is already escaped
+fn update_connection(device: &str, reading: &IndiReading) -> String {
+    format!(
+        "<setSwitchVector device=\"{}\" name=\"CONNECTION\" state=\"{}\" timestamp=\"{}\">\n{}</setSwitchVector>\n",
+        device,
+        connection_state(reading),
+        indi_timestamp(),
+        connection_switches("oneSwitch", reading)
+    )
+}
+
+fn connection_state(reading: &IndiReading) -> &'static str {
+    if reading.connected { "Ok" } else { "Idle" }
+}
+
+fn connection_switches(element: &str, reading: &IndiReading) -> String {
+    let on_off = |on: bool| if on { "On" } else { "Off" };
+    format!(
+        "<{element} name=\"CONNECT\">{}</{element}>\n<{element} name=\"DISCONNECT\">{}</{element}>\n",
+        on_off(reading.connected),
+        on_off(!reading.connected)
+    )
+}
+
+// INDI timestamps are UTC without a zone suffix
+fn indi_timestamp() -> String {
+    Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&quot;", "\"").replace("&apos;", "'").replace("&lt;", "<").replace("&gt;", ">").replace("&amp;", "&")
+}
+
+// One complete top-level element from the client
+#[derive(Debug, PartialEq)]
+pub struct ClientMessage {
+    pub tag: String,
+    pub attributes: HashMap<String, String>,
+}
+
+// Take the complete elements off the front of `buffer`, leaving a partial one for the next read
+pub fn take_messages(buffer: &mut String) -> Vec<ClientMessage> {
+    let mut messages = Vec::new();
+    loop {
+        let Some(start) = buffer.find('<') else {
+            buffer.clear();
+            return messages;
+        };
+        let Some(open_end) = buffer[start..].find('>').map(|end| start + end) else {
+            buffer.drain(..start);
+            return messages;
+        };
+        let open = &buffer[start + 1..open_end];
+        let self_closing = open.ends_with('/');
+        let open = open.trim_end_matches('/');
+        let tag: String = open.chars().take_while(|c| !c.is_whitespace()).collect();
+        // Declarations, comments and stray closing tags carry nothing
+        if tag.starts_with(['?', '!', '/']) || tag.is_empty() {
+            buffer.drain(..=open_end);
+            continue;
+        }
+        let end = if self_closing {
+            open_end + 1
+        } else {
+            let close = format!("</{}>", tag);
+            match buffer[open_end..].find(&close) {
+                Some(close_at) => open_end + close_at + close.len(),
+                None => {
+                    buffer.drain(..start);
+                    return messages;
+                }
+            }
+        };
+        let attributes = parse_attributes(&open[tag.len()..]);
+        messages.push(ClientMessage { tag, attributes });
+        buffer.drain(..end);
+    }
+}
+
+// name="value" or name='value' pairs of an opening tag
+fn parse_attributes(mut text: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    while let Some(equals) = text.find('=') {
+        let name = text[..equals].trim().to_string();
+        let rest = text[equals + 1..].trim_start();
+        let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let Some(value_end) = rest[1..].find(quote) else {
+            break;
+        };
+        attributes.insert(name, unescape(&rest[1..1 + value_end]));
+        text = &rest[value_end + 2..];
+    }
+    attributes
+}
+
+#[derive(Clone)]
+pub struct IndiServer {
+    devices: DeviceRegistry,
+    max_clients: usize,
+    clients: Arc<AtomicUsize>,
+}
+
+impl IndiServer {
+    pub fn new(devices: DeviceRegistry, max_clients: usize) -> Self {
+        Self {
+            devices,
+            max_clients,
+            clients: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // Accept INDI clients until cancelled
+    pub async fn serve(self, listener: TcpListener, cancel_token: CancellationToken) {
+        if let Ok(addr) = listener.local_addr() {
+            info!("INDI server on {}", addr);
+        }
+        loop {
+            let (stream, peer) = tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("INDI server accept failed: {}", e);
+                        continue;
+                    }
+                },
+            };
+            if self.clients.fetch_add(1, Ordering::SeqCst) >= self.max_clients {
+                self.clients.fetch_sub(1, Ordering::SeqCst);
+                warn!("INDI server refused {}: {} clients already connected", peer, self.max_clients);
+                continue;
+            }
+            let server = self.clone();
+            let cancel_token = cancel_token.clone();
+            tokio::spawn(async move {
+                info!("INDI client {} connected", peer);
+                if let Err(e) = server.serve_client(stream, peer, &cancel_token).await {
+                    debug!("INDI client {}: {}", peer, e);
+                }
+                server.clients.fetch_sub(1, Ordering::SeqCst);
+                info!("INDI client {} disconnected", peer);
+            });
+        }
+    }
+
+    // INDI device names, fixed for the client's session: the Alpaca Name, numbered when two match
+    async fn device_names(&self) -> Vec<(String, DeviceHandle)> {
+        let mut names: Vec<(String, DeviceHandle)> = Vec::with_capacity(self.devices.len());
+        for device in self.devices.devices() {
+            let firmware_name = device.device_state.read().await.device_name.clone();
+            let mut name = device.connection_manager.identity().device_name(&firmware_name);
+            if name.trim().is_empty() || names.iter().any(|(taken, _)| *taken == name) {
+                name = format!("{} {}", name.trim(), device.device_number).trim().to_string();
+            }
+            names.push((name, device.clone()));
+        }
+        names
+    }
+
+    async fn serve_client(&self, stream: TcpStream, peer: SocketAddr, cancel_token: &CancellationToken) -> std::io::Result<()> {
+        let (mut reader, mut writer) = stream.into_split();
+        let names = self.device_names().await;
+        // Readings last sent, for the devices whose properties the client asked for
+        let mut sent: HashMap<String, IndiReading> = HashMap::new();
+        let mut pending = String::new();
+        let mut chunk = [0u8; 4096];
+        let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => return Ok(()),
+                read = reader.read(&mut chunk) => {
+                    let read = read?;
+                    if read == 0 {
+                        return Ok(());
+                    }
+                    pending.push_str(&String::from_utf8_lossy(&chunk[..read]));
+                    let mut reply = String::new();
+                    for message in take_messages(&mut pending) {
+                        reply.push_str(&self.answer(&names, &message, &mut sent).await);
+                    }
+                    if pending.len() > MAX_PENDING_BYTES {
+                        warn!("INDI client {} sent an oversized element, closing", peer);
+                        return Ok(());
+                    }
+                    writer.write_all(reply.as_bytes()).await?;
+                }
+                _ = interval.tick() => {
+                    let mut updates = String::new();
+                    for (name, device) in &names {
+                        let Some(previous) = sent.get(name).copied() else {
+                            continue;
+                        };
+                        let reading = IndiReading::of(device).await;
+                        let update = update_properties(name, &previous, &reading);
+                        if !update.is_empty() {
+                            updates.push_str(&update);
+                            sent.insert(name.clone(), reading);
+                        }
+                    }
+                    if !updates.is_empty() {
+                        writer.write_all(updates.as_bytes()).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    async fn answer(
+        &self,
+        names: &[(String, DeviceHandle)],
+        message: &ClientMessage,
+        sent: &mut HashMap<String, IndiReading>,
+    ) -> String {
+        let device = message.attributes.get("device");
+        let property = message.attributes.get("name").map(String::as_str);
+        let mut reply = String::new();
+        match message.tag.as_str() {
+            "getProperties" => {
+                for (name, handle) in names.iter().filter(|(name, _)| device.is_none_or(|device| device == name)) {
+                    let reading = IndiReading::of(handle).await;
+                    reply.push_str(&define_properties(name, property, &reading));
+                    sent.insert(name.clone(), reading);
+                }
+            }
+            // Connecting and disconnecting is the bridge's business; the client is told how it is
+            "newSwitchVector" if property == Some("CONNECTION") => {
+                if let Some((name, handle)) = names.iter().find(|(name, _)| Some(name) == device) {
+                    reply.push_str(&update_connection(&escape(name), &IndiReading::of(handle).await));
+                }
+            }
+            other => debug!("Ignoring INDI {} from client", other),
+        }
+        reply
+    }
+}
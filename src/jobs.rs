@@ -0,0 +1,221 @@
+// src/jobs.rs
+// Background jobs for slow device operations (calibration, factory reset), so HTTP clients
+// submit them and poll for the result instead of holding a request open for many seconds
+
+use crate::connection_manager::ConnectionManager;
+use crate::errors::BridgeError;
+use crate::events::EventKind;
+use crate::protocol;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+// Finished jobs kept for GET /api/jobs/{id}; the oldest are dropped first
+const FINISHED_JOB_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobOperation {
+    Calibrate,
+    SetPark,
+    FactoryReset,
+}
+
+impl JobOperation {
+    fn command(self) -> &'static str {
+        match self {
+            JobOperation::Calibrate => protocol::CALIBRATE,
+            JobOperation::SetPark => protocol::SOFTWARE_SET_PARK,
+            JobOperation::FactoryReset => protocol::FACTORY_RESET,
+        }
+    }
+
+    fn timeout(self, manager: &ConnectionManager) -> Duration {
+        match self {
+            JobOperation::SetPark => manager.set_park_timeout(),
+            _ => manager.command_timeout(self.command()),
+        }
+    }
+
+    // Set park goes through the averaged capture rather than a single command. Once `cancel`
+    // fires the operation ends right away, and the command it was waiting for is abandoned
+    pub async fn run(self, manager: &ConnectionManager, cancel: &CancellationToken) -> crate::errors::Result<String> {
+        match self {
+            JobOperation::SetPark => tokio::select! {
+                _ = cancel.cancelled() => Err(BridgeError::Cancelled),
+                result = manager.set_park_position() => result,
+            },
+            _ => manager.send_command_with_cancel(self.command(), cancel).await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: u64,
+    pub operation: JobOperation,
+    pub device_number: u32,
+    pub status: JobStatus,
+    // Seconds since the Unix epoch, serialized as RFC 3339
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub submitted_at: u64,
+    pub elapsed_ms: u64,
+    // The operation's command timeout; elapsed / timeout is the best progress estimate the firmware allows
+    pub timeout_ms: u64,
+    // Latest calibration phase and percentage reported by the firmware
+    pub phase: Option<String>,
+    pub percent: Option<u8>,
+    pub response: Option<String>,
+    pub error: Option<String>,
+}
+
+struct JobEntry {
+    job: Job,
+    started: Instant,
+    cancel: CancellationToken,
+}
+
+impl JobEntry {
+    fn snapshot(&self) -> Job {
+        let mut job = self.job.clone();
+        if job.status == JobStatus::Running {
+            job.elapsed_ms = self.started.elapsed().as_millis() as u64;
+        }
+        job
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct JobManager {
+    jobs: Arc<Mutex<BTreeMap<u64, JobEntry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Start the operation in the background and return its job right away
+    pub fn submit(&self, operation: JobOperation, device_number: u32, manager: Arc<ConnectionManager>) -> Job {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let cancel = CancellationToken::new();
+        let timeout = operation.timeout(&manager);
+        let job = Job {
+            id,
+            operation,
+            device_number,
+            status: JobStatus::Running,
+            submitted_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            elapsed_ms: 0,
+            timeout_ms: timeout.as_millis() as u64,
+            phase: None,
+            percent: None,
+            response: None,
+            error: None,
+        };
+        self.jobs.lock().unwrap().insert(
+            id,
+            JobEntry {
+                job: job.clone(),
+                started: Instant::now(),
+                cancel: cancel.clone(),
+            },
+        );
+        info!("Job {}: {:?} on device {} started", id, operation, device_number);
+
+        let jobs = self.clone();
+        let mut events = manager.event_bus().subscribe();
+        tokio::spawn(async move {
+            let command = operation.run(&manager, &cancel);
+            tokio::pin!(command);
+            let outcome = loop {
+                // Progress published before the final response is applied before the job settles
+                tokio::select! {
+                    biased;
+                    Ok(event) = events.recv(), if operation == JobOperation::Calibrate => {
+                        if let EventKind::CalibrationProgress { phase, percent } = event.kind {
+                            jobs.update_progress(id, phase, percent);
+                        }
+                    }
+                    result = &mut command => break result,
+                }
+            };
+            jobs.finish(id, outcome);
+        });
+        job
+    }
+
+    fn update_progress(&self, id: u64, phase: String, percent: Option<u8>) {
+        if let Some(entry) = self.jobs.lock().unwrap().get_mut(&id) {
+            entry.job.phase = Some(phase);
+            entry.job.percent = percent;
+        }
+    }
+
+    fn finish(&self, id: u64, outcome: crate::errors::Result<String>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        // Cancelled jobs are already settled
+        let Some(entry) = jobs.get_mut(&id).filter(|entry| entry.job.status == JobStatus::Running) else {
+            return;
+        };
+        entry.job.elapsed_ms = entry.started.elapsed().as_millis() as u64;
+        match outcome {
+            Ok(response) => {
+                entry.job.status = JobStatus::Succeeded;
+                entry.job.response = Some(response);
+            }
+            Err(e) => {
+                entry.job.status = JobStatus::Failed;
+                entry.job.error = Some(e.to_string());
+            }
+        }
+        info!("Job {}: {:?} finished as {:?}", id, entry.job.operation, entry.job.status);
+
+        let finished: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, entry)| entry.job.status != JobStatus::Running)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in finished.iter().take(finished.len().saturating_sub(FINISHED_JOB_LIMIT)) {
+            jobs.remove(id);
+        }
+    }
+
+    pub fn get(&self, id: u64) -> Option<Job> {
+        self.jobs.lock().unwrap().get(&id).map(JobEntry::snapshot)
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().values().map(JobEntry::snapshot).collect()
+    }
+
+    // Cancel a running job, or forget a finished one. The pending command is abandoned so other
+    // commands can proceed; the firmware itself only stops if an abort_command is configured
+    pub fn cancel(&self, id: u64) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let entry = jobs.get_mut(&id)?;
+        if entry.job.status == JobStatus::Running {
+            entry.cancel.cancel();
+            entry.job.status = JobStatus::Cancelled;
+            entry.job.elapsed_ms = entry.started.elapsed().as_millis() as u64;
+            info!("Job {}: {:?} cancelled", id, entry.job.operation);
+            Some(entry.job.clone())
+        } else {
+            jobs.remove(&id).map(|entry| entry.job)
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// src/lib.rs
+// Library crate shared by the bridge binary, the helper binaries and the integration tests
+
+pub mod device_state;
+pub mod serial_client;
+pub mod device_transport;
+pub mod alpaca_server;
+pub mod alpaca_params;
+pub mod port_discovery;
+pub mod usb_hotplug;
+pub mod connection_manager;
+pub mod discovery_server;
+pub mod alpaca_scan;
+pub mod errors;
+pub mod simulator;
+pub mod fault_injection;
+pub mod bench;
+pub mod command_queue;
+pub mod command_history;
+pub mod protocol;
+pub mod config;
+pub mod config_migrations;
+pub mod config_store;
+pub mod secrets;
+pub mod events;
+pub mod pagination;
+pub mod timestamps;
+pub mod ascom_clients;
+pub mod ascom_profile;
+pub mod transaction_log;
+pub mod session_recording;
+pub mod serial_tee;
+pub mod indi_server;
+pub mod device_registry;
+#[cfg(feature = "remote-sensors")]
+pub mod remote_sensor;
+pub mod sensor_voting;
+pub mod health;
+pub mod noise;
+pub mod park_capture;
+pub mod temperature_compensation;
+pub mod http_metrics;
+pub mod device_reset;
+pub mod jobs;
+pub mod calibration;
+pub mod firmware_schema;
+pub mod http_cache;
+pub mod status_delta;
+pub mod web_users;
+pub mod backups;
+pub mod session_log;
+pub mod nightly_report;
+pub mod notifications;
+pub mod sun_safety;
+pub mod safety_schedule;
+pub mod self_test;
+pub mod server_info;
+pub mod safe_mode;
+pub mod system_log;
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+pub mod status_outputs;
+#[cfg(feature = "relays")]
+pub mod relays;
+#[cfg(feature = "chat-bot")]
+pub mod chat_bot;
+#[cfg(feature = "mdns")]
+pub mod mdns;
+#[cfg(feature = "telescope-client")]
+pub mod telescope_client;
+#[cfg(feature = "telescope-client")]
+pub mod telescope_manager;
+#[cfg(feature = "web-ui")]
+pub mod web_assets;
@@ -1,30 +1,64 @@
 // src/main.rs
 // Add discovery server startup
 
-mod device_state;
-mod serial_client;
-mod alpaca_server;
-mod port_discovery;
-mod connection_manager;
-mod discovery_server;  // Add this line
-mod errors;
-
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, error, warn};
-use tracing_subscriber;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::prelude::*;
 
-use device_state::DeviceState;
-use connection_manager::ConnectionManager;
-use alpaca_server::create_alpaca_server;
-use discovery_server::start_discovery_server;  // Add this line
+use telescope_park_bridge::alpaca_scan::{self, ScanOptions};
+use telescope_park_bridge::ascom_profile::{self, DynamicDriver};
+use telescope_park_bridge::bench::{self, BenchOptions};
+use telescope_park_bridge::config::{self, BridgeConfig, ConfigIssues, IdentityConfig, RemoteConfig, SafetyConfig, SerialConfig};
+use telescope_park_bridge::config_store::ConfigStore;
+use telescope_park_bridge::port_discovery;
+use telescope_park_bridge::usb_hotplug::UsbHotplug;
+use telescope_park_bridge::secrets::{SecretLocation, SecretStore};
+use telescope_park_bridge::self_test::{self, SelfTestStatus};
+use telescope_park_bridge::safe_mode::{self, SafeMode};
+use telescope_park_bridge::server_info;
+use telescope_park_bridge::session_recording;
+use telescope_park_bridge::system_log::{self, SystemLogLayer};
+use telescope_park_bridge::serial_tee::SerialTee;
+use telescope_park_bridge::indi_server::IndiServer;
+use telescope_park_bridge::sensor_voting::VotingMember;
+use telescope_park_bridge::transaction_log::TransactionLog;
+use telescope_park_bridge::device_registry::{DeviceHandle, DeviceRegistry};
+use telescope_park_bridge::device_state::DeviceState;
+use telescope_park_bridge::connection_manager::ConnectionManager;
+use telescope_park_bridge::alpaca_server::{create_alpaca_server, ServerOptions};
+use telescope_park_bridge::discovery_server::{start_discovery_server, DiscoveryTracker};
+use telescope_park_bridge::timestamps;
+use telescope_park_bridge::web_users::{self, WebUsers};
+use telescope_park_bridge::backups::BackupScheduler;
+use telescope_park_bridge::session_log;
+use telescope_park_bridge::nightly_report;
+use telescope_park_bridge::notifications::Notifications;
+use telescope_park_bridge::sun_safety;
+use telescope_park_bridge::safety_schedule;
+#[cfg(feature = "web-ui")]
+use telescope_park_bridge::web_assets::WebAssets;
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+use telescope_park_bridge::status_outputs::{self, StatusOutputs};
+#[cfg(feature = "relays")]
+use telescope_park_bridge::relays::{self, Relay};
+#[cfg(feature = "chat-bot")]
+use telescope_park_bridge::chat_bot::ChatBot;
+#[cfg(feature = "telescope-client")]
+use telescope_park_bridge::telescope_manager::{TelescopeManager, TelescopeRegistry};
+#[cfg(feature = "mdns")]
+use telescope_park_bridge::mdns::MdnsAdvertiser;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    #[arg(short, long, help = "Serial port (e.g., COM3, /dev/ttyUSB0, /dev/ttyACM0)")]
+    #[arg(short, long, help = "Serial port (e.g., COM3, /dev/ttyACM0, or tcp://host:port)")]
     port: Option<String>,
 
     #[arg(short, long, default_value = "115200", help = "Baud rate for serial communication")]
@@ -36,29 +70,238 @@ struct Args {
     #[arg(long, default_value = "11111", help = "HTTP server port for ASCOM Alpaca")]
     http_port: u16,
 
-    #[arg(long, help = "Auto-select first available nRF52840-like device")]
+    #[arg(long, help = "Probe serial ports and connect to the one answering as the park sensor")]
     auto: bool,
 
+    #[arg(long, conflicts_with_all = ["port", "auto"], help = "Mirror the sensor of another bridge instead of a serial port (e.g., http://pier-north:11111)")]
+    remote: Option<String>,
+
     #[arg(short, long, help = "Enable debug logging")]
     debug: bool,
+
+    #[arg(long, global = true, help = "Subcommands print JSON on standard output and log to standard error")]
+    json: bool,
+
+    #[arg(long, help = "Path to a TOML configuration file")]
+    config: Option<String>,
+
+    #[arg(long, help = "Record all serial traffic to this file (JSON lines) for later replay with park-sensor-sim")]
+    record: Option<String>,
+
+    #[arg(long, value_name = "ADDR", help = "Stream the serial traffic read-only over TCP for diagnostic tools (e.g., 127.0.0.1:11112)")]
+    serial_tee: Option<String>,
+
+    #[arg(long, help = "Log every HTTP request (request id, status, latency) at INFO level")]
+    access_log: bool,
+
+    #[arg(long, help = "Log every ASCOM Alpaca device API transaction to this file (JSON lines)")]
+    transaction_log: Option<String>,
+
+    #[arg(long, help = "Allow /api/command to send any well-formed firmware command, including factory reset")]
+    expert_mode: bool,
+
+    #[arg(long, help = "Check every firmware reply against the bridge's schemas and count mismatches at /api/firmware/schema")]
+    strict_schema: bool,
+
+    #[arg(long, help = "Enable the debug fault-injection API (/api/debug/faults) - never use in production")]
+    fault_injection: bool,
+
+    #[arg(long, help = "Serve only the JSON and ASCOM Alpaca APIs, without the web UI")]
+    headless: bool,
+
+    #[arg(long, help = "Exit when the configuration or secrets file does not load, instead of serving the safe-mode repair page")]
+    no_safe_mode: bool,
+
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1", help = "Bind address of the safe-mode repair page, which has no sign-in (on --http-port)")]
+    safe_mode_bind: String,
+
+    #[cfg(feature = "web-ui")]
+    #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = ".", help = "Serve the web UI from templates/ and assets/ below DIR (default: current directory) with live reload, instead of the embedded copy")]
+    dev_assets: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = "Measure serial round-trip latency and throughput (the bridge must not hold the port)")]
+    Bench {
+        #[arg(short, long, help = "Serial port to benchmark (defaults to the global --port)")]
+        port: Option<String>,
+
+        #[arg(long, value_delimiter = ',', help = "Comma-separated baud rates to test (defaults to --baud)")]
+        bauds: Vec<u32>,
+
+        #[arg(short = 'n', long, default_value = "50", help = "Commands to send per baud rate")]
+        count: u32,
+
+        #[arg(long, default_value = "01", help = "Firmware command to time")]
+        command: String,
+
+        #[arg(long, default_value = "3000", help = "Per-command timeout in milliseconds")]
+        timeout_ms: u64,
+    },
+
+    #[command(about = "Broadcast Alpaca discovery and list the servers that answer with their configured devices")]
+    Discover {
+        #[arg(long, default_value = "2000", help = "How long to wait for replies, in milliseconds")]
+        timeout_ms: u64,
+
+        #[arg(long = "target", value_name = "ADDRESS", help = "Send discovery here instead of 255.255.255.255, e.g. a subnet broadcast or a host (repeatable)")]
+        targets: Vec<std::net::IpAddr>,
+
+        #[arg(long, default_value = "32227", help = "UDP discovery port")]
+        discovery_port: u16,
+    },
+
+    #[command(about = "List serial ports, likely park sensors first")]
+    ListPorts,
+
+    #[command(name = "selftest", about = "Run the startup self-check against a device and report each step (the bridge must not hold the port)")]
+    SelfTest {
+        #[arg(short, long, help = "Serial port (defaults to the global --port, then the configured device's port)")]
+        port: Option<String>,
+
+        #[arg(long, default_value = "0", help = "Configured device whose port and settings to use")]
+        device_number: u32,
+
+        #[arg(long, default_value = "30", help = "Give up when the check has not finished after this many seconds")]
+        timeout_secs: u64,
+    },
+
+    #[command(about = "Keep API tokens in the OS keyring (or bridge.secrets.toml next to --config) instead of the config file")]
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsAction,
+    },
+
+    #[command(about = "Print the password_hash for a [[web_auth.users]] entry, reading the password from standard input")]
+    HashPassword,
+
+    #[command(about = "Register a device with the ASCOM Platform as an Alpaca Dynamic Client driver, for COM-only Windows applications")]
+    Ascom {
+        #[command(subcommand)]
+        action: AscomAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum AscomAction {
+    #[command(about = "Write the driver's ASCOM Profile entries (Windows, elevated prompt), or export them with --reg-file")]
+    Register {
+        #[arg(long, default_value = "0", help = "Alpaca device number to register")]
+        device_number: u32,
+
+        #[arg(long, default_value = "127.0.0.1", help = "Address the driver reaches the bridge at (the port is --http-port)")]
+        host: String,
+
+        #[arg(long, help = "Name shown in the ASCOM Chooser (default: the configured device name)")]
+        name: Option<String>,
+
+        #[arg(long, help = "N of the ASCOM.AlpacaDynamicN.SafetyMonitor ProgID (default: a driver already pointing at this device, or the next free number)")]
+        driver_number: Option<u32>,
+
+        #[arg(long, value_name = "FILE", help = "Write a .reg file for regedit instead of the registry, e.g. for another PC")]
+        reg_file: Option<String>,
+    },
+
+    #[command(about = "Remove a dynamic driver's ASCOM Profile entries (Windows, elevated prompt)")]
+    Unregister {
+        #[arg(help = "ProgID such as ASCOM.AlpacaDynamic1.SafetyMonitor")]
+        prog_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretsAction {
+    #[command(about = "Store a secret (e.g. safety_force.token), reading the value from standard input")]
+    Set {
+        name: String,
+
+        #[arg(long, help = "Write to the secrets file instead of the OS keyring")]
+        file: bool,
+    },
+
+    #[command(about = "Remove a secret from the keyring and the secrets file")]
+    Delete { name: String },
+
+    #[command(about = "Show which secrets are set and where; values are never printed")]
+    List,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
+    let console_level = if args.debug { LevelFilter::DEBUG } else { LevelFilter::INFO };
     
-    // Setup logging
-    let subscriber = tracing_subscriber::fmt()
-        .with_max_level(if args.debug { 
-            tracing::Level::DEBUG 
-        } else { 
-            tracing::Level::INFO 
-        })
-        .finish();
+    if let Some(command) = args.command {
+        // Standard output is reserved for the JSON document
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(console_level)
+            .with_writer(if args.json { BoxMakeWriter::new(std::io::stderr) } else { BoxMakeWriter::new(std::io::stdout) })
+            .finish();
+        tracing::subscriber::set_global_default(subscriber)?;
+        let code = match run_subcommand(command, args.port, args.baud, args.http_port, args.config, args.json).await {
+            Ok(code) => code,
+            Err(e) if args.json => {
+                println!("{}", serde_json::json!({ "error": format!("{:#}", e) }));
+                EXIT_ERROR
+            }
+            Err(e) => {
+                eprintln!("Error: {:?}", e);
+                EXIT_ERROR
+            }
+        };
+        std::process::exit(code);
+    }
     
-    tracing::subscriber::set_global_default(subscriber)?;
+    // [logging] decides where messages go, so the file is read first, with its warnings (such
+    // as schema upgrades) going to the console. A file that does not load starts safe mode, which
+    // serves a repair page until it does
+    let mut safe_mode_ran = false;
+    let mut config = loop {
+        let console = tracing_subscriber::fmt().with_max_level(console_level).finish();
+        let error = match tracing::subscriber::with_default(console, || load_config(args.config.as_deref())) {
+            Ok(config) => break config,
+            Err(e) if args.no_safe_mode => return Err(e.into()),
+            Err(e) => e,
+        };
+        if !safe_mode_ran {
+            tracing::subscriber::set_global_default(tracing_subscriber::fmt().with_max_level(console_level).finish())?;
+            safe_mode_ran = true;
+        }
+        // Without sign-in, so only on this computer unless asked otherwise
+        let listener = tokio::net::TcpListener::bind(format!("{}:{}", args.safe_mode_bind, args.http_port)).await?;
+        let safe_mode = SafeMode::new(args.config.clone().map(std::path::PathBuf::from), error.to_string());
+        tokio::select! {
+            served = safe_mode.serve(listener, args.headless) => served?,
+            _ = shutdown_signal() => return Ok(()),
+        }
+    };
+    if safe_mode_ran {
+        warn!("Logging to the console until the bridge is restarted; [logging] was not read at startup");
+    } else {
+        let (system_log, system_log_error) = match SystemLogLayer::new(&config.logging) {
+            Ok(layer) => (layer, None),
+            Err(e) => (None, Some(e)),
+        };
+        let console = (config.logging.console || system_log.is_none())
+            .then(|| tracing_subscriber::fmt::layer().with_filter(console_level));
+        let system_log = system_log.map(|layer| layer.with_filter(system_log::level_filter(config.logging.level)));
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(console).with(system_log))?;
+        if let Some(e) = system_log_error {
+            warn!("Cannot log to {}: {}; logging to the console instead", config.logging.target, e);
+        }
+    }
     
-    info!("nRF52840 Telescope Park Bridge v{} starting...", env!("CARGO_PKG_VERSION"));
+    server_info::mark_started();
+    info!(
+        "nRF52840 Telescope Park Bridge v{} ({}, built {}) starting...",
+        env!("CARGO_PKG_VERSION"),
+        env!("GIT_COMMIT"),
+        env!("BUILD_TIMESTAMP")
+    );
     
     if args.debug {
         info!("Debug logging enabled");
@@ -67,40 +310,117 @@ async fn main() -> Result<()> {
     // Note about UDP discovery port
     info!("Note: Discovery requires UDP port 32227 - may need firewall exception");
     
-    // Initialize shared state
-    let device_state = Arc::new(RwLock::new(DeviceState::new()));
-    let connection_manager = Arc::new(ConnectionManager::new(device_state.clone()));
+    if let Some(path) = &args.config {
+        info!("Loaded configuration from {}", path);
+        safe_mode::keep_last_good(std::path::Path::new(path));
+    }
+    check_command_line(&args)?;
+    config.command_api.expert_mode |= args.expert_mode;
+    if config.command_api.expert_mode {
+        warn!("Expert mode enabled - /api/command accepts any firmware command, including factory reset");
+    }
+    config.strict_schema |= args.strict_schema;
+    if config.strict_schema {
+        info!("Strict schema checking enabled - firmware replies that do not match are logged and counted at /api/firmware/schema");
+    }
+    if config.safety_force.is_enabled() {
+        warn!("POST /api/safety/force is enabled - authorized clients can override IsSafe");
+    }
+    timestamps::set_display_timezone(config.display_timezone);
+    info!("API timestamps use the {} timezone", config.display_timezone);
+    
+    // Initialize shared state: one device from the command line, or the config file's [[devices]]
+    let devices = if config.devices.is_empty() {
+        DeviceRegistry::new(vec![build_device(&config, 0, config.identity.clone(), config.safety, config.serial, None)])
+    } else {
+        info!("Serving {} devices from the configuration file", config.devices.len());
+        DeviceRegistry::new(
+            config
+                .devices
+                .iter()
+                .map(|device| {
+                    build_device(
+                        &config,
+                        device.device_number,
+                        device.identity(&config.identity),
+                        device.safety(config.safety),
+                        device.serial(config.serial),
+                        device.unique_id.clone(),
+                    )
+                })
+                .collect(),
+        )
+    }
+    .with_notifications(Notifications::new(config.notifications.clone()));
+    let devices = match &args.config {
+        Some(path) => devices.with_config_store(ConfigStore::new(path)),
+        None => devices,
+    };
+    // The mounts' Alpaca servers, reconnected like the serial link when a controller drops off
+    #[cfg(feature = "telescope-client")]
+    let telescopes = TelescopeRegistry::new(
+        config
+            .telescopes
+            .iter()
+            .map(|telescope| {
+                TelescopeManager::from_config(telescope)
+                    .with_reconnect(config.reconnect)
+                    .with_event_bus(devices.primary().connection_manager.event_bus())
+            })
+            .collect(),
+    );
+    #[cfg(feature = "telescope-client")]
+    let devices = {
+        telescopes.start_all().await;
+        devices.with_telescopes(telescopes.clone())
+    };
+    let connection_manager = devices.primary().connection_manager.clone();
+    if args.fault_injection {
+        warn!("Fault injection API enabled - responses may be deliberately dropped, delayed or corrupted");
+        for device in devices.devices() {
+            device.connection_manager.enable_fault_injection();
+        }
+    }
+    
+    // Start session recording before connecting so the startup exchange is captured too
+    let _recording_handle = match &args.record {
+        Some(path) => Some(session_recording::start_recording(
+            &connection_manager.traffic_tap(),
+            std::path::Path::new(path),
+        )?),
+        None => None,
+    };
+    
+    let transaction_log = match args.transaction_log.as_ref().or(config.transaction_log.as_ref()) {
+        Some(path) => Some(TransactionLog::open(std::path::Path::new(path))?),
+        None => None,
+    };
     
-    // Determine target port
-    let target_port = if let Some(port) = args.port {
+    // Determine target ports
+    let target_port = if !config.devices.is_empty() {
+        if args.port.is_some() || args.auto || args.remote.is_some() {
+            warn!("--port, --auto and --remote are ignored when [[devices]] are configured");
+        }
+        None
+    } else if let Some(port) = args.port {
         Some(port)
     } else if args.auto {
+        // Each candidate is asked for its version, so a mount controller or GPS is never taken
         match port_discovery::discover_ports() {
             Ok(ports) => {
-                let mut found_port = None;
-                
-                // Look for nRF52840-like devices
-                for port in &ports {
-                    if port.description.to_lowercase().contains("usb") || 
-                       port.description.to_lowercase().contains("serial") ||
-                       port.description.to_lowercase().contains("xiao") ||
-                       port.description.to_lowercase().contains("nrf52") {
-                        info!("Found potential nRF52840 device: {} ({})", port.name, port.description);
-                        found_port = Some(port.name.clone());
-                        break;
-                    }
+                info!("Probing {} serial port(s) for the park sensor...", ports.len());
+                let found = port_discovery::find_park_sensor(
+                    &ports,
+                    args.baud,
+                    &config.serial,
+                    &config.framing,
+                    port_discovery::PROBE_TIMEOUT,
+                )
+                .await;
+                if found.is_none() {
+                    warn!("No serial port answered like the park sensor; use --port or the web interface to connect");
                 }
-                
-                if found_port.is_none() {
-                    // Fallback: use first available port
-                    if let Some(first_port) = ports.first() {
-                        info!("No nRF52840-like device found, using first available: {} ({})", 
-                              first_port.name, first_port.description);
-                        found_port = Some(first_port.name.clone());
-                    }
-                }
-                
-                found_port
+                found.map(|(port, _)| port.name)
             }
             Err(e) => {
                 error!("Failed to discover ports: {}", e);
@@ -111,10 +431,62 @@ async fn main() -> Result<()> {
         None
     };
     
-    // Auto-connect if port was specified or found
+    let mut connections: Vec<(Arc<ConnectionManager>, String, u32)> = config
+        .devices
+        .iter()
+        .filter_map(|device| {
+            let manager = devices.get(device.device_number)?.connection_manager.clone();
+            Some((manager, device.port.clone()?, device.baud))
+        })
+        .collect();
     if let Some(port) = target_port {
+        connections.push((connection_manager.clone(), port, args.baud));
+    }
+    
+    let mut remotes: Vec<(Arc<ConnectionManager>, RemoteConfig)> = config
+        .devices
+        .iter()
+        .filter_map(|device| {
+            let manager = devices.get(device.device_number)?.connection_manager.clone();
+            Some((manager, device.remote.clone()?))
+        })
+        .collect();
+    if let (Some(url), true) = (&args.remote, config.devices.is_empty()) {
+        remotes.push((connection_manager.clone(), RemoteConfig::new(url)));
+    }
+    for (manager, remote) in remotes {
+        if let Err(e) = manager.connect_remote(remote).await {
+            error!("Remote sensor setup failed: {}", e);
+        }
+    }
+    
+    // Voting devices read the states of their member sensors
+    for device in &config.devices {
+        let (Some(voting), Some(handle)) = (&device.voting, devices.get(device.device_number)) else {
+            continue;
+        };
+        let members = voting
+            .members
+            .iter()
+            .filter_map(|number| devices.get(*number))
+            .map(|member| VotingMember {
+                device_number: member.device_number,
+                device_state: member.device_state.clone(),
+                max_data_age_secs: member.connection_manager.max_data_age_secs(),
+            })
+            .collect();
+        if let Err(e) = handle.connection_manager.connect_voting(device.device_number, members, voting.policy).await {
+            error!("Voting device {} setup failed: {}", device.device_number, e);
+        }
+    }
+    
+    // Auto-connect if ports were specified or found
+    if connections.is_empty() && args.remote.is_none() && config.devices.is_empty() && config.usb.is_none() {
+        info!("No port specified. Use --port, --auto, or web interface to connect.");
+    }
+    for (manager, port, baud) in connections {
         info!("Attempting auto-connection to {}...", port);
-        match connection_manager.connect(port.clone(), args.baud).await {
+        match manager.connect(port.clone(), baud).await {
             Ok(_) => {
                 info!("Successfully auto-connected to {}", port);
             }
@@ -123,22 +495,164 @@ async fn main() -> Result<()> {
                 info!("Use the web interface to manually connect to your device.");
             }
         }
-    } else {
-        info!("No port specified. Use --port, --auto, or web interface to connect.");
     }
     
     // Start the discovery server
     info!("Starting ASCOM Alpaca discovery server...");
+    let discovery_tracker = DiscoveryTracker::new(config.discovery);
+    let discovery_server_tracker = discovery_tracker.clone();
     let discovery_handle = tokio::spawn(async move {
-        if let Err(e) = start_discovery_server(args.http_port).await {
+        if let Err(e) = start_discovery_server(args.http_port, discovery_server_tracker).await {
             error!("Discovery server error: {}", e);
         }
     });
     
     // Start the ASCOM Alpaca server
     info!("Starting ASCOM Alpaca server...");
-    let server_handle = tokio::spawn(async move {
-        if let Err(e) = create_alpaca_server(args.bind, args.http_port, device_state, connection_manager.clone()).await {
+    let http = config.http;
+    let headless = args.headless || http.headless;
+    if headless {
+        info!("Headless mode: serving the JSON and ASCOM Alpaca APIs without the web UI");
+    }
+    #[cfg(feature = "web-ui")]
+    let assets = match &args.dev_assets {
+        Some(_) if headless => {
+            warn!("--dev-assets is ignored in headless mode");
+            WebAssets::embedded()
+        }
+        Some(dir) => {
+            warn!("Serving the web UI from {} with live reload (--dev-assets)", dir);
+            WebAssets::from_dir(dir)
+        }
+        None => WebAssets::embedded(),
+    };
+    let shutdown = CancellationToken::new();
+    
+    // Sensors attached by USB id, wherever their port shows up
+    let mut usb_devices: Vec<(u32, config::UsbMatch, u32)> = config
+        .devices
+        .iter()
+        .filter_map(|device| Some((device.device_number, device.usb.clone()?, device.baud)))
+        .collect();
+    if let (Some(usb), true) = (&config.usb, config.devices.is_empty()) {
+        usb_devices.push((0, usb.clone(), args.baud));
+    }
+    for (device_number, usb, baud) in usb_devices {
+        if let Some(device) = devices.get(device_number) {
+            tokio::spawn(UsbHotplug::new(usb, baud).run(device.clone(), shutdown.clone()));
+        }
+    }
+    
+    // Read-only stream of the serial conversation
+    if let Some(bind) = args.serial_tee.as_ref().or(config.serial_tee.bind.as_ref()) {
+        let tee_config = &config.serial_tee;
+        if let Some(device) = devices.get(tee_config.device_number) {
+            let listener = tokio::net::TcpListener::bind(bind.as_str())
+                .await
+                .map_err(|e| anyhow::anyhow!("Cannot open the serial traffic stream on {}: {}", bind, e))?;
+            let tee = SerialTee::new(device.connection_manager.traffic_tap(), tee_config.format, tee_config.max_clients);
+            tokio::spawn(tee.serve(listener, shutdown.clone()));
+        }
+    }
+    
+    // The devices as INDI weather devices for KStars/Ekos
+    if let Some(bind) = &config.indi.bind {
+        let listener = tokio::net::TcpListener::bind(bind.as_str())
+            .await
+            .map_err(|e| anyhow::anyhow!("Cannot open the INDI server on {}: {}", bind, e))?;
+        tokio::spawn(IndiServer::new(devices.clone(), config.indi.max_clients).serve(listener, shutdown.clone()));
+    }
+
+    // Panel lamps mirroring the configured device
+    #[cfg(all(feature = "gpio", target_os = "linux"))]
+    let lamps_handle = match (config.gpio.is_enabled(), devices.get(config.gpio.device_number)) {
+        (true, Some(device)) => {
+            let outputs = StatusOutputs::open(&config.gpio, std::path::Path::new(status_outputs::LEDS_DIR))?;
+            Some(tokio::spawn(outputs.run(device.clone(), shutdown.clone())))
+        }
+        _ => None,
+    };
+    
+    // Relays switched on IsSafe changes, set from the current state right away
+    #[cfg(feature = "relays")]
+    if !config.relays.is_empty() {
+        let outputs = config.relays.iter().cloned().map(Relay::new).collect::<Result<Vec<_>, _>>()?;
+        info!("Switching {} relay(s) on IsSafe changes", outputs.len());
+        tokio::spawn(relays::run_relays(outputs, devices.clone(), shutdown.clone()));
+    }
+    
+    // Events posted to the club's chat, and !commands from it
+    #[cfg(feature = "chat-bot")]
+    if let (true, Some(device)) = (config.chat_bot.is_enabled(), devices.get(config.chat_bot.device_number)) {
+        let bot = ChatBot::new(config.chat_bot.clone())?.with_notifications(devices.notifications().clone());
+        tokio::spawn(bot.run(device.clone(), shutdown.clone()));
+    }
+    
+    // One timeline of every device's events for /api/session_log and the backups
+    tokio::spawn(session_log::run_session_log(devices.clone(), shutdown.clone()));
+
+    // IsSafe false while the Sun is up at the site
+    if config.sun_safety.is_enabled() {
+        tokio::spawn(sun_safety::run_sun_safety(config.sun_safety, devices.clone(), shutdown.clone()));
+    }
+
+    // IsSafe false during the configured time windows
+    if !config.safety_schedules.is_empty() {
+        info!("Applying {} safety schedule(s)", config.safety_schedules.len());
+        tokio::spawn(safety_schedule::run_safety_schedules(config.safety_schedules.clone(), devices.clone(), shutdown.clone()));
+    }
+
+    // End-of-night summaries for /api/reports/nightly, posted by the chat bot
+    tokio::spawn(nightly_report::run_nightly_reports(config.nightly_report, devices.clone(), shutdown.clone()));
+
+    // Settings and history copied off the SD card, and once more on shutdown
+    let backup_handle = config.backup.directory.is_some().then(|| {
+        let scheduler = BackupScheduler::new(config.backup.clone(), devices.clone(), args.config.clone().map(std::path::PathBuf::from));
+        tokio::spawn(scheduler.run(shutdown.clone()))
+    });
+    
+    // Browsable as _http._tcp and _alpaca._tcp where the discovery broadcast does not get through
+    #[cfg(feature = "mdns")]
+    let mdns = match (config.mdns.enabled, args.bind.parse::<std::net::IpAddr>()) {
+        (false, _) => None,
+        (true, Ok(bind)) if bind.is_loopback() => {
+            info!("Not advertising via mDNS while bound to {}", bind);
+            None
+        }
+        (true, Ok(bind)) => match MdnsAdvertiser::start(&config.mdns, bind, args.http_port, &devices).await {
+            Ok(advertiser) => Some(advertiser),
+            Err(e) => {
+                warn!("mDNS advertisement unavailable: {}", e);
+                None
+            }
+        },
+        (true, Err(_)) => {
+            warn!("Not advertising via mDNS: bind address {} is not an IP address", args.bind);
+            None
+        }
+    };
+    
+    let web_users = WebUsers::new(&config.web_auth);
+    if web_users.is_enabled() {
+        info!("Web interface sign-in required for {} user(s)", config.web_auth.users.len());
+    }
+    
+    let server_shutdown = shutdown.clone();
+    let mut server_handle = tokio::spawn(async move {
+        let options = ServerOptions {
+            bind_address: args.bind,
+            port: args.http_port,
+            access_log: args.access_log || config.access_log,
+            transaction_log,
+            request_timeout: http.request_timeout(),
+            shutdown: server_shutdown,
+            #[cfg(feature = "web-ui")]
+            assets,
+            headless,
+            web_users,
+            events: config.events.clone(),
+        };
+        if let Err(e) = create_alpaca_server(options, devices, discovery_tracker).await {
             error!("Failed to start ASCOM Alpaca server: {}", e);
         }
     });
@@ -148,10 +662,390 @@ async fn main() -> Result<()> {
         _ = discovery_handle => {
             warn!("Discovery server terminated");
         }
-        _ = server_handle => {
+        _ = &mut server_handle => {
             warn!("ASCOM Alpaca server terminated");
         }
+        _ = shutdown_signal() => {
+            // Stop accepting connections but let in-flight ASCOM transactions complete
+            let grace = Duration::from_secs(http.shutdown_grace_secs);
+            info!("Shutting down, waiting up to {}s for in-flight requests", grace.as_secs());
+            shutdown.cancel();
+            if tokio::time::timeout(grace, server_handle).await.is_err() {
+                warn!("Requests still open after {}s, closing them", grace.as_secs());
+            }
+            #[cfg(all(feature = "gpio", target_os = "linux"))]
+            if let Some(lamps_handle) = lamps_handle {
+                let _ = lamps_handle.await;
+            }
+            if let Some(backup_handle) = backup_handle {
+                let _ = backup_handle.await;
+            }
+            #[cfg(feature = "mdns")]
+            if let Some(mdns) = mdns {
+                mdns.stop().await;
+            }
+            #[cfg(feature = "telescope-client")]
+            telescopes.stop_all().await;
+        }
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+// The config file (defaults without --config) with its secrets from the keyring or secrets file
+fn load_config(path: Option<&str>) -> telescope_park_bridge::errors::Result<BridgeConfig> {
+    let path = path.map(std::path::Path::new);
+    let mut config = match path {
+        Some(path) => BridgeConfig::load(path)?,
+        None => BridgeConfig::default(),
+    };
+    config.resolve_secrets(&SecretStore::for_config(path))?;
+    Ok(config)
+}
+
+// Ctrl-C, or SIGTERM from a service manager restarting the bridge
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(e) => {
+                warn!("Cannot listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                return;
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+// Command-line settings get the same checks as the config file, before anything is started
+fn check_command_line(args: &Args) -> Result<()> {
+    let mut issues = ConfigIssues::default();
+    if let Some(port) = &args.port {
+        if let Err(message) = config::check_port_syntax(port) {
+            issues.push("--port", message);
+        }
+    }
+    if let Err(message) = config::check_baud(args.baud) {
+        issues.push("--baud", message);
+    }
+    if let Some(url) = &args.remote {
+        if let Err(message) = RemoteConfig::new(url).validate() {
+            issues.push("--remote", message);
+        }
+    }
+    if let Some(addr) = &args.serial_tee {
+        if let Err(message) = config::check_socket_addr(addr) {
+            issues.push("--serial-tee", message);
+        }
+    }
+    if !issues.is_empty() {
+        anyhow::bail!("Invalid command line, {}", issues);
+    }
+    Ok(())
+}
+
+// Device state and connection manager for one sensor, with the shared serial settings from the config
+fn build_device(
+    config: &BridgeConfig,
+    device_number: u32,
+    identity: IdentityConfig,
+    safety: SafetyConfig,
+    serial: SerialConfig,
+    unique_id: Option<String>,
+) -> DeviceHandle {
+    let mut state = DeviceState::new();
+    if let Some(unique_id) = unique_id {
+        state.unique_id = unique_id;
+    }
+    let device_state = Arc::new(RwLock::new(state));
+    let connection_manager = Arc::new(
+        ConnectionManager::new(device_state.clone())
+            .with_command_timeouts(config.command_timeouts())
+            .with_heartbeat(config.heartbeat)
+            .with_serial(serial)
+            .with_framing(config.framing.clone())
+            .with_reconnect(config.reconnect)
+            .with_auto_reset(config.auto_reset)
+            .with_health(config.health)
+            .with_set_park(config.set_park)
+            .with_temperature_compensation(config.temperature_compensation)
+            .with_safety(safety)
+            .with_identity(identity)
+            .with_command_api(config.command_api.clone())
+            .with_safety_force(config.safety_force.clone())
+            .with_maintenance(config.maintenance)
+            .with_command_history(config.command_history.size)
+            .with_strict_schema(config.strict_schema),
+    );
+    DeviceHandle {
+        device_number,
+        device_state,
+        connection_manager,
+    }
+}
+
+// Subcommand exit codes for scripts and health checks; clap exits with 2 on a bad command line
+const EXIT_OK: i32 = 0;
+// The subcommand ran and found a problem: a failed self-check or benchmark, nothing discovered
+const EXIT_CHECK_FAILED: i32 = 1;
+// The subcommand could not run: unreachable device, bad config, I/O error
+const EXIT_ERROR: i32 = 3;
+
+// Print a subcommand's result as JSON or as its human-readable form
+fn print_output<T: serde::Serialize>(json: bool, value: &T, text: impl FnOnce() -> String) -> Result<()> {
+    match json {
+        true => println!("{}", serde_json::to_string_pretty(value)?),
+        false => println!("{}", text()),
+    }
+    Ok(())
+}
+
+async fn run_subcommand(
+    command: Command,
+    global_port: Option<String>,
+    global_baud: u32,
+    http_port: u16,
+    config: Option<String>,
+    json: bool,
+) -> Result<i32> {
+    match command {
+        Command::Ascom { action } => run_ascom(action, http_port, config.as_deref()).map(|_| EXIT_OK),
+        Command::ListPorts => {
+            let ports = port_discovery::discover_ports()?;
+            print_output(json, &ports, || port_discovery::format_table(&ports))?;
+            Ok(if ports.is_empty() { EXIT_CHECK_FAILED } else { EXIT_OK })
+        }
+        Command::SelfTest { port, device_number, timeout_secs } => {
+            run_self_test(port.or(global_port), global_baud, device_number, timeout_secs, config.as_deref(), json).await
+        }
+        Command::Discover { timeout_ms, targets, discovery_port } => {
+            let mut options = ScanOptions {
+                discovery_port,
+                timeout: std::time::Duration::from_millis(timeout_ms),
+                ..ScanOptions::default()
+            };
+            if !targets.is_empty() {
+                options.targets = targets;
+            }
+
+            let servers = alpaca_scan::scan(&options).await?;
+            print_output(json, &servers, || alpaca_scan::format_table(&servers))?;
+            Ok(if servers.is_empty() { EXIT_CHECK_FAILED } else { EXIT_OK })
+        }
+        Command::HashPassword => {
+            use std::io::IsTerminal;
+            if std::io::stdin().is_terminal() {
+                eprint!("Password: ");
+            }
+            let mut password = String::new();
+            std::io::stdin().read_line(&mut password)?;
+            let password = password.trim_end_matches(['\r', '\n']);
+            if password.is_empty() {
+                anyhow::bail!("the password must not be empty");
+            }
+            println!("{}", web_users::hash_password(password)?);
+            Ok(EXIT_OK)
+        }
+        Command::Secrets { action } => {
+            let store = SecretStore::for_config(config.as_deref().map(std::path::Path::new));
+            run_secrets(&store, action).map(|_| EXIT_OK)
+        }
+        Command::Bench { port, bauds, count, command, timeout_ms } => {
+            let Some(port) = port.or(global_port) else {
+                anyhow::bail!("bench needs a serial port: use --port");
+            };
+            let options = BenchOptions {
+                port,
+                baud_rates: if bauds.is_empty() { vec![global_baud] } else { bauds },
+                count,
+                command,
+                timeout: std::time::Duration::from_millis(timeout_ms),
+            };
+            
+            let results = bench::run_bench(&options).await;
+            print_output(json, &results, || bench::format_report(&options, &results))?;
+            
+            if results.iter().any(|r| r.error.is_some() || r.completed == 0) {
+                return Ok(EXIT_CHECK_FAILED);
+            }
+            Ok(EXIT_OK)
+        }
+    }
+}
+
+// Connect like the bridge would, wait for the startup self-check and report it
+async fn run_self_test(
+    port: Option<String>,
+    baud: u32,
+    device_number: u32,
+    timeout_secs: u64,
+    config_path: Option<&str>,
+    json: bool,
+) -> Result<i32> {
+    let config = match config_path {
+        Some(path) => BridgeConfig::load(std::path::Path::new(path))?,
+        None => BridgeConfig::default(),
+    };
+    let device = config.devices.iter().find(|device| device.device_number == device_number);
+    let (port, baud) = match (port, device) {
+        (Some(port), _) => (port, baud),
+        (None, Some(device)) if device.port.is_some() => (device.port.clone().unwrap_or_default(), device.baud),
+        _ => anyhow::bail!("selftest needs a serial port: use --port"),
+    };
+    let handle = match device {
+        Some(device) => build_device(
+            &config,
+            device.device_number,
+            device.identity(&config.identity),
+            device.safety(config.safety),
+            device.serial(config.serial),
+            None,
+        ),
+        None => build_device(&config, 0, config.identity.clone(), config.safety, config.serial, None),
+    };
+
+    let manager = &handle.connection_manager;
+    manager.connect(port.clone(), baud).await?;
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(timeout_secs);
+    let report = loop {
+        let report = manager.self_test().report();
+        let finished = matches!(report.status, SelfTestStatus::Passed | SelfTestStatus::Failed);
+        if finished || tokio::time::Instant::now() >= deadline {
+            break report;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+    let last_error = handle.device_state.read().await.error_message.clone();
+    let _ = manager.disconnect().await;
+
+    match report.status {
+        SelfTestStatus::Passed | SelfTestStatus::Failed => {
+            print_output(json, &report, || self_test::format_report(&report))?;
+            Ok(if report.status == SelfTestStatus::Passed { EXIT_OK } else { EXIT_CHECK_FAILED })
+        }
+        SelfTestStatus::NotRun => match last_error {
+            Some(error) => anyhow::bail!("could not connect to {}: {}", port, error),
+            None => anyhow::bail!("could not connect to {} within {} s", port, timeout_secs),
+        },
+        SelfTestStatus::Running => anyhow::bail!("the self-check on {} did not finish within {} s", port, timeout_secs),
+    }
+}
+
+fn run_secrets(store: &SecretStore, action: SecretsAction) -> Result<()> {
+    match action {
+        SecretsAction::Set { name, file } => {
+            use std::io::IsTerminal;
+            if std::io::stdin().is_terminal() {
+                eprint!("Value for {}: ", name);
+            }
+            let mut value = String::new();
+            std::io::stdin().read_line(&mut value)?;
+            let location = if file { SecretLocation::File } else { SecretLocation::Keyring };
+            match store.set(&name, value.trim_end_matches(['\r', '\n']), location)? {
+                SecretLocation::Keyring => {
+                    println!("{} stored in the OS keyring", name);
+                    if cfg!(target_os = "linux") {
+                        println!("Note: the Linux kernel keyring is cleared on reboot; use --file for a bridge running as a service");
+                    }
+                }
+                SecretLocation::File => println!("{} stored in {}", name, store.file().display()),
+            }
+        }
+        SecretsAction::Delete { name } => {
+            let removed = store.delete(&name)?;
+            if removed.is_empty() {
+                println!("{} was not set", name);
+            }
+            for location in removed {
+                println!("{} removed from the {}", name, location);
+            }
+        }
+        SecretsAction::List => {
+            for (name, location) in store.list()? {
+                match location {
+                    Some(location) => println!("{:<24} set ({})", name, location),
+                    None => println!("{:<24} not set", name),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_ascom(action: AscomAction, http_port: u16, config_path: Option<&str>) -> Result<()> {
+    match action {
+        AscomAction::Register { device_number, host, name, driver_number, reg_file } => {
+            let config = match config_path {
+                Some(path) => BridgeConfig::load(std::path::Path::new(path))?,
+                None => BridgeConfig::default(),
+            };
+            let device = config.devices.iter().find(|device| device.device_number == device_number);
+            if device.is_none() && (device_number != 0 || !config.devices.is_empty()) {
+                anyhow::bail!("Device {} is not configured", device_number);
+            }
+            let identity = device.map_or_else(|| config.identity.clone(), |device| device.identity(&config.identity));
+            let description = name.unwrap_or_else(|| identity.device_name("Telescope Park Sensor"));
+            let driver = DynamicDriver {
+                driver_number: driver_number.unwrap_or(1),
+                description,
+                host,
+                port: http_port,
+                device_number,
+                unique_id: device.and_then(|device| device.unique_id.clone()),
+            };
+            if driver.unique_id.is_none() {
+                println!("Note: no unique_id is configured for device {}, so the driver cannot rediscover the bridge at a new address", device_number);
+            }
+
+            if let Some(path) = reg_file {
+                std::fs::write(&path, ascom_profile::encode_reg_file(&driver.to_reg_file()))?;
+                println!("{} for \"{}\" written to {}", driver.prog_id(), driver.description, path);
+                if driver_number.is_none() {
+                    println!("Use --driver-number if {} is already taken on the Windows PC", driver.prog_id());
+                }
+                println!("Import it with regedit on the Windows PC, then finish the registration in the ASCOM Chooser");
+                return Ok(());
+            }
+            #[cfg(all(windows, feature = "desktop"))]
+            {
+                let driver = DynamicDriver {
+                    driver_number: match driver_number {
+                        Some(number) => number,
+                        None => ascom_profile::free_driver_number(&driver.host, driver.port, device_number)?,
+                    },
+                    ..driver
+                };
+                ascom_profile::install(&driver)
+                    .map_err(|e| anyhow::anyhow!("Cannot write the ASCOM Profile ({}); run from an elevated prompt", e))?;
+                println!("{} for \"{}\" written to the ASCOM Profile", driver.prog_id(), driver.description);
+                println!("To create its COM driver, open the ASCOM Chooser and use Alpaca > Manage Alpaca Dynamic Drivers (admin)");
+                Ok(())
+            }
+            #[cfg(not(all(windows, feature = "desktop")))]
+            anyhow::bail!("Writing the ASCOM Profile needs the Windows build (feature desktop); use --reg-file to export it")
+        }
+        AscomAction::Unregister { prog_id } => {
+            if ascom_profile::driver_number(&prog_id).is_none() {
+                anyhow::bail!("{} is not an Alpaca dynamic SafetyMonitor driver (ASCOM.AlpacaDynamicN.SafetyMonitor)", prog_id);
+            }
+            #[cfg(all(windows, feature = "desktop"))]
+            {
+                ascom_profile::uninstall(&prog_id)
+                    .map_err(|e| anyhow::anyhow!("Cannot remove {} from the ASCOM Profile ({}); run from an elevated prompt", prog_id, e))?;
+                println!("{} removed from the ASCOM Profile", prog_id);
+                Ok(())
+            }
+            #[cfg(not(all(windows, feature = "desktop")))]
+            anyhow::bail!("Removing {} from the ASCOM Profile needs the Windows build (feature desktop)", prog_id)
+        }
+    }
+}
@@ -0,0 +1,104 @@
+// src/mdns.rs
+// mDNS/Bonjour advertisement (cargo feature mdns): the web interface as _http._tcp and the Alpaca
+// API as _alpaca._tcp under the bridge's name, for networks where the Alpaca discovery broadcast
+// on UDP 32227 does not get through
+
+use crate::config::MdnsConfig;
+use crate::device_registry::DeviceRegistry;
+use crate::errors::{BridgeError, Result};
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::net::IpAddr;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+pub const HTTP_SERVICE: &str = "_http._tcp.local.";
+pub const ALPACA_SERVICE: &str = "_alpaca._tcp.local.";
+
+// How long shutdown waits for the goodbye packets to go out
+const GOODBYE_WAIT: Duration = Duration::from_secs(1);
+
+pub struct MdnsAdvertiser {
+    daemon: ServiceDaemon,
+    // Full names of the registered services, e.g. "Park Bridge._http._tcp.local."
+    services: Vec<String>,
+}
+
+impl MdnsAdvertiser {
+    // Register both services for the HTTP server on `bind`:`port`; every interface address is
+    // announced when bound to all of them
+    pub async fn start(config: &MdnsConfig, bind: IpAddr, port: u16, devices: &DeviceRegistry) -> Result<Self> {
+        let instance = config
+            .instance_name
+            .clone()
+            .unwrap_or_else(|| devices.primary().connection_manager.identity().server_name());
+        let host = format!("{}.local.", config.host_name.clone().unwrap_or_else(|| host_label(&instance)));
+
+        let mut alpaca_properties = vec![
+            ("version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+            ("devices".to_string(), devices.len().to_string()),
+        ];
+        for device in devices.devices() {
+            let state = device.device_state.read().await;
+            let name = device.connection_manager.identity().device_name(&state.device_name);
+            alpaca_properties.push((format!("safetymonitor{}", device.device_number), name));
+        }
+        let http_properties = vec![("path".to_string(), "/".to_string())];
+
+        let daemon = ServiceDaemon::new().map_err(mdns_error)?;
+        let mut advertiser = Self {
+            daemon,
+            services: Vec::new(),
+        };
+        for (service_type, properties) in [(HTTP_SERVICE, http_properties), (ALPACA_SERVICE, alpaca_properties)] {
+            let addresses = if bind.is_unspecified() { String::new() } else { bind.to_string() };
+            let mut service = ServiceInfo::new(service_type, &instance, &host, addresses, port, &properties[..])
+                .map_err(mdns_error)?;
+            if bind.is_unspecified() {
+                service = service.enable_addr_auto();
+            }
+            let fullname = service.get_fullname().to_string();
+            advertiser.daemon.register(service).map_err(mdns_error)?;
+            debug!("Registered mDNS service {}", fullname);
+            advertiser.services.push(fullname);
+        }
+        info!("Advertising \"{}\" via mDNS as {} on port {}", instance, host, port);
+        Ok(advertiser)
+    }
+
+    // Say goodbye so browsers drop the bridge at once instead of after the record TTL
+    pub async fn stop(self) {
+        for service in &self.services {
+            match self.daemon.unregister(service) {
+                Ok(receiver) => {
+                    let _ = tokio::time::timeout(GOODBYE_WAIT, receiver.recv_async()).await;
+                }
+                Err(e) => warn!("Cannot withdraw mDNS service {}: {}", service, e),
+            }
+        }
+        if let Err(e) = self.daemon.shutdown() {
+            debug!("mDNS daemon shutdown: {}", e);
+        }
+    }
+}
+
+// DNS host label made from the instance name: "Pier 2 Park Bridge" becomes "pier-2-park-bridge"
+pub fn host_label(instance: &str) -> String {
+    let mut label = String::new();
+    for c in instance.chars() {
+        if c.is_ascii_alphanumeric() {
+            label.push(c.to_ascii_lowercase());
+        } else if !label.is_empty() && !label.ends_with('-') {
+            label.push('-');
+        }
+    }
+    label.truncate(63);
+    let label = label.trim_end_matches('-');
+    match label.is_empty() {
+        true => "park-bridge".to_string(),
+        false => label.to_string(),
+    }
+}
+
+fn mdns_error(e: mdns_sd::Error) -> BridgeError {
+    BridgeError::Mdns(e.to_string())
+}
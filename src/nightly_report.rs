@@ -0,0 +1,325 @@
+// src/nightly_report.rs
+// End-of-night summaries (/api/reports/nightly): how long each device reported safe, unsafe or
+// disconnected, sampled as it happens, plus the park changes, mount slews, flips and parks and the
+// alerts of the night, counted from the session log. Each finished night is published as a
+// nightly_report event, which the chat bot posts.
+
+use crate::config::NightlyReportConfig;
+use crate::device_registry::DeviceRegistry;
+use crate::events::{EventKind, TelescopeChange, TelescopeLink};
+use crate::session_log::SessionEntry;
+use crate::timestamps;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+// How often IsSafe and the connection are sampled for the safe/unsafe times
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NightlyReport {
+    // Date the night began, in the display timezone, e.g. "2024-05-01"
+    pub night: String,
+    // From the night's start, or from when the bridge started if that was later
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub from: u64,
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub to: u64,
+    // Report on the night still running (?night=current)
+    pub in_progress: bool,
+    pub devices: Vec<DeviceNight>,
+    pub telescopes: Vec<TelescopeNight>,
+    // Health warnings, sensor disagreements, forced IsSafe, firmware reboots, board resets and
+    // lost mount links
+    pub alerts: Vec<SessionEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeviceNight {
+    pub device_number: u32,
+    pub safe_secs: u64,
+    pub unsafe_secs: u64,
+    pub disconnected_secs: u64,
+    // IsSafe changes seen by the sampling
+    pub safety_changes: u32,
+    // park_state_changed events
+    pub park_changes: u32,
+    pub disconnects: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TelescopeNight {
+    pub telescope: String,
+    pub slews: u32,
+    pub parks: u32,
+    pub unparks: u32,
+    // Meridian flips
+    pub flips: u32,
+    pub link_losses: u32,
+}
+
+impl NightlyReport {
+    // One line for chat, e.g. "Night of 2024-05-01: device 0 safe 7h 12m, unsafe 1h 3m, ..."
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        for device in &self.devices {
+            let mut part = format!(
+                "device {} safe {}, unsafe {}",
+                device.device_number,
+                duration_text(device.safe_secs),
+                duration_text(device.unsafe_secs)
+            );
+            if device.disconnected_secs > 0 {
+                part.push_str(&format!(", disconnected {}", duration_text(device.disconnected_secs)));
+            }
+            part.push_str(&format!(", {} park change(s)", device.park_changes));
+            if device.disconnects > 0 {
+                part.push_str(&format!(", {} disconnect(s)", device.disconnects));
+            }
+            parts.push(part);
+        }
+        for telescope in &self.telescopes {
+            parts.push(format!(
+                "mount {} {} slew(s), {} flip(s), {} park(s)",
+                telescope.telescope, telescope.slews, telescope.flips, telescope.parks
+            ));
+        }
+        parts.push(format!("{} alert(s)", self.alerts.len()));
+        format!("Night of {}: {}", self.night, parts.join("; "))
+    }
+}
+
+// e.g. "7h 12m"
+fn duration_text(secs: u64) -> String {
+    format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+}
+
+// Sampled state of one device during the running night
+#[derive(Debug, Clone, Copy, Default)]
+struct DeviceTally {
+    safe_secs: u64,
+    unsafe_secs: u64,
+    disconnected_secs: u64,
+    safety_changes: u32,
+    disconnects: u32,
+    // (connected, IsSafe) at the last sample, which holds until the next one
+    last: Option<(bool, bool)>,
+}
+
+#[derive(Debug, Default)]
+struct Night {
+    from: u64,
+    to: u64,
+    last_sample: Option<u64>,
+    devices: BTreeMap<u32, DeviceTally>,
+}
+
+// The running night and the finished ones; shared by the registry's clones
+#[derive(Clone, Default)]
+pub struct NightlyReports {
+    night: Arc<Mutex<Night>>,
+    finished: Arc<Mutex<VecDeque<NightlyReport>>>,
+}
+
+impl NightlyReports {
+    // Newest finished night
+    pub fn latest(&self) -> Option<NightlyReport> {
+        self.finished.lock().unwrap().back().cloned()
+    }
+
+    // Finished night by the date it began
+    pub fn night(&self, night: &str) -> Option<NightlyReport> {
+        self.finished.lock().unwrap().iter().rev().find(|report| report.night == night).cloned()
+    }
+
+    // Dates of the finished nights kept, oldest first
+    pub fn nights(&self) -> Vec<String> {
+        self.finished.lock().unwrap().iter().map(|report| report.night.clone()).collect()
+    }
+
+    // Start a night at `now`, ending at the next end_hour
+    pub fn begin(&self, now: u64, end_hour: u32) {
+        *self.night.lock().unwrap() = Night {
+            from: now,
+            to: timestamps::next_display_hour(now, end_hour),
+            ..Night::default()
+        };
+    }
+
+    // When the running night ends
+    pub fn ends_at(&self) -> u64 {
+        self.night.lock().unwrap().to
+    }
+
+    // Add the time since the last sample to the state seen then, and count changes
+    pub async fn sample(&self, devices: &DeviceRegistry, now: u64) {
+        let mut readings = Vec::with_capacity(devices.len());
+        for device in devices.devices() {
+            let state = device.device_state.read().await;
+            let is_safe = state.is_safe_now(device.connection_manager.max_data_age_secs());
+            readings.push((device.device_number, state.connected, is_safe));
+        }
+        let mut night = self.night.lock().unwrap();
+        let elapsed = now.saturating_sub(night.last_sample.unwrap_or(now));
+        night.last_sample = Some(now);
+        for (device_number, connected, is_safe) in readings {
+            let tally = night.devices.entry(device_number).or_default();
+            if let Some((was_connected, was_safe)) = tally.last {
+                match (was_connected, was_safe) {
+                    (_, true) => tally.safe_secs += elapsed,
+                    (true, false) => tally.unsafe_secs += elapsed,
+                    (false, false) => tally.disconnected_secs += elapsed,
+                }
+                if was_safe != is_safe {
+                    tally.safety_changes += 1;
+                }
+                if was_connected && !connected {
+                    tally.disconnects += 1;
+                }
+            }
+            tally.last = Some((connected, is_safe));
+        }
+    }
+
+    // Report on the running night up to `now`
+    pub fn current(&self, devices: &DeviceRegistry, now: u64) -> NightlyReport {
+        let night = self.night.lock().unwrap();
+        build_report(&night, devices, now.min(night.to), true)
+    }
+
+    // Close the running night: keep its report, publish it on the primary device's event bus
+    // and start the next night where this one ended
+    pub fn finish(&self, devices: &DeviceRegistry, now: u64, config: &NightlyReportConfig) -> NightlyReport {
+        let report = {
+            let mut night = self.night.lock().unwrap();
+            let report = build_report(&night, devices, night.to, false);
+            // The last readings carry over, so the next night's first interval is counted too
+            let from = night.to;
+            night.from = from;
+            night.to = timestamps::next_display_hour(now.max(from), config.end_hour);
+            for tally in night.devices.values_mut() {
+                *tally = DeviceTally {
+                    last: tally.last,
+                    ..DeviceTally::default()
+                };
+            }
+            report
+        };
+        {
+            let mut finished = self.finished.lock().unwrap();
+            finished.push_back(report.clone());
+            while finished.len() > config.keep {
+                finished.pop_front();
+            }
+        }
+        let summary = report.summary();
+        info!("{}", summary);
+        devices.primary().connection_manager.event_bus().publish(EventKind::NightlyReport {
+            night: report.night.clone(),
+            summary,
+        });
+        report
+    }
+}
+
+fn build_report(night: &Night, devices: &DeviceRegistry, to: u64, in_progress: bool) -> NightlyReport {
+    let entries: Vec<SessionEntry> = devices
+        .session_log()
+        .entries()
+        .into_iter()
+        .filter(|entry| entry.timestamp >= night.from && entry.timestamp < to)
+        .collect();
+    let mut device_nights: BTreeMap<u32, DeviceNight> = devices
+        .devices()
+        .map(|device| {
+            let tally = night.devices.get(&device.device_number).copied().unwrap_or_default();
+            let device_night = DeviceNight {
+                device_number: device.device_number,
+                safe_secs: tally.safe_secs,
+                unsafe_secs: tally.unsafe_secs,
+                disconnected_secs: tally.disconnected_secs,
+                safety_changes: tally.safety_changes,
+                park_changes: 0,
+                disconnects: tally.disconnects,
+            };
+            (device.device_number, device_night)
+        })
+        .collect();
+    let mut telescopes: BTreeMap<String, TelescopeNight> = BTreeMap::new();
+    let mut alerts = Vec::new();
+    for entry in entries {
+        match &entry.kind {
+            EventKind::ParkStateChanged { .. } => {
+                if let Some(device) = entry.device_number.and_then(|number| device_nights.get_mut(&number)) {
+                    device.park_changes += 1;
+                }
+            }
+            EventKind::TelescopeChanged { telescope, change, .. } => {
+                let night = telescope_night(&mut telescopes, telescope);
+                match change {
+                    TelescopeChange::SlewStarted => night.slews += 1,
+                    TelescopeChange::Parked => night.parks += 1,
+                    TelescopeChange::Unparked => night.unparks += 1,
+                    TelescopeChange::PierFlipped => night.flips += 1,
+                    _ => {}
+                }
+            }
+            EventKind::TelescopeLinkChanged { telescope, state, .. } => {
+                let night = telescope_night(&mut telescopes, telescope);
+                if *state == TelescopeLink::Lost {
+                    night.link_losses += 1;
+                    alerts.push(entry.clone());
+                }
+            }
+            EventKind::HealthWarning { .. }
+            | EventKind::SensorDisagreement { .. }
+            | EventKind::SafetyForced { .. }
+            | EventKind::FirmwareRebooted { .. }
+            | EventKind::DeviceReset { .. } => alerts.push(entry.clone()),
+            _ => {}
+        }
+    }
+    NightlyReport {
+        // The evening before the morning it ends, also when the bridge started after midnight
+        night: timestamps::format_date(night.to.saturating_sub(86_400)),
+        from: night.from,
+        to,
+        in_progress,
+        devices: device_nights.into_values().collect(),
+        telescopes: telescopes.into_values().collect(),
+        alerts,
+    }
+}
+
+fn telescope_night<'a>(telescopes: &'a mut BTreeMap<String, TelescopeNight>, telescope: &str) -> &'a mut TelescopeNight {
+    telescopes.entry(telescope.to_string()).or_insert_with(|| TelescopeNight {
+        telescope: telescope.to_string(),
+        ..TelescopeNight::default()
+    })
+}
+
+// Sample the devices until cancelled, finishing each night at end_hour
+pub async fn run_nightly_reports(config: NightlyReportConfig, devices: DeviceRegistry, cancel_token: CancellationToken) {
+    let reports = devices.nightly_reports().clone();
+    reports.begin(unix_now(), config.end_hour);
+    info!("Nightly reports at {:02}:00, first at {}", config.end_hour, timestamps::format_secs(reports.ends_at()));
+    let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        let now = unix_now();
+        reports.sample(&devices, now).await;
+        if now >= reports.ends_at() {
+            reports.finish(&devices, now, &config);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
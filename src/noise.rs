@@ -0,0 +1,87 @@
+// src/noise.rs
+// Noise of the pitch/roll readings while parked (/api/noise), so the position tolerance can be
+// set safely above the sensor's noise floor instead of guessed
+
+use serde::Serialize;
+use std::collections::VecDeque;
+
+// Parked readings kept, about ten minutes of position polls
+pub const NOISE_WINDOW_SAMPLES: usize = 600;
+// Fewer readings than this give no statistics
+const MIN_SAMPLES: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AxisNoise {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub peak_to_peak: f64,
+}
+
+// Served at /api/noise
+#[derive(Debug, Clone, Serialize)]
+pub struct NoiseStats {
+    pub samples: usize,
+    pub window: usize,
+    pub pitch: Option<AxisNoise>,
+    pub roll: Option<AxisNoise>,
+    pub position_tolerance: f32,
+    // Twice the larger of three standard deviations and half the peak-to-peak spread on either
+    // axis, rounded up to 0.1°
+    pub suggested_tolerance: Option<f64>,
+}
+
+// Readings of the current park session; a new session starts empty, because the mount rarely
+// comes back to exactly the same spot
+#[derive(Debug, Default)]
+pub struct NoiseWindow {
+    readings: VecDeque<(f32, f32)>,
+}
+
+impl NoiseWindow {
+    pub fn push(&mut self, pitch: f32, roll: f32) {
+        if self.readings.len() == NOISE_WINDOW_SAMPLES {
+            self.readings.pop_front();
+        }
+        self.readings.push_back((pitch, roll));
+    }
+
+    pub fn clear(&mut self) {
+        self.readings.clear();
+    }
+
+    pub fn stats(&self, position_tolerance: f32) -> NoiseStats {
+        let enough = self.readings.len() >= MIN_SAMPLES;
+        let pitch = enough.then(|| axis_noise(self.readings.iter().map(|(pitch, _)| *pitch as f64)));
+        let roll = enough.then(|| axis_noise(self.readings.iter().map(|(_, roll)| *roll as f64)));
+        let suggested_tolerance = pitch.as_ref().zip(roll.as_ref()).map(|(pitch, roll)| {
+            let excursion = |axis: &AxisNoise| (3.0 * axis.std_dev).max(axis.peak_to_peak / 2.0);
+            let tolerance = 2.0 * excursion(pitch).max(excursion(roll));
+            ((tolerance * 10.0).ceil() / 10.0).max(0.1)
+        });
+        NoiseStats {
+            samples: self.readings.len(),
+            window: NOISE_WINDOW_SAMPLES,
+            pitch,
+            roll,
+            position_tolerance,
+            suggested_tolerance,
+        }
+    }
+}
+
+fn axis_noise(values: impl Iterator<Item = f64> + Clone) -> AxisNoise {
+    let count = values.clone().count() as f64;
+    let mean = values.clone().sum::<f64>() / count;
+    let variance = values.clone().map(|value| (value - mean).powi(2)).sum::<f64>() / count;
+    let min = values.clone().fold(f64::INFINITY, f64::min);
+    let max = values.fold(f64::NEG_INFINITY, f64::max);
+    AxisNoise {
+        mean,
+        std_dev: variance.sqrt(),
+        min,
+        max,
+        peak_to_peak: max - min,
+    }
+}
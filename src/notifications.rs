@@ -0,0 +1,252 @@
+// src/notifications.rs
+// Snoozes and duplicate suppression shared by the notification channels (/api/notifications):
+// a channel asks before sending whether it is snoozed and whether it sent the same text within
+// [notifications] dedup_secs, so a flapping sensor posts once per window instead of every time.
+// The text itself can come from a template ([notifications.templates], or a channel's own such as
+// [chat_bot.templates]) naming the observatory and the readings, for sites running several bridges
+
+use crate::config::NotificationsConfig;
+use crate::device_registry::DeviceHandle;
+use crate::events::BridgeEvent;
+use crate::timestamps;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+// Template key used for every announced event without a template of its own
+pub const DEFAULT_TEMPLATE: &str = "default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    // Send it; the count is how many identical texts were held back since it last went out
+    Send { held_back: u32 },
+    Snoozed,
+    Duplicate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelStatus {
+    pub channel: String,
+    // null when not snoozed
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub snoozed_until: u64,
+    // Held back since the bridge started
+    pub snoozed_count: u64,
+    pub duplicate_count: u64,
+}
+
+#[derive(Debug, Default)]
+struct Channel {
+    snoozed_until: Option<u64>,
+    snoozed_count: u64,
+    duplicate_count: u64,
+    // Text -> (last sent, identical texts held back since)
+    sent: HashMap<String, (u64, u32)>,
+}
+
+// Shared by the registry's clones
+#[derive(Clone, Default)]
+pub struct Notifications {
+    config: NotificationsConfig,
+    channels: Arc<Mutex<BTreeMap<String, Channel>>>,
+}
+
+impl Notifications {
+    pub fn new(config: NotificationsConfig) -> Self {
+        Self {
+            config,
+            channels: Arc::default(),
+        }
+    }
+
+    pub fn config(&self) -> &NotificationsConfig {
+        &self.config
+    }
+
+    // The template for an event type: the channel's own, else the shared one; the "default"
+    // templates only apply to events that have a built-in message
+    pub fn template<'a>(&'a self, channel_templates: &'a HashMap<String, String>, event_type: &str, has_message: bool) -> Option<&'a str> {
+        let find = |key: &str| channel_templates.get(key).or_else(|| self.config.templates.get(key));
+        find(event_type)
+            .or_else(|| if has_message { find(DEFAULT_TEMPLATE) } else { None })
+            .map(String::as_str)
+    }
+
+    // Make a channel known to /api/notifications, e.g. "chat_bot"
+    pub fn register(&self, channel: &str) {
+        self.channels.lock().unwrap().entry(channel.to_string()).or_default();
+    }
+
+    // Whether `text` should go out on the channel at `now`; counts it as sent if so
+    pub fn deliver(&self, channel: &str, text: &str, now: u64) -> Delivery {
+        let mut channels = self.channels.lock().unwrap();
+        let channel = channels.entry(channel.to_string()).or_default();
+        if channel.snoozed_until.is_some_and(|until| now < until) {
+            channel.snoozed_count += 1;
+            return Delivery::Snoozed;
+        }
+        channel.snoozed_until = None;
+        let window = self.config.dedup_secs;
+        let expired = |sent_at: u64| now.saturating_sub(sent_at) >= window;
+        // Texts whose window passed with nothing held back need no remembering
+        channel.sent.retain(|_, (sent_at, held_back)| *held_back > 0 || !expired(*sent_at));
+        match channel.sent.get_mut(text) {
+            Some((sent_at, held_back)) if !expired(*sent_at) => {
+                *held_back += 1;
+                channel.duplicate_count += 1;
+                Delivery::Duplicate
+            }
+            Some((sent_at, held_back)) => {
+                *sent_at = now;
+                Delivery::Send {
+                    held_back: std::mem::take(held_back),
+                }
+            }
+            None => {
+                channel.sent.insert(text.to_string(), (now, 0));
+                Delivery::Send { held_back: 0 }
+            }
+        }
+    }
+
+    // Hold back everything on the channel until `until`; false for an unknown channel
+    pub fn snooze(&self, channel: &str, until: u64) -> bool {
+        let mut channels = self.channels.lock().unwrap();
+        let Some(channel) = channels.get_mut(channel) else {
+            return false;
+        };
+        channel.snoozed_until = Some(until);
+        true
+    }
+
+    // End a snooze early; false for an unknown channel
+    pub fn unsnooze(&self, channel: &str) -> bool {
+        let mut channels = self.channels.lock().unwrap();
+        let Some(channel) = channels.get_mut(channel) else {
+            return false;
+        };
+        channel.snoozed_until = None;
+        true
+    }
+
+    pub fn status(&self, now: u64) -> Vec<ChannelStatus> {
+        self.channels
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, channel)| ChannelStatus {
+                channel: name.clone(),
+                snoozed_until: channel.snoozed_until.filter(|until| now < *until).unwrap_or(0),
+                snoozed_count: channel.snoozed_count,
+                duplicate_count: channel.duplicate_count,
+            })
+            .collect()
+    }
+}
+
+// Values a template can use for an event on a device: the built-in message, the event's own
+// fields as on /api/events, and the device's identity and current readings
+pub async fn event_variables(event: &BridgeEvent, device: &DeviceHandle, message: &str) -> HashMap<String, String> {
+    let identity = device.connection_manager.identity();
+    let max_data_age = device.connection_manager.max_data_age_secs();
+    let state = device.device_state.read().await;
+    let mut variables: HashMap<String, String> = [
+        ("message", message.to_string()),
+        ("event", event.kind.type_name().to_string()),
+        ("time", timestamps::format_secs(event.timestamp)),
+        ("device_number", device.device_number.to_string()),
+        ("device_name", identity.device_name(&state.device_name)),
+        ("server_name", identity.server_name.clone().unwrap_or_default()),
+        ("location", identity.location.clone().unwrap_or_default()),
+        ("pitch", format!("{:.2}", state.current_pitch)),
+        ("roll", format!("{:.2}", state.current_roll)),
+        ("safety", if state.is_safe_now(max_data_age) { "safe" } else { "unsafe" }.to_string()),
+        ("park_state", state.park_status_summary()),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect();
+    drop(state);
+
+    // Nested values (votes, firmware data) are left out; the event's pitch and roll win
+    if let Ok(Value::Object(fields)) = serde_json::to_value(&event.kind) {
+        for (name, value) in fields {
+            let value = match value {
+                Value::String(text) => text,
+                Value::Bool(flag) => flag.to_string(),
+                Value::Number(number) if number.is_f64() => format!("{:.2}", number.as_f64().unwrap_or_default()),
+                Value::Number(number) => number.to_string(),
+                Value::Null => String::new(),
+                Value::Array(_) | Value::Object(_) => continue,
+            };
+            if name != "type" {
+                variables.insert(name, value);
+            }
+        }
+    }
+    variables
+}
+
+// Template syntax: {name} is replaced by a variable, {{ and }} are literal braces
+pub fn check_template(template: &str) -> Result<(), String> {
+    parse_template(template).map(|_| ())
+}
+
+// Fill in a template checked with check_template; unknown variables stay as written, so a
+// misspelt name shows in the message
+pub fn render_template(template: &str, variables: &HashMap<String, String>) -> String {
+    let Ok(parts) = parse_template(template) else {
+        return template.to_string();
+    };
+    parts
+        .into_iter()
+        .map(|part| match part {
+            TemplatePart::Text(text) => text,
+            TemplatePart::Variable(name) => variables.get(&name).cloned().unwrap_or_else(|| format!("{{{}}}", name)),
+        })
+        .collect()
+}
+
+enum TemplatePart {
+    Text(String),
+    Variable(String),
+}
+
+fn parse_template(template: &str) -> Result<Vec<TemplatePart>, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) if c.is_ascii_alphanumeric() || c == '_' => name.push(c),
+                        Some(c) => return Err(format!("'{}' in {{{}...}}: variable names are letters, digits and _", c, name)),
+                        None => return Err(format!("{{{} is not closed with }}", name)),
+                    }
+                }
+                if name.is_empty() {
+                    return Err("{} names no variable; write {{ for a literal brace".to_string());
+                }
+                parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                parts.push(TemplatePart::Variable(name));
+            }
+            '}' => return Err("} without {; write }} for a literal brace".to_string()),
+            c => text.push(c),
+        }
+    }
+    parts.push(TemplatePart::Text(text));
+    Ok(parts)
+}
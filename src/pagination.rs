@@ -0,0 +1,70 @@
+// src/pagination.rs
+// Cursor, time-range, sort order and limit/offset filters shared by the event and command history endpoints
+
+use serde::Deserialize;
+
+// Largest page a single request may ask for
+pub const MAX_PAGE_LIMIT: usize = 1000;
+
+// Response header carrying the number of matching entries before offset/limit are applied
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+// Entries that can be paged: a monotonically increasing id and a time
+pub trait Paginated {
+    fn id(&self) -> u64;
+    // Seconds since the Unix epoch
+    fn timestamp_secs(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    // Oldest first
+    #[default]
+    Asc,
+    Desc,
+}
+
+// Query string of a paged endpoint; without any parameters every buffered entry is returned, oldest first
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageQuery {
+    // Only entries with a larger id than this, for incremental polling
+    pub since: Option<u64>,
+    // Inclusive time range, RFC 3339 or Unix seconds
+    #[serde(default, deserialize_with = "crate::timestamps::deserialize_optional_secs")]
+    pub from: Option<u64>,
+    #[serde(default, deserialize_with = "crate::timestamps::deserialize_optional_secs")]
+    pub to: Option<u64>,
+    #[serde(default)]
+    pub order: SortOrder,
+    #[serde(default)]
+    pub offset: usize,
+    // Capped at MAX_PAGE_LIMIT
+    pub limit: Option<usize>,
+}
+
+pub struct Page<T> {
+    pub items: Vec<T>,
+    // Matching entries before offset and limit
+    pub total: usize,
+}
+
+impl PageQuery {
+    // `entries` must be in id order, oldest first
+    pub fn apply<T: Paginated>(&self, entries: Vec<T>) -> Page<T> {
+        let mut matching: Vec<T> = entries
+            .into_iter()
+            .filter(|entry| self.since.is_none_or(|since| entry.id() > since))
+            .filter(|entry| self.from.is_none_or(|from| entry.timestamp_secs() >= from))
+            .filter(|entry| self.to.is_none_or(|to| entry.timestamp_secs() <= to))
+            .collect();
+        if self.order == SortOrder::Desc {
+            matching.reverse();
+        }
+
+        let total = matching.len();
+        let limit = self.limit.unwrap_or(MAX_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+        let items = matching.into_iter().skip(self.offset).take(limit).collect();
+        Page { items, total }
+    }
+}
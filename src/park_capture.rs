@@ -0,0 +1,208 @@
+// src/park_capture.rs
+// Averaged set-park ([set_park]): samples the position for a few seconds, drops outliers, then has
+// the firmware set park right after a reading that matches the average and reads the result back
+
+use crate::config::SetParkConfig;
+use crate::connection_manager::ConnectionManager;
+use crate::device_state::{FirmwareResponse, PositionResponse};
+use crate::errors::{BridgeError, Result};
+use crate::protocol;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+// Floor for the outlier and match thresholds, so a perfectly quiet sensor does not have readings
+// rejected over float rounding
+const MIN_SPREAD: f64 = 0.01;
+// Median absolute deviation to standard deviation, for normally distributed noise
+const MAD_SCALE: f64 = 1.4826;
+
+// Mean of the readings left after outlier rejection
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct AveragedPosition {
+    pub samples: usize,
+    pub rejected: usize,
+    pub pitch: f64,
+    pub roll: f64,
+    pub pitch_std_dev: f64,
+    pub roll_std_dev: f64,
+}
+
+impl AveragedPosition {
+    fn is_near(&self, pitch: f32, roll: f32, threshold: f64) -> bool {
+        (pitch as f64 - self.pitch).abs() <= threshold && (roll as f64 - self.roll).abs() <= threshold
+    }
+}
+
+// Response of an averaged set-park
+#[derive(Debug, Clone, Serialize)]
+pub struct ParkCapture {
+    #[serde(flatten)]
+    pub average: AveragedPosition,
+    // Set-park commands sent
+    pub attempts: u32,
+    // The park position the firmware stored, read back afterwards; null if it has no get-park command
+    pub park_pitch: Option<f32>,
+    pub park_roll: Option<f32>,
+    // Whether the stored park landed within the match threshold of the average (null: not read back)
+    pub matched: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ParkPosition {
+    park_pitch: f32,
+    park_roll: f32,
+}
+
+// Per axis, readings further than `outlier_mads` scaled median absolute deviations from the
+// median are dropped; a reading is kept only if both its axes are
+pub fn average_without_outliers(readings: &[(f32, f32)], outlier_mads: f64) -> Option<AveragedPosition> {
+    if readings.is_empty() {
+        return None;
+    }
+    let pitch_range = inlier_range(readings.iter().map(|(pitch, _)| *pitch as f64).collect(), outlier_mads);
+    let roll_range = inlier_range(readings.iter().map(|(_, roll)| *roll as f64).collect(), outlier_mads);
+    let kept: Vec<(f64, f64)> = readings
+        .iter()
+        .map(|(pitch, roll)| (*pitch as f64, *roll as f64))
+        .filter(|(pitch, roll)| (pitch - pitch_range.0).abs() <= pitch_range.1 && (roll - roll_range.0).abs() <= roll_range.1)
+        .collect();
+
+    let count = kept.len() as f64;
+    let pitch = kept.iter().map(|(pitch, _)| pitch).sum::<f64>() / count;
+    let roll = kept.iter().map(|(_, roll)| roll).sum::<f64>() / count;
+    let std_dev = |values: &mut dyn Iterator<Item = f64>, mean: f64| {
+        (values.map(|value| (value - mean).powi(2)).sum::<f64>() / count).sqrt()
+    };
+    Some(AveragedPosition {
+        samples: readings.len(),
+        rejected: readings.len() - kept.len(),
+        pitch,
+        roll,
+        pitch_std_dev: std_dev(&mut kept.iter().map(|(pitch, _)| *pitch), pitch),
+        roll_std_dev: std_dev(&mut kept.iter().map(|(_, roll)| *roll), roll),
+    })
+}
+
+// Median and the largest distance from it that still counts as an inlier
+fn inlier_range(mut values: Vec<f64>, outlier_mads: f64) -> (f64, f64) {
+    let center = median(&mut values);
+    let mut deviations: Vec<f64> = values.iter().map(|value| (value - center).abs()).collect();
+    let mad = median(&mut deviations) * MAD_SCALE;
+    (center, outlier_mads * mad.max(MIN_SPREAD))
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+    match values.len() % 2 {
+        0 => (values[middle - 1] + values[middle]) / 2.0,
+        _ => values[middle],
+    }
+}
+
+// Sample, average, then set park. Fails without touching the stored park when the readings
+// scatter too much or no later reading comes back to the average, i.e. the telescope is moving
+pub async fn capture(manager: &ConnectionManager, config: &SetParkConfig) -> Result<ParkCapture> {
+    let interval = Duration::from_millis(config.interval_ms);
+    let mut readings = Vec::with_capacity(config.samples as usize);
+    for index in 0..config.samples {
+        if index > 0 {
+            tokio::time::sleep(interval).await;
+        }
+        readings.push(read_position(manager).await?);
+    }
+    let average = average_without_outliers(&readings, config.outlier_mads)
+        .ok_or_else(|| BridgeError::CommandFailed("no position readings".to_string()))?;
+    info!(
+        "Set park: average pitch={:.3}° roll={:.3}° from {} of {} readings",
+        average.pitch,
+        average.roll,
+        average.samples - average.rejected,
+        average.samples
+    );
+    if average.rejected * 2 > average.samples {
+        return Err(BridgeError::CommandFailed(format!(
+            "{} of {} position readings were outliers; is the telescope moving?",
+            average.rejected, average.samples
+        )));
+    }
+    let spread = average.pitch_std_dev.max(average.roll_std_dev);
+    if spread > config.max_std_dev {
+        return Err(BridgeError::CommandFailed(format!(
+            "position readings scatter by {:.2}° (limit {:.2}°); is the telescope moving?",
+            spread, config.max_std_dev
+        )));
+    }
+
+    // The firmware stores its own next reading, so set park right after one close to the average
+    // and check what was stored
+    let threshold = spread.max(MIN_SPREAD);
+    let mut attempts = 0;
+    let mut stored = None;
+    for _ in 0..config.samples {
+        tokio::time::sleep(interval).await;
+        let (pitch, roll) = read_position(manager).await?;
+        if !average.is_near(pitch, roll, threshold) {
+            debug!("Set park: reading pitch={:.3}° roll={:.3}° is off the average", pitch, roll);
+            continue;
+        }
+        attempts += 1;
+        manager.send_command(protocol::SOFTWARE_SET_PARK).await?;
+        stored = read_park(manager).await?;
+        match stored {
+            Some((pitch, roll)) if !average.is_near(pitch, roll, threshold) && attempts < config.attempts => {
+                debug!("Set park: stored pitch={:.3}° roll={:.3}° is off the average, retrying", pitch, roll);
+            }
+            _ => break,
+        }
+    }
+    if attempts == 0 {
+        return Err(BridgeError::CommandFailed(format!(
+            "no reading came back within {:.2}° of the average; is the telescope moving?",
+            threshold
+        )));
+    }
+
+    let matched = stored.map(|(pitch, roll)| average.is_near(pitch, roll, threshold));
+    if let (Some((pitch, roll)), Some(false)) = (stored, matched) {
+        warn!(
+            "Set park: stored pitch={:.3}° roll={:.3}° is more than {:.2}° from the average after {} attempts",
+            pitch, roll, threshold, attempts
+        );
+    }
+    Ok(ParkCapture {
+        average,
+        attempts,
+        park_pitch: stored.map(|(pitch, _)| pitch),
+        park_roll: stored.map(|(_, roll)| roll),
+        matched,
+    })
+}
+
+async fn read_position(manager: &ConnectionManager) -> Result<(f32, f32)> {
+    let position: PositionResponse = serde_json::from_value(query(manager, protocol::GET_POSITION).await?)?;
+    Ok((position.pitch, position.roll))
+}
+
+// None when the firmware has no get-park command
+async fn read_park(manager: &ConnectionManager) -> Result<Option<(f32, f32)>> {
+    match query(manager, protocol::GET_PARK).await {
+        Ok(data) => {
+            let park: ParkPosition = serde_json::from_value(data)?;
+            Ok(Some((park.park_pitch, park.park_roll)))
+        }
+        Err(BridgeError::InvalidCommand(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// Data payload of a command's response
+async fn query(manager: &ConnectionManager, command: &str) -> Result<serde_json::Value> {
+    let response = manager.send_command(command).await?;
+    let parsed: FirmwareResponse = serde_json::from_str(&response)?;
+    parsed
+        .data
+        .ok_or_else(|| BridgeError::InvalidResponse(format!("no data in {}", response)))
+}
@@ -1,6 +1,20 @@
+use crate::config::{FlowControlMode, FramingConfig, SerialConfig, UsbMatch};
+use crate::protocol;
 use anyhow::Result;
 use serialport::SerialPortType;
 use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::time::Instant;
+use tokio_serial::SerialPortBuilderExt;
+use tracing::{debug, info};
+
+// How long --auto waits for a candidate port to answer <08>
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Lets a board that prints on connect finish before the probe is written
+const PROBE_SETTLE: Duration = Duration::from_millis(200);
 
 #[derive(Debug, Clone, Serialize)]
 pub struct PortInfo {
@@ -8,6 +22,21 @@ pub struct PortInfo {
     pub description: String,
     pub manufacturer: Option<String>,
     pub vid_pid: Option<String>,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+}
+
+impl PortInfo {
+    // Whether this is the USB device of a [usb] / [devices.usb] table
+    pub fn matches(&self, usb: &UsbMatch) -> bool {
+        let serial_matches = match (&usb.serial_number, &self.serial_number) {
+            (None, _) => true,
+            (Some(wanted), Some(serial)) => wanted.trim().eq_ignore_ascii_case(serial.trim()),
+            (Some(_), None) => false,
+        };
+        usb.vid.is_none_or(|vid| self.vid == Some(vid)) && usb.pid.is_none_or(|pid| self.pid == Some(pid)) && serial_matches
+    }
 }
 
 pub fn discover_ports() -> Result<Vec<PortInfo>> {
@@ -16,9 +45,11 @@ pub fn discover_ports() -> Result<Vec<PortInfo>> {
     let mut discovered_ports = Vec::new();
     
     for port in ports {
+        let mut usb_ids = (None, None, None);
         let (description, manufacturer, vid_pid) = match &port.port_type {
             SerialPortType::UsbPort(usb_info) => {
                 let vid_pid = format!("VID:{:04X} PID:{:04X}", usb_info.vid, usb_info.pid);
+                usb_ids = (Some(usb_info.vid), Some(usb_info.pid), usb_info.serial_number.clone());
                 
                 // Enhanced description for known nRF52840 devices
                 let description = if usb_info.vid == 0x2886 {  // Seeed Studio VID
@@ -55,6 +86,9 @@ pub fn discover_ports() -> Result<Vec<PortInfo>> {
             description,
             manufacturer,
             vid_pid,
+            vid: usb_ids.0,
+            pid: usb_ids.1,
+            serial_number: usb_ids.2,
         });
     }
     
@@ -68,6 +102,24 @@ pub fn discover_ports() -> Result<Vec<PortInfo>> {
     Ok(discovered_ports)
 }
 
+// Fixed-width listing for the list-ports subcommand, likely sensors first
+pub fn format_table(ports: &[PortInfo]) -> String {
+    if ports.is_empty() {
+        return "No serial ports found".to_string();
+    }
+    let mut table = format!("{:<24} {:<26} {:<20} {}\n", "port", "usb id", "serial number", "description");
+    for port in ports {
+        table.push_str(&format!(
+            "{:<24} {:<26} {:<20} {}\n",
+            port.name,
+            port.vid_pid.as_deref().unwrap_or("-"),
+            port.serial_number.as_deref().unwrap_or("-"),
+            port.description
+        ));
+    }
+    table
+}
+
 fn get_device_priority(description: &str) -> i32 {
     let desc_lower = description.to_lowercase();
     
@@ -87,4 +139,96 @@ fn get_device_priority(description: &str) -> i32 {
     } else {
         0    // Lowest priority for unknown devices
     }
-}
\ No newline at end of file
+}
+
+// What a port answered to the version probe
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeReply {
+    pub device_name: String,
+    pub firmware_version: String,
+}
+
+// The park sensor firmware's <08> reply carries firmwareVersion and deviceName; a mount
+// controller or GPS on another port answers <08> with something else or not at all
+fn park_sensor_reply(line: &str) -> Option<ProbeReply> {
+    let reply: Value = serde_json::from_str(line.trim()).ok()?;
+    if reply.get("status").and_then(Value::as_str) != Some("ok") {
+        return None;
+    }
+    let data = reply.get("data")?;
+    Some(ProbeReply {
+        device_name: data.get("deviceName")?.as_str()?.to_string(),
+        firmware_version: data.get("firmwareVersion")?.as_str()?.to_string(),
+    })
+}
+
+// Open the port briefly, send <08> and wait for the park sensor's answer; the error says why the
+// port is not taken to be the sensor
+pub async fn probe_port(
+    port_name: &str,
+    baud_rate: u32,
+    serial: &SerialConfig,
+    framing: &FramingConfig,
+    timeout: Duration,
+) -> std::result::Result<ProbeReply, String> {
+    let mut port = tokio_serial::new(port_name, baud_rate)
+        .timeout(timeout)
+        .flow_control(serial.flow_control.into())
+        .open_native_async()
+        .map_err(|e| format!("cannot open: {}", e))?;
+    {
+        use tokio_serial::SerialPort;
+        let _ = port.write_data_terminal_ready(serial.dtr);
+        if serial.flow_control != FlowControlMode::Hardware {
+            let _ = port.write_request_to_send(serial.rts);
+        }
+    }
+    tokio::time::sleep(PROBE_SETTLE).await;
+    port.write_all(framing.encode(protocol::GET_VERSION).as_bytes())
+        .await
+        .map_err(|e| format!("cannot write: {}", e))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut received = String::new();
+    let mut chunk = [0u8; 512];
+    loop {
+        let read = match tokio::time::timeout_at(deadline, port.read(&mut chunk)).await {
+            Ok(Ok(0)) => return Err("closed without answering".to_string()),
+            Ok(Ok(read)) => read,
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+            Ok(Err(e)) => return Err(format!("cannot read: {}", e)),
+            Err(_) if received.trim().is_empty() => return Err(format!("no answer within {} ms", timeout.as_millis())),
+            Err(_) => return Err("answered, but not like the park sensor firmware".to_string()),
+        };
+        received.push_str(&String::from_utf8_lossy(&chunk[..read]));
+        // Acks, events and startup chatter come before the reply; only complete lines are checked
+        while let Some(end) = received.find(&framing.response_terminator) {
+            let line: String = received.drain(..end + framing.response_terminator.len()).collect();
+            if let Some(reply) = park_sensor_reply(&line) {
+                return Ok(reply);
+            }
+            debug!("Probe of {}: ignoring {}", port_name, line.trim());
+        }
+    }
+}
+
+// The first port, in discover_ports() order, that answers like the park sensor. Bluetooth ports
+// are skipped: opening one can block for a long time while the OS tries to pair
+pub async fn find_park_sensor(
+    ports: &[PortInfo],
+    baud_rate: u32,
+    serial: &SerialConfig,
+    framing: &FramingConfig,
+    timeout: Duration,
+) -> Option<(PortInfo, ProbeReply)> {
+    for port in ports.iter().filter(|port| port.description != "Bluetooth Serial Port") {
+        match probe_port(&port.name, baud_rate, serial, framing, timeout).await {
+            Ok(reply) => {
+                info!("{} is {} (firmware {})", port.name, reply.device_name, reply.firmware_version);
+                return Some((port.clone(), reply));
+            }
+            Err(reason) => info!("{} ({}) is not the park sensor: {}", port.name, port.description, reason),
+        }
+    }
+    None
+}
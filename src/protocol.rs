@@ -0,0 +1,318 @@
+// src/protocol.rs
+// nRF52840 firmware command codes and how long each command may take to answer
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Help text listing the firmware's commands; newer builds answer with a structured list
+pub const HELP: &str = "00";
+pub const GET_STATUS: &str = "01";
+pub const GET_POSITION: &str = "02";
+pub const IS_PARKED: &str = "03";
+pub const SET_PARK: &str = "04";
+pub const GET_PARK: &str = "05";
+pub const CALIBRATE: &str = "06";
+pub const TOGGLE_DEBUG: &str = "07";
+pub const GET_VERSION: &str = "08";
+// Followed by three digits: tolerance in hundredths of a degree
+pub const SET_TOLERANCE_PREFIX: &str = "0A";
+pub const GET_TOLERANCE: &str = "0B";
+pub const SYSTEM_INFO: &str = "0C";
+pub const SOFTWARE_SET_PARK: &str = "0D";
+pub const FACTORY_RESET: &str = "0E";
+
+// Codes accepted by POST /api/command by default; factory reset needs expert mode
+pub const DEFAULT_ALLOWED_COMMANDS: &[&str] = &[
+    GET_STATUS,
+    GET_POSITION,
+    IS_PARKED,
+    SET_PARK,
+    GET_PARK,
+    CALIBRATE,
+    TOGGLE_DEBUG,
+    GET_VERSION,
+    SET_TOLERANCE_PREFIX,
+    GET_TOLERANCE,
+    SYSTEM_INFO,
+    SOFTWARE_SET_PARK,
+];
+
+// Longest command the firmware accepts: a code plus parameter digits
+pub const MAX_COMMAND_LEN: usize = 8;
+
+// Cheapest command with a data response, used as the link heartbeat
+pub const PING: &str = GET_VERSION;
+
+// Queries are answered straight from RAM; anything slower than this means the link is in trouble
+pub const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Allowance on top of a command's timeout for queueing behind other commands
+pub const QUEUE_GRACE: Duration = Duration::from_secs(5);
+
+// Built-in timeout for a command code, before any config overrides
+pub fn default_timeout(command: &str) -> Duration {
+    match command_code(command) {
+        // IMU calibration averages samples for several seconds
+        CALIBRATE => Duration::from_secs(30),
+        // Flash writes
+        FACTORY_RESET => Duration::from_secs(20),
+        SET_PARK | SOFTWARE_SET_PARK | SET_TOLERANCE_PREFIX => Duration::from_secs(10),
+        _ => DEFAULT_COMMAND_TIMEOUT,
+    }
+}
+
+// The two-character command code, without any parameter digits
+pub fn command_code(command: &str) -> &str {
+    command.get(..2).unwrap_or(command)
+}
+
+// A two-digit hex code optionally followed by decimal parameter digits, e.g. "0B" or "0A150"
+pub fn validate_command(command: &str) -> Result<(), String> {
+    // Checked first, so splitting after the code cannot land inside a character
+    if !command.is_ascii() {
+        return Err(format!("'{}' is not a hex command code with decimal parameters", command));
+    }
+    if command.len() < 2 || command.len() > MAX_COMMAND_LEN {
+        return Err(format!("command must be 2 to {} characters long", MAX_COMMAND_LEN));
+    }
+    let (code, parameters) = command.split_at(2);
+    if !code.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("'{}' is not a two-digit hex command code", code));
+    }
+    if !parameters.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("command parameters '{}' must be decimal digits", parameters));
+    }
+    Ok(())
+}
+
+// Tolerance range <0A###> can express: 0.01° to 9.99° in hundredths
+pub const MIN_TOLERANCE: f32 = 0.01;
+pub const MAX_TOLERANCE: f32 = 9.99;
+
+// <0A###> for a tolerance in degrees, e.g. 1.5 -> "0A150"
+pub fn set_tolerance_command(degrees: f32) -> Result<String, String> {
+    if !(MIN_TOLERANCE..=MAX_TOLERANCE).contains(&degrees) {
+        return Err(format!("tolerance must be between {:.2}° and {:.2}°", MIN_TOLERANCE, MAX_TOLERANCE));
+    }
+    Ok(format!("{}{:03}", SET_TOLERANCE_PREFIX, (degrees * 100.0).round() as u32))
+}
+
+// Versions of the firmware's JSON protocol; the firmware reports its own as protocolVersion in the
+// <08> reply and the bridge speaks the highest version both sides know
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtocolVersion {
+    // Original firmware without protocolVersion: a reply is recognised by the fields it carries
+    #[default]
+    V1,
+    // Data replies name the command they answer, so fields added by newer firmware cannot make
+    // one reply look like another
+    V2,
+}
+
+// Newest version this bridge speaks
+pub const LATEST_PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion::V2;
+
+// Which firmware reply a data payload is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyKind {
+    Status,
+    Position,
+    ParkStatus,
+    Version,
+    Message,
+    Unknown,
+}
+
+impl ProtocolVersion {
+    // Version to speak with firmware reporting `reported` (the protocolVersion of its <08> reply);
+    // firmware newer than the bridge is spoken to in the bridge's latest version
+    pub fn negotiate(reported: Option<&Value>) -> Self {
+        match reported.and_then(Value::as_u64) {
+            None | Some(0) | Some(1) => Self::V1,
+            Some(_) => LATEST_PROTOCOL_VERSION,
+        }
+    }
+
+    pub fn number(self) -> u8 {
+        match self {
+            Self::V1 => 1,
+            Self::V2 => 2,
+        }
+    }
+
+    // Classify a data payload; `command` is the code a V2 reply names, absent on V1 replies and
+    // unsolicited events, which are recognised by their fields in either version
+    pub fn classify(self, command: Option<&str>, data: &Value) -> ReplyKind {
+        if let (Self::V2, Some(command)) = (self, command) {
+            return match command_code(command) {
+                GET_STATUS => ReplyKind::Status,
+                GET_POSITION => ReplyKind::Position,
+                IS_PARKED => ReplyKind::ParkStatus,
+                GET_VERSION => ReplyKind::Version,
+                _ if data.get("message").is_some() => ReplyKind::Message,
+                _ => ReplyKind::Unknown,
+            };
+        }
+        let has = |field: &str| data.get(field).is_some();
+        if has("parked") && has("calibrated") {
+            ReplyKind::Status
+        } else if has("pitch") && has("roll") {
+            ReplyKind::Position
+        } else if has("parked") && has("currentPitch") {
+            ReplyKind::ParkStatus
+        } else if has("firmwareVersion") {
+            ReplyKind::Version
+        } else if has("message") {
+            ReplyKind::Message
+        } else {
+            ReplyKind::Unknown
+        }
+    }
+}
+
+// Per-command timeouts: protocol defaults plus overrides keyed by command code
+#[derive(Debug, Clone, Default)]
+pub struct CommandTimeouts {
+    overrides: HashMap<String, Duration>,
+}
+
+impl CommandTimeouts {
+    pub fn new(overrides: HashMap<String, Duration>) -> Self {
+        Self {
+            overrides: overrides
+                .into_iter()
+                .map(|(command, timeout)| (command.to_uppercase(), timeout))
+                .collect(),
+        }
+    }
+
+    pub fn timeout_for(&self, command: &str) -> Duration {
+        let code = command_code(command).to_uppercase();
+        self.overrides
+            .get(&code)
+            .copied()
+            .unwrap_or_else(|| default_timeout(&code))
+    }
+
+    // Slowest command the bridge may wait for, across the built-in codes and any overrides
+    pub fn longest(&self) -> Duration {
+        DEFAULT_ALLOWED_COMMANDS
+            .iter()
+            .chain([&FACTORY_RESET])
+            .map(|command| default_timeout(command))
+            .chain(self.overrides.values().copied())
+            .max()
+            .unwrap_or(DEFAULT_COMMAND_TIMEOUT)
+    }
+}
+
+// What the connected firmware supports, from its answer to <00> at connect time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareCapabilities {
+    // False until the firmware listed its commands; every command is then assumed to exist
+    pub discovered: bool,
+    pub commands: Vec<String>,
+    pub set_tolerance: bool,
+    // Optional hardware extras, only assumed when the firmware names them
+    pub identify: bool,
+    pub battery: bool,
+    pub temperature: bool,
+}
+
+impl Default for FirmwareCapabilities {
+    fn default() -> Self {
+        Self {
+            discovered: false,
+            commands: Vec::new(),
+            set_tolerance: true,
+            identify: false,
+            battery: false,
+            temperature: false,
+        }
+    }
+}
+
+impl FirmwareCapabilities {
+    // From the data payload of <00>: {"commands": [...], "features": [...]} or a help message
+    // such as "Available Commands: 01-0E" or one "<0A###> Set tolerance" line per command
+    pub fn from_help(data: &Value) -> Option<Self> {
+        let strings = |name: &str| -> Option<Vec<String>> {
+            data.get(name)?
+                .as_array()
+                .map(|values| values.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        };
+        let (mut commands, description) = match strings("commands") {
+            Some(commands) => {
+                let features = strings("features").or_else(|| strings("capabilities")).unwrap_or_default();
+                let codes = commands.iter().map(|command| command_code(command).to_uppercase()).collect();
+                (codes, features.join(" "))
+            }
+            None => {
+                let text = data.get("message").and_then(Value::as_str)?;
+                (help_commands(text), text.to_string())
+            }
+        };
+        if commands.is_empty() {
+            return None;
+        }
+        commands.sort();
+        commands.dedup();
+        let description = description.to_lowercase();
+        Some(Self {
+            discovered: true,
+            set_tolerance: commands.iter().any(|command| command == SET_TOLERANCE_PREFIX),
+            identify: description.contains("identify"),
+            battery: description.contains("battery"),
+            temperature: description.contains("temperature"),
+            commands,
+        })
+    }
+
+    // Only the codes the bridge itself uses are gated; expert-mode codes it does not know are
+    // left for the firmware to answer
+    pub fn supports(&self, command: &str) -> bool {
+        let code = command_code(command).to_uppercase();
+        let known = DEFAULT_ALLOWED_COMMANDS.iter().chain([&FACTORY_RESET]).any(|known| *known == code);
+        !self.discovered || !known || self.commands.contains(&code)
+    }
+}
+
+// Command codes named in a help text: "<0A###>"-style codes when the text uses brackets, otherwise
+// bare two-digit codes and "01-0E" ranges
+fn help_commands(text: &str) -> Vec<String> {
+    let is_code = |token: &str| {
+        token.len() == 2
+            && token.chars().all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c))
+            && token.chars().any(|c| c.is_ascii_digit())
+    };
+    let bracketed: Vec<&str> = text
+        .split('<')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('>').map(|(inside, _)| inside))
+        .collect();
+    if !bracketed.is_empty() {
+        // Bracketed codes may carry parameter placeholders or digits after the code
+        return bracketed
+            .into_iter()
+            .map(command_code)
+            .filter(|code| is_code(code))
+            .map(str::to_string)
+            .collect();
+    }
+    let mut commands = Vec::new();
+    for token in text.split(|c: char| !c.is_ascii_alphanumeric() && c != '-') {
+        match token.split_once('-') {
+            Some((first, last)) if is_code(first) && is_code(last) => {
+                let first = u8::from_str_radix(first, 16).unwrap_or_default();
+                let last = u8::from_str_radix(last, 16).unwrap_or_default();
+                commands.extend((first..=last).map(|code| format!("{:02X}", code)));
+            }
+            None if is_code(token) => commands.push(token.to_string()),
+            _ => {}
+        }
+    }
+    commands
+}
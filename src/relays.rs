@@ -0,0 +1,198 @@
+// src/relays.rs
+// Relay outputs (feature "relays"): USB HID relay boards and Shelly/Tasmota network relays switched
+// when a device's IsSafe changes, e.g. to cut power to the roof motor while the mount is unparked
+
+use crate::config::{RelayConfig, RelayDriver};
+use crate::device_registry::DeviceRegistry;
+use crate::errors::{BridgeError, Result};
+use crate::events::EventKind;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+// Requests to a network relay that take longer than this count as failed
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// IsSafe is re-read this often; a failed switch is retried after RETRY_INTERVAL
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+// Vendor and product id shared by the common USB HID relay boards
+const USB_RELAY_VENDOR_ID: u16 = 0x16c0;
+const USB_RELAY_PRODUCT_ID: u16 = 0x05df;
+
+// Feature report commands of those boards, followed by the relay number
+const USB_RELAY_ON: u8 = 0xff;
+const USB_RELAY_OFF: u8 = 0xfd;
+
+fn relay_error(e: impl std::fmt::Display) -> BridgeError {
+    BridgeError::Relay(e.to_string())
+}
+
+#[derive(Clone)]
+pub struct Relay {
+    config: RelayConfig,
+    client: reqwest::Client,
+}
+
+impl Relay {
+    pub fn new(config: RelayConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("telescope_park_bridge/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(relay_error)?;
+        Ok(Self { config, client })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    pub async fn switch(&self, on: bool) -> Result<()> {
+        match &self.config.driver {
+            RelayDriver::UsbHid { board, channel } => {
+                let (board, channel) = (board.clone(), *channel);
+                tokio::task::spawn_blocking(move || switch_usb_hid(board.as_deref(), channel, on))
+                    .await
+                    .map_err(relay_error)?
+            }
+            RelayDriver::Shelly { url, channel, generation } => {
+                let (path, query) = match generation {
+                    1 => (format!("/relay/{}", channel), vec![("turn", if on { "on" } else { "off" }.to_string())]),
+                    _ => ("/rpc/Switch.Set".to_string(), vec![("id", channel.to_string()), ("on", on.to_string())]),
+                };
+                self.get(url, &path, &query).await
+            }
+            RelayDriver::Tasmota { url, channel } => {
+                let command = format!("Power{} {}", channel, if on { "On" } else { "Off" });
+                self.get(url, "/cm", &[("cmnd", command)]).await
+            }
+        }
+    }
+
+    async fn get(&self, base: &str, path: &str, query: &[(&str, String)]) -> Result<()> {
+        let url = format!("{}{}", base.trim_end_matches('/'), path);
+        let response = self.client.get(&url).query(query).send().await.map_err(relay_error)?;
+        if !response.status().is_success() {
+            return Err(BridgeError::Relay(format!("{} answered {}", url, response.status())));
+        }
+        Ok(())
+    }
+}
+
+fn switch_usb_hid(board: Option<&str>, channel: u8, on: bool) -> Result<()> {
+    let api = hidapi::HidApi::new().map_err(relay_error)?;
+    let boards = api
+        .device_list()
+        .filter(|info| info.vendor_id() == USB_RELAY_VENDOR_ID && info.product_id() == USB_RELAY_PRODUCT_ID);
+    for info in boards {
+        let device = info.open_device(&api).map_err(relay_error)?;
+        if let Some(board) = board {
+            // Report 0 starts with the five-byte board id
+            let mut report = [0u8; 9];
+            device.get_feature_report(&mut report).map_err(relay_error)?;
+            let id = report[1..6].split(|byte| *byte == 0).next().unwrap_or_default();
+            if id != board.as_bytes() {
+                continue;
+            }
+        }
+        let command = if on { USB_RELAY_ON } else { USB_RELAY_OFF };
+        return device
+            .send_feature_report(&[0, command, channel, 0, 0, 0, 0, 0, 0])
+            .map_err(relay_error);
+    }
+    Err(BridgeError::Relay(match board {
+        Some(board) => format!("USB relay board {} not found", board),
+        None => "no USB relay board found".to_string(),
+    }))
+}
+
+// A relay with what it should show and what it was last switched to
+struct RelayState {
+    relay: Relay,
+    // IsSafe last seen; None until the first read
+    is_safe: Option<bool>,
+    target: Option<bool>,
+    applied: Option<bool>,
+    last_attempt: Option<Instant>,
+}
+
+// Follow the devices' IsSafe until cancelled, switching each relay as its on_safe/on_unsafe say.
+// The relays are set once at startup from the current state, and failed switches are retried.
+pub async fn run_relays(relays: Vec<Relay>, devices: DeviceRegistry, cancel_token: CancellationToken) {
+    let mut states: Vec<RelayState> = relays
+        .into_iter()
+        .map(|relay| RelayState {
+            relay,
+            is_safe: None,
+            target: None,
+            applied: None,
+            last_attempt: None,
+        })
+        .collect();
+    let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Relay outputs stopped");
+                return;
+            }
+            _ = interval.tick() => {}
+        }
+
+        for state in &mut states {
+            let config = &state.relay.config;
+            let Some(device) = devices.get(config.device_number) else {
+                continue;
+            };
+            let device_state = device.device_state.read().await;
+            // Relays stay as they are during maintenance, then follow IsSafe again
+            if device_state.active_maintenance().is_some() {
+                continue;
+            }
+            let is_safe = device_state.is_safe_now(device.connection_manager.max_data_age_secs());
+            drop(device_state);
+            if state.is_safe != Some(is_safe) {
+                state.is_safe = Some(is_safe);
+                let action = if is_safe { config.on_safe } else { config.on_unsafe };
+                if let Some(target) = action.target() {
+                    state.target = Some(target);
+                    state.last_attempt = None;
+                }
+            }
+
+            let Some(target) = state.target.filter(|target| state.applied != Some(*target)) else {
+                continue;
+            };
+            if state.last_attempt.is_some_and(|attempt| attempt.elapsed() < RETRY_INTERVAL) {
+                continue;
+            }
+            // Only the first failure is reported; retries are logged at debug level
+            let first_attempt = state.last_attempt.replace(Instant::now()).is_none();
+            let name = state.relay.name().to_string();
+            let switched = if target { "on" } else { "off" };
+            let error = match state.relay.switch(target).await {
+                Ok(()) => {
+                    info!("Relay {} switched {} (IsSafe {})", name, switched, is_safe);
+                    state.applied = Some(target);
+                    None
+                }
+                Err(e) if first_attempt => {
+                    warn!("Cannot switch relay {} {}: {}; retrying every {}s", name, switched, e, RETRY_INTERVAL.as_secs());
+                    Some(e.to_string())
+                }
+                Err(e) => {
+                    debug!("Relay {} retry failed: {}", name, e);
+                    continue;
+                }
+            };
+            device.connection_manager.event_bus().publish(EventKind::RelaySwitched {
+                relay: name,
+                device_number: device.device_number,
+                on: target,
+                error,
+            });
+        }
+    }
+}
@@ -0,0 +1,206 @@
+// src/remote_sensor.rs
+// Remote-sensor driver: mirrors a sensor served by another bridge (or any Alpaca SafetyMonitor)
+// over HTTP, so a central bridge can re-export sensors attached to distant machines
+
+use crate::config::RemoteConfig;
+use crate::device_state::DeviceState;
+use crate::errors::{BridgeError, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+// Requests to a remote bridge that take longer than this count as a failed poll
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How the remote is read: the bridge web API carries the full device state, plain Alpaca only IsSafe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RemoteApi {
+    BridgeStatus,
+    Alpaca,
+}
+
+#[derive(Serialize)]
+struct RemoteCommandRequest<'a> {
+    command: &'a str,
+}
+
+#[derive(Deserialize)]
+struct RemoteCommandResponse {
+    success: bool,
+    response: Option<String>,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct AlpacaValue<T> {
+    #[serde(rename = "Value")]
+    value: T,
+    #[serde(rename = "ErrorNumber", default)]
+    error_number: i64,
+    #[serde(rename = "ErrorMessage", default)]
+    error_message: String,
+}
+
+#[derive(Clone)]
+pub struct RemoteSensor {
+    config: RemoteConfig,
+    client: reqwest::Client,
+}
+
+impl RemoteSensor {
+    pub fn new(config: RemoteConfig) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .user_agent(concat!("telescope_park_bridge/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(remote_error)?;
+        Ok(Self { config, client })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.config.url
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.config.url.trim_end_matches('/'), path)
+    }
+
+    // Forward a raw firmware command through the remote bridge's /api/command
+    pub async fn send_command(&self, command: &str) -> Result<String> {
+        let response: RemoteCommandResponse = self
+            .client
+            .post(self.endpoint("/api/command"))
+            .json(&RemoteCommandRequest { command })
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(remote_error)?
+            .json()
+            .await
+            .map_err(remote_error)?;
+
+        if response.success {
+            Ok(response.response.unwrap_or_default())
+        } else {
+            Err(BridgeError::CommandFailed(response.message))
+        }
+    }
+
+    async fn fetch_status(&self) -> Result<Option<DeviceState>> {
+        let response = self
+            .client
+            .get(self.endpoint("/api/status"))
+            .send()
+            .await
+            .map_err(remote_error)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let status = response
+            .error_for_status()
+            .map_err(remote_error)?
+            .json()
+            .await
+            .map_err(remote_error)?;
+        Ok(Some(status))
+    }
+
+    async fn fetch_alpaca<T: serde::de::DeserializeOwned>(&self, property: &str) -> Result<T> {
+        let url = self.endpoint(&format!(
+            "/api/v1/safetymonitor/{}/{}",
+            self.config.device_number, property
+        ));
+        let reply: AlpacaValue<T> = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(remote_error)?
+            .json()
+            .await
+            .map_err(remote_error)?;
+
+        if reply.error_number != 0 {
+            return Err(BridgeError::Remote(format!(
+                "{} returned Alpaca error {}: {}",
+                property, reply.error_number, reply.error_message
+            )));
+        }
+        Ok(reply.value)
+    }
+
+    async fn poll(&self, api: &mut RemoteApi, state: &Arc<RwLock<DeviceState>>) -> Result<()> {
+        if *api == RemoteApi::BridgeStatus {
+            match self.fetch_status().await? {
+                Some(remote) => {
+                    state.write().await.update_from_remote(&remote);
+                    return Ok(());
+                }
+                None => {
+                    info!("{} has no bridge status API, falling back to Alpaca IsSafe", self.config.url);
+                    *api = RemoteApi::Alpaca;
+                }
+            }
+        }
+
+        let is_safe: bool = self.fetch_alpaca("issafe").await?;
+        let name: Option<String> = self.fetch_alpaca("name").await.ok();
+        state.write().await.update_from_remote_is_safe(is_safe, name);
+        Ok(())
+    }
+}
+
+fn remote_error(error: reqwest::Error) -> BridgeError {
+    if error.is_timeout() {
+        BridgeError::Timeout
+    } else {
+        BridgeError::Remote(error.to_string())
+    }
+}
+
+// Poll the remote until cancelled; failures mark the local device disconnected (IsSafe false)
+pub async fn run_remote_sensor(
+    remote: RemoteSensor,
+    device_state: Arc<RwLock<DeviceState>>,
+    cancel_token: CancellationToken,
+) {
+    // The bridge status API only describes a bridge's primary device
+    let mut api = if remote.config.device_number == 0 {
+        RemoteApi::BridgeStatus
+    } else {
+        RemoteApi::Alpaca
+    };
+    let mut interval = tokio::time::interval(Duration::from_secs(remote.config.poll_interval_secs));
+    let mut failing = false;
+    info!("Mirroring remote sensor {} (device {})", remote.config.url, remote.config.device_number);
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Remote sensor {} polling cancelled", remote.config.url);
+                return;
+            }
+            _ = interval.tick() => {}
+        }
+
+        match remote.poll(&mut api, &device_state).await {
+            Ok(()) => {
+                if failing {
+                    info!("Remote sensor {} reachable again", remote.config.url);
+                    failing = false;
+                }
+            }
+            Err(e) => {
+                if !failing {
+                    warn!("Remote sensor {} poll failed: {}", remote.config.url, e);
+                    failing = true;
+                }
+                device_state.write().await.set_error(&format!("Remote sensor unreachable: {}", e));
+            }
+        }
+    }
+}
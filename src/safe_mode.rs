@@ -0,0 +1,481 @@
+// src/safe_mode.rs
+// Served instead of the bridge when the --config file or the secrets file does not load, so a
+// truncated write cannot take a remote observatory offline: the web UI and /api/safe_mode show
+// the error and take a corrected file or an earlier copy, after which the bridge starts normally
+
+use crate::backups;
+use crate::config::BridgeConfig;
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use toml_edit::{Document, Item, TableLike, Value};
+use tracing::{info, warn};
+
+// Copy of the config file the bridge last started with, kept next to it
+const LAST_GOOD_SUFFIX: &str = ".last-good";
+// The file a repair replaced, kept to see what went wrong
+const BROKEN_SUFFIX: &str = ".broken";
+// Restore point name of the .last-good copy; the others are backup file names
+const LAST_GOOD: &str = "last-good";
+// Shown instead of tokens and password hashes; saved back as the value it stands for
+const REDACTED: &str = "<redacted>";
+
+// GET /api/safe_mode
+#[derive(Debug, Clone, Serialize)]
+pub struct SafeModeStatus {
+    pub safe_mode: bool,
+    // Why the bridge did not start
+    pub error: String,
+    // null without --config
+    pub config_file: Option<String>,
+    // Newest first
+    pub restore_points: Vec<RestorePoint>,
+}
+
+// An earlier copy of the config file
+#[derive(Debug, Clone, Serialize)]
+pub struct RestorePoint {
+    // "last-good" or a backup file name
+    pub name: String,
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub saved_at: u64,
+    // Why the copy would not load either; null when it can be restored
+    pub problem: Option<String>,
+}
+
+// GET and PUT /api/safe_mode/config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigText {
+    pub config: String,
+}
+
+// POST /api/safe_mode/restore
+#[derive(Debug, Clone, Deserialize)]
+pub struct RestoreRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SafeModeResponse {
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Clone)]
+pub struct SafeMode {
+    config_path: Option<PathBuf>,
+    error: String,
+    // Cancelled once a repair is saved, or on /api/safe_mode/retry: the bridge then starts again
+    repaired: CancellationToken,
+    // One repair at a time
+    lock: Arc<Mutex<()>>,
+}
+
+impl SafeMode {
+    pub fn new(config_path: Option<PathBuf>, error: impl Into<String>) -> Self {
+        Self {
+            config_path,
+            error: error.into(),
+            repaired: CancellationToken::new(),
+            lock: Arc::default(),
+        }
+    }
+
+    // The JSON API, and the repair page at / unless headless (cargo feature web-ui). Every other
+    // path answers 503, so Alpaca clients and monitors see the bridge is not running
+    pub fn router(self, headless: bool) -> Router {
+        let routes = Router::new()
+            .route("/api/safe_mode", get(api_status))
+            .route("/api/safe_mode/config", get(api_get_config).put(api_put_config))
+            .route("/api/safe_mode/restore", post(api_restore))
+            .route("/api/safe_mode/retry", post(api_retry));
+        #[cfg(feature = "web-ui")]
+        let routes = match headless {
+            true => routes,
+            false => routes
+                .route("/", get(web_page))
+                .route("/assets/:name", get(web_asset)),
+        };
+        #[cfg(not(feature = "web-ui"))]
+        let _ = headless;
+        routes.fallback(not_running).with_state(self)
+    }
+
+    // Serve until a repair is saved or a retry is requested
+    pub async fn serve(self, listener: tokio::net::TcpListener, headless: bool) -> std::io::Result<()> {
+        let repaired = self.repaired.clone();
+        warn!("Safe mode: {}", self.error);
+        if let Ok(address) = listener.local_addr() {
+            warn!("Safe mode: repair the configuration at http://{}/ (or /api/safe_mode)", address);
+        }
+        axum::serve(listener, self.router(headless))
+            .with_graceful_shutdown(async move { repaired.cancelled().await })
+            .await
+    }
+
+    pub async fn status(&self) -> SafeModeStatus {
+        SafeModeStatus {
+            safe_mode: true,
+            error: self.error.clone(),
+            config_file: self.config_path.as_ref().map(|path| path.display().to_string()),
+            restore_points: self.restore_points().await.into_iter().map(|(point, _)| point).collect(),
+        }
+    }
+
+    // The config file as it is now, with secret values redacted; empty when it cannot be read
+    pub async fn config_text(&self) -> String {
+        redact(&self.raw_config_text().await)
+    }
+
+    async fn raw_config_text(&self) -> String {
+        match &self.config_path {
+            Some(path) => tokio::fs::read_to_string(path).await.unwrap_or_default(),
+            None => String::new(),
+        }
+    }
+
+    // The .last-good copy and the scheduled backups holding a config file, newest first
+    async fn restore_points(&self) -> Vec<(RestorePoint, String)> {
+        let Some(path) = &self.config_path else {
+            return Vec::new();
+        };
+        let mut points = Vec::new();
+        let last_good_path = with_suffix(path, LAST_GOOD_SUFFIX);
+        let last_good = tokio::fs::read_to_string(&last_good_path).await.ok();
+        if let Some(text) = &last_good {
+            let saved_at = match tokio::fs::metadata(&last_good_path).await.and_then(|metadata| metadata.modified()) {
+                Ok(modified) => modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+                Err(_) => 0,
+            };
+            points.push(restore_point(LAST_GOOD, saved_at, text.clone()));
+        }
+
+        // The [backup] directory from whichever copy still parses
+        let directory = [Some(self.raw_config_text().await), last_good]
+            .into_iter()
+            .flatten()
+            .find_map(|text| BridgeConfig::parse(&text).ok()?.backup.directory);
+        let Some(directory) = directory else {
+            return points;
+        };
+        let directory = Path::new(&directory);
+        let names = backups::list_backups(directory).await.unwrap_or_else(|e| {
+            warn!("Safe mode: cannot list the backups in {}: {}", directory.display(), e);
+            Vec::new()
+        });
+        for name in names.into_iter().rev() {
+            match backups::read_backup(&directory.join(&name)).await {
+                Ok(backups::StoredBackup { created_at, config: Some(text) }) => {
+                    points.push(restore_point(&name, created_at, text));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Safe mode: cannot read the backup {}: {}", name, e),
+            }
+        }
+        points
+    }
+
+    // Replace the config file with `text` if it loads, keeping the old file as .broken
+    pub async fn replace_config(&self, text: &str) -> Result<String, (StatusCode, String)> {
+        let Some(path) = &self.config_path else {
+            return Err((StatusCode::CONFLICT, "the bridge was started without --config".to_string()));
+        };
+        check_config(text).map_err(|problem| (StatusCode::BAD_REQUEST, problem))?;
+        let _guard = self.lock.lock().await;
+        let io_error = |path: &Path, e: std::io::Error| (StatusCode::INTERNAL_SERVER_ERROR, format!("{}: {}", path.display(), e));
+        if tokio::fs::try_exists(path).await.unwrap_or(false) {
+            let broken = with_suffix(path, BROKEN_SUFFIX);
+            tokio::fs::copy(path, &broken).await.map_err(|e| io_error(&broken, e))?;
+        }
+        // Written next to the file and renamed over it, like the setup pages do
+        let temporary = with_suffix(path, ".tmp");
+        tokio::fs::write(&temporary, text).await.map_err(|e| io_error(&temporary, e))?;
+        tokio::fs::rename(&temporary, path).await.map_err(|e| io_error(path, e))?;
+        info!("Safe mode: {} replaced, starting the bridge", path.display());
+        self.repaired.cancel();
+        Ok(format!("Saved {}; the bridge is starting", path.display()))
+    }
+
+    // Start over without changes, e.g. after the secrets file was fixed by hand
+    pub fn retry(&self) {
+        info!("Safe mode: trying to start the bridge again");
+        self.repaired.cancel();
+    }
+}
+
+// Keep a copy of a config file that loaded, for safe mode to restore; unchanged copies are not
+// rewritten, to spare SD cards
+pub fn keep_last_good(config_path: &Path) {
+    let Ok(text) = std::fs::read(config_path) else {
+        return;
+    };
+    let last_good = with_suffix(config_path, LAST_GOOD_SUFFIX);
+    if std::fs::read(&last_good).is_ok_and(|copy| copy == text) {
+        return;
+    }
+    if let Err(e) = std::fs::write(&last_good, text) {
+        warn!("Cannot keep a copy of the configuration in {}: {}", last_good.display(), e);
+    }
+}
+
+// Whether the text would load at startup, with the same checks
+fn check_config(text: &str) -> Result<(), String> {
+    let config = BridgeConfig::parse(text)?;
+    let issues = config.issues();
+    if !issues.is_empty() {
+        return Err(issues.to_string());
+    }
+    Ok(())
+}
+
+fn restore_point(name: &str, saved_at: u64, text: String) -> (RestorePoint, String) {
+    let point = RestorePoint {
+        name: name.to_string(),
+        saved_at,
+        problem: check_config(&text).err(),
+    };
+    (point, text)
+}
+
+// Whether a key holds a token or password hash, such as safety_force.token or
+// web_auth.users.password_hash
+fn is_secret_key(key: &str) -> bool {
+    key == "token" || key.ends_with("_token") || key.contains("password") || key.contains("secret")
+}
+
+// The config text with the values of secret keys replaced by REDACTED: by key path when the
+// text parses, which covers inline tables and arrays, and line by line when it does not
+fn redact(text: &str) -> String {
+    let Ok(mut document) = text.parse::<Document>() else {
+        return redact_lines(text);
+    };
+    visit_secrets(document.as_table_mut(), "", &mut |_, value| {
+        let decor = value.decor().clone();
+        *value = Value::from(REDACTED);
+        *value.decor_mut() = decor;
+    });
+    document.to_string()
+}
+
+// An edit of the redacted text with the REDACTED values put back from the file it was made from;
+// values at places the file does not have stay REDACTED
+fn unredact(edited: &str, original: &str) -> String {
+    let (Ok(mut original), Ok(mut document)) = (original.parse::<Document>(), edited.parse::<Document>()) else {
+        return unredact_lines(edited, original);
+    };
+    let mut secrets = HashMap::new();
+    visit_secrets(original.as_table_mut(), "", &mut |path, value| {
+        secrets.insert(path.to_string(), value.clone());
+    });
+    visit_secrets(document.as_table_mut(), "", &mut |path, value| {
+        if let Some(secret) = secrets.get(path).filter(|_| value.as_str() == Some(REDACTED)) {
+            let decor = value.decor().clone();
+            *value = secret.clone();
+            *value.decor_mut() = decor;
+        }
+    });
+    document.to_string()
+}
+
+// Call `visit` with the path and value of every secret key below `table`, array entries numbered
+fn visit_secrets(table: &mut dyn TableLike, path: &str, visit: &mut dyn FnMut(&str, &mut Value)) {
+    for (key, item) in table.iter_mut() {
+        let path = format!("{}.{}", path, key.get());
+        match item {
+            Item::Value(value) if is_secret_key(key.get()) => visit(&path, value),
+            Item::Value(value) => visit_value_secrets(value, &path, visit),
+            Item::Table(table) => visit_secrets(table, &path, visit),
+            Item::ArrayOfTables(tables) => {
+                for (index, table) in tables.iter_mut().enumerate() {
+                    visit_secrets(table, &format!("{}[{}]", path, index), visit);
+                }
+            }
+            Item::None => {}
+        }
+    }
+}
+
+fn visit_value_secrets(value: &mut Value, path: &str, visit: &mut dyn FnMut(&str, &mut Value)) {
+    match value {
+        Value::InlineTable(table) => visit_secrets(table, path, visit),
+        Value::Array(array) => {
+            for (index, value) in array.iter_mut().enumerate() {
+                visit_value_secrets(value, &format!("{}[{}]", path, index), visit);
+            }
+        }
+        _ => {}
+    }
+}
+
+// Where a `key = value` entry sits in a file that may not parse: the table header above it, the
+// key, and how often that pair came before (for [[arrays]] of tables such as web_auth.users)
+type Place = (String, String, usize);
+
+// A file that does not parse, as `key = value` entries and other lines. An entry's value is the
+// text after its first '=', running on over the next lines while a multi-line string is open
+fn entries(text: &str) -> Vec<(Option<Place>, &str, String)> {
+    let mut table = String::new();
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    let mut entries: Vec<(Option<Place>, &str, String)> = Vec::new();
+    let mut open_string: Option<&str> = None;
+    for line in text.split_inclusive('\n') {
+        if let Some(delimiter) = open_string {
+            if line.matches(delimiter).count() % 2 == 1 {
+                open_string = None;
+            }
+            if let Some((_, _, value)) = entries.last_mut() {
+                value.push_str(line);
+            }
+            continue;
+        }
+        let trimmed = line.trim();
+        let assignment = line.find('=').filter(|_| !trimmed.starts_with('#') && !trimmed.starts_with('['));
+        let Some(equals) = assignment else {
+            if trimmed.starts_with('[') {
+                table = trimmed.to_string();
+            }
+            entries.push((None, line, String::new()));
+            continue;
+        };
+        let (head, value) = line.split_at(equals + 1);
+        open_string = ["\"\"\"", "'''"].into_iter().find(|delimiter| value.matches(delimiter).count() % 2 == 1);
+        let key = head[..equals].trim().trim_matches('"').to_string();
+        let count = seen.entry((table.clone(), key.clone())).or_default();
+        *count += 1;
+        entries.push((Some((table.clone(), key, *count)), head, value.to_string()));
+    }
+    entries
+}
+
+// Whether a secret key is assigned anywhere in the text, e.g. inside an inline table
+fn mentions_secret_key(text: &str) -> bool {
+    let mut rest = text;
+    while let Some(equals) = rest.find('=') {
+        let before = rest[..equals].trim_end();
+        let start = before
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '"')))
+            .map_or(0, |index| index + 1);
+        if is_secret_key(before[start..].trim_matches('"')) {
+            return true;
+        }
+        rest = &rest[equals + 1..];
+    }
+    false
+}
+
+// The whole value of each entry with a secret key in it, including a multi-line string, is
+// replaced, so a broken file shows no secret however it is written
+fn redact_lines(text: &str) -> String {
+    entries(text)
+        .into_iter()
+        .map(|(place, head, value)| match place {
+            Some((_, key, _)) if is_secret_key(&key) || mentions_secret_key(&value) => {
+                let ending = &value[value.trim_end().len()..];
+                format!("{} \"{}\"{}", head, REDACTED, ending)
+            }
+            _ => format!("{}{}", head, value),
+        })
+        .collect()
+}
+
+fn unredact_lines(edited: &str, original: &str) -> String {
+    let secrets: HashMap<Place, String> = entries(original)
+        .into_iter()
+        .filter_map(|(place, _, value)| Some((place?, value.trim_end().to_string())))
+        .collect();
+    entries(edited)
+        .into_iter()
+        .map(|(place, head, value)| {
+            let redacted = value.trim() == format!("\"{}\"", REDACTED);
+            match place.and_then(|place| secrets.get(&place)).filter(|_| redacted) {
+                Some(secret) => format!("{}{}{}", head, secret, &value[value.trim_end().len()..]),
+                None => format!("{}{}", head, value),
+            }
+        })
+        .collect()
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn answer(result: Result<String, (StatusCode, String)>) -> (StatusCode, Json<SafeModeResponse>) {
+    match result {
+        Ok(message) => (StatusCode::OK, Json(SafeModeResponse { success: true, message })),
+        Err((status, message)) => (status, Json(SafeModeResponse { success: false, message })),
+    }
+}
+
+async fn api_status(State(safe_mode): State<SafeMode>) -> Json<SafeModeStatus> {
+    Json(safe_mode.status().await)
+}
+
+async fn api_get_config(State(safe_mode): State<SafeMode>) -> Json<ConfigText> {
+    Json(ConfigText {
+        config: safe_mode.config_text().await,
+    })
+}
+
+async fn api_put_config(State(safe_mode): State<SafeMode>, Json(request): Json<ConfigText>) -> (StatusCode, Json<SafeModeResponse>) {
+    // The edit was made on the redacted text
+    let config = unredact(&request.config, &safe_mode.raw_config_text().await);
+    answer(safe_mode.replace_config(&config).await)
+}
+
+async fn api_restore(State(safe_mode): State<SafeMode>, Json(request): Json<RestoreRequest>) -> (StatusCode, Json<SafeModeResponse>) {
+    let points = safe_mode.restore_points().await;
+    let result = match points.into_iter().find(|(point, _)| point.name == request.name) {
+        None => Err((StatusCode::NOT_FOUND, format!("no restore point named '{}'", request.name))),
+        Some((RestorePoint { problem: Some(problem), .. }, _)) => Err((StatusCode::BAD_REQUEST, problem)),
+        Some((_, text)) => safe_mode.replace_config(&text).await,
+    };
+    answer(result)
+}
+
+async fn api_retry(State(safe_mode): State<SafeMode>) -> Json<SafeModeResponse> {
+    safe_mode.retry();
+    Json(SafeModeResponse {
+        success: true,
+        message: "The bridge is starting".to_string(),
+    })
+}
+
+async fn not_running(State(safe_mode): State<SafeMode>) -> (StatusCode, Json<SafeModeResponse>) {
+    let message = format!("The bridge is in safe mode, see /api/safe_mode: {}", safe_mode.error);
+    (StatusCode::SERVICE_UNAVAILABLE, Json(SafeModeResponse { success: false, message }))
+}
+
+#[cfg(feature = "web-ui")]
+async fn web_page(State(safe_mode): State<SafeMode>) -> axum::response::Response {
+    use axum::response::{Html, IntoResponse};
+    let page = crate::web_assets::SafeModePage {
+        config: safe_mode.config_text().await,
+        status: Some(safe_mode.status().await),
+        ..Default::default()
+    };
+    match crate::web_assets::WebAssets::embedded().render_safe_mode(page).await {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Template error: {:#}", e)).into_response(),
+    }
+}
+
+#[cfg(feature = "web-ui")]
+async fn web_asset(axum::extract::Path(name): axum::extract::Path<String>) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    match crate::web_assets::WebAssets::embedded().serve(&name).await {
+        Some(response) => response,
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
@@ -0,0 +1,88 @@
+// src/safety_schedule.rs
+// Recurring "never safe" windows ([[safety_schedules]]), e.g. 10:00-16:00 while the roof is
+// serviced: while one is in force for a device its IsSafe is false whatever the sensor reports,
+// and devicestate names the window
+
+use crate::config::SafetyScheduleConfig;
+use crate::device_registry::DeviceRegistry;
+use crate::events::EventKind;
+use crate::timestamps;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+// Windows are set to the minute
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+// Whether the window covers `minute` of `weekday` (0 = Monday); a window running past midnight
+// belongs to the day it started on
+pub fn is_in_window(schedule: &SafetyScheduleConfig, weekday: u32, minute: u32) -> bool {
+    let starts_on = |day: u32| schedule.days.is_empty() || schedule.days.iter().any(|listed| listed.index() == day);
+    let (start, end) = (schedule.start.0, schedule.end.0);
+    if start < end {
+        starts_on(weekday) && (start..end).contains(&minute)
+    } else {
+        (starts_on(weekday) && minute >= start) || (starts_on((weekday + 6) % 7) && minute < end)
+    }
+}
+
+pub struct SafetySchedules {
+    schedules: Vec<SafetyScheduleConfig>,
+}
+
+impl SafetySchedules {
+    pub fn new(schedules: Vec<SafetyScheduleConfig>) -> Self {
+        Self { schedules }
+    }
+
+    // Window in force for the device at `now`, the first listed when several overlap
+    pub fn active(&self, device_number: u32, now: u64) -> Option<&SafetyScheduleConfig> {
+        let (weekday, minute) = timestamps::display_weekday_minute(now);
+        self.schedules.iter().find(|schedule| {
+            (schedule.devices.is_empty() || schedule.devices.contains(&device_number))
+                && is_in_window(schedule, weekday, minute)
+        })
+    }
+
+    // Hold IsSafe false on the devices with a window in force at `now`, publishing each window
+    // beginning and ending on the device's event bus
+    pub async fn update(&self, devices: &DeviceRegistry, now: u64) {
+        for device in devices.devices() {
+            let active = self.active(device.device_number, now).map(|schedule| schedule.name.clone());
+            let previous = {
+                let mut state = device.device_state.write().await;
+                if state.safety_schedule == active {
+                    continue;
+                }
+                std::mem::replace(&mut state.safety_schedule, active.clone())
+            };
+            let events = device.connection_manager.event_bus();
+            for (schedule, began) in previous.map(|name| (name, false)).into_iter().chain(active.map(|name| (name, true))) {
+                let change = if began { "began; IsSafe is false until it ends" } else { "ended" };
+                info!("Safety schedule {} {} on device {}", schedule, change, device.device_number);
+                events.publish(EventKind::SafetyScheduleChanged {
+                    device_number: device.device_number,
+                    schedule,
+                    active: began,
+                });
+            }
+        }
+    }
+}
+
+// Apply the schedules until cancelled
+pub async fn run_safety_schedules(schedules: Vec<SafetyScheduleConfig>, devices: DeviceRegistry, cancel_token: CancellationToken) {
+    let schedules = SafetySchedules::new(schedules);
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        schedules.update(&devices, unix_now()).await;
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
@@ -0,0 +1,209 @@
+// src/secrets.rs
+// API tokens and other credentials kept out of the config file: the OS keyring (Windows Credential
+// Manager, macOS Keychain, Linux kernel keyring) first, a private TOML file as the fallback
+
+use crate::errors::{BridgeError, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+// Keyring service the entries are filed under, one entry per secret name
+const KEYRING_SERVICE: &str = "telescope_park_bridge";
+
+// Default file name, next to the config file
+pub const DEFAULT_SECRETS_FILE: &str = "bridge.secrets.toml";
+
+// Secrets the bridge reads, named after the config field they fill in when it is left out
+pub const SECRET_NAMES: &[&str] = &["safety_force.token", "chat_bot.token", "events.token"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretLocation {
+    Keyring,
+    File,
+}
+
+impl std::fmt::Display for SecretLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Keyring => f.write_str("OS keyring"),
+            Self::File => f.write_str("secrets file"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SecretStore {
+    file: PathBuf,
+    use_keyring: bool,
+}
+
+impl SecretStore {
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            use_keyring: true,
+        }
+    }
+
+    // Only the file, e.g. on hosts without a usable keyring and in tests
+    pub fn file_only(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            use_keyring: false,
+        }
+    }
+
+    // bridge.secrets.toml next to the config file, or in the working directory without one
+    pub fn for_config(config_path: Option<&Path>) -> Self {
+        let dir = config_path.and_then(Path::parent).unwrap_or(Path::new(""));
+        Self::new(dir.join(DEFAULT_SECRETS_FILE))
+    }
+
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    // The keyring wins over the file; an unusable keyring counts as empty
+    pub fn get(&self, name: &str) -> Result<Option<(String, SecretLocation)>> {
+        check_name(name)?;
+        if let Some(value) = self.keyring_get(name) {
+            return Ok(Some((value, SecretLocation::Keyring)));
+        }
+        Ok(self.read_file()?.remove(name).map(|value| (value, SecretLocation::File)))
+    }
+
+    // Store in the keyring unless `location` is File; falls back to the file when the keyring
+    // refuses the entry. Returns where the secret ended up.
+    pub fn set(&self, name: &str, value: &str, location: SecretLocation) -> Result<SecretLocation> {
+        check_name(name)?;
+        if value.trim().is_empty() {
+            return Err(BridgeError::Config(format!("secret {} must not be empty", name)));
+        }
+        if location == SecretLocation::Keyring && self.use_keyring {
+            match keyring_entry(name).and_then(|entry| entry.set_password(value)) {
+                Ok(()) => {
+                    // Drop a stale copy so the file cannot disagree with the keyring later
+                    self.remove_from_file(name)?;
+                    return Ok(SecretLocation::Keyring);
+                }
+                Err(e) => warn!("OS keyring unavailable ({}), storing {} in {}", e, name, self.file.display()),
+            }
+        }
+        let mut secrets = self.read_file()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.write_file(&secrets)?;
+        Ok(SecretLocation::File)
+    }
+
+    // Remove the secret everywhere; returns the locations it was found in
+    pub fn delete(&self, name: &str) -> Result<Vec<SecretLocation>> {
+        check_name(name)?;
+        let mut removed = Vec::new();
+        if self.use_keyring && keyring_entry(name).and_then(|entry| entry.delete_credential()).is_ok() {
+            removed.push(SecretLocation::Keyring);
+        }
+        if self.remove_from_file(name)? {
+            removed.push(SecretLocation::File);
+        }
+        Ok(removed)
+    }
+
+    // Where each known secret is set; values are never returned
+    pub fn list(&self) -> Result<Vec<(&'static str, Option<SecretLocation>)>> {
+        SECRET_NAMES
+            .iter()
+            .map(|name| Ok((*name, self.get(name)?.map(|(_, location)| location))))
+            .collect()
+    }
+
+    fn keyring_get(&self, name: &str) -> Option<String> {
+        if !self.use_keyring {
+            return None;
+        }
+        match keyring_entry(name).and_then(|entry| entry.get_password()) {
+            Ok(value) => Some(value),
+            Err(keyring::Error::NoEntry) => None,
+            Err(e) => {
+                debug!("OS keyring lookup of {} failed: {}", name, e);
+                None
+            }
+        }
+    }
+
+    fn read_file(&self) -> Result<BTreeMap<String, String>> {
+        let text = match std::fs::read_to_string(&self.file) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(BridgeError::Config(format!("{}: {}", self.file.display(), e))),
+        };
+        warn_if_readable_by_others(&self.file);
+        toml::from_str(&text).map_err(|e| BridgeError::Config(format!("{}: {}", self.file.display(), e)))
+    }
+
+    fn write_file(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let text = toml::to_string(secrets).map_err(|e| BridgeError::Config(e.to_string()))?;
+        write_private(&self.file, text.as_bytes())
+            .map_err(|e| BridgeError::Config(format!("{}: {}", self.file.display(), e)))
+    }
+
+    fn remove_from_file(&self, name: &str) -> Result<bool> {
+        let mut secrets = self.read_file()?;
+        if secrets.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.write_file(&secrets)?;
+        Ok(true)
+    }
+}
+
+fn check_name(name: &str) -> Result<()> {
+    if SECRET_NAMES.contains(&name) {
+        Ok(())
+    } else {
+        Err(BridgeError::Config(format!(
+            "unknown secret '{}'; known secrets: {}",
+            name,
+            SECRET_NAMES.join(", ")
+        )))
+    }
+}
+
+fn keyring_entry(name: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+}
+
+// Owner read/write only where the platform has Unix permissions
+#[cfg(unix)]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // mode() only applies to new files
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+#[cfg(unix)]
+fn warn_if_readable_by_others(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        if metadata.permissions().mode() & 0o077 != 0 {
+            warn!("{} is readable by other users; chmod 600 it", path.display());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_readable_by_others(_path: &Path) {}
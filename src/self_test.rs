@@ -0,0 +1,255 @@
+// src/self_test.rs
+// Startup self-check run on every serial connection: capability discovery, version, calibration,
+// park definition and a test position read. The device only counts as operational (and can report IsSafe true) once it
+// passes, and the per-step results are served at /api/selftest.
+
+use crate::command_queue::CommandQueue;
+use crate::device_state::{DeviceState, FirmwareResponse};
+use crate::errors::{BridgeError, Result};
+use crate::protocol::{self, CommandTimeouts, FirmwareCapabilities, ProtocolVersion};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelfTestStatus {
+    // No serial connection yet, or a remote or voting device, which has no firmware of its own
+    NotRun,
+    Running,
+    Passed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepOutcome {
+    Passed,
+    // Worth fixing, but the device can still work
+    Warning,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestStep {
+    pub name: &'static str,
+    pub command: &'static str,
+    pub outcome: StepOutcome,
+    pub detail: String,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub status: SelfTestStatus,
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub started_at: u64,
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub finished_at: u64,
+    pub steps: Vec<SelfTestStep>,
+}
+
+impl Default for SelfTestReport {
+    fn default() -> Self {
+        Self {
+            status: SelfTestStatus::NotRun,
+            started_at: 0,
+            finished_at: 0,
+            steps: Vec::new(),
+        }
+    }
+}
+
+// Judges a step's data payload
+type StepCheck = fn(&Value) -> (StepOutcome, String);
+
+// Steps in the order they run: name, firmware command and check
+const STEPS: &[(&str, &str, StepCheck)] = &[
+    ("version", protocol::GET_VERSION, check_version),
+    ("calibration", protocol::GET_STATUS, check_calibration),
+    ("park_definition", protocol::GET_PARK, check_park_definition),
+    ("position", protocol::GET_POSITION, check_position),
+];
+
+// Latest report of one device, shared between its serial connections and the API
+#[derive(Clone, Default)]
+pub struct SelfTest {
+    report: Arc<Mutex<SelfTestReport>>,
+    command_timeouts: CommandTimeouts,
+}
+
+impl SelfTest {
+    pub fn new(command_timeouts: CommandTimeouts) -> Self {
+        Self {
+            report: Arc::default(),
+            command_timeouts,
+        }
+    }
+
+    pub fn report(&self) -> SelfTestReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    // Run every step through the command queue, then mark the device operational if none failed
+    pub async fn run(self, command_queue: CommandQueue, device_state: Arc<RwLock<DeviceState>>) {
+        *self.report.lock().unwrap() = SelfTestReport {
+            status: SelfTestStatus::Running,
+            started_at: unix_now(),
+            ..SelfTestReport::default()
+        };
+        let started = Instant::now();
+        let (outcome, detail) = self.discover_capabilities(&command_queue, &device_state).await;
+        self.record("capabilities", protocol::HELP, started, outcome, detail);
+        for (name, command, check) in STEPS {
+            let started = Instant::now();
+            let (outcome, detail) = match self.query(&command_queue, command).await {
+                Ok(data) => check(&data),
+                Err(e) => (StepOutcome::Failed, e.to_string()),
+            };
+            self.record(name, command, started, outcome, detail);
+        }
+
+        let passed = {
+            let mut report = self.report.lock().unwrap();
+            let passed = report.steps.iter().all(|step| step.outcome != StepOutcome::Failed);
+            report.status = if passed { SelfTestStatus::Passed } else { SelfTestStatus::Failed };
+            report.finished_at = unix_now();
+            passed
+        };
+        device_state.write().await.operational = passed;
+        match passed {
+            true => info!("Self-check passed, device operational"),
+            false => warn!("Self-check failed; IsSafe stays false until the device passes it (see /api/selftest)"),
+        }
+    }
+
+    fn record(&self, name: &'static str, command: &'static str, started: Instant, outcome: StepOutcome, detail: String) {
+        match outcome {
+            StepOutcome::Passed => info!("Self-check {}: {}", name, detail),
+            _ => warn!("Self-check {} {:?}: {}", name, outcome, detail),
+        }
+        self.report.lock().unwrap().steps.push(SelfTestStep {
+            name,
+            command,
+            outcome,
+            detail,
+            duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+        });
+    }
+
+    // Parse the help text into the device's capabilities; firmware without a usable one keeps
+    // every command available, so this step never fails the check
+    async fn discover_capabilities(
+        &self,
+        command_queue: &CommandQueue,
+        device_state: &RwLock<DeviceState>,
+    ) -> (StepOutcome, String) {
+        let data = match self.query(command_queue, protocol::HELP).await {
+            Ok(data) => data,
+            Err(e) => return (StepOutcome::Warning, format!("no help text ({}), assuming every command", e)),
+        };
+        let Some(capabilities) = FirmwareCapabilities::from_help(&data) else {
+            return (StepOutcome::Warning, "help text lists no commands, assuming every command".to_string());
+        };
+        let extras: Vec<&str> = [
+            ("identify", capabilities.identify),
+            ("battery", capabilities.battery),
+            ("temperature", capabilities.temperature),
+        ]
+        .into_iter()
+        .filter_map(|(name, supported)| supported.then_some(name))
+        .collect();
+        let mut detail = format!("commands {}", capabilities.commands.join(" "));
+        if !extras.is_empty() {
+            detail.push_str(&format!("; {}", extras.join(", ")));
+        }
+        device_state.write().await.capabilities = capabilities;
+        (StepOutcome::Passed, detail)
+    }
+
+    // Data payload of a command's response
+    async fn query(&self, command_queue: &CommandQueue, command: &str) -> Result<Value> {
+        let command_timeout = self.command_timeouts.timeout_for(command);
+        let receiver = command_queue.push_internal(command, command_timeout);
+        let response = tokio::time::timeout(command_timeout + protocol::QUEUE_GRACE, receiver)
+            .await
+            .map_err(|_| BridgeError::Timeout)?
+            .map_err(|_| BridgeError::Device("Connection closed".to_string()))??;
+        let parsed: FirmwareResponse = serde_json::from_str(&response)?;
+        parsed
+            .data
+            .ok_or_else(|| BridgeError::InvalidResponse(format!("no data in {}", response)))
+    }
+}
+
+// Fixed-width listing of a report's steps, for the selftest subcommand
+pub fn format_report(report: &SelfTestReport) -> String {
+    let mut text = format!("{:<16} {:<4} {:<8} {:>8}  {}\n", "step", "cmd", "outcome", "ms", "detail");
+    for step in &report.steps {
+        let outcome = match step.outcome {
+            StepOutcome::Passed => "passed",
+            StepOutcome::Warning => "warning",
+            StepOutcome::Failed => "FAILED",
+        };
+        text.push_str(&format!(
+            "{:<16} {:<4} {:<8} {:>8.1}  {}\n",
+            step.name, step.command, outcome, step.duration_ms, step.detail
+        ));
+    }
+    let status = match report.status {
+        SelfTestStatus::NotRun => "not run",
+        SelfTestStatus::Running => "still running",
+        SelfTestStatus::Passed => "passed",
+        SelfTestStatus::Failed => "failed",
+    };
+    text.push_str(&format!("\nSelf-check {}\n", status));
+    text
+}
+
+fn check_version(data: &Value) -> (StepOutcome, String) {
+    let protocol = ProtocolVersion::negotiate(data.get("protocolVersion"));
+    match data.get("firmwareVersion").or_else(|| data.get("version")).and_then(Value::as_str) {
+        Some(version) if !version.trim().is_empty() => {
+            (StepOutcome::Passed, format!("firmware {}, protocol v{}", version, protocol.number()))
+        }
+        _ => (StepOutcome::Failed, "the firmware did not report its version".to_string()),
+    }
+}
+
+fn check_calibration(data: &Value) -> (StepOutcome, String) {
+    match data.get("calibrated").and_then(Value::as_bool) {
+        Some(true) => (StepOutcome::Passed, "calibrated".to_string()),
+        Some(false) => (StepOutcome::Warning, "not calibrated; park detection may be off".to_string()),
+        None => (StepOutcome::Failed, "the status has no calibration flag".to_string()),
+    }
+}
+
+fn check_park_definition(data: &Value) -> (StepOutcome, String) {
+    let angle = |name: &str| data.get(name).and_then(Value::as_f64).filter(|angle| angle.is_finite());
+    match (angle("parkPitch"), angle("parkRoll")) {
+        // A fresh or factory-reset board reports 0/0 until a park position is set
+        (Some(pitch), Some(roll)) if pitch == 0.0 && roll == 0.0 => {
+            (StepOutcome::Warning, "park position is 0°/0°, probably never set".to_string())
+        }
+        (Some(pitch), Some(roll)) => (StepOutcome::Passed, format!("park at pitch {:.2}°, roll {:.2}°", pitch, roll)),
+        _ => (StepOutcome::Failed, "the firmware did not report a park position".to_string()),
+    }
+}
+
+fn check_position(data: &Value) -> (StepOutcome, String) {
+    let angle = |name: &str| data.get(name).and_then(Value::as_f64);
+    match (angle("pitch"), angle("roll")) {
+        (Some(pitch), Some(roll)) if pitch.abs() <= 180.0 && roll.abs() <= 180.0 => {
+            (StepOutcome::Passed, format!("pitch {:.2}°, roll {:.2}°", pitch, roll))
+        }
+        (Some(pitch), Some(roll)) => (StepOutcome::Failed, format!("implausible reading pitch {}, roll {}", pitch, roll)),
+        _ => (StepOutcome::Failed, "the firmware did not report a position".to_string()),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
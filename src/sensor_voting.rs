@@ -0,0 +1,105 @@
+// src/sensor_voting.rs
+// Redundant sensor voting: a virtual device whose IsSafe is voted by several sensors on one mount,
+// so a single IMU failing optimistically cannot report an unparked mount as safe
+
+use crate::config::VotingPolicy;
+use crate::device_state::DeviceState;
+use crate::events::{EventBus, EventKind, SensorVote};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+// Member states are cheap to read, so the vote is recounted often
+const VOTE_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Clone)]
+pub struct VotingMember {
+    pub device_number: u32,
+    pub device_state: Arc<RwLock<DeviceState>>,
+    pub max_data_age_secs: u64,
+}
+
+pub fn tally(policy: VotingPolicy, votes: &[SensorVote]) -> bool {
+    let safe = votes.iter().filter(|vote| vote.available && vote.is_safe).count();
+    match policy {
+        VotingPolicy::All => !votes.is_empty() && safe == votes.len(),
+        VotingPolicy::Majority => safe * 2 > votes.len(),
+    }
+}
+
+// Recount the vote until cancelled, mirroring the result into the voting device's state
+pub async fn run_voting(
+    device_number: u32,
+    members: Vec<VotingMember>,
+    policy: VotingPolicy,
+    device_state: Arc<RwLock<DeviceState>>,
+    events: EventBus,
+    cancel_token: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(VOTE_INTERVAL);
+    let mut disagreeing = false;
+    info!(
+        "Device {} voting over sensors {:?} ({:?} policy)",
+        device_number,
+        members.iter().map(|member| member.device_number).collect::<Vec<_>>(),
+        policy
+    );
+
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => {
+                debug!("Device {} voting cancelled", device_number);
+                return;
+            }
+            _ = interval.tick() => {}
+        }
+
+        let mut votes = Vec::with_capacity(members.len());
+        let mut reference: Option<DeviceState> = None;
+        for member in &members {
+            let state = member.device_state.read().await;
+            let available = state.connected && !state.is_stale(member.max_data_age_secs);
+            votes.push(SensorVote {
+                device_number: member.device_number,
+                available,
+                is_safe: state.is_safe_now(member.max_data_age_secs),
+            });
+            if available && reference.is_none() {
+                reference = Some(state.clone());
+            }
+        }
+
+        let is_safe = tally(policy, &votes);
+        let safe_count = votes.iter().filter(|vote| vote.is_safe).count();
+        let unanimous = safe_count == 0 || safe_count == votes.len();
+
+        if !unanimous && !disagreeing {
+            warn!(
+                "Device {}: sensors disagree - {} of {} report safe, voting {}",
+                device_number,
+                safe_count,
+                votes.len(),
+                if is_safe { "safe" } else { "unsafe" }
+            );
+            events.publish(EventKind::SensorDisagreement {
+                device_number,
+                is_safe,
+                votes: votes.clone(),
+            });
+        } else if unanimous && disagreeing {
+            info!("Device {}: sensors agree again", device_number);
+        }
+        disagreeing = !unanimous;
+
+        let mut state = device_state.write().await;
+        match reference {
+            Some(reference) => state.update_from_vote(&reference, is_safe),
+            None => state.set_error("No voting sensor available"),
+        }
+        if disagreeing {
+            state.error_message = Some(format!("Sensors disagree: {} of {} report safe", safe_count, votes.len()));
+        }
+    }
+}
@@ -4,23 +4,88 @@
 
 use crate::device_state::{DeviceState, FirmwareResponse, StatusResponse, PositionResponse, ParkStatusResponse};
 use crate::errors::{BridgeError, Result};
-use crate::connection_manager::CommandRequest;
+use crate::calibration;
+use crate::firmware_schema::SchemaChecker;
+use crate::command_history::CommandHistory;
+use crate::command_queue::CommandQueue;
+use crate::config::{FramingConfig, HealthConfig, HeartbeatConfig, SerialConfig};
+use crate::device_transport::{self, DeviceTransport, TCP_PREFIX};
+use crate::events::{EventBus, EventKind};
+use crate::protocol::{self, FirmwareCapabilities, ProtocolVersion, ReplyKind};
+use crate::fault_injection::{FaultAction, FaultInjector, SharedFaultInjector};
+use crate::health::HealthMonitor;
+use crate::temperature_compensation::TemperatureCompensation;
+use crate::self_test::SelfTest;
+use crate::session_recording::{TrafficDirection, TrafficTap};
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::sync::{RwLock, mpsc};
+use tokio::sync::RwLock;
 use tokio::time::{interval, timeout};
-use tokio_serial::SerialPortBuilderExt;
 use tokio_util::sync::CancellationToken;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, warn, Instrument};
+
+// Per-connection plumbing shared with the ConnectionManager
+#[derive(Clone)]
+pub struct SerialClientContext {
+    pub command_queue: CommandQueue,
+    pub fault_injector: SharedFaultInjector,
+    pub traffic: TrafficTap,
+    pub heartbeat: HeartbeatConfig,
+    pub serial: SerialConfig,
+    pub framing: FramingConfig,
+    pub events: EventBus,
+    pub health: HealthMonitor,
+    pub compensation: TemperatureCompensation,
+    pub history: CommandHistory,
+    pub self_test: SelfTest,
+    pub schema: SchemaChecker,
+}
+
+impl Default for SerialClientContext {
+    fn default() -> Self {
+        Self {
+            command_queue: CommandQueue::new(),
+            fault_injector: FaultInjector::shared(false),
+            traffic: TrafficTap::new(),
+            heartbeat: HeartbeatConfig::default(),
+            serial: SerialConfig::default(),
+            framing: FramingConfig::default(),
+            health: HealthMonitor::new(HealthConfig::default(), EventBus::new()),
+            events: EventBus::new(),
+            compensation: TemperatureCompensation::default(),
+            history: CommandHistory::default(),
+            self_test: SelfTest::default(),
+            schema: SchemaChecker::default(),
+        }
+    }
+}
 
 // Enhanced pending command structure to handle ACK + data response
 #[derive(Debug)]
 struct PendingCommand {
+    // The queue's id, matched by cancellations of this exact command
+    id: u64,
     command: String,
     response_sender: tokio::sync::oneshot::Sender<Result<String>>,
     received_ack: bool,
+    // The firmware's ACK line, kept for the command history
+    ack: Option<String>,
+    recorded: bool,
     start_time: std::time::Instant,
+    timeout: Duration,
+    // Request span of the API call that issued the command
+    span: tracing::Span,
+}
+
+impl PendingCommand {
+    // Hand the result to the waiting caller and log the exchange in the command history
+    fn finish(self, result: Result<String>, history: &CommandHistory) {
+        if self.recorded {
+            history.record(&self.command, self.start_time.elapsed(), self.ack, &result);
+        }
+        let _ = self.response_sender.send(result);
+    }
 }
 
 pub async fn run_serial_client(
@@ -29,8 +94,7 @@ pub async fn run_serial_client(
     device_state: Arc<RwLock<DeviceState>>,
 ) -> Result<()> {
     let cancel_token = CancellationToken::new();
-    let (_cmd_sender, cmd_receiver) = mpsc::unbounded_channel::<CommandRequest>();
-    run_serial_client_with_commands(port_name, baud_rate, device_state, cancel_token, cmd_receiver).await
+    run_serial_client_with_commands(port_name, baud_rate, device_state, cancel_token, SerialClientContext::default()).await
 }
 
 pub async fn run_serial_client_with_cancellation(
@@ -39,8 +103,7 @@ pub async fn run_serial_client_with_cancellation(
     device_state: Arc<RwLock<DeviceState>>,
     cancel_token: CancellationToken,
 ) -> Result<()> {
-    let (_cmd_sender, cmd_receiver) = mpsc::unbounded_channel::<CommandRequest>();
-    run_serial_client_with_commands(port_name, baud_rate, device_state, cancel_token, cmd_receiver).await
+    run_serial_client_with_commands(port_name, baud_rate, device_state, cancel_token, SerialClientContext::default()).await
 }
 
 pub async fn run_serial_client_with_commands(
@@ -48,87 +111,95 @@ pub async fn run_serial_client_with_commands(
     baud_rate: u32,
     device_state: Arc<RwLock<DeviceState>>,
     cancel_token: CancellationToken,
-    mut cmd_receiver: mpsc::UnboundedReceiver<CommandRequest>,
+    context: SerialClientContext,
 ) -> Result<()> {
     info!("Starting serial client for nRF52840 device on port: {}", port_name);
+    begin_session(&device_state, &port_name).await;
+    let result = connect_and_monitor_with_commands(&port_name, baud_rate, device_state.clone(), cancel_token, &context).await;
+    end_session(&device_state, &port_name).await;
+    result
+}
 
-    {
-        let mut state = device_state.write().await;
-        state.serial_port = Some(port_name.clone());
-        state.connected = false;
-    }
-
-    let result = connect_and_monitor_with_commands(&port_name, baud_rate, device_state.clone(), cancel_token, &mut cmd_receiver).await;
-    
-    {
-        let mut state = device_state.write().await;
-        state.reset_to_disconnected();
-    }
-    
-    info!("Serial client stopped for port: {}", port_name);
+// Same as run_serial_client_with_commands over a link opened by the caller, e.g. a MockTransport
+pub async fn run_client_with_transport<T: DeviceTransport>(
+    transport: T,
+    link_name: String,
+    device_state: Arc<RwLock<DeviceState>>,
+    cancel_token: CancellationToken,
+    context: SerialClientContext,
+) -> Result<()> {
+    info!("Starting device client on {}", link_name);
+    begin_session(&device_state, &link_name).await;
+    let result = monitor_link(transport, &link_name, device_state.clone(), cancel_token, &context).await;
+    end_session(&device_state, &link_name).await;
     result
 }
 
+async fn begin_session(device_state: &RwLock<DeviceState>, link_name: &str) {
+    let mut state = device_state.write().await;
+    state.serial_port = Some(link_name.to_string());
+    state.connected = false;
+    // Whatever the previous connection learned is re-established by the self-check
+    state.operational = false;
+    state.capabilities = FirmwareCapabilities::default();
+    state.protocol_version = ProtocolVersion::default();
+}
+
+async fn end_session(device_state: &RwLock<DeviceState>, link_name: &str) {
+    device_state.write().await.reset_to_disconnected();
+    info!("Serial client stopped for port: {}", link_name);
+}
+
+// Open the port, or the TCP connection for a tcp:// name, and run the client over it
 async fn connect_and_monitor_with_commands(
     port_name: &str,
     baud_rate: u32,
     device_state: Arc<RwLock<DeviceState>>,
     cancel_token: CancellationToken,
-    cmd_receiver: &mut mpsc::UnboundedReceiver<CommandRequest>,
+    context: &SerialClientContext,
 ) -> Result<()> {
-    info!("Connecting to nRF52840 at {} at {} baud", port_name, baud_rate);
-    
-    let mut port = tokio_serial::new(port_name, baud_rate)
-        .timeout(Duration::from_millis(1000))
-        .data_bits(tokio_serial::DataBits::Eight)
-        .flow_control(tokio_serial::FlowControl::None)
-        .parity(tokio_serial::Parity::None)
-        .stop_bits(tokio_serial::StopBits::One)
-        .open_native_async()
-        .map_err(|e| {
-            error!("Failed to open serial port {}: {}", port_name, e);
-            BridgeError::Serial(e)
-        })?;
-    
-    #[cfg(windows)]
-    {
-        use tokio_serial::SerialPort;
-        if let Err(e) = port.write_data_terminal_ready(true) {
-            warn!("Failed to set DTR: {}", e);
-        } else {
-            debug!("DTR set to true");
+    match port_name.strip_prefix(TCP_PREFIX) {
+        Some(address) => {
+            info!("Connecting to nRF52840 through the serial server at {}", address);
+            let transport = device_transport::connect_tcp(address, &context.framing).await?;
+            monitor_link(transport, port_name, device_state, cancel_token, context).await
         }
-        if let Err(e) = port.write_request_to_send(false) {
-            warn!("Failed to set RTS: {}", e);
-        } else {
-            debug!("RTS set to false");
+        None => {
+            info!("Connecting to nRF52840 at {} at {} baud", port_name, baud_rate);
+            let transport = device_transport::open_serial(port_name, baud_rate, &context.serial, &context.framing)?;
+            tokio::time::sleep(Duration::from_millis(1000)).await;
+            monitor_link(transport, port_name, device_state, cancel_token, context).await
         }
     }
-    
-    tokio::time::sleep(Duration::from_millis(1000)).await;
-    
-    let (reader, mut writer) = tokio::io::split(port);
-    let mut reader = BufReader::new(reader);
+}
+
+// Poll the sensor and carry the queued commands over the link until cancelled or the link fails
+async fn monitor_link<T: DeviceTransport>(
+    mut transport: T,
+    link_name: &str,
+    device_state: Arc<RwLock<DeviceState>>,
+    cancel_token: CancellationToken,
+    context: &SerialClientContext,
+) -> Result<()> {
+    let SerialClientContext { command_queue, fault_injector, traffic, heartbeat, framing, events, health, compensation, history, self_test, schema, .. } = context;
     
     info!("Serial connection established to nRF52840 device");
     
     // Read startup messages
     info!("Reading device startup messages...");
     let start_time = std::time::Instant::now();
-    let mut line_buffer = String::new();
     while start_time.elapsed() < Duration::from_secs(3) {
-        line_buffer.clear();
         tokio::select! {
             _ = cancel_token.cancelled() => {
                 info!("Cancelled during startup message reading");
                 return Ok(());
             }
-            result = tokio::time::timeout(Duration::from_millis(100), reader.read_line(&mut line_buffer)) => {
+            result = tokio::time::timeout(Duration::from_millis(100), transport.read_line()) => {
                 match result {
-                    Ok(Ok(bytes_read)) => {
-                        if bytes_read > 0 {
+                    Ok(Ok(Some(line))) => {
+                        if !line.is_empty() {
                             debug!("Device startup message received");
-                            if bytes_read > 10 {
+                            if line.len() >= 10 {
                                 break;
                             }
                         }
@@ -145,62 +216,156 @@ async fn connect_and_monitor_with_commands(
         state.clear_error();
     }
     
+    // Runs alongside the loop below, which carries its commands; IsSafe stays false until it passes
+    let self_check = tokio::spawn(self_test.clone().run(command_queue.clone(), device_state.clone()));
+    
     let mut status_interval = interval(Duration::from_secs(2));
     let mut position_interval = interval(Duration::from_secs(1));
     
+    // Any received line proves the link is alive; pings are only sent after a silent period
+    let heartbeat_period = Duration::from_secs(heartbeat.interval_secs.max(1));
+    let mut heartbeat_interval = interval(heartbeat_period);
+    let mut last_received = std::time::Instant::now();
+    let mut missed_heartbeats = 0u32;
+    // Set when the loop exits because the link failed rather than being cancelled
+    let mut link_error: Option<BridgeError> = None;
+    
     let mut status_poll_count = 0u32;
     let mut position_poll_count = 0u32;
     
     info!("Sending initial status query to nRF52840");
-    if let Err(e) = send_command(&mut writer, "01").await {
-        warn!("Failed to send initial status command: {}", e);
-    }
+    command_queue.push_poll(protocol::GET_STATUS);
     
     // Enhanced pending command handling for ACK + data responses
     let mut pending_commands: Vec<PendingCommand> = Vec::new();
+    // Polls held back while a user command awaits its data response, so the responses
+    // to interleaved status traffic cannot be mistaken for the command's reply
+    let mut deferred_polls: Vec<String> = Vec::new();
+    // Send times of polls whose data response has not arrived yet; lets an unexpected data
+    // response be told apart from an ordinary poll reply, and times the poll round trip
+    let mut polls_awaiting_data: VecDeque<std::time::Instant> = VecDeque::new();
+    // Time of the last write, used to keep the minimum gap between commands
+    let mut last_sent: Option<std::time::Instant> = None;
     
     loop {
+        let gap_remaining = last_sent
+            .map(|sent| command_queue.min_gap().saturating_sub(sent.elapsed()))
+            .unwrap_or_default();
+        
         tokio::select! {
             _ = cancel_token.cancelled() => {
                 info!("Serial client cancelled - exiting cleanly");
                 break;
             }
             
-            cmd_request = cmd_receiver.recv() => {
-                if let Some(cmd_req) = cmd_request {
-                    info!("Processing command: {}", cmd_req.command);
-                    
-                    match send_command(&mut writer, &cmd_req.command).await {
-                        Ok(()) => {
-                            pending_commands.push(PendingCommand {
-                                command: cmd_req.command.clone(),
-                                response_sender: cmd_req.response_sender,
-                                received_ack: false,
-                                start_time: std::time::Instant::now(),
-                            });
-                            info!("Command {} sent, waiting for ACK + data response", cmd_req.command);
-                        }
-                        Err(e) => {
-                            error!("Failed to send command {}: {}", cmd_req.command, e);
-                            let _ = cmd_req.response_sender.send(Err(e));
+            queued = async {
+                tokio::time::sleep(gap_remaining).await;
+                command_queue.next().await
+            } => {
+                if queued.response_sender.is_none() && !pending_commands.is_empty() {
+                    defer_poll(&mut deferred_polls, &queued.command);
+                    continue;
+                }
+                
+                let span = queued.span;
+                let result = send_command(&mut transport, &queued.command, framing, traffic)
+                    .instrument(span.clone())
+                    .await;
+                last_sent = Some(std::time::Instant::now());
+                
+                match (result, queued.response_sender) {
+                    (Ok(()), Some(response_sender)) => {
+                        span.in_scope(|| info!("Command {} sent, waiting for ACK + data response", queued.command));
+                        pending_commands.push(PendingCommand {
+                            id: queued.id,
+                            command: queued.command.clone(),
+                            response_sender,
+                            received_ack: false,
+                            ack: None,
+                            recorded: queued.recorded,
+                            start_time: std::time::Instant::now(),
+                            timeout: queued.timeout,
+                            span,
+                        });
+                    }
+                    (Ok(()), None) => polls_awaiting_data.push_back(std::time::Instant::now()),
+                    (Err(e), Some(response_sender)) => {
+                        span.in_scope(|| error!("Failed to send command {}: {}", queued.command, e));
+                        let result = Err(e);
+                        if queued.recorded {
+                            history.record(&queued.command, Duration::ZERO, None, &result);
                         }
+                        let _ = response_sender.send(result);
+                    }
+                    (Err(e), None) => {
+                        error!("Error sending poll {}: {}", queued.command, e);
+                        break;
+                    }
+                }
+            }
+            
+            cancellation = command_queue.next_cancellation() => {
+                let Some(index) = pending_commands.iter().position(|pending_cmd| cancellation.matches(pending_cmd.id, &pending_cmd.command)) else {
+                    debug!("No pending command {} to cancel", cancellation.command);
+                    continue;
+                };
+                let cancelled_cmd = pending_commands.remove(index);
+                cancelled_cmd.span.in_scope(|| warn!("Command {} cancelled after {:.1} seconds",
+                    cancelled_cmd.command, cancelled_cmd.start_time.elapsed().as_secs_f64()));
+                cancelled_cmd.finish(Err(BridgeError::Cancelled), history);
+                
+                if let Some(abort) = cancellation.abort_command {
+                    // The abort's own replies are unsolicited and only reach the event bus
+                    info!("Sending abort command {}", abort);
+                    if let Err(e) = send_command(&mut transport, &abort, framing, traffic).await {
+                        error!("Error sending abort command {}: {}", abort, e);
+                        break;
+                    }
+                    last_sent = Some(std::time::Instant::now());
+                }
+                
+                if pending_commands.is_empty() && !deferred_polls.is_empty() {
+                    debug!("Command cancelled, resuming {} deferred poll(s)", deferred_polls.len());
+                    for poll in deferred_polls.drain(..) {
+                        command_queue.push_poll(&poll);
                     }
                 }
             }
             
-            result = read_response(&mut reader) => {
+            result = read_response(&mut transport, traffic) => {
                 match result {
                     Ok(response) => {
+                        // Debug-only fault injection sits between the port and the protocol logic
+                        let action = fault_injector.lock().unwrap().apply(&response);
+                        let response = match action {
+                            FaultAction::Pass(response) => response,
+                            FaultAction::Drop => continue,
+                            FaultAction::Delay(delay, response) => {
+                                tokio::time::sleep(delay).await;
+                                response
+                            }
+                        };
+                        last_received = std::time::Instant::now();
+                        missed_heartbeats = 0;
+                        
                         // Process response and handle command matching
                         if let Err(e) = process_response_with_commands(
                             response, 
                             device_state.clone(), 
-                            &mut pending_commands
+                            &mut pending_commands,
+                            &mut polls_awaiting_data,
+                            events,
+                            health,
+                            compensation,
+                            history,
+                            schema,
                         ).await {
                             warn!("Error processing response: {}", e);
                         }
                     }
                     Err(BridgeError::Timeout) => {
+                        // After a silent read period any outstanding poll reply is lost
+                        polls_awaiting_data.clear();
                         static mut TIMEOUT_COUNT: u32 = 0;
                         unsafe {
                             TIMEOUT_COUNT += 1;
@@ -208,34 +373,48 @@ async fn connect_and_monitor_with_commands(
                                 debug!("No response from device (timeout) - cycle {}", TIMEOUT_COUNT);
                             }
                         }
-                        
-                        // Check for timed out commands (15 second timeout)
-                        let now = std::time::Instant::now();
-                        let mut timed_out_indices = Vec::new();
-                        
-                        for (index, cmd) in pending_commands.iter().enumerate() {
-                            if now.duration_since(cmd.start_time) > Duration::from_secs(15) {
-                                timed_out_indices.push(index);
-                            }
-                        }
-                        
-                        // Remove timed out commands in reverse order to maintain indices
-                        for &index in timed_out_indices.iter().rev() {
-                            let timed_out_cmd = pending_commands.remove(index);
-                            warn!("Command {} timed out after 15 seconds", timed_out_cmd.command);
-                            let _ = timed_out_cmd.response_sender.send(Err(BridgeError::Timeout));
-                        }
                     }
                     Err(e) => {
                         error!("Error reading from serial: {}", e);
                         
                         for cmd in pending_commands.drain(..) {
                             error!("Command {} failed due to serial error", cmd.command);
-                            let _ = cmd.response_sender.send(Err(BridgeError::Device("Serial connection failed".to_string())));
+                            cmd.finish(Err(BridgeError::Device("Serial connection failed".to_string())), history);
                         }
+                        link_error = Some(e);
                         break;
                     }
                 }
+                
+                expire_timed_out_commands(&mut pending_commands, history);
+                
+                if pending_commands.is_empty() && !deferred_polls.is_empty() {
+                    debug!("Command traffic settled, resuming {} deferred poll(s)", deferred_polls.len());
+                    for poll in deferred_polls.drain(..) {
+                        command_queue.push_poll(&poll);
+                    }
+                }
+            }
+            
+            _ = heartbeat_interval.tick(), if heartbeat.interval_secs > 0 => {
+                if last_received.elapsed() < heartbeat_period {
+                    continue;
+                }
+                // A ping would be deferred behind a pending command, and slow ones such as
+                // calibration may be silent for longer than the heartbeat window; their own
+                // timeout covers them until it runs out
+                if pending_commands.iter().any(|pending| pending.start_time.elapsed() <= pending.timeout) {
+                    continue;
+                }
+                if missed_heartbeats >= heartbeat.max_missed {
+                    error!("No reply from nRF52840 for {:.0} seconds ({} pings unanswered) - treating serial link as dead",
+                           last_received.elapsed().as_secs_f64(), missed_heartbeats);
+                    link_error = Some(BridgeError::Device("Serial link unresponsive".to_string()));
+                    break;
+                }
+                missed_heartbeats += 1;
+                debug!("Serial link silent for {:.1}s, sending heartbeat ping {}", last_received.elapsed().as_secs_f64(), missed_heartbeats);
+                command_queue.push_poll(protocol::PING);
             }
             
             _ = status_interval.tick() => {
@@ -243,9 +422,10 @@ async fn connect_and_monitor_with_commands(
                 if status_poll_count % 5 == 0 {
                     debug!("Polling device status (cycle {})", status_poll_count);
                 }
-                if let Err(e) = send_command(&mut writer, "01").await {
-                    error!("Error sending status check: {}", e);
-                    break;
+                if pending_commands.is_empty() {
+                    command_queue.push_poll(protocol::GET_STATUS);
+                } else {
+                    defer_poll(&mut deferred_polls, protocol::GET_STATUS);
                 }
             }
             
@@ -254,23 +434,31 @@ async fn connect_and_monitor_with_commands(
                 if position_poll_count % 10 == 0 {
                     debug!("Polling park status (cycle {})", position_poll_count);
                 }
-                if let Err(e) = send_command(&mut writer, "03").await {
-                    error!("Error sending park status check: {}", e);
-                    break;
+                if pending_commands.is_empty() {
+                    command_queue.push_poll(protocol::IS_PARKED);
+                } else {
+                    defer_poll(&mut deferred_polls, protocol::IS_PARKED);
                 }
             }
         }
     }
     
+    self_check.abort();
+    
     // Clean up any remaining pending commands
     for cmd in pending_commands.drain(..) {
         warn!("Cleaning up pending command: {}", cmd.command);
-        let _ = cmd.response_sender.send(Err(BridgeError::Device("Connection closed".to_string())));
+        cmd.finish(Err(BridgeError::Device("Connection closed".to_string())), history);
     }
+    command_queue.fail_all("Connection closed");
     
-    info!("Starting serial port cleanup for {}", port_name);
-    drop(reader);
-    drop(writer);
+    info!("Starting serial port cleanup for {}", link_name);
+    match timeout(Duration::from_secs(1), transport.close()).await {
+        Ok(Err(e)) => debug!("Error closing {}: {}", link_name, e),
+        Err(_) => debug!("Timed out closing {}", link_name),
+        Ok(Ok(())) => {}
+    }
+    drop(transport);
     tokio::time::sleep(Duration::from_millis(1000)).await;
     
     {
@@ -278,34 +466,66 @@ async fn connect_and_monitor_with_commands(
         state.reset_to_disconnected();
     }
     
-    info!("Serial port {} released and connection monitor stopped", port_name);
-    Ok(())
+    info!("Serial port {} released and connection monitor stopped", link_name);
+    match link_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+// Fail pending commands whose per-command timeout has elapsed
+fn expire_timed_out_commands(pending_commands: &mut Vec<PendingCommand>, history: &CommandHistory) {
+    let now = std::time::Instant::now();
+    let mut index = 0;
+    while index < pending_commands.len() {
+        if now.duration_since(pending_commands[index].start_time) > pending_commands[index].timeout {
+            let timed_out_cmd = pending_commands.remove(index);
+            timed_out_cmd.span.in_scope(|| {
+                warn!("Command {} timed out after {:.1} seconds", timed_out_cmd.command, timed_out_cmd.timeout.as_secs_f64())
+            });
+            timed_out_cmd.finish(Err(BridgeError::Timeout), history);
+        } else {
+            index += 1;
+        }
+    }
+}
+
+// Remember a skipped poll once; it is re-queued when no user command is in flight
+fn defer_poll(deferred_polls: &mut Vec<String>, command: &str) {
+    if !deferred_polls.iter().any(|poll| poll == command) {
+        debug!("Deferring poll {} while a command is in flight", command);
+        deferred_polls.push(command.to_string());
+    }
 }
 
-async fn send_command(writer: &mut tokio::io::WriteHalf<tokio_serial::SerialStream>, command: &str) -> Result<()> {
-    let command_str = format!("<{}>\n", command);
+async fn send_command<T: DeviceTransport>(
+    transport: &mut T,
+    command: &str,
+    framing: &FramingConfig,
+    traffic: &TrafficTap,
+) -> Result<()> {
+    let command_str = framing.encode(command);
     debug!("Sending command to nRF52840: {}", command_str.trim());
+    traffic.publish(TrafficDirection::Tx, command_str.trim());
     
-    writer.write_all(command_str.as_bytes()).await?;
-    writer.flush().await?;
+    transport.write_line(command).await?;
     
     Ok(())
 }
 
-async fn read_response(reader: &mut BufReader<tokio::io::ReadHalf<tokio_serial::SerialStream>>) -> Result<String> {
-    let mut line = String::new();
-    
-    match timeout(Duration::from_secs(3), reader.read_line(&mut line)).await {
-        Ok(Ok(bytes_read)) => {
-            if bytes_read == 0 {
-                return Err(BridgeError::Io(std::io::Error::new(
-                    std::io::ErrorKind::UnexpectedEof,
-                    "Device disconnected"
-                )));
-            }
-            
+async fn read_response<T: DeviceTransport>(
+    transport: &mut T,
+    traffic: &TrafficTap,
+) -> Result<String> {
+    match timeout(Duration::from_secs(3), transport.read_line()).await {
+        Ok(Ok(None)) => Err(BridgeError::Io(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "Device disconnected"
+        ))),
+        Ok(Ok(Some(line))) => {
             let trimmed = line.trim();
             if !trimmed.is_empty() {
+                traffic.publish(TrafficDirection::Rx, trimmed);
                 static mut RECEIVE_COUNT: u32 = 0;
                 unsafe {
                     RECEIVE_COUNT += 1;
@@ -328,10 +548,17 @@ async fn read_response(reader: &mut BufReader<tokio::io::ReadHalf<tokio_serial::
 }
 
 // Enhanced response processing with proper ACK + data command handling
+#[allow(clippy::too_many_arguments)]
 async fn process_response_with_commands(
     response: String, 
     device_state: Arc<RwLock<DeviceState>>,
-    pending_commands: &mut Vec<PendingCommand>
+    pending_commands: &mut Vec<PendingCommand>,
+    polls_awaiting_data: &mut VecDeque<std::time::Instant>,
+    events: &EventBus,
+    health: &HealthMonitor,
+    compensation: &TemperatureCompensation,
+    history: &CommandHistory,
+    schema: &SchemaChecker,
 ) -> Result<()> {
     if response.is_empty() || response.starts_with("=====") || response.starts_with("Device ready") {
         return Ok(());
@@ -345,10 +572,18 @@ async fn process_response_with_commands(
     let parsed: FirmwareResponse = match serde_json::from_str(&response) {
         Ok(parsed) => parsed,
         Err(e) => {
-            debug!("Non-JSON response from device: {} (parse error: {})", response, e);
+            if let Some(progress) = calibration::from_text(&response) {
+                publish_calibration_progress(progress, events);
+            } else {
+                debug!("Non-JSON response from device: {} (parse error: {})", response, e);
+            }
             return Ok(());
         }
     };
+    if schema.is_strict() {
+        let version = device_state.read().await.protocol_version;
+        schema.check(version, &response);
+    }
     
     static mut RESPONSE_COUNT: u32 = 0;
     unsafe {
@@ -362,39 +597,62 @@ async fn process_response_with_commands(
         "ack" => {
             // Handle ACK - mark command as acknowledged but don't send response yet
             if let Some(command) = &parsed.command {
-                for pending_cmd in pending_commands.iter_mut() {
-                    if pending_cmd.command == *command && !pending_cmd.received_ack {
-                        pending_cmd.received_ack = true;
-                        info!("Command {} acknowledged, waiting for data response", command);
-                        break;
-                    }
+                let unacked = pending_commands
+                    .iter_mut()
+                    .find(|pending_cmd| pending_cmd.command == *command && !pending_cmd.received_ack);
+                
+                if let Some(pending_cmd) = unacked {
+                    pending_cmd.received_ack = true;
+                    pending_cmd.ack = Some(response.clone());
+                    pending_cmd.span.in_scope(|| info!("Command {} acknowledged, waiting for data response", command));
+                } else if pending_commands.iter().any(|pending_cmd| pending_cmd.command == *command) {
+                    // Firmware retransmitted an ACK; acknowledging twice must not shift the matching
+                    debug!("Duplicate ACK for command {} ignored", command);
                 }
+                // Otherwise it is the ACK of a periodic poll, which has no pending entry
             }
         }
         "ok" => {
             // Handle data response - send to waiting command if any
-            // Look for commands that have received ACK and are waiting for data
-            if let Some(_data) = &parsed.data {
-                let mut cmd_to_complete = None;
-                
-                for (index, pending_cmd) in pending_commands.iter().enumerate() {
-                    if pending_cmd.received_ack {
-                        // This is the data response for an acknowledged command
-                        cmd_to_complete = Some(index);
-                        break;
-                    }
-                }
+            // Only a command that has received its ACK can own a data response
+            if parsed.data.is_some() {
+                let acked_index = pending_commands.iter().position(|pending_cmd| pending_cmd.received_ack);
                 
-                if let Some(index) = cmd_to_complete {
+                if let Some(index) = acked_index {
                     let completed_cmd = pending_commands.remove(index);
-                    info!("Command {} completed with data response", completed_cmd.command);
-                    let _ = completed_cmd.response_sender.send(Ok(response.clone()));
+                    health.record_latency(completed_cmd.start_time.elapsed());
+                    completed_cmd.span.in_scope(|| info!("Command {} completed with data response", completed_cmd.command));
+                    completed_cmd.finish(Ok(response.clone()), history);
+                } else if let Some(sent) = polls_awaiting_data.pop_front() {
+                    health.record_latency(sent.elapsed());
+                } else {
+                    // Not requested by anyone: still apply it, but never hand it to a pending command
+                    debug!("Unsolicited data response from nRF52840: {}", response);
+                    events.publish(EventKind::UnsolicitedResponse { response: response.clone() });
                 }
             }
             
             // Also process for device state updates (even if it was a command response)
             if let Some(data) = parsed.data {
-                update_device_state_from_data(data, device_state).await?;
+                apply_device_data(data, parsed.command.as_deref(), device_state, events, health, compensation).await?;
+            }
+        }
+        "event" => {
+            // Spontaneous notification (e.g. park change); never matched against pending commands
+            let event = parsed.event.unwrap_or_else(|| "unknown".to_string());
+            info!("nRF52840 event: {}", event);
+            events.publish(EventKind::FirmwareEvent {
+                event,
+                data: parsed.data.clone(),
+            });
+            if let Some(data) = parsed.data {
+                apply_device_data(data, None, device_state, events, health, compensation).await?;
+            }
+        }
+        "progress" => {
+            // Intermediate calibration report; the command stays pending until its data response
+            if let Some(progress) = calibration::from_response(&parsed) {
+                publish_calibration_progress(progress, events);
             }
         }
         "error" => {
@@ -404,8 +662,8 @@ async fn process_response_with_commands(
             // If there are pending commands, fail the first one
             if !pending_commands.is_empty() {
                 let failed_cmd = pending_commands.remove(0);
-                error!("Command {} failed with device error: {}", failed_cmd.command, error_msg);
-                let _ = failed_cmd.response_sender.send(Err(BridgeError::Device(error_msg.clone())));
+                failed_cmd.span.in_scope(|| error!("Command {} failed with device error: {}", failed_cmd.command, error_msg));
+                failed_cmd.finish(Err(BridgeError::Device(error_msg.clone())), history);
             }
             
             let mut state = device_state.write().await;
@@ -419,70 +677,136 @@ async fn process_response_with_commands(
     Ok(())
 }
 
+fn publish_calibration_progress(progress: calibration::CalibrationProgress, events: &EventBus) {
+    match progress.percent {
+        Some(percent) => info!("Calibration: {} {}%", progress.phase, percent),
+        None => info!("Calibration: {}", progress.phase),
+    }
+    events.publish(EventKind::CalibrationProgress {
+        phase: progress.phase,
+        percent: progress.percent,
+    });
+}
+
+// Update the device state from a data payload and announce park-state transitions; `command` is
+// the code a reply names (protocol v2). Positions are temperature-compensated first
+async fn apply_device_data(
+    mut data: serde_json::Value,
+    command: Option<&str>,
+    device_state: Arc<RwLock<DeviceState>>,
+    events: &EventBus,
+    health: &HealthMonitor,
+    compensation: &TemperatureCompensation,
+) -> Result<()> {
+    let uptime = data.get("uptime").and_then(|v| v.as_u64());
+    let free_heap = data.get("freeHeap").and_then(|v| v.as_u64());
+    if uptime.is_some() || free_heap.is_some() {
+        health.record_status(uptime, free_heap);
+    }
+    
+    let was_parked = {
+        let state = device_state.read().await;
+        compensation.apply(&mut data, &state);
+        state.is_parked
+    };
+    update_device_state_from_data(data, command, device_state.clone()).await?;
+    
+    let state = device_state.read().await;
+    if state.is_parked && !was_parked {
+        health.park_session_started();
+    }
+    if state.is_parked {
+        health.record_parked_position(
+            state.current_pitch,
+            state.current_roll,
+            state.park_pitch,
+            state.park_roll,
+            state.position_tolerance,
+        );
+    }
+    if state.is_parked != was_parked {
+        events.publish(EventKind::ParkStateChanged {
+            parked: state.is_parked,
+            pitch: state.current_pitch,
+            roll: state.current_roll,
+        });
+    }
+    Ok(())
+}
+
 async fn update_device_state_from_data(
     data: serde_json::Value,
+    command: Option<&str>,
     device_state: Arc<RwLock<DeviceState>>,
 ) -> Result<()> {
     let mut state = device_state.write().await;
+    if let Some(temperature) = data.get("temperature").and_then(|v| v.as_f64()) {
+        state.temperature = Some(temperature as f32);
+    }
     
     static mut UPDATE_COUNT: u32 = 0;
     unsafe { UPDATE_COUNT += 1; }
     
-    if let Ok(status_data) = serde_json::from_value::<StatusResponse>(data.clone()) {
-        unsafe {
-            if UPDATE_COUNT % 10 == 0 {
-                debug!("Updating device status from nRF52840: parked={}, calibrated={} (cycle {})", 
-                       status_data.parked, status_data.calibrated, UPDATE_COUNT);
-            }
-        }
-        state.update_from_status(&status_data);
-        return Ok(());
-    }
-    
-    if let Ok(position_data) = serde_json::from_value::<PositionResponse>(data.clone()) {
-        unsafe {
-            if UPDATE_COUNT % 20 == 0 {
-                debug!("Updating position from nRF52840: pitch={:.2}, roll={:.2} (cycle {})", 
-                       position_data.pitch, position_data.roll, UPDATE_COUNT);
+    match state.protocol_version.classify(command, &data) {
+        ReplyKind::Status => {
+            let status_data: StatusResponse = serde_json::from_value(data)?;
+            unsafe {
+                if UPDATE_COUNT % 10 == 0 {
+                    debug!("Updating device status from nRF52840: parked={}, calibrated={} (cycle {})", 
+                           status_data.parked, status_data.calibrated, UPDATE_COUNT);
+                }
             }
+            state.update_from_status(&status_data);
         }
-        state.update_from_position(&position_data);
-        return Ok(());
-    }
-    
-    if let Ok(park_data) = serde_json::from_value::<ParkStatusResponse>(data.clone()) {
-        let was_parked = state.is_parked;
-        let now_parked = park_data.parked;
-        
-        if was_parked != now_parked {
-            info!("Park status CHANGED: {} -> {} at pitch={:.2}°, roll={:.2}°", 
-                  if was_parked { "PARKED" } else { "NOT PARKED" },
-                  if now_parked { "PARKED" } else { "NOT PARKED" },
-                  park_data.current_pitch, park_data.current_roll);
-        } else {
+        ReplyKind::Position => {
+            let position_data: PositionResponse = serde_json::from_value(data)?;
             unsafe {
                 if UPDATE_COUNT % 20 == 0 {
-                    debug!("Updating park status from nRF52840: parked={}, pitch={:.2}, roll={:.2} (cycle {})", 
-                           park_data.parked, park_data.current_pitch, park_data.current_roll, UPDATE_COUNT);
+                    debug!("Updating position from nRF52840: pitch={:.2}, roll={:.2} (cycle {})", 
+                           position_data.pitch, position_data.roll, UPDATE_COUNT);
                 }
             }
+            state.update_from_position(&position_data);
         }
-        
-        state.update_from_park_status(&park_data);
-        return Ok(());
-    }
-    
-    if let Some(message) = data.get("message") {
-        if let Some(msg_str) = message.as_str() {
-            info!("nRF52840 message: {}", msg_str);
-            return Ok(());
+        ReplyKind::ParkStatus => {
+            let park_data: ParkStatusResponse = serde_json::from_value(data)?;
+            let was_parked = state.is_parked;
+            let now_parked = park_data.parked;
+            
+            if was_parked != now_parked {
+                info!("Park status CHANGED: {} -> {} at pitch={:.2}°, roll={:.2}°", 
+                      if was_parked { "PARKED" } else { "NOT PARKED" },
+                      if now_parked { "PARKED" } else { "NOT PARKED" },
+                      park_data.current_pitch, park_data.current_roll);
+            } else {
+                unsafe {
+                    if UPDATE_COUNT % 20 == 0 {
+                        debug!("Updating park status from nRF52840: parked={}, pitch={:.2}, roll={:.2} (cycle {})", 
+                               park_data.parked, park_data.current_pitch, park_data.current_roll, UPDATE_COUNT);
+                    }
+                }
+            }
+            
+            state.update_from_park_status(&park_data);
         }
-    }
-    
-    unsafe {
-        if UPDATE_COUNT % 50 == 0 {
-            debug!("Unknown data format from nRF52840: {}", data);
+        ReplyKind::Version => {
+            // Also the heartbeat reply, so a reflashed sensor is renegotiated without reconnecting
+            let negotiated = ProtocolVersion::negotiate(data.get("protocolVersion"));
+            if negotiated != state.protocol_version {
+                info!("Speaking protocol v{} with the nRF52840", negotiated.number());
+                state.protocol_version = negotiated;
+            }
+        }
+        ReplyKind::Message => {
+            if let Some(msg_str) = data.get("message").and_then(|message| message.as_str()) {
+                info!("nRF52840 message: {}", msg_str);
+            }
         }
+        ReplyKind::Unknown => unsafe {
+            if UPDATE_COUNT % 50 == 0 {
+                debug!("Unknown data format from nRF52840: {}", data);
+            }
+        },
     }
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+// src/serial_tee.rs
+// Read-only TCP tee of a device's serial traffic: diagnostic tools watch the conversation live
+// while the bridge keeps exclusive ownership of the port
+
+use crate::config::TeeFormat;
+use crate::session_recording::{TrafficDirection, TrafficLine, TrafficTap};
+use crate::timestamps;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+#[derive(Clone)]
+pub struct SerialTee {
+    tap: TrafficTap,
+    format: TeeFormat,
+    max_clients: usize,
+    clients: Arc<AtomicUsize>,
+}
+
+impl SerialTee {
+    pub fn new(tap: TrafficTap, format: TeeFormat, max_clients: usize) -> Self {
+        Self {
+            tap,
+            format,
+            max_clients,
+            clients: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    // Accept viewers until cancelled; each gets the traffic from the moment it connects
+    pub async fn serve(self, listener: TcpListener, cancel_token: CancellationToken) {
+        if let Ok(addr) = listener.local_addr() {
+            info!("Serial traffic stream (read-only) on tcp://{}", addr);
+            if !addr.ip().is_loopback() {
+                warn!("The serial traffic stream is reachable from the network without authentication");
+            }
+        }
+        loop {
+            let (stream, peer) = tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Serial traffic stream accept failed: {}", e);
+                        continue;
+                    }
+                },
+            };
+            if self.clients.fetch_add(1, Ordering::SeqCst) >= self.max_clients {
+                self.clients.fetch_sub(1, Ordering::SeqCst);
+                warn!("Serial traffic stream refused {}: {} viewers already connected", peer, self.max_clients);
+                continue;
+            }
+            let tee = self.clone();
+            let cancel_token = cancel_token.clone();
+            tokio::spawn(async move {
+                info!("Serial traffic viewer {} connected", peer);
+                tee.stream_to(stream, peer, cancel_token).await;
+                tee.clients.fetch_sub(1, Ordering::SeqCst);
+                info!("Serial traffic viewer {} disconnected", peer);
+            });
+        }
+    }
+
+    async fn stream_to(&self, stream: TcpStream, peer: SocketAddr, cancel_token: CancellationToken) {
+        let mut receiver = self.tap.subscribe();
+        let (mut reader, mut writer) = stream.into_split();
+        let mut discard = [0u8; 256];
+        loop {
+            let line = tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                // Input is never forwarded to the device; reading only notices the viewer leaving
+                read = reader.read(&mut discard) => match read {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => continue,
+                },
+                received = receiver.recv() => match received {
+                    Ok(line) => line,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Serial traffic viewer {} fell behind, {} lines skipped", peer, skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+            };
+            if writer.write_all(self.render(&line).as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn render(&self, line: &TrafficLine) -> String {
+        match self.format {
+            TeeFormat::Text => {
+                let direction = match line.direction {
+                    TrafficDirection::Tx => "TX",
+                    TrafficDirection::Rx => "RX",
+                };
+                format!("{} {} {}\n", timestamps::format_millis(line.timestamp_ms), direction, line.line)
+            }
+            TeeFormat::Json => serde_json::to_string(line).map(|json| json + "\n").unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+// src/server_info.rs
+// Which build is running and since when (/api/about, and the server block of /api/status), so
+// remote users can confirm that an update actually took. The uptime is only on /api/about: it
+// changes every second and would change the /api/status ETag with it
+
+use serde::Serialize;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static STARTED_AT: OnceLock<u64> = OnceLock::new();
+
+// Cargo features compiled into this build
+const FEATURES: &[(&str, bool)] = &[
+    ("web-ui", cfg!(feature = "web-ui")),
+    ("remote-sensors", cfg!(feature = "remote-sensors")),
+    ("desktop", cfg!(feature = "desktop")),
+    ("gpio", cfg!(feature = "gpio")),
+    ("relays", cfg!(feature = "relays")),
+    ("mdns", cfg!(feature = "mdns")),
+    ("chat-bot", cfg!(feature = "chat-bot")),
+    ("telescope-client", cfg!(feature = "telescope-client")),
+    ("event-log", cfg!(feature = "event-log")),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerInfo {
+    pub version: &'static str,
+    // Short commit hash, with "-dirty" for uncommitted changes; "unknown" outside a git checkout
+    pub git_commit: &'static str,
+    // UTC, e.g. "2024-05-01T18:30:00Z"
+    pub build_timestamp: &'static str,
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub started_at: u64,
+}
+
+// GET /api/about
+#[derive(Debug, Clone, Serialize)]
+pub struct About {
+    pub name: &'static str,
+    #[serde(flatten)]
+    pub server: ServerInfo,
+    pub uptime_secs: u64,
+    pub features: Vec<&'static str>,
+}
+
+// Record the start time; called first thing in main, later calls keep the first time
+pub fn mark_started() -> u64 {
+    *STARTED_AT.get_or_init(unix_now)
+}
+
+pub fn server_info() -> ServerInfo {
+    ServerInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: env!("GIT_COMMIT"),
+        build_timestamp: env!("BUILD_TIMESTAMP"),
+        started_at: mark_started(),
+    }
+}
+
+pub fn about() -> About {
+    let server = server_info();
+    About {
+        name: env!("CARGO_PKG_NAME"),
+        uptime_secs: unix_now().saturating_sub(server.started_at),
+        server,
+        features: FEATURES.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name).collect(),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
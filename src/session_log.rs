@@ -0,0 +1,146 @@
+// src/session_log.rs
+// Observing session log: park sensor transitions, safety changes and mount events from every
+// device's event bus merged into one timeline (/api/session_log), kept for much longer than the
+// per-device event buffer so a post-mortem after a failed night has one coherent record
+
+use crate::device_registry::DeviceRegistry;
+use crate::events::{BridgeEvent, EventCategory, EventKind};
+use crate::pagination::Paginated;
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+// About two busy nights of slews, tracking changes and park transitions
+const SESSION_LOG_LIMIT: usize = 5000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionEntry {
+    pub id: u64,
+    // When the event was published, serialized as RFC 3339
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub timestamp: u64,
+    // Sensor whose bus carried the event; null for mount events, which name their telescope
+    pub device_number: Option<u32>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Paginated for SessionEntry {
+    fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn timestamp_secs(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+impl SessionEntry {
+    pub fn in_categories(&self, categories: &[EventCategory]) -> bool {
+        categories.is_empty() || categories.contains(&self.kind.category())
+    }
+}
+
+// Shared by the registry's clones; filled by run_session_log()
+#[derive(Clone)]
+pub struct SessionLog {
+    entries: Arc<Mutex<VecDeque<SessionEntry>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Default for SessionLog {
+    fn default() -> Self {
+        Self {
+            entries: Arc::default(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+}
+
+impl SessionLog {
+    // Every entry, oldest first
+    pub fn entries(&self) -> Vec<SessionEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn record(&self, device_number: u32, event: BridgeEvent) {
+        if !belongs_in_session(&event.kind) {
+            return;
+        }
+        let from_mount = matches!(event.kind, EventKind::TelescopeLinkChanged { .. } | EventKind::TelescopeChanged { .. });
+        let entry = SessionEntry {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            timestamp: event.timestamp,
+            device_number: (!from_mount).then_some(device_number),
+            kind: event.kind,
+        };
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == SESSION_LOG_LIMIT {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+// Calibration progress and stray responses say nothing about how the night went
+fn belongs_in_session(kind: &EventKind) -> bool {
+    !matches!(kind, EventKind::CalibrationProgress { .. } | EventKind::UnsolicitedResponse { .. })
+}
+
+// Follow every device's event bus into the registry's session log until cancelled. Events still
+// buffered from before the start (e.g. the mounts connecting) are recorded first.
+pub async fn run_session_log(devices: DeviceRegistry, cancel_token: CancellationToken) {
+    let log = devices.session_log().clone();
+    let mut receivers = Vec::with_capacity(devices.len());
+    let mut backlog = Vec::new();
+    // Newest buffered event per device, as subscribing first means some may arrive twice
+    let mut last_ids = HashMap::new();
+    for device in devices.devices() {
+        let bus = device.connection_manager.event_bus();
+        receivers.push((device.device_number, bus.subscribe()));
+        let recent = bus.recent(None);
+        last_ids.insert(device.device_number, recent.last().map_or(0, |event| event.id));
+        backlog.extend(recent.into_iter().map(|event| (device.device_number, event)));
+    }
+    backlog.sort_by_key(|(_, event)| event.timestamp);
+    for (device_number, event) in backlog {
+        log.record(device_number, event);
+    }
+    info!("Recording the session log from {} device(s)", receivers.len());
+
+    let mut feed = futures_util::stream::select_all(receivers.into_iter().map(|(device_number, receiver)| {
+        Box::pin(futures_util::stream::unfold(receiver, move |receiver| next_event(device_number, receiver)))
+    }));
+    loop {
+        let next = tokio::select! {
+            _ = cancel_token.cancelled() => return,
+            next = feed.next() => next,
+        };
+        let Some((device_number, event)) = next else {
+            return;
+        };
+        if event.id > last_ids.get(&device_number).copied().unwrap_or(0) {
+            log.record(device_number, event);
+        }
+    }
+}
+
+async fn next_event(
+    device_number: u32,
+    mut receiver: broadcast::Receiver<BridgeEvent>,
+) -> Option<((u32, BridgeEvent), broadcast::Receiver<BridgeEvent>)> {
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return Some(((device_number, event), receiver)),
+            Err(RecvError::Lagged(missed)) => {
+                warn!("Session log missed {} event(s) of device {}", missed, device_number);
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
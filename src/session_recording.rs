@@ -0,0 +1,190 @@
+// src/session_recording.rs
+// Serial traffic tap, session recorder (JSON lines) and timed replay as a virtual device
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, Notify};
+use tracing::{debug, error, info, warn};
+
+// Replay speed multipliers replay_recording() accepts
+pub const REPLAY_SPEEDS: RangeInclusive<f64> = 0.01..=1000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrafficDirection {
+    // Bridge -> device
+    Tx,
+    // Device -> bridge
+    Rx,
+}
+
+// One line of serial traffic as seen by the bridge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrafficLine {
+    // Wall-clock time in milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub direction: TrafficDirection,
+    pub line: String,
+}
+
+// Broadcast tap the serial client publishes every sent and received line to
+#[derive(Clone)]
+pub struct TrafficTap {
+    sender: broadcast::Sender<TrafficLine>,
+}
+
+impl Default for TrafficTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrafficTap {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(1024);
+        Self { sender }
+    }
+
+    pub fn publish(&self, direction: TrafficDirection, line: &str) {
+        // Skip the allocation entirely when nobody is listening
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.sender.send(TrafficLine {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            direction,
+            line: line.to_string(),
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TrafficLine> {
+        self.sender.subscribe()
+    }
+}
+
+// Record every line passing through the tap to a JSON-lines file until the tap is dropped
+pub fn start_recording(tap: &TrafficTap, path: &Path) -> std::io::Result<tokio::task::JoinHandle<()>> {
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = std::io::BufWriter::new(file);
+    let mut receiver = tap.subscribe();
+    let path_display = path.display().to_string();
+    info!("Recording serial session to {}", path_display);
+
+    Ok(tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(line) => {
+                    let result = serde_json::to_string(&line)
+                        .map_err(std::io::Error::from)
+                        .and_then(|json| writeln!(writer, "{}", json))
+                        .and_then(|_| writer.flush());
+                    if let Err(e) = result {
+                        error!("Failed to write session recording {}: {}", path_display, e);
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Session recorder fell behind, {} lines not recorded", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        info!("Session recording to {} stopped", path_display);
+    }))
+}
+
+pub fn load_recording(path: &Path) -> std::io::Result<Vec<TrafficLine>> {
+    let file = std::fs::File::open(path)?;
+    let mut lines = Vec::new();
+    for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: TrafficLine = serde_json::from_str(&line).map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("line {}: {}", index + 1, e),
+            )
+        })?;
+        lines.push(entry);
+    }
+    Ok(lines)
+}
+
+// Play the device side of a recording back onto a stream with the original spacing
+// (scaled by `speed`, within REPLAY_SPEEDS). Playback starts when the bridge sends its first line; later commands
+// are read and logged but not answered, since the recording already contains the replies
+pub async fn replay_recording<S>(stream: S, recording: &[TrafficLine], speed: f64, repeat: bool) -> std::io::Result<()>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    if !REPLAY_SPEEDS.contains(&speed) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("replay speed {} is outside {}..={}", speed, REPLAY_SPEEDS.start(), REPLAY_SPEEDS.end()),
+        ));
+    }
+    let (reader, mut writer) = tokio::io::split(stream);
+    let bridge_attached = Arc::new(Notify::new());
+
+    let attached = bridge_attached.clone();
+    let drain = async move {
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    attached.notify_one();
+                    debug!("Replay ignoring bridge command: {}", line.trim());
+                }
+            }
+        }
+    };
+
+    let play = async move {
+        let device_lines: Vec<&TrafficLine> = recording
+            .iter()
+            .filter(|l| l.direction == TrafficDirection::Rx)
+            .collect();
+        let Some(first) = device_lines.first() else {
+            warn!("Recording contains no device output to replay");
+            return Ok(());
+        };
+        let origin_ms = first.timestamp_ms;
+
+        info!("Waiting for the bridge to send its first command...");
+        bridge_attached.notified().await;
+
+        loop {
+            let started = Instant::now();
+            for entry in &device_lines {
+                let offset = Duration::from_millis(entry.timestamp_ms.saturating_sub(origin_ms)).div_f64(speed);
+                if let Some(wait) = offset.checked_sub(started.elapsed()) {
+                    tokio::time::sleep(wait).await;
+                }
+                writer.write_all(format!("{}\n", entry.line).as_bytes()).await?;
+                writer.flush().await?;
+            }
+            info!("Replayed {} device lines", device_lines.len());
+            if !repeat {
+                return Ok::<(), std::io::Error>(());
+            }
+        }
+    };
+
+    tokio::select! {
+        result = play => result,
+        _ = drain => Ok(()),
+    }
+}
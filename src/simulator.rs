@@ -0,0 +1,533 @@
+// src/simulator.rs
+// Firmware simulator speaking the nRF52840 park sensor protocol
+// Shared by the park-sensor-sim binary and the integration test emulator
+
+use crate::fault_injection::{FaultAction, FaultInjector, FaultPlan};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+const STARTUP_BANNER: [&str; 2] = [
+    "===== nRF52840 Telescope Park Sensor Simulator =====",
+    "Device ready - simulated firmware",
+];
+
+// Mutable sensor state shared between the protocol task, the scenario runner and tests
+#[derive(Debug, Clone)]
+pub struct SimulatorState {
+    pub pitch: f32,
+    pub roll: f32,
+    pub park_pitch: f32,
+    pub park_roll: f32,
+    pub tolerance: f32,
+    pub calibrated: bool,
+    pub free_heap: u64,
+    pub firmware_version: String,
+    pub platform: String,
+    // Message answering <00>, from which the bridge learns the supported commands
+    pub help: String,
+    // Reported in the <08> reply from 2 on; v2 replies also name their command, and readings
+    // carry the park and calibration flags as newer firmware does
+    pub protocol_version: u8,
+
+    // Peak amplitude (degrees) of random jitter added to every reported reading
+    pub noise_amplitude: f32,
+    // IMU temperature (°C) reported with readings; None leaves it out as older firmware does
+    pub temperature: Option<f32>,
+    // Tilt added to both axes per °C away from 20 °C, like a mount expanding in the sun
+    pub tilt_per_degree: f32,
+    // Number of upcoming commands that will be answered with an error response
+    pub pending_errors: u32,
+    // Commands are ignored until this instant (simulated reboot in progress)
+    pub offline_until: Option<Instant>,
+    // Transport-level faults applied to outgoing response lines
+    pub faults: FaultInjector,
+
+    pub booted_at: Instant,
+    pub commands_received: Vec<String>,
+    rng_state: u64,
+}
+
+impl Default for SimulatorState {
+    fn default() -> Self {
+        Self {
+            pitch: 0.0,
+            roll: 0.0,
+            park_pitch: 0.0,
+            park_roll: 0.0,
+            tolerance: 2.0,
+            calibrated: true,
+            free_heap: 200_000,
+            firmware_version: "simulator".to_string(),
+            platform: "nRF52840 Simulator".to_string(),
+            help: "Available Commands: 01-0E".to_string(),
+            protocol_version: 1,
+            noise_amplitude: 0.0,
+            temperature: None,
+            tilt_per_degree: 0.0,
+            pending_errors: 0,
+            offline_until: None,
+            faults: FaultInjector::new(true),
+            booted_at: Instant::now(),
+            commands_received: Vec::new(),
+            rng_state: 0x2545_F491_4F6C_DD1D,
+        }
+    }
+}
+
+impl SimulatorState {
+    pub fn is_parked(&self) -> bool {
+        (self.pitch - self.park_pitch).abs() <= self.tolerance
+            && (self.roll - self.park_roll).abs() <= self.tolerance
+    }
+
+    pub fn uptime_ms(&self) -> u64 {
+        self.booted_at.elapsed().as_millis() as u64
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline_until.map(|until| Instant::now() < until).unwrap_or(false)
+    }
+
+    // Simple xorshift jitter so noisy scenarios don't need an RNG dependency
+    fn jitter(&mut self) -> f32 {
+        if self.noise_amplitude == 0.0 {
+            return 0.0;
+        }
+        self.rng_state ^= self.rng_state << 13;
+        self.rng_state ^= self.rng_state >> 7;
+        self.rng_state ^= self.rng_state << 17;
+        let unit = (self.rng_state % 2001) as f32 / 1000.0 - 1.0;
+        unit * self.noise_amplitude
+    }
+
+    fn reading(&mut self) -> (f32, f32) {
+        let thermal = self.tilt_per_degree * (self.temperature.unwrap_or(20.0) - 20.0);
+        let pitch = self.pitch + thermal + self.jitter();
+        let roll = self.roll + thermal + self.jitter();
+        (pitch, roll)
+    }
+
+    // Adds the temperature to a reading's payload when the simulated IMU reports one
+    fn with_temperature(&self, mut data: Value) -> Value {
+        if let Some(temperature) = self.temperature {
+            data["temperature"] = json!(temperature);
+        }
+        data
+    }
+
+    fn reading_is_parked(&self, pitch: f32, roll: f32) -> bool {
+        (pitch - self.park_pitch).abs() <= self.tolerance && (roll - self.park_roll).abs() <= self.tolerance
+    }
+
+    // Data payload of the park status command (03)
+    fn park_status(&mut self) -> Value {
+        let (pitch, roll) = self.reading();
+        self.with_temperature(json!({
+            "parked": self.reading_is_parked(pitch, roll),
+            "currentPitch": pitch,
+            "currentRoll": roll,
+            "parkPitch": self.park_pitch,
+            "parkRoll": self.park_roll,
+            "tolerance": self.tolerance,
+            "pitchDiff": (pitch - self.park_pitch).abs(),
+            "rollDiff": (roll - self.park_roll).abs(),
+        }))
+    }
+
+    // Unsolicited notification line, carrying the current park status as its data
+    pub fn event_message(&mut self, event: &str) -> Value {
+        json!({ "status": "event", "event": event, "data": self.park_status() })
+    }
+
+    // Produce the ACK + data response lines for a single firmware command
+    pub fn handle_command(&mut self, command: &str) -> Vec<Value> {
+        self.commands_received.push(command.to_string());
+
+        if self.pending_errors > 0 {
+            self.pending_errors -= 1;
+            return vec![json!({ "status": "error", "message": format!("Simulated error for command {}", command) })];
+        }
+
+        let code = command.get(..2).unwrap_or(command);
+        let ack = json!({ "status": "ack", "command": command });
+
+        let data = match code {
+            "00" => json!({ "message": self.help }),
+            "01" => {
+                let (pitch, roll) = self.reading();
+                self.with_temperature(json!({
+                    "deviceName": "Telescope Park Sensor",
+                    "version": self.firmware_version,
+                    "manufacturer": "Corey Smart",
+                    "platform": self.platform,
+                    "imu": "LSM6DS3TR-C",
+                    "parked": self.reading_is_parked(pitch, roll),
+                    "calibrated": self.calibrated,
+                    "uptime": self.uptime_ms(),
+                    "parkPitch": self.park_pitch,
+                    "parkRoll": self.park_roll,
+                    "tolerance": self.tolerance,
+                    "freeHeap": self.free_heap,
+                }))
+            }
+            "02" => {
+                let (pitch, roll) = self.reading();
+                let mut reading = json!({ "pitch": pitch, "roll": roll, "timestamp": self.uptime_ms() });
+                if self.protocol_version >= 2 {
+                    reading["parked"] = json!(self.reading_is_parked(pitch, roll));
+                    reading["calibrated"] = json!(self.calibrated);
+                }
+                self.with_temperature(reading)
+            }
+            "03" => self.park_status(),
+            "04" | "0D" => {
+                // Like the firmware, the park position is a single (noisy) reading
+                let (pitch, roll) = self.reading();
+                self.park_pitch = pitch;
+                self.park_roll = roll;
+                json!({ "message": "Park position set" })
+            }
+            "05" => json!({ "parkPitch": self.park_pitch, "parkRoll": self.park_roll }),
+            "06" => {
+                self.calibrated = true;
+                json!({ "message": "Calibration complete" })
+            }
+            "07" => json!({ "message": "Debug toggled" }),
+            "08" => {
+                let mut version = json!({
+                    "firmwareVersion": self.firmware_version,
+                    "deviceName": "Telescope Park Sensor",
+                    "manufacturer": "Corey Smart",
+                    "platform": self.platform,
+                    "imu": "LSM6DS3TR-C",
+                });
+                if self.protocol_version >= 2 {
+                    version["protocolVersion"] = json!(self.protocol_version);
+                }
+                version
+            }
+            "0A" => match command[2..].parse::<u32>() {
+                Ok(hundredths) => {
+                    self.tolerance = hundredths as f32 / 100.0;
+                    json!({ "message": "Tolerance set", "tolerance": self.tolerance })
+                }
+                Err(_) => return vec![json!({ "status": "error", "message": "Invalid tolerance value" })],
+            },
+            "0B" => json!({ "message": "Tolerance", "tolerance": self.tolerance }),
+            "0C" => json!({ "message": "System info", "uptime": self.uptime_ms(), "freeHeap": self.free_heap }),
+            "0E" => {
+                *self = Self {
+                    commands_received: std::mem::take(&mut self.commands_received),
+                    firmware_version: self.firmware_version.clone(),
+                    platform: self.platform.clone(),
+                    help: self.help.clone(),
+                    protocol_version: self.protocol_version,
+                    ..Self::default()
+                };
+                json!({ "message": "Factory reset complete" })
+            }
+            _ => return vec![json!({ "status": "error", "message": format!("Unknown command: {}", command) })],
+        };
+
+        let mut responses = vec![ack];
+        if code == "06" {
+            for (phase, percent) in [("collecting_samples", 0), ("collecting_samples", 50), ("computing_offsets", 100)] {
+                responses.push(json!({
+                    "status": "progress",
+                    "command": command,
+                    "data": { "phase": phase, "percent": percent },
+                }));
+            }
+        }
+        match self.protocol_version {
+            0 | 1 => responses.push(json!({ "status": "ok", "data": data })),
+            _ => responses.push(json!({ "status": "ok", "command": code, "data": data })),
+        }
+        responses
+    }
+}
+
+// A single step of a simulator scenario script
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScenarioStep {
+    Wait(Duration),
+    Park,
+    Unpark { pitch: f32, roll: f32 },
+    Move { pitch: f32, roll: f32 },
+    Noise(f32),
+    Temperature { celsius: f32, tilt_per_degree: Option<f32> },
+    Errors(u32),
+    Reboot(Duration),
+    Fault(FaultPlan),
+    Event(String),
+    Repeat,
+}
+
+// Parse a scenario script: one step per line, '#' starts a comment
+//
+//   wait 5          # seconds
+//   unpark 30 -5    # move away from park (default 30 0)
+//   park            # return to the park position
+//   move 1.5 0.2    # set an exact position
+//   noise 0.8       # jitter amplitude in degrees (0 disables)
+//   temperature 35 0.02  # report an IMU temperature in °C, optionally tilting by 0.02° per °C from 20 °C
+//   errors 3        # answer the next 3 commands with an error
+//   reboot 2        # emit the startup banner and go silent for 2 seconds
+//   drop 2          # drop the next 2 response lines
+//   corrupt 1       # corrupt the JSON of the next response line
+//   delay-ack 500   # delay every ACK by 500 ms (0 disables)
+//   stale 10        # withhold data responses for 10 seconds
+//   event park_changed  # send an unsolicited event message with the park status
+//   repeat          # restart the script from the top
+pub fn parse_scenario(text: &str) -> Result<Vec<ScenarioStep>, String> {
+    let mut steps = Vec::new();
+
+    for (index, raw_line) in text.lines().enumerate() {
+        let line = raw_line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let keyword = parts.next().unwrap_or("").to_lowercase();
+        let args: Vec<&str> = parts.collect();
+        let line_no = index + 1;
+
+        let number = |i: usize, default: Option<f32>| -> Result<f32, String> {
+            match args.get(i) {
+                Some(value) => value
+                    .parse::<f32>()
+                    .map_err(|_| format!("line {}: invalid number '{}'", line_no, value)),
+                None => default.ok_or_else(|| format!("line {}: '{}' needs more arguments", line_no, keyword)),
+            }
+        };
+        // Negative, NaN and overflowing durations are errors, not panics
+        let seconds = |i: usize, default: Option<f32>| -> Result<Duration, String> {
+            let value = number(i, default)?;
+            Duration::try_from_secs_f32(value).map_err(|_| format!("line {}: invalid duration '{}'", line_no, value))
+        };
+
+        let step = match keyword.as_str() {
+            "wait" => ScenarioStep::Wait(seconds(0, None)?),
+            "park" => ScenarioStep::Park,
+            "unpark" => ScenarioStep::Unpark {
+                pitch: number(0, Some(30.0))?,
+                roll: number(1, Some(0.0))?,
+            },
+            "move" => ScenarioStep::Move {
+                pitch: number(0, None)?,
+                roll: number(1, None)?,
+            },
+            "noise" => ScenarioStep::Noise(number(0, None)?),
+            "temperature" => ScenarioStep::Temperature {
+                celsius: number(0, None)?,
+                tilt_per_degree: args.get(1).map(|_| number(1, None)).transpose()?,
+            },
+            "errors" => ScenarioStep::Errors(number(0, Some(1.0))? as u32),
+            "reboot" => ScenarioStep::Reboot(seconds(0, Some(2.0))?),
+            "drop" => ScenarioStep::Fault(FaultPlan {
+                drop_responses: number(0, Some(1.0))? as u32,
+                ..FaultPlan::default()
+            }),
+            "corrupt" => ScenarioStep::Fault(FaultPlan {
+                corrupt_responses: number(0, Some(1.0))? as u32,
+                ..FaultPlan::default()
+            }),
+            "delay-ack" => ScenarioStep::Fault(FaultPlan {
+                ack_delay_ms: number(0, None)? as u64,
+                ..FaultPlan::default()
+            }),
+            "stale" => ScenarioStep::Fault(FaultPlan {
+                stale_secs: number(0, None)? as u64,
+                ..FaultPlan::default()
+            }),
+            "event" => match args.first() {
+                Some(name) => ScenarioStep::Event(name.to_string()),
+                None => return Err(format!("line {}: 'event' needs an event name", line_no)),
+            },
+            "repeat" => ScenarioStep::Repeat,
+            other => return Err(format!("line {}: unknown scenario step '{}'", line_no, other)),
+        };
+        steps.push(step);
+    }
+
+    if steps.contains(&ScenarioStep::Repeat) && !steps.iter().any(|s| matches!(s, ScenarioStep::Wait(_))) {
+        return Err("a repeating scenario needs at least one 'wait' step".to_string());
+    }
+
+    Ok(steps)
+}
+
+// Execute scenario steps against the shared state; unsolicited output (reboot banners, events)
+// is sent to the protocol task through `output`
+pub async fn run_scenario(
+    steps: Vec<ScenarioStep>,
+    state: Arc<Mutex<SimulatorState>>,
+    output: mpsc::UnboundedSender<String>,
+) {
+    if steps.is_empty() {
+        return;
+    }
+
+    let mut index = 0;
+    while index < steps.len() {
+        let step = steps[index].clone();
+        index += 1;
+        info!("Scenario step: {:?}", step);
+
+        match step {
+            ScenarioStep::Wait(duration) => tokio::time::sleep(duration).await,
+            ScenarioStep::Park => {
+                let mut state = state.lock().unwrap();
+                state.pitch = state.park_pitch;
+                state.roll = state.park_roll;
+            }
+            ScenarioStep::Unpark { pitch, roll } => {
+                let mut state = state.lock().unwrap();
+                state.pitch = state.park_pitch + pitch;
+                state.roll = state.park_roll + roll;
+            }
+            ScenarioStep::Move { pitch, roll } => {
+                let mut state = state.lock().unwrap();
+                state.pitch = pitch;
+                state.roll = roll;
+            }
+            ScenarioStep::Noise(amplitude) => state.lock().unwrap().noise_amplitude = amplitude,
+            ScenarioStep::Temperature { celsius, tilt_per_degree } => {
+                let mut state = state.lock().unwrap();
+                state.temperature = Some(celsius);
+                if let Some(tilt_per_degree) = tilt_per_degree {
+                    state.tilt_per_degree = tilt_per_degree;
+                }
+            }
+            ScenarioStep::Errors(count) => state.lock().unwrap().pending_errors += count,
+            ScenarioStep::Reboot(duration) => {
+                {
+                    let mut state = state.lock().unwrap();
+                    state.booted_at = Instant::now();
+                    state.offline_until = Some(Instant::now() + duration);
+                }
+                for line in STARTUP_BANNER {
+                    let _ = output.send(line.to_string());
+                }
+            }
+            ScenarioStep::Fault(plan) => state.lock().unwrap().faults.inject(plan),
+            ScenarioStep::Event(name) => {
+                let message = state.lock().unwrap().event_message(&name);
+                let _ = output.send(message.to_string());
+            }
+            ScenarioStep::Repeat => index = 0,
+        }
+    }
+}
+
+// Serve the firmware protocol on any byte stream (real serial port, pty master, ...)
+pub async fn run_simulator<S>(
+    stream: S,
+    state: Arc<Mutex<SimulatorState>>,
+    mut unsolicited: mpsc::UnboundedReceiver<String>,
+) -> std::io::Result<()>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    // next_line() is cancel-safe: a command cut short when an unsolicited message wins the
+    // select! is completed by the next call
+    let mut lines = BufReader::new(reader).lines();
+
+    for line in STARTUP_BANNER {
+        writer.write_all(format!("{}\n", line).as_bytes()).await?;
+    }
+    writer.flush().await?;
+
+    loop {
+        tokio::select! {
+            result = lines.next_line() => {
+                let Some(line) = result? else {
+                    return Ok(());
+                };
+
+                let trimmed = line.trim();
+                let Some(command) = trimmed.strip_prefix('<').and_then(|s| s.strip_suffix('>')) else {
+                    continue;
+                };
+
+                let actions: Vec<FaultAction> = {
+                    let mut state = state.lock().unwrap();
+                    if state.is_offline() {
+                        debug!("Ignoring command {} while rebooting", command);
+                        continue;
+                    }
+                    let responses = state.handle_command(command);
+                    responses
+                        .into_iter()
+                        .map(|response| state.faults.apply(&response.to_string()))
+                        .collect()
+                };
+
+                for action in actions {
+                    let response = match action {
+                        FaultAction::Pass(response) => response,
+                        FaultAction::Drop => continue,
+                        FaultAction::Delay(delay, response) => {
+                            tokio::time::sleep(delay).await;
+                            response
+                        }
+                    };
+                    writer.write_all(format!("{}\n", response).as_bytes()).await?;
+                }
+                writer.flush().await?;
+            }
+            Some(message) = unsolicited.recv() => {
+                writer.write_all(format!("{}\n", message).as_bytes()).await?;
+                writer.flush().await?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[test]
+    fn scenario_durations_must_be_valid() {
+        assert!(matches!(parse_scenario("wait 1.5").unwrap()[..], [ScenarioStep::Wait(wait)] if wait == Duration::from_millis(1500)));
+        for text in ["wait -1", "wait NaN", "wait inf", "reboot 1e30"] {
+            let error = parse_scenario(text).err().unwrap_or_else(|| panic!("{} was accepted", text));
+            assert!(error.contains("line 1: invalid duration"), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn unsolicited_messages_do_not_lose_a_partly_read_command() {
+        let (mut bridge, device) = tokio::io::duplex(4096);
+        let (unsolicited, receiver) = mpsc::unbounded_channel();
+        let state = Arc::new(Mutex::new(SimulatorState::default()));
+        tokio::spawn(run_simulator(device, state, receiver));
+
+        bridge.write_all(b"<0").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        unsolicited.send("{\"status\":\"event\",\"event\":\"test\"}".to_string()).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        bridge.write_all(b"B>\n").await.unwrap();
+
+        let mut received = String::new();
+        let mut buffer = [0u8; 1024];
+        while !received.contains("\"tolerance\"") {
+            let read = tokio::time::timeout(Duration::from_secs(2), bridge.read(&mut buffer))
+                .await
+                .expect("no reply to the command")
+                .unwrap();
+            assert!(read > 0, "simulator closed the stream: {}", received);
+            received.push_str(&String::from_utf8_lossy(&buffer[..read]));
+        }
+        assert!(received.contains("\"event\":\"test\""), "{}", received);
+        assert!(received.contains("\"command\":\"0B\""), "{}", received);
+    }
+}
@@ -0,0 +1,90 @@
+// src/status_delta.rs
+// /api/status/delta: the primary device's status as the fields changed since a sequence number
+// the client got earlier, for dashboards polling over metered links. Fields are compared at the
+// top level; a nested object that changed is sent whole
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Statuses kept to compare against; a client further behind gets the full status
+const HISTORY_LEN: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusDelta {
+    // Pass back as ?since= (or If-None-Match, it is also the ETag) on the next poll
+    pub sequence: u64,
+    // True when `changed` holds every field: on the first poll, or for a sequence no longer kept
+    pub full: bool,
+    pub changed: Map<String, Value>,
+    // Fields the status no longer has
+    pub removed: Vec<String>,
+}
+
+// Shared by the router's clones
+#[derive(Clone)]
+pub struct StatusHistory {
+    inner: Arc<Mutex<History>>,
+}
+
+struct History {
+    next_sequence: u64,
+    statuses: VecDeque<(u64, Map<String, Value>)>,
+}
+
+impl Default for StatusHistory {
+    fn default() -> Self {
+        // Sequences start at the start time in milliseconds, so a sequence from before a restart
+        // is not taken for one of this run's
+        Self {
+            inner: Arc::new(Mutex::new(History {
+                next_sequence: unix_now_millis(),
+                statuses: VecDeque::with_capacity(HISTORY_LEN),
+            })),
+        }
+    }
+}
+
+impl StatusHistory {
+    // Record the current status and compare it with the one at `since`; an unchanged status
+    // keeps its sequence
+    pub fn delta(&self, since: Option<u64>, status: Map<String, Value>) -> StatusDelta {
+        let mut history = self.inner.lock().unwrap();
+        let sequence = match history.statuses.back() {
+            Some((sequence, last)) if *last == status => *sequence,
+            _ => {
+                let sequence = history.next_sequence;
+                history.next_sequence += 1;
+                if history.statuses.len() == HISTORY_LEN {
+                    history.statuses.pop_front();
+                }
+                history.statuses.push_back((sequence, status.clone()));
+                sequence
+            }
+        };
+        let base = since.and_then(|since| history.statuses.iter().find(|(kept, _)| *kept == since));
+        match base {
+            Some((_, base)) => StatusDelta {
+                sequence,
+                full: false,
+                removed: base.keys().filter(|field| !status.contains_key(*field)).cloned().collect(),
+                changed: status.into_iter().filter(|(field, value)| base.get(field) != Some(value)).collect(),
+            },
+            None => StatusDelta {
+                sequence,
+                full: true,
+                changed: status,
+                removed: Vec::new(),
+            },
+        }
+    }
+}
+
+fn unix_now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
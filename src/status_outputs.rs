@@ -0,0 +1,140 @@
+// src/status_outputs.rs
+// Panel lamps in the dome (feature "gpio", Linux only): GPIO pins or kernel LEDs lit while the
+// sensor reports safe and while it is connected, so the state is visible without a screen
+
+use crate::config::{GpioConfig, StatusOutput};
+use crate::device_registry::DeviceHandle;
+use crate::errors::{BridgeError, Result};
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+// Kernel LED class directory; LED outputs are named after its entries
+pub const LEDS_DIR: &str = "/sys/class/leds";
+
+// Line owner shown by gpioinfo
+const CONSUMER: &str = "telescope_park_bridge";
+
+// The device state is cheap to read, so lamps follow it closely
+const UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+enum Lamp {
+    Pin(LineHandle),
+    // Brightness file of a kernel LED
+    Led(PathBuf),
+}
+
+impl Lamp {
+    fn set(&self, lit: bool) -> Result<()> {
+        match self {
+            Self::Pin(handle) => handle
+                .set_value(lit as u8)
+                .map_err(|e| BridgeError::Device(format!("GPIO write failed: {}", e))),
+            Self::Led(brightness) => Ok(std::fs::write(brightness, if lit { "1" } else { "0" })?),
+        }
+    }
+}
+
+// A lamp with the value last written, so unchanged states are not rewritten every tick
+struct LampState {
+    name: &'static str,
+    lamp: Lamp,
+    lit: Option<bool>,
+}
+
+impl LampState {
+    fn show(&mut self, lit: bool) {
+        if self.lit == Some(lit) {
+            return;
+        }
+        // Remember the value even when the write fails, so a broken lamp warns once per change
+        self.lit = Some(lit);
+        match self.lamp.set(lit) {
+            Ok(()) => debug!("{} lamp {}", self.name, if lit { "on" } else { "off" }),
+            Err(e) => warn!("Cannot switch the {} lamp: {}", self.name, e),
+        }
+    }
+}
+
+pub struct StatusOutputs {
+    safe: Option<LampState>,
+    connected: Option<LampState>,
+}
+
+impl StatusOutputs {
+    // Claim the configured pins and LEDs; `leds_dir` is normally LEDS_DIR
+    pub fn open(config: &GpioConfig, leds_dir: &Path) -> Result<Self> {
+        let mut chip = None;
+        let mut open = |name: &'static str, output: &Option<StatusOutput>| -> Result<Option<LampState>> {
+            let Some(output) = output else {
+                return Ok(None);
+            };
+            let lamp = match output {
+                StatusOutput::Pin(pin) => {
+                    let chip = match &mut chip {
+                        Some(chip) => chip,
+                        None => chip.insert(
+                            Chip::new(&config.chip)
+                                .map_err(|e| BridgeError::Config(format!("gpio.chip {}: {}", config.chip, e)))?,
+                        ),
+                    };
+                    Lamp::Pin(request_pin(chip, *pin, config.active_low)?)
+                }
+                StatusOutput::Led(led) => Lamp::Led(claim_led(&leds_dir.join(led))?),
+            };
+            info!("{} lamp on {}", name, output);
+            Ok(Some(LampState { name, lamp, lit: None }))
+        };
+        Ok(Self {
+            safe: open("Safe", &config.safe)?,
+            connected: open("Connected", &config.connected)?,
+        })
+    }
+
+    // Mirror the device until cancelled, then switch the lamps off: a bridge that stopped
+    // reporting must not leave a "safe" lamp lit
+    pub async fn run(mut self, device: DeviceHandle, cancel_token: CancellationToken) {
+        let max_data_age = device.connection_manager.max_data_age_secs();
+        let mut interval = tokio::time::interval(UPDATE_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                _ = interval.tick() => {}
+            }
+            let (is_safe, connected) = {
+                let state = device.device_state.read().await;
+                (state.is_safe_now(max_data_age), state.connected)
+            };
+            self.show(is_safe, connected);
+        }
+        self.show(false, false);
+        debug!("Status lamps switched off");
+    }
+
+    fn show(&mut self, is_safe: bool, connected: bool) {
+        if let Some(lamp) = &mut self.safe {
+            lamp.show(is_safe);
+        }
+        if let Some(lamp) = &mut self.connected {
+            lamp.show(connected);
+        }
+    }
+}
+
+fn request_pin(chip: &mut Chip, pin: u32, active_low: bool) -> Result<LineHandle> {
+    let mut flags = LineRequestFlags::OUTPUT;
+    if active_low {
+        flags |= LineRequestFlags::ACTIVE_LOW;
+    }
+    chip.get_line(pin)
+        .and_then(|line| line.request(flags, 0, CONSUMER))
+        .map_err(|e| BridgeError::Config(format!("GPIO pin {}: {}", pin, e)))
+}
+
+// Take the LED over from its kernel trigger (e.g. SD card activity on a Pi's ACT LED)
+fn claim_led(dir: &Path) -> Result<PathBuf> {
+    let claim = std::fs::write(dir.join("trigger"), "none").map(|_| dir.join("brightness"));
+    claim.map_err(|e| BridgeError::Config(format!("LED {}: {}", dir.display(), e)))
+}
@@ -0,0 +1,98 @@
+// src/sun_safety.rs
+// Sun altitude at the site ([sun_safety]): while the Sun is above max_altitude every device
+// reports IsSafe false, or a warning is only published, so solar-blind equipment is not declared
+// safe in daylight just because the mount happens to be parked
+
+use crate::config::{SunAction, SunSafetyConfig};
+use crate::device_registry::DeviceRegistry;
+use crate::events::EventKind;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+// The Sun moves about a quarter of a degree per minute
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+// Unix time of J2000.0 (2000-01-01 12:00 UTC)
+const J2000_SECS: f64 = 946_728_000.0;
+
+// Geometric altitude of the Sun's centre in degrees, without refraction; the low-precision
+// formulas of the Astronomical Almanac, good to about 0.1° for decades around 2000
+pub fn sun_altitude(secs: u64, latitude: f64, longitude: f64) -> f64 {
+    let days = (secs as f64 - J2000_SECS) / 86_400.0;
+    let mean_anomaly = (357.529 + 0.985_600_28 * days).to_radians();
+    let mean_longitude = 280.459 + 0.985_647_36 * days;
+    let ecliptic_longitude =
+        (mean_longitude + 1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin()).to_radians();
+    let obliquity = (23.439 - 0.000_000_36 * days).to_radians();
+
+    let right_ascension = (obliquity.cos() * ecliptic_longitude.sin()).atan2(ecliptic_longitude.cos());
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+    let sidereal_degrees = 280.460_618_37 + 360.985_647_366_29 * days + longitude;
+    let hour_angle = sidereal_degrees.to_radians() - right_ascension;
+
+    let latitude = latitude.to_radians();
+    let altitude = (latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos()).asin();
+    altitude.to_degrees()
+}
+
+// Applies the Sun's altitude to the devices, publishing each crossing of max_altitude once
+pub struct SunSafety {
+    config: SunSafetyConfig,
+    // Side of max_altitude at the last update; None before the first
+    above: Option<bool>,
+}
+
+impl SunSafety {
+    pub fn new(config: SunSafetyConfig) -> Self {
+        Self { config, above: None }
+    }
+
+    // Evaluate the altitude at `now` and return it; does nothing without a site
+    pub async fn update(&mut self, devices: &DeviceRegistry, now: u64) -> Option<f64> {
+        let (latitude, longitude) = self.config.site()?;
+        let altitude = sun_altitude(now, latitude, longitude);
+        let above = altitude > self.config.max_altitude;
+        let forces_unsafe = self.config.action == SunAction::Unsafe;
+        if forces_unsafe {
+            for device in devices.devices() {
+                device.device_state.write().await.sun_unsafe = above;
+            }
+        }
+
+        // A Sun already down at startup is not news
+        let previous = self.above.replace(above);
+        if previous != Some(above) && (above || previous.is_some()) {
+            if above {
+                let effect = if forces_unsafe { "; IsSafe is false" } else { "" };
+                warn!("Sun at {:.1}°, above {:.1}°{}", altitude, self.config.max_altitude, effect);
+            } else {
+                info!("Sun at {:.1}°, below {:.1}° again", altitude, self.config.max_altitude);
+            }
+            devices.primary().connection_manager.event_bus().publish(EventKind::SunAltitudeCrossed {
+                above,
+                altitude,
+                max_altitude: self.config.max_altitude,
+                forces_unsafe,
+            });
+        }
+        Some(altitude)
+    }
+}
+
+// Follow the Sun until cancelled
+pub async fn run_sun_safety(config: SunSafetyConfig, devices: DeviceRegistry, cancel_token: CancellationToken) {
+    let mut sun = SunSafety::new(config);
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = cancel_token.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+        sun.update(&devices, unix_now()).await;
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
@@ -0,0 +1,322 @@
+// src/system_log.rs
+// Log output to syslog on Unix or the Windows Event Log ([logging] target), as a tracing layer
+// next to the console output
+
+use crate::config::{LogLevel, LogTarget, LoggingConfig};
+use std::fmt::Write as _;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+pub struct SystemLogLayer {
+    sink: Sink,
+}
+
+enum Sink {
+    #[cfg(unix)]
+    Syslog(syslog::SyslogSink),
+    #[cfg(all(windows, feature = "event-log"))]
+    EventLog(event_log::EventLogSink),
+}
+
+impl SystemLogLayer {
+    // None for target = "console"; an error when the system log cannot be reached
+    pub fn new(config: &LoggingConfig) -> std::io::Result<Option<Self>> {
+        let sink = match config.target {
+            LogTarget::Console => return Ok(None),
+            #[cfg(unix)]
+            LogTarget::Syslog => Sink::Syslog(syslog::SyslogSink::open(config)?),
+            #[cfg(all(windows, feature = "event-log"))]
+            LogTarget::Eventlog => Sink::EventLog(event_log::EventLogSink::open(&config.name)?),
+            #[allow(unreachable_patterns)]
+            target => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    format!("{} is not available on this platform", target),
+                ))
+            }
+        };
+        Ok(Some(Self { sink }))
+    }
+}
+
+pub fn level_filter(level: LogLevel) -> LevelFilter {
+    match level {
+        LogLevel::Error => LevelFilter::ERROR,
+        LogLevel::Warn => LevelFilter::WARN,
+        LogLevel::Info => LevelFilter::INFO,
+        LogLevel::Debug => LevelFilter::DEBUG,
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SystemLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+        let level = *event.metadata().level();
+        match &self.sink {
+            #[cfg(unix)]
+            Sink::Syslog(sink) => sink.send(level, &message.0),
+            #[cfg(all(windows, feature = "event-log"))]
+            Sink::EventLog(sink) => sink.send(level, &message.0),
+        }
+    }
+}
+
+// The message, followed by any other fields as key=value
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record(field, format_args!("{:?}", value));
+    }
+}
+
+impl MessageVisitor {
+    fn record(&mut self, field: &Field, value: std::fmt::Arguments<'_>) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.0);
+            let _ = write!(self.0, "{}{}", value, fields);
+        } else {
+            let _ = write!(self.0, " {}={}", field.name(), value);
+        }
+    }
+}
+
+#[cfg(unix)]
+mod syslog {
+    use crate::config::LoggingConfig;
+    use std::os::unix::net::UnixDatagram;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc::{self, Receiver, SyncSender};
+    use tracing::Level;
+
+    // Where the local syslog daemon listens on Linux, macOS and the BSDs
+    const DEFAULT_SOCKETS: &[&str] = &["/dev/log", "/var/run/syslog", "/var/run/log"];
+
+    // Lines waiting for the writer thread; more are dropped while the daemon is stuck
+    const QUEUE_LINES: usize = 1024;
+
+    // Lines go to a writer thread, so a slow or hung syslog daemon never blocks the code that
+    // logged them
+    pub struct SyslogSink {
+        lines: SyncSender<String>,
+        name: String,
+        facility: u8,
+    }
+
+    impl SyslogSink {
+        pub fn open(config: &LoggingConfig) -> std::io::Result<Self> {
+            let path = match &config.syslog_socket {
+                Some(path) => PathBuf::from(path),
+                None => DEFAULT_SOCKETS
+                    .iter()
+                    .map(PathBuf::from)
+                    .find(|path| path.exists())
+                    .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no syslog socket found"))?,
+            };
+            let socket = connect(&path)?;
+            let (lines, queued) = mpsc::sync_channel(QUEUE_LINES);
+            std::thread::Builder::new()
+                .name("syslog".to_string())
+                .spawn(move || write_lines(socket, &path, queued))?;
+            Ok(Self {
+                lines,
+                name: config.name.clone(),
+                facility: config.syslog_facility.code(),
+            })
+        }
+
+        // RFC 3164 line as the C library's syslog() sends it
+        pub fn send(&self, level: Level, message: &str) {
+            let severity = match level {
+                Level::ERROR => 3,
+                Level::WARN => 4,
+                Level::INFO => 6,
+                _ => 7,
+            };
+            let line = format!(
+                "<{}>{} {}[{}]: {}",
+                self.facility * 8 + severity,
+                chrono::Local::now().format("%b %e %H:%M:%S"),
+                self.name,
+                std::process::id(),
+                message
+            );
+            let _ = self.lines.try_send(line);
+        }
+    }
+
+    // Reconnects once per line if the daemon was restarted; ends with the sink
+    fn write_lines(mut socket: UnixDatagram, path: &Path, lines: Receiver<String>) {
+        for line in lines {
+            if socket.send(line.as_bytes()).is_err() {
+                if let Ok(reconnected) = connect(path) {
+                    socket = reconnected;
+                    let _ = socket.send(line.as_bytes());
+                }
+            }
+        }
+    }
+
+    fn connect(path: &Path) -> std::io::Result<UnixDatagram> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(socket)
+    }
+}
+
+#[cfg(all(windows, feature = "event-log"))]
+mod event_log {
+    use tracing::Level;
+    use windows_sys::Win32::Foundation::{ERROR_SUCCESS, HANDLE};
+    use windows_sys::Win32::System::EventLog::{
+        DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE,
+        EVENTLOG_WARNING_TYPE,
+    };
+    use windows_sys::Win32::System::Registry::{
+        RegCloseKey, RegCreateKeyExW, RegSetValueExW, HKEY, HKEY_LOCAL_MACHINE, KEY_SET_VALUE, REG_CREATED_NEW_KEY,
+        REG_DWORD, REG_EXPAND_SZ, REG_OPTION_NON_VOLATILE,
+    };
+
+    // Ids 1 to 1000 of this message file are a bare "%1", so the Event Viewer shows the text as is
+    const MESSAGE_FILE: &str = r"%SystemRoot%\System32\EventCreate.exe";
+    const EVENT_ID: u32 = 1000;
+
+    pub struct EventLogSink {
+        handle: HANDLE,
+    }
+
+    // The handle may be used from any thread; ReportEventW is thread-safe
+    unsafe impl Send for EventLogSink {}
+    unsafe impl Sync for EventLogSink {}
+
+    impl EventLogSink {
+        pub fn open(source: &str) -> std::io::Result<Self> {
+            register(source);
+            let source = wide(source);
+            let handle = unsafe { RegisterEventSourceW(std::ptr::null(), source.as_ptr()) };
+            if handle.is_null() {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(Self { handle })
+        }
+
+        pub fn send(&self, level: Level, message: &str) {
+            let event_type = match level {
+                Level::ERROR => EVENTLOG_ERROR_TYPE,
+                Level::WARN => EVENTLOG_WARNING_TYPE,
+                _ => EVENTLOG_INFORMATION_TYPE,
+            };
+            let message = wide(message);
+            let strings = [message.as_ptr()];
+            unsafe {
+                ReportEventW(
+                    self.handle,
+                    event_type,
+                    0,
+                    EVENT_ID,
+                    std::ptr::null_mut(),
+                    1,
+                    0,
+                    strings.as_ptr(),
+                    std::ptr::null(),
+                );
+            }
+        }
+    }
+
+    impl Drop for EventLogSink {
+        fn drop(&mut self) {
+            unsafe {
+                DeregisterEventSource(self.handle);
+            }
+        }
+    }
+
+    // Register the source under the Application log, which needs an elevated process such as a
+    // service; a source already registered, e.g. with New-EventLog, is left alone
+    fn register(source: &str) {
+        let path = wide(&format!(r"SYSTEM\CurrentControlSet\Services\EventLog\Application\{}", source));
+        let mut key: HKEY = std::ptr::null_mut();
+        let mut disposition = 0;
+        unsafe {
+            let created = RegCreateKeyExW(
+                HKEY_LOCAL_MACHINE,
+                path.as_ptr(),
+                0,
+                std::ptr::null(),
+                REG_OPTION_NON_VOLATILE,
+                KEY_SET_VALUE,
+                std::ptr::null(),
+                &mut key,
+                &mut disposition,
+            );
+            if created != ERROR_SUCCESS {
+                return;
+            }
+            if disposition == REG_CREATED_NEW_KEY {
+                let file = wide(MESSAGE_FILE);
+                RegSetValueExW(
+                    key,
+                    wide("EventMessageFile").as_ptr(),
+                    0,
+                    REG_EXPAND_SZ,
+                    file.as_ptr().cast(),
+                    (file.len() * 2) as u32,
+                );
+                let types: u32 = (EVENTLOG_ERROR_TYPE | EVENTLOG_WARNING_TYPE | EVENTLOG_INFORMATION_TYPE) as u32;
+                RegSetValueExW(
+                    key,
+                    wide("TypesSupported").as_ptr(),
+                    0,
+                    REG_DWORD,
+                    (&types as *const u32).cast(),
+                    4,
+                );
+            }
+            RegCloseKey(key);
+        }
+    }
+
+    fn wide(text: &str) -> Vec<u16> {
+        text.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::syslog::SyslogSink;
+    use crate::config::LoggingConfig;
+    use std::os::unix::net::UnixDatagram;
+    use std::time::{Duration, Instant};
+    use tracing::Level;
+
+    #[test]
+    fn a_daemon_that_stops_reading_does_not_block_logging() {
+        let dir = std::env::temp_dir().join(format!("park-bridge-syslog-stuck-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log.sock");
+        // Bound but never read, so its receive queue fills up after a few hundred lines
+        let _daemon = UnixDatagram::bind(&path).unwrap();
+        let config = LoggingConfig {
+            syslog_socket: Some(path.display().to_string()),
+            ..LoggingConfig::default()
+        };
+        let sink = SyslogSink::open(&config).unwrap();
+
+        let started = Instant::now();
+        for n in 0..20_000 {
+            sink.send(Level::WARN, &format!("line {}", n));
+        }
+        assert!(started.elapsed() < Duration::from_secs(2), "{:?}", started.elapsed());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,7 +1,21 @@
-use ascom_alpaca::Client;
+// src/telescope_client.rs
+// Alpaca Telescope client (cargo feature telescope-client) for the mount the park sensor sits on,
+// over plain reqwest so timeouts, retries and connection pooling can be tuned per mount
+
+use crate::config::TelescopeConfig;
+use crate::errors::TelescopeError;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, info};
-use std::sync::Arc;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::OnceCell;
+use tracing::{debug, info, warn};
+
+// Port of ASCOM Remote / the Alpaca server on the local machine
+const LOCAL_ALPACA_URL: &str = "http://localhost:11111";
 
 #[derive(Debug, Clone)]
 pub enum TelescopeConnection {
@@ -9,23 +23,102 @@ pub enum TelescopeConnection {
     Local { prog_id: String },
 }
 
-#[derive(Debug)]
-pub struct TelescopeClient {
-    connection: TelescopeConnection,
-    client: Option<Arc<Client>>,
-    device_number: u32,
+// HTTP behaviour towards the mount controller
+#[derive(Debug, Clone)]
+pub struct TelescopeClientOptions {
+    // Limit for establishing the TCP connection
+    pub connect_timeout: Duration,
+    // Limit for each request, connecting included
+    pub request_timeout: Duration,
+    // Extra attempts for GETs that fail with a network error, timeout or 5xx; PUTs change the
+    // mount's state and are never repeated
+    pub get_retries: u32,
+    // Delay before the first retry, doubled for each further one, plus up to half of it as jitter
+    pub retry_delay: Duration,
+    // Idle keep-alive connections are closed after this
+    pub pool_idle_timeout: Duration,
+    pub pool_max_idle_per_host: usize,
+}
+
+impl Default for TelescopeClientOptions {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(3),
+            request_timeout: Duration::from_secs(10),
+            get_retries: 2,
+            retry_delay: Duration::from_millis(250),
+            pool_idle_timeout: Duration::from_secs(90),
+            pool_max_idle_per_host: 4,
+        }
+    }
 }
 
-impl Clone for TelescopeClient {
-    fn clone(&self) -> Self {
+// Timeouts and retries from [telescope]; the pool settings keep their defaults
+impl From<&TelescopeConfig> for TelescopeClientOptions {
+    fn from(config: &TelescopeConfig) -> Self {
         Self {
-            connection: self.connection.clone(),
-            client: self.client.clone(),
-            device_number: self.device_number,
+            connect_timeout: Duration::from_millis(config.connect_timeout_ms),
+            request_timeout: Duration::from_millis(config.request_timeout_ms),
+            get_retries: config.get_retries,
+            retry_delay: Duration::from_millis(config.retry_delay_ms),
+            ..Self::default()
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct TelescopeClient {
+    connection: TelescopeConnection,
+    options: TelescopeClientOptions,
+    client: Option<AlpacaClient>,
+    device_number: u32,
+    // Read once per connection, as the driver cannot change them while connected
+    capabilities: Arc<OnceCell<Capabilities>>,
+    // Axes set moving by move_axis, by axis number, so they can be stopped on disconnect
+    moving: Arc<Mutex<[bool; 2]>>,
+}
+
+// Properties that are fixed for a connected driver
+#[derive(Debug, Clone)]
+struct Capabilities {
+    name: String,
+    description: String,
+    can_park: bool,
+    can_set_park: bool,
+    can_home: bool,
+    can_slew: bool,
+    // By axis number
+    can_move_axis: [bool; 2],
+    axis_rates: [Vec<RateRange>; 2],
+    supported_actions: Vec<String>,
+}
+
+// One AxisRates entry, degrees per second; any rate from minimum to maximum is accepted
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct RateRange {
+    #[serde(rename(deserialize = "Minimum"))]
+    pub minimum: f64,
+    #[serde(rename(deserialize = "Maximum"))]
+    pub maximum: f64,
+}
+
+// What MoveAxis accepts on one axis, as the driver reports it
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct AxisRates {
+    pub can_move: bool,
+    // Empty when the driver cannot move the axis
+    pub rates: Vec<RateRange>,
+}
+
+// The requested rate if a range allows it, otherwise the nearest rate one does
+fn clamp_rate(rate: f64, ranges: &[RateRange]) -> f64 {
+    ranges
+        .iter()
+        .map(|range| rate.clamp(range.minimum.min(range.maximum), range.maximum.max(range.minimum)))
+        .min_by(|a, b| (a - rate).abs().total_cmp(&(b - rate).abs()))
+        .unwrap_or(rate)
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TelescopeStatus {
     pub connected: bool,
@@ -33,13 +126,15 @@ pub struct TelescopeStatus {
     pub description: String,
     pub ra: f64,          // Right Ascension in decimal hours
     pub dec: f64,         // Declination in decimal degrees
-    pub azimuth: f64,     // Azimuth in degrees
-    pub altitude: f64,    // Altitude in degrees
+    // Degrees; None from drivers that do not implement them (optional in ITelescope)
+    pub azimuth: Option<f64>,
+    pub altitude: Option<f64>,
     pub tracking: bool,
     pub slewing: bool,
     pub at_home: bool,
     pub at_park: bool,
     pub can_park: bool,
+    pub can_set_park: bool,
     pub can_home: bool,
     pub can_slew: bool,
     pub can_move_axis: bool,
@@ -54,13 +149,14 @@ impl Default for TelescopeStatus {
             description: "Unknown".to_string(),
             ra: 0.0,
             dec: 0.0,
-            azimuth: 0.0,
-            altitude: 0.0,
+            azimuth: None,
+            altitude: None,
             tracking: false,
             slewing: false,
             at_home: false,
             at_park: false,
             can_park: false,
+            can_set_park: false,
             can_home: false,
             can_slew: false,
             can_move_axis: false,
@@ -70,6 +166,7 @@ impl Default for TelescopeStatus {
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
 pub enum SlewDirection {
     North,
     South,
@@ -84,154 +181,473 @@ pub enum TelescopeAxis {
     Secondary, // Dec/Altitude
 }
 
+impl SlewDirection {
+    // North/South move the secondary axis, East/West the primary; the second is the rate's sign
+    pub fn axis(self) -> (TelescopeAxis, f64) {
+        match self {
+            SlewDirection::North => (TelescopeAxis::Secondary, 1.0),
+            SlewDirection::South => (TelescopeAxis::Secondary, -1.0),
+            SlewDirection::East => (TelescopeAxis::Primary, 1.0),
+            SlewDirection::West => (TelescopeAxis::Primary, -1.0),
+        }
+    }
+}
+
+impl TelescopeAxis {
+    pub fn number(self) -> u8 {
+        match self {
+            TelescopeAxis::Primary => 0,
+            TelescopeAxis::Secondary => 1,
+        }
+    }
+}
+
 impl TelescopeClient {
     pub fn new(connection: TelescopeConnection) -> Self {
         Self {
             connection,
+            options: TelescopeClientOptions::default(),
             client: None,
             device_number: 0,
+            capabilities: Arc::default(),
+            moving: Arc::default(),
         }
     }
 
-    pub async fn connect(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        match &self.connection {
+    // Applies from the next connect()
+    pub fn with_options(mut self, options: TelescopeClientOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub async fn connect(&mut self) -> Result<(), TelescopeError> {
+        let (url, device_number) = match &self.connection {
             TelescopeConnection::Alpaca { url, device_number } => {
                 info!("Connecting to Alpaca telescope at {} device {}", url, device_number);
-                let client = Arc::new(Client::new(url)?);
-                self.client = Some(client.clone());
-                self.device_number = *device_number;
-                
-                // Test connection by getting device info
-                let _info = client.get_devices().await?;
-                
-                Ok(())
+                (url.clone(), *device_number)
             }
             TelescopeConnection::Local { prog_id } => {
                 info!("Connecting to local ASCOM telescope: {}", prog_id);
                 // For local ASCOM connections, we'll use the default client which connects to localhost
-                let client = Arc::new(Client::new("http://localhost:11111")?);
-                self.client = Some(client.clone());
-                self.device_number = 0;
-                
-                // Test connection
-                let _info = client.get_devices().await?;
-                
-                Ok(())
+                (LOCAL_ALPACA_URL.to_string(), 0)
             }
-        }
+        };
+        let client = AlpacaClient::new(&url, device_number, &self.options)?;
+
+        // Test connection by getting device info, then have the driver connect to the mount, which
+        // it will not have done yet after a restart of the Alpaca server
+        let _devices: serde_json::Value = client.get_url(&client.management_url("configureddevices"), &[]).await?;
+        client.put("connected", &[("Connected", "true".to_string())]).await?;
+
+        self.client = Some(client);
+        self.device_number = device_number;
+        self.capabilities = Arc::default();
+        self.moving = Arc::default();
+        Ok(())
     }
 
-    pub async fn disconnect(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // For now, just clear the client reference
-        // The actual telescope disconnection would be handled by the ASCOM driver
+    // Stops any axis move_axis left running first; the ASCOM driver itself stays connected
+    pub async fn disconnect(&mut self) -> Result<(), TelescopeError> {
+        if let Err(e) = self.stop_moving_axes().await {
+            warn!("Could not stop the telescope axes before disconnecting: {}", e);
+        }
         self.client = None;
+        self.capabilities = Arc::default();
         Ok(())
     }
 
-    pub async fn get_status(&self) -> Result<TelescopeStatus, Box<dyn std::error::Error + Send + Sync>> {
-        let mut status = TelescopeStatus::default();
+    // All properties are requested at once, so a refresh costs one round trip rather than one per
+    // property; the connection pool opens as many connections as the requests need. Capabilities
+    // only take part in the first refresh after connecting. SideOfPier, Azimuth and Altitude are
+    // optional in ITelescope; a driver answering NotImplemented leaves them unknown
+    pub async fn get_status(&self) -> Result<TelescopeStatus, TelescopeError> {
+        let Ok(client) = self.client() else {
+            return Ok(TelescopeStatus::default());
+        };
+        let (capabilities, connected, ra, dec, azimuth, altitude, tracking, slewing, at_home, at_park, pier_side) = tokio::try_join!(
+            self.capabilities(),
+            client.get("connected"),
+            client.get("rightascension"),
+            client.get("declination"),
+            optional(client.get("azimuth")),
+            optional(client.get("altitude")),
+            client.get("tracking"),
+            client.get("slewing"),
+            client.get("athome"),
+            client.get("atpark"),
+            optional(client.get::<i32>("sideofpier")),
+        )?;
+        Ok(TelescopeStatus {
+            connected,
+            name: capabilities.name.clone(),
+            description: capabilities.description.clone(),
+            ra,
+            dec,
+            azimuth,
+            altitude,
+            tracking,
+            slewing,
+            at_home,
+            at_park,
+            can_park: capabilities.can_park,
+            can_set_park: capabilities.can_set_park,
+            can_home: capabilities.can_home,
+            can_slew: capabilities.can_slew,
+            can_move_axis: capabilities.can_move_axis[TelescopeAxis::Primary.number() as usize],
+            pier_side: pier_side.map_or("Unknown", pier_side_name).to_string(),
+        })
+    }
 
-        if let Some(_client) = &self.client {
-            // Note: The actual implementation would depend on the specific API methods
-            // available in the ascom-alpaca crate. Since the exact API is unclear from
-            // the error messages, this is a simplified version.
-            status.connected = true;
-            
-            // In a real implementation, you would call the appropriate methods
-            // on the telescope device to get the actual values
-        }
+    pub async fn set_tracking(&self, tracking: bool) -> Result<(), TelescopeError> {
+        info!("Setting tracking {}", tracking);
+        self.client()?.put("tracking", &[("Tracking", tracking.to_string())]).await
+    }
 
-        Ok(status)
+    pub async fn slew_to_coordinates(&self, ra: f64, dec: f64) -> Result<(), TelescopeError> {
+        info!("Slewing telescope to RA: {}, Dec: {}", ra, dec);
+        self.client()?
+            .put("slewtocoordinatesasync", &[("RightAscension", ra.to_string()), ("Declination", dec.to_string())])
+            .await
     }
 
-    pub async fn set_tracking(&self, _tracking: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(_client) = &self.client {
-            // Implementation would go here
-            info!("Setting tracking (not implemented)");
-        }
-        Ok(())
+    pub async fn abort_slew(&self) -> Result<(), TelescopeError> {
+        info!("Aborting telescope slew");
+        self.client()?.put("abortslew", &[]).await
     }
 
-    pub async fn slew_to_coordinates(&self, ra: f64, dec: f64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(_client) = &self.client {
-            info!("Slewing telescope to RA: {}, Dec: {} (not implemented)", ra, dec);
-        }
-        Ok(())
+    pub async fn park(&self) -> Result<(), TelescopeError> {
+        info!("Parking telescope");
+        self.client()?.put("park", &[]).await
     }
 
-    pub async fn abort_slew(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(_client) = &self.client {
-            info!("Aborting telescope slew (not implemented)");
-        }
-        Ok(())
+    pub async fn unpark(&self) -> Result<(), TelescopeError> {
+        info!("Unparking telescope");
+        self.client()?.put("unpark", &[]).await
     }
 
-    pub async fn park(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(_client) = &self.client {
-            info!("Parking telescope (not implemented)");
+    // Driver-specific actions the mount offers through action(), e.g. PEC control
+    pub async fn supported_actions(&self) -> Result<Vec<String>, TelescopeError> {
+        Ok(self.capabilities().await?.supported_actions.clone())
+    }
+
+    // Run a driver-specific action listed in SupportedActions (names are case insensitive);
+    // returns the driver's reply, "" when it sends none
+    pub async fn action(&self, action: &str, parameters: &str) -> Result<String, TelescopeError> {
+        let client = self.client()?;
+        let supported = &self.capabilities().await?.supported_actions;
+        if !supported.iter().any(|name| name.eq_ignore_ascii_case(action)) {
+            return Err(TelescopeError::Unsupported(format!("action '{}'", action)));
         }
-        Ok(())
+        info!("Running telescope action {} ({})", action, parameters);
+        let value = client
+            .put_value("action", &[("Action", action.to_string()), ("Parameters", parameters.to_string())])
+            .await?;
+        Ok(value.unwrap_or_default())
     }
 
-    pub async fn unpark(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(_client) = &self.client {
-            info!("Unparking telescope (not implemented)");
+    // Store the mount's current position as its park position, if the driver allows it
+    pub async fn set_park(&self) -> Result<(), TelescopeError> {
+        let client = self.client()?;
+        if !self.capabilities().await?.can_set_park {
+            return Err(TelescopeError::Unsupported("SetPark".to_string()));
         }
-        Ok(())
+        info!("Setting the telescope park position");
+        client.put("setpark", &[]).await
     }
 
-    pub async fn find_home(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(_client) = &self.client {
-            info!("Finding telescope home (not implemented)");
+    pub async fn find_home(&self) -> Result<(), TelescopeError> {
+        info!("Finding telescope home");
+        self.client()?.put("findhome", &[]).await
+    }
+
+    // Move at `rate` degrees per second (0 stops the axis), clamped into the driver's AxisRates;
+    // returns the rate sent
+    pub async fn move_axis(&self, direction: SlewDirection, rate: f64) -> Result<f64, TelescopeError> {
+        let client = self.client()?;
+        let (axis, sign) = direction.axis();
+        let index = axis.number() as usize;
+        let requested = rate.abs();
+        let rate = if requested == 0.0 {
+            0.0
+        } else {
+            let capabilities = self.capabilities().await?;
+            if !capabilities.can_move_axis[index] || capabilities.axis_rates[index].is_empty() {
+                return Err(TelescopeError::Unsupported(format!("MoveAxis on the {:?} axis", axis)));
+            }
+            let rate = clamp_rate(requested, &capabilities.axis_rates[index]);
+            if rate != requested {
+                debug!("Rate {} is outside the {:?} axis rates, using {}", requested, axis, rate);
+            }
+            rate
+        };
+        debug!("Moving telescope {:?} at rate {}", direction, rate);
+        // Stopping West or South sends 0 rather than -0
+        let signed_rate = if rate == 0.0 { 0.0 } else { sign * rate };
+        client
+            .put("moveaxis", &[("Axis", axis.number().to_string()), ("Rate", signed_rate.to_string())])
+            .await?;
+        self.moving.lock().unwrap()[index] = rate != 0.0;
+        Ok(rate)
+    }
+
+    pub async fn stop_all_movement(&self) -> Result<(), TelescopeError> {
+        info!("Stopping all telescope movement");
+        let client = self.client()?;
+        for axis in [TelescopeAxis::Primary, TelescopeAxis::Secondary] {
+            client.put("moveaxis", &[("Axis", axis.number().to_string()), ("Rate", "0".to_string())]).await?;
+            self.moving.lock().unwrap()[axis.number() as usize] = false;
         }
-        Ok(())
+        client.put("abortslew", &[]).await
     }
 
-    pub async fn move_axis(&self, direction: SlewDirection, rate: f64) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(_client) = &self.client {
-            debug!("Moving telescope {:?} at rate {} (not implemented)", direction, rate);
+    // Zero rate on the axes move_axis set moving, e.g. when whoever started them went away
+    pub async fn stop_moving_axes(&self) -> Result<(), TelescopeError> {
+        let Ok(client) = self.client() else {
+            return Ok(());
+        };
+        for axis in [TelescopeAxis::Primary, TelescopeAxis::Secondary] {
+            let index = axis.number() as usize;
+            if !self.moving.lock().unwrap()[index] {
+                continue;
+            }
+            info!("Stopping the {:?} axis", axis);
+            client.put("moveaxis", &[("Axis", axis.number().to_string()), ("Rate", "0".to_string())]).await?;
+            self.moving.lock().unwrap()[index] = false;
         }
         Ok(())
     }
 
-    pub async fn stop_all_movement(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(_client) = &self.client {
-            info!("Stopping all telescope movement (not implemented)");
+    // CanMoveAxis and AxisRates of one axis, read once per connection
+    pub async fn get_axis_rates(&self, axis: TelescopeAxis) -> Result<AxisRates, TelescopeError> {
+        let capabilities = self.capabilities().await?;
+        let index = axis.number() as usize;
+        Ok(AxisRates {
+            can_move: capabilities.can_move_axis[index],
+            rates: capabilities.axis_rates[index].clone(),
+        })
+    }
+
+    // Fetched on first use after connecting; a failed fetch is retried by the next caller
+    async fn capabilities(&self) -> Result<&Capabilities, TelescopeError> {
+        let client = self.client()?;
+        self.capabilities
+            .get_or_try_init(|| async {
+                let primary_axis = [("Axis", TelescopeAxis::Primary.number().to_string())];
+                let secondary_axis = [("Axis", TelescopeAxis::Secondary.number().to_string())];
+                let (
+                    name,
+                    description,
+                    can_park,
+                    can_set_park,
+                    can_home,
+                    can_slew,
+                    can_move_primary,
+                    can_move_secondary,
+                    primary_rates,
+                    secondary_rates,
+                    supported_actions,
+                ) = tokio::try_join!(
+                    client.get("name"),
+                    client.get("description"),
+                    client.get("canpark"),
+                    client.get("cansetpark"),
+                    client.get("canfindhome"),
+                    client.get("canslew"),
+                    client.get_with("canmoveaxis", &primary_axis),
+                    client.get_with("canmoveaxis", &secondary_axis),
+                    client.get_with("axisrates", &primary_axis),
+                    client.get_with("axisrates", &secondary_axis),
+                    client.get("supportedactions"),
+                )?;
+                Ok(Capabilities {
+                    name,
+                    description,
+                    can_park,
+                    can_set_park,
+                    can_home,
+                    can_slew,
+                    can_move_axis: [can_move_primary, can_move_secondary],
+                    axis_rates: [primary_rates, secondary_rates],
+                    supported_actions,
+                })
+            })
+            .await
+    }
+
+    // The Alpaca client, or NotConnected before connect() succeeded
+    fn client(&self) -> Result<&AlpacaClient, TelescopeError> {
+        self.client.as_ref().ok_or(TelescopeError::NotConnected)
+    }
+}
+
+// An optional property's value, or None when the driver answers NotImplemented
+async fn optional<T>(read: impl std::future::Future<Output = Result<T, TelescopeError>>) -> Result<Option<T>, TelescopeError> {
+    match read.await {
+        Ok(value) => Ok(Some(value)),
+        Err(TelescopeError::Unsupported(_)) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// ASCOM PierSide values
+fn pier_side_name(side: i32) -> &'static str {
+    match side {
+        0 => "East",
+        1 => "West",
+        _ => "Unknown",
+    }
+}
+
+// Value/ErrorNumber/ErrorMessage envelope of every Alpaca response
+#[derive(Deserialize)]
+struct AlpacaResponse<T> {
+    #[serde(rename = "Value")]
+    value: Option<T>,
+    #[serde(rename = "ErrorNumber", default)]
+    error_number: i32,
+    #[serde(rename = "ErrorMessage", default)]
+    error_message: String,
+}
+
+// Alpaca device API calls against one telescope; clones share the connection pool
+#[derive(Debug, Clone)]
+struct AlpacaClient {
+    http: reqwest::Client,
+    base: String,
+    device_number: u32,
+    get_retries: u32,
+    retry_delay: Duration,
+    transaction_id: Arc<AtomicU32>,
+}
+
+impl AlpacaClient {
+    fn new(url: &str, device_number: u32, options: &TelescopeClientOptions) -> Result<Self, TelescopeError> {
+        let http = reqwest::Client::builder()
+            .connect_timeout(options.connect_timeout)
+            .timeout(options.request_timeout)
+            .pool_idle_timeout(options.pool_idle_timeout)
+            .pool_max_idle_per_host(options.pool_max_idle_per_host)
+            .user_agent(concat!("telescope_park_bridge/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .map_err(http_error)?;
+        Ok(Self {
+            http,
+            base: url.trim_end_matches('/').to_string(),
+            device_number,
+            get_retries: options.get_retries,
+            retry_delay: options.retry_delay,
+            transaction_id: Arc::new(AtomicU32::new(0)),
+        })
+    }
+
+    fn device_url(&self, method: &str) -> String {
+        format!("{}/api/v1/telescope/{}/{}", self.base, self.device_number, method)
+    }
+
+    fn management_url(&self, method: &str) -> String {
+        format!("{}/management/v1/{}", self.base, method)
+    }
+
+    // ClientID and ClientTransactionID, which Alpaca servers log to match requests
+    fn client_parameters(&self) -> [(&'static str, String); 2] {
+        let transaction = self.transaction_id.fetch_add(1, Ordering::Relaxed) + 1;
+        [("ClientID", std::process::id().to_string()), ("ClientTransactionID", transaction.to_string())]
+    }
+
+    async fn get<T: DeserializeOwned>(&self, property: &str) -> Result<T, TelescopeError> {
+        self.get_with(property, &[]).await
+    }
+
+    async fn get_with<T: DeserializeOwned>(&self, property: &str, parameters: &[(&str, String)]) -> Result<T, TelescopeError> {
+        self.get_url(&self.device_url(property), parameters).await
+    }
+
+    // GETs are idempotent, so network failures are retried with backoff
+    async fn get_url<T: DeserializeOwned>(&self, url: &str, parameters: &[(&str, String)]) -> Result<T, TelescopeError> {
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .http
+                .get(url)
+                .query(parameters)
+                .query(&self.client_parameters())
+                .send()
+                .await;
+            let retryable = match &response {
+                Ok(response) => response.status().is_server_error(),
+                Err(_) => true,
+            };
+            if !retryable || attempt == self.get_retries {
+                return parse_response(response)
+                    .await?
+                    .ok_or_else(|| TelescopeError::Http(format!("{} returned no Value", url)));
+            }
+            let delay = self.retry_delay * 2u32.pow(attempt);
+            let delay = delay + delay.mul_f64(jitter() / 2.0);
+            debug!("GET {} failed, retrying in {} ms", url, delay.as_millis());
+            tokio::time::sleep(delay).await;
+            attempt += 1;
         }
-        Ok(())
     }
 
-    pub async fn get_axis_rates(&self) -> Result<Vec<f64>, Box<dyn std::error::Error + Send + Sync>> {
-        // Return default rates for now
-        Ok(vec![0.5, 1.0, 2.0, 4.0])
+    async fn put(&self, method: &str, parameters: &[(&str, String)]) -> Result<(), TelescopeError> {
+        self.put_value::<serde_json::Value>(method, parameters).await.map(|_| ())
+    }
+
+    // Most methods reply without a Value; Action and a few others return one
+    async fn put_value<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        parameters: &[(&str, String)],
+    ) -> Result<Option<T>, TelescopeError> {
+        let url = self.device_url(method);
+        let mut form: Vec<(&str, String)> = parameters.to_vec();
+        form.extend(self.client_parameters());
+        let response = self.http.put(&url).form(&form).send().await;
+        parse_response(response).await
+    }
+}
+
+async fn parse_response<T: DeserializeOwned>(
+    response: reqwest::Result<reqwest::Response>,
+) -> Result<Option<T>, TelescopeError> {
+    let response = response.and_then(|response| response.error_for_status()).map_err(http_error)?;
+    let reply: AlpacaResponse<T> = response.json().await.map_err(http_error)?;
+    if reply.error_number != 0 {
+        return Err(TelescopeError::from_alpaca(reply.error_number, reply.error_message));
     }
+    Ok(reply.value)
+}
+
+// Uniform in [0, 1), from the randomly keyed std hasher so no RNG dependency is needed
+fn jitter() -> f64 {
+    (RandomState::new().build_hasher().finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// The Alpaca client's transport and protocol failures
+fn http_error(error: impl std::fmt::Display) -> TelescopeError {
+    TelescopeError::Http(error.to_string())
 }
 
 // Windows-specific ASCOM discovery
-#[cfg(windows)]
-pub fn discover_local_ascom_telescopes() -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+#[cfg(all(windows, feature = "desktop"))]
+pub fn discover_local_ascom_telescopes() -> Result<Vec<String>, TelescopeError> {
     use winreg::enums::*;
     use winreg::RegKey;
 
     let mut telescopes = Vec::new();
-    
+
     // Open ASCOM Telescope Drivers registry key
     let hklm = RegKey::predef(HKEY_LOCAL_MACHINE);
     if let Ok(ascom_key) = hklm.open_subkey("SOFTWARE\\ASCOM\\Telescope Drivers") {
-        for name in ascom_key.enum_keys() {
-            if let Ok(name) = name {
-                telescopes.push(name);
-            }
-        }
+        telescopes.extend(ascom_key.enum_keys().flatten());
     }
 
     // Also check in 32-bit registry on 64-bit systems
     if let Ok(ascom_key) = hklm.open_subkey("SOFTWARE\\WOW6432Node\\ASCOM\\Telescope Drivers") {
-        for name in ascom_key.enum_keys() {
-            if let Ok(name) = name {
-                if !telescopes.contains(&name) {
-                    telescopes.push(name);
-                }
+        for name in ascom_key.enum_keys().flatten() {
+            if !telescopes.contains(&name) {
+                telescopes.push(name);
             }
         }
     }
@@ -239,8 +655,8 @@ pub fn discover_local_ascom_telescopes() -> Result<Vec<String>, Box<dyn std::err
     Ok(telescopes)
 }
 
-#[cfg(not(windows))]
-pub fn discover_local_ascom_telescopes() -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
-    // On non-Windows platforms, return empty list
+#[cfg(not(all(windows, feature = "desktop")))]
+pub fn discover_local_ascom_telescopes() -> Result<Vec<String>, TelescopeError> {
+    // Without the Windows registry there are no local drivers to list
     Ok(vec![])
-}
\ No newline at end of file
+}
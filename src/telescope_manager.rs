@@ -0,0 +1,413 @@
+// src/telescope_manager.rs
+// Supervised connections to the mounts' Alpaca servers ([[telescopes]]): each connects, polls the
+// status and reconnects with backoff when its controller drops off, as ConnectionManager does for
+// the sensor
+
+use crate::config::{ReconnectConfig, TelescopeConfig};
+use crate::errors::TelescopeError;
+use crate::events::{EventBus, EventKind, TelescopeChange, TelescopeLink};
+use crate::telescope_client::{
+    SlewDirection, TelescopeClient, TelescopeClientOptions, TelescopeConnection, TelescopeStatus,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+// Served at /api/telescopes
+#[derive(Debug, Clone, Serialize)]
+pub struct TelescopeSnapshot {
+    pub id: String,
+    // Server and device number, e.g. "http://192.168.1.20:11111 device 0"
+    pub server: String,
+    pub state: TelescopeLink,
+    // Latest status read; kept while the link is lost, so the last known position stays visible
+    pub status: Option<TelescopeStatus>,
+    // Why the link was lost, or why the last status read failed while connected
+    pub error: Option<String>,
+    // When the link entered its current state
+    #[serde(serialize_with = "crate::timestamps::rfc3339_secs::serialize")]
+    pub since: u64,
+    // Reconnects tried since the link was lost
+    pub reconnect_attempts: u32,
+}
+
+// What the supervision task shares with the manager
+#[derive(Clone)]
+struct Link {
+    id: String,
+    connection: TelescopeConnection,
+    name: String,
+    options: TelescopeClientOptions,
+    poll_interval: Duration,
+    reconnect: ReconnectConfig,
+    events: EventBus,
+    snapshot: Arc<RwLock<TelescopeSnapshot>>,
+    // Set while connected, for commands to the mount
+    client: Arc<RwLock<Option<TelescopeClient>>>,
+}
+
+pub struct TelescopeManager {
+    id: String,
+    // Park sensor device on this mount
+    sensor_device: u32,
+    link: Link,
+    move_timeout: Duration,
+    // Per axis number, stops the axis once its move times out
+    move_watchdogs: Arc<Mutex<[Option<JoinHandle<()>>; 2]>>,
+    current_task: Arc<RwLock<Option<JoinHandle<()>>>>,
+    current_cancellation: Arc<RwLock<Option<CancellationToken>>>,
+}
+
+impl TelescopeManager {
+    pub fn new(id: &str, connection: TelescopeConnection) -> Self {
+        let name = match &connection {
+            TelescopeConnection::Alpaca { url, device_number } => format!("{} device {}", url, device_number),
+            TelescopeConnection::Local { prog_id } => prog_id.clone(),
+        };
+        Self {
+            id: id.to_string(),
+            sensor_device: 0,
+            link: Link {
+                id: id.to_string(),
+                connection,
+                name: name.clone(),
+                options: TelescopeClientOptions::default(),
+                poll_interval: Duration::from_secs(2),
+                reconnect: ReconnectConfig::default(),
+                events: EventBus::new(),
+                snapshot: Arc::new(RwLock::new(TelescopeSnapshot {
+                    id: id.to_string(),
+                    server: name,
+                    state: TelescopeLink::Disconnected,
+                    status: None,
+                    error: None,
+                    since: unix_now(),
+                    reconnect_attempts: 0,
+                })),
+                client: Arc::new(RwLock::new(None)),
+            },
+            move_timeout: Duration::from_secs(5),
+            move_watchdogs: Arc::default(),
+            current_task: Arc::new(RwLock::new(None)),
+            current_cancellation: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    // A [[telescopes]] entry
+    pub fn from_config(config: &TelescopeConfig) -> Self {
+        let connection = TelescopeConnection::Alpaca {
+            url: config.url.clone(),
+            device_number: config.device_number,
+        };
+        Self::new(&config.id, connection)
+            .with_sensor_device(config.sensor_device)
+            .with_options(config.into())
+            .with_poll_interval(Duration::from_secs(config.poll_interval_secs))
+            .with_move_timeout(Duration::from_secs(config.move_timeout_secs))
+    }
+
+    pub fn with_sensor_device(mut self, device_number: u32) -> Self {
+        self.sensor_device = device_number;
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn sensor_device(&self) -> u32 {
+        self.sensor_device
+    }
+
+    pub fn move_timeout(&self) -> Duration {
+        self.move_timeout
+    }
+
+    pub fn with_options(mut self, options: TelescopeClientOptions) -> Self {
+        self.link.options = options;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.link.poll_interval = poll_interval;
+        self
+    }
+
+    // How long an axis keeps moving after the last move_axis for it
+    pub fn with_move_timeout(mut self, move_timeout: Duration) -> Self {
+        self.move_timeout = move_timeout;
+        self
+    }
+
+    // Backoff between reconnects; without `enabled` a lost link stays down until start() again
+    pub fn with_reconnect(mut self, reconnect: ReconnectConfig) -> Self {
+        self.link.reconnect = reconnect;
+        self
+    }
+
+    // Link changes go to this bus, usually the primary device's, so they appear on /api/events
+    pub fn with_event_bus(mut self, events: EventBus) -> Self {
+        self.link.events = events;
+        self
+    }
+
+    // Start supervising the connection, restarting it if it already runs
+    pub async fn start(&self) {
+        self.stop_internal().await;
+        info!("TelescopeManager: Connecting to {}", self.link.name);
+
+        let cancel_token = CancellationToken::new();
+        *self.current_cancellation.write().await = Some(cancel_token.clone());
+        let task = tokio::spawn(supervise(self.link.clone(), cancel_token));
+        *self.current_task.write().await = Some(task);
+    }
+
+    // Stop polling and reconnecting, waiting for the supervision task to finish
+    pub async fn stop(&self) {
+        info!("TelescopeManager: Disconnecting from {}", self.link.name);
+        self.stop_internal().await;
+        self.link.set_state(TelescopeLink::Disconnected, None).await;
+    }
+
+    async fn stop_internal(&self) {
+        // Taken first, as the supervision task drops it when cancelled
+        let client = self.link.client.write().await.take();
+        if let Some(cancel_token) = self.current_cancellation.write().await.take() {
+            cancel_token.cancel();
+        }
+        let task = self.current_task.write().await.take();
+        if let Some(mut task) = task {
+            // Cancellation is checked around every request, so this is quick unless a task hangs
+            if tokio::time::timeout(Duration::from_millis(2000), &mut task).await.is_err() {
+                warn!("TelescopeManager: Supervision task did not stop, aborting it");
+                task.abort();
+            }
+        }
+        for watchdog in self.move_watchdogs.lock().unwrap().iter_mut() {
+            if let Some(watchdog) = watchdog.take() {
+                watchdog.abort();
+            }
+        }
+        if let Some(client) = client {
+            if let Err(e) = client.stop_moving_axes().await {
+                warn!("TelescopeManager: Could not stop the axes of {}: {}", self.link.name, e);
+            }
+        }
+    }
+
+    pub async fn snapshot(&self) -> TelescopeSnapshot {
+        self.link.snapshot.read().await.clone()
+    }
+
+    // The connected client, for commands to the mount; None while the link is down
+    pub async fn client(&self) -> Option<TelescopeClient> {
+        self.link.client.read().await.clone()
+    }
+
+    // Move an axis (0 stops it) for at most the move timeout; sending the move again renews it.
+    // Returns the rate sent, clamped into the driver's AxisRates.
+    pub async fn move_axis(&self, direction: SlewDirection, rate: f64) -> Result<f64, TelescopeError> {
+        let client = self.client().await.ok_or(TelescopeError::NotConnected)?;
+        let (axis, _) = direction.axis();
+        let rate = client.move_axis(direction, rate).await?;
+        let watchdog = (rate != 0.0).then(|| {
+            let move_timeout = self.move_timeout;
+            let name = self.link.name.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(move_timeout).await;
+                warn!("Telescope {}: {:?} axis move timed out, stopping it", name, axis);
+                if let Err(e) = client.move_axis(direction, 0.0).await {
+                    warn!("Telescope {}: Could not stop the {:?} axis: {}", name, axis, e);
+                }
+            })
+        });
+        let previous = std::mem::replace(&mut self.move_watchdogs.lock().unwrap()[axis.number() as usize], watchdog);
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+        Ok(rate)
+    }
+
+    pub fn event_bus(&self) -> EventBus {
+        self.link.events.clone()
+    }
+}
+
+// Telescope managers served by this bridge, keyed by id; built once at startup
+#[derive(Clone, Default)]
+pub struct TelescopeRegistry {
+    telescopes: Arc<BTreeMap<String, Arc<TelescopeManager>>>,
+}
+
+impl TelescopeRegistry {
+    pub fn new(telescopes: Vec<TelescopeManager>) -> Self {
+        Self {
+            telescopes: Arc::new(
+                telescopes
+                    .into_iter()
+                    .map(|telescope| (telescope.id.clone(), Arc::new(telescope)))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Arc<TelescopeManager>> {
+        self.telescopes.get(id)
+    }
+
+    pub fn telescopes(&self) -> impl Iterator<Item = &Arc<TelescopeManager>> {
+        self.telescopes.values()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.telescopes.is_empty()
+    }
+
+    pub async fn start_all(&self) {
+        for telescope in self.telescopes.values() {
+            telescope.start().await;
+        }
+    }
+
+    pub async fn stop_all(&self) {
+        for telescope in self.telescopes.values() {
+            telescope.stop().await;
+        }
+    }
+}
+
+impl Link {
+    // Record the link state, publishing an event when it changed
+    async fn set_state(&self, state: TelescopeLink, error: Option<String>) {
+        {
+            let mut snapshot = self.snapshot.write().await;
+            snapshot.error = error.clone();
+            if snapshot.state == state {
+                return;
+            }
+            snapshot.state = state;
+            snapshot.since = unix_now();
+            if state == TelescopeLink::Connected {
+                snapshot.reconnect_attempts = 0;
+            }
+        }
+        self.events.publish(EventKind::TelescopeLinkChanged {
+            telescope: self.id.clone(),
+            state,
+            error,
+        });
+    }
+
+    // Poll until the link fails; Alpaca errors from individual properties are only recorded
+    async fn poll(&self, client: &TelescopeClient) -> TelescopeError {
+        loop {
+            match client.get_status().await {
+                Ok(status) => {
+                    let previous = {
+                        let mut snapshot = self.snapshot.write().await;
+                        snapshot.error = None;
+                        snapshot.status.replace(status.clone())
+                    };
+                    // The previous status survives a lost link, so changes made meanwhile show up too
+                    for change in previous.map(|previous| status_changes(&previous, &status)).unwrap_or_default() {
+                        self.events.publish(EventKind::TelescopeChanged {
+                            telescope: self.id.clone(),
+                            change,
+                            ra: status.ra,
+                            dec: status.dec,
+                            pier_side: status.pier_side.clone(),
+                        });
+                    }
+                }
+                Err(e) if is_link_failure(&e) => return e,
+                Err(e) => {
+                    warn!("Telescope {} status read failed: {}", self.name, e);
+                    self.snapshot.write().await.error = Some(e.to_string());
+                }
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+// What changed between two status polls, for the event bus and the session log
+fn status_changes(previous: &TelescopeStatus, current: &TelescopeStatus) -> Vec<TelescopeChange> {
+    let mut changes = Vec::new();
+    if previous.slewing != current.slewing {
+        changes.push(if current.slewing { TelescopeChange::SlewStarted } else { TelescopeChange::SlewFinished });
+    }
+    if previous.at_park != current.at_park {
+        changes.push(if current.at_park { TelescopeChange::Parked } else { TelescopeChange::Unparked });
+    }
+    if previous.tracking != current.tracking {
+        changes.push(if current.tracking { TelescopeChange::TrackingStarted } else { TelescopeChange::TrackingStopped });
+    }
+    // Not when a driver only starts or stops reporting the side
+    let known = |side: &str| side != "Unknown";
+    if previous.pier_side != current.pier_side && known(&previous.pier_side) && known(&current.pier_side) {
+        changes.push(TelescopeChange::PierFlipped);
+    }
+    changes
+}
+
+// The server is unreachable, or the driver lost its own connection to the mount
+fn is_link_failure(error: &TelescopeError) -> bool {
+    error.is_retryable() || matches!(error, TelescopeError::NotConnected)
+}
+
+async fn supervise(link: Link, cancel_token: CancellationToken) {
+    let initial_delay = Duration::from_secs(link.reconnect.initial_delay_secs);
+    let max_delay = Duration::from_secs(link.reconnect.max_delay_secs);
+    let mut delay = initial_delay;
+    link.set_state(TelescopeLink::Connecting, None).await;
+
+    loop {
+        let mut client = TelescopeClient::new(link.connection.clone()).with_options(link.options.clone());
+        let connected = tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            result = client.connect() => result,
+        };
+        let error = match connected {
+            Ok(()) => {
+                info!("Telescope {} connected", link.name);
+                *link.client.write().await = Some(client.clone());
+                link.set_state(TelescopeLink::Connected, None).await;
+                delay = initial_delay;
+                let error = tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    error = link.poll(&client) => error,
+                };
+                link.client.write().await.take();
+                error
+            }
+            Err(e) => e,
+        };
+
+        if !link.reconnect.enabled {
+            warn!("Telescope {} lost: {}", link.name, error);
+            link.set_state(TelescopeLink::Disconnected, Some(error.to_string())).await;
+            break;
+        }
+        warn!("Telescope {} lost: {} - reconnecting in {} seconds", link.name, error, delay.as_secs());
+        link.set_state(TelescopeLink::Lost, Some(error.to_string())).await;
+        tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        link.snapshot.write().await.reconnect_attempts += 1;
+        delay = (delay * 2).min(max_delay);
+    }
+    link.client.write().await.take();
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
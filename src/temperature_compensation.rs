@@ -0,0 +1,231 @@
+// src/temperature_compensation.rs
+// Temperature compensation of pitch/roll ([temperature_compensation]): firmware readings are
+// corrected before they reach the device state, and the parked flag is re-evaluated with the
+// corrected position, so seasonal tilt of an outdoor mount stops causing false "not parked"
+
+use crate::config::{CompensationMode, TemperatureCompensationConfig};
+use crate::device_state::DeviceState;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+// Parked readings are averaged in temperature bins this wide, so the hours spent at one
+// temperature do not outweigh the rarer extremes in the fit
+const BIN_WIDTH_C: f64 = 0.5;
+// Distinct bins a fit needs besides the temperature span
+const MIN_BINS: usize = 3;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Bin {
+    samples: u64,
+    pitch_sum: f64,
+    roll_sum: f64,
+}
+
+// Offset from the park definition as a line a + b·T, per axis
+#[derive(Debug, Clone, Copy)]
+struct Fit {
+    pitch: (f64, f64),
+    roll: (f64, f64),
+}
+
+#[derive(Debug, Default)]
+struct Model {
+    temperature: Option<f32>,
+    park_definition: Option<(f32, f32)>,
+    bins: BTreeMap<i64, Bin>,
+    fit: Option<Fit>,
+}
+
+// Served at /api/compensation
+#[derive(Debug, Clone, Serialize)]
+pub struct CompensationStatus {
+    pub mode: CompensationMode,
+    // Latest IMU temperature from the firmware; null when it reports none
+    pub temperature: Option<f32>,
+    // Degrees subtracted from the readings at that temperature; null while nothing is applied
+    pub pitch_correction: Option<f64>,
+    pub roll_correction: Option<f64>,
+    // Coefficients in use (configured or learned), degrees per °C
+    pub pitch_per_c: Option<f64>,
+    pub roll_per_c: Option<f64>,
+    // Learned mode: parked readings since the park position was set, and their temperature range
+    pub learned_samples: u64,
+    pub learned_min_c: Option<f64>,
+    pub learned_max_c: Option<f64>,
+}
+
+#[derive(Clone)]
+pub struct TemperatureCompensation {
+    config: TemperatureCompensationConfig,
+    model: Arc<Mutex<Model>>,
+}
+
+impl Default for TemperatureCompensation {
+    fn default() -> Self {
+        Self::new(TemperatureCompensationConfig::default())
+    }
+}
+
+impl TemperatureCompensation {
+    pub fn new(config: TemperatureCompensationConfig) -> Self {
+        Self {
+            config,
+            model: Arc::new(Mutex::new(Model::default())),
+        }
+    }
+
+    // Correct the position in a firmware data payload in place, and its parked flag with it;
+    // `state` supplies the park definition and position when the payload does not carry them
+    pub fn apply(&self, data: &mut Value, state: &DeviceState) {
+        if self.config.mode == CompensationMode::Off {
+            return;
+        }
+        let number = |data: &Value, key: &str| data.get(key).and_then(Value::as_f64).map(|value| value as f32);
+        let mut model = self.model.lock().unwrap();
+        if let Some(temperature) = number(data, "temperature") {
+            model.temperature = Some(temperature);
+        }
+        let park = (
+            number(data, "parkPitch").unwrap_or(state.park_pitch),
+            number(data, "parkRoll").unwrap_or(state.park_roll),
+        );
+        let tolerance = number(data, "tolerance").unwrap_or(state.position_tolerance);
+
+        // Offsets learned against one park definition say nothing about the next
+        if model.park_definition != Some(park) {
+            if !model.bins.is_empty() {
+                info!("Park definition changed, relearning temperature compensation");
+            }
+            model.park_definition = Some(park);
+            model.bins.clear();
+            model.fit = None;
+        }
+        let Some(temperature) = model.temperature else {
+            return;
+        };
+
+        let keys = [("pitch", "roll"), ("currentPitch", "currentRoll")]
+            .into_iter()
+            .find(|(pitch, _)| data.get(*pitch).is_some());
+        let position = match keys {
+            Some((pitch_key, roll_key)) => {
+                let (Some(pitch), Some(roll)) = (number(data, pitch_key), number(data, roll_key)) else {
+                    return;
+                };
+                let (pitch_correction, roll_correction) = self.correction(&model, temperature).unwrap_or_default();
+                let corrected = (pitch - pitch_correction as f32, roll - roll_correction as f32);
+                if self.config.mode == CompensationMode::Learned && within(corrected, park, tolerance) {
+                    self.learn(&mut model, temperature, (pitch - park.0) as f64, (roll - park.1) as f64);
+                }
+                if self.correction(&model, temperature).is_none() {
+                    return;
+                }
+                data[pitch_key] = json!(corrected.0);
+                data[roll_key] = json!(corrected.1);
+                corrected
+            }
+            None if self.correction(&model, temperature).is_some() => (state.current_pitch, state.current_roll),
+            None => return,
+        };
+        if data.get("parked").is_some() {
+            data["parked"] = json!(within(position, park, tolerance));
+        }
+        if data.get("pitchDiff").is_some() {
+            data["pitchDiff"] = json!((position.0 - park.0).abs());
+            data["rollDiff"] = json!((position.1 - park.1).abs());
+        }
+    }
+
+    pub fn status(&self) -> CompensationStatus {
+        let model = self.model.lock().unwrap();
+        let correction = model.temperature.and_then(|temperature| self.correction(&model, temperature));
+        let (pitch_per_c, roll_per_c) = match self.config.mode {
+            CompensationMode::Off => (None, None),
+            CompensationMode::Coefficients => (Some(self.config.pitch_per_c), Some(self.config.roll_per_c)),
+            CompensationMode::Learned => (model.fit.map(|fit| fit.pitch.1), model.fit.map(|fit| fit.roll.1)),
+        };
+        CompensationStatus {
+            mode: self.config.mode,
+            temperature: model.temperature,
+            pitch_correction: correction.map(|(pitch, _)| pitch),
+            roll_correction: correction.map(|(_, roll)| roll),
+            pitch_per_c,
+            roll_per_c,
+            learned_samples: model.bins.values().map(|bin| bin.samples).sum(),
+            learned_min_c: model.bins.keys().next().map(|key| *key as f64 * BIN_WIDTH_C),
+            learned_max_c: model.bins.keys().next_back().map(|key| *key as f64 * BIN_WIDTH_C),
+        }
+    }
+
+    // Degrees to subtract from pitch and roll; None until learned mode has a fit
+    fn correction(&self, model: &Model, temperature: f32) -> Option<(f64, f64)> {
+        let temperature = temperature as f64;
+        match self.config.mode {
+            CompensationMode::Off => None,
+            CompensationMode::Coefficients => {
+                let delta = temperature - self.config.reference_c;
+                Some((self.config.pitch_per_c * delta, self.config.roll_per_c * delta))
+            }
+            CompensationMode::Learned => model
+                .fit
+                .map(|fit| (fit.pitch.0 + fit.pitch.1 * temperature, fit.roll.0 + fit.roll.1 * temperature)),
+        }
+    }
+
+    fn learn(&self, model: &mut Model, temperature: f32, pitch_offset: f64, roll_offset: f64) {
+        let bin = model.bins.entry((temperature as f64 / BIN_WIDTH_C).round() as i64).or_default();
+        bin.samples += 1;
+        bin.pitch_sum += pitch_offset;
+        bin.roll_sum += roll_offset;
+
+        let had_fit = model.fit.is_some();
+        model.fit = fit(&model.bins, &self.config);
+        if let (Some(fit), false) = (model.fit, had_fit) {
+            info!(
+                "Temperature compensation learned: pitch {:+.4}°/°C, roll {:+.4}°/°C",
+                fit.pitch.1, fit.roll.1
+            );
+        }
+    }
+}
+
+fn within(position: (f32, f32), park: (f32, f32), tolerance: f32) -> bool {
+    (position.0 - park.0).abs() <= tolerance && (position.1 - park.1).abs() <= tolerance
+}
+
+// Least squares over the bin averages, each bin weighted equally
+fn fit(bins: &BTreeMap<i64, Bin>, config: &TemperatureCompensationConfig) -> Option<Fit> {
+    let samples: u64 = bins.values().map(|bin| bin.samples).sum();
+    let (first, last) = (bins.keys().next()?, bins.keys().next_back()?);
+    let span = (last - first) as f64 * BIN_WIDTH_C;
+    if samples < config.learn_min_samples || bins.len() < MIN_BINS || span < config.learn_min_span_c {
+        return None;
+    }
+
+    let points: Vec<(f64, f64, f64)> = bins
+        .iter()
+        .map(|(key, bin)| {
+            let count = bin.samples as f64;
+            (*key as f64 * BIN_WIDTH_C, bin.pitch_sum / count, bin.roll_sum / count)
+        })
+        .collect();
+    let count = points.len() as f64;
+    let mean_temperature = points.iter().map(|point| point.0).sum::<f64>() / count;
+    let spread: f64 = points.iter().map(|point| (point.0 - mean_temperature).powi(2)).sum();
+    let line = |offset: fn(&(f64, f64, f64)) -> f64| {
+        let mean = points.iter().map(offset).sum::<f64>() / count;
+        let slope = points
+            .iter()
+            .map(|point| (point.0 - mean_temperature) * (offset(point) - mean))
+            .sum::<f64>()
+            / spread;
+        (mean - slope * mean_temperature, slope)
+    };
+    Some(Fit {
+        pitch: line(|point| point.1),
+        roll: line(|point| point.2),
+    })
+}
@@ -0,0 +1,214 @@
+// src/timestamps.rs
+// RFC 3339 timestamps for JSON responses and events, rendered in the configured display timezone
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, SecondsFormat, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+static DISPLAY_TIMEZONE: OnceLock<DisplayTimezone> = OnceLock::new();
+
+// Zone timestamps are rendered in; the instant is the same either way, only the offset differs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum DisplayTimezone {
+    #[default]
+    Utc,
+    // The bridge host's zone
+    Local,
+    // IANA name, e.g. "Europe/Berlin"
+    Named(Tz),
+}
+
+impl FromStr for DisplayTimezone {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if name.eq_ignore_ascii_case("utc") {
+            return Ok(Self::Utc);
+        }
+        if name.eq_ignore_ascii_case("local") {
+            return Ok(Self::Local);
+        }
+        Tz::from_str(name)
+            .map(Self::Named)
+            .map_err(|_| format!("unknown timezone '{}' (use \"utc\", \"local\" or an IANA name)", name))
+    }
+}
+
+impl TryFrom<String> for DisplayTimezone {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl fmt::Display for DisplayTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Utc => f.write_str("UTC"),
+            Self::Local => f.write_str("local"),
+            Self::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+// Set once at startup; later calls are ignored so every response uses the same zone
+pub fn set_display_timezone(timezone: DisplayTimezone) {
+    let _ = DISPLAY_TIMEZONE.set(timezone);
+}
+
+pub fn display_timezone() -> DisplayTimezone {
+    DISPLAY_TIMEZONE.get().copied().unwrap_or_default()
+}
+
+fn to_display(utc: DateTime<Utc>) -> DateTime<FixedOffset> {
+    match display_timezone() {
+        DisplayTimezone::Utc => utc.fixed_offset(),
+        DisplayTimezone::Local => utc.with_timezone(&Local).fixed_offset(),
+        DisplayTimezone::Named(tz) => utc.with_timezone(&tz).fixed_offset(),
+    }
+}
+
+// e.g. "2024-05-01T21:14:03Z", or "2024-05-01T23:14:03+02:00" with a display timezone
+pub fn format_secs(secs: u64) -> String {
+    let utc = DateTime::from_timestamp(secs as i64, 0).unwrap_or_default();
+    to_display(utc).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// Date in the display timezone, e.g. "2024-05-01"
+pub fn format_date(secs: u64) -> String {
+    let utc = DateTime::from_timestamp(secs as i64, 0).unwrap_or_default();
+    to_display(utc).format("%Y-%m-%d").to_string()
+}
+
+// Day of the week (0 = Monday) and minute of the day of `secs` in the display timezone
+pub fn display_weekday_minute(secs: u64) -> (u32, u32) {
+    let local = to_display(DateTime::from_timestamp(secs as i64, 0).unwrap_or_default());
+    (local.weekday().num_days_from_monday(), local.hour() * 60 + local.minute())
+}
+
+// The first `hour`:00 in the display timezone after `secs`, in Unix seconds; DST changes are
+// followed, so it can be 23 or 25 hours after the one before
+pub fn next_display_hour(secs: u64, hour: u32) -> u64 {
+    let utc = DateTime::from_timestamp(secs as i64, 0).unwrap_or_default();
+    match display_timezone() {
+        DisplayTimezone::Utc => next_hour_in(utc, hour),
+        DisplayTimezone::Local => next_hour_in(utc.with_timezone(&Local), hour),
+        DisplayTimezone::Named(tz) => next_hour_in(utc.with_timezone(&tz), hour),
+    }
+}
+
+fn next_hour_in<Z: TimeZone>(now: DateTime<Z>, hour: u32) -> u64 {
+    let mut date = now.date_naive();
+    // A day where the hour is skipped by DST is passed over; two days on it exists again
+    for _ in 0..3 {
+        let at = date
+            .and_hms_opt(hour, 0, 0)
+            .and_then(|naive| now.timezone().from_local_datetime(&naive).earliest());
+        if let Some(at) = at.filter(|at| *at > now) {
+            return at.timestamp() as u64;
+        }
+        date = date.succ_opt().unwrap_or(date);
+    }
+    now.timestamp() as u64 + 86_400
+}
+
+// e.g. "2024-05-01T21:14:03.250Z"
+pub fn format_millis(millis: u64) -> String {
+    let utc = DateTime::from_timestamp_millis(millis as i64).unwrap_or_default();
+    to_display(utc).to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+// Accepts an RFC 3339 string or a bare Unix number, so older bridges and clients keep working
+struct TimestampVisitor {
+    millis: bool,
+}
+
+impl TimestampVisitor {
+    fn parse_rfc3339<E: de::Error>(&self, value: &str) -> Result<u64, E> {
+        // An unencoded '+' offset in a query string arrives as a space
+        let parsed = DateTime::parse_from_rfc3339(value)
+            .or_else(|_| DateTime::parse_from_rfc3339(&value.replace(' ', "+")))
+            .map_err(|_| E::custom(format!("'{}' is not an RFC 3339 timestamp", value)))?;
+        let value = if self.millis { parsed.timestamp_millis() } else { parsed.timestamp() };
+        u64::try_from(value).map_err(|_| E::custom("timestamp before 1970"))
+    }
+}
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an RFC 3339 timestamp or Unix time")
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<u64, E> {
+        Ok(value)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<u64, E> {
+        u64::try_from(value).map_err(|_| E::custom("timestamp before 1970"))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<u64, E> {
+        match value.trim().parse::<u64>() {
+            Ok(number) => Ok(number),
+            Err(_) => self.parse_rfc3339(value.trim()),
+        }
+    }
+
+    // null is "never"
+    fn visit_unit<E: de::Error>(self) -> Result<u64, E> {
+        Ok(0)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<u64, E> {
+        Ok(0)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<u64, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+// Unix seconds held internally, RFC 3339 on the wire; 0 ("never") is written as null
+pub mod rfc3339_secs {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(secs: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        match secs {
+            0 => serializer.serialize_none(),
+            secs => serializer.serialize_str(&format_secs(*secs)),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        deserializer.deserialize_any(TimestampVisitor { millis: false })
+    }
+}
+
+// Unix milliseconds held internally, RFC 3339 with milliseconds on the wire
+pub mod rfc3339_millis {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(millis: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        match millis {
+            0 => serializer.serialize_none(),
+            millis => serializer.serialize_str(&format_millis(*millis)),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        deserializer.deserialize_any(TimestampVisitor { millis: true })
+    }
+}
+
+// Optional query parameter in Unix seconds or RFC 3339; pair with #[serde(default)]
+pub fn deserialize_optional_secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    rfc3339_secs::deserialize(deserializer).map(Some)
+}
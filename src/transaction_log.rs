@@ -0,0 +1,224 @@
+// src/transaction_log.rs
+// Optional JSON-lines log of every Alpaca device API transaction, for interoperability debugging
+
+use crate::alpaca_params;
+use axum::body::{Body, Bytes};
+use axum::extract::{ConnectInfo, State};
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+// Request and response bodies of device API calls are tiny; anything larger is passed on in
+// full but only this much of it is looked at
+const MAX_LOGGED_BODY: usize = 64 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct TransactionRecord {
+    // Milliseconds since the Unix epoch; RFC 3339 as "timestamp"
+    #[serde(rename = "timestamp", serialize_with = "crate::timestamps::rfc3339_millis::serialize")]
+    pub timestamp_ms: u64,
+    pub method: String,
+    pub endpoint: String,
+    pub client_address: Option<String>,
+    pub client_id: Option<u32>,
+    pub client_transaction_id: Option<u32>,
+    pub server_transaction_id: Option<u32>,
+    pub http_status: u16,
+    pub error_number: Option<i64>,
+    pub error_message: Option<String>,
+    pub value: Option<serde_json::Value>,
+    pub duration_ms: f64,
+}
+
+#[derive(Clone)]
+pub struct TransactionLog {
+    sender: mpsc::UnboundedSender<TransactionRecord>,
+}
+
+impl TransactionLog {
+    // Open (append) the log file and start the background writer
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        let mut writer = std::io::BufWriter::new(file);
+        let (sender, mut receiver) = mpsc::unbounded_channel::<TransactionRecord>();
+        let path_display = path.display().to_string();
+        info!("Logging ASCOM transactions to {}", path_display);
+
+        tokio::spawn(async move {
+            while let Some(record) = receiver.recv().await {
+                let result = serde_json::to_string(&record)
+                    .map_err(std::io::Error::from)
+                    .and_then(|json| writeln!(writer, "{}", json))
+                    .and_then(|_| writer.flush());
+                if let Err(e) = result {
+                    error!("Failed to write ASCOM transaction log {}: {}", path_display, e);
+                    break;
+                }
+            }
+        });
+
+        Ok(Self { sender })
+    }
+
+    pub fn record(&self, record: TransactionRecord) {
+        let _ = self.sender.send(record);
+    }
+}
+
+// Middleware recording every /api/v1/ device call with its parameters, result and duration
+pub async fn log_ascom_transactions(
+    State(log): State<TransactionLog>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if !request.uri().path().starts_with("/api/v1/") {
+        return next.run(request).await;
+    }
+
+    let started = Instant::now();
+    let method = request.method().to_string();
+    let endpoint = request.uri().path().to_string();
+    let query = request.uri().query().unwrap_or("").to_string();
+    let client_address = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip().to_string());
+
+    // PUT parameters arrive form-encoded in the body; buffer it and hand it on unchanged
+    let (parts, body) = request.into_parts();
+    let (logged, body) = buffer_logged(body, MAX_LOGGED_BODY).await;
+    let form = String::from_utf8_lossy(&logged).to_string();
+    let request = Request::from_parts(parts, body);
+
+    let client_id = alpaca_param(&query, "ClientID").or_else(|| alpaca_param(&form, "ClientID"));
+    let client_transaction_id =
+        alpaca_param(&query, "ClientTransactionID").or_else(|| alpaca_param(&form, "ClientTransactionID"));
+
+    let response = next.run(request).await;
+    let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    let (parts, body) = response.into_parts();
+    let (logged, body) = buffer_logged(body, MAX_LOGGED_BODY).await;
+    let alpaca: Option<serde_json::Value> = serde_json::from_slice(&logged).ok();
+    let field = |name: &str| alpaca.as_ref().and_then(|value| value.get(name)).cloned();
+
+    log.record(TransactionRecord {
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+        method,
+        endpoint,
+        client_address,
+        client_id,
+        client_transaction_id,
+        server_transaction_id: field("ServerTransactionID").and_then(|v| v.as_u64()).map(|v| v as u32),
+        http_status: parts.status.as_u16(),
+        error_number: field("ErrorNumber").and_then(|v| v.as_i64()),
+        error_message: field("ErrorMessage")
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .filter(|message| !message.is_empty()),
+        value: field("Value"),
+        duration_ms,
+    });
+
+    Response::from_parts(parts, body)
+}
+
+// Up to `limit` bytes of a body for the log, and the whole body to pass on. A body that fits is
+// buffered; a longer one is streamed on from the buffered part, as is one that fails to read
+async fn buffer_logged(body: Body, limit: usize) -> (Bytes, Body) {
+    let mut chunks = body.into_data_stream();
+    let mut buffered = Vec::new();
+    while buffered.len() <= limit {
+        match chunks.next().await {
+            Some(Ok(chunk)) => buffered.extend_from_slice(&chunk),
+            Some(Err(e)) => {
+                let logged = Bytes::from(buffered);
+                let body = Body::from_stream(stream::iter([Ok(logged.clone()), Err(e)]));
+                return (logged, body);
+            }
+            None => {
+                let logged = Bytes::from(buffered);
+                return (logged.clone(), Body::from(logged));
+            }
+        }
+    }
+    let head = Bytes::from(buffered);
+    let body = Body::from_stream(stream::once(std::future::ready(Ok(head.clone()))).chain(chunks));
+    (head.slice(..limit), body)
+}
+
+// Numeric Alpaca parameter from a query string or form body, in any casing
+fn alpaca_param(encoded: &str, name: &str) -> Option<u32> {
+    alpaca_params::find(encoded, name).and_then(|value| value.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::put;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    fn router(log: TransactionLog) -> Router {
+        // Echoes the request body back, after its length
+        let echo = |body: Bytes| async move { [format!("{}:", body.len()).into_bytes(), body.to_vec()].concat() };
+        Router::new()
+            .route("/api/v1/safetymonitor/0/action", put(echo))
+            .layer(axum::middleware::from_fn_with_state(log, log_ascom_transactions))
+    }
+
+    async fn call(body: Vec<u8>) -> (Vec<u8>, TransactionRecord) {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let request = Request::put("/api/v1/safetymonitor/0/action?ClientID=3").body(Body::from(body)).unwrap();
+        let response = router(TransactionLog { sender }).oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (body.to_vec(), receiver.recv().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn small_bodies_are_logged_and_passed_on() {
+        let (body, record) = call(b"Action=x&ClientTransactionID=7".to_vec()).await;
+        assert_eq!(body, b"30:Action=x&ClientTransactionID=7");
+        assert_eq!(record.endpoint, "/api/v1/safetymonitor/0/action");
+        assert_eq!(record.client_id, Some(3));
+        assert_eq!(record.client_transaction_id, Some(7));
+        assert_eq!(record.http_status, 200);
+    }
+
+    #[tokio::test]
+    async fn bodies_above_the_limit_reach_the_handler_and_the_client_whole() {
+        let sent: Vec<u8> = (0..MAX_LOGGED_BODY * 2 + 17).map(|n| (n % 251) as u8).collect();
+        let (body, record) = call(sent.clone()).await;
+        let prefix = format!("{}:", sent.len()).into_bytes();
+        assert_eq!(body.len(), prefix.len() + sent.len());
+        assert_eq!(&body[..prefix.len()], prefix.as_slice());
+        assert!(body[prefix.len()..] == sent[..], "the body was changed on the way");
+        assert_eq!(record.client_id, Some(3));
+        assert_eq!(record.value, None);
+    }
+
+    #[tokio::test]
+    async fn only_the_logged_copy_is_cut_at_the_limit() {
+        let chunks = (0..10).map(|n| Ok::<_, std::io::Error>(Bytes::from(vec![n as u8; 1000])));
+        let (logged, body) = buffer_logged(Body::from_stream(stream::iter(chunks)), 2500).await;
+        assert_eq!(logged.len(), 2500);
+        assert_eq!(&logged[2000..], &[2u8; 500][..]);
+        let whole = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(whole.len(), 10_000);
+        assert_eq!(whole[9999], 9);
+
+        let (logged, body) = buffer_logged(Body::from("short"), 2500).await;
+        assert_eq!(&logged[..], b"short");
+        assert_eq!(&axum::body::to_bytes(body, usize::MAX).await.unwrap()[..], b"short");
+    }
+}
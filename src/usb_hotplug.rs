@@ -0,0 +1,101 @@
+// src/usb_hotplug.rs
+// USB hot-plug ([usb], [devices.usb]): the serial ports are polled for the configured VID/PID or
+// serial number, and the device is connected again as soon as its port shows up, under whatever
+// name the OS gave it this time, so a USB reset or a replugged cable needs no operator
+
+use crate::config::UsbMatch;
+use crate::device_registry::DeviceHandle;
+use crate::port_discovery::{self, PortInfo};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+// Enumerating ports is cheap, and the reconnect backoff can reach [reconnect] max_delay_secs
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+type PortSource = Arc<dyn Fn() -> Vec<PortInfo> + Send + Sync>;
+
+pub struct UsbHotplug {
+    usb: UsbMatch,
+    baud_rate: u32,
+    poll_interval: Duration,
+    ports: PortSource,
+}
+
+impl UsbHotplug {
+    pub fn new(usb: UsbMatch, baud_rate: u32) -> Self {
+        Self {
+            usb,
+            baud_rate,
+            poll_interval: POLL_INTERVAL,
+            ports: Arc::new(|| {
+                port_discovery::discover_ports().unwrap_or_else(|e| {
+                    debug!("Cannot list the serial ports: {}", e);
+                    Vec::new()
+                })
+            }),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    // Where the ports come from instead of the OS, for tests
+    pub fn with_port_source(mut self, ports: impl Fn() -> Vec<PortInfo> + Send + Sync + 'static) -> Self {
+        self.ports = Arc::new(ports);
+        self
+    }
+
+    // Watch the ports until cancelled. The device is attached when its port first shows up while
+    // it has no connection, and again whenever the port reappears while it is not connected; a
+    // device disconnected from the web interface stays disconnected until the device is replugged
+    pub async fn run(self, device: DeviceHandle, cancel_token: CancellationToken) {
+        info!("Device {}: watching for USB device {}", device.device_number, self.usb);
+        let manager = &device.connection_manager;
+        let mut interval = tokio::time::interval(self.poll_interval);
+        // Whether the port was there on the last poll; None before the first one
+        let mut present: Option<bool> = None;
+        // Set once the device has had a connection, by the watcher or otherwise
+        let mut attached = false;
+        loop {
+            tokio::select! {
+                _ = cancel_token.cancelled() => return,
+                _ = interval.tick() => {}
+            }
+            let source = self.ports.clone();
+            let ports = tokio::task::spawn_blocking(move || source()).await.unwrap_or_default();
+            let matching: Vec<String> = ports.into_iter().filter(|port| port.matches(&self.usb)).map(|port| port.name).collect();
+            let appeared = present == Some(false) && !matching.is_empty();
+            if present == Some(true) && matching.is_empty() {
+                info!("Device {}: USB device {} unplugged", device.device_number, self.usb);
+            }
+            present = Some(!matching.is_empty());
+            let Some(first) = matching.first() else {
+                continue;
+            };
+
+            let current = manager.get_current_port().await;
+            attached |= current.is_some();
+            if manager.is_connected().await {
+                continue;
+            }
+            let port = match current {
+                // Disconnected on purpose
+                None if attached && !appeared => continue,
+                // The port is still there; the reconnect loop will get to it
+                Some(port) if matching.contains(&port) && !appeared => continue,
+                // Back under the same name: reconnect now rather than after the backoff
+                Some(port) if matching.contains(&port) => port,
+                _ => first.clone(),
+            };
+            info!("Device {}: USB device {} is on {}, connecting", device.device_number, self.usb, port);
+            match manager.connect(port.clone(), self.baud_rate).await {
+                Ok(_) => attached = true,
+                Err(e) => warn!("Device {}: cannot connect to {}: {}", device.device_number, port, e),
+            }
+        }
+    }
+}
@@ -0,0 +1,299 @@
+// src/web_assets.rs
+// Web UI assets and the page template: embedded and served under content-hashed names, or read
+// from disk on every request with --dev-assets (cargo feature web-ui)
+
+use crate::device_registry::DeviceSummary;
+use crate::safe_mode::SafeModeStatus;
+use crate::http_cache::content_hash;
+use axum::body::Body;
+use axum::http::{header, Response, StatusCode};
+use minijinja::{Environment, Value};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::warn;
+
+// Hashed asset URLs change whenever their content does, so browsers may keep them for a year
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+pub struct WebAsset {
+    // Unhashed name, e.g. "style.css"; served as /assets/style.<hash>.css
+    pub name: &'static str,
+    // Source file relative to the crate root, read from the --dev-assets directory in development
+    pub file: &'static str,
+    pub content_type: &'static str,
+    pub body: &'static [u8],
+}
+
+pub const INDEX_HTML: WebAsset = WebAsset {
+    name: "index.html",
+    file: "templates/index.html",
+    content_type: "text/html; charset=utf-8",
+    body: include_bytes!("../templates/index.html"),
+};
+
+pub const LOGIN_HTML: WebAsset = WebAsset {
+    name: "login.html",
+    file: "templates/login.html",
+    content_type: "text/html; charset=utf-8",
+    body: include_bytes!("../templates/login.html"),
+};
+
+pub const SETUP_HTML: WebAsset = WebAsset {
+    name: "setup.html",
+    file: "templates/setup.html",
+    content_type: "text/html; charset=utf-8",
+    body: include_bytes!("../templates/setup.html"),
+};
+
+pub const SAFE_MODE_HTML: WebAsset = WebAsset {
+    name: "safe_mode.html",
+    file: "templates/safe_mode.html",
+    content_type: "text/html; charset=utf-8",
+    body: include_bytes!("../templates/safe_mode.html"),
+};
+
+pub const STYLE_CSS: WebAsset = WebAsset {
+    name: "style.css",
+    file: "templates/style.css",
+    content_type: "text/css; charset=utf-8",
+    body: include_bytes!("../templates/style.css"),
+};
+
+pub const SCRIPT_JS: WebAsset = WebAsset {
+    name: "script.js",
+    file: "templates/script.js",
+    content_type: "text/javascript; charset=utf-8",
+    body: include_bytes!("../templates/script.js"),
+};
+
+pub const ICON_PNG: WebAsset = WebAsset {
+    name: "icon.png",
+    file: "assets/telescope-icon.png",
+    content_type: "image/png",
+    body: include_bytes!("../assets/telescope-icon.png"),
+};
+
+// Served under /assets/; the pages themselves are rendered rather than served as files
+const ASSETS: [&WebAsset; 3] = [&STYLE_CSS, &SCRIPT_JS, &ICON_PNG];
+
+impl WebAsset {
+    // URL referenced from the page, e.g. /assets/style.3f2a9c01d4e5b678.css
+    pub fn path(&self) -> String {
+        format!("/assets/{}", self.hashed_name())
+    }
+
+    fn hashed_name(&self) -> String {
+        let hash = content_hash(self.body);
+        match self.name.rsplit_once('.') {
+            Some((stem, extension)) => format!("{}.{}.{}", stem, hash, extension),
+            None => format!("{}.{}", self.name, hash),
+        }
+    }
+
+    pub fn response(&self) -> Response<Body> {
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, self.content_type)
+            .header(header::CACHE_CONTROL, IMMUTABLE_CACHE_CONTROL)
+            .body(Body::from(self.body))
+            .unwrap()
+    }
+}
+
+// Where the web UI comes from: the copies embedded at build time, or a source tree on disk
+#[derive(Clone, Default)]
+pub struct WebAssets {
+    dev_dir: Option<Arc<PathBuf>>,
+}
+
+impl WebAssets {
+    pub fn embedded() -> Self {
+        Self::default()
+    }
+
+    // Read templates/ and assets/ below `dir` on every request, so UI edits show up on reload
+    pub fn from_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dev_dir: Some(Arc::new(dir.into())),
+        }
+    }
+
+    pub fn is_dev(&self) -> bool {
+        self.dev_dir.is_some()
+    }
+
+    // File contents, falling back to the embedded copy when the file cannot be read
+    pub async fn load(&self, asset: &WebAsset) -> Cow<'static, [u8]> {
+        let Some(dir) = &self.dev_dir else {
+            return Cow::Borrowed(asset.body);
+        };
+        let path = dir.join(asset.file);
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => Cow::Owned(bytes),
+            Err(e) => {
+                warn!("Cannot read {} ({}), serving the embedded copy", path.display(), e);
+                Cow::Borrowed(asset.body)
+            }
+        }
+    }
+
+    // Hashed URLs when embedded; plain, uncached names in development
+    pub fn url(&self, asset: &WebAsset) -> String {
+        if self.is_dev() {
+            format!("/assets/{}", asset.name)
+        } else {
+            asset.path()
+        }
+    }
+
+    pub async fn serve(&self, name: &str) -> Option<Response<Body>> {
+        if !self.is_dev() {
+            return find(name).map(WebAsset::response);
+        }
+        let asset = ASSETS.into_iter().find(|asset| asset.name == name)?;
+        let body = self.load(asset).await.into_owned();
+        Some(
+            Response::builder()
+                .status(StatusCode::OK)
+                .header(header::CONTENT_TYPE, asset.content_type)
+                .header(header::CACHE_CONTROL, "no-store")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+    }
+
+    // Hash over the page and every asset on disk; the dev page reloads itself when it changes
+    pub async fn revision(&self) -> String {
+        let mut contents = Vec::new();
+        for asset in [&INDEX_HTML, &LOGIN_HTML, &SETUP_HTML, &SAFE_MODE_HTML].into_iter().chain(ASSETS) {
+            contents.extend_from_slice(&self.load(asset).await);
+        }
+        content_hash(&contents)
+    }
+}
+
+// Typed context of templates/index.html
+#[derive(Debug, Serialize)]
+pub struct IndexPage {
+    pub version: &'static str,
+    pub build: &'static str,
+    // Bridge-generated asset URLs, marked safe so autoescaping leaves their slashes alone
+    pub style_url: Value,
+    pub script_url: Value,
+    pub icon_url: Value,
+    // Adds the script polling /dev/reload (--dev-assets only)
+    pub live_reload: bool,
+    // Every served device; listed in the header when there is more than one
+    pub devices: Vec<DeviceSummary>,
+}
+
+impl WebAssets {
+    pub async fn render_index(&self, devices: Vec<DeviceSummary>) -> Result<String, minijinja::Error> {
+        let page = IndexPage {
+            version: env!("CARGO_PKG_VERSION"),
+            build: env!("BUILD_TIMESTAMP"),
+            style_url: Value::from_safe_string(self.url(&STYLE_CSS)),
+            script_url: Value::from_safe_string(self.url(&SCRIPT_JS)),
+            icon_url: Value::from_safe_string(self.url(&ICON_PNG)),
+            live_reload: self.is_dev(),
+            devices,
+        };
+        let source = String::from_utf8_lossy(&self.load(&INDEX_HTML).await).into_owned();
+        let mut env = Environment::new();
+        // The .html name turns on HTML autoescaping for device names and the like
+        env.add_template(INDEX_HTML.name, &source)?;
+        env.get_template(INDEX_HTML.name)?.render(&page)
+    }
+
+    // Sign-in form of templates/login.html, shown when [[web_auth.users]] are configured
+    pub async fn render_login(&self) -> Result<String, minijinja::Error> {
+        let page = LoginPage {
+            style_url: Value::from_safe_string(self.url(&STYLE_CSS)),
+            icon_url: Value::from_safe_string(self.url(&ICON_PNG)),
+        };
+        let source = String::from_utf8_lossy(&self.load(&LOGIN_HTML).await).into_owned();
+        let mut env = Environment::new();
+        env.add_template(LOGIN_HTML.name, &source)?;
+        env.get_template(LOGIN_HTML.name)?.render(&page)
+    }
+
+    // The Alpaca setup pages of templates/setup.html: the device list, and a device's form
+    pub async fn render_setup(&self, mut page: SetupPage) -> Result<String, minijinja::Error> {
+        page.style_url = Value::from_safe_string(self.url(&STYLE_CSS));
+        page.icon_url = Value::from_safe_string(self.url(&ICON_PNG));
+        let source = String::from_utf8_lossy(&self.load(&SETUP_HTML).await).into_owned();
+        let mut env = Environment::new();
+        env.add_template(SETUP_HTML.name, &source)?;
+        env.get_template(SETUP_HTML.name)?.render(&page)
+    }
+
+    // The repair page of templates/safe_mode.html, served while the configuration does not load
+    pub async fn render_safe_mode(&self, mut page: SafeModePage) -> Result<String, minijinja::Error> {
+        page.version = env!("CARGO_PKG_VERSION");
+        page.style_url = Value::from_safe_string(self.url(&STYLE_CSS));
+        page.icon_url = Value::from_safe_string(self.url(&ICON_PNG));
+        let source = String::from_utf8_lossy(&self.load(&SAFE_MODE_HTML).await).into_owned();
+        let mut env = Environment::new();
+        env.add_template(SAFE_MODE_HTML.name, &source)?;
+        env.get_template(SAFE_MODE_HTML.name)?.render(&page)
+    }
+}
+
+// Typed context of templates/safe_mode.html; version and asset URLs are filled in by render_safe_mode()
+#[derive(Debug, Default, Serialize)]
+pub struct SafeModePage {
+    pub version: &'static str,
+    pub style_url: Value,
+    pub icon_url: Value,
+    pub status: Option<SafeModeStatus>,
+    // The config file as it is now, for editing
+    pub config: String,
+}
+
+// Typed context of templates/login.html
+#[derive(Debug, Serialize)]
+pub struct LoginPage {
+    pub style_url: Value,
+    pub icon_url: Value,
+}
+
+// Typed context of templates/setup.html; the asset URLs are filled in by render_setup()
+#[derive(Debug, Default, Serialize)]
+pub struct SetupPage {
+    pub version: &'static str,
+    pub style_url: Value,
+    pub icon_url: Value,
+    pub devices: Vec<DeviceSummary>,
+    // The device whose form is shown; None on /setup
+    pub device: Option<DeviceSetup>,
+    // None when nothing can be saved
+    pub config_path: Option<String>,
+    pub saved: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+// Form values as typed, so a rejected form comes back with the user's input
+#[derive(Debug, Default, Serialize)]
+pub struct DeviceSetup {
+    pub device_number: u32,
+    pub name: String,
+    // False for remote and voting devices, which have no port or baud rate
+    pub serial: bool,
+    pub ports: Vec<String>,
+    pub port: String,
+    pub baud: String,
+    pub tolerance: String,
+    pub tolerance_settable: bool,
+    pub max_data_age_secs: String,
+    pub refresh_after_secs: String,
+    pub refresh_wait_ms: String,
+    // "false" or "error"
+    pub on_stale: String,
+}
+
+// Asset for a hashed file name; stale hashes from an older build are not found
+pub fn find(hashed_name: &str) -> Option<&'static WebAsset> {
+    ASSETS.into_iter().find(|asset| asset.hashed_name() == hashed_name)
+}
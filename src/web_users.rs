@@ -0,0 +1,334 @@
+// src/web_users.rs
+// Web interface accounts with session cookies: once [[web_auth.users]] are configured the web UI
+// and JSON API need a signed-in user, and changes need an admin. API clients send the same
+// accounts as Basic credentials. The ASCOM Alpaca endpoints stay open, Alpaca clients cannot sign in.
+// Changes made with a session cookie must repeat the session's CSRF token in the X-CSRF-Token
+// header, which another site's page cannot read, so it cannot drive the signed-in browser.
+
+use crate::config::{tokens_match, WebAuthConfig, WebRole, WebUserConfig};
+use crate::errors::{BridgeError, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+pub const SESSION_COOKIE: &str = "park_session";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+// Argon2 checks running at once; each takes about 19 MiB, which a flood of requests must not
+// multiply on a Pi
+const MAX_VERIFICATIONS: usize = 2;
+// Basic credentials that verified are accepted again without Argon2 for this long, so scripts
+// sending them on every request cost one check per minute
+const BASIC_CACHE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebUser {
+    pub name: String,
+    pub role: WebRole,
+}
+
+#[derive(Clone)]
+struct Session {
+    user: WebUser,
+    csrf_token: String,
+    expires: Instant,
+}
+
+// Basic credentials of a user that verified recently
+struct VerifiedBasic {
+    password: String,
+    user: WebUser,
+    expires: Instant,
+}
+
+#[derive(Clone)]
+pub struct WebUsers {
+    users: Arc<Vec<WebUserConfig>>,
+    session_ttl: Duration,
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+    verifications: Arc<Semaphore>,
+    // By user name
+    verified_basic: Arc<Mutex<HashMap<String, VerifiedBasic>>>,
+}
+
+impl Default for WebUsers {
+    fn default() -> Self {
+        Self {
+            users: Arc::default(),
+            session_ttl: Duration::default(),
+            sessions: Arc::default(),
+            verifications: Arc::new(Semaphore::new(MAX_VERIFICATIONS)),
+            verified_basic: Arc::default(),
+        }
+    }
+}
+
+impl WebUsers {
+    pub fn new(config: &WebAuthConfig) -> Self {
+        Self {
+            users: Arc::new(config.users.clone()),
+            session_ttl: Duration::from_secs(config.session_hours * 3600),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.users.is_empty()
+    }
+
+    // Argon2 is slow on purpose, so the check runs off the async worker threads, a few at a time.
+    // Unknown names are checked against a dummy hash, so the answer takes as long as for a user
+    // that exists and does not give away which names do
+    pub async fn verify(&self, name: &str, password: &str) -> Option<WebUser> {
+        let user = self.users.iter().find(|user| user.name == name).cloned();
+        let password = password.to_string();
+        let permit = self.verifications.clone().acquire_owned().await.ok()?;
+        let (matches, user) = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let matches = {
+                let password_hash = match &user {
+                    Some(user) => user.password_hash.as_str(),
+                    None => dummy_hash(),
+                };
+                PasswordHash::new(password_hash)
+                    .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+            };
+            (matches, user)
+        })
+        .await
+        .ok()?;
+        let user = user.filter(|_| matches)?;
+        Some(WebUser {
+            name: name.to_string(),
+            role: user.role,
+        })
+    }
+
+    // Basic credentials, from the cache while they verified within BASIC_CACHE_TTL
+    async fn verify_basic(&self, name: &str, password: &str) -> Option<WebUser> {
+        {
+            let mut verified = self.verified_basic.lock().unwrap();
+            verified.retain(|_, entry| entry.expires > Instant::now());
+            if let Some(entry) = verified.get(name).filter(|entry| tokens_match(&entry.password, password)) {
+                return Some(entry.user.clone());
+            }
+        }
+        let user = self.verify(name, password).await?;
+        let entry = VerifiedBasic {
+            password: password.to_string(),
+            user: user.clone(),
+            expires: Instant::now() + BASIC_CACHE_TTL,
+        };
+        self.verified_basic.lock().unwrap().insert(name.to_string(), entry);
+        Some(user)
+    }
+
+    // A new session for the user, with its id for the cookie
+    async fn sign_in(&self, name: &str, password: &str) -> Option<(String, Session)> {
+        let user = self.verify(name, password).await?;
+        let id = random_token();
+        let session = Session {
+            user,
+            csrf_token: random_token(),
+            expires: Instant::now() + self.session_ttl,
+        };
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| session.expires > Instant::now());
+        sessions.insert(id.clone(), session.clone());
+        Some((id, session))
+    }
+
+    pub fn sign_out(&self, session_id: &str) -> Option<WebUser> {
+        self.sessions.lock().unwrap().remove(session_id).map(|session| session.user)
+    }
+
+    fn session(&self, session_id: &str) -> Option<Session> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get(session_id) {
+            Some(session) if session.expires > Instant::now() => Some(session.clone()),
+            Some(_) => {
+                sessions.remove(session_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    // The web UI's session cookie, or Basic credentials from an API client
+    pub async fn authenticate(&self, headers: &HeaderMap) -> Option<WebUser> {
+        if let Some(session) = session_cookie(headers).and_then(|id| self.session(id)) {
+            return Some(session.user);
+        }
+        let (name, password) = basic_credentials(headers)?;
+        self.verify_basic(&name, &password).await
+    }
+}
+
+// Hash of a password nobody has, checked for unknown user names; made once, on first use
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash_password(&random_token()).unwrap_or_default())
+}
+
+// PHC string for a [[web_auth.users]] password_hash
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(uuid::Uuid::new_v4().as_bytes()).map_err(|e| BridgeError::Config(e.to_string()))?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| BridgeError::Config(format!("Cannot hash the password: {}", e)))
+}
+
+fn random_token() -> String {
+    format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple())
+}
+
+// Requests authenticated by Basic credentials carry no cookie a browser would add by itself,
+// so only session requests need the token
+fn csrf_token_matches(users: &WebUsers, headers: &HeaderMap) -> bool {
+    let Some(session) = session_cookie(headers).and_then(|id| users.session(id)) else {
+        return true;
+    };
+    let sent = headers.get(CSRF_HEADER).and_then(|value| value.to_str().ok()).unwrap_or_default();
+    tokens_match(&session.csrf_token, sent)
+}
+
+fn session_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| pair.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='))
+}
+
+fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let encoded = headers.get(header::AUTHORIZATION)?.to_str().ok()?.strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD.decode(encoded.trim()).ok()?;
+    let credentials = String::from_utf8(decoded).ok()?;
+    let (name, password) = credentials.split_once(':')?;
+    Some((name.to_string(), password.to_string()))
+}
+
+fn session_cookie_header(value: &str, max_age: Duration) -> String {
+    format!("{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}", SESSION_COOKIE, value, max_age.as_secs())
+}
+
+fn refusal(status: StatusCode, message: impl Into<String>) -> Response<Body> {
+    (status, Json(json!({ "success": false, "message": message.into() }))).into_response()
+}
+
+// Sign-in routes plus the check in front of every route of `router`
+pub fn protect(router: Router, users: WebUsers) -> Router {
+    let session_routes = Router::new()
+        .route("/api/login", post(api_login))
+        .route("/api/logout", post(api_logout))
+        .route("/api/session", get(api_session))
+        .with_state(users.clone());
+    router
+        .merge(session_routes)
+        .layer(middleware::from_fn_with_state(users, require_web_user))
+}
+
+// /api/events, its stream and the session log, guarded by alpaca_server::protect_event_feed instead
+pub fn is_event_feed(path: &str) -> bool {
+    path == "/api/events" || path.starts_with("/api/events/") || path == "/api/session_log"
+}
+
+// Open without signing in: the Alpaca device and management APIs, the one-line status for
+// displays, the sign-in page and what it loads, and the forced-safety endpoint and event feed,
+// which check their own bearer tokens
+fn is_public(path: &str) -> bool {
+    is_event_feed(path)
+        || path.starts_with("/api/v1/")
+        || path.starts_with("/management/")
+        || path.starts_with("/assets/")
+        || matches!(
+            path,
+            "/status.txt" | "/status.json" | "/login" | "/api/login" | "/api/logout" | "/api/session" | "/api/safety/force" | "/favicon.ico" | "/icon-192.png" | "/icon-512.png"
+        )
+}
+
+async fn require_web_user(State(users): State<WebUsers>, request: Request, next: Next) -> Response<Body> {
+    let path = request.uri().path();
+    if is_public(path) {
+        return next.run(request).await;
+    }
+    let Some(user) = users.authenticate(request.headers()).await else {
+        // Browsers opening a page go to the sign-in form; everything else gets a plain 401
+        let wants_page = request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/html"));
+        if request.method() == Method::GET && wants_page {
+            return Redirect::to(&format!("/login?next={}", urlencoding::encode(path))).into_response();
+        }
+        return refusal(StatusCode::UNAUTHORIZED, "Sign in required");
+    };
+    let changes_something = !matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS);
+    if changes_something && user.role != WebRole::Admin {
+        info!("Web user {} (viewer) refused {} {}", user.name, request.method(), path);
+        return refusal(StatusCode::FORBIDDEN, format!("{} is a viewer; changes need an admin", user.name));
+    }
+    if changes_something && !csrf_token_matches(&users, request.headers()) {
+        warn!("Refused {} {} for web user {}: missing or wrong CSRF token", request.method(), path, user.name);
+        return refusal(StatusCode::FORBIDDEN, "Missing or wrong CSRF token; reload the page");
+    }
+    next.run(request).await
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    name: String,
+    password: String,
+}
+
+async fn api_login(State(users): State<WebUsers>, Json(login): Json<LoginRequest>) -> Response<Body> {
+    let Some((session_id, session)) = users.sign_in(&login.name, &login.password).await else {
+        warn!("Failed web sign-in as {}", login.name);
+        return refusal(StatusCode::UNAUTHORIZED, "Wrong user name or password");
+    };
+    let user = session.user;
+    info!("Web user {} signed in as {:?}", user.name, user.role);
+    let cookie = session_cookie_header(&session_id, users.session_ttl);
+    (
+        [(header::SET_COOKIE, cookie)],
+        Json(json!({ "success": true, "name": user.name, "role": user.role, "csrf_token": session.csrf_token })),
+    )
+        .into_response()
+}
+
+async fn api_logout(State(users): State<WebUsers>, headers: HeaderMap) -> Response<Body> {
+    if let Some(user) = session_cookie(&headers).and_then(|id| users.sign_out(id)) {
+        info!("Web user {} signed out", user.name);
+    }
+    (
+        [(header::SET_COOKIE, session_cookie_header("", Duration::ZERO))],
+        Json(json!({ "success": true })),
+    )
+        .into_response()
+}
+
+// Who the web UI is showing the page to, and the token its changes must send; user is null when
+// nobody is signed in. The permissive CORS layer does not allow credentials, so other sites'
+// pages cannot read this with the cookie.
+async fn api_session(State(users): State<WebUsers>, headers: HeaderMap) -> Json<serde_json::Value> {
+    let session = session_cookie(&headers).and_then(|id| users.session(id));
+    let csrf_token = session.as_ref().map(|session| session.csrf_token.clone());
+    Json(json!({ "login_required": true, "user": session.map(|session| session.user), "csrf_token": csrf_token }))
+}
@@ -1,5 +1,13 @@
 let logElement = document.getElementById('log');
 let currentlyConnected = false;
+// Sent with every change while signed in, see loadSession()
+let csrfToken = null;
+
+function controlHeaders() {
+    const headers = { 'Content-Type': 'application/json' };
+    if (csrfToken) headers['X-CSRF-Token'] = csrfToken;
+    return headers;
+}
 
 function switchTab(tabName) {
     // Hide all tab contents
@@ -64,6 +72,11 @@ function highlightJSON(jsonString) {
 async function fetchStatus() {
     try {
         const response = await fetch('/api/status');
+        if (response.status === 401) {
+            // Signed out or the session expired
+            location.href = '/login?next=' + encodeURIComponent(location.pathname);
+            return;
+        }
         const data = await response.json();
         updateUI(data);
     } catch (error) {
@@ -119,7 +132,7 @@ async function connectToPort() {
         
         const response = await fetch('/api/connect', {
             method: 'POST',
-            headers: { 'Content-Type': 'application/json' },
+            headers: controlHeaders(),
             body: JSON.stringify({ port: port, baud_rate: baudRate })
         });
         
@@ -142,7 +155,7 @@ async function disconnectFromPort() {
         
         const response = await fetch('/api/disconnect', {
             method: 'POST',
-            headers: { 'Content-Type': 'application/json' }
+            headers: controlHeaders()
         });
         
         const data = await response.json();
@@ -174,7 +187,7 @@ async function setParkPosition() {
         
         const response = await fetch('/api/device/set_park', {
             method: 'POST',
-            headers: { 'Content-Type': 'application/json' }
+            headers: controlHeaders()
         });
         
         const data = await response.json();
@@ -187,6 +200,100 @@ async function setParkPosition() {
     } catch (error) {
         log('❌ Error setting park position: ' + error.message);
     }
+    refreshCommandHistory();
+}
+
+// Mounts watched through [[telescopes]] whose SetPark follows the sensor's set park
+let parkMounts = [];
+let maintenanceOn = false;
+
+// Show the mount park button when a connected mount on the sensor (device 0) can SetPark
+async function loadParkMounts() {
+    try {
+        const response = await fetch('/api/telescopes');
+        if (!response.ok) return;
+        const telescopes = await response.json();
+        parkMounts = telescopes
+            .filter(t => t.interlock.sensor_device === 0 && t.status && t.status.can_set_park)
+            .map(t => t.id);
+        document.getElementById('mount-park-section').hidden = parkMounts.length === 0;
+    } catch (error) {
+        log('❌ Failed to load the mounts: ' + error.message);
+    }
+}
+
+async function setMountPark() {
+    for (const id of parkMounts) {
+        if (!confirm('Set the current position of mount ' + id + ' as its park position?')) {
+            continue;
+        }
+        try {
+            const response = await fetch('/api/telescopes/' + encodeURIComponent(id) + '/setpark', {
+                method: 'POST',
+                headers: controlHeaders()
+            });
+            const data = await response.json();
+            log((data.success ? '✅ ' : '❌ Failed to set mount park: ') + data.message);
+        } catch (error) {
+            log('❌ Error setting mount park: ' + error.message);
+        }
+    }
+}
+
+async function toggleMaintenance() {
+    try {
+        let response;
+        if (maintenanceOn) {
+            response = await fetch('/api/maintenance', { method: 'DELETE', headers: controlHeaders() });
+        } else {
+            const minutes = prompt('Maintenance mode for how many minutes?', '60');
+            if (minutes === null) return;
+            const reason = prompt('Reason (optional)', '') || null;
+            response = await fetch('/api/maintenance', {
+                method: 'POST',
+                headers: controlHeaders(),
+                body: JSON.stringify({ minutes: Number(minutes), reason: reason })
+            });
+        }
+        const data = await response.json();
+        if (!response.ok) {
+            log('❌ Maintenance mode: ' + data.message);
+        } else if (maintenanceOn) {
+            log('✅ ' + data.message);
+        } else {
+            log('🛠️ Maintenance mode until ' + new Date(data.expires_at).toLocaleTimeString());
+        }
+    } catch (error) {
+        log('❌ Error switching maintenance mode: ' + error.message);
+    }
+    fetchStatus();
+}
+
+// Submit a long-running device operation as a job and poll until it finishes
+async function runJob(operation) {
+    const response = await fetch('/api/jobs', {
+        method: 'POST',
+        headers: controlHeaders(),
+        body: JSON.stringify({ operation: operation })
+    });
+    let job = await response.json();
+    
+    let lastProgress = '';
+    while (job.status === 'running') {
+        await new Promise(resolve => setTimeout(resolve, 500));
+        const progress = await fetch('/api/jobs/' + job.id);
+        job = await progress.json();
+        
+        // Calibration reports its phase, e.g. "collecting samples 40%"
+        if (job.phase) {
+            const label = job.phase.replace(/_/g, ' ') + (job.percent !== null ? ' ' + job.percent + '%' : '');
+            if (label !== lastProgress) {
+                log('⏳ ' + label);
+                lastProgress = label;
+            }
+        }
+    }
+    return job;
 }
 
 async function calibrateSensor() {
@@ -202,21 +309,17 @@ async function calibrateSensor() {
     try {
         log('🎯 Starting IMU calibration...');
         
-        const response = await fetch('/api/device/calibrate', {
-            method: 'POST',
-            headers: { 'Content-Type': 'application/json' }
-        });
+        const job = await runJob('calibrate');
         
-        const data = await response.json();
-        
-        if (data.success) {
-            log('✅ ' + data.message);
+        if (job.status === 'succeeded') {
+            log('✅ Sensor calibration completed');
         } else {
-            log('❌ Calibration failed: ' + data.message);
+            log('❌ Calibration failed: ' + (job.error || job.status));
         }
     } catch (error) {
         log('❌ Error during calibration: ' + error.message);
     }
+    refreshCommandHistory();
 }
 
 async function factoryReset() {
@@ -236,21 +339,17 @@ async function factoryReset() {
     try {
         log('🏭 Performing factory reset...');
         
-        const response = await fetch('/api/device/factory_reset', {
-            method: 'POST',
-            headers: { 'Content-Type': 'application/json' }
-        });
+        const job = await runJob('factory_reset');
         
-        const data = await response.json();
-        
-        if (data.success) {
-            log('✅ ' + data.message);
+        if (job.status === 'succeeded') {
+            log('✅ Factory reset completed');
         } else {
-            log('❌ Factory reset failed: ' + data.message);
+            log('❌ Factory reset failed: ' + (job.error || job.status));
         }
     } catch (error) {
         log('❌ Error during factory reset: ' + error.message);
     }
+    refreshCommandHistory();
 }
 
 async function sendManualCommand() {
@@ -277,7 +376,7 @@ async function sendManualCommand() {
         
         const response = await fetch('/api/command', {
             method: 'POST',
-            headers: { 'Content-Type': 'application/json' },
+            headers: controlHeaders(),
             body: JSON.stringify({ command: command })
         });
         
@@ -301,11 +400,37 @@ async function sendManualCommand() {
     } catch (error) {
         log('❌ Error sending command: ' + error.message);
     }
+    refreshCommandHistory();
     
     // Clear the command input
     document.getElementById('manual-command').value = '';
 }
 
+// Transcript of recent commands with their ACK/data replies, newest first
+async function refreshCommandHistory() {
+    try {
+        const response = await fetch('/api/command/history');
+        const history = await response.json();
+        const lines = history.reverse().map(function(entry) {
+            const time = new Date(entry.sent_at).toLocaleTimeString();
+            let text = time + '  <' + entry.command + '>  ' + entry.outcome + ' in ' + entry.duration_ms.toFixed(0) + ' ms';
+            if (entry.ack) {
+                text += '\n    ACK:  ' + entry.ack;
+            }
+            if (entry.response) {
+                text += '\n    Data: ' + entry.response;
+            }
+            if (entry.error) {
+                text += '\n    Error: ' + entry.error;
+            }
+            return text;
+        });
+        document.getElementById('command-history').textContent = lines.length ? lines.join('\n') : 'No commands sent yet';
+    } catch (error) {
+        log('❌ Error loading command history: ' + error.message);
+    }
+}
+
 function updateConnectionButtons(connected) {
     const connectBtn = document.getElementById('connect-btn');
     const disconnectBtn = document.getElementById('disconnect-btn');
@@ -327,10 +452,22 @@ function updateConnectionButtons(connected) {
     currentlyConnected = connected;
 }
 
+function forcedSafetyText(override) {
+    const until = new Date(override.expires_at).toLocaleTimeString();
+    let text = '🧪 IsSafe FORCED ' + (override.is_safe ? 'SAFE' : 'UNSAFE') + ' until ' + until;
+    if (override.reason) {
+        text += ' - ' + override.reason;
+    }
+    return text;
+}
+
 function updateUI(data) {
     // Header park status (visible on all tabs)
     const headerStatus = document.getElementById('header-park-status');
-    if (data.connected) {
+    if (data.safety_override) {
+        headerStatus.className = 'header-status forced';
+        headerStatus.textContent = forcedSafetyText(data.safety_override);
+    } else if (data.connected) {
         if (data.is_parked || data.is_safe) {
             headerStatus.className = 'header-status parked';
             headerStatus.innerHTML = '✅ TELESCOPE PARKED';
@@ -342,6 +479,11 @@ function updateUI(data) {
         headerStatus.className = 'header-status disconnected';
         headerStatus.innerHTML = '🚫 DISCONNECTED';
     }
+    maintenanceOn = Boolean(data.maintenance);
+    if (data.maintenance) {
+        headerStatus.textContent += ' · 🛠️ MAINTENANCE until ' + new Date(data.maintenance.expires_at).toLocaleTimeString();
+    }
+    document.getElementById('maintenance-btn').textContent = maintenanceOn ? '✅ End Maintenance' : '🛠️ Start Maintenance';
 
     // Connection status
     const connStatus = document.getElementById('connection-status');
@@ -360,7 +502,10 @@ function updateUI(data) {
     
     // Safety status (park status)
     const safetyStatus = document.getElementById('safety-status');
-    if (data.connected) {
+    if (data.safety_override) {
+        safetyStatus.className = 'status forced';
+        safetyStatus.textContent = forcedSafetyText(data.safety_override) + ' (test override - ASCOM clients see this value)';
+    } else if (data.connected) {
         if (data.is_parked || data.is_safe) {
             safetyStatus.className = 'status safe';
             safetyStatus.innerHTML = '✅ Telescope is PARKED (Safe)';
@@ -439,6 +584,28 @@ async function testASCOMConnection() {
     }
 }
 
+// Show who is signed in when [[web_auth.users]] are configured; viewers get no controls
+async function loadSession() {
+    try {
+        const response = await fetch('/api/session');
+        if (!response.ok) return;
+        const session = await response.json();
+        const user = session.user;
+        if (!user) return;
+        csrfToken = session.csrf_token;
+        document.getElementById('user-name').textContent = '👤 ' + user.name + ' (' + user.role + ')';
+        document.getElementById('user-bar').hidden = false;
+        document.body.classList.toggle('viewer', user.role !== 'admin');
+    } catch (error) {
+        log('❌ Failed to load the session: ' + error.message);
+    }
+}
+
+async function signOut() {
+    await fetch('/api/logout', { method: 'POST' });
+    location.href = '/login';
+}
+
 // Handle Enter key in manual command input
 document.addEventListener('DOMContentLoaded', function() {
     const commandInput = document.getElementById('manual-command');
@@ -449,6 +616,9 @@ document.addEventListener('DOMContentLoaded', function() {
             }
         });
     }
+    loadSession();
+    refreshCommandHistory();
+    loadParkMounts();
 });
 
 // Auto-refresh every 1 second for real-time updates
@@ -0,0 +1,64 @@
+// tests/common/firmware_emulator.rs
+// Pseudo-terminal firmware emulator built on the library simulator
+
+use std::sync::{Arc, Mutex};
+use telescope_park_bridge::simulator::{run_simulator, SimulatorState};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_serial::{SerialPort, SerialStream};
+
+pub struct FirmwareEmulator {
+    pub state: Arc<Mutex<SimulatorState>>,
+    pub unsolicited: mpsc::UnboundedSender<String>,
+    port_name: String,
+    task: JoinHandle<()>,
+    // Keep the slave side open so the pty does not hang up between bridge connections
+    _slave: SerialStream,
+}
+
+impl FirmwareEmulator {
+    // Create a pty pair and start answering firmware commands on the master side
+    pub fn start() -> Self {
+        let (master, slave) = SerialStream::pair().expect("failed to create pty pair");
+        let port_name = slave.name().expect("pty slave has no name");
+        let mut initial_state = SimulatorState::default();
+        initial_state.firmware_version = "emulator".to_string();
+        initial_state.platform = "nRF52840 Emulator".to_string();
+        let state = Arc::new(Mutex::new(initial_state));
+        let (unsolicited, unsolicited_receiver) = mpsc::unbounded_channel();
+
+        let task_state = state.clone();
+        let task = tokio::spawn(async move {
+            let _ = run_simulator(master, task_state, unsolicited_receiver).await;
+        });
+
+        Self {
+            state,
+            unsolicited,
+            port_name,
+            task,
+            _slave: slave,
+        }
+    }
+
+    // Path of the virtual serial port the bridge should open
+    pub fn port_name(&self) -> &str {
+        &self.port_name
+    }
+
+    pub fn set_position(&self, pitch: f32, roll: f32) {
+        let mut state = self.state.lock().unwrap();
+        state.pitch = pitch;
+        state.roll = roll;
+    }
+
+    pub fn snapshot(&self) -> SimulatorState {
+        self.state.lock().unwrap().clone()
+    }
+}
+
+impl Drop for FirmwareEmulator {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,207 @@
+// tests/common/mod.rs
+// Shared harness for the end-to-end integration tests
+
+#![allow(dead_code)]
+
+pub mod firmware_emulator;
+
+use axum::body::Body;
+use axum::http::{Method, Request, Response, StatusCode};
+use axum::Router;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use telescope_park_bridge::alpaca_server::create_router;
+use telescope_park_bridge::connection_manager::ConnectionManager;
+use telescope_park_bridge::device_registry::DeviceRegistry;
+use telescope_park_bridge::device_state::DeviceState;
+use telescope_park_bridge::discovery_server::DiscoveryTracker;
+use tokio::sync::RwLock;
+use tower::ServiceExt;
+
+pub use firmware_emulator::FirmwareEmulator;
+
+// JSON request to any router, e.g. the safe-mode one; null sends no body
+pub async fn send(router: &Router, method: Method, uri: &str, body: Value) -> (StatusCode, Value) {
+    let mut builder = Request::builder().method(method).uri(uri);
+    let body = match body {
+        Value::Null => Body::empty(),
+        body => {
+            builder = builder.header("content-type", "application/json");
+            Body::from(body.to_string())
+        }
+    };
+    let response = router.clone().oneshot(builder.body(body).unwrap()).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+// Poll a device state not owned by a TestBridge until the predicate holds, panicking on timeout
+pub async fn wait_for_state<F>(device_state: &RwLock<DeviceState>, timeout: Duration, predicate: F)
+where
+    F: Fn(&DeviceState) -> bool,
+{
+    let start = std::time::Instant::now();
+    loop {
+        {
+            let state = device_state.read().await;
+            if predicate(&state) {
+                return;
+            }
+        }
+        if start.elapsed() > timeout {
+            let state = device_state.read().await;
+            panic!("Timed out waiting for device state condition; last state: {:?}", *state);
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+// A bridge instance wired to an emulated device, without binding any sockets
+pub struct TestBridge {
+    pub emulator: FirmwareEmulator,
+    pub device_state: Arc<RwLock<DeviceState>>,
+    pub connection_manager: Arc<ConnectionManager>,
+    pub router: Router,
+}
+
+impl TestBridge {
+    // Start an emulator, connect the real serial client to it and wait for the startup self-check
+    pub async fn start() -> Self {
+        Self::start_with(|manager| manager).await
+    }
+
+    // Same as start(), with a hook to adjust the connection manager's settings
+    pub async fn start_with<F>(configure: F) -> Self
+    where
+        F: FnOnce(ConnectionManager) -> ConnectionManager,
+    {
+        let emulator = FirmwareEmulator::start();
+        let device_state = Arc::new(RwLock::new(DeviceState::new()));
+        let connection_manager = Arc::new(configure(ConnectionManager::new(device_state.clone())));
+        let router = create_router(
+            DeviceRegistry::single(device_state.clone(), connection_manager.clone()),
+            DiscoveryTracker::default(),
+        );
+
+        connection_manager
+            .connect(emulator.port_name().to_string(), 115200)
+            .await
+            .expect("connect to emulator failed");
+
+        let bridge = Self {
+            emulator,
+            device_state,
+            connection_manager,
+            router,
+        };
+
+        bridge
+            .wait_for(Duration::from_secs(10), |state| {
+                state.connected && state.operational && state.device_version == "emulator"
+            })
+            .await;
+        bridge
+    }
+
+    // Poll the shared device state until the predicate holds, panicking on timeout
+    pub async fn wait_for<F>(&self, timeout: Duration, predicate: F)
+    where
+        F: Fn(&DeviceState) -> bool,
+    {
+        wait_for_state(&self.device_state, timeout, predicate).await
+    }
+
+    // Serve the router on an ephemeral localhost port, for clients that need a real socket
+    pub async fn serve(&self) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = self.router.clone();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        format!("http://{}", address)
+    }
+
+    pub async fn get(&self, uri: &str) -> (StatusCode, Value) {
+        self.request(Method::GET, uri, None, &[], Body::empty()).await
+    }
+
+    pub async fn post_json(&self, uri: &str, body: Value) -> (StatusCode, Value) {
+        self.request(Method::POST, uri, Some("application/json"), &[], Body::from(body.to_string())).await
+    }
+
+    pub async fn delete(&self, uri: &str) -> (StatusCode, Value) {
+        self.request(Method::DELETE, uri, None, &[], Body::empty()).await
+    }
+
+    pub async fn put_form(&self, uri: &str, form: &str) -> (StatusCode, Value) {
+        self.request(
+            Method::PUT,
+            uri,
+            Some("application/x-www-form-urlencoded"),
+            &[],
+            Body::from(form.to_string()),
+        )
+        .await
+    }
+
+    // JSON request with extra headers (e.g. Authorization); null sends no body
+    pub async fn send_json(&self, method: Method, uri: &str, headers: &[(&str, &str)], body: Value) -> (StatusCode, Value) {
+        if body.is_null() {
+            return self.request(method, uri, None, headers, Body::empty()).await;
+        }
+        self.request(method, uri, Some("application/json"), headers, Body::from(body.to_string())).await
+    }
+
+    // GET with extra request headers, returning the raw response for header checks
+    pub async fn get_response(&self, uri: &str, headers: &[(&str, &str)]) -> Response<Body> {
+        let mut builder = Request::builder().method(Method::GET).uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        self.router.clone().oneshot(builder.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    // Rendered page of a GET, or of a POST of the urlencoded form when one is given
+    pub async fn page(&self, uri: &str, form: Option<&str>) -> (StatusCode, String) {
+        let request = match form {
+            Some(form) => Request::post(uri)
+                .header("content-type", "application/x-www-form-urlencoded")
+                .body(Body::from(form.to_string())),
+            None => Request::get(uri).body(Body::empty()),
+        };
+        let response = self.router.clone().oneshot(request.unwrap()).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        content_type: Option<&str>,
+        headers: &[(&str, &str)],
+        body: Body,
+    ) -> (StatusCode, Value) {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(content_type) = content_type {
+            builder = builder.header("content-type", content_type);
+        }
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+
+        let response = self
+            .router
+            .clone()
+            .oneshot(builder.body(body).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let value = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, value)
+    }
+}
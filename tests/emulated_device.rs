@@ -0,0 +1,3915 @@
+// tests/emulated_device.rs
+// End-to-end tests driving the real serial client and HTTP endpoints against the pty firmware emulator
+
+#![cfg(unix)]
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestBridge;
+use park_bridge_client::{BridgeClient, Error as ClientError, EventKind, ForceSafety, JobOperation, JobStatus, PageQuery};
+use serde_json::{json, Value};
+use std::time::Duration;
+use telescope_park_bridge::config::{
+    BridgeConfig,     CommandApiConfig, FramingConfig, HeartbeatConfig, IdentityConfig, ReconnectConfig, SafetyConfig,
+    SafetyForceConfig, StaleReply, VotingPolicy,
+};
+use std::sync::Arc;
+use telescope_park_bridge::alpaca_server::{create_api_router, create_router, protect_event_feed};
+#[cfg(feature = "web-ui")]
+use telescope_park_bridge::alpaca_server::create_router_with_assets;
+use telescope_park_bridge::config_migrations::CURRENT_SCHEMA_VERSION;
+use telescope_park_bridge::connection_manager::ConnectionManager;
+use telescope_park_bridge::device_registry::{DeviceHandle, DeviceRegistry};
+use telescope_park_bridge::device_state::DeviceState;
+use telescope_park_bridge::discovery_server::DiscoveryTracker;
+use telescope_park_bridge::fault_injection::{FaultInjector, FaultPlan};
+use telescope_park_bridge::protocol::{ProtocolVersion, ReplyKind};
+use telescope_park_bridge::sensor_voting::VotingMember;
+use telescope_park_bridge::secrets::{SecretLocation, SecretStore};
+use telescope_park_bridge::session_recording::TrafficDirection;
+#[cfg(feature = "web-ui")]
+use telescope_park_bridge::web_assets::{self, WebAssets};
+use tokio::sync::RwLock;
+
+#[tokio::test]
+async fn status_polling_populates_device_state() {
+    let bridge = TestBridge::start().await;
+
+    let (status, body) = bridge.get("/api/status").await;
+    assert!(status.is_success());
+    assert_eq!(body["connected"], true);
+    assert_eq!(body["device_version"], "emulator");
+    assert_eq!(body["platform"], "nRF52840 Emulator");
+    assert_eq!(body["is_calibrated"], true);
+}
+
+#[tokio::test]
+async fn startup_self_check_gates_the_device() {
+    let bridge = TestBridge::start().await;
+
+    let (status, body) = bridge.get("/api/selftest").await;
+    assert!(status.is_success());
+    assert_eq!(body["status"], "passed", "self-check report: {}", body);
+    let steps: Vec<_> = body["steps"].as_array().unwrap().iter().map(|step| step["name"].clone()).collect();
+    assert_eq!(
+        steps,
+        vec![json!("capabilities"), json!("version"), json!("calibration"), json!("park_definition"), json!("position")]
+    );
+    assert_eq!(body["steps"][1]["detail"], "firmware emulator, protocol v1");
+    let (_, status_body) = bridge.get("/api/status").await;
+    assert_eq!(status_body["operational"], true);
+
+    // Until the next connection passes the check, IsSafe stays false even when parked
+    bridge.device_state.write().await.operational = false;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(body["Value"], false);
+
+    let (status, _) = bridge.get("/api/selftest?device_number=3").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn status_line_for_displays() {
+    let bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_parked).await;
+
+    let response = bridge.get_response("/status.txt", &[]).await;
+    assert_eq!(response.headers()["content-type"], "text/plain; charset=utf-8");
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let line = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(line.starts_with("PARKED | pitch "), "{}", line);
+    assert!(line.trim_end().ends_with("s old"), "{}", line);
+
+    let (_, body) = bridge.get("/status.json").await;
+    assert_eq!(body["state"], "parked");
+    assert_eq!(body["is_safe"], true);
+    assert_eq!(body["stale"], false);
+    assert_eq!(body["text"], line.trim_end());
+
+    bridge.device_state.write().await.operational = false;
+    let (_, body) = bridge.get("/status.json").await;
+    assert_eq!(body["state"], "not_ready");
+    assert!(body["text"].as_str().unwrap().starts_with("NOT READY | "));
+
+    bridge.post_json("/api/disconnect", json!({})).await;
+    let (_, body) = bridge.get("/status.json").await;
+    assert_eq!(body["text"], "DISCONNECTED");
+    assert_eq!(body["is_safe"], false);
+
+    let (status, _) = bridge.get("/status.txt?device_number=3").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn firmware_capabilities_gate_commands() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager.with_command_api(CommandApiConfig {
+            expert_mode: true,
+            ..CommandApiConfig::default()
+        })
+    })
+    .await;
+    let (_, body) = bridge.get("/api/status").await;
+    assert_eq!(body["capabilities"]["discovered"], true);
+    assert_eq!(body["capabilities"]["commands"].as_array().unwrap().len(), 14);
+    assert_eq!(body["capabilities"]["set_tolerance"], true);
+    assert_eq!(body["capabilities"]["battery"], false);
+
+    // Older firmware listing its commands one per line, without tolerance setting
+    bridge.emulator.state.lock().unwrap().help =
+        "<01> Status\n<02> Position\n<03> Parked?\n<05> Park position\n<08> Version\n<0B> Tolerance\n<0C> Battery and temperature"
+            .to_string();
+    bridge.connection_manager.connect(bridge.emulator.port_name().to_string(), 115200).await.unwrap();
+    bridge
+        .wait_for(Duration::from_secs(10), |state| state.operational && !state.capabilities.set_tolerance)
+        .await;
+    let (_, body) = bridge.get("/api/status").await;
+    assert_eq!(body["capabilities"]["commands"], json!(["01", "02", "03", "05", "08", "0B", "0C"]));
+    assert_eq!(body["capabilities"]["battery"], true);
+    assert_eq!(body["capabilities"]["temperature"], true);
+
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "0A150" })).await;
+    assert_eq!(body["success"], false);
+    assert!(body["message"].as_str().unwrap().contains("does not support command 0A"), "{}", body);
+    assert!(!bridge.emulator.snapshot().commands_received.iter().any(|c| c.starts_with("0A")));
+    // Codes the bridge does not use still reach the firmware in expert mode
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "FF" })).await;
+    assert!(body["message"].as_str().unwrap().contains("Unknown command"), "{}", body);
+}
+
+#[tokio::test]
+async fn protocol_version_is_negotiated_from_the_version_reply() {
+    let bridge = TestBridge::start().await;
+    let (_, body) = bridge.get("/api/status").await;
+    assert_eq!(body["protocol_version"], "v1");
+
+    // Newer firmware names the command in its replies and adds fields to existing ones
+    bridge.emulator.state.lock().unwrap().protocol_version = 2;
+    bridge.connection_manager.connect(bridge.emulator.port_name().to_string(), 115200).await.unwrap();
+    bridge
+        .wait_for(Duration::from_secs(10), |state| {
+            state.operational && state.protocol_version == ProtocolVersion::V2
+        })
+        .await;
+    let (_, body) = bridge.get("/api/selftest").await;
+    assert_eq!(body["status"], "passed", "self-check report: {}", body);
+    assert_eq!(body["steps"][1]["detail"], "firmware emulator, protocol v2");
+
+    // A reading with the new flags would pass for a status reply if recognised by its fields
+    bridge.emulator.set_position(25.0, -3.0);
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "02" })).await;
+    let reply: serde_json::Value = serde_json::from_str(body["response"].as_str().unwrap()).unwrap();
+    assert_eq!(reply["data"]["parked"], false);
+    assert_eq!(ProtocolVersion::V1.classify(None, &reply["data"]), ReplyKind::Status);
+    assert_eq!(ProtocolVersion::V2.classify(Some("02"), &reply["data"]), ReplyKind::Position);
+    let (_, body) = bridge.get("/api/status").await;
+    assert_eq!(body["current_pitch"], 25.0);
+}
+
+#[tokio::test]
+async fn issafe_follows_park_state() {
+    let bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/issafe?ClientTransactionID=7").await;
+    assert_eq!(body["Value"], true);
+    assert_eq!(body["ClientTransactionID"], 7);
+    assert_eq!(body["ErrorNumber"], 0);
+
+    // Move the mount away from the park position
+    bridge.emulator.set_position(25.0, -3.0);
+    bridge.wait_for(Duration::from_secs(5), |state| !state.is_safe).await;
+
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(body["Value"], false);
+}
+
+#[tokio::test]
+async fn stale_data_makes_issafe_false_without_error() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager
+            .with_heartbeat(HeartbeatConfig { interval_secs: 0, max_missed: 1 })
+            .with_safety(SafetyConfig {
+                max_data_age_secs: 2,
+                refresh_after_secs: 0,
+                ..SafetyConfig::default()
+            })
+    })
+    .await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+
+    // The firmware stops answering polls while the port stays open
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        drop_responses: u32::MAX,
+        ..FaultPlan::default()
+    });
+    bridge.emulator.state.lock().unwrap().faults = faults;
+    bridge.wait_for(Duration::from_secs(10), |state| state.is_stale(2)).await;
+
+    let (status, body) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert!(status.is_success());
+    assert_eq!(body["Value"], false);
+    assert_eq!(body["ErrorNumber"], 0);
+
+    let (_, body) = bridge.get("/api/status").await;
+    assert_eq!(body["stale"], true);
+    assert_eq!(body["is_safe"], true);
+
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/devicestate").await;
+    let values = body["Value"].as_array().unwrap();
+    let value_of = |name: &str| values.iter().find(|v| v["Name"] == name).map(|v| v["Value"].clone());
+    assert_eq!(value_of("IsSafe"), Some(json!(false)));
+    assert_eq!(value_of("Stale"), Some(json!(true)));
+
+    // Fresh data clears the flag again
+    bridge.emulator.state.lock().unwrap().faults.clear();
+    bridge.wait_for(Duration::from_secs(10), |state| !state.is_stale(2)).await;
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(body["Value"], true);
+}
+
+#[tokio::test]
+async fn stale_data_can_make_issafe_an_error() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager
+            .with_heartbeat(HeartbeatConfig { interval_secs: 0, max_missed: 1 })
+            .with_safety(SafetyConfig {
+                max_data_age_secs: 2,
+                refresh_after_secs: 0,
+                on_stale: StaleReply::Error,
+                ..SafetyConfig::default()
+            })
+    })
+    .await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/issafe?ClientTransactionID=4").await;
+    assert_eq!(body["Value"], true);
+    assert_eq!(body["ErrorNumber"], 0);
+
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        drop_responses: u32::MAX,
+        ..FaultPlan::default()
+    });
+    bridge.emulator.state.lock().unwrap().faults = faults;
+    bridge.wait_for(Duration::from_secs(10), |state| state.is_stale(2)).await;
+
+    let (status, body) = bridge.get("/api/v1/safetymonitor/0/issafe?ClientTransactionID=5").await;
+    assert!(status.is_success());
+    assert_eq!(body["ErrorNumber"], 0x408);
+    assert!(body["ErrorMessage"].as_str().unwrap().contains("stale"), "{}", body);
+    assert_eq!(body["ClientTransactionID"], 5);
+
+    // devicestate keeps reporting the flag instead of failing
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/devicestate").await;
+    assert_eq!(body["ErrorNumber"], 0);
+}
+
+#[tokio::test]
+async fn stale_issafe_read_waits_for_fresh_poll() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager
+            .with_heartbeat(HeartbeatConfig { interval_secs: 0, max_missed: 1 })
+            .with_safety(SafetyConfig {
+                max_data_age_secs: 2,
+                refresh_after_secs: 1,
+                refresh_wait_ms: 3000,
+                ..SafetyConfig::default()
+            })
+    })
+    .await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        drop_responses: u32::MAX,
+        ..FaultPlan::default()
+    });
+    bridge.emulator.state.lock().unwrap().faults = faults;
+    bridge.wait_for(Duration::from_secs(10), |state| state.is_stale(2)).await;
+
+    // The firmware answers again: the read polls and waits instead of reporting the stale cache
+    bridge.emulator.state.lock().unwrap().faults.clear();
+    let (status, body) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert!(status.is_success());
+    assert_eq!(body["Value"], true);
+}
+
+#[tokio::test]
+async fn status_and_about_report_the_running_build() {
+    let bridge = TestBridge::start().await;
+
+    let (status, about) = bridge.get("/api/about").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(about["name"], "telescope_park_bridge");
+    assert_eq!(about["version"], env!("CARGO_PKG_VERSION"));
+    assert!(!about["git_commit"].as_str().unwrap().is_empty());
+    assert!(about["build_timestamp"].as_str().unwrap().ends_with('Z'));
+    assert!(about["started_at"].is_string());
+    let features: Vec<&str> = about["features"].as_array().unwrap().iter().filter_map(|f| f.as_str()).collect();
+    assert_eq!(features.contains(&"web-ui"), cfg!(feature = "web-ui"));
+
+    // The same server block rides along with the device state
+    let (_, body) = bridge.get("/api/status").await;
+    assert_eq!(body["connected"], true);
+    assert_eq!(body["server"]["git_commit"], about["git_commit"]);
+    assert_eq!(body["server"]["started_at"], about["started_at"]);
+    // Only /api/about has the uptime, which would change the status ETag every second
+    assert!(about["uptime_secs"].is_u64());
+    assert!(body["server"].get("uptime_secs").is_none(), "{}", body);
+
+    let client = BridgeClient::new(&bridge.serve().await).unwrap();
+    let about = client.about().await.unwrap();
+    assert_eq!(about.server.version, env!("CARGO_PKG_VERSION"));
+    let server = client.status().await.unwrap().server.unwrap();
+    assert_eq!(server.started_at, about.server.started_at);
+}
+
+#[tokio::test]
+async fn status_and_web_assets_are_cacheable() {
+    let bridge = TestBridge::start().await;
+
+    let response = bridge.get_response("/api/status", &[]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["cache-control"], "no-cache");
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+
+    // Each poll that reads the sensor moves last_update and the firmware uptime, so a new tag
+    let last_update = bridge.device_state.read().await.last_update;
+    bridge.wait_for(Duration::from_secs(10), |state| state.last_update > last_update).await;
+    let response = bridge.get_response("/api/status", &[("if-none-match", &etag)]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()["etag"], etag.as_str());
+
+    // Without polls nothing changes, and a client holding the tag gets 304 without a body
+    bridge.connection_manager.disconnect().await.unwrap();
+    let response = bridge.get_response("/api/status", &[]).await;
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let response = bridge.get_response("/api/status", &[("if-none-match", &etag)]).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()["etag"], etag.as_str());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(body.is_empty());
+
+    // The page links content-hashed assets that browsers may keep for a year
+    #[cfg(feature = "web-ui")]
+    {
+        let response = bridge.get_response("/", &[]).await;
+        let page = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let page = String::from_utf8(page.to_vec()).unwrap();
+        assert!(!page.contains("Serving"));
+        for asset in [&web_assets::STYLE_CSS, &web_assets::SCRIPT_JS, &web_assets::ICON_PNG] {
+            assert!(page.contains(&asset.path()), "{} not linked", asset.name);
+            let response = bridge.get_response(&asset.path(), &[]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["cache-control"], web_assets::IMMUTABLE_CACHE_CONTROL);
+        }
+        let response = bridge.get_response("/assets/style.0000000000000000.css", &[]).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
+
+#[cfg(feature = "web-ui")]
+#[tokio::test]
+async fn dev_assets_are_read_from_disk_and_reload() {
+    let mut bridge = TestBridge::start().await;
+    let dir = std::env::temp_dir().join(format!("park-bridge-dev-assets-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("templates")).unwrap();
+    std::fs::write(dir.join("templates/style.css"), "body { color: red; }").unwrap();
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    bridge.router = create_router_with_assets(devices, DiscoveryTracker::default(), WebAssets::from_dir(&dir));
+
+    let text = |response: axum::http::Response<axum::body::Body>| async move {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    };
+    let page = text(bridge.get_response("/", &[]).await).await;
+    assert!(page.contains("/assets/style.css"));
+    assert!(page.contains("/dev/reload"));
+    let response = bridge.get_response("/assets/style.css", &[]).await;
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    assert_eq!(text(response).await, "body { color: red; }");
+    // Files missing from the directory fall back to the embedded copy
+    let script = text(bridge.get_response("/assets/script.js", &[]).await).await;
+    assert_eq!(script.as_bytes(), web_assets::SCRIPT_JS.body);
+
+    let (_, before) = bridge.get("/dev/reload").await;
+    std::fs::write(dir.join("templates/style.css"), "body { color: blue; }").unwrap();
+    let (_, after) = bridge.get("/dev/reload").await;
+    assert_ne!(before["revision"], after["revision"]);
+    let response = bridge.get_response("/assets/style.css", &[]).await;
+    assert_eq!(text(response).await, "body { color: blue; }");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Embedded builds do not expose the reload endpoint
+    let plain = TestBridge::start().await;
+    let (status, _) = plain.get("/dev/reload").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn headless_router_serves_only_the_apis() {
+    let mut bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    bridge.router = create_api_router(devices, DiscoveryTracker::default());
+
+    for uri in ["/", "/setup", "/setup/v1/safetymonitor/0/setup", "/favicon.ico", "/assets/style.css"] {
+        let response = bridge.get_response(uri, &[]).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{} is still served", uri);
+    }
+    let (status, body) = bridge.get("/api/status").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["connected"], true);
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(body["ErrorNumber"], 0);
+    let (status, _) = bridge.get("/management/v1/configureddevices").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[cfg(feature = "web-ui")]
+#[tokio::test]
+async fn setup_pages_save_settings_to_the_config_file() {
+    use telescope_park_bridge::config_store::ConfigStore;
+
+    let mut bridge = TestBridge::start().await;
+    let dir = std::env::temp_dir().join(format!("park-bridge-setup-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("bridge.toml");
+    let original = "# Pier north\n[safety]\n# The sensor reports every second\nmax_data_age_secs = 30\n";
+    std::fs::write(&path, original).unwrap();
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone())
+        .with_config_store(ConfigStore::new(&path));
+    bridge.router = create_router(devices, DiscoveryTracker::default());
+    let port = bridge.emulator.port_name().to_string();
+
+    let (status, page) = bridge.page("/setup", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains("href=\"/setup/v1/safetymonitor/0/setup\""), "{}", page);
+    let (status, page) = bridge.page("/setup/v1/safetymonitor/0/setup", None).await;
+    assert_eq!(status, StatusCode::OK);
+    // Autoescaping writes the port's slashes as &#x2f;
+    assert!(page.contains(&format!("value=\"{}\"", port.replace('/', "&#x2f;"))), "{}", page);
+    assert!(page.contains("value=\"2.00\""), "{}", page);
+    let (status, page) = bridge.page("/setup/v1/safetymonitor/3/setup", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(page.contains("There is no SafetyMonitor #3"));
+
+    // A rejected form comes back with its input and leaves the file alone
+    let form = |tolerance: &str, max_data_age_secs: &str| {
+        format!(
+            "port={}&baud=115200&tolerance={}&max_data_age_secs={}&refresh_after_secs=5&refresh_wait_ms=1000",
+            urlencoding::encode(&port),
+            tolerance,
+            max_data_age_secs
+        )
+    };
+    let (status, page) = bridge.page("/setup/v1/safetymonitor/0/setup", Some(&form("12", "0"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(page.contains("safety.max_data_age_secs"), "{}", page);
+    assert!(page.contains("tolerance must be between"), "{}", page);
+    assert!(page.contains("value=\"12\""));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+    let bad_port = form("1.25", "45").replace(&urlencoding::encode(&port).into_owned(), &urlencoding::encode("coé1"));
+    let (status, page) = bridge.page("/setup/v1/safetymonitor/0/setup", Some(&bad_port)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(page.contains("is not a serial port name"), "{}", page);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+
+    let (status, page) = bridge.page("/setup/v1/safetymonitor/0/setup", Some(&form("1.25", "45"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(page.contains("Tolerance set to 1.25°"), "{}", page);
+    assert!(!page.contains("setup-error"), "{}", page);
+    let saved = std::fs::read_to_string(&path).unwrap();
+    assert!(saved.starts_with("# Pier north\n[safety]\n# The sensor reports every second\n"), "{}", saved);
+    let config = BridgeConfig::load(&path).unwrap();
+    assert_eq!(config.safety.max_data_age_secs, 45);
+    assert_eq!(config.devices.len(), 1);
+    assert_eq!(config.devices[0].port.as_deref(), Some(port.as_str()));
+    // Applied to the running device as well, without reconnecting to the unchanged port
+    assert_eq!(bridge.connection_manager.max_data_age_secs(), 45);
+    assert_eq!(bridge.emulator.snapshot().tolerance, 1.25);
+    assert!(bridge.device_state.read().await.connected);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn web_users_need_sign_in_and_viewers_cannot_change_anything() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use base64::Engine;
+    use telescope_park_bridge::web_users::{self, WebUsers};
+    use tower::ServiceExt;
+
+    let text = format!(
+        "[[web_auth.users]]\nname = \"alice\"\nrole = \"admin\"\npassword_hash = \"{}\"\n\n\
+         [[web_auth.users]]\nname = \"bob\"\nrole = \"viewer\"\npassword_hash = \"{}\"",
+        web_users::hash_password("alice-pw").unwrap(),
+        web_users::hash_password("bob-pw").unwrap(),
+    );
+    let config = BridgeConfig::parse(&text).unwrap();
+    assert!(config.issues().is_empty(), "{}", config.issues());
+    let broken = BridgeConfig::parse(
+        "[web_auth]\nsession_hours = 0\n[[web_auth.users]]\nname = \"a:b\"\nrole = \"admin\"\npassword_hash = \"secret\"",
+    )
+    .unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["web_auth.users[0].name", "web_auth.users[0].password_hash", "web_auth.session_hours"]);
+
+    let mut bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    bridge.router = web_users::protect(create_router(devices, DiscoveryTracker::default()), WebUsers::new(&config.web_auth));
+
+    // ASCOM clients cannot sign in, so the Alpaca API stays open
+    let (status, _) = bridge.get("/api/status").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, body) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["ErrorNumber"], 0);
+    #[cfg(feature = "web-ui")]
+    {
+        let response = bridge.get_response("/setup", &[("accept", "text/html")]).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers()["location"], "/login?next=%2Fsetup");
+        assert_eq!(bridge.get_response("/login", &[]).await.status(), StatusCode::OK);
+    }
+
+    let sign_in = |name: &str, password: &str| {
+        let request = Request::post("/api/login")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "name": name, "password": password }).to_string()))
+            .unwrap();
+        bridge.router.clone().oneshot(request)
+    };
+    assert_eq!(sign_in("bob", "alice-pw").await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    let response = sign_in("bob", "bob-pw").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let set_cookie = response.headers()["set-cookie"].to_str().unwrap();
+    assert!(set_cookie.contains("HttpOnly") && set_cookie.contains("SameSite=Strict"), "{}", set_cookie);
+    let cookie = set_cookie.split(';').next().unwrap().to_string();
+    let viewer = [("cookie", cookie.as_str())];
+
+    let (status, body) = bridge.send_json(Method::GET, "/api/session", &viewer, json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["user"], json!({ "name": "bob", "role": "viewer" }));
+    let (status, body) = bridge.send_json(Method::GET, "/api/status", &viewer, json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["connected"], true);
+    let (status, body) = bridge.send_json(Method::POST, "/api/device/set_park", &viewer, json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["success"], false);
+
+    // API clients send an admin's credentials with every request instead of signing in
+    let basic = format!("Basic {}", base64::engine::general_purpose::STANDARD.encode("alice:alice-pw"));
+    let (status, body) = bridge
+        .send_json(Method::POST, "/api/device/set_park", &[("authorization", basic.as_str())], json!({}))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["success"], true);
+    // Verified credentials are remembered for a while; other passwords and unknown names are not
+    let (status, _) = bridge.send_json(Method::GET, "/api/status", &[("authorization", basic.as_str())], json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    for credentials in ["alice:bob-pw", "mallory:alice-pw"] {
+        let basic = format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials));
+        let (status, _) = bridge.send_json(Method::GET, "/api/status", &[("authorization", basic.as_str())], json!(null)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{}", credentials);
+    }
+
+    let (status, _) = bridge.send_json(Method::POST, "/api/logout", &viewer, json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = bridge.send_json(Method::GET, "/api/status", &viewer, json!(null)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn web_session_changes_need_the_csrf_token() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use telescope_park_bridge::web_users::{self, WebUsers};
+    use tower::ServiceExt;
+
+    let config = BridgeConfig::parse(&format!(
+        "[[web_auth.users]]\nname = \"alice\"\nrole = \"admin\"\npassword_hash = \"{}\"",
+        web_users::hash_password("alice-pw").unwrap()
+    ))
+    .unwrap();
+    let mut bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    bridge.router = web_users::protect(create_router(devices, DiscoveryTracker::default()), WebUsers::new(&config.web_auth));
+
+    let request = Request::post("/api/login")
+        .header("content-type", "application/json")
+        .body(Body::from(json!({ "name": "alice", "password": "alice-pw" }).to_string()))
+        .unwrap();
+    let response = bridge.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response.headers()["set-cookie"].to_str().unwrap().split(';').next().unwrap().to_string();
+    let (_, session) = bridge.send_json(Method::GET, "/api/session", &[("cookie", cookie.as_str())], json!(null)).await;
+    let token = session["csrf_token"].as_str().unwrap().to_string();
+    assert_eq!(token.len(), 64);
+
+    // What a page on another site could send through the signed-in browser
+    for headers in [vec![("cookie", cookie.as_str())], vec![("cookie", cookie.as_str()), ("x-csrf-token", "guess")]] {
+        let (status, body) = bridge.send_json(Method::POST, "/api/device/set_park", &headers, json!({})).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body["message"], "Missing or wrong CSRF token; reload the page");
+    }
+    let (status, body) = bridge
+        .send_json(Method::POST, "/api/device/set_park", &[("cookie", cookie.as_str()), ("x-csrf-token", token.as_str())], json!({}))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["success"], true);
+    // Reading needs no token
+    let (status, _) = bridge.send_json(Method::GET, "/api/status", &[("cookie", cookie.as_str())], json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn event_feed_needs_the_token_and_filters_by_category() {
+    use futures_util::StreamExt;
+    use telescope_park_bridge::config::EventsConfig;
+    use telescope_park_bridge::events::EventKind as BusEventKind;
+    use telescope_park_bridge::web_users::WebUsers;
+
+    let mut bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    let events = EventsConfig {
+        token: Some("feed-token".to_string()),
+    };
+    bridge.router = protect_event_feed(create_router(devices, DiscoveryTracker::default()), events, WebUsers::default());
+    let bus = bridge.connection_manager.event_bus().clone();
+    bus.publish(BusEventKind::SafetyForceCleared { device_number: 0, expired: true });
+    bus.publish(BusEventKind::FirmwareRebooted { previous_uptime: 500, uptime: 3 });
+
+    let (status, _) = bridge.get("/api/events").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = bridge.send_json(Method::GET, "/api/events", &[("authorization", "Bearer guess")], json!(null)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    // Only the feed is guarded by the token
+    let (status, _) = bridge.get("/api/status").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let token = [("authorization", "Bearer feed-token")];
+    let (status, body) = bridge.send_json(Method::GET, "/api/events?category=safety", &token, json!(null)).await;
+    assert_eq!(status, StatusCode::OK);
+    let kinds: Vec<&str> = body.as_array().unwrap().iter().map(|event| event["type"].as_str().unwrap()).collect();
+    assert_eq!(kinds, ["safety_force_cleared"]);
+    let (status, body) = bridge.send_json(Method::GET, "/api/events?category=safety,roof", &token, json!(null)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("'roof'"), "{}", body);
+
+    let response = bridge.get_response("/api/events/stream?category=telescope,safety&since=0", &token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/event-stream");
+    bus.publish(BusEventKind::HealthWarning {
+        issue: telescope_park_bridge::health::HealthIssue::SlowResponses,
+        message: "slow".to_string(),
+    });
+    let parked = bus.publish(BusEventKind::ParkStateChanged { parked: true, pitch: 1.0, roll: 2.0 });
+
+    // The buffered safety event is replayed, then new events follow; the sensor's are filtered out
+    let mut body = response.into_body().into_data_stream();
+    let mut text = String::new();
+    while !text.contains(&format!("id: {}", parked.id)) {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.next()).await.unwrap().unwrap().unwrap();
+        text.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+    let types: Vec<String> = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|data| serde_json::from_str::<serde_json::Value>(data).unwrap()["type"].as_str().unwrap().to_string())
+        .collect();
+    // The emulator may have published park changes of its own
+    assert!(types.iter().all(|kind| kind == "safety_force_cleared" || kind == "park_state_changed"), "{:?}", types);
+    assert!(types.contains(&"safety_force_cleared".to_string()), "{:?}", types);
+}
+
+#[tokio::test]
+async fn responses_are_compressed_when_accepted() {
+    let bridge = TestBridge::start().await;
+
+    for encoding in ["gzip", "br"] {
+        let response = bridge.get_response("/api/status", &[("accept-encoding", encoding)]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], encoding);
+    }
+    #[cfg(feature = "web-ui")]
+    {
+        let response = bridge.get_response("/", &[("accept-encoding", "gzip")]).await;
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+    }
+
+    // Clients that do not ask get plain responses
+    let response = bridge.get_response("/api/status", &[]).await;
+    assert!(response.headers().get("content-encoding").is_none());
+}
+
+#[tokio::test]
+async fn http_metrics_are_recorded_per_route() {
+    let bridge = TestBridge::start().await;
+
+    for _ in 0..3 {
+        bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    }
+    bridge.get("/api/v1/safetymonitor/7/issafe").await;
+
+    let (status, body) = bridge.get("/api/metrics").await;
+    assert!(status.is_success());
+    let routes = body["http"].as_array().unwrap();
+    let issafe = routes
+        .iter()
+        .find(|route| route["route"] == "/api/v1/safetymonitor/:device_number/issafe")
+        .expect("issafe route recorded");
+    assert_eq!(issafe["method"], "GET");
+    assert_eq!(issafe["requests"], 4);
+    assert_eq!(issafe["errors"], 1);
+    let bucketed: u64 = issafe["latency_buckets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|bucket| bucket["count"].as_u64().unwrap())
+        .sum();
+    assert_eq!(bucketed, 4);
+
+    // Serial backend health sits next to the endpoint figures
+    assert_eq!(body["devices"][0]["device_number"], 0);
+    assert!(body["devices"][0]["health"]["latency_samples"].as_u64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn configured_identity_is_advertised() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager.with_identity(IdentityConfig {
+            device_name: Some("North Pier Park Sensor".to_string()),
+            location: Some("North Pier".to_string()),
+            ..IdentityConfig::default()
+        })
+    })
+    .await;
+
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/name").await;
+    assert_eq!(body["Value"], "North Pier Park Sensor");
+    let (_, body) = bridge.get("/management/v1/configureddevices").await;
+    assert_eq!(body["Value"][0]["DeviceName"], "North Pier Park Sensor");
+    let (_, body) = bridge.get("/management/v1/description").await;
+    assert_eq!(body["Value"]["Location"], "North Pier");
+    assert_eq!(body["Value"]["Manufacturer"], "Corey Smart");
+}
+
+#[tokio::test]
+async fn multiple_devices_are_served_by_number() {
+    let mut north = TestBridge::start_with(|manager| {
+        manager.with_identity(IdentityConfig {
+            device_name: Some("North Pier Park Sensor".to_string()),
+            ..IdentityConfig::default()
+        })
+    })
+    .await;
+    let south = TestBridge::start_with(|manager| {
+        manager.with_identity(IdentityConfig {
+            device_name: Some("South Pier Park Sensor".to_string()),
+            ..IdentityConfig::default()
+        })
+    })
+    .await;
+    let handle = |number: u32, bridge: &TestBridge| DeviceHandle {
+        device_number: number,
+        device_state: bridge.device_state.clone(),
+        connection_manager: bridge.connection_manager.clone(),
+    };
+    let devices = DeviceRegistry::new(vec![handle(0, &north), handle(1, &south)]);
+    north.router = create_router(devices, DiscoveryTracker::default());
+
+    let (_, body) = north.get("/management/v1/configureddevices").await;
+    assert_eq!(body["Value"][0]["DeviceName"], "North Pier Park Sensor");
+    assert_eq!(body["Value"][1]["DeviceName"], "South Pier Park Sensor");
+    assert_eq!(body["Value"][1]["DeviceNumber"], 1);
+    assert_eq!(body["Value"][1]["DeviceType"], "SafetyMonitor");
+    let south_id = south.device_state.read().await.unique_id.clone();
+    assert_eq!(body["Value"][1]["UniqueID"], south_id.as_str());
+
+    // The page lists every served sensor once there is more than one
+    #[cfg(feature = "web-ui")]
+    {
+        let response = north.get_response("/", &[]).await;
+        let page = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let page = String::from_utf8(page.to_vec()).unwrap();
+        assert!(page.contains("Serving 2 sensors"));
+        assert!(page.contains("#1 South Pier Park Sensor"));
+    }
+
+    // Only the south mount moves away from its park position
+    north.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    south.emulator.set_position(25.0, -3.0);
+    south.wait_for(Duration::from_secs(5), |state| !state.is_safe).await;
+
+    let (_, body) = north.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(body["Value"], true);
+    let (_, body) = north.get("/api/v1/safetymonitor/1/issafe").await;
+    assert_eq!(body["Value"], false);
+    let (_, body) = north.get("/api/v1/safetymonitor/1/name").await;
+    assert_eq!(body["Value"], "South Pier Park Sensor");
+
+    let (status, body) = north.get("/api/v1/safetymonitor/2/issafe").await;
+    assert_eq!(status, 400);
+    assert_eq!(body["ErrorNumber"], 1024);
+
+    let (_, body) = north.get("/api/devices").await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+#[cfg(feature = "remote-sensors")]
+#[tokio::test]
+async fn remote_sensor_mirrors_another_bridge() {
+    let upstream = TestBridge::start().await;
+    upstream.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+
+    // Serve the upstream bridge over real HTTP, as on a distant machine
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let router = upstream.router.clone();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let mut central = TestBridge::start().await;
+    let device_state = Arc::new(RwLock::new(DeviceState::new()));
+    let manager = Arc::new(ConnectionManager::new(device_state.clone()));
+    manager
+        .connect_remote(telescope_park_bridge::config::RemoteConfig {
+            poll_interval_secs: 1,
+            ..telescope_park_bridge::config::RemoteConfig::new(&url)
+        })
+        .await
+        .unwrap();
+    central.device_state = device_state;
+    central.router = create_router(DeviceRegistry::single(central.device_state.clone(), manager), DiscoveryTracker::default());
+
+    central.wait_for(Duration::from_secs(10), |state| state.connected && state.is_safe).await;
+    let (_, body) = central.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(body["Value"], true);
+
+    // Moving the remote mount shows up on the central bridge
+    upstream.emulator.set_position(25.0, -3.0);
+    central.wait_for(Duration::from_secs(10), |state| !state.is_safe).await;
+    let (_, body) = central.get("/api/status").await;
+    assert_eq!(body["current_pitch"], 25.0);
+    assert_eq!(body["serial_port"], url.as_str());
+
+    // Commands are forwarded to the remote bridge's firmware
+    let (_, body) = central.post_json("/api/command", json!({ "command": "0B" })).await;
+    assert_eq!(body["success"], true, "forwarded command failed: {}", body);
+    assert!(upstream.emulator.snapshot().commands_received.iter().any(|c| c == "0B"));
+}
+
+#[tokio::test]
+async fn redundant_sensors_vote_on_issafe() {
+    let first = TestBridge::start().await;
+    let second = TestBridge::start().await;
+    first.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    second.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+
+    let mut voted = TestBridge::start().await;
+    let device_state = Arc::new(RwLock::new(DeviceState::new()));
+    let manager = Arc::new(ConnectionManager::new(device_state.clone()));
+    let member = |number: u32, bridge: &TestBridge| VotingMember {
+        device_number: number,
+        device_state: bridge.device_state.clone(),
+        max_data_age_secs: 30,
+    };
+    manager
+        .connect_voting(2, vec![member(0, &first), member(1, &second)], VotingPolicy::All)
+        .await
+        .unwrap();
+    voted.device_state = device_state;
+    voted.router = create_router(DeviceRegistry::single(voted.device_state.clone(), manager), DiscoveryTracker::default());
+
+    voted.wait_for(Duration::from_secs(5), |state| state.connected && state.is_safe).await;
+
+    // One sensor sees the mount move: under all-must-agree the vote turns unsafe
+    second.emulator.set_position(25.0, -3.0);
+    voted.wait_for(Duration::from_secs(5), |state| !state.is_safe).await;
+    let (_, body) = voted.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(body["Value"], false);
+
+    let (_, events) = voted.get("/api/events").await;
+    let disagreement = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["type"] == "sensor_disagreement")
+        .expect("no disagreement event");
+    assert_eq!(disagreement["is_safe"], false);
+    assert_eq!(disagreement["votes"][0]["is_safe"], true);
+    assert_eq!(disagreement["votes"][1]["is_safe"], false);
+}
+
+#[tokio::test]
+async fn health_monitor_flags_heap_loss_and_reboots() {
+    let bridge = TestBridge::start().await;
+    let health = bridge.connection_manager.health_monitor();
+    // Long uptime, so the simulated reboot below always reports a smaller one
+    bridge.emulator.state.lock().unwrap().booted_at = std::time::Instant::now() - Duration::from_secs(3600);
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while health.status().uptime.is_none_or(|uptime| uptime < 3_600_000) {
+        assert!(std::time::Instant::now() < deadline, "no status reading recorded");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Heap leaking well beyond the default 20% threshold
+    bridge.emulator.state.lock().unwrap().free_heap = 120_000;
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while health.status().warnings.is_empty() {
+        assert!(std::time::Instant::now() < deadline, "heap loss not flagged");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let (_, body) = bridge.get("/api/health").await;
+    assert_eq!(body["warnings"][0]["issue"], "heap_shrinking");
+    assert_eq!(body["heap_baseline"], 200_000);
+
+    // Uptime going backwards is a reboot, which also resets the heap baseline
+    bridge.emulator.state.lock().unwrap().booted_at = std::time::Instant::now();
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while health.status().reboots == 0 {
+        assert!(std::time::Instant::now() < deadline, "reboot not detected: {:?}", health.status());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let (_, events) = bridge.get("/api/events").await;
+    let events = events.as_array().unwrap();
+    assert!(events.iter().any(|e| e["type"] == "health_warning" && e["issue"] == "heap_shrinking"));
+    assert!(events.iter().any(|e| e["type"] == "firmware_rebooted"));
+    assert!(health.status().warnings.is_empty());
+}
+
+#[tokio::test]
+async fn health_monitor_flags_park_drift() {
+    use telescope_park_bridge::config::HealthConfig;
+    use telescope_park_bridge::events::{EventBus, EventKind as BusEventKind};
+    use telescope_park_bridge::health::{HealthIssue, HealthMonitor};
+
+    let config = HealthConfig {
+        park_drift_min_days: 1,
+        ..HealthConfig::default()
+    };
+    let bridge = TestBridge::start_with(|manager| manager.with_health(config)).await;
+    let health = bridge.connection_manager.health_monitor();
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    while health.status().park_drift.is_none() {
+        assert!(std::time::Instant::now() < deadline, "no parked reading recorded");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let (_, body) = bridge.get("/api/health").await;
+    assert_eq!(body["park_drift"]["days"], 1);
+
+    // Readings 1.2° off a park definition with 2° tolerance use up 60% of it
+    let events = EventBus::new();
+    let monitor = HealthMonitor::new(config, events.clone());
+    for _ in 0..99 {
+        monitor.record_parked_position(11.2, 3.0, 10.0, 3.0, 2.0);
+    }
+    assert!(monitor.status().warnings.is_empty(), "warned before enough readings");
+    monitor.record_parked_position(11.2, 3.0, 10.0, 3.0, 2.0);
+    let status = monitor.status();
+    let drift = status.park_drift.unwrap();
+    assert!((drift.pitch_offset - 1.2).abs() < 1e-4);
+    assert!((drift.percent_of_tolerance - 60.0).abs() < 0.01);
+    assert_eq!(status.warnings[0].issue, HealthIssue::ParkDrift);
+    assert!(events
+        .recent(None)
+        .iter()
+        .any(|event| matches!(event.kind, BusEventKind::HealthWarning { issue: HealthIssue::ParkDrift, .. })));
+
+    // A new park position starts over
+    monitor.record_parked_position(11.2, 3.0, 11.2, 3.0, 2.0);
+    assert!(monitor.status().warnings.is_empty());
+    assert_eq!(monitor.status().park_drift.unwrap().samples, 1);
+
+    let broken = BridgeConfig::parse("[health]\npark_drift_window_days = 2\npark_drift_min_days = 3").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["health.park_drift_min_days"]);
+}
+
+#[tokio::test]
+async fn noise_statistics_while_parked() {
+    let bridge = TestBridge::start().await;
+    let health = bridge.connection_manager.health_monitor();
+    bridge.emulator.state.lock().unwrap().noise_amplitude = 0.2;
+    health.park_session_started();
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(20);
+    while health.noise(2.0).suggested_tolerance.is_none() {
+        assert!(std::time::Instant::now() < deadline, "no noise statistics: {:?}", health.noise(2.0));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    let (status, body) = bridge.get("/api/noise").await;
+    assert!(status.is_success());
+    assert!(body["samples"].as_u64().unwrap() >= 10);
+    assert_eq!(body["window"], 600);
+    assert_eq!(body["position_tolerance"], 2.0);
+    for axis in ["pitch", "roll"] {
+        let std_dev = body[axis]["std_dev"].as_f64().unwrap();
+        let peak_to_peak = body[axis]["peak_to_peak"].as_f64().unwrap();
+        assert!(std_dev > 0.0 && std_dev < 0.2, "{}: {}", axis, body);
+        assert!(peak_to_peak > 0.0 && peak_to_peak <= 0.4 + 1e-6, "{}: {}", axis, body);
+    }
+    let suggested = body["suggested_tolerance"].as_f64().unwrap();
+    assert!((0.1..=0.8).contains(&suggested), "{}", body);
+
+    // A new park session starts over
+    health.park_session_started();
+    let (_, body) = bridge.get("/api/noise").await;
+    assert!(body["samples"].as_u64().unwrap() < 10);
+    assert!(body["pitch"].is_null());
+
+    let (status, _) = bridge.get("/api/noise?device_number=4").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn temperature_compensation_keeps_warm_sensor_parked() {
+    use telescope_park_bridge::config::{CompensationMode, TemperatureCompensationConfig};
+    use telescope_park_bridge::device_state::DeviceState;
+    use telescope_park_bridge::temperature_compensation::TemperatureCompensation;
+
+    // At 40 °C the mount tilts 3° on both axes, beyond the 2° tolerance
+    let config = TemperatureCompensationConfig {
+        mode: CompensationMode::Coefficients,
+        pitch_per_c: 0.15,
+        roll_per_c: 0.15,
+        ..TemperatureCompensationConfig::default()
+    };
+    let bridge = TestBridge::start_with(|manager| manager.with_temperature_compensation(config)).await;
+    {
+        let mut emulator = bridge.emulator.state.lock().unwrap();
+        emulator.temperature = Some(40.0);
+        emulator.tilt_per_degree = 0.15;
+    }
+    bridge
+        .wait_for(Duration::from_secs(5), |state| state.temperature == Some(40.0) && state.current_pitch.abs() < 0.01)
+        .await;
+    let state = bridge.device_state.read().await.clone();
+    assert!(state.is_parked && state.current_roll.abs() < 0.01, "{:?}", state);
+    let (status, body) = bridge.get("/api/compensation").await;
+    assert!(status.is_success());
+    assert_eq!(body["mode"], "coefficients");
+    assert!((body["pitch_correction"].as_f64().unwrap() - 3.0).abs() < 1e-4, "{}", body);
+    let (status, _) = bridge.get("/api/compensation?device_number=2").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Learned mode fits parked offsets of 0.1° per °C, then corrects a reading well outside
+    // the tolerance at 45 °C
+    let learned = TemperatureCompensation::new(TemperatureCompensationConfig {
+        mode: CompensationMode::Learned,
+        learn_min_samples: 50,
+        ..TemperatureCompensationConfig::default()
+    });
+    let state = DeviceState::new();
+    let reading = |temperature: f32| {
+        let offset = 0.1 * (temperature - 20.0);
+        json!({ "pitch": offset, "roll": -offset, "parked": offset.abs() <= 2.0, "temperature": temperature })
+    };
+    for step in 0..60 {
+        let mut data = reading(12.0 + (step % 20) as f32 * 0.8);
+        learned.apply(&mut data, &state);
+    }
+    let status = learned.status();
+    assert_eq!(status.learned_samples, 60);
+    assert!((status.pitch_per_c.unwrap() - 0.1).abs() < 1e-3, "{:?}", status);
+    assert!((status.roll_per_c.unwrap() + 0.1).abs() < 1e-3, "{:?}", status);
+    let mut data = reading(45.0);
+    learned.apply(&mut data, &state);
+    assert!(data["pitch"].as_f64().unwrap().abs() < 0.01, "{}", data);
+    assert_eq!(data["parked"], true);
+
+    let broken = BridgeConfig::parse("[temperature_compensation]\nmode = \"coefficients\"\nroll_per_c = 5.0").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["temperature_compensation.roll_per_c"]);
+}
+
+#[tokio::test]
+async fn set_park_updates_firmware_park_position() {
+    let bridge = TestBridge::start().await;
+
+    bridge.emulator.set_position(12.5, 4.0);
+    bridge.wait_for(Duration::from_secs(5), |state| !state.is_parked).await;
+
+    let (_, body) = bridge.post_json("/api/device/set_park", json!({})).await;
+    assert_eq!(body["success"], true, "set_park failed: {}", body);
+
+    let emulator_state = bridge.emulator.snapshot();
+    assert_eq!(emulator_state.park_pitch, 12.5);
+    assert_eq!(emulator_state.park_roll, 4.0);
+    assert!(emulator_state.commands_received.iter().any(|c| c == "0D"));
+
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_parked).await;
+}
+
+#[tokio::test]
+async fn set_park_averages_readings_and_rejects_motion() {
+    use telescope_park_bridge::config::SetParkConfig;
+    use telescope_park_bridge::park_capture::average_without_outliers;
+
+    // One bumped reading out of ten does not move the average
+    let mut readings = vec![(10.0, 3.0); 9];
+    readings.push((14.0, 3.0));
+    let average = average_without_outliers(&readings, 3.0).unwrap();
+    assert_eq!((average.samples, average.rejected), (10, 1));
+    assert!((average.pitch - 10.0).abs() < 1e-6 && (average.roll - 3.0).abs() < 1e-6);
+
+    let config = SetParkConfig {
+        samples: 20,
+        interval_ms: 20,
+        ..SetParkConfig::default()
+    };
+    let bridge = TestBridge::start_with(|manager| manager.with_set_park(config)).await;
+    {
+        let mut emulator = bridge.emulator.state.lock().unwrap();
+        emulator.pitch = 12.5;
+        emulator.roll = 4.0;
+        emulator.noise_amplitude = 0.3;
+    }
+    let (_, body) = bridge.post_json("/api/device/set_park", json!({})).await;
+    assert_eq!(body["success"], true, "set_park failed: {}", body);
+    let capture: serde_json::Value = serde_json::from_str(body["response"].as_str().unwrap()).unwrap();
+    assert_eq!(capture["samples"], 20);
+    assert!((capture["pitch"].as_f64().unwrap() - 12.5).abs() < 0.15, "{}", capture);
+    assert!((capture["roll"].as_f64().unwrap() - 4.0).abs() < 0.15, "{}", capture);
+    let attempts = capture["attempts"].as_u64().unwrap();
+    assert!((1..=3).contains(&attempts), "{}", capture);
+    let emulator = bridge.emulator.snapshot();
+    assert_eq!(capture["park_pitch"].as_f64().unwrap() as f32, emulator.park_pitch);
+    assert_eq!(emulator.commands_received.iter().filter(|c| *c == "0D").count() as u64, attempts);
+
+    // Readings scattered by a slewing telescope leave the park position alone
+    let park_before = (emulator.park_pitch, emulator.park_roll);
+    bridge.emulator.state.lock().unwrap().noise_amplitude = 5.0;
+    let (_, body) = bridge.post_json("/api/device/set_park", json!({})).await;
+    assert_eq!(body["success"], false, "{}", body);
+    assert!(body["message"].as_str().unwrap().contains("moving"), "{}", body);
+    let emulator = bridge.emulator.snapshot();
+    assert_eq!((emulator.park_pitch, emulator.park_roll), park_before);
+    assert_eq!(emulator.commands_received.iter().filter(|c| *c == "0D").count() as u64, attempts);
+
+    let broken = BridgeConfig::parse("[set_park]\nsamples = 0\nmax_std_dev = -1.0").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["set_park.samples", "set_park.max_std_dev"]);
+}
+
+#[tokio::test]
+async fn manual_command_returns_data_response() {
+    let bridge = TestBridge::start().await;
+
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    assert_eq!(body["success"], true, "command failed: {}", body);
+    let response: serde_json::Value = serde_json::from_str(body["response"].as_str().unwrap()).unwrap();
+    assert_eq!(response["status"], "ok");
+    assert_eq!(response["data"]["tolerance"], 2.0);
+}
+
+#[tokio::test]
+async fn jobs_run_long_operations_in_the_background() {
+    let bridge = TestBridge::start().await;
+
+    let (status, job) = bridge.post_json("/api/jobs", json!({ "operation": "calibrate" })).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(job["status"], "running");
+    assert_eq!(job["timeout_ms"], 30000);
+    let uri = format!("/api/jobs/{}", job["id"]);
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    let job = loop {
+        let (_, job) = bridge.get(&uri).await;
+        if job["status"] != "running" {
+            break job;
+        }
+        assert!(tokio::time::Instant::now() < deadline, "job still running: {}", job);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    };
+    assert_eq!(job["status"], "succeeded", "{}", job);
+    assert!(job["response"].as_str().unwrap().contains("ok"));
+    assert_eq!(job["phase"], "computing_offsets");
+    assert_eq!(job["percent"], 100);
+
+    let (_, events) = bridge.get("/api/events").await;
+    let progress: Vec<_> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|event| event["type"] == "calibration_progress")
+        .map(|event| (event["phase"].as_str().unwrap().to_string(), event["percent"].as_u64()))
+        .collect();
+    assert_eq!(
+        progress,
+        [
+            ("collecting_samples".to_string(), Some(0)),
+            ("collecting_samples".to_string(), Some(50)),
+            ("computing_offsets".to_string(), Some(100)),
+        ]
+    );
+
+    // Deleting a finished job forgets it
+    let (status, _) = bridge.delete(&uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = bridge.get(&uri).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, _) = bridge.post_json("/api/jobs", json!({ "operation": "calibrate", "device_number": 7 })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn cancelling_a_job_frees_the_command_pipeline() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager
+            .with_heartbeat(HeartbeatConfig { interval_secs: 0, max_missed: 1 })
+            .with_command_api(CommandApiConfig {
+                abort_command: Some("07".to_string()),
+                ..CommandApiConfig::default()
+            })
+    })
+    .await;
+
+    // The calibration is acknowledged but its data response never arrives
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        stale_secs: 60,
+        ..FaultPlan::default()
+    });
+    bridge.emulator.state.lock().unwrap().faults = faults;
+    let (_, job) = bridge.post_json("/api/jobs", json!({ "operation": "calibrate" })).await;
+    let uri = format!("/api/jobs/{}", job["id"]);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let (status, job) = bridge.delete(&uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(job["status"], "cancelled");
+
+    // The abort code is written and other commands no longer wait behind the calibration
+    bridge.emulator.state.lock().unwrap().faults.clear();
+    let started = std::time::Instant::now();
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    assert_eq!(body["success"], true, "command failed: {}", body);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(bridge.emulator.snapshot().commands_received.iter().any(|command| command == "07"));
+
+    let (_, history) = bridge.get("/api/command/history").await;
+    assert_eq!(history[0]["command"], "06");
+    assert_eq!(history[0]["outcome"], "cancelled");
+}
+
+#[tokio::test]
+async fn abandoned_commands_do_not_hold_back_later_ones() {
+    use telescope_park_bridge::errors::BridgeError;
+    use tokio_util::sync::CancellationToken;
+
+    let bridge = TestBridge::start_with(|manager| manager.with_heartbeat(HeartbeatConfig { interval_secs: 0, max_missed: 1 })).await;
+    let silence = |bridge: &TestBridge| {
+        let mut faults = FaultInjector::new(true);
+        faults.inject(FaultPlan {
+            stale_secs: 60,
+            ..FaultPlan::default()
+        });
+        bridge.emulator.state.lock().unwrap().faults = faults;
+    };
+
+    // An HTTP client that gives up: axum drops the handler future with the request
+    silence(&bridge);
+    let gave_up = tokio::time::timeout(Duration::from_millis(500), bridge.post_json("/api/command", json!({ "command": "0B" }))).await;
+    assert!(gave_up.is_err());
+    bridge.emulator.state.lock().unwrap().faults.clear();
+    let started = std::time::Instant::now();
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    assert_eq!(body["success"], true, "command failed: {}", body);
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // A cancelled token ends the wait right away
+    silence(&bridge);
+    let cancel = CancellationToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        trigger.cancel();
+    });
+    let started = std::time::Instant::now();
+    let result = bridge.connection_manager.send_command_with_cancel("0B", &cancel).await;
+    assert!(matches!(result, Err(BridgeError::Cancelled)), "{:?}", result);
+    assert!(started.elapsed() < Duration::from_secs(2));
+    bridge.emulator.state.lock().unwrap().faults.clear();
+    let started = std::time::Instant::now();
+    bridge.connection_manager.send_command("0B").await.expect("command after the cancelled one failed");
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let (_, history) = bridge.get("/api/command/history").await;
+    let outcomes: Vec<&str> = history.as_array().unwrap().iter().map(|entry| entry["outcome"].as_str().unwrap()).collect();
+    assert_eq!(outcomes, ["cancelled", "completed", "cancelled", "completed"], "{}", history);
+}
+
+#[tokio::test]
+async fn command_history_records_replies_and_failures() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager.with_command_api(CommandApiConfig {
+            expert_mode: true,
+            ..CommandApiConfig::default()
+        })
+    })
+    .await;
+
+    bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    bridge.post_json("/api/command", json!({ "command": "FF" })).await;
+
+    let (_, history) = bridge.get("/api/command/history").await;
+    let history = history.as_array().unwrap();
+    assert_eq!(history.len(), 2, "{:?}", history);
+    assert_eq!(history[0]["command"], "0B");
+    assert_eq!(history[0]["outcome"], "completed");
+    assert!(history[0]["ack"].as_str().unwrap().contains("ack"));
+    assert!(history[0]["response"].as_str().unwrap().contains("tolerance"));
+    assert_eq!(history[1]["command"], "FF");
+    assert_eq!(history[1]["outcome"], "failed");
+    assert!(history[1]["error"].as_str().unwrap().contains("Unknown command"));
+}
+
+#[tokio::test]
+async fn history_and_events_are_paged_and_filtered() {
+    let bridge = TestBridge::start().await;
+    for _ in 0..3 {
+        bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    }
+    bridge.post_json("/api/command", json!({ "command": "08" })).await;
+
+    let response = bridge.get_response("/api/command/history?order=desc&limit=2", &[]).await;
+    assert_eq!(response.headers()["x-total-count"], "4");
+    let (_, page) = bridge.get("/api/command/history?order=desc&limit=2").await;
+    let page = page.as_array().unwrap();
+    assert_eq!(page.len(), 2);
+    assert_eq!(page[0]["command"], "08");
+    assert_eq!(page[0]["id"], 4);
+    assert_eq!(page[1]["id"], 3);
+
+    let (_, page) = bridge.get("/api/command/history?offset=1&limit=1").await;
+    assert_eq!(page[0]["id"], 2);
+    let (_, page) = bridge.get("/api/command/history?since=3").await;
+    assert_eq!(page.as_array().unwrap().len(), 1);
+    let (_, page) = bridge.get("/api/command/history?to=0").await;
+    assert!(page.as_array().unwrap().is_empty());
+    let (status, _) = bridge.get("/api/command/history?order=sideways").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    let message = bridge.emulator.state.lock().unwrap().event_message("park_changed");
+    bridge.emulator.unsolicited.send(message.to_string()).unwrap();
+    // Replies arrive in order, so the event has been handled once this command returns
+    bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    let (_, events) = bridge.get(&format!("/api/events?from={}&order=desc&limit=1", now)).await;
+    assert_eq!(events.as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn api_timestamps_are_rfc3339() {
+    let bridge = TestBridge::start().await;
+    bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+
+    let (_, status) = bridge.get("/api/status").await;
+    let last_update = chrono::DateTime::parse_from_rfc3339(status["last_update"].as_str().unwrap()).unwrap();
+    assert!((chrono::Utc::now() - last_update.to_utc()).num_seconds().abs() < 60);
+    let (_, history) = bridge.get("/api/command/history").await;
+    let sent_at = history[0]["sent_at"].as_str().unwrap();
+    assert!(sent_at.ends_with('Z') && chrono::DateTime::parse_from_rfc3339(sent_at).is_ok(), "{}", sent_at);
+
+    let (_, events) = bridge.get("/api/events?order=desc&limit=1").await;
+    let timestamp = events[0]["timestamp"].as_str().unwrap().to_string();
+    let (_, page) = bridge.get(&format!("/api/events?from={}", timestamp)).await;
+    assert!(!page.as_array().unwrap().is_empty());
+    let (_, page) = bridge.get("/api/events?from=2999-01-01T00:00:00Z").await;
+    assert!(page.as_array().unwrap().is_empty());
+
+    // Status from an older bridge still carries epoch seconds
+    let mut legacy = status.clone();
+    legacy["last_update"] = json!(1_700_000_000);
+    let state: DeviceState = serde_json::from_value(legacy).unwrap();
+    assert_eq!(state.last_update, 1_700_000_000);
+}
+
+#[tokio::test]
+async fn forced_safety_overrides_issafe_until_it_expires() {
+    let request = json!({ "state": "unsafe", "minutes": 0.03, "reason": "shutdown drill" });
+    let disabled = TestBridge::start().await;
+    let (status, _) = disabled.post_json("/api/safety/force", request.clone()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let bridge = TestBridge::start_with(|manager| {
+        manager.with_safety_force(SafetyForceConfig {
+            token: Some("s3cret".to_string()),
+            ..SafetyForceConfig::default()
+        })
+    })
+    .await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+
+    let wrong = [("authorization", "Bearer nope")];
+    let (status, _) = bridge.send_json(Method::POST, "/api/safety/force", &wrong, request.clone()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let auth = [("authorization", "Bearer s3cret")];
+    let too_long = json!({ "state": "unsafe", "minutes": 121 });
+    let (status, _) = bridge.send_json(Method::POST, "/api/safety/force", &auth, too_long).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, forced) = bridge.send_json(Method::POST, "/api/safety/force", &auth, request).await;
+    assert_eq!(status, StatusCode::OK, "{}", forced);
+    assert_eq!(forced["is_safe"], false);
+    let (_, issafe) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(issafe["Value"], false);
+    let (_, status) = bridge.get("/api/status").await;
+    assert_eq!(status["is_safe"], false);
+    assert_eq!(status["safety_override"]["reason"], "shutdown drill");
+    let (_, devices) = bridge.get("/api/devices").await;
+    assert_eq!(devices[0]["forced"], true);
+
+    // The override lapses by itself and the sensor's own IsSafe applies again
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    let (_, issafe) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(issafe["Value"], true);
+    let (_, events) = bridge.get("/api/events").await;
+    let kinds: Vec<&str> = events.as_array().unwrap().iter().filter_map(|event| event["type"].as_str()).collect();
+    assert!(kinds.contains(&"safety_forced") && kinds.contains(&"safety_force_cleared"), "{:?}", kinds);
+
+    bridge.send_json(Method::POST, "/api/safety/force", &auth, json!({ "state": "unsafe", "minutes": 5 })).await;
+    let (_, cleared) = bridge.send_json(Method::DELETE, "/api/safety/force", &auth, serde_json::Value::Null).await;
+    assert_eq!(cleared["message"], "Forced IsSafe cleared");
+    let (_, issafe) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(issafe["Value"], true);
+}
+
+#[tokio::test]
+async fn sun_above_the_limit_makes_issafe_false() {
+    use telescope_park_bridge::events::EventKind as BusEventKind;
+    use telescope_park_bridge::sun_safety::{sun_altitude, SunSafety};
+
+    // Berlin at the June solstice: local noon and midnight
+    let (noon, midnight) = (1_718_968_020, 1_719_011_220);
+    assert!((sun_altitude(noon, 52.52, 13.40) - 60.92).abs() < 0.2);
+    assert!((sun_altitude(midnight, 52.52, 13.40) + 14.04).abs() < 0.2);
+
+    let mut bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    bridge.router = create_router(devices.clone(), DiscoveryTracker::default());
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    let config = BridgeConfig::parse("[sun_safety]\nlatitude = 52.52\nlongitude = 13.40").unwrap();
+    assert!(config.issues().is_empty(), "{}", config.issues());
+    let mut events = bridge.connection_manager.event_bus().subscribe();
+    // The sun_altitude_crossed events published so far, skipping the sensor's own
+    let mut crossings = move || {
+        let mut crossings = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let BusEventKind::SunAltitudeCrossed { above, forces_unsafe, .. } = event.kind {
+                crossings.push((above, forces_unsafe));
+            }
+        }
+        crossings
+    };
+
+    // Down at startup: nothing to announce
+    let mut sun = SunSafety::new(config.sun_safety);
+    sun.update(&devices, midnight).await;
+    assert_eq!(crossings(), []);
+    let altitude = sun.update(&devices, noon).await.unwrap();
+    assert!(altitude > 60.0);
+    assert_eq!(crossings(), [(true, true)]);
+    let (_, issafe) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(issafe["Value"], false);
+    let (_, status) = bridge.get("/api/status").await;
+    assert_eq!((status["is_safe"].clone(), status["sun_unsafe"].clone()), (json!(false), json!(true)));
+    let (_, summary) = bridge.get("/status.json").await;
+    assert!(summary["text"].as_str().unwrap().ends_with("| sun up"), "{}", summary);
+
+    sun.update(&devices, noon + 60).await;
+    sun.update(&devices, midnight).await;
+    assert_eq!(crossings(), [(false, true)]);
+    let (_, issafe) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(issafe["Value"], true);
+
+    // Warning only: the event, but IsSafe follows the sensor
+    let config = BridgeConfig::parse("[sun_safety]\nlatitude = 52.52\nlongitude = 13.40\naction = \"warn\"").unwrap();
+    let mut sun = SunSafety::new(config.sun_safety);
+    sun.update(&devices, noon).await;
+    assert_eq!(crossings(), [(true, false)]);
+    let (_, issafe) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(issafe["Value"], true);
+
+    let broken = BridgeConfig::parse("[sun_safety]\nlatitude = 95.0\nmax_altitude = -30.0").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["sun_safety", "sun_safety.latitude", "sun_safety.max_altitude"]);
+}
+
+#[tokio::test]
+async fn safety_schedules_hold_issafe_false_in_their_windows() {
+    use telescope_park_bridge::safety_schedule::SafetySchedules;
+
+    let config = BridgeConfig::parse(
+        "[[safety_schedules]]\nname = \"daytime\"\nstart = \"10:00\"\nend = \"16:00\"\ndays = [\"mon\"]\n\
+         [[safety_schedules]]\nname = \"roof maintenance\"\nstart = \"22:00\"\nend = \"02:00\"\ndays = [\"tue\"]\n",
+    )
+    .unwrap();
+    assert!(config.issues().is_empty(), "{}", config.issues());
+    let schedules = SafetySchedules::new(config.safety_schedules);
+    // Monday 2024-05-06 00:00 UTC, the display timezone in tests
+    let monday = 1_714_953_600;
+    let at = |day: u64, hour: u64, minute: u64| monday + day * 86_400 + hour * 3600 + minute * 60;
+    let active = |now: u64| schedules.active(0, now).map(|schedule| schedule.name.as_str());
+    assert_eq!(active(at(0, 9, 59)), None);
+    assert_eq!(active(at(0, 10, 0)), Some("daytime"));
+    assert_eq!(active(at(0, 16, 0)), None);
+    assert_eq!(active(at(1, 12, 0)), None);
+    // Starts Tuesday evening and runs into Wednesday, but not from Monday into Tuesday
+    assert_eq!(active(at(1, 1, 0)), None);
+    assert_eq!(active(at(1, 23, 0)), Some("roof maintenance"));
+    assert_eq!(active(at(2, 1, 59)), Some("roof maintenance"));
+    assert_eq!(active(at(2, 2, 0)), None);
+
+    let mut bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    bridge.router = create_router(devices.clone(), DiscoveryTracker::default());
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+
+    schedules.update(&devices, at(0, 12, 0)).await;
+    let (_, issafe) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(issafe["Value"], false);
+    let (_, state) = bridge.get("/api/v1/safetymonitor/0/devicestate").await;
+    let schedule = state["Value"].as_array().unwrap().iter().find(|value| value["Name"] == "SafetySchedule").unwrap();
+    assert_eq!(schedule["Value"], "daytime");
+    let (_, status) = bridge.get("/api/status").await;
+    assert_eq!((status["is_safe"].clone(), status["safety_schedule"].clone()), (json!(false), json!("daytime")));
+
+    schedules.update(&devices, at(0, 16, 30)).await;
+    let (_, issafe) = bridge.get("/api/v1/safetymonitor/0/issafe").await;
+    assert_eq!(issafe["Value"], true);
+    let (_, state) = bridge.get("/api/v1/safetymonitor/0/devicestate").await;
+    assert!(state["Value"].as_array().unwrap().iter().any(|value| value["Name"] == "SafetySchedule" && value["Value"] == ""));
+    let (_, events) = bridge.get("/api/events?category=safety").await;
+    let changes: Vec<(String, bool)> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| (event["schedule"].as_str().unwrap().to_string(), event["active"].as_bool().unwrap()))
+        .collect();
+    assert_eq!(changes, [("daytime".to_string(), true), ("daytime".to_string(), false)]);
+
+    let broken = BridgeConfig::parse(
+        "[[safety_schedules]]\nname = \"a\"\nstart = \"10:00\"\nend = \"10:00\"\ndevices = [3]\n\
+         [[safety_schedules]]\nname = \"a\"\nstart = \"01:00\"\nend = \"02:00\"\n",
+    )
+    .unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["safety_schedules[0].end", "safety_schedules[0].devices", "safety_schedules[1].name"]);
+    let error = BridgeConfig::parse("[[safety_schedules]]\nname = \"a\"\nstart = \"25:00\"\nend = \"10:00\"\n").unwrap_err();
+    assert!(error.contains("'25:00' is not a time of day"), "{}", error);
+}
+
+#[tokio::test]
+async fn maintenance_mode_is_flagged_everywhere_until_it_expires() {
+    let bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+
+    let (status, _) = bridge.post_json("/api/maintenance", json!({ "minutes": 481 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = bridge.post_json("/api/maintenance", json!({ "device_number": 3 })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, started) = bridge.post_json("/api/maintenance", json!({ "minutes": 0.03, "reason": "collimation" })).await;
+    assert_eq!(status, StatusCode::OK, "{}", started);
+    assert_eq!(started["reason"], "collimation");
+
+    let (_, status) = bridge.get("/api/status").await;
+    assert_eq!(status["maintenance"]["reason"], "collimation");
+    // Only alerts are held back; IsSafe still follows the sensor
+    assert_eq!(status["is_safe"], true);
+    let (_, devices) = bridge.get("/api/devices").await;
+    assert_eq!(devices[0]["maintenance"], true);
+    let (_, state) = bridge.get("/api/v1/safetymonitor/0/devicestate").await;
+    assert!(state["Value"].as_array().unwrap().iter().any(|value| value["Name"] == "Maintenance" && value["Value"] == true));
+    let (_, summary) = bridge.get("/status.json").await;
+    assert_eq!(summary["maintenance"], true);
+    assert!(summary["text"].as_str().unwrap().ends_with("| MAINTENANCE"), "{}", summary);
+
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    let (_, status) = bridge.get("/api/status").await;
+    assert_eq!(status["maintenance"], serde_json::Value::Null);
+    let (_, events) = bridge.get("/api/events?category=safety").await;
+    let kinds: Vec<(&str, bool)> = events
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|event| (event["type"].as_str().unwrap(), event["expired"].as_bool().unwrap_or(false)))
+        .collect();
+    assert_eq!(kinds, [("maintenance_started", false), ("maintenance_ended", true)]);
+
+    bridge.post_json("/api/maintenance", json!({})).await;
+    let (_, devices) = bridge.get("/api/devices").await;
+    assert_eq!(devices[0]["maintenance"], true);
+    let (_, ended) = bridge.delete("/api/maintenance").await;
+    assert_eq!(ended["message"], "Maintenance mode ended");
+    let (_, ended) = bridge.delete("/api/maintenance").await;
+    assert_eq!(ended["message"], "Maintenance mode was not on");
+
+    let broken = BridgeConfig::parse("[maintenance]\ndefault_minutes = 600").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["maintenance.default_minutes"]);
+}
+
+#[tokio::test]
+async fn notifications_are_deduplicated_and_snoozed_per_channel() {
+    use telescope_park_bridge::notifications::{Delivery, Notifications};
+
+    let mut bridge = TestBridge::start().await;
+    let notifications = Notifications::new(BridgeConfig::default().notifications);
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone())
+        .with_notifications(notifications.clone());
+    bridge.router = create_router(devices, DiscoveryTracker::default());
+    notifications.register("chat_bot");
+
+    // A flapping sensor: the same texts again and again within dedup_secs (600)
+    let start = 1_700_000_000;
+    assert_eq!(notifications.deliver("chat_bot", "Parked", start), Delivery::Send { held_back: 0 });
+    assert_eq!(notifications.deliver("chat_bot", "Not parked", start + 5), Delivery::Send { held_back: 0 });
+    for offset in 1..=3 {
+        assert_eq!(notifications.deliver("chat_bot", "Parked", start + 10 * offset), Delivery::Duplicate);
+    }
+    assert_eq!(notifications.deliver("chat_bot", "Parked", start + 600), Delivery::Send { held_back: 3 });
+    assert_eq!(notifications.deliver("chat_bot", "Not parked", start + 605), Delivery::Send { held_back: 0 });
+
+    let (_, channels) = bridge.get("/api/notifications").await;
+    assert_eq!(channels[0]["channel"], "chat_bot");
+    assert_eq!(channels[0]["duplicate_count"], 3);
+    assert_eq!(channels[0]["snoozed_until"], serde_json::Value::Null);
+
+    let (status, _) = bridge.post_json("/api/notifications/chat_bot/snooze", json!({ "minutes": 721 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = bridge.post_json("/api/notifications/pushover/snooze", json!({ "minutes": 30 })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["message"], "No notification channel pushover (channels: chat_bot)");
+    let (status, snoozed) = bridge.post_json("/api/notifications/chat_bot/snooze", json!({ "minutes": 30 })).await;
+    assert_eq!(status, StatusCode::OK, "{}", snoozed);
+    assert!(snoozed["snoozed_until"].is_string(), "{}", snoozed);
+
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    assert_eq!(notifications.deliver("chat_bot", "Sensor firmware rebooted", now), Delivery::Snoozed);
+    let (_, resumed) = bridge.delete("/api/notifications/chat_bot/snooze").await;
+    assert_eq!(resumed["message"], "Notifications on chat_bot resumed");
+    assert_eq!(notifications.deliver("chat_bot", "Sensor firmware rebooted", now), Delivery::Send { held_back: 0 });
+    let (_, channels) = bridge.get("/api/notifications").await;
+    assert_eq!(channels[0]["snoozed_count"], 1);
+
+    let broken = BridgeConfig::parse("[notifications]\nmax_snooze_minutes = 0").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["notifications.max_snooze_minutes"]);
+}
+
+#[test]
+fn older_config_schemas_are_upgraded_on_load() {
+    // Unversioned files predate the [[devices]] name -> device_name rename
+    let legacy = "[[devices]]\ndevice_number = 0\nname = \"North Pier\"\n";
+    let config = BridgeConfig::parse(legacy).unwrap();
+    assert_eq!(config.schema_version, CURRENT_SCHEMA_VERSION);
+    assert_eq!(config.devices[0].device_name.as_deref(), Some("North Pier"));
+
+    let current = format!("schema_version = {}\n[[devices]]\ndevice_number = 0\nname = \"North Pier\"\n", CURRENT_SCHEMA_VERSION);
+    let error = BridgeConfig::parse(&current).unwrap_err();
+    assert!(error.contains("unknown field `name`") && error.contains("line 4"), "{}", error);
+
+    // Version 2 files had a single [telescope]
+    let config = BridgeConfig::parse("schema_version = 2\n[telescope]\nurl = \"http://mount:11111\"\n").unwrap();
+    assert_eq!(config.telescopes.len(), 1);
+    assert_eq!((config.telescopes[0].id.as_str(), config.telescopes[0].url.as_str()), ("main", "http://mount:11111"));
+
+    let error = BridgeConfig::parse("schema_version = 99").unwrap_err();
+    assert!(error.contains("newer"), "{}", error);
+}
+
+#[test]
+fn config_problems_are_all_reported_by_field() {
+    let text = r#"
+        [heartbeat]
+        interval_secs = 5000
+
+        [[devices]]
+        device_number = 1
+        port = "ttyACM0"
+        baud = 12
+
+        [devices.remote]
+        url = "http://pier-west.local:11111"
+    "#;
+    let issues = BridgeConfig::parse(text).unwrap().issues();
+    let fields: Vec<&str> = issues.iter().map(|issue| issue.field.as_str()).collect();
+    for field in ["heartbeat.interval_secs", "devices", "devices[0].port", "devices[0].baud"] {
+        assert!(fields.contains(&field), "{} missing from {}", field, issues);
+    }
+    let summary = issues.to_string();
+    assert!(summary.contains("mutually exclusive") && summary.contains("/dev/ttyACM0"), "{}", summary);
+
+    assert!(BridgeConfig::parse("[[devices]]\ndevice_number = 0\nport = \"COM3\"").unwrap().issues().is_empty());
+}
+
+#[cfg(feature = "mdns")]
+#[tokio::test]
+async fn bridge_is_advertised_via_mdns() {
+    use telescope_park_bridge::config::MdnsConfig;
+    use telescope_park_bridge::mdns::{host_label, MdnsAdvertiser};
+
+    assert_eq!(host_label("Pier 2 — Park Bridge"), "pier-2-park-bridge");
+    assert_eq!(host_label("***"), "park-bridge");
+    let broken = BridgeConfig::parse("[mdns]\ninstance_name = \"\"\nhost_name = \"pier.local\"").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["mdns.instance_name", "mdns.host_name"]);
+
+    let bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    let config = MdnsConfig {
+        instance_name: Some("Test Park Bridge".to_string()),
+        ..MdnsConfig::default()
+    };
+    let advertiser = MdnsAdvertiser::start(&config, "127.0.0.1".parse().unwrap(), 11111, &devices)
+        .await
+        .expect("mDNS registration failed");
+    advertiser.stop().await;
+}
+
+#[tokio::test]
+async fn discover_lists_responding_servers_and_their_devices() {
+    use telescope_park_bridge::alpaca_scan::{self, ScanOptions};
+
+    let bridge = TestBridge::start().await;
+    let url = bridge.serve().await;
+    let alpaca_port: u16 = url.rsplit(':').next().unwrap().parse().unwrap();
+
+    // Stands in for the discovery server, which always binds the well-known port
+    let responder = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let discovery_port = responder.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut buf = [0; 64];
+        while let Ok((len, from)) = responder.recv_from(&mut buf).await {
+            if &buf[..len] == b"alpacadiscovery1" {
+                let reply = json!({ "AlpacaPort": alpaca_port }).to_string();
+                let _ = responder.send_to(reply.as_bytes(), from).await;
+            }
+        }
+    });
+
+    let options = ScanOptions {
+        targets: vec!["127.0.0.1".parse().unwrap()],
+        discovery_port,
+        timeout: Duration::from_millis(500),
+    };
+    let servers = alpaca_scan::scan(&options).await.unwrap();
+    assert_eq!(servers.len(), 1);
+    let server = &servers[0];
+    assert_eq!(server.alpaca_port, alpaca_port);
+    assert!(server.error.is_none(), "{:?}", server.error);
+    assert!(server.server_name.is_some());
+    assert_eq!(server.devices.len(), 1);
+    assert_eq!(server.devices[0].device_type, "SafetyMonitor");
+    assert_eq!(server.devices[0].device_number, 0);
+
+    let table = alpaca_scan::format_table(&servers);
+    assert!(table.contains(&format!("127.0.0.1:{}", alpaca_port)));
+    assert!(table.contains("SafetyMonitor"));
+    assert!(table.contains("1 server(s) found"));
+}
+
+#[test]
+fn system_log_sends_syslog_lines() {
+    use std::os::unix::net::UnixDatagram;
+    use telescope_park_bridge::config::{LogTarget, LoggingConfig};
+    use telescope_park_bridge::system_log::{self, SystemLogLayer};
+    use tracing_subscriber::prelude::*;
+
+    let broken = BridgeConfig::parse("[logging]\nconsole = false\nname = \"\"").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["logging.name", "logging.console"]);
+    let eventlog = BridgeConfig::parse("[logging]\ntarget = \"eventlog\"").unwrap();
+    assert!(eventlog.issues().iter().any(|issue| issue.field == "logging.target"));
+
+    let dir = std::env::temp_dir().join(format!("park-bridge-syslog-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("log.sock");
+    let daemon = UnixDatagram::bind(&path).unwrap();
+    daemon.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    let config: LoggingConfig = BridgeConfig::parse(&format!(
+        "[logging]\ntarget = \"syslog\"\nlevel = \"warn\"\nname = \"park-test\"\nsyslog_facility = \"local3\"\nsyslog_socket = \"{}\"",
+        path.display()
+    ))
+    .unwrap()
+    .logging;
+    assert_eq!(config.target, LogTarget::Syslog);
+    let layer = SystemLogLayer::new(&config).unwrap().unwrap().with_filter(system_log::level_filter(config.level));
+
+    tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+        tracing::info!("below the configured level");
+        tracing::warn!(port = "/dev/ttyACM0", "Serial read failed");
+    });
+    let mut buf = [0; 512];
+    let len = daemon.recv(&mut buf).unwrap();
+    let line = String::from_utf8_lossy(&buf[..len]).to_string();
+    // local3 (19) * 8 + warning (4)
+    assert!(line.starts_with("<156>"), "{}", line);
+    assert!(
+        line.ends_with(&format!(" park-test[{}]: Serial read failed port=/dev/ttyACM0", std::process::id())),
+        "{}",
+        line
+    );
+    daemon.set_nonblocking(true).unwrap();
+    assert!(daemon.recv(&mut buf).is_err(), "the info message should have been filtered out");
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn json_subcommands_report_results_and_exit_codes() {
+    use common::FirmwareEmulator;
+    use tokio::process::Command;
+
+    let bin = env!("CARGO_BIN_EXE_telescope_park_bridge");
+    let emulator = FirmwareEmulator::start();
+    let output = Command::new(bin)
+        .args(["--json", "selftest", "--port", emulator.port_name(), "--timeout-secs", "10"])
+        .output()
+        .await
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).expect("selftest printed no JSON");
+    assert_eq!(output.status.code(), Some(0), "{}", report);
+    assert_eq!(report["status"], "passed");
+    assert_eq!(report["steps"][1]["detail"], "firmware emulator, protocol v1");
+
+    emulator.state.lock().unwrap().calibrated = false;
+    emulator.state.lock().unwrap().firmware_version = String::new();
+    let output = Command::new(bin)
+        .args(["selftest", "--json", "--port", emulator.port_name(), "--timeout-secs", "10"])
+        .output()
+        .await
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(report["status"], "failed");
+
+    let output = Command::new(bin)
+        .args(["--json", "selftest", "--port", "/dev/park-bridge-missing", "--timeout-secs", "1"])
+        .output()
+        .await
+        .unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    assert!(error["error"].as_str().unwrap().contains("/dev/park-bridge-missing"));
+
+    // Nobody answers discovery on a port that was just freed
+    let unused_port = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let output = Command::new(bin)
+        .args(["--json", "discover", "--target", "127.0.0.1", "--timeout-ms", "200"])
+        .args(["--discovery-port", &unused_port.to_string()])
+        .output()
+        .await
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap(), json!([]));
+}
+
+#[tokio::test]
+async fn client_crate_drives_the_bridge_api() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager.with_safety_force(SafetyForceConfig {
+            token: Some("s3cret".to_string()),
+            ..SafetyForceConfig::default()
+        })
+    })
+    .await;
+    let client = BridgeClient::new(&bridge.serve().await).unwrap();
+
+    let status = client.status().await.unwrap();
+    assert!(status.connected);
+    assert_eq!(status.device_version, "emulator");
+    assert!(status.last_update.is_some());
+    assert_eq!(client.devices().await.unwrap().len(), 1);
+    assert!(!client.send_command("01").await.unwrap().is_empty());
+    let history = client.command_history(&PageQuery::default()).await.unwrap();
+    assert!(history.items.iter().any(|record| record.command == "01"));
+    assert!(matches!(client.send_command("0E").await, Err(ClientError::Failed(_))));
+
+    let job = client.submit_job(JobOperation::SetPark, None).await.unwrap();
+    let job = client.wait_for_job(job.id).await.unwrap();
+    assert_eq!(job.status, JobStatus::Succeeded);
+
+    let force = ForceSafety {
+        device_number: 0,
+        is_safe: false,
+        minutes: 5.0,
+        reason: Some("client test".to_string()),
+    };
+    match client.force_safety(&force).await {
+        Err(ClientError::Api { status, .. }) => assert_eq!(status.as_u16(), 401),
+        other => panic!("expected 401 without a token, got {:?}", other),
+    }
+    let client = client.with_token("s3cret");
+    let mut events = client.subscribe_events(Duration::from_millis(50)).await.unwrap();
+    client.force_safety(&force).await.unwrap();
+    assert!(!client.is_safe(0).await.unwrap());
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await.unwrap().unwrap();
+    assert!(matches!(event.kind, EventKind::SafetyForced { is_safe: false, .. }));
+
+    client.clear_forced_safety(0).await.unwrap();
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await.unwrap().unwrap();
+    assert!(matches!(event.kind, EventKind::SafetyForceCleared { expired: false, .. }));
+}
+
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+#[tokio::test]
+async fn status_lamps_follow_issafe_and_connection() {
+    use telescope_park_bridge::status_outputs::StatusOutputs;
+    use tokio_util::sync::CancellationToken;
+
+    // Stand-ins for /sys/class/leds entries
+    let leds = std::env::temp_dir().join(format!("park-bridge-leds-{}", std::process::id()));
+    for led in ["safe", "link"] {
+        std::fs::create_dir_all(leds.join(led)).unwrap();
+        std::fs::write(leds.join(led).join("trigger"), "mmc0").unwrap();
+        std::fs::write(leds.join(led).join("brightness"), "0").unwrap();
+    }
+    let brightness = |led: &str| std::fs::read_to_string(leds.join(led).join("brightness")).unwrap();
+    let wait_for_lamp = |led: &'static str, value: &'static str| {
+        let brightness = &brightness;
+        async move {
+            for _ in 0..50 {
+                if brightness(led) == value {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            panic!("{} lamp never showed {}", led, value);
+        }
+    };
+
+    let config = BridgeConfig::parse("[gpio]\nsafe = { led = \"safe\" }\nconnected = { led = \"link\" }").unwrap();
+    assert!(config.issues().is_empty());
+    let clash = BridgeConfig::parse("[gpio]\ndevice_number = 2\nsafe = { pin = 17 }\nconnected = { pin = 17 }").unwrap();
+    let fields: Vec<String> = clash.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["gpio.device_number", "gpio.connected"]);
+
+    let bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    let device = DeviceHandle {
+        device_number: 0,
+        device_state: bridge.device_state.clone(),
+        connection_manager: bridge.connection_manager.clone(),
+    };
+    let outputs = StatusOutputs::open(&config.gpio, &leds).unwrap();
+    assert_eq!(std::fs::read_to_string(leds.join("safe").join("trigger")).unwrap(), "none");
+    let cancel = CancellationToken::new();
+    let lamps = tokio::spawn(outputs.run(device, cancel.clone()));
+    wait_for_lamp("safe", "1").await;
+    wait_for_lamp("link", "1").await;
+
+    bridge.emulator.set_position(25.0, -3.0);
+    wait_for_lamp("safe", "0").await;
+    assert_eq!(brightness("link"), "1");
+
+    // Stopping the bridge leaves every lamp dark
+    bridge.emulator.set_position(0.0, 0.0);
+    wait_for_lamp("safe", "1").await;
+    cancel.cancel();
+    lamps.await.unwrap();
+    assert_eq!(brightness("safe"), "0");
+    assert_eq!(brightness("link"), "0");
+    std::fs::remove_dir_all(&leds).unwrap();
+}
+
+#[cfg(feature = "relays")]
+#[tokio::test]
+async fn relays_switch_on_issafe_changes() {
+    use axum::extract::{OriginalUri, State};
+    use std::sync::Mutex;
+    use telescope_park_bridge::relays::{self, Relay};
+    use tokio_util::sync::CancellationToken;
+
+    // One HTTP server standing in for both network relays, recording the requested URIs
+    let requests: Arc<Mutex<Vec<String>>> = Arc::default();
+    let relay_server = axum::Router::new()
+        .fallback(|State(requests): State<Arc<Mutex<Vec<String>>>>, OriginalUri(uri): OriginalUri| async move {
+            requests.lock().unwrap().push(uri.to_string());
+            "{}"
+        })
+        .with_state(requests.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, relay_server).await });
+    let wait_for_request = |uri: String| {
+        let requests = requests.clone();
+        async move {
+            for _ in 0..50 {
+                if requests.lock().unwrap().contains(&uri) {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            panic!("{} was never requested, got {:?}", uri, requests.lock().unwrap());
+        }
+    };
+
+    let text = format!(
+        "[[relays]]\nname = \"roof motor\"\n[relays.driver]\ntype = \"shelly\"\nurl = \"{url}\"\n\n\
+         [[relays]]\nname = \"warning light\"\non_unsafe = \"on\"\non_safe = \"off\"\n\
+         [relays.driver]\ntype = \"tasmota\"\nurl = \"{url}/\"\nchannel = 2",
+    );
+    let config = BridgeConfig::parse(&text).unwrap();
+    assert!(config.issues().is_empty(), "{}", config.issues());
+    let broken = BridgeConfig::parse(
+        "[[relays]]\nname = \"x\"\non_unsafe = \"none\"\non_safe = \"none\"\n[relays.driver]\ntype = \"usb_hid\"\nchannel = 9",
+    )
+    .unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["relays[0].on_unsafe", "relays[0].driver.channel"]);
+
+    let bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    let mut events = bridge.connection_manager.event_bus().subscribe();
+    let outputs = config.relays.iter().cloned().map(|relay| Relay::new(relay).unwrap()).collect();
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    let cancel = CancellationToken::new();
+    tokio::spawn(relays::run_relays(outputs, devices, cancel.clone()));
+
+    // Set from the current state at startup
+    wait_for_request("/rpc/Switch.Set?id=0&on=true".to_string()).await;
+    wait_for_request("/cm?cmnd=Power2+Off".to_string()).await;
+    let relay_event = async {
+        loop {
+            let event = serde_json::to_value(events.recv().await.unwrap()).unwrap();
+            if event["type"] == "relay_switched" {
+                return event;
+            }
+        }
+    };
+    let event = tokio::time::timeout(Duration::from_secs(5), relay_event).await.unwrap();
+    assert_eq!(event["error"], serde_json::Value::Null);
+
+    bridge.emulator.set_position(25.0, -3.0);
+    wait_for_request("/rpc/Switch.Set?id=0&on=false".to_string()).await;
+    wait_for_request("/cm?cmnd=Power2+On".to_string()).await;
+    cancel.cancel();
+}
+
+#[cfg(feature = "chat-bot")]
+#[tokio::test]
+async fn chat_bot_posts_events_and_answers_commands() {
+    use axum::extract::{Query, State};
+    use axum::http::HeaderMap;
+    use axum::routing::{get, put};
+    use axum::Json;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use telescope_park_bridge::chat_bot::ChatBot;
+    use tokio_util::sync::CancellationToken;
+
+    // Stand-in for the Discord API and a Matrix homeserver: messages waiting to be read, what the
+    // bot posted, and how often each platform was polled
+    #[derive(Clone, Default)]
+    struct Chat {
+        incoming: Arc<Mutex<Vec<serde_json::Value>>>,
+        posted: Arc<Mutex<Vec<String>>>,
+        discord_polls: Arc<AtomicUsize>,
+        matrix_polls: Arc<AtomicUsize>,
+    }
+    fn authorized(headers: &HeaderMap, expected: &str) -> bool {
+        headers.get("authorization").is_some_and(|value| value == expected)
+    }
+    let chat = Chat::default();
+    let server = axum::Router::new()
+        .route(
+            "/api/v10/channels/123/messages",
+            get(|State(chat): State<Chat>| async move {
+                chat.discord_polls.fetch_add(1, Ordering::SeqCst);
+                Json(std::mem::take(&mut *chat.incoming.lock().unwrap()))
+            })
+            .post(|State(chat): State<Chat>, headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                if !authorized(&headers, "Bot discord-token") {
+                    return (StatusCode::UNAUTHORIZED, Json(json!({})));
+                }
+                chat.posted.lock().unwrap().push(body["content"].as_str().unwrap().to_string());
+                (StatusCode::OK, Json(json!({ "id": "1" })))
+            }),
+        )
+        .route(
+            "/_matrix/client/v3/sync",
+            get(|State(chat): State<Chat>, Query(query): Query<HashMap<String, String>>| async move {
+                chat.matrix_polls.fetch_add(1, Ordering::SeqCst);
+                // A short long poll
+                if query.contains_key("since") {
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                }
+                let events = std::mem::take(&mut *chat.incoming.lock().unwrap());
+                Json(json!({
+                    "next_batch": "s1",
+                    "rooms": { "join": { "!dome:example.org": { "timeline": { "events": events } } } },
+                }))
+            }),
+        )
+        .route(
+            "/_matrix/client/v3/rooms/!dome:example.org/send/m.room.message/:transaction",
+            put(|State(chat): State<Chat>, headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                if !authorized(&headers, "Bearer matrix-token") || body["msgtype"] != "m.notice" {
+                    return (StatusCode::UNAUTHORIZED, Json(json!({})));
+                }
+                chat.posted.lock().unwrap().push(body["body"].as_str().unwrap().to_string());
+                (StatusCode::OK, Json(json!({ "event_id": "$1" })))
+            }),
+        )
+        .with_state(chat.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, server).await });
+    let wait_for_post = |prefix: &'static str| {
+        let posted = chat.posted.clone();
+        async move {
+            for _ in 0..100 {
+                if posted.lock().unwrap().iter().any(|text| text.starts_with(prefix)) {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            panic!("'{}' was never posted, got {:?}", prefix, posted.lock().unwrap());
+        }
+    };
+    let wait_for_polls = |polls: Arc<AtomicUsize>| async move {
+        while polls.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    };
+
+    let discord = BridgeConfig::parse(&format!(
+        "[chat_bot]\nplatform = \"discord\"\nurl = \"{url}/api/v10\"\nchannel = \"123\"\ntoken = \"discord-token\"\n\
+         authorized_users = [\"42\"]\npoll_interval_secs = 1",
+    ))
+    .unwrap();
+    assert!(discord.issues().is_empty(), "{}", discord.issues());
+    let broken = BridgeConfig::parse("[chat_bot]\nplatform = \"matrix\"\nchannel = \"dome\"").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["chat_bot.url", "chat_bot.channel"]);
+
+    let bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    let device = DeviceHandle {
+        device_number: 0,
+        device_state: bridge.device_state.clone(),
+        connection_manager: bridge.connection_manager.clone(),
+    };
+    let cancel = CancellationToken::new();
+    tokio::spawn(ChatBot::new(discord.chat_bot).unwrap().run(device.clone(), cancel.clone()));
+    // The first poll only skips the channel's history
+    wait_for_polls(chat.discord_polls.clone()).await;
+
+    let message = |id: &str, author: serde_json::Value, content: &str| json!({ "id": id, "author": author, "content": content });
+    chat.incoming.lock().unwrap().extend([
+        message("102", json!({ "id": "7" }), "!park"),
+        message("101", json!({ "id": "7" }), "!status"),
+        message("103", json!({ "id": "9", "bot": true }), "!status"),
+        message("104", json!({ "id": "7" }), "clear skies tonight"),
+    ]);
+    wait_for_post("7 may not run !park").await;
+    {
+        let posted = chat.posted.lock().unwrap();
+        // Answered oldest first, and other bots are ignored
+        assert_eq!(*posted, ["Device 0: Connected | Parked | IsSafe: safe", "7 may not run !park"]);
+    }
+    chat.incoming.lock().unwrap().push(message("105", json!({ "id": "42" }), "!park"));
+    wait_for_post("Recording the current position as the park position").await;
+    wait_for_post("Park position set").await;
+
+    bridge.emulator.set_position(25.0, -3.0);
+    wait_for_post("Telescope left the park position").await;
+    cancel.cancel();
+
+    let matrix = BridgeConfig::parse(&format!(
+        "[chat_bot]\nplatform = \"matrix\"\nurl = \"{url}\"\nchannel = \"!dome:example.org\"\ntoken = \"matrix-token\"",
+    ))
+    .unwrap();
+    assert!(matrix.issues().is_empty(), "{}", matrix.issues());
+    let cancel = CancellationToken::new();
+    tokio::spawn(ChatBot::new(matrix.chat_bot).unwrap().run(device, cancel.clone()));
+    wait_for_polls(chat.matrix_polls.clone()).await;
+    let event = |msgtype: &str, body: &str| {
+        json!({ "type": "m.room.message", "sender": "@alice:example.org", "content": { "msgtype": msgtype, "body": body } })
+    };
+    chat.incoming.lock().unwrap().extend([event("m.notice", "!calibrate"), event("m.text", "!calibrate")]);
+    wait_for_post("@alice:example.org may not run !calibrate").await;
+    assert_eq!(chat.posted.lock().unwrap().iter().filter(|text| text.contains("!calibrate")).count(), 1);
+    cancel.cancel();
+}
+
+// Reply of the stand-in mounts to a Telescope property read or command
+#[cfg(feature = "telescope-client")]
+fn alpaca_reply(property: &str) -> serde_json::Value {
+    let value = match property {
+        "name" | "description" => json!("Test Mount"),
+        "rightascension" | "declination" | "azimuth" | "altitude" => json!(12.5),
+        "sideofpier" => json!(1),
+        "axisrates" => json!([{ "Minimum": 0.0, "Maximum": 1.5 }, { "Minimum": 3.0, "Maximum": 3.0 }]),
+        "atpark" => json!(false),
+        "supportedactions" => json!(["PEC:Start", "PEC:Stop"]),
+        "action" => json!("PEC started"),
+        _ => json!(true),
+    };
+    json!({ "Value": value, "ErrorNumber": 0, "ErrorMessage": "" })
+}
+
+#[cfg(feature = "telescope-client")]
+#[tokio::test]
+async fn telescope_client_retries_busy_mount_reads_but_not_commands() {
+    use axum::extract::{OriginalUri, State};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use telescope_park_bridge::errors::TelescopeError;
+    use telescope_park_bridge::telescope_client::{
+        RateRange, TelescopeAxis, TelescopeClient, TelescopeClientOptions, TelescopeConnection,
+    };
+
+    // A mount controller on a slow link that answers every request to a device path with 503 the
+    // first time (connecting aside), and stalls on "altitude" while `stall` is set
+    #[derive(Clone, Default)]
+    struct Mount {
+        requests: Arc<Mutex<HashMap<(Method, String), usize>>>,
+        stall: Arc<Mutex<bool>>,
+    }
+    let mount = Mount::default();
+    let server = axum::Router::new()
+        .fallback(|State(mount): State<Mount>, method: Method, OriginalUri(uri): OriginalUri| async move {
+            let path = uri.path().to_string();
+            let seen = {
+                let mut requests = mount.requests.lock().unwrap();
+                let count = requests.entry((method.clone(), path.clone())).or_default();
+                *count += 1;
+                *count
+            };
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if path.starts_with("/management/") || method == Method::PUT && path.ends_with("/connected") {
+                return (StatusCode::OK, axum::Json(json!({ "Value": [], "ErrorNumber": 0 })));
+            }
+            if seen == 1 {
+                return (StatusCode::SERVICE_UNAVAILABLE, axum::Json(json!({})));
+            }
+            let property = path.rsplit('/').next().unwrap();
+            if property == "altitude" && *mount.stall.lock().unwrap() {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+            (StatusCode::OK, axum::Json(alpaca_reply(property)))
+        })
+        .with_state(mount.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, server).await });
+
+    let options = TelescopeClientOptions {
+        request_timeout: Duration::from_millis(500),
+        get_retries: 1,
+        retry_delay: Duration::from_millis(10),
+        ..TelescopeClientOptions::default()
+    };
+    let mut client = TelescopeClient::new(TelescopeConnection::Alpaca { url, device_number: 0 }).with_options(options);
+    assert!(matches!(client.park().await, Err(TelescopeError::NotConnected)));
+    client.connect().await.unwrap();
+
+    // Each read fails once and succeeds on the retry, all of them concurrently
+    let started = std::time::Instant::now();
+    let status = client.get_status().await.unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1), "{:?}", started.elapsed());
+    assert!(status.connected && status.can_park && !status.at_park);
+    assert_eq!(status.name, "Test Mount");
+    assert_eq!(status.altitude, Some(12.5));
+    assert_eq!(status.pier_side, "West");
+    let gets = |property: &str| {
+        let key = (Method::GET, format!("/api/v1/telescope/0/{}", property));
+        mount.requests.lock().unwrap().get(&key).copied().unwrap_or_default()
+    };
+    assert_eq!(gets("slewing"), 2);
+
+    // A command is sent once, and its failure reported
+    let error = client.park().await.unwrap_err();
+    assert!(error.is_retryable(), "{}", error);
+    let puts = mount.requests.lock().unwrap()[&(Method::PUT, "/api/v1/telescope/0/park".to_string())];
+    assert_eq!(puts, 1);
+    client.park().await.unwrap();
+
+    // Capabilities are read once per connection
+    let primary = client.get_axis_rates(TelescopeAxis::Primary).await.unwrap();
+    assert!(primary.can_move);
+    assert_eq!(primary.rates, [RateRange { minimum: 0.0, maximum: 1.5 }, RateRange { minimum: 3.0, maximum: 3.0 }]);
+    client.get_status().await.unwrap();
+    // (axisrates is read for both axes)
+    assert_eq!((gets("name"), gets("axisrates"), gets("slewing")), (2, 3, 3));
+    client.connect().await.unwrap();
+    client.get_status().await.unwrap();
+    assert_eq!((gets("name"), gets("axisrates"), gets("slewing")), (3, 5, 4));
+
+    // A stalled read gives up after the request timeout on every attempt
+    *mount.stall.lock().unwrap() = true;
+    let started = std::time::Instant::now();
+    let error = client.get_status().await.unwrap_err();
+    assert!(matches!(error, TelescopeError::Http(_)), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(3), "{:?}", started.elapsed());
+    assert_eq!(gets("altitude"), 6);
+}
+
+#[cfg(feature = "telescope-client")]
+#[tokio::test]
+async fn telescope_manager_reconnects_after_the_mount_controller_restarts() {
+    use axum::extract::{OriginalUri, State};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use telescope_park_bridge::config::ReconnectConfig;
+    use telescope_park_bridge::events::BridgeEvent;
+    use telescope_park_bridge::telescope_manager::{TelescopeManager, TelescopeRegistry};
+
+    // A mount controller that answers 503 to everything while `down` is set
+    let down = Arc::new(AtomicBool::new(false));
+    let server = axum::Router::new()
+        .fallback(|State(down): State<Arc<AtomicBool>>, OriginalUri(uri): OriginalUri| async move {
+            if down.load(Ordering::SeqCst) {
+                return (StatusCode::SERVICE_UNAVAILABLE, axum::Json(json!({})));
+            }
+            (StatusCode::OK, axum::Json(alpaca_reply(uri.path().rsplit('/').next().unwrap())))
+        })
+        .with_state(down.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, server).await });
+
+    let text = format!("[[telescopes]]\nid = \"main\"\nurl = \"{}\"\nget_retries = 0\nretry_delay_ms = 10", url);
+    let config = BridgeConfig::parse(&text).unwrap();
+    assert!(config.issues().is_empty(), "{}", config.issues());
+
+    let mut bridge = TestBridge::start().await;
+    let (status, body) = bridge.get("/api/telescopes/main").await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{}", body);
+
+    let mut events = bridge.connection_manager.event_bus().subscribe();
+    let reconnect = ReconnectConfig {
+        enabled: true,
+        initial_delay_secs: 1,
+        max_delay_secs: 1,
+    };
+    let manager = TelescopeManager::from_config(&config.telescopes[0])
+        .with_poll_interval(Duration::from_millis(100))
+        .with_reconnect(reconnect)
+        .with_event_bus(bridge.connection_manager.event_bus());
+    let telescopes = TelescopeRegistry::new(vec![manager]);
+    let manager = telescopes.get("main").unwrap().clone();
+    bridge.router = create_router(
+        DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone()).with_telescopes(telescopes),
+        DiscoveryTracker::default(),
+    );
+    manager.start().await;
+    async fn next_link_state(events: &mut tokio::sync::broadcast::Receiver<BridgeEvent>) -> serde_json::Value {
+        let next = async {
+            loop {
+                let event = serde_json::to_value(events.recv().await.unwrap()).unwrap();
+                if event["type"] == "telescope_link_changed" {
+                    return event;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), next).await.unwrap()
+    }
+    assert_eq!(next_link_state(&mut events).await["state"], "connecting");
+    assert_eq!(next_link_state(&mut events).await["state"], "connected");
+    assert!(manager.client().await.is_some());
+    for _ in 0..50 {
+        if manager.snapshot().await.status.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // The controller reboots: the link is lost once, however many reconnects fail
+    down.store(true, Ordering::SeqCst);
+    let lost = next_link_state(&mut events).await;
+    assert_eq!((lost["telescope"].clone(), lost["state"].clone()), (json!("main"), json!("lost")));
+    assert!(lost["error"].as_str().unwrap().contains("503"), "{}", lost);
+    assert!(manager.client().await.is_none());
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    let (status, body) = bridge.get("/api/telescopes/main").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["state"], "lost");
+    assert!(body["reconnect_attempts"].as_u64().unwrap() >= 1, "{}", body);
+    assert_eq!(body["status"]["name"], "Test Mount");
+
+    down.store(false, Ordering::SeqCst);
+    assert_eq!(next_link_state(&mut events).await["state"], "connected");
+    let (_, body) = bridge.get("/api/telescopes/main").await;
+    assert_eq!((body["state"].clone(), body["reconnect_attempts"].clone()), (json!("connected"), json!(0)));
+
+    manager.stop().await;
+    assert_eq!(next_link_state(&mut events).await["state"], "disconnected");
+    assert!(manager.client().await.is_none());
+}
+
+#[cfg(feature = "telescope-client")]
+#[tokio::test]
+async fn telescopes_are_served_by_id_with_a_park_interlock() {
+    use axum::extract::{OriginalUri, State};
+    use std::sync::Mutex;
+    use telescope_park_bridge::telescope_manager::{TelescopeManager, TelescopeRegistry};
+
+    // The main mount, recording the commands it gets; the piggyback tracker is switched off
+    let commands: Arc<Mutex<Vec<String>>> = Arc::default();
+    let server = axum::Router::new()
+        .fallback(|State(commands): State<Arc<Mutex<Vec<String>>>>, method: Method, OriginalUri(uri): OriginalUri| async move {
+            let property = uri.path().rsplit('/').next().unwrap().to_string();
+            if method == Method::PUT && property != "connected" {
+                commands.lock().unwrap().push(property.clone());
+            }
+            // Like real drivers, only Action answers a command with a Value
+            match method == Method::PUT && property != "action" {
+                true => axum::Json(json!({ "ErrorNumber": 0, "ErrorMessage": "" })),
+                false => axum::Json(alpaca_reply(&property)),
+            }
+        })
+        .with_state(commands.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, server).await });
+    let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+
+    let text = format!(
+        "[[telescopes]]\nid = \"main\"\nurl = \"{url}\"\n\n\
+         [[telescopes]]\nid = \"piggyback\"\nurl = \"http://{closed}\"\nget_retries = 0\nconnect_timeout_ms = 500\nrequest_timeout_ms = 500",
+    );
+    let config = BridgeConfig::parse(&text).unwrap();
+    assert!(config.issues().is_empty(), "{}", config.issues());
+    let broken = BridgeConfig::parse(
+        "[[telescopes]]\nid = \"main mount\"\nurl = \"mount:11111\"\nsensor_device = 3\n[[telescopes]]\nid = \"main mount\"\nurl = \"http://mount\"",
+    )
+    .unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(
+        fields,
+        ["telescopes[0].id", "telescopes[0].url", "telescopes[0].sensor_device", "telescopes[1].id", "telescopes[1].id"]
+    );
+
+    let mut bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_parked).await;
+    let telescopes = TelescopeRegistry::new(
+        config
+            .telescopes
+            .iter()
+            .map(|telescope| TelescopeManager::from_config(telescope).with_poll_interval(Duration::from_millis(100)))
+            .collect(),
+    );
+    bridge.router = create_router(
+        DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone()).with_telescopes(telescopes.clone()),
+        DiscoveryTracker::default(),
+    );
+    telescopes.start_all().await;
+    for _ in 0..50 {
+        if telescopes.get("main").unwrap().snapshot().await.status.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let (status, body) = bridge.get("/api/telescopes").await;
+    assert_eq!(status, StatusCode::OK);
+    let ids: Vec<&str> = body.as_array().unwrap().iter().map(|telescope| telescope["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["main", "piggyback"]);
+    // The sensor says parked while the mount reports AtPark false
+    let (_, main) = bridge.get("/api/telescopes/main").await;
+    assert_eq!(main["state"], "connected");
+    assert_eq!(main["interlock"], json!({ "sensor_device": 0, "sensor_parked": true, "mount_at_park": false, "agree": false }));
+    let (_, piggyback) = bridge.get("/api/telescopes/piggyback").await;
+    assert_ne!(piggyback["state"], "connected");
+    assert_eq!(piggyback["interlock"]["agree"], serde_json::Value::Null);
+
+    let (status, body) = bridge.post_json("/api/telescopes/main/park", json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, _) = bridge.post_json("/api/telescopes/main/abort", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    // CanSetPark is true, so SetPark goes through
+    assert_eq!(main["status"]["can_set_park"], true);
+    let (status, _) = bridge.post_json("/api/telescopes/main/setpark", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(*commands.lock().unwrap(), ["park", "moveaxis", "moveaxis", "abortslew", "setpark"]);
+
+    // Driver actions are forwarded when SupportedActions lists them
+    let (_, actions) = bridge.get("/api/telescopes/main/actions").await;
+    assert_eq!(actions, json!(["PEC:Start", "PEC:Stop"]));
+    let (status, body) = bridge.post_json("/api/telescopes/main/action", json!({ "action": "pec:start" })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body, json!({ "action": "pec:start", "value": "PEC started" }));
+    let (status, _) = bridge.post_json("/api/telescopes/main/action", json!({ "action": "Reboot" })).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(commands.lock().unwrap().last().unwrap(), "action");
+    assert_eq!(commands.lock().unwrap().len(), 6);
+    let (status, _) = bridge.post_json("/api/telescopes/main/explode", json!({})).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = bridge.post_json("/api/telescopes/piggyback/park", json!({})).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let (status, _) = bridge.get("/api/telescopes/guide").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    telescopes.stop_all().await;
+}
+
+#[cfg(feature = "telescope-client")]
+#[tokio::test]
+async fn telescope_moves_are_clamped_and_stopped_when_not_renewed() {
+    use axum::extract::{OriginalUri, State};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use telescope_park_bridge::telescope_manager::{TelescopeManager, TelescopeRegistry};
+
+    // Records each MoveAxis as "axis rate"; both axes offer 0-1.5 and 3 degrees per second
+    let moves: Arc<Mutex<Vec<String>>> = Arc::default();
+    let server = axum::Router::new()
+        .fallback(
+            |State(moves): State<Arc<Mutex<Vec<String>>>>,
+             OriginalUri(uri): OriginalUri,
+             axum::Form(form): axum::Form<HashMap<String, String>>| async move {
+                let property = uri.path().rsplit('/').next().unwrap().to_string();
+                if property == "moveaxis" {
+                    moves.lock().unwrap().push(format!("{} {}", form["Axis"], form["Rate"]));
+                }
+                axum::Json(alpaca_reply(&property))
+            },
+        )
+        .with_state(moves.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, server).await });
+
+    let config = BridgeConfig::parse(&format!("[[telescopes]]\nid = \"main\"\nurl = \"{url}\"\nmove_timeout_secs = 0")).unwrap();
+    let fields: Vec<String> = config.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["telescopes[0].move_timeout_secs"]);
+
+    let mut bridge = TestBridge::start().await;
+    let telescope = TelescopeManager::from_config(&config.telescopes[0]).with_move_timeout(Duration::from_millis(400));
+    let telescopes = TelescopeRegistry::new(vec![telescope]);
+    bridge.router = create_router(
+        DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone()).with_telescopes(telescopes.clone()),
+        DiscoveryTracker::default(),
+    );
+    telescopes.start_all().await;
+    for _ in 0..50 {
+        if telescopes.get("main").unwrap().client().await.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    // Rates outside AxisRates go to the nearest supported one; West is the primary axis reversed
+    let (status, body) = bridge.post_json("/api/telescopes/main/move", json!({ "direction": "north", "rate": 2.0 })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["rate"], 1.5);
+    let (_, body) = bridge.post_json("/api/telescopes/main/move", json!({ "direction": "west", "rate": 5.0 })).await;
+    assert_eq!(body["rate"], 3.0);
+    let (status, _) = bridge.post_json("/api/telescopes/main/move", json!({ "direction": "west", "rate": -1.0 })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(*moves.lock().unwrap(), ["1 1.5", "0 -3"]);
+    let (status, rates) = bridge.get("/api/telescopes/main/axis_rates").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        rates["secondary"],
+        json!({ "can_move": true, "rates": [{ "minimum": 0.0, "maximum": 1.5 }, { "minimum": 3.0, "maximum": 3.0 }] })
+    );
+
+    // Renewing a move keeps it going; both stop once no renewal comes
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    bridge.post_json("/api/telescopes/main/move", json!({ "direction": "west", "rate": 3.0 })).await;
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    assert_eq!(*moves.lock().unwrap(), ["1 1.5", "0 -3", "0 -3", "1 0"]);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(*moves.lock().unwrap(), ["1 1.5", "0 -3", "0 -3", "1 0", "0 0"]);
+
+    // An axis still moving when the bridge lets go of the mount is stopped at once
+    moves.lock().unwrap().clear();
+    bridge.post_json("/api/telescopes/main/move", json!({ "direction": "east", "rate": 1.0 })).await;
+    telescopes.stop_all().await;
+    assert_eq!(*moves.lock().unwrap(), ["0 1", "0 0"]);
+    let (status, _) = bridge.get("/api/telescopes/main/axis_rates").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(moves.lock().unwrap().len(), 2);
+}
+
+#[cfg(feature = "telescope-client")]
+#[tokio::test]
+async fn telescope_pier_flips_are_published() {
+    use axum::extract::{OriginalUri, State};
+    use std::sync::Mutex;
+    use telescope_park_bridge::events::{EventBus, EventKind as BusEventKind, TelescopeChange};
+    use telescope_park_bridge::telescope_manager::TelescopeManager;
+    use telescope_park_bridge::telescope_client::TelescopeConnection;
+
+    // A mount whose SideOfPier follows `side`
+    let side = Arc::new(Mutex::new(1));
+    let server = axum::Router::new()
+        .fallback(|State(side): State<Arc<Mutex<i32>>>, OriginalUri(uri): OriginalUri| async move {
+            match uri.path().rsplit('/').next().unwrap() {
+                "sideofpier" => axum::Json(json!({ "Value": *side.lock().unwrap(), "ErrorNumber": 0 })),
+                property => axum::Json(alpaca_reply(property)),
+            }
+        })
+        .with_state(side.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, server).await });
+
+    let bus = EventBus::new();
+    let mut events = bus.subscribe();
+    let telescope = TelescopeManager::new("main", TelescopeConnection::Alpaca { url, device_number: 0 })
+        .with_poll_interval(Duration::from_millis(100))
+        .with_event_bus(bus.clone());
+    telescope.start().await;
+    for _ in 0..50 {
+        if telescope.snapshot().await.status.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    // West to East is a flip; a driver briefly reporting Unknown (-1) in between is not
+    for value in [0, -1, 0] {
+        *side.lock().unwrap() = value;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+    }
+    telescope.stop().await;
+
+    let mut flips = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let BusEventKind::TelescopeChanged { change, pier_side, .. } = event.kind {
+            flips.push((change, pier_side));
+        }
+    }
+    assert_eq!(flips, [(TelescopeChange::PierFlipped, "East".to_string())]);
+    assert_eq!(telescope.snapshot().await.status.unwrap().pier_side, "East");
+}
+
+#[cfg(feature = "telescope-client")]
+#[tokio::test]
+async fn telescope_status_leaves_unimplemented_optional_properties_unknown() {
+    use axum::extract::OriginalUri;
+    use telescope_park_bridge::telescope_manager::TelescopeManager;
+    use telescope_park_bridge::telescope_client::TelescopeConnection;
+
+    // A mount without SideOfPier, Azimuth and Altitude, which ITelescope allows
+    let server = axum::Router::new().fallback(|OriginalUri(uri): OriginalUri| async move {
+        match uri.path().rsplit('/').next().unwrap() {
+            "sideofpier" | "azimuth" | "altitude" => {
+                axum::Json(json!({ "ErrorNumber": 0x400, "ErrorMessage": "Property not implemented" }))
+            }
+            property => axum::Json(alpaca_reply(property)),
+        }
+    });
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, server).await });
+
+    let telescope = TelescopeManager::new("main", TelescopeConnection::Alpaca { url, device_number: 0 })
+        .with_poll_interval(Duration::from_millis(100));
+    telescope.start().await;
+    for _ in 0..50 {
+        if telescope.snapshot().await.status.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let snapshot = telescope.snapshot().await;
+    telescope.stop().await;
+
+    let status = snapshot.status.expect("no status from a mount without optional properties");
+    assert_eq!(snapshot.error, None);
+    assert_eq!(status.ra, 12.5);
+    assert_eq!((status.azimuth, status.altitude), (None, None));
+    assert_eq!(status.pier_side, "Unknown");
+}
+
+#[cfg(feature = "telescope-client")]
+#[tokio::test]
+async fn session_log_merges_sensor_and_mount_events() {
+    use axum::extract::{OriginalUri, State};
+    use std::sync::Mutex;
+    use telescope_park_bridge::events::EventKind as BusEventKind;
+    use telescope_park_bridge::session_log::run_session_log;
+    use telescope_park_bridge::telescope_manager::{TelescopeManager, TelescopeRegistry};
+    use telescope_park_bridge::telescope_client::TelescopeConnection;
+    use tokio_util::sync::CancellationToken;
+
+    // A mount whose Slewing and AtPark follow `mount`
+    let mount: Arc<Mutex<(bool, bool)>> = Arc::default();
+    let server = axum::Router::new()
+        .fallback(|State(mount): State<Arc<Mutex<(bool, bool)>>>, OriginalUri(uri): OriginalUri| async move {
+            let property = uri.path().rsplit('/').next().unwrap();
+            let (slewing, at_park) = *mount.lock().unwrap();
+            match property {
+                "slewing" => axum::Json(json!({ "Value": slewing, "ErrorNumber": 0 })),
+                "atpark" => axum::Json(json!({ "Value": at_park, "ErrorNumber": 0 })),
+                _ => axum::Json(alpaca_reply(property)),
+            }
+        })
+        .with_state(mount.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, server).await });
+
+    let mut bridge = TestBridge::start().await;
+    let bus = bridge.connection_manager.event_bus();
+    let telescope = TelescopeManager::new("main", TelescopeConnection::Alpaca { url, device_number: 0 })
+        .with_poll_interval(Duration::from_millis(100))
+        .with_event_bus(bus.clone());
+    let telescopes = TelescopeRegistry::new(vec![telescope]);
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone())
+        .with_telescopes(telescopes.clone());
+    bridge.router = create_router(devices.clone(), DiscoveryTracker::default());
+
+    // Published before the log starts, so they come from the bus's buffer
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_parked).await;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    bus.publish(BusEventKind::SafetyForceCleared { device_number: 0, expired: true });
+    let cancel = CancellationToken::new();
+    tokio::spawn(run_session_log(devices.clone(), cancel.clone()));
+    telescopes.start_all().await;
+    let main = telescopes.get("main").unwrap();
+    for _ in 0..50 {
+        if main.snapshot().await.status.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    // A slew to park as the mount polls see it, then a sensor transition
+    *mount.lock().unwrap() = (true, false);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    *mount.lock().unwrap() = (false, true);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    bus.publish(BusEventKind::CalibrationProgress { phase: "collecting_samples".to_string(), percent: None });
+    bus.publish(BusEventKind::ParkStateChanged { parked: false, pitch: 0.0, roll: 0.0 });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (status, log) = bridge.get("/api/session_log").await;
+    assert_eq!(status, StatusCode::OK);
+    let timeline: Vec<String> = log
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| match entry["type"].as_str().unwrap() {
+            "telescope_changed" => format!("{} {}", entry["telescope"].as_str().unwrap(), entry["change"].as_str().unwrap()),
+            "telescope_link_changed" => format!("{} {}", entry["telescope"].as_str().unwrap(), entry["state"].as_str().unwrap()),
+            other => format!("device {} {}", entry["device_number"], other),
+        })
+        .collect();
+    assert_eq!(
+        timeline,
+        [
+            "device 0 park_state_changed",
+            "device 0 safety_force_cleared",
+            "main connecting",
+            "main connected",
+            "main slew_started",
+            "main slew_finished",
+            "main parked",
+            "device 0 park_state_changed",
+        ]
+    );
+    assert_eq!(log[4]["device_number"], serde_json::Value::Null);
+    assert_eq!(log[4]["ra"], 12.5);
+
+    let (_, safety) = bridge.get("/api/session_log?category=safety").await;
+    assert_eq!(safety.as_array().unwrap().len(), 1);
+    let (_, newest) = bridge.get("/api/session_log?order=desc&limit=1").await;
+    assert_eq!(newest[0]["type"], "park_state_changed");
+    cancel.cancel();
+    telescopes.stop_all().await;
+}
+
+#[tokio::test]
+async fn nightly_report_sums_sampled_safety_and_logged_events() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use telescope_park_bridge::events::EventKind as BusEventKind;
+    use telescope_park_bridge::health::HealthIssue;
+    use telescope_park_bridge::session_log::run_session_log;
+    use tokio_util::sync::CancellationToken;
+
+    let mut bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    bridge.router = create_router(devices.clone(), DiscoveryTracker::default());
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_parked).await;
+    let cancel = CancellationToken::new();
+    tokio::spawn(run_session_log(devices.clone(), cancel.clone()));
+
+    let (status, body) = bridge.get("/api/reports/nightly").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["message"], "No night has finished yet");
+
+    // A night that began a minute ago, sampled then and now: parked, so safe throughout
+    let config = BridgeConfig::default().nightly_report;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let reports = devices.nightly_reports();
+    reports.begin(now - 60, config.end_hour);
+    assert!(reports.ends_at() > now && reports.ends_at() <= now + 86_400);
+    reports.sample(&devices, now - 60).await;
+    reports.sample(&devices, now).await;
+
+    let bus = bridge.connection_manager.event_bus();
+    bus.publish(BusEventKind::ParkStateChanged { parked: false, pitch: 3.0, roll: 0.0 });
+    bus.publish(BusEventKind::HealthWarning {
+        issue: HealthIssue::SlowResponses,
+        message: "responses slower than 500 ms".to_string(),
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (status, current) = bridge.get("/api/reports/nightly?night=current").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(current["in_progress"], true);
+    assert_eq!(current["devices"][0]["safe_secs"], 60);
+    assert_eq!(current["devices"][0]["unsafe_secs"], 0);
+
+    let mut events = bus.subscribe();
+    let report = reports.finish(&devices, now, &config);
+    assert_eq!(report.devices[0].safe_secs, 60);
+    // The emulator's initial park state and the one above
+    assert_eq!(report.devices[0].park_changes, 2);
+    assert_eq!(report.alerts.len(), 1);
+    let published = events.recv().await.unwrap();
+    let BusEventKind::NightlyReport { night, summary } = published.kind else {
+        panic!("expected a nightly report, got {:?}", published.kind);
+    };
+    assert_eq!(night, report.night);
+    assert!(summary.contains("device 0 safe 0h 1m, unsafe 0h 0m, 2 park change(s)"), "{}", summary);
+    assert!(summary.ends_with("1 alert(s)"), "{}", summary);
+
+    let (status, latest) = bridge.get("/api/reports/nightly").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(latest["night"], report.night);
+    assert_eq!(latest["in_progress"], false);
+    assert_eq!(latest["alerts"][0]["type"], "health_warning");
+    let (status, _) = bridge.get(&format!("/api/reports/nightly?night={}", report.night)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, missing) = bridge.get("/api/reports/nightly?night=1999-01-01").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(missing["message"].as_str().unwrap().ends_with(&format!("(kept: {})", report.night)));
+
+    // The next night starts where this one ended, with nothing counted yet
+    let (_, next) = bridge.get("/api/reports/nightly?night=current").await;
+    assert_eq!(next["devices"][0]["safe_secs"], 0);
+    assert_eq!(next["alerts"].as_array().unwrap().len(), 0);
+    cancel.cancel();
+
+    let broken = BridgeConfig::parse("[nightly_report]\nend_hour = 24\nkeep = 0").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["nightly_report.end_hour", "nightly_report.keep"]);
+}
+
+#[tokio::test]
+async fn indi_clients_see_a_weather_device_following_issafe() {
+    use telescope_park_bridge::indi_server::{take_messages, IndiServer};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_util::sync::CancellationToken;
+
+    // Elements split across reads are kept until complete
+    let mut pending = "<?xml version=\"1.0\"?><getProperties version='1.7'/><newSwitchVector device=\"A &amp; B\" name=\"CONN".to_string();
+    let messages = take_messages(&mut pending);
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].tag, "getProperties");
+    assert_eq!(messages[0].attributes["version"], "1.7");
+    pending.push_str("ECTION\"><oneSwitch name=\"CONNECT\">On</oneSwitch></newSwitchVector>");
+    let messages = take_messages(&mut pending);
+    assert_eq!(messages[0].attributes["device"], "A & B");
+    assert!(pending.is_empty());
+
+    let bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_safe).await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let cancel = CancellationToken::new();
+    tokio::spawn(IndiServer::new(devices, 1).serve(listener, cancel.clone()));
+
+    let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+    client.write_all(b"<getProperties version=\"1.7\"/>\n").await.unwrap();
+    let mut received = String::new();
+    let mut read_until = async |client: &mut tokio::net::TcpStream, marker: &str| {
+        let mut chunk = [0u8; 4096];
+        while !received.contains(marker) {
+            let read = tokio::time::timeout(Duration::from_secs(10), client.read(&mut chunk)).await.unwrap().unwrap();
+            assert!(read > 0, "INDI server closed the connection: {}", received);
+            received.push_str(&String::from_utf8_lossy(&chunk[..read]));
+        }
+        std::mem::take(&mut received)
+    };
+    let defined = read_until(&mut client, "</defNumberVector>").await;
+    assert!(defined.contains("name=\"DRIVER_INTERFACE\" label=\"Interface\">128<"), "{}", defined);
+    assert!(defined.contains("name=\"WEATHER_STATUS\" label=\"Status\" group=\"Main Control\" state=\"Ok\""), "{}", defined);
+    assert!(defined.contains("<defSwitch name=\"CONNECT\">On</defSwitch>"), "{}", defined);
+
+    // Unparking turns the status to Alert, which Ekos treats as unsafe
+    bridge.emulator.set_position(12.5, 4.0);
+    let update = read_until(&mut client, "</setLightVector>").await;
+    assert!(update.contains("name=\"WEATHER_STATUS\" state=\"Alert\""), "{}", update);
+    assert!(update.contains("<oneLight name=\"WEATHER_PARKED\">Alert</oneLight>"), "{}", update);
+
+    let broken = BridgeConfig::parse("[indi]\nbind = \"7624\"").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["indi.bind"]);
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn serial_tee_streams_traffic_read_only() {
+    use telescope_park_bridge::config::TeeFormat;
+    use telescope_park_bridge::serial_tee::SerialTee;
+    use telescope_park_bridge::session_recording::TrafficLine;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio_util::sync::CancellationToken;
+
+    let bridge = TestBridge::start().await;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let tee = SerialTee::new(bridge.connection_manager.traffic_tap(), TeeFormat::Json, 1);
+    let cancel = CancellationToken::new();
+    tokio::spawn(tee.serve(listener, cancel.clone()));
+
+    let mut viewer = tokio::net::TcpStream::connect(addr).await.unwrap();
+    // A second viewer is over max_clients and gets closed right away
+    let mut refused = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let mut buf = [0u8; 16];
+    let read = tokio::time::timeout(Duration::from_secs(5), refused.read(&mut buf)).await.unwrap();
+    assert!(matches!(read, Ok(0) | Err(_)));
+
+    // Whatever a viewer sends never reaches the device
+    viewer.write_all(b"<0E>\n").await.unwrap();
+    let mut lines = BufReader::new(viewer).lines();
+    let mut seen = Vec::new();
+    while !(seen.iter().any(|l: &TrafficLine| l.direction == TrafficDirection::Tx)
+        && seen.iter().any(|l| l.direction == TrafficDirection::Rx))
+    {
+        let line = tokio::time::timeout(Duration::from_secs(10), lines.next_line()).await.unwrap().unwrap().unwrap();
+        seen.push(serde_json::from_str(&line).unwrap());
+    }
+    assert!(seen.iter().all(|l| l.line != "<0E>"), "{:?}", seen);
+    assert!(!bridge.emulator.snapshot().commands_received.iter().any(|c| c == "0E"));
+    cancel.cancel();
+}
+
+#[tokio::test]
+async fn replay_refuses_speeds_out_of_range() {
+    use telescope_park_bridge::session_recording::{replay_recording, TrafficLine};
+
+    let recording: Vec<TrafficLine> = Vec::new();
+    for speed in [0.0, 1e-300, f64::NAN, 1e300] {
+        let (stream, _bridge_end) = tokio::io::duplex(64);
+        let error = replay_recording(stream, &recording, speed, false).await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput, "{}", speed);
+    }
+}
+
+#[test]
+fn ascom_dynamic_driver_profile_points_at_the_bridge() {
+    use telescope_park_bridge::ascom_profile::{self, DynamicDriver};
+
+    let driver = DynamicDriver {
+        driver_number: 3,
+        description: r#"North "Pier" \ Park"#.to_string(),
+        host: "192.168.1.20".to_string(),
+        port: 11111,
+        device_number: 1,
+        unique_id: Some("north-pier-park".to_string()),
+    };
+    assert_eq!(driver.prog_id(), "ASCOM.AlpacaDynamic3.SafetyMonitor");
+    assert_eq!(ascom_profile::driver_number(&driver.prog_id()), Some(3));
+    assert_eq!(ascom_profile::driver_number("ASCOM.Simulator.SafetyMonitor"), None);
+
+    let reg = driver.to_reg_file();
+    assert!(reg.starts_with("Windows Registry Editor Version 5.00\r\n"));
+    assert!(reg.contains(r"[HKEY_LOCAL_MACHINE\SOFTWARE\WOW6432Node\ASCOM\SafetyMonitor Drivers\ASCOM.AlpacaDynamic3.SafetyMonitor]"));
+    assert!(reg.contains(r#"@="North \"Pier\" \\ Park""#));
+    for line in ["\"IP Address\"=\"192.168.1.20\"", "\"Port Number\"=\"11111\"", "\"Remote Device Number\"=\"1\"", "\"Enable Rediscovery\"=\"True\""] {
+        assert!(reg.contains(line), "{} missing from\n{}", line, reg);
+    }
+    let encoded = ascom_profile::encode_reg_file(&reg);
+    assert_eq!(&encoded[..4], &[0xff, 0xfe, b'W', 0]);
+}
+
+#[tokio::test]
+async fn backups_keep_settings_and_compacted_history_and_rotate() {
+    use telescope_park_bridge::backups::BackupScheduler;
+    use telescope_park_bridge::config::BackupConfig;
+    use telescope_park_bridge::events::EventKind as BusEventKind;
+
+    let broken = BridgeConfig::parse("[backup]\ndirectory = \"\"\ninterval_hours = 0\nkeep = 0").unwrap();
+    let fields: Vec<String> = broken.issues().iter().map(|issue| issue.field.clone()).collect();
+    assert_eq!(fields, ["backup.directory", "backup.interval_hours", "backup.keep"]);
+
+    let bridge = TestBridge::start().await;
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    assert_eq!(body["success"], true, "{}", body);
+    let (_, body) = bridge.post_json("/api/device/set_park", json!({})).await;
+    assert_eq!(body["success"], true, "{}", body);
+    let bus = bridge.connection_manager.event_bus();
+    bus.publish(BusEventKind::CalibrationProgress { phase: "collecting_samples".to_string(), percent: Some(40) });
+    bus.publish(BusEventKind::SafetyForceCleared { device_number: 0, expired: false });
+
+    let dir = std::env::temp_dir().join(format!("park-bridge-backups-{}", std::process::id()));
+    let backups = dir.join("backups");
+    std::fs::create_dir_all(&backups).unwrap();
+    for old in ["park-bridge-backup-20200101T000000Z.json", "park-bridge-backup-20210101T000000Z.json", "notes.txt"] {
+        std::fs::write(backups.join(old), "{}").unwrap();
+    }
+    let config_path = dir.join("bridge.toml");
+    std::fs::write(&config_path, "[backup]\nkeep = 2\n").unwrap();
+
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    let config = BackupConfig {
+        keep: 2,
+        ..BackupConfig::default()
+    };
+    let scheduler = BackupScheduler::new(config, devices, Some(config_path));
+    let path = scheduler.write_backup(&backups).await.unwrap();
+
+    // The oldest backup goes; files that are not backups stay
+    let mut names: Vec<String> = std::fs::read_dir(&backups)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    let newest = path.file_name().unwrap().to_string_lossy().into_owned();
+    assert_eq!(names, ["notes.txt", "park-bridge-backup-20210101T000000Z.json", newest.as_str()]);
+
+    let backup: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(backup["config"], "[backup]\nkeep = 2\n");
+    let device = &backup["devices"][0];
+    let state = bridge.device_state.read().await.clone();
+    assert_eq!(device["settings"]["position_tolerance"], state.position_tolerance as f64);
+    assert_eq!(device["settings"]["park_pitch"], state.park_pitch as f64);
+    let commands: Vec<&str> = device["commands"].as_array().unwrap().iter().map(|c| c["command"].as_str().unwrap()).collect();
+    assert!(commands.contains(&"0D") && !commands.contains(&"0B"), "{:?}", commands);
+    let events: Vec<&str> = device["events"].as_array().unwrap().iter().map(|e| e["type"].as_str().unwrap()).collect();
+    assert!(events.contains(&"safety_force_cleared") && !events.contains(&"calibration_progress"), "{:?}", events);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn secrets_fill_config_fields_left_out_of_the_file() {
+    let dir = std::env::temp_dir().join(format!("park-bridge-secrets-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let store = SecretStore::file_only(dir.join("bridge.secrets.toml"));
+
+    assert!(store.set("smtp.password", "x", SecretLocation::File).is_err());
+    let location = store.set("safety_force.token", "s3cret", SecretLocation::Keyring).unwrap();
+    assert_eq!(location, SecretLocation::File);
+    assert_eq!(
+        store.list().unwrap(),
+        vec![("safety_force.token", Some(SecretLocation::File)), ("chat_bot.token", None), ("events.token", None)]
+    );
+
+    let mut config = BridgeConfig::default();
+    config.resolve_secrets(&store).unwrap();
+    assert_eq!(config.safety_force.token.as_deref(), Some("s3cret"));
+    // A token written into the config file itself still takes precedence
+    let mut config = BridgeConfig::parse("[safety_force]\ntoken = \"inline\"").unwrap();
+    config.resolve_secrets(&store).unwrap();
+    assert_eq!(config.safety_force.token.as_deref(), Some("inline"));
+
+    assert_eq!(store.delete("safety_force.token").unwrap(), vec![SecretLocation::File]);
+    assert!(store.get("safety_force.token").unwrap().is_none());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn command_api_rejects_malformed_and_unlisted_commands() {
+    let bridge = TestBridge::start().await;
+
+    for command in ["", "0", "<01>", "0B\n08", "ZZ", "0A1x0", "0A123456789", "aé", "0Bé"] {
+        let (_, body) = bridge.post_json("/api/command", json!({ "command": command })).await;
+        assert_eq!(body["success"], false, "{:?} was accepted", command);
+    }
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "0E" })).await;
+    assert_eq!(body["success"], false);
+    assert!(body["message"].as_str().unwrap().contains("expert mode"), "{}", body);
+
+    let expert = TestBridge::start_with(|manager| {
+        manager.with_command_api(CommandApiConfig {
+            expert_mode: true,
+            ..CommandApiConfig::default()
+        })
+    })
+    .await;
+    let (_, body) = expert.post_json("/api/command", json!({ "command": "0E" })).await;
+    assert_eq!(body["success"], true, "command failed: {}", body);
+    let (_, body) = expert.post_json("/api/command", json!({ "command": "aé" })).await;
+    assert_eq!(body["success"], false, "{}", body);
+}
+
+#[tokio::test]
+async fn crlf_command_framing_reaches_firmware() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager.with_framing(FramingConfig {
+            command_terminator: "\r\n".to_string(),
+            ..FramingConfig::default()
+        })
+    })
+    .await;
+
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    assert_eq!(body["success"], true, "command failed: {}", body);
+}
+
+#[tokio::test]
+async fn polls_wait_for_in_flight_command() {
+    let bridge = TestBridge::start().await;
+    let mut traffic = bridge.connection_manager.traffic_tap().subscribe();
+
+    // A slow ACK keeps the calibrate command in flight across several poll intervals
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        ack_delay_ms: 2500,
+        ..FaultPlan::default()
+    });
+    bridge.emulator.state.lock().unwrap().faults = faults;
+
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "06" })).await;
+    assert_eq!(body["success"], true, "calibrate failed: {}", body);
+
+    let mut lines = Vec::new();
+    while let Ok(line) = traffic.try_recv() {
+        lines.push(line);
+    }
+    let sent = lines
+        .iter()
+        .position(|l| l.direction == TrafficDirection::Tx && l.line == "<06>")
+        .expect("calibrate command was not sent");
+    let acked = sent
+        + lines[sent..]
+            .iter()
+            .position(|l| l.direction == TrafficDirection::Rx && l.line.contains("\"command\":\"06\""))
+            .expect("calibrate was not acknowledged");
+    let answered = acked
+        + lines[acked..]
+            .iter()
+            .position(|l| l.direction == TrafficDirection::Rx && l.line.contains("\"ok\""))
+            .expect("calibrate data response missing");
+
+    let interleaved: Vec<&str> = lines[sent + 1..answered]
+        .iter()
+        .filter(|l| l.direction == TrafficDirection::Tx)
+        .map(|l| l.line.as_str())
+        .collect();
+    assert!(interleaved.is_empty(), "polls sent while command in flight: {:?}", interleaved);
+
+    // Polling resumes once the command has completed
+    let resumed = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Ok(line) = traffic.recv().await {
+                if line.direction == TrafficDirection::Tx {
+                    return line.line;
+                }
+            }
+        }
+    })
+    .await
+    .expect("polling did not resume");
+    assert!(resumed == "<01>" || resumed == "<03>");
+}
+
+#[tokio::test]
+async fn fault_api_refuses_out_of_range_plans() {
+    let bridge = TestBridge::start().await;
+    bridge.connection_manager.enable_fault_injection();
+
+    let (status, body) = bridge.post_json("/api/debug/faults", json!({ "stale_secs": u64::MAX })).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("stale_secs"), "{}", body);
+    let (status, body) = bridge
+        .post_json("/api/debug/faults", json!({ "drop_responses": u32::MAX, "corrupt_responses": u32::MAX }))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let (status, body) = bridge.post_json("/api/debug/faults", json!({ "drop_responses": 1 })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["drop_responses"], u32::MAX);
+
+    // Arming directly caps the stale period instead of overflowing
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        stale_secs: u64::MAX,
+        ..FaultPlan::default()
+    });
+    assert!((86_399..=86_400).contains(&faults.status().stale_remaining_secs));
+}
+
+#[tokio::test]
+async fn silent_link_is_detected_and_reconnected() {
+    let bridge = TestBridge::start_with(|manager| {
+        manager
+            .with_heartbeat(HeartbeatConfig { interval_secs: 1, max_missed: 2 })
+            .with_reconnect(ReconnectConfig { enabled: true, initial_delay_secs: 1, max_delay_secs: 1 })
+    })
+    .await;
+
+    // The port stays open but the firmware stops answering anything
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        drop_responses: u32::MAX,
+        ..FaultPlan::default()
+    });
+    bridge.emulator.state.lock().unwrap().faults = faults;
+
+    bridge
+        .wait_for(Duration::from_secs(15), |state| {
+            state.error_message.as_deref().is_some_and(|e| e.contains("reconnecting"))
+        })
+        .await;
+    let pings = bridge.emulator.snapshot().commands_received.iter().filter(|c| *c == "08").count();
+    assert!(pings >= 1, "no heartbeat ping was sent");
+
+    // Once the firmware answers again the bridge reconnects on its own
+    bridge.emulator.state.lock().unwrap().faults.clear();
+    bridge
+        .wait_for(Duration::from_secs(15), |state| state.connected && state.error_message.is_none())
+        .await;
+}
+
+#[tokio::test]
+async fn silent_long_command_outlives_the_heartbeat_window() {
+    let bridge = TestBridge::start_with(|manager| manager.with_heartbeat(HeartbeatConfig { interval_secs: 1, max_missed: 2 })).await;
+
+    // Calibration that says nothing for twice the heartbeat window before its ACK and result
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        ack_delay_ms: 6000,
+        ..FaultPlan::default()
+    });
+    bridge.emulator.state.lock().unwrap().faults = faults;
+    let response = bridge.connection_manager.send_command("06").await.unwrap();
+    bridge.emulator.state.lock().unwrap().faults.clear();
+
+    assert!(response.contains("Calibration complete"), "{}", response);
+    let state = bridge.device_state.read().await;
+    assert!(state.connected, "{:?}", state.error_message);
+    assert_eq!(state.error_message, None);
+}
+
+#[tokio::test]
+async fn firmware_events_reach_event_bus_not_commands() {
+    let bridge = TestBridge::start().await;
+    bridge.wait_for(Duration::from_secs(5), |state| state.is_parked).await;
+
+    // Mount moves off park and the firmware announces it without being asked
+    bridge.emulator.set_position(20.0, 0.0);
+    let message = bridge.emulator.state.lock().unwrap().event_message("park_changed");
+    bridge.emulator.unsolicited.send(message.to_string()).unwrap();
+
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "0B" })).await;
+    assert_eq!(body["success"], true, "command failed: {}", body);
+    let response: serde_json::Value = serde_json::from_str(body["response"].as_str().unwrap()).unwrap();
+    assert_eq!(response["data"]["tolerance"], 2.0);
+
+    let (_, events) = bridge.get("/api/events").await;
+    let events = events.as_array().unwrap();
+    assert!(events
+        .iter()
+        .any(|e| e["type"] == "firmware_event" && e["event"] == "park_changed" && e["data"]["parked"] == false));
+    assert!(events.iter().any(|e| e["type"] == "park_state_changed" && e["parked"] == false));
+
+    let last_id = events.last().unwrap()["id"].as_u64().unwrap();
+    let (_, newer) = bridge.get(&format!("/api/events?since={}", last_id)).await;
+    assert!(newer.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn firmware_error_fails_pending_command() {
+    // Unknown codes only get past the /api/command allowlist in expert mode
+    let bridge = TestBridge::start_with(|manager| {
+        manager.with_command_api(CommandApiConfig {
+            expert_mode: true,
+            ..CommandApiConfig::default()
+        })
+    })
+    .await;
+
+    let (_, body) = bridge.post_json("/api/command", json!({ "command": "FF" })).await;
+    assert_eq!(body["success"], false);
+    assert!(body["message"].as_str().unwrap().contains("Unknown command"));
+}
+
+#[tokio::test]
+async fn ascom_connected_round_trip() {
+    let bridge = TestBridge::start().await;
+
+    let (status, body) = bridge
+        .put_form("/api/v1/safetymonitor/0/connected", "Connected=True&ClientTransactionID=42")
+        .await;
+    assert!(status.is_success());
+    assert_eq!(body["ClientTransactionID"], 42);
+
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/connected").await;
+    assert_eq!(body["Value"], true);
+
+    let (status, body) = bridge.get("/api/v1/safetymonitor/1/issafe").await;
+    assert_eq!(status.as_u16(), 400);
+    assert_eq!(body["ErrorNumber"], 1024);
+}
+
+#[tokio::test]
+async fn alpaca_paths_resolve_devices_by_type_and_number() {
+    use telescope_park_bridge::device_registry::{AlpacaDeviceType, AlpacaDevices};
+
+    let bridge = TestBridge::start().await;
+    let devices = DeviceRegistry::single(bridge.device_state.clone(), bridge.connection_manager.clone());
+    assert_eq!(AlpacaDeviceType::parse("safetymonitor"), Some(AlpacaDeviceType::SafetyMonitor));
+    assert_eq!(AlpacaDeviceType::parse("SWITCH"), Some(AlpacaDeviceType::Switch));
+    assert_eq!(AlpacaDeviceType::parse("camera"), None);
+    assert!(devices.alpaca_device(AlpacaDeviceType::SafetyMonitor, 0).is_some());
+    assert!(devices.alpaca_device(AlpacaDeviceType::ObservingConditions, 0).is_none());
+
+    // Every common endpoint answers an unknown device number the same way
+    for endpoint in ["connected", "description", "driverinfo", "driverversion", "interfaceversion", "name", "supportedactions"] {
+        let (status, body) = bridge.get(&format!("/api/v1/safetymonitor/3/{}?ClientTransactionID=9", endpoint)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", endpoint);
+        assert_eq!(body["ErrorNumber"], 1024, "{}", endpoint);
+        assert_eq!(body["ErrorMessage"], "Invalid device number: 3", "{}", endpoint);
+        assert_eq!(body["ClientTransactionID"], 9, "{}", endpoint);
+    }
+    let (status, _) = bridge.get("/api/v1/camera/0/name").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn alpaca_errors_carry_ascom_numbers_and_the_transaction() {
+    use telescope_park_bridge::errors::{BridgeError, TelescopeError};
+
+    assert_eq!(BridgeError::NotConnected.alpaca_error_number(), 0x407);
+    assert_eq!(BridgeError::Timeout.alpaca_error_number(), 0x500);
+    assert_eq!(BridgeError::ValueNotSet(String::new()).alpaca_error_number(), 0x402);
+    assert_eq!(BridgeError::from(TelescopeError::from_alpaca(0x40B, "parked")).alpaca_error_number(), 0x40B);
+    assert!(BridgeError::UnknownDevice(2).is_bad_request());
+    assert!(!BridgeError::Timeout.is_bad_request());
+
+    let bridge = TestBridge::start().await;
+    let (status, body) = bridge
+        .put_form("/api/v1/safetymonitor/0/connected", "Connected=maybe&ClientTransactionID=17")
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["ErrorNumber"], 0x401);
+    assert_eq!(body["ErrorMessage"], "Invalid Connected parameter - must be 'true' or 'false'");
+    assert_eq!(body["ClientTransactionID"], 17);
+    let (status, body) = bridge.put_form("/api/v1/safetymonitor/0/connected", "Connected=&ClientTransactionID=18").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["ErrorNumber"], 0x402);
+    assert_eq!(body["ClientTransactionID"], 18);
+    let (status, body) = bridge.put_form("/api/v1/safetymonitor/4/connected", "Connected=true&ClientTransactionID=19").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!((body["ErrorNumber"].as_i64(), body["ClientTransactionID"].as_i64()), (Some(0x400), Some(19)));
+}
+
+#[tokio::test]
+async fn connected_ascom_clients_are_listed() {
+    let bridge = TestBridge::start().await;
+
+    bridge
+        .put_form("/api/v1/safetymonitor/0/connected", "Connected=True&ClientID=5&ClientTransactionID=1")
+        .await;
+    bridge.get("/api/v1/safetymonitor/0/issafe?ClientID=5&ClientTransactionID=2").await;
+
+    let (_, clients) = bridge.get("/api/clients").await;
+    let clients = clients.as_array().unwrap();
+    assert_eq!(clients.len(), 1);
+    assert_eq!(clients[0]["client_id"], 5);
+    assert_eq!(clients[0]["last_endpoint"], "issafe");
+    assert_eq!(clients[0]["transactions"], 2);
+
+    bridge
+        .put_form("/api/v1/safetymonitor/0/connected", "Connected=False&ClientID=5&ClientTransactionID=3")
+        .await;
+    let (_, clients) = bridge.get("/api/clients").await;
+    assert!(clients.as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn alpaca_parameter_names_are_case_insensitive() {
+    let bridge = TestBridge::start().await;
+
+    let (status, body) = bridge
+        .put_form("/api/v1/safetymonitor/0/connected", "CONNECTED=true&clientid=8&clienttransactionid=12")
+        .await;
+    assert!(status.is_success());
+    assert_eq!(body["ClientTransactionID"], 12);
+
+    let (_, body) = bridge.get("/api/v1/safetymonitor/0/issafe?CLIENTID=8&clientTransactionId=13").await;
+    assert_eq!(body["ClientTransactionID"], 13);
+    let (_, body) = bridge.get("/management/v1/description?clienttransactionid=14").await;
+    assert_eq!(body["ClientTransactionID"], 14);
+
+    let (_, clients) = bridge.get("/api/clients").await;
+    assert_eq!(clients[0]["client_id"], 8);
+    assert_eq!(clients[0]["transactions"], 2);
+}
+
+#[tokio::test]
+async fn disconnect_resets_state() {
+    let bridge = TestBridge::start().await;
+
+    let (_, body) = bridge.post_json("/api/disconnect", json!({})).await;
+    assert_eq!(body["success"], true);
+
+    let state = bridge.device_state.read().await;
+    assert!(!state.connected);
+    assert!(state.serial_port.is_none());
+}
+
+#[tokio::test]
+async fn auto_detection_probes_ports_for_the_park_sensor() {
+    use telescope_park_bridge::config::SerialConfig;
+    use telescope_park_bridge::port_discovery::{find_park_sensor, probe_port, PortInfo};
+
+    let emulator = common::FirmwareEmulator::start();
+    // A port that never answers, like a mount controller waiting for its own protocol
+    let (_silent_master, silent) = tokio_serial::SerialStream::pair().unwrap();
+    let silent_name = tokio_serial::SerialPort::name(&silent).unwrap();
+    let serial = SerialConfig::default();
+    let framing = FramingConfig::default();
+    let timeout = Duration::from_millis(500);
+
+    let reply = probe_port(emulator.port_name(), 115200, &serial, &framing, timeout).await.unwrap();
+    assert_eq!(reply.device_name, "Telescope Park Sensor");
+    assert_eq!(reply.firmware_version, "emulator");
+    let reason = probe_port(&silent_name, 115200, &serial, &framing, timeout).await.unwrap_err();
+    assert!(reason.contains("no answer"), "{}", reason);
+
+    let candidate = |name: &str| PortInfo {
+        name: name.to_string(),
+        description: "USB Serial Device".to_string(),
+        manufacturer: None,
+        vid_pid: None,
+        vid: None,
+        pid: None,
+        serial_number: None,
+    };
+    let ports = [candidate(&silent_name), candidate(emulator.port_name())];
+    let (found, _) = find_park_sensor(&ports, 115200, &serial, &framing, timeout).await.unwrap();
+    assert_eq!(found.name, emulator.port_name());
+}
+
+#[tokio::test]
+async fn usb_hotplug_attaches_the_device_when_its_port_appears() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use telescope_park_bridge::config::UsbMatch;
+    use telescope_park_bridge::port_discovery::PortInfo;
+    use telescope_park_bridge::usb_hotplug::UsbHotplug;
+    use tokio_util::sync::CancellationToken;
+
+    let emulator = common::FirmwareEmulator::start();
+    let device_state = Arc::new(RwLock::new(DeviceState::new()));
+    let connection_manager = Arc::new(ConnectionManager::new(device_state.clone()));
+    let device = DeviceHandle {
+        device_number: 0,
+        device_state: device_state.clone(),
+        connection_manager: connection_manager.clone(),
+    };
+    let port = |name: &str, serial_number: &str| PortInfo {
+        name: name.to_string(),
+        description: "Seeed Studio XIAO nRF52840 (or compatible)".to_string(),
+        manufacturer: None,
+        vid_pid: Some("VID:2886 PID:0045".to_string()),
+        vid: Some(0x2886),
+        pid: Some(0x0045),
+        serial_number: Some(serial_number.to_string()),
+    };
+    let usb = UsbMatch {
+        vid: Some(0x2886),
+        serial_number: Some("8a3f2c1d".to_string()),
+        ..UsbMatch::default()
+    };
+    assert!(port("/dev/ttyACM0", "8A3F2C1D").matches(&usb));
+    assert!(!port("/dev/ttyACM0", "0000").matches(&usb));
+
+    // Another board of the same kind is always there; the sensor is plugged in later
+    let plugged_in = Arc::new(AtomicBool::new(false));
+    let emulator_port = emulator.port_name().to_string();
+    let ports = {
+        let plugged_in = plugged_in.clone();
+        move || {
+            let mut ports = vec![port("/dev/ttyACM9", "0000")];
+            if plugged_in.load(Ordering::SeqCst) {
+                ports.push(port(&emulator_port, "8A3F2C1D"));
+            }
+            ports
+        }
+    };
+    let cancel = CancellationToken::new();
+    let watcher = UsbHotplug::new(usb, 115200)
+        .with_poll_interval(Duration::from_millis(50))
+        .with_port_source(ports);
+    let watcher = tokio::spawn(watcher.run(device, cancel.clone()));
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(connection_manager.get_current_port().await.is_none());
+    plugged_in.store(true, Ordering::SeqCst);
+    common::wait_for_state(&device_state, Duration::from_secs(10), |state| state.connected).await;
+    assert_eq!(connection_manager.get_current_port().await.as_deref(), Some(emulator.port_name()));
+
+    // Disconnected from the web interface: the port is still there, but it is not taken again
+    connection_manager.disconnect().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert!(connection_manager.get_current_port().await.is_none());
+
+    cancel.cancel();
+    watcher.await.unwrap();
+}
+
+#[tokio::test]
+async fn device_client_runs_over_tcp_and_mock_transports() {
+    use std::sync::Mutex;
+    use telescope_park_bridge::config::check_port_syntax;
+    use telescope_park_bridge::device_transport::MockTransport;
+    use telescope_park_bridge::protocol;
+    use telescope_park_bridge::serial_client::{run_client_with_transport, SerialClientContext};
+    use telescope_park_bridge::simulator::{run_simulator, SimulatorState};
+    use tokio_util::sync::CancellationToken;
+
+    assert!(check_port_syntax("tcp://pier-north.local:4000").is_ok());
+    assert!(check_port_syntax("tcp://pier-north.local").is_err());
+    assert!(check_port_syntax("tcp://:4000").is_err());
+    // Not a char boundary after three bytes
+    assert!(check_port_syntax("coé1").is_err());
+    assert!(check_port_syntax(r"\\.\cö").is_err());
+    assert!(check_port_syntax("COM3").is_ok());
+
+    // A serial server on the network, such as ser2net in front of the sensor
+    let simulator = |version: &str| {
+        let mut state = SimulatorState::default();
+        state.firmware_version = version.to_string();
+        Arc::new(Mutex::new(state))
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let tcp_state = simulator("tcp");
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (_unsolicited, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let _ = run_simulator(stream, tcp_state, receiver).await;
+    });
+    let device_state = Arc::new(RwLock::new(DeviceState::new()));
+    let connection_manager = ConnectionManager::new(device_state.clone());
+    connection_manager.connect(format!("tcp://{}", address), 115200).await.unwrap();
+    common::wait_for_state(&device_state, Duration::from_secs(10), |state| {
+        state.connected && state.operational && state.device_version == "tcp"
+    })
+    .await;
+    let reply = connection_manager.send_command(protocol::GET_STATUS).await.unwrap();
+    assert!(reply.contains("Telescope Park Sensor"), "{}", reply);
+    connection_manager.disconnect().await.unwrap();
+    server.await.unwrap();
+
+    // The mock hands every command to the test, which answers from a simulator
+    let (transport, mut device) = MockTransport::pair();
+    let mock_state = simulator("mock");
+    let firmware = tokio::spawn(async move {
+        device.send_line("===== nRF52840 Telescope Park Sensor =====");
+        let mut commands = Vec::new();
+        while let Some(command) = device.next_command().await {
+            let responses = mock_state.lock().unwrap().handle_command(&command);
+            for response in responses {
+                device.send_line(response.to_string());
+            }
+            commands.push(command);
+        }
+        commands
+    });
+    let device_state = Arc::new(RwLock::new(DeviceState::new()));
+    let cancel = CancellationToken::new();
+    let client = tokio::spawn(run_client_with_transport(
+        transport,
+        "mock".to_string(),
+        device_state.clone(),
+        cancel.clone(),
+        SerialClientContext::default(),
+    ));
+    common::wait_for_state(&device_state, Duration::from_secs(10), |state| {
+        state.connected && state.operational && state.device_version == "mock"
+    })
+    .await;
+    assert_eq!(device_state.read().await.serial_port.as_deref(), Some("mock"));
+    cancel.cancel();
+    client.await.unwrap().unwrap();
+    assert!(!device_state.read().await.connected);
+    // Commands reach the transport unframed
+    let commands = firmware.await.unwrap();
+    assert!(commands.iter().any(|command| command == protocol::GET_STATUS), "{:?}", commands);
+}
+
+#[tokio::test]
+async fn strict_schema_counts_replies_that_drift_from_the_protocol() {
+    use std::sync::Mutex;
+    use telescope_park_bridge::device_transport::MockTransport;
+    use telescope_park_bridge::firmware_schema::SchemaChecker;
+    use telescope_park_bridge::serial_client::{run_client_with_transport, SerialClientContext};
+    use telescope_park_bridge::simulator::SimulatorState;
+    use tokio_util::sync::CancellationToken;
+
+    // The emulated v1 firmware matches every schema
+    let bridge = TestBridge::start_with(|manager| manager.with_strict_schema(true)).await;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let (status, report) = bridge.get("/api/firmware/schema").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(report["strict"], true);
+    assert!(report["checked"].as_u64().unwrap() > 5, "{}", report);
+    assert_eq!(report["violations"], 0, "{}", report);
+    let (status, _) = bridge.get("/api/firmware/schema?device_number=7").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // V2 firmware adding a field to its position reading and sending the temperature as text
+    let (transport, mut device) = MockTransport::pair();
+    let mut state = SimulatorState::default();
+    state.protocol_version = 2;
+    let state = Arc::new(Mutex::new(state));
+    let firmware = tokio::spawn(async move {
+        device.send_line("===== nRF52840 Telescope Park Sensor =====");
+        while let Some(command) = device.next_command().await {
+            let responses = state.lock().unwrap().handle_command(&command);
+            for mut response in responses {
+                match (response["status"].as_str(), command.as_str()) {
+                    (Some("ok"), "02") => response["data"]["heading"] = json!(181.5),
+                    (Some("ok"), "01") => response["data"]["temperature"] = json!("21.5"),
+                    _ => {}
+                }
+                device.send_line(response.to_string());
+            }
+        }
+    });
+    let schema = SchemaChecker::new(true);
+    let device_state = Arc::new(RwLock::new(DeviceState::new()));
+    let cancel = CancellationToken::new();
+    let client = tokio::spawn(run_client_with_transport(
+        transport,
+        "mock".to_string(),
+        device_state.clone(),
+        cancel.clone(),
+        SerialClientContext {
+            schema: schema.clone(),
+            ..SerialClientContext::default()
+        },
+    ));
+    // The bridge itself still takes the replies
+    common::wait_for_state(&device_state, Duration::from_secs(10), |state| state.operational).await;
+    assert_eq!(device_state.read().await.protocol_version, ProtocolVersion::V2);
+    cancel.cancel();
+    client.await.unwrap().unwrap();
+    firmware.await.unwrap();
+
+    let report = schema.report();
+    let schemas: Vec<&str> = report.schemas.keys().map(String::as_str).collect();
+    assert_eq!(schemas, ["position_v2", "status"], "{:?}", report);
+    assert!(report.schemas["position_v2"].last_problem.contains("heading"), "{:?}", report);
+    assert!(report.schemas["status"].last_problem.contains("expected f32"), "{:?}", report);
+    assert!(report.schemas["status"].last_response.contains("\"21.5\""), "{:?}", report);
+    assert_eq!(report.violations, report.schemas.values().map(|seen| seen.count).sum::<u64>());
+}
+
+#[tokio::test]
+async fn notification_templates_fill_in_event_and_device_values() {
+    use telescope_park_bridge::events::EventKind;
+    use telescope_park_bridge::notifications::{self, Notifications};
+
+    let config = BridgeConfig::parse(
+        r#"
+        [notifications.templates]
+        default = "[{location}] {message}"
+        park_state_changed = "[{location}] {device_name} {park_state} at pitch {pitch}, roll {roll} ({time}) {{ok}}"
+        telescope_changed = "{telescope}: {change}"
+
+        [chat_bot.templates]
+        sun_altitude_crossed = "Sun {altitude}° at {location}"
+        "#,
+    )
+    .unwrap();
+    assert!(config.issues().is_empty(), "{}", config.issues());
+    let bridge = TestBridge::start_with(|manager| {
+        manager.with_identity(IdentityConfig {
+            device_name: Some("North Pier Park Sensor".to_string()),
+            location: Some("North Pier".to_string()),
+            ..IdentityConfig::default()
+        })
+    })
+    .await;
+    bridge.emulator.set_position(0.0, 0.0);
+    bridge.wait_for(Duration::from_secs(10), |state| state.is_parked).await;
+    let device = DeviceHandle {
+        device_number: 0,
+        device_state: bridge.device_state.clone(),
+        connection_manager: bridge.connection_manager.clone(),
+    };
+    let notifications = Notifications::new(config.notifications.clone());
+    let channel = &config.chat_bot.templates;
+    let render = |event: &telescope_park_bridge::events::BridgeEvent, message: Option<&str>| {
+        let template = notifications.template(channel, event.kind.type_name(), message.is_some()).map(str::to_string);
+        let device = device.clone();
+        let event = event.clone();
+        let message = message.unwrap_or_default().to_string();
+        async move {
+            let template = template?;
+            Some(notifications::render_template(&template, &notifications::event_variables(&event, &device, &message).await))
+        }
+    };
+    let events = bridge.connection_manager.event_bus();
+
+    let parked = events.publish(EventKind::ParkStateChanged { parked: true, pitch: 0.25, roll: -0.5 });
+    let text = render(&parked, Some("Telescope parked")).await.unwrap();
+    assert!(text.starts_with("[North Pier] North Pier Park Sensor Parked at pitch 0.25, roll -0.50 ("), "{}", text);
+    assert!(text.ends_with(") {ok}"), "{}", text);
+    // The channel's own template wins; "default" wraps the built-in message of the others
+    let sun = events.publish(EventKind::SunAltitudeCrossed { above: true, altitude: 3.5, max_altitude: -6.0, forces_unsafe: true });
+    assert_eq!(render(&sun, Some("The Sun is up")).await.unwrap(), "Sun 3.50° at North Pier");
+    let rebooted = events.publish(EventKind::FirmwareRebooted { previous_uptime: 9, uptime: 1 });
+    assert_eq!(render(&rebooted, Some("Sensor firmware rebooted")).await.unwrap(), "[North Pier] Sensor firmware rebooted");
+    // An event without a built-in message is only sent with a template of its own
+    let progress = events.publish(EventKind::CalibrationProgress { phase: "sampling".to_string(), percent: Some(40) });
+    assert!(render(&progress, None).await.is_none());
+
+    assert_eq!(notifications::render_template("{nope} {message}", &Default::default()), "{nope} {message}");
+    let broken = BridgeConfig::parse(
+        r#"
+        [notifications.templates]
+        park_state = "{device_name"
+        "#,
+    )
+    .unwrap();
+    let issues = broken.issues().to_string();
+    assert!(issues.contains("notifications.templates.park_state: is not an event type"), "{}", issues);
+    assert!(issues.contains("is not closed"), "{}", issues);
+}
+
+#[tokio::test]
+async fn safe_mode_restores_a_broken_config_file() {
+    use telescope_park_bridge::safe_mode::{self, SafeMode};
+
+    let dir = std::env::temp_dir().join(format!("park-bridge-safe-mode-{}", std::process::id()));
+    let backups = dir.join("backups");
+    std::fs::create_dir_all(&backups).unwrap();
+    let path = dir.join("bridge.toml");
+    let good = format!("[safety]\nmax_data_age_secs = 20\n\n[backup]\ndirectory = {:?}\n", backups.display().to_string());
+    std::fs::write(&path, &good).unwrap();
+    safe_mode::keep_last_good(&path);
+    let older = "[safety]\nmax_data_age_secs = 45\n";
+    let backup = json!({ "created_at": "2024-05-01T12:00:00Z", "config": older, "devices": [] });
+    std::fs::write(backups.join("park-bridge-backup-20240501T120000Z.json"), backup.to_string()).unwrap();
+    // A write cut short by a power failure
+    let broken = "[safety]\nmax_data_age_se";
+    std::fs::write(&path, broken).unwrap();
+
+    let error = BridgeConfig::load(&path).unwrap_err().to_string();
+    let safe_mode = SafeMode::new(Some(path.clone()), error.clone());
+    let router = safe_mode.clone().router(false);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server = tokio::spawn(safe_mode.serve(listener, true));
+
+    let (status, body) = common::send(&router, Method::GET, "/api/safe_mode", Value::Null).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["error"], error);
+    let points = body["restore_points"].as_array().unwrap();
+    assert_eq!(points.len(), 2, "{}", body);
+    assert_eq!(points[0]["name"], "last-good");
+    assert_eq!(points[0]["problem"], Value::Null);
+    assert_eq!(points[1]["name"], "park-bridge-backup-20240501T120000Z.json");
+    assert_eq!(points[1]["saved_at"], "2024-05-01T12:00:00Z");
+    let (_, body) = common::send(&router, Method::GET, "/api/safe_mode/config", Value::Null).await;
+    assert_eq!(body["config"], broken);
+
+    // Alpaca clients and monitors learn that the bridge is not running
+    let (status, body) = common::send(&router, Method::GET, "/api/v1/safetymonitor/0/issafe", Value::Null).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(body["message"].as_str().unwrap().contains("safe mode"), "{}", body);
+    #[cfg(feature = "web-ui")]
+    {
+        use tower::ServiceExt;
+        let request = axum::http::Request::get("/").body(axum::body::Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let page = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(String::from_utf8_lossy(&page).contains("Safe Mode"));
+    }
+
+    // A replacement that would not load either is refused and leaves the file alone
+    let (status, body) = common::send(
+        &router,
+        Method::PUT,
+        "/api/safe_mode/config",
+        json!({ "config": "[safety]\nmax_data_age_secs = 0\n" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["message"].as_str().unwrap().contains("max_data_age_secs"), "{}", body);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), broken);
+    let (status, _) = common::send(&router, Method::POST, "/api/safe_mode/restore", json!({ "name": "nope" })).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Restoring puts the copy back, keeps the broken file aside and ends safe mode
+    let (status, body) = common::send(&router, Method::POST, "/api/safe_mode/restore", json!({ "name": "last-good" })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), good);
+    assert_eq!(std::fs::read_to_string(dir.join("bridge.toml.broken")).unwrap(), broken);
+    assert_eq!(BridgeConfig::load(&path).unwrap().safety.max_data_age_secs, 20);
+    tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().unwrap();
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn safe_mode_redacts_secrets_and_keeps_them_on_save() {
+    use telescope_park_bridge::safe_mode::SafeMode;
+
+    let dir = std::env::temp_dir().join(format!("park-bridge-safe-mode-secrets-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("bridge.toml");
+    let broken = "[safety]\nmax_data_age_secs = 0\n\n[events]\ntoken = \"s3cret\"\n";
+    std::fs::write(&path, broken).unwrap();
+    let error = BridgeConfig::load(&path).unwrap_err().to_string();
+    let router = SafeMode::new(Some(path.clone()), error).router(true);
+
+    let (_, body) = common::send(&router, Method::GET, "/api/safe_mode/config", Value::Null).await;
+    let shown = body["config"].as_str().unwrap().to_string();
+    assert!(!shown.contains("s3cret"), "{}", shown);
+    assert!(shown.contains("token = \"<redacted>\""), "{}", shown);
+
+    // Fixing the edited text keeps the token the file had
+    let fixed = shown.replace("max_data_age_secs = 0", "max_data_age_secs = 30");
+    let (status, body) = common::send(&router, Method::PUT, "/api/safe_mode/config", json!({ "config": fixed })).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let saved = BridgeConfig::load(&path).unwrap();
+    assert_eq!(saved.events.token.as_deref(), Some("s3cret"));
+    assert_eq!(saved.safety.max_data_age_secs, 30);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn safe_mode_redacts_inline_table_users_and_multi_line_secrets() {
+    use telescope_park_bridge::{safe_mode::SafeMode, web_users};
+
+    let dir = std::env::temp_dir().join(format!("park-bridge-safe-mode-inline-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("bridge.toml");
+    let hash = web_users::hash_password("alice-pw").unwrap();
+    let secrets = format!(
+        "[web_auth]\nusers = [{{ name = \"alice\", role = \"admin\", password_hash = \"{}\" }}]\n\n[events]\ntoken = \"\"\"\ns3cret\"\"\"\n",
+        hash
+    );
+    // A file that is valid TOML but an invalid config, and one that does not parse at all
+    for (mistake, fix) in [("max_data_age_secs = 0", "max_data_age_secs = 30"), ("max_data_age_secs = = 0", "max_data_age_secs = 30")] {
+        std::fs::write(&path, format!("[safety]\n{}\n\n{}", mistake, secrets)).unwrap();
+        let error = BridgeConfig::load(&path).unwrap_err().to_string();
+        let router = SafeMode::new(Some(path.clone()), error).router(true);
+
+        let (_, body) = common::send(&router, Method::GET, "/api/safe_mode/config", Value::Null).await;
+        let shown = body["config"].as_str().unwrap().to_string();
+        assert!(!shown.contains(&hash) && !shown.contains("s3cret"), "{}", shown);
+        assert!(shown.contains("<redacted>"), "{}", shown);
+
+        let (status, body) = common::send(&router, Method::PUT, "/api/safe_mode/config", json!({ "config": shown.replace(mistake, fix) })).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        let saved = BridgeConfig::load(&path).unwrap();
+        assert_eq!(saved.web_auth.users[0].password_hash, hash);
+        assert_eq!(saved.events.token.as_deref(), Some("s3cret"));
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn status_delta_sends_only_changed_fields() {
+    let bridge = TestBridge::start_with(|manager| manager.with_heartbeat(HeartbeatConfig { interval_secs: 0, max_missed: 1 })).await;
+
+    let (status, first) = bridge.get("/api/status/delta").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(first["full"], true);
+    assert_eq!(first["changed"]["connected"], true);
+    assert!(first["changed"].get("server").is_none());
+    let sequence = first["sequence"].as_u64().unwrap();
+
+    // The sensor moves: the position is sent, the identity is not
+    bridge.emulator.set_position(12.5, -3.0);
+    bridge.wait_for(Duration::from_secs(10), |state| (state.current_pitch - 12.5).abs() < 0.5).await;
+    let (_, delta) = bridge.get(&format!("/api/status/delta?since={}", sequence)).await;
+    assert_eq!(delta["full"], false);
+    assert!(delta["changed"].get("current_pitch").is_some(), "{}", delta);
+    assert!(delta["changed"].get("device_name").is_none(), "{}", delta);
+    assert!(delta["sequence"].as_u64().unwrap() > sequence);
+
+    // A sequence from before a restart gets everything
+    let (_, delta) = bridge.get("/api/status/delta?since=1").await;
+    assert_eq!(delta["full"], true);
+
+    // While the firmware is silent nothing changes, and the ETag gets 304
+    let mut faults = FaultInjector::new(true);
+    faults.inject(FaultPlan {
+        drop_responses: u32::MAX,
+        ..FaultPlan::default()
+    });
+    bridge.emulator.state.lock().unwrap().faults = faults;
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let response = bridge.get_response("/api/status/delta", &[]).await;
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    let response = bridge.get_response("/api/status/delta", &[("if-none-match", &etag)]).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let (_, delta) = bridge.get(&format!("/api/status/delta?since={}", etag.trim_matches('"'))).await;
+    assert_eq!(delta["changed"], json!({}));
+}